@@ -0,0 +1,113 @@
+//! Operator endpoints changing API1's behaviour at runtime.
+//!
+//! They sit behind the same `X-Api-Key` check as the API routes.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::audit::{AuditPage, AuditQuery, AuditQueryError};
+use common::flags::{FlagStates, FlagUpdate};
+use common::logging::{self, FilterError, LogFilter};
+use common::payload_log::{PayloadLogSettings, PayloadLogUpdate};
+use common::problem::{ErrorCode, Query};
+use tracing::info;
+
+use crate::{error_for_status, error_response, request_id_from, ApiError, AppState};
+
+pub async fn get_payload_logging(State(state): State<AppState>) -> Json<PayloadLogSettings> {
+    Json(state.payload_log.settings())
+}
+
+pub async fn put_payload_logging(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<PayloadLogUpdate>, JsonRejection>,
+) -> Result<Json<PayloadLogSettings>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(update) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        enabled = update.enabled,
+        "Payload logging changed"
+    );
+    state.payload_log.set_enabled(update.enabled);
+    Ok(Json(state.payload_log.settings()))
+}
+
+pub async fn get_log_level(headers: HeaderMap) -> Result<Json<LogFilter>, ApiError> {
+    let filter = logging::filter().ok_or(FilterError::NotInstalled);
+    filter
+        .map(|filter| Json(LogFilter { filter }))
+        .map_err(|e| filter_error(e, &request_id_from(&headers)))
+}
+
+pub async fn put_log_level(
+    headers: HeaderMap,
+    body: Result<Json<LogFilter>, JsonRejection>,
+) -> Result<Json<LogFilter>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(update) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    let filter = logging::set_filter(&update.filter).map_err(|e| filter_error(e, &request_id))?;
+    info!(request_id = %request_id, filter = %filter, "Log filter changed");
+    Ok(Json(LogFilter { filter }))
+}
+
+pub async fn get_audit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditPage>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let audit = state.audit.as_ref().ok_or_else(|| {
+        error_response(
+            ErrorCode::Unavailable,
+            "Audit logging is disabled; set AUDIT_LOG_PATH",
+            &request_id,
+        )
+    })?;
+    audit.query(query).await.map(Json).map_err(|e| {
+        let code = match e {
+            AuditQueryError::Invalid(_) => ErrorCode::InvalidRequest,
+            AuditQueryError::Read(_) => ErrorCode::Internal,
+        };
+        error_response(code, e.to_string(), &request_id)
+    })
+}
+
+pub async fn get_flags(State(state): State<AppState>) -> Json<FlagStates> {
+    Json(state.flags.states())
+}
+
+pub async fn put_flags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<FlagUpdate>, JsonRejection>,
+) -> Result<Json<FlagStates>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(update) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    let states = state
+        .flags
+        .update(&update)
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+    info!(request_id = %request_id, flags = ?update, "Feature flags changed");
+    Ok(Json(states))
+}
+
+fn filter_error(error: FilterError, request_id: &str) -> ApiError {
+    let code = match error {
+        FilterError::Invalid(_) => ErrorCode::InvalidRequest,
+        FilterError::NotInstalled => ErrorCode::Unavailable,
+    };
+    error_response(code, error.to_string(), request_id)
+}
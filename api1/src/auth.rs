@@ -0,0 +1,114 @@
+//! API key authentication for every route except the health probes and `/metrics`.
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::Request,
+    response::{IntoResponse, Response},
+};
+use common::auth::{is_valid_key, redact_key, API_KEY_HEADER};
+use common::problem::ErrorCode;
+use common::{ErrorResponse, REQUEST_ID_HEADER};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{info_span, warn, Instrument};
+use uuid::Uuid;
+
+/// Tower layer rejecting requests without a valid `X-Api-Key` with `401`.
+///
+/// Accepted requests run in an `api_key` span whose `api_key` field is the
+/// redacted key, so their log lines say which client sent them.
+///
+/// With no keys configured, authentication is disabled and every request
+/// passes through.
+#[derive(Clone)]
+pub struct ApiKeyLayer {
+    api_keys: Arc<Vec<String>>,
+}
+
+impl ApiKeyLayer {
+    pub fn new(api_keys: Arc<Vec<String>>) -> Self {
+        ApiKeyLayer { api_keys }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyLayer {
+    type Service = ApiKey<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKey {
+            inner,
+            api_keys: self.api_keys.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ApiKey<S> {
+    inner: S,
+    api_keys: Arc<Vec<String>>,
+}
+
+impl<S> Service<Request<Body>> for ApiKey<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if self.api_keys.is_empty() {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let provided = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if let Some(provided) = provided.filter(|provided| is_valid_key(&self.api_keys, provided)) {
+            let span = info_span!("api_key", api_key = %redact_key(provided));
+            let response = span.in_scope(|| self.inner.call(request));
+            return Box::pin(response.instrument(span));
+        }
+
+        let response = unauthorized(&request, provided);
+        Box::pin(std::future::ready(Ok(response)))
+    }
+}
+
+fn unauthorized(request: &Request<Body>, provided: Option<&str>) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or_else(|| "unknown".to_string(), |info| info.0.ip().to_string());
+
+    warn!(
+        request_id = %request_id,
+        client = %client,
+        api_key = %provided.map_or_else(|| "<missing>".to_string(), redact_key),
+        "Rejected request with missing or invalid API key"
+    );
+
+    ErrorResponse::new(
+        ErrorCode::Unauthorized,
+        "Missing or invalid API key",
+        &request_id,
+    )
+    .into_response()
+}
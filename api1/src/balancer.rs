@@ -0,0 +1,224 @@
+//! Spreads API2 calls across the instances listed in `API2_URLS`.
+//!
+//! Each instance's recent results are tracked: after
+//! `API2_EJECT_AFTER_FAILURES` consecutive failures it is ejected for
+//! `API2_EJECT_SECS`, and only tried once every instance still in rotation
+//! has been. When the period ends it rejoins with a clean record.
+
+pub use common::balancing::Strategy;
+use common::config::Config;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicUsize, Ordering};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const DEFAULT_API2_URL: &str = "http://api2:4000";
+const DEFAULT_EJECT_AFTER_FAILURES: u32 = 3;
+const DEFAULT_EJECT_MS: i64 = 30_000;
+
+/// Recent results of calls to one instance.
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: AtomicU32,
+    /// Unix milliseconds of the last failure, 0 if none.
+    last_failure_ms: AtomicI64,
+    /// Unix milliseconds until which the instance is ejected, 0 if never.
+    ejected_until_ms: AtomicI64,
+}
+
+/// An instance's health as reported by `/health`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceHealth {
+    pub consecutive_failures: u32,
+    pub ejected: bool,
+}
+
+pub struct Backends {
+    urls: Vec<String>,
+    strategy: Strategy,
+    next: AtomicUsize,
+    health: Vec<Health>,
+    eject_after_failures: u32,
+    eject_ms: i64,
+}
+
+impl Backends {
+    /// # Panics
+    ///
+    /// Panics if `urls` is empty.
+    pub fn new(urls: Vec<String>, strategy: Strategy) -> Self {
+        assert!(!urls.is_empty(), "At least one API2 URL is required");
+        Backends {
+            health: urls.iter().map(|_| Health::default()).collect(),
+            urls,
+            strategy,
+            next: AtomicUsize::new(0),
+            eject_after_failures: DEFAULT_EJECT_AFTER_FAILURES,
+            eject_ms: DEFAULT_EJECT_MS,
+        }
+    }
+
+    /// Uses `api2_urls`, or the single `api2_url`, with `api2_lb_strategy`
+    /// and the `api2_eject_*` settings.
+    pub fn from_config(config: &Config) -> Self {
+        let urls = match (&config.api2_urls, &config.api2_url) {
+            (Some(urls), _) if !urls.is_empty() => urls.clone(),
+            (_, Some(url)) => vec![url.clone()],
+            _ => vec![DEFAULT_API2_URL.to_string()],
+        };
+        let strategy = Strategy::from_config(config);
+        Backends {
+            eject_after_failures: config
+                .api2_eject_after_failures
+                .unwrap_or(DEFAULT_EJECT_AFTER_FAILURES),
+            eject_ms: config
+                .api2_eject_secs
+                .map_or(DEFAULT_EJECT_MS, |secs| secs as i64 * 1000),
+            ..Backends::new(urls, strategy)
+        }
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    fn is_ejected(&self, backend: usize, now_ms: i64) -> bool {
+        now_ms
+            < self.health[backend]
+                .ejected_until_ms
+                .load(Ordering::Relaxed)
+    }
+
+    /// Indices of every instance, each once, starting with the one the
+    /// strategy picks for this call; later ones are failover targets.
+    /// Ejected instances come last.
+    pub fn order(&self, now_ms: i64) -> impl Iterator<Item = usize> {
+        let (mut order, ejected): (Vec<usize>, Vec<usize>) =
+            (0..self.urls.len()).partition(|&backend| !self.is_ejected(backend, now_ms));
+        if !order.is_empty() {
+            let first = match self.strategy {
+                Strategy::RoundRobin | Strategy::LeastRecentlyFailed => {
+                    self.next.fetch_add(1, Ordering::Relaxed) % order.len()
+                }
+                Strategy::Random => (Uuid::new_v4().as_u128() % order.len() as u128) as usize,
+            };
+            order.rotate_left(first);
+        }
+        if self.strategy == Strategy::LeastRecentlyFailed {
+            // Stable, so instances that failed equally recently keep rotating.
+            order.sort_by_key(|&backend| {
+                self.health[backend].last_failure_ms.load(Ordering::Relaxed)
+            });
+        }
+        order.extend(ejected);
+        order.into_iter()
+    }
+
+    /// Records the result of a call to `backend`, ejecting it after too
+    /// many failures in a row.
+    pub fn record(&self, backend: usize, success: bool, now_ms: i64) {
+        let health = &self.health[backend];
+        if success {
+            health.consecutive_failures.store(0, Ordering::Relaxed);
+            return;
+        }
+        health.last_failure_ms.store(now_ms, Ordering::Relaxed);
+        let failures = health.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.eject_after_failures {
+            health.consecutive_failures.store(0, Ordering::Relaxed);
+            let was_ejected = self.is_ejected(backend, now_ms);
+            health
+                .ejected_until_ms
+                .store(now_ms + self.eject_ms, Ordering::Relaxed);
+            if !was_ejected {
+                warn!(
+                    api2_url = %self.urls[backend],
+                    failures,
+                    eject_secs = self.eject_ms / 1000,
+                    "Ejecting API2 instance after consecutive failures"
+                );
+            }
+        } else if failures == 1 {
+            info!(api2_url = %self.urls[backend], "API2 instance call failed");
+        }
+    }
+
+    pub fn health(&self, backend: usize, now_ms: i64) -> InstanceHealth {
+        InstanceHealth {
+            consecutive_failures: self.health[backend]
+                .consecutive_failures
+                .load(Ordering::Relaxed),
+            ejected: self.is_ejected(backend, now_ms),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backends(count: usize, strategy: Strategy) -> Backends {
+        Backends::new(
+            (0..count)
+                .map(|i| format!("http://api2-{i}:4000"))
+                .collect(),
+            strategy,
+        )
+    }
+
+    #[test]
+    fn round_robin_rotates_starting_instance() {
+        let backends = backends(3, Strategy::RoundRobin);
+        let orders: Vec<Vec<usize>> = (0..4).map(|_| backends.order(0).collect()).collect();
+        assert_eq!(
+            orders,
+            [[0, 1, 2], [1, 2, 0], [2, 0, 1], [0, 1, 2]].map(Vec::from)
+        );
+    }
+
+    #[test]
+    fn random_visits_every_instance_once() {
+        let backends = backends(5, Strategy::Random);
+        for _ in 0..20 {
+            let mut order: Vec<usize> = backends.order(0).collect();
+            order.sort_unstable();
+            assert_eq!(order, [0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn ejects_failing_instances_until_the_period_ends() {
+        let backends = Backends {
+            eject_ms: 1000,
+            ..backends(3, Strategy::RoundRobin)
+        };
+        backends.record(1, false, 100);
+        backends.record(1, true, 110);
+        backends.record(1, false, 120);
+        backends.record(1, false, 130);
+        assert_eq!(
+            backends.health(1, 130),
+            InstanceHealth {
+                consecutive_failures: 2,
+                ejected: false
+            }
+        );
+        backends.record(1, false, 140);
+        assert!(backends.health(1, 140).ejected);
+
+        // Still a last resort while ejected, then back in rotation.
+        let orders: Vec<Vec<usize>> = (0..3).map(|_| backends.order(500).collect()).collect();
+        assert_eq!(orders, [[0, 2, 1], [2, 0, 1], [0, 2, 1]].map(Vec::from));
+        assert_eq!(backends.order(1140).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(backends.health(1, 1140).consecutive_failures, 0);
+    }
+
+    #[test]
+    fn least_recently_failed_prefers_instances_that_failed_longest_ago() {
+        let backends = backends(3, Strategy::LeastRecentlyFailed);
+        backends.record(0, false, 200);
+        backends.record(2, false, 100);
+        assert_eq!(backends.order(300).collect::<Vec<_>>(), [1, 2, 0]);
+        backends.record(1, false, 250);
+        assert_eq!(backends.order(300).collect::<Vec<_>>(), [2, 0, 1]);
+    }
+}
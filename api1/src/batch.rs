@@ -0,0 +1,66 @@
+//! Proxy for API2's batch time endpoint.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::problem::ErrorCode;
+use common::trace_context::TraceContext;
+use common::{BatchTimeItem, BatchTimeRequest, BatchTimeResponse};
+use tracing::info;
+
+use crate::{
+    api2_source, call_context, error_response, forward_to_api2, request_id_from, ApiError, AppState,
+};
+
+pub async fn post_time_batch(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    body: Result<Json<BatchTimeRequest>, JsonRejection>,
+) -> Result<Json<BatchTimeResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(batch) = body.map_err(|rejection| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            rejection.body_text(),
+            &request_id,
+        )
+    })?;
+
+    info!(
+        request_id = %request_id,
+        count = batch.timezones.len(),
+        "Received batch time request"
+    );
+
+    // Reject oversized batches here rather than paying for the round trip.
+    if batch.timezones.len() > state.max_batch_size {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Batch of {} timezones exceeds the limit of {}",
+                batch.timezones.len(),
+                state.max_batch_size
+            ),
+            &request_id,
+        ));
+    }
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (mut response, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_batch(api2_url, &context, &batch)
+    })
+    .await?;
+
+    let source = api2_source(answered.backend);
+    let items = response.items.iter_mut().filter_map(|item| match item {
+        BatchTimeItem::Time(time) => Some(time),
+        BatchTimeItem::Error(_) => None,
+    });
+    for result in items.chain(&mut response.results) {
+        result.source = source.clone();
+    }
+    Ok(Json(response))
+}
@@ -0,0 +1,172 @@
+//! Short-lived LRU cache of `/time` responses, so bursts of identical
+//! requests reach API2 once per TTL window.
+
+use common::config::Config;
+use common::TimeResponse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TTL_MS: u64 = 500;
+const DEFAULT_CAPACITY: usize = 128;
+
+struct Entry {
+    response: TimeResponse,
+    fetched: Instant,
+    /// Value of `Entries::clock` when last read or written.
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    clock: u64,
+}
+
+impl Entries {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+pub struct TimeCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl Default for TimeCache {
+    fn default() -> Self {
+        TimeCache::new(DEFAULT_CAPACITY, Duration::from_millis(DEFAULT_TTL_MS))
+    }
+}
+
+impl TimeCache {
+    /// A capacity of zero disables caching.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        TimeCache {
+            ttl,
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Uses `cache_capacity` and `cache_ttl_ms`, falling back to the defaults.
+    pub fn from_config(config: &Config) -> Self {
+        TimeCache::new(
+            config.cache_capacity.unwrap_or(DEFAULT_CAPACITY),
+            Duration::from_millis(config.cache_ttl_ms.unwrap_or(DEFAULT_TTL_MS)),
+        )
+    }
+
+    /// Returns a copy of the entry for `key` and its age if it is younger
+    /// than the TTL. Expired entries are dropped.
+    pub fn get(&self, key: &str, now: Instant) -> Option<(TimeResponse, Duration)> {
+        let mut entries = self.entries.lock().expect("time cache lock poisoned");
+        let tick = entries.tick();
+        let entry = entries.map.get_mut(key)?;
+        let age = now.duration_since(entry.fetched);
+        if age >= self.ttl {
+            entries.map.remove(key);
+            return None;
+        }
+        entry.last_used = tick;
+        Some((entry.response.clone(), age))
+    }
+
+    /// Stores `response`, evicting the least recently used entry when full.
+    pub fn insert(&self, key: String, response: TimeResponse, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("time cache lock poisoned");
+        let last_used = entries.tick();
+        if entries.map.len() >= self.capacity && !entries.map.contains_key(&key) {
+            // A linear scan is cheap at the capacities this cache is used with.
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.map.remove(&oldest);
+            }
+        }
+        entries.map.insert(
+            key,
+            Entry {
+                response,
+                fetched: now,
+                last_used,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .expect("time cache lock poisoned")
+            .map
+            .len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(timezone: &str) -> TimeResponse {
+        TimeResponse {
+            timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+            timezone: timezone.to_string(),
+            request_id: "upstream".to_string(),
+            source: "api2-service".to_string(),
+            utc_offset_seconds: None,
+            utc_offset_label: None,
+            display: None,
+            format: None,
+            degraded: None,
+            clock_offset_ms: None,
+        }
+    }
+
+    #[test]
+    fn expires_entries_after_ttl() {
+        let cache = TimeCache::new(4, Duration::from_millis(500));
+        let start = Instant::now();
+        cache.insert("UTC".to_string(), response("UTC"), start);
+
+        let (_, age) = cache
+            .get("UTC", start + Duration::from_millis(499))
+            .unwrap();
+        assert_eq!(age, Duration::from_millis(499));
+        assert!(cache
+            .get("UTC", start + Duration::from_millis(500))
+            .is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = TimeCache::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert("A".to_string(), response("A"), now);
+        cache.insert("B".to_string(), response("B"), now);
+        cache.get("A", now);
+        cache.insert("C".to_string(), response("C"), now);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("A", now).is_some());
+        assert!(cache.get("B", now).is_none());
+        assert!(cache.get("C", now).is_some());
+    }
+}
@@ -0,0 +1,235 @@
+//! Circuit breaker that stops calling API2 during a sustained outage.
+//!
+//! Closed: calls go through and failures are counted. After `threshold`
+//! failures, each within `window` of the previous one, the breaker opens
+//! and calls fail fast. Once `reset` has passed it is half-open: one probe
+//! call goes through, and its outcome closes or re-opens the breaker.
+
+use common::config::Config;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DEFAULT_THRESHOLD: u32 = 5;
+const DEFAULT_WINDOW_SECS: u64 = 30;
+const DEFAULT_RESET_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Times are Unix milliseconds, passed in so tests can control the clock.
+pub struct CircuitBreaker {
+    threshold: u32,
+    window_ms: i64,
+    reset_ms: i64,
+    failures: AtomicU32,
+    last_failure_ms: AtomicI64,
+    /// When the breaker last opened; zero while closed.
+    opened_at_ms: AtomicI64,
+    probe_in_flight: AtomicBool,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker::new(DEFAULT_THRESHOLD, DEFAULT_WINDOW_SECS, DEFAULT_RESET_SECS)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, window_secs: u64, reset_secs: u64) -> Self {
+        let millis = |secs: u64| i64::try_from(secs.saturating_mul(1000)).unwrap_or(i64::MAX);
+        CircuitBreaker {
+            threshold: threshold.max(1),
+            window_ms: millis(window_secs),
+            reset_ms: millis(reset_secs),
+            failures: AtomicU32::new(0),
+            last_failure_ms: AtomicI64::new(0),
+            opened_at_ms: AtomicI64::new(0),
+            probe_in_flight: AtomicBool::new(false),
+        }
+    }
+
+    /// Uses the `circuit_breaker_*` settings, falling back to the defaults.
+    pub fn from_config(config: &Config) -> Self {
+        CircuitBreaker::new(
+            config
+                .circuit_breaker_threshold
+                .unwrap_or(DEFAULT_THRESHOLD),
+            config
+                .circuit_breaker_window_secs
+                .unwrap_or(DEFAULT_WINDOW_SECS),
+            config
+                .circuit_breaker_reset_secs
+                .unwrap_or(DEFAULT_RESET_SECS),
+        )
+    }
+
+    pub fn state(&self, now_ms: i64) -> CircuitState {
+        let opened_at = self.opened_at_ms.load(Ordering::Acquire);
+        if opened_at == 0 {
+            CircuitState::Closed
+        } else if now_ms - opened_at < self.reset_ms {
+            CircuitState::Open
+        } else {
+            CircuitState::HalfOpen
+        }
+    }
+
+    /// Time until the breaker lets a probe through; zero unless open.
+    pub fn retry_after(&self, now_ms: i64) -> Duration {
+        let opened_at = self.opened_at_ms.load(Ordering::Acquire);
+        if opened_at == 0 {
+            return Duration::ZERO;
+        }
+        let remaining = self.reset_ms - (now_ms - opened_at);
+        Duration::from_millis(u64::try_from(remaining).unwrap_or(0))
+    }
+
+    /// Permission for one call, or `None` while open or while another
+    /// half-open probe is in flight.
+    pub fn try_acquire(&self, now_ms: i64) -> Option<Permit<'_>> {
+        match self.state(now_ms) {
+            CircuitState::Closed => Some(Permit {
+                breaker: self,
+                probe: false,
+            }),
+            CircuitState::Open => None,
+            CircuitState::HalfOpen => self
+                .probe_in_flight
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .ok()
+                .map(|_| Permit {
+                    breaker: self,
+                    probe: true,
+                }),
+        }
+    }
+
+    fn record_success(&self, probe: bool) {
+        self.failures.store(0, Ordering::Release);
+        if probe {
+            self.opened_at_ms.store(0, Ordering::Release);
+            self.probe_in_flight.store(false, Ordering::Release);
+            info!("API2 probe succeeded; circuit closed");
+        }
+    }
+
+    fn record_failure(&self, probe: bool, now_ms: i64) {
+        if probe {
+            self.opened_at_ms.store(now_ms, Ordering::Release);
+            self.probe_in_flight.store(false, Ordering::Release);
+            warn!("API2 probe failed; circuit re-opened");
+            return;
+        }
+
+        let previous = self.last_failure_ms.swap(now_ms, Ordering::AcqRel);
+        let failures = if now_ms - previous > self.window_ms {
+            self.failures.store(1, Ordering::Release);
+            1
+        } else {
+            self.failures.fetch_add(1, Ordering::AcqRel) + 1
+        };
+        if failures >= self.threshold
+            && self
+                .opened_at_ms
+                .compare_exchange(0, now_ms, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            warn!(failures, "API2 failing repeatedly; circuit opened");
+        }
+    }
+}
+
+/// One permitted call. Report its outcome with [`Permit::success`] or
+/// [`Permit::failure`]; a probe dropped without either frees the probe slot.
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Permit<'_> {
+    pub fn success(mut self) {
+        self.breaker.record_success(self.probe);
+        self.probe = false;
+    }
+
+    pub fn failure(mut self, now_ms: i64) {
+        self.breaker.record_failure(self.probe, now_ms);
+        self.probe = false;
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.probe_in_flight.store(false, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: i64 = 1_700_000_000_000;
+
+    #[test]
+    fn opens_probes_and_closes() {
+        let breaker = CircuitBreaker::new(3, 30, 60);
+        for second in 0..3 {
+            breaker
+                .try_acquire(START + second * 1000)
+                .unwrap()
+                .failure(START + second * 1000);
+        }
+        let opened = START + 2000;
+        assert_eq!(breaker.state(opened), CircuitState::Open);
+        assert!(breaker.try_acquire(opened + 59_999).is_none());
+        assert_eq!(breaker.retry_after(opened + 15_000).as_secs(), 45);
+
+        // Half-open: a single probe, which fails and re-opens the breaker.
+        let probe_time = opened + 60_000;
+        assert_eq!(breaker.state(probe_time), CircuitState::HalfOpen);
+        let probe = breaker.try_acquire(probe_time).unwrap();
+        assert!(breaker.try_acquire(probe_time).is_none());
+        assert_eq!(breaker.retry_after(probe_time), Duration::ZERO);
+        probe.failure(probe_time);
+        assert_eq!(breaker.state(probe_time), CircuitState::Open);
+        assert_eq!(breaker.retry_after(probe_time).as_secs(), 60);
+
+        // The next probe succeeds and closes it.
+        let probe_time = probe_time + 60_000;
+        breaker.try_acquire(probe_time).unwrap().success();
+        assert_eq!(breaker.state(probe_time), CircuitState::Closed);
+        assert!(breaker.try_acquire(probe_time).is_some());
+    }
+
+    #[test]
+    fn failures_outside_window_do_not_accumulate() {
+        let breaker = CircuitBreaker::new(2, 30, 60);
+        breaker.try_acquire(START).unwrap().failure(START);
+        let later = START + 31_000;
+        breaker.try_acquire(later).unwrap().failure(later);
+        assert_eq!(breaker.state(later), CircuitState::Closed);
+
+        // A success resets the streak as well.
+        breaker.try_acquire(later).unwrap().success();
+        breaker.try_acquire(later).unwrap().failure(later);
+        assert_eq!(breaker.state(later), CircuitState::Closed);
+    }
+
+    #[test]
+    fn dropped_probe_frees_the_slot() {
+        let breaker = CircuitBreaker::new(1, 30, 60);
+        breaker.try_acquire(START).unwrap().failure(START);
+        let probe_time = START + 60_000;
+        drop(breaker.try_acquire(probe_time).unwrap());
+        assert!(breaker.try_acquire(probe_time).is_some());
+    }
+}
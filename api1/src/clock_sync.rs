@@ -0,0 +1,45 @@
+use axum::response::Json;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub struct ClockSynchronisation {
+    t1: String,
+    t2: String,
+    formula: &'static str,
+    instructions: Vec<&'static str>,
+}
+
+const FORMULA: &str = "offset = ((t1 - client_send) + (t2 - client_receive)) / 2";
+
+const INSTRUCTIONS: [&str; 6] = [
+    "1. Record client_send from your local clock immediately before sending the request.",
+    "2. Record client_receive from your local clock as soon as the response arrives.",
+    "3. Parse t1 (server receipt time) and t2 (server send time) from this response.",
+    "4. Compute offset = ((t1 - client_send) + (t2 - client_receive)) / 2; add it to your clock to match the server.",
+    "5. Optionally compute round_trip = (client_receive - client_send) - (t2 - t1) to judge the sample's quality.",
+    "Example: client_send=10:00:00.000, t1=10:00:00.150, t2=10:00:00.152, client_receive=10:00:00.102 gives offset = (0.150 + 0.050) / 2 = +0.100s (your clock is 100 ms behind) and round_trip = 0.102 - 0.002 = 0.100s.",
+];
+
+pub async fn get_clock_synchronisation() -> Json<ClockSynchronisation> {
+    let t1 = Utc::now();
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Received clock synchronisation request"
+    );
+
+    let mut response = ClockSynchronisation {
+        t1: t1.to_rfc3339_opts(SecondsFormat::Micros, true),
+        t2: String::new(),
+        formula: FORMULA,
+        instructions: INSTRUCTIONS.to_vec(),
+    };
+    // Stamp t2 last so it reflects the moment the response leaves the handler.
+    response.t2 = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+
+    Json(response)
+}
@@ -0,0 +1,64 @@
+//! Proxy for API2's timestamp conversion endpoint.
+
+use axum::{
+    extract::{Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::grpc::{ConvertTimeRequest, CONVERT_TIME_PATH};
+use common::problem::Query;
+use common::trace_context::TraceContext;
+use common::TimeConvertResponse;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{call_context, forward_to_api2, grpc_upstream, request_id_from, ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TimeConvertQuery {
+    timestamp: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+pub async fn get_time_convert(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimeConvertQuery>,
+) -> Result<Json<TimeConvertResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+
+    info!(
+        request_id = %request_id,
+        timestamp = ?params.timestamp,
+        from = ?params.from,
+        to = ?params.to,
+        "Received time convert request"
+    );
+
+    if let Some(upstream) = &state.grpc {
+        let request = ConvertTimeRequest {
+            timestamp: params.timestamp,
+            from: params.from,
+            to: params.to,
+        };
+        let converted =
+            grpc_upstream::call(&state, upstream, &request_id, CONVERT_TIME_PATH, &request).await?;
+        return Ok(Json(converted));
+    }
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (converted, _) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_convert(
+            api2_url,
+            &context,
+            params.timestamp.as_deref(),
+            params.from.as_deref(),
+            params.to.as_deref(),
+        )
+    })
+    .await?;
+
+    Ok(Json(converted))
+}
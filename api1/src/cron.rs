@@ -0,0 +1,38 @@
+//! Proxy for API2's cron preview endpoint.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::trace_context::TraceContext;
+use common::{CronRequest, CronResponse};
+use tracing::info;
+
+use crate::{call_context, error_for_status, forward_to_api2, request_id_from, ApiError, AppState};
+
+pub async fn post_cron_next(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    body: Result<Json<CronRequest>, JsonRejection>,
+) -> Result<Json<CronResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(request) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        expression = %request.expression,
+        timezone = ?request.timezone,
+        "Received cron request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (next, _) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.cron_next(api2_url, &context, &request)
+    })
+    .await?;
+    Ok(Json(next))
+}
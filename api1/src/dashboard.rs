@@ -0,0 +1,100 @@
+//! `/dashboard`: a world clock page for demos, compiled into the binary.
+//!
+//! The page shows a live clock for each of `DASHBOARD_TIMEZONES`. It
+//! subscribes to all of them over `/time/ws`, falling back to one
+//! `/time/stream` per zone and then to polling `/time` every second, and
+//! skips the transports whose feature flag is not on. Browsers cannot send
+//! `X-Api-Key` on a WebSocket or `EventSource`, so with `API_KEYS` set the
+//! page polls, with the key given in its URL fragment as `#api_key=...`.
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse},
+};
+use common::flags::FlagState;
+use serde_json::json;
+
+use crate::AppState;
+
+pub const PATH: &str = "/dashboard";
+
+const TEMPLATE: &str = include_str!("dashboard.html");
+const POLL_INTERVAL_MS: u64 = 1000;
+
+const DEFAULT_TIMEZONES: [&str; 6] = [
+    "UTC",
+    "America/New_York",
+    "Europe/London",
+    "Asia/Bangkok",
+    "Asia/Tokyo",
+    "Australia/Sydney",
+];
+
+/// Zones shown unless `DASHBOARD_TIMEZONES` is set.
+pub fn default_timezones() -> Vec<String> {
+    DEFAULT_TIMEZONES
+        .iter()
+        .map(|zone| zone.to_string())
+        .collect()
+}
+
+/// How the page can get updates, in order of preference.
+fn transports(state: &AppState) -> Vec<&'static str> {
+    let mut transports = Vec::new();
+    if state.api_keys.is_empty() {
+        if state.flags.state("time_ws") == FlagState::On {
+            transports.push("ws");
+        }
+        if state.flags.state("time_stream") == FlagState::On {
+            transports.push("sse");
+        }
+    }
+    transports.push("poll");
+    transports
+}
+
+/// The page, with its settings written into the script; `<` is escaped so
+/// no value can close the `<script>` element.
+fn render(state: &AppState) -> String {
+    let config = json!({
+        "timezones": *state.dashboard_timezones,
+        "transports": transports(state),
+        "poll_interval_ms": POLL_INTERVAL_MS,
+    });
+    TEMPLATE.replace("{{config}}", &config.to_string().replace('<', "\\u003c"))
+}
+
+pub async fn get_dashboard(State(state): State<AppState>) -> impl IntoResponse {
+    ([(header::CACHE_CONTROL, "no-cache")], Html(render(&state)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::flags::FeatureFlags;
+    use std::sync::Arc;
+
+    #[test]
+    fn prefers_streams_the_flags_and_api_keys_allow() {
+        let state = AppState::new("http://api2");
+        assert_eq!(transports(&state), ["ws", "sse", "poll"]);
+        let page = render(&state);
+        assert!(page.contains(r#""timezones":["UTC","America/New_York","#));
+        assert!(!page.contains("{{config}}"));
+
+        let state = AppState {
+            flags: Arc::new(FeatureFlags::new(&[("time_ws", FlagState::Off)])),
+            ..state
+        };
+        assert_eq!(transports(&state), ["sse", "poll"]);
+
+        let state = AppState {
+            api_keys: Arc::new(vec!["key-1".to_string()]),
+            dashboard_timezones: Arc::new(vec!["</script>".to_string()]),
+            ..state
+        };
+        assert_eq!(transports(&state), ["poll"]);
+        assert!(render(&state).contains(r#"["\u003c/script>"]"#));
+    }
+}
@@ -0,0 +1,49 @@
+//! Proxy for API2's timezone offset difference endpoint.
+
+use axum::{
+    extract::{Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::problem::Query;
+use common::trace_context::TraceContext;
+use common::TimeDiffResponse;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{call_context, forward_to_api2, request_id_from, ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TimeDiffQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+pub async fn get_time_diff(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimeDiffQuery>,
+) -> Result<Json<TimeDiffResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+
+    info!(
+        request_id = %request_id,
+        from = ?params.from,
+        to = ?params.to,
+        "Received time diff request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (diff, _) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_diff(
+            api2_url,
+            &context,
+            params.from.as_deref(),
+            params.to.as_deref(),
+        )
+    })
+    .await?;
+
+    Ok(Json(diff))
+}
@@ -0,0 +1,63 @@
+//! Local time for `/time` while API2 is unavailable, with
+//! `FALLBACK_LOCAL_TIME=true`.
+//!
+//! API1 reads its own clock and timezone database, so the answer is only as
+//! good as this host's clock; responses say so with `degraded: true`.
+
+use chrono::{Offset, Utc};
+use chrono_tz::Tz;
+use common::format::{format_utc_offset, TimestampFormat};
+use common::{resolve_timezone_alias, TimeResponse};
+
+/// `source` of responses computed by API1 itself.
+pub const FALLBACK_SOURCE: &str = "api1-fallback";
+
+/// The current time in `timezone`, as API2 would have rendered it apart
+/// from `display`.
+pub fn local_time(
+    timezone: &str,
+    format: &TimestampFormat,
+    request_id: &str,
+) -> Result<TimeResponse, String> {
+    let tz = resolve_timezone_alias(timezone)
+        .parse::<Tz>()
+        .map_err(|_| format!("Invalid timezone: {timezone}"))?;
+    let local = Utc::now().with_timezone(&tz);
+    let utc_offset_seconds = local.offset().fix().local_minus_utc();
+    Ok(TimeResponse {
+        timestamp: format.format(&local),
+        timezone: timezone.to_string(),
+        request_id: request_id.to_string(),
+        source: FALLBACK_SOURCE.to_string(),
+        utc_offset_seconds: Some(utc_offset_seconds),
+        utc_offset_label: Some(format_utc_offset(utc_offset_seconds)),
+        display: None,
+        format: Some(format.name().to_string()),
+        degraded: Some(true),
+        clock_offset_ms: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_time_in_zone_or_rejects_unknown_zones() {
+        let time = local_time("Asia/Kolkata", &TimestampFormat::Rfc3339, "req-1").unwrap();
+        assert!(time.timestamp.ends_with("+05:30"), "{}", time.timestamp);
+        assert_eq!(time.utc_offset_seconds, Some(19800));
+        assert_eq!(time.source, "api1-fallback");
+        assert_eq!(time.degraded, Some(true));
+        assert_eq!(
+            local_time("PST", &TimestampFormat::Unix, "req-2")
+                .unwrap()
+                .timezone,
+            "PST"
+        );
+        assert_eq!(
+            local_time("Mars/Olympus", &TimestampFormat::Rfc3339, "req-3").unwrap_err(),
+            "Invalid timezone: Mars/Olympus"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Calls to API2's gRPC service, used instead of HTTP for `/time` and
+//! `/time/convert` when `API2_TRANSPORT=grpc`.
+//!
+//! Calls share the circuit breaker, `API2_TIMEOUT_MS` and upstream metrics
+//! with the HTTP path. They go to the single `API2_GRPC_URL` and are not
+//! retried.
+
+use axum::http::StatusCode;
+use common::config::Config;
+use common::grpc::{Client, Code, Message, Transport};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::{
+    circuit_open, error_for_status, now_ms, ApiError, AppState, UPSTREAM_ERRORS_TOTAL,
+    UPSTREAM_REQUEST_DURATION_SECONDS,
+};
+
+/// `source` label of responses API2 answered over gRPC.
+pub const GRPC_SOURCE: &str = "api1->api2[grpc]";
+
+#[derive(Clone)]
+pub struct GrpcUpstream {
+    client: Client,
+    url: Arc<str>,
+}
+
+impl GrpcUpstream {
+    pub fn new(url: &str) -> Self {
+        GrpcUpstream {
+            client: Client::default(),
+            url: url.into(),
+        }
+    }
+
+    /// `Some` when `API2_TRANSPORT` is `grpc`.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        match Transport::from_config(config) {
+            Transport::Http => None,
+            Transport::Grpc => config.api2_grpc_url.as_deref().map(GrpcUpstream::new),
+        }
+    }
+}
+
+/// Calls `path` with `request`, mapping failures as [`crate::send_to_api2`]
+/// does: API2's validation errors are relayed, everything else is a `5xx`.
+pub async fn call<Req: Message, Resp: Message>(
+    state: &AppState,
+    upstream: &GrpcUpstream,
+    request_id: &str,
+    path: &str,
+    request: &Req,
+) -> Result<Resp, ApiError> {
+    let Some(permit) = state.breaker.try_acquire(now_ms()) else {
+        return Err(circuit_open(state, request_id));
+    };
+    info!(
+        request_id = %request_id,
+        api2_url = %upstream.url,
+        path = %path,
+        "Forwarding request to API2 over gRPC"
+    );
+
+    let started = Instant::now();
+    let result = upstream
+        .client
+        .unary(
+            &upstream.url,
+            path,
+            request,
+            request_id,
+            state.upstream_timeout(),
+        )
+        .await;
+    state.metrics.observe(
+        &UPSTREAM_REQUEST_DURATION_SECONDS,
+        &[],
+        started.elapsed().as_secs_f64(),
+    );
+    let status = match result {
+        Ok(response) => {
+            permit.success();
+            return Ok(response);
+        }
+        Err(status) => status,
+    };
+
+    let relayed = matches!(
+        status.code,
+        Code::InvalidArgument | Code::NotFound | Code::FailedPrecondition
+    );
+    if relayed {
+        permit.success();
+        let http_status = StatusCode::from_u16(status.http_status()).expect("valid status");
+        return Err(error_for_status(http_status, status.message, request_id));
+    }
+    permit.failure(now_ms());
+
+    let (kind, http_status, message) = match status.code {
+        Code::DeadlineExceeded => (
+            "timeout",
+            StatusCode::GATEWAY_TIMEOUT,
+            format!(
+                "Upstream timeout after {}ms",
+                state.api2_timeout.as_millis()
+            ),
+        ),
+        Code::Unavailable => (
+            "connect",
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Failed to connect to API2".to_string(),
+        ),
+        code => (
+            "status",
+            StatusCode::BAD_GATEWAY,
+            format!("API2 returned gRPC status: {code:?}"),
+        ),
+    };
+    state
+        .metrics
+        .increment(&UPSTREAM_ERRORS_TOTAL, &[("kind", kind)]);
+    error!(
+        request_id = %request_id,
+        code = ?status.code,
+        error = %status.message,
+        "gRPC call to API2 failed"
+    );
+    Err(error_for_status(http_status, message, request_id))
+}
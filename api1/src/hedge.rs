@@ -0,0 +1,215 @@
+//! Hedged calls to API2, against the multi-second stalls API2 occasionally
+//! has.
+//!
+//! With `API2_HEDGE_PERCENTILE` set, a call still unanswered after that
+//! percentile of recent API2 latencies, and at least
+//! `API2_HEDGE_MIN_DELAY_MS`, is sent a second time to the next instance in
+//! balancer order (the same one when there is only one). Whichever call
+//! answers first is used and the other is cancelled. Only `GET` calls are
+//! hedged, since sending anything else twice could repeat its effect, and
+//! none are until [`MIN_SAMPLES`] latencies have been seen.
+
+use common::api2_client::SendError;
+use common::config::Config;
+use common::metrics::Counter;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::sla::{nearest_rank, LatencyRecorder};
+
+/// Latencies needed before the percentile is trusted as a hedge delay.
+pub const MIN_SAMPLES: usize = 20;
+const DEFAULT_MIN_DELAY: Duration = Duration::from_millis(10);
+
+pub const UPSTREAM_HEDGES_TOTAL: Counter = Counter {
+    name: "upstream_hedges_total",
+    help: "Hedged API2 calls by which call answered: primary or hedge.",
+};
+
+/// One of the two calls of a hedged pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Call {
+    Primary,
+    Hedge,
+}
+
+impl Call {
+    pub fn label(self) -> &'static str {
+        match self {
+            Call::Primary => "primary",
+            Call::Hedge => "hedge",
+        }
+    }
+}
+
+pub struct Hedging {
+    percentile: f64,
+    min_delay: Duration,
+    /// Recent latencies of answered API2 calls.
+    latency: LatencyRecorder,
+}
+
+impl Hedging {
+    pub fn new(percentile: f64, min_delay: Duration) -> Self {
+        Hedging {
+            percentile,
+            min_delay,
+            latency: LatencyRecorder::default(),
+        }
+    }
+
+    /// Hedging at `hedge_percentile`, or `None` when it is unset.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Hedging::new(
+            config.hedge_percentile?,
+            config
+                .hedge_min_delay_ms
+                .map_or(DEFAULT_MIN_DELAY, Duration::from_millis),
+        ))
+    }
+
+    pub fn record(&self, latency: Duration) {
+        self.latency.record(latency.as_secs_f64() * 1000.0);
+    }
+
+    /// How long to wait for a call before hedging it; `None` until enough
+    /// latencies have been seen.
+    pub fn delay(&self) -> Option<Duration> {
+        let samples = self.latency.sorted_samples();
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let percentile = nearest_rank(&samples, self.percentile)?;
+        Some(Duration::from_secs_f64(percentile / 1000.0).max(self.min_delay))
+    }
+}
+
+/// Whether API2 answered the call, rather than failing or returning a 5xx
+/// that the other call might do better than.
+fn answered(result: &Result<reqwest::Response, SendError>) -> bool {
+    matches!(result, Ok(response) if !response.status().is_server_error())
+}
+
+/// Makes the primary call with `send`, and the hedge too if the primary has
+/// not finished after `delay`. Returns the first answer, or the last result
+/// when neither answers, with the call it came from; the call is `None` when
+/// no hedge was sent. Dropping the other call's future cancels it.
+pub async fn race<F, Fut>(
+    delay: Duration,
+    send: F,
+) -> (Result<reqwest::Response, SendError>, Option<Call>)
+where
+    F: Fn(Call) -> Fut,
+    Fut: Future<Output = Result<reqwest::Response, SendError>>,
+{
+    let primary = send(Call::Primary);
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => return (result, None),
+        () = tokio::time::sleep(delay) => {}
+    }
+
+    let hedge = send(Call::Hedge);
+    tokio::pin!(hedge);
+    tokio::select! {
+        result = &mut primary => {
+            if answered(&result) {
+                (result, Some(Call::Primary))
+            } else {
+                (hedge.await, Some(Call::Hedge))
+            }
+        }
+        result = &mut hedge => {
+            if answered(&result) {
+                (result, Some(Call::Hedge))
+            } else {
+                (primary.await, Some(Call::Primary))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn waits_for_enough_samples_and_the_minimum_delay() {
+        let hedging = Hedging::new(95.0, Duration::from_millis(10));
+        for ms in 1..MIN_SAMPLES as u64 {
+            hedging.record(Duration::from_millis(ms));
+        }
+        assert_eq!(hedging.delay(), None);
+        hedging.record(Duration::from_millis(200));
+        assert_eq!(hedging.delay(), Some(Duration::from_millis(19)));
+
+        let hedging = Hedging::new(50.0, Duration::from_millis(10));
+        for _ in 0..MIN_SAMPLES {
+            hedging.record(Duration::from_millis(1));
+        }
+        assert_eq!(hedging.delay(), Some(Duration::from_millis(10)));
+    }
+
+    #[tokio::test]
+    async fn takes_the_first_answer_and_cancels_the_other() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        // Counts handlers dropped before they answered.
+        struct Unfinished(Option<Arc<AtomicUsize>>);
+        impl Drop for Unfinished {
+            fn drop(&mut self) {
+                if let Some(counter) = &self.0 {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+        let cancelled = Arc::new(AtomicUsize::new(0));
+        let counter = cancelled.clone();
+        let router = Router::new().route(
+            "/sleep/:ms",
+            get(move |Path(ms): Path<u64>| async move {
+                let mut unfinished = Unfinished(Some(counter));
+                tokio::time::sleep(Duration::from_millis(ms)).await;
+                unfinished.0 = None;
+                ms.to_string()
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let client = common::api2_client::Api2Client::default();
+        let http = reqwest::Client::new();
+        let send = |primary_ms: u64, hedge_ms: u64| {
+            let (client, http, base_url) = (&client, &http, &base_url);
+            move |call: Call| async move {
+                let ms = if call == Call::Primary {
+                    primary_ms
+                } else {
+                    hedge_ms
+                };
+                client
+                    .send(http.get(format!("{base_url}/sleep/{ms}")))
+                    .await
+            }
+        };
+
+        let (result, call) = race(Duration::from_millis(100), send(5, 5)).await;
+        assert_eq!(result.unwrap().text().await.unwrap(), "5");
+        assert_eq!(call, None);
+
+        let (result, call) = race(Duration::from_millis(20), send(5_000, 10)).await;
+        assert_eq!(result.unwrap().text().await.unwrap(), "10");
+        assert_eq!(call, Some(Call::Hedge));
+
+        let (result, call) = race(Duration::from_millis(20), send(60, 5_000)).await;
+        assert_eq!(result.unwrap().text().await.unwrap(), "60");
+        assert_eq!(call, Some(Call::Primary));
+
+        // Dropping the losing calls closed their connections, and API2
+        // stopped working on them.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cancelled.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,362 @@
+//! `Idempotency-Key` support for API1's `POST` routes.
+//!
+//! The first response to a key is kept for `IDEMPOTENCY_TTL_SECS` and
+//! replayed, with `Idempotent-Replayed: true`, to retries of the same
+//! request instead of calling API2 again. Keys are scoped to the caller's
+//! `X-Api-Key`. Reusing a key for a different method, URL or body, or
+//! while its first request is still running, answers `409`. `5xx`
+//! responses are not kept, so a retry after an outage runs again.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use common::auth::API_KEY_HEADER;
+use common::config::Config;
+use common::problem::ErrorCode;
+use common::REQUEST_ID_HEADER;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error_response;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set on responses replayed from the store.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const DEFAULT_TTL_SECS: u64 = 86_400;
+const DEFAULT_CAPACITY: usize = 1000;
+const MAX_KEY_CHARS: usize = 255;
+
+/// A response as first sent, to be sent again.
+#[derive(Debug, Clone)]
+struct Stored {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Stored {
+    fn replay(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+struct Entry {
+    /// Hash of the method, URL and body the key was first used with.
+    fingerprint: u64,
+    /// `None` while the first request is still running.
+    response: Option<Stored>,
+    created: Instant,
+}
+
+/// What to do with a request carrying a key.
+#[derive(Debug)]
+enum Begin {
+    Run,
+    Replay(Stored),
+    InProgress,
+    Mismatch,
+}
+
+pub struct IdempotencyStore {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        IdempotencyStore::new(DEFAULT_CAPACITY, Duration::from_secs(DEFAULT_TTL_SECS))
+    }
+}
+
+impl IdempotencyStore {
+    /// A capacity of zero disables the store; keys are then ignored.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        IdempotencyStore {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Uses `idempotency_capacity` and `idempotency_ttl_secs`, falling back
+    /// to 1000 keys for a day.
+    pub fn from_config(config: &Config) -> Self {
+        IdempotencyStore::new(
+            config.idempotency_capacity.unwrap_or(DEFAULT_CAPACITY),
+            Duration::from_secs(config.idempotency_ttl_secs.unwrap_or(DEFAULT_TTL_SECS)),
+        )
+    }
+
+    /// Claims `key` for a request with `fingerprint`, or says why not.
+    fn begin(&self, key: &str, fingerprint: u64, now: Instant) -> Begin {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        if let Some(entry) = entries.get(key) {
+            if now.duration_since(entry.created) < self.ttl {
+                return match &entry.response {
+                    _ if entry.fingerprint != fingerprint => Begin::Mismatch,
+                    None => Begin::InProgress,
+                    Some(stored) => Begin::Replay(stored.clone()),
+                };
+            }
+            entries.remove(key);
+        }
+        if entries.len() >= self.capacity {
+            entries.retain(|_, entry| now.duration_since(entry.created) < self.ttl);
+        }
+        if entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key.to_string(),
+            Entry {
+                fingerprint,
+                response: None,
+                created: now,
+            },
+        );
+        Begin::Run
+    }
+
+    /// Records the response to a request [`begin`](Self::begin) let run, or
+    /// releases the key when there is none worth keeping.
+    fn finish(&self, key: &str, fingerprint: u64, response: Option<Stored>) {
+        let mut entries = self.entries.lock().expect("idempotency lock poisoned");
+        let claimed = entries
+            .get(key)
+            .is_some_and(|entry| entry.fingerprint == fingerprint && entry.response.is_none());
+        if !claimed {
+            // Evicted or expired while running.
+            return;
+        }
+        match response {
+            Some(stored) => {
+                if let Some(entry) = entries.get_mut(key) {
+                    entry.response = Some(stored);
+                }
+            }
+            None => {
+                entries.remove(key);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .expect("idempotency lock poisoned")
+            .len()
+    }
+}
+
+/// Releases a claimed key if the request is abandoned, e.g. by the request
+/// timeout, before [`IdempotencyStore::finish`] runs.
+struct Claim {
+    store: Arc<IdempotencyStore>,
+    key: String,
+    fingerprint: u64,
+    finished: bool,
+}
+
+impl Claim {
+    fn finish(mut self, response: Option<Stored>) {
+        self.finished = true;
+        self.store.finish(&self.key, self.fingerprint, response);
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.store.finish(&self.key, self.fingerprint, None);
+        }
+    }
+}
+
+fn hash(parts: &[&[u8]]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Middleware replaying responses to `POST` requests that repeat an
+/// `Idempotency-Key`.
+pub async fn replay(
+    State(store): State<Arc<IdempotencyStore>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST || store.capacity == 0 {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER).cloned() else {
+        return next.run(request).await;
+    };
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_CHARS => key.to_string(),
+        _ => {
+            return error_response(
+                ErrorCode::InvalidRequest,
+                format!("Idempotency-Key must be 1 to {MAX_KEY_CHARS} visible ASCII characters"),
+                &request_id,
+            )
+            .into_response();
+        }
+    };
+
+    let (parts, body) = request.into_parts();
+    // The request limits have already read the body into memory.
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return error_response(
+            ErrorCode::InvalidRequest,
+            "Failed to read request body",
+            &request_id,
+        )
+        .into_response();
+    };
+    let api_key = parts
+        .headers
+        .get(API_KEY_HEADER)
+        .map_or(&[][..], HeaderValue::as_bytes);
+    let scoped = format!("{:016x}:{key}", hash(&[api_key]));
+    let fingerprint = hash(&[
+        parts.method.as_str().as_bytes(),
+        parts.uri.to_string().as_bytes(),
+        &body,
+    ]);
+
+    match store.begin(&scoped, fingerprint, Instant::now()) {
+        Begin::Run => {}
+        Begin::Replay(stored) => {
+            info!(request_id = %request_id, idempotency_key = %key, "Replaying stored response");
+            return stored.replay();
+        }
+        Begin::InProgress => {
+            return error_response(
+                ErrorCode::Conflict,
+                format!("A request with Idempotency-Key {key} is still in progress"),
+                &request_id,
+            )
+            .into_response();
+        }
+        Begin::Mismatch => {
+            return error_response(
+                ErrorCode::Conflict,
+                format!("Idempotency-Key {key} was already used for a different request"),
+                &request_id,
+            )
+            .into_response();
+        }
+    }
+
+    let claim = Claim {
+        store,
+        key: scoped,
+        fingerprint,
+        finished: false,
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status().is_server_error() {
+        claim.finish(None);
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        claim.finish(None);
+        return error_response(
+            ErrorCode::Internal,
+            "Failed to read response body",
+            &request_id,
+        )
+        .into_response();
+    };
+    claim.finish(Some(Stored {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    }));
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(body: &'static str) -> Stored {
+        Stored {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from(body),
+        }
+    }
+
+    #[test]
+    fn replays_matching_requests_and_rejects_others() {
+        let store = IdempotencyStore::new(10, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(matches!(store.begin("k", 1, now), Begin::Run));
+        assert!(matches!(store.begin("k", 1, now), Begin::InProgress));
+        assert!(matches!(store.begin("k", 2, now), Begin::Mismatch));
+
+        store.finish("k", 1, Some(stored("first")));
+        match store.begin("k", 1, now) {
+            Begin::Replay(stored) => assert_eq!(stored.body, "first"),
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(store.begin("k", 2, now), Begin::Mismatch));
+
+        // Expired keys may be reused for anything.
+        let later = now + Duration::from_secs(60);
+        assert!(matches!(store.begin("k", 2, later), Begin::Run));
+    }
+
+    #[test]
+    fn releases_keys_without_a_response_and_evicts_the_oldest() {
+        let store = Arc::new(IdempotencyStore::new(2, Duration::from_secs(60)));
+        let now = Instant::now();
+        assert!(matches!(store.begin("a", 1, now), Begin::Run));
+        drop(Claim {
+            store: Arc::clone(&store),
+            key: "a".to_string(),
+            fingerprint: 1,
+            finished: false,
+        });
+        assert_eq!(store.len(), 0);
+
+        for (offset, key) in ["a", "b", "c"].into_iter().enumerate() {
+            let at = now + Duration::from_secs(offset as u64);
+            assert!(matches!(store.begin(key, 1, at), Begin::Run));
+            store.finish(key, 1, Some(stored(key)));
+        }
+        assert_eq!(store.len(), 2);
+        assert!(matches!(store.begin("a", 2, now), Begin::Run));
+    }
+}
@@ -0,0 +1,2672 @@
+//! API1, the gateway in front of the time provider (API2).
+//!
+//! [`app`] builds the router, so tests can drive it without a listener;
+//! [`run`] serves it as the `api1` binary does.
+
+use axum::{
+    extract::{Extension, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware,
+    response::{IntoResponse, Json},
+    routing::{get, post, MethodRouter},
+    Router,
+};
+use common::api2_client::{Api2Client, Api2Request, CallContext, SendError};
+use common::audit::{self, AuditLayer, AuditLog};
+use common::auth::API_KEY_HEADER;
+use common::compression::CompressionLayer;
+use common::config::Config;
+use common::cors::CorsPolicy;
+use common::etag;
+use common::flags::{self, FeatureFlags};
+use common::format::TimestampFormat;
+use common::health;
+use common::limits::{self, RequestLimits};
+use common::logging;
+use common::metrics::{self, MetricsLayer, Registry};
+use common::payload_log::{self, PayloadLog};
+use common::problem::{ErrorCode, Query};
+use common::shutdown::{self, Shutdown};
+use common::tenants::{TenantLayer, Tenants, TENANT_HEADER};
+use common::tls;
+use common::trace_context::{self, TraceContext};
+use common::unix::{self, SocketMode};
+use common::versioning;
+use common::{ErrorResponse, TimeResponse, REQUEST_ID_HEADER};
+use futures_util::future::join_all;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+mod admin;
+mod auth;
+mod balancer;
+mod batch;
+mod cache;
+mod circuit_breaker;
+mod clock_sync;
+mod convert;
+mod cron;
+mod dashboard;
+mod diff;
+mod fallback;
+mod grpc_upstream;
+mod hedge;
+mod idempotency;
+mod load_shed;
+mod openapi;
+mod rate_limit;
+mod readiness;
+mod retry;
+mod skew;
+mod sla;
+mod stream;
+mod time_request;
+mod timers;
+mod timezones;
+mod v2;
+mod ws;
+
+#[derive(Debug, Deserialize)]
+struct TimeQuery {
+    timezone: Option<String>,
+    format: Option<String>,
+    /// Language tag for the `display` field, passed on to API2.
+    locale: Option<String>,
+    /// `bypass` skips the cache lookup; the fresh response is still cached.
+    cache: Option<String>,
+}
+
+const UPSTREAM_REQUEST_DURATION_SECONDS: metrics::Histogram = metrics::Histogram {
+    name: "upstream_request_duration_seconds",
+    help: "Latency of API2 calls in seconds, including retries.",
+};
+
+const UPSTREAM_ERRORS_TOTAL: metrics::Counter = metrics::Counter {
+    name: "upstream_errors_total",
+    help: "Failed API2 calls by failure kind.",
+};
+
+const TIME_FALLBACK_RESPONSES_TOTAL: metrics::Counter = metrics::Counter {
+    name: "time_fallback_responses_total",
+    help: "/time responses computed by API1 because API2 was unavailable.",
+};
+
+const TIME_CACHE_LOOKUPS_TOTAL: metrics::Counter = metrics::Counter {
+    name: "time_cache_lookups_total",
+    help: "/time cache lookups by result: hit, miss or bypass.",
+};
+
+/// State shared by every handler, built once at startup.
+#[derive(Clone)]
+pub struct AppState {
+    /// Builds every call to API2 on one pooled HTTP client.
+    api2: Api2Client,
+    /// Set when `API2_TRANSPORT=grpc`: `/time` and `/time/convert` call
+    /// API2 over gRPC instead.
+    grpc: Option<grpc_upstream::GrpcUpstream>,
+    backends: Arc<balancer::Backends>,
+    /// Deadline for each individual call to API2.
+    api2_timeout: Duration,
+    retry: retry::RetryPolicy,
+    /// Set when `API2_HEDGE_PERCENTILE` is.
+    hedging: Option<Arc<hedge::Hedging>>,
+    latency: Arc<sla::LatencyRecorder>,
+    metrics: Arc<Registry>,
+    rate_limiter: Arc<rate_limit::RateLimiter>,
+    /// Set when `MAX_IN_FLIGHT` is.
+    load_shedder: Option<Arc<load_shed::LoadShedder>>,
+    max_batch_size: usize,
+    cors: CorsPolicy,
+    /// Recent `/time` responses, keyed by timezone and format.
+    time_cache: Arc<cache::TimeCache>,
+    /// Accepted `X-Api-Key` values; empty disables authentication.
+    api_keys: Arc<Vec<String>>,
+    audit: Option<AuditLog>,
+    breaker: Arc<circuit_breaker::CircuitBreaker>,
+    shutdown: Shutdown,
+    readiness: Arc<readiness::ReadinessCache>,
+    limits: Arc<RequestLimits>,
+    /// Answer `/time` from the local clock when API2 fails with a 5xx.
+    fallback_local_time: bool,
+    /// First responses to `POST` requests by `Idempotency-Key`.
+    idempotency: Arc<idempotency::IdempotencyStore>,
+    payload_log: Arc<PayloadLog>,
+    flags: Arc<FeatureFlags>,
+    /// Zones shown on `/dashboard`.
+    dashboard_timezones: Arc<Vec<String>>,
+    tenants: Arc<Tenants>,
+}
+
+/// Response header reporting how many calls to API2 a response took.
+const UPSTREAM_ATTEMPTS_HEADER: &str = "x-upstream-attempts";
+
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_API2_TIMEOUT_MS: u64 = 5000;
+
+impl AppState {
+    /// Deadline for one call to API2: `API2_TIMEOUT_MS`, or less when the
+    /// request being served has less time left than that.
+    fn upstream_timeout(&self) -> Duration {
+        limits::remaining().map_or(self.api2_timeout, |remaining| {
+            remaining.min(self.api2_timeout)
+        })
+    }
+
+    /// Defaults for every setting, calling the API2 instance at `api2_url`.
+    pub fn new(api2_url: impl Into<String>) -> Self {
+        AppState {
+            api2: Api2Client::default(),
+            grpc: None,
+            backends: Arc::new(balancer::Backends::new(
+                vec![api2_url.into()],
+                balancer::Strategy::RoundRobin,
+            )),
+            api2_timeout: Duration::from_millis(DEFAULT_API2_TIMEOUT_MS),
+            retry: retry::RetryPolicy::default(),
+            hedging: None,
+            latency: Arc::new(sla::LatencyRecorder::default()),
+            metrics: Arc::new(Registry::default()),
+            rate_limiter: Arc::new(rate_limit::RateLimiter::default()),
+            load_shedder: None,
+            max_batch_size: common::DEFAULT_MAX_BATCH_SIZE,
+            cors: CorsPolicy::default(),
+            time_cache: Arc::new(cache::TimeCache::default()),
+            api_keys: Arc::new(Vec::new()),
+            audit: None,
+            breaker: Arc::new(circuit_breaker::CircuitBreaker::default()),
+            shutdown: Shutdown::default(),
+            readiness: Arc::new(readiness::ReadinessCache::default()),
+            limits: Arc::new(RequestLimits::default()),
+            fallback_local_time: false,
+            idempotency: Arc::new(idempotency::IdempotencyStore::default()),
+            payload_log: Arc::new(PayloadLog::default()),
+            flags: Arc::new(FeatureFlags::default()),
+            dashboard_timezones: Arc::new(dashboard::default_timezones()),
+            tenants: Arc::new(Tenants::default()),
+        }
+    }
+
+    /// Every setting in `config` except `API_KEYS` and the shutdown signal,
+    /// which [`run`] adds.
+    pub fn from_config(config: &Config) -> Self {
+        let metrics = Arc::new(Registry::default());
+        AppState {
+            api2: Api2Client::from_config(config),
+            grpc: grpc_upstream::GrpcUpstream::from_config(config),
+            backends: Arc::new(balancer::Backends::from_config(config)),
+            api2_timeout: Duration::from_millis(
+                config.timeout_ms.unwrap_or(DEFAULT_API2_TIMEOUT_MS),
+            ),
+            retry: retry::RetryPolicy::from_config(config),
+            hedging: hedge::Hedging::from_config(config).map(Arc::new),
+            rate_limiter: Arc::new(rate_limit::RateLimiter::from_config(config)),
+            load_shedder: load_shed::LoadShedder::from_config(config, metrics.clone())
+                .map(Arc::new),
+            metrics,
+            max_batch_size: config.max_batch_size(),
+            cors: CorsPolicy::from_config(config),
+            time_cache: Arc::new(cache::TimeCache::from_config(config)),
+            audit: AuditLog::from_config(config),
+            breaker: Arc::new(circuit_breaker::CircuitBreaker::from_config(config)),
+            readiness: Arc::new(readiness::ReadinessCache::from_config(config)),
+            limits: Arc::new(RequestLimits::from_config(config)),
+            fallback_local_time: config.fallback_local_time.unwrap_or(false),
+            idempotency: Arc::new(idempotency::IdempotencyStore::from_config(config)),
+            payload_log: Arc::new(PayloadLog::from_config(config)),
+            flags: Arc::new(FeatureFlags::from_config(config)),
+            dashboard_timezones: Arc::new(
+                config
+                    .dashboard_timezones
+                    .clone()
+                    .unwrap_or_else(dashboard::default_timezones),
+            ),
+            tenants: Arc::new(Tenants::from_config(config)),
+            ..AppState::new(String::new())
+        }
+    }
+}
+
+/// Serves API1 on the listeners `config` selects until a shutdown signal,
+/// then drains in-flight requests. Logging is set up by the caller.
+pub async fn run(config: Config) {
+    info!("API1 initializing");
+
+    let port = config.port.unwrap_or(DEFAULT_PORT);
+    let api_keys = config.api_keys();
+    if api_keys.is_empty() {
+        warn!("API_KEYS is not set; API key authentication is disabled");
+    }
+    let shutdown = Shutdown::on_signal().with_delay(config.shutdown_delay());
+    let app = app(AppState {
+        api_keys: Arc::new(api_keys),
+        shutdown: shutdown.clone(),
+        ..AppState::from_config(&config)
+    });
+
+    let socket_mode = SocketMode::from_config(&config);
+    let unix_app = app.clone();
+    let unix_shutdown = shutdown.clone();
+
+    let tcp_server = async {
+        if !socket_mode.tcp() {
+            return Ok(());
+        }
+        let tls = tls::acceptor_from_config(&config);
+        let scheme = if tls.is_some() { "HTTPS" } else { "HTTP" };
+        info!("API1 starting on port {} ({})", port, scheme);
+
+        let addr = SocketAddr::new(config.bind_address(), port);
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        info!("HTTP server listening on: {}", addr);
+
+        match tls {
+            Some(acceptor) => {
+                let server = tls::serve(listener, acceptor, app, shutdown.clone().requested());
+                shutdown
+                    .clone()
+                    .drain(server, config.shutdown_timeout())
+                    .await
+            }
+            None => {
+                let server = axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(shutdown.clone().requested());
+                shutdown
+                    .clone()
+                    .drain(server, config.shutdown_timeout())
+                    .await
+            }
+        }
+    };
+    let unix_server = async {
+        if !socket_mode.unix() {
+            return Ok(());
+        }
+        unix::serve_from_config(&config, unix_app, unix_shutdown).await
+    };
+    // Both listeners stop on the same signal and drain independently.
+    tokio::try_join!(tcp_server, unix_server).unwrap();
+    shutdown.log_summary();
+    info!("API1 shut down");
+}
+
+/// The router of every API1 route, with its middleware.
+pub fn app(state: AppState) -> Router {
+    let cors = state.cors.layer();
+
+    let metrics_layer = MetricsLayer::new(state.metrics.clone());
+    let rate_limit_layer = rate_limit::RateLimitLayer::new(
+        state.rate_limiter.clone(),
+        state.api_keys.clone(),
+        state.metrics.clone(),
+    );
+    let audit_layer = AuditLayer::new(state.audit.clone());
+    let load_shed_layer = load_shed::LoadShedLayer::new(state.load_shedder.clone());
+    let tenant_layer = TenantLayer::new(state.tenants.clone());
+
+    let v1 = api_routes(get(get_time).post(time_request::post_time));
+    let v2 = api_routes(get(v2::get_time).post(v2::post_time));
+    let authenticated = Router::new()
+        .route("/", get(root))
+        .route(
+            payload_log::ADMIN_PATH,
+            get(admin::get_payload_logging).put(admin::put_payload_logging),
+        )
+        .route(
+            logging::ADMIN_PATH,
+            get(admin::get_log_level).put(admin::put_log_level),
+        )
+        .route(audit::ADMIN_PATH, get(admin::get_audit))
+        .route(
+            flags::ADMIN_PATH,
+            get(admin::get_flags).put(admin::put_flags),
+        )
+        .route("/debug/skew", get(skew::get_debug_skew))
+        .merge(
+            v1.clone()
+                .route_layer(middleware::from_fn(versioning::deprecated)),
+        )
+        .nest(versioning::V1_PREFIX, v1)
+        .nest(versioning::V2_PREFIX, v2)
+        .route_layer(middleware::from_fn_with_state(
+            state.idempotency.clone(),
+            idempotency::replay,
+        ))
+        .route_layer(auth::ApiKeyLayer::new(state.api_keys.clone()));
+
+    // Probes, scrapers, readers of the docs and the dashboard page need no
+    // key; the dashboard's own calls do.
+    Router::new()
+        .route("/health", get(health_check))
+        .route(health::LIVE_PATH, get(readiness::get_live))
+        .route(health::READY_PATH, get(readiness::get_ready))
+        .route(metrics::METRICS_PATH, get(get_metrics))
+        .merge(common::openapi::routes(openapi::document().into_json()))
+        .route(dashboard::PATH, get(dashboard::get_dashboard))
+        .merge(authenticated)
+        .route_layer(middleware::from_fn_with_state(
+            state.flags.clone(),
+            flags::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.payload_log.clone(),
+            payload_log::log,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.limits.clone(),
+            limits::enforce,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            sla::record_latency,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.shutdown.clone(),
+            shutdown::track_in_flight,
+        ))
+        .with_state(state)
+        .layer(middleware::from_fn(logging::access_log))
+        .layer(middleware::from_fn(trace_context::trace_context_middleware))
+        .layer(load_shed_layer)
+        .layer(metrics_layer)
+        .layer(rate_limit_layer)
+        .layer(
+            ServiceBuilder::new()
+                // Assign an X-Request-ID when the client sent none, and echo it back.
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(tenant_layer)
+                .layer(audit_layer)
+                .layer(TraceLayer::new_for_http())
+                .layer(CompressionLayer::new())
+                .layer(cors),
+        )
+}
+
+/// The routes served under each API version, which differ only in `/time`.
+fn api_routes(time: MethodRouter<AppState>) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/time",
+            time.route_layer(middleware::from_fn(etag::conditional)),
+        )
+        .route("/time/batch", post(batch::post_time_batch))
+        .route("/time/diff", get(diff::get_time_diff))
+        .route("/time/convert", get(convert::get_time_convert))
+        .route("/time/stream", get(stream::get_time_stream))
+        .route("/time/ws", get(ws::get_time_ws))
+        .route(
+            "/timezones",
+            get(timezones::get_timezones).route_layer(middleware::from_fn(etag::conditional)),
+        )
+        .route(
+            "/time/clock-synchronisation",
+            get(clock_sync::get_clock_synchronisation),
+        )
+        .route(
+            "/time/api-response-time-sla",
+            get(sla::get_api_response_time_sla),
+        )
+        .route("/cron/next", post(cron::post_cron_next))
+        .route("/timer", post(timers::post_timer))
+        .route(
+            "/timer/:id",
+            get(timers::get_timer).delete(timers::delete_timer),
+        )
+}
+
+async fn root() -> &'static str {
+    "API1 - Time Service Gateway"
+}
+
+/// Deadline for the API2 probe made by every `/health` call.
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Probes one API2 instance's `/health`, returning its status and any error.
+async fn probe_api2(
+    api2: &Api2Client,
+    probe: reqwest::RequestBuilder,
+) -> (&'static str, Option<String>) {
+    let probe = api2.send(probe.timeout(HEALTH_PROBE_TIMEOUT)).await;
+    match probe {
+        Ok(response) if response.status().is_success() => ("healthy", None),
+        Ok(response) => (
+            "unhealthy",
+            Some(format!("API2 returned status: {}", response.status())),
+        ),
+        Err(e) => ("unreachable", Some(e.to_string())),
+    }
+}
+
+/// Reports healthy when a live probe of at least one API2 instance's
+/// `/health` succeeds, since calls fail over to the healthy ones.
+async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.shutdown.is_requested() {
+        return state.shutdown.health_response("api1");
+    }
+    let urls = state.backends.urls();
+    let probes = join_all(
+        urls.iter()
+            .map(|url| probe_api2(&state.api2, state.api2.health(url))),
+    )
+    .await;
+    let instances: Vec<_> = urls
+        .iter()
+        .zip(&probes)
+        .enumerate()
+        .map(|(backend, (url, (status, _)))| {
+            let health = state.backends.health(backend, now_ms());
+            serde_json::json!({
+                "url": url,
+                "status": status,
+                "ejected": health.ejected,
+                "consecutive_failures": health.consecutive_failures
+            })
+        })
+        .collect();
+    let failure = match probes.iter().find(|(_, error)| error.is_none()) {
+        Some(_) => None,
+        None => probes
+            .into_iter()
+            .next()
+            .map(|(status, error)| (status, error.unwrap_or_default())),
+    };
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let cache_size = state.time_cache.len();
+    let cache_capacity = state.time_cache.capacity();
+    let circuit_breaker = state.breaker.state(now_ms());
+    let feature_flags = state.flags.states();
+    match failure {
+        None => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "status": "healthy",
+                "service": "api1",
+                "timestamp": timestamp,
+                "dependencies": { "api2": "healthy", "api2_instances": instances },
+                "cache_size": cache_size,
+                "cache_capacity": cache_capacity,
+                "circuit_breaker": circuit_breaker,
+                "feature_flags": feature_flags
+            })),
+        ),
+        Some((api2_status, error)) => {
+            warn!(api2 = api2_status, error = %error, "API2 health probe failed");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "status": "degraded",
+                    "service": "api1",
+                    "timestamp": timestamp,
+                    "dependencies": {
+                        "api2": api2_status,
+                        "api2_instances": instances,
+                        "error": error
+                    },
+                    "cache_size": cache_size,
+                    "cache_capacity": cache_capacity,
+                    "circuit_breaker": circuit_breaker,
+                    "feature_flags": feature_flags
+                })),
+            )
+        }
+    }
+}
+
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        state.metrics.render(),
+    )
+}
+
+/// Error half of every handler result: extra response headers such as
+/// `Retry-After`, and the error sent as `application/problem+json`.
+type ApiError = (HeaderMap, ErrorResponse);
+
+fn error_response(code: ErrorCode, error: impl Into<String>, request_id: &str) -> ApiError {
+    (
+        HeaderMap::new(),
+        ErrorResponse::new(code, error, request_id),
+    )
+}
+
+/// An error whose status was decided elsewhere, such as by an extractor.
+fn error_for_status(status: StatusCode, error: impl Into<String>, request_id: &str) -> ApiError {
+    (
+        HeaderMap::new(),
+        ErrorResponse::with_status(status, ErrorCode::from_status(status), error, request_id),
+    )
+}
+
+/// `503` for a call refused by the open circuit breaker, with `Retry-After`
+/// set to the seconds left until it lets a probe through.
+fn circuit_open(state: &AppState, request_id: &str) -> ApiError {
+    state
+        .metrics
+        .increment(&UPSTREAM_ERRORS_TOTAL, &[("kind", "circuit_open")]);
+    warn!(request_id = %request_id, "Circuit open; not calling API2");
+    let retry_after = state.breaker.retry_after(now_ms());
+    let (mut headers, body) =
+        error_response(ErrorCode::UpstreamUnavailable, "circuit open", request_id);
+    let seconds = retry_after.as_millis().div_ceil(1000).max(1) as u64;
+    headers.insert(header::RETRY_AFTER, seconds.into());
+    (headers, body)
+}
+
+/// Correlation ID from the `X-Request-ID` header, or a fresh one.
+fn request_id_from(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Request ID, trace context and tenant to send with calls made for this
+/// request.
+fn call_context<'a>(
+    request_id: &'a str,
+    trace: &'a TraceContext,
+    headers: &'a HeaderMap,
+) -> CallContext<'a> {
+    let tenant = headers
+        .get(TENANT_HEADER)
+        .and_then(|value| value.to_str().ok());
+    CallContext {
+        request_id,
+        trace,
+        tracestate: headers
+            .get(trace_context::TRACESTATE_HEADER)
+            .and_then(|value| value.to_str().ok()),
+        tenant,
+        // The tenant layer only resolves a tenant with keys from one of
+        // them, so API2 needs the key to resolve it again.
+        tenant_key: tenant.and(
+            headers
+                .get(API_KEY_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        ),
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Sends a request to API2 with retries, mapping each failure mode to the
+/// status API1 reports and recording upstream metrics. Fails fast with `503`
+/// while the circuit breaker is open.
+///
+/// `build` receives the base URL of the API2 instance to call. On success
+/// the instance that answered and the attempts made are returned with the
+/// response. `instance` pins the call, retries included, to one instance.
+async fn send_to_api2(
+    state: &AppState,
+    request_id: &str,
+    instance: Option<usize>,
+    build: impl Fn(&str) -> reqwest::RequestBuilder,
+) -> Result<(reqwest::Response, Answered), ApiError> {
+    let upstream_error = |kind: &str| {
+        state
+            .metrics
+            .increment(&UPSTREAM_ERRORS_TOTAL, &[("kind", kind)]);
+    };
+
+    let Some(permit) = state.breaker.try_acquire(now_ms()) else {
+        return Err(circuit_open(state, request_id));
+    };
+
+    let started = Instant::now();
+    let backend = AtomicUsize::new(0);
+    let hedge_delay = state
+        .hedging
+        .as_ref()
+        .and_then(|hedging| hedging.delay())
+        .filter(|_| {
+            let probe = build(&state.backends.urls()[0]).build();
+            probe.is_ok_and(|request| request.method().is_safe())
+        });
+    let (result, attempts) = state
+        .retry
+        .send(request_id, || {
+            send_hedged(state, request_id, instance, &build, &backend, hedge_delay)
+        })
+        .await;
+    let answered = Answered {
+        backend: backend.into_inner(),
+        attempts,
+    };
+    match &result {
+        Ok(response) if !response.status().is_server_error() => permit.success(),
+        _ => permit.failure(now_ms()),
+    }
+    state.metrics.observe(
+        &UPSTREAM_REQUEST_DURATION_SECONDS,
+        &[],
+        started.elapsed().as_secs_f64(),
+    );
+
+    match result {
+        Ok(response) if response.status().is_success() => Ok((response, answered)),
+        Ok(response) => {
+            let status = response.status();
+            // API2's own validation errors are the caller's to fix: relay them.
+            if status.is_client_error() {
+                if let Ok(mut body) = response.json::<ErrorResponse>().await {
+                    body.status = status.as_u16();
+                    return Err((HeaderMap::new(), body));
+                }
+            }
+
+            upstream_error("status");
+            error!(
+                request_id = %request_id,
+                status = %status,
+                attempts,
+                "API2 returned error status"
+            );
+
+            Err(error_response(
+                ErrorCode::UpstreamBadResponse,
+                format!("API2 returned status: {status}"),
+                request_id,
+            ))
+        }
+        Err(e) if e.is_timeout() => {
+            upstream_error("timeout");
+            let timeout_ms = state.api2_timeout.as_millis();
+            error!(
+                request_id = %request_id,
+                timeout_ms = timeout_ms as u64,
+                attempts,
+                "API2 request timed out"
+            );
+
+            Err(error_response(
+                ErrorCode::UpstreamTimeout,
+                format!("Upstream timeout after {timeout_ms}ms"),
+                request_id,
+            ))
+        }
+        Err(e) => {
+            upstream_error("connect");
+            error!(
+                request_id = %request_id,
+                error = %e,
+                attempts,
+                "Failed to connect to API2"
+            );
+
+            Err(error_response(
+                ErrorCode::UpstreamUnavailable,
+                "Failed to connect to API2",
+                request_id,
+            ))
+        }
+    }
+}
+
+/// [`send_with_failover`], sending a second call if the first is still
+/// unanswered after `hedge_delay` and keeping whichever answers first.
+async fn send_hedged(
+    state: &AppState,
+    request_id: &str,
+    instance: Option<usize>,
+    build: &impl Fn(&str) -> reqwest::RequestBuilder,
+    used: &AtomicUsize,
+    hedge_delay: Option<Duration>,
+) -> Result<reqwest::Response, SendError> {
+    let Some(delay) = hedge_delay else {
+        return send_with_failover(state, request_id, instance, build, used).await;
+    };
+    let used_by = [AtomicUsize::new(0), AtomicUsize::new(0)];
+    let (result, call) = hedge::race(delay, |call| {
+        if call == hedge::Call::Hedge {
+            info!(
+                request_id = %request_id,
+                delay_ms = delay.as_millis() as u64,
+                "API2 slow to answer; hedging"
+            );
+        }
+        send_with_failover(state, request_id, instance, build, &used_by[call as usize])
+    })
+    .await;
+    if let Some(call) = call {
+        state
+            .metrics
+            .increment(&hedge::UPSTREAM_HEDGES_TOTAL, &[("winner", call.label())]);
+    }
+    let call = call.unwrap_or(hedge::Call::Primary);
+    used.store(
+        used_by[call as usize].load(Ordering::Relaxed),
+        Ordering::Relaxed,
+    );
+    result
+}
+
+/// Sends to each API2 instance in balancer order until one is reachable,
+/// recording the index of the last one tried in `used`. Only connection
+/// failures move on to the next instance, but network errors and 5xx
+/// responses all count towards ejecting an instance. A pinned `instance` is
+/// the only one tried, even while ejected.
+async fn send_with_failover(
+    state: &AppState,
+    request_id: &str,
+    instance: Option<usize>,
+    build: &impl Fn(&str) -> reqwest::RequestBuilder,
+    used: &AtomicUsize,
+) -> Result<reqwest::Response, SendError> {
+    let order: Vec<usize> = match instance {
+        Some(instance) => vec![instance],
+        None => state.backends.order(now_ms()).collect(),
+    };
+    let mut order = order.into_iter().peekable();
+    loop {
+        let backend = order.next().expect("at least one API2 instance");
+        let api2_url = &state.backends.urls()[backend];
+        info!(
+            request_id = %request_id,
+            api2_url = %api2_url,
+            "Forwarding request to API2"
+        );
+        used.store(backend, Ordering::Relaxed);
+        let started = Instant::now();
+        let result = state.api2.send(build(api2_url)).await;
+        let success = matches!(&result, Ok(response) if !response.status().is_server_error());
+        state.backends.record(backend, success, now_ms());
+        if let Some(hedging) = state.hedging.as_ref().filter(|_| success) {
+            hedging.record(started.elapsed());
+        }
+        match result {
+            Err(e) if e.is_connect() && order.peek().is_some() => warn!(
+                request_id = %request_id,
+                api2_url = %api2_url,
+                error = %e,
+                "API2 instance unreachable; failing over"
+            ),
+            result => return result,
+        }
+    }
+}
+
+/// Which API2 instance answered a call, and after how many attempts.
+#[derive(Debug, Clone, Copy)]
+struct Answered {
+    backend: usize,
+    attempts: u32,
+}
+
+/// `source` label naming the API2 instance that served a response.
+fn api2_source(backend: usize) -> String {
+    format!("api1->api2[{backend}]")
+}
+
+/// [`send_to_api2`] bounded by [`AppState::upstream_timeout`], decoding the
+/// JSON body.
+async fn forward_to_api2<T: DeserializeOwned>(
+    state: &AppState,
+    request_id: &str,
+    build: impl Fn(&str) -> Api2Request<T>,
+) -> Result<(T, Answered), ApiError> {
+    forward(state, request_id, None, build).await
+}
+
+/// [`forward_to_api2`] to the API2 instance at index `instance` only, for
+/// state held by that instance.
+async fn forward_to_instance<T: DeserializeOwned>(
+    state: &AppState,
+    request_id: &str,
+    instance: usize,
+    build: impl Fn(&str) -> Api2Request<T>,
+) -> Result<(T, Answered), ApiError> {
+    forward(state, request_id, Some(instance), build).await
+}
+
+async fn forward<T: DeserializeOwned>(
+    state: &AppState,
+    request_id: &str,
+    instance: Option<usize>,
+    build: impl Fn(&str) -> Api2Request<T>,
+) -> Result<(T, Answered), ApiError> {
+    let (response, answered) = send_to_api2(state, request_id, instance, |api2_url| {
+        build(api2_url)
+            .timeout(state.upstream_timeout())
+            .into_builder()
+    })
+    .await?;
+    let body = response.json::<T>().await.map_err(|e| {
+        state
+            .metrics
+            .increment(&UPSTREAM_ERRORS_TOTAL, &[("kind", "parse")]);
+        error!(
+            request_id = %request_id,
+            error = %e,
+            "Failed to parse response from API2"
+        );
+
+        error_response(
+            ErrorCode::Internal,
+            "Failed to parse response from API2",
+            request_id,
+        )
+    })?;
+    Ok((body, answered))
+}
+
+async fn get_time(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimeQuery>,
+) -> Result<(HeaderMap, Json<TimeResponse>), ApiError> {
+    let request_id = request_id_from(&headers);
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Received time request"
+    );
+
+    // Validate here so a bad format is a 400, not a 502 relayed from API2.
+    let format = TimestampFormat::from_param(params.format.as_deref())
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+    let bypass = match params.cache.as_deref() {
+        None => false,
+        Some("bypass") => true,
+        Some(other) => {
+            return Err(error_response(
+                ErrorCode::InvalidRequest,
+                format!("Invalid cache mode: {other} (expected bypass)"),
+                &request_id,
+            ))
+        }
+    };
+
+    let mut cache_key = timezone.clone();
+    for (name, value) in [("format", &params.format), ("locale", &params.locale)] {
+        if let Some(value) = value {
+            let separator = if cache_key.contains('?') { '&' } else { '?' };
+            cache_key.push_str(&format!("{separator}{name}={value}"));
+        }
+    }
+    let cached = if bypass {
+        None
+    } else {
+        state.time_cache.get(&cache_key, Instant::now())
+    };
+    let lookup = match (&cached, bypass) {
+        (_, true) => "bypass",
+        (Some(_), false) => "hit",
+        (None, false) => "miss",
+    };
+    state
+        .metrics
+        .increment(&TIME_CACHE_LOOKUPS_TOTAL, &[("result", lookup)]);
+    if let Some((cached, age)) = cached {
+        info!(request_id = %request_id, timezone = %timezone, "Serving time from cache");
+        let max_age = state.time_cache.ttl().as_secs();
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_str(&format!("max-age={max_age}")).expect("valid header value"),
+        );
+        response_headers.insert(header::AGE, age.as_secs().into());
+        return Ok((
+            response_headers,
+            Json(TimeResponse {
+                request_id,
+                source: "api1->cache".to_string(),
+                ..cached
+            }),
+        ));
+    }
+
+    let answer = match &state.grpc {
+        Some(upstream) => {
+            let query = common::TimeQuery {
+                timezone: Some(timezone.clone()),
+                request_id: None,
+                format: params.format.clone(),
+                locale: params.locale.clone(),
+            };
+            grpc_upstream::call(
+                &state,
+                upstream,
+                &request_id,
+                common::grpc::GET_TIME_PATH,
+                &query,
+            )
+            .await
+            .map(|time_data| (time_data, grpc_upstream::GRPC_SOURCE.to_string(), 1))
+        }
+        None => {
+            let context = call_context(&request_id, &trace, &headers);
+            forward_to_api2(&state, &request_id, |api2_url| {
+                state.api2.time(
+                    api2_url,
+                    &context,
+                    &timezone,
+                    params.format.as_deref(),
+                    params.locale.as_deref(),
+                )
+            })
+            .await
+            .map(|(time_data, answered)| {
+                (time_data, api2_source(answered.backend), answered.attempts)
+            })
+        }
+    };
+    let (time_data, source, attempts) = match answer {
+        Ok(answer) => answer,
+        Err(error) if state.fallback_local_time && error.1.status_code().is_server_error() => {
+            warn!(
+                request_id = %request_id,
+                status = %error.1.status_code(),
+                "API2 unavailable; answering from the local clock"
+            );
+            let time_data = fallback::local_time(&timezone, &format, &request_id)
+                .map_err(|e| error_response(ErrorCode::InvalidTimezone, e, &request_id))?;
+            state.metrics.increment(&TIME_FALLBACK_RESPONSES_TOTAL, &[]);
+            // Not cached, so API2's answer is used again as soon as it is back.
+            return Ok((HeaderMap::new(), Json(time_data)));
+        }
+        Err(error) => return Err(error),
+    };
+
+    info!(
+        request_id = %request_id,
+        timestamp = %time_data.timestamp,
+        attempts,
+        "Successfully received response from API2"
+    );
+    state
+        .time_cache
+        .insert(cache_key, time_data.clone(), Instant::now());
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(UPSTREAM_ATTEMPTS_HEADER, attempts.into());
+    Ok((
+        response_headers,
+        Json(TimeResponse {
+            request_id,
+            source,
+            ..time_data
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    /// Serves `router` on an ephemeral local port and returns its base URL.
+    async fn serve(router: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let service = router.into_make_service_with_connect_info::<SocketAddr>();
+            axum::serve(listener, service).await.unwrap()
+        });
+        format!("http://{addr}")
+    }
+
+    async fn mock_api2_time(Query(params): Query<common::TimeQuery>) -> Json<TimeResponse> {
+        Json(TimeResponse {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            timezone: params.timezone.unwrap_or_default(),
+            request_id: params.request_id.unwrap_or_default(),
+            source: "api2-service".to_string(),
+            utc_offset_seconds: Some(0),
+            utc_offset_label: Some("+00:00".to_string()),
+            display: None,
+            format: None,
+            degraded: None,
+            clock_offset_ms: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn handles_500_concurrent_requests() {
+        let api2_url = serve(Router::new().route("/time", get(mock_api2_time))).await;
+        let state = AppState {
+            rate_limiter: Arc::new(rate_limit::RateLimiter::new(1000.0, 1000.0)),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+
+        let requests: Vec<_> = (0..500)
+            .map(|_| {
+                let client = client.clone();
+                let url = format!("{api1_url}/time?timezone=UTC");
+                tokio::spawn(async move { client.get(url).send().await.map(|r| r.status()) })
+            })
+            .collect();
+
+        for request in requests {
+            let status = request.await.unwrap().unwrap();
+            assert_eq!(status, reqwest::StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn measures_clock_skew_to_api2() {
+        // An API2 whose clock runs two seconds fast.
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(|| async {
+                let ahead = chrono::Utc::now() + chrono::Duration::seconds(2);
+                Json(serde_json::json!({
+                    "timestamp": ahead.to_rfc3339(),
+                    "timezone": "UTC",
+                    "request_id": "",
+                    "source": "api2-service",
+                }))
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let report: serde_json::Value = reqwest::get(format!("{api1_url}/debug/skew?samples=3"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(report["samples"], 3);
+        assert_eq!(report["measurements"].as_array().unwrap().len(), 3);
+        assert_eq!(report["measurements"][0]["source"], "api1->api2[0]");
+        let offset = report["best_offset_ms"].as_f64().unwrap();
+        assert!((1_900.0..2_100.0).contains(&offset), "{report}");
+        let round_trip = &report["round_trip_ms"];
+        assert!(round_trip["min"].as_f64() <= round_trip["max"].as_f64());
+
+        let response = reqwest::get(format!("{api1_url}/debug/skew?samples=0"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn feature_flags_hide_routes_until_switched_on() {
+        let api2_url = serve(Router::new().route("/time", get(mock_api2_time))).await;
+        let state = AppState {
+            flags: Arc::new(FeatureFlags::new(&[("time_stream", flags::FlagState::Off)])),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+        let status = |path: &str| {
+            let request = client.get(format!("{api1_url}{path}")).send();
+            async move { request.await.unwrap().status() }
+        };
+
+        assert_eq!(status("/time/stream").await, reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(
+            status("/v2/time/stream").await,
+            reqwest::StatusCode::NOT_FOUND
+        );
+        assert_eq!(status("/time").await, reqwest::StatusCode::OK);
+        let health: serde_json::Value = client
+            .get(format!("{api1_url}/health"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["feature_flags"]["time_stream"], "off");
+
+        let states: serde_json::Value = client
+            .put(format!("{api1_url}/admin/flags"))
+            .json(&serde_json::json!({ "time_stream": "unavailable" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(states["time_stream"], "unavailable");
+        assert_eq!(
+            status("/time/stream").await,
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_api2_over_grpc_when_configured() {
+        let grpc_api2 = common::grpc::Server::default().unary(
+            common::grpc::GET_TIME_PATH,
+            |metadata, query: common::TimeQuery| async move {
+                match query.timezone.as_deref() {
+                    Some("Asia/Tokyo") => Ok(TimeResponse {
+                        timestamp: "2025-01-01T09:00:00+09:00".to_string(),
+                        timezone: "Asia/Tokyo".to_string(),
+                        request_id: metadata.request_id.unwrap_or_default(),
+                        source: "api2-service".to_string(),
+                        utc_offset_seconds: Some(9 * 3600),
+                        utc_offset_label: Some("+09:00".to_string()),
+                        display: query.locale,
+                        format: None,
+                        degraded: None,
+                        clock_offset_ms: None,
+                    }),
+                    _ => Err(common::grpc::Status::new(
+                        common::grpc::Code::InvalidArgument,
+                        "Invalid timezone: Foo/Bar",
+                    )),
+                }
+            },
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(grpc_api2.serve(listener, std::future::pending()));
+
+        // The HTTP backend is unreachable: every answer must come over gRPC.
+        let state = AppState {
+            grpc: Some(grpc_upstream::GrpcUpstream::new(&grpc_url)),
+            ..AppState::new("http://127.0.0.1:1")
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("{api1_url}/time?timezone=Asia/Tokyo&locale=th"))
+            .header(REQUEST_ID_HEADER, "req-grpc")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.source, grpc_upstream::GRPC_SOURCE);
+        assert_eq!(body.request_id, "req-grpc");
+        assert_eq!(body.display.as_deref(), Some("th"));
+
+        let invalid = client
+            .get(format!("{api1_url}/time?timezone=Foo/Bar"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: ErrorResponse = invalid.json().await.unwrap();
+        assert_eq!(body.error, "Invalid timezone: Foo/Bar");
+    }
+
+    /// Mock API2 that answers with `failure` for the first `failures` calls.
+    async fn flaky_api2(failures: usize, failure: StatusCode) -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let router = Router::new().route(
+            "/time",
+            get(move |query: Query<common::TimeQuery>| {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                        Err(failure)
+                    } else {
+                        Ok(mock_api2_time(query).await)
+                    }
+                }
+            }),
+        );
+        (serve(router).await, calls)
+    }
+
+    #[tokio::test]
+    async fn hedges_calls_to_a_stalled_instance() {
+        let stalled = serve(Router::new().route(
+            "/time",
+            get(|query: Query<common::TimeQuery>| async move {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                mock_api2_time(query).await
+            }),
+        ))
+        .await;
+        let healthy = serve(Router::new().route("/time", get(mock_api2_time))).await;
+        let state = AppState::from_config(&Config {
+            api2_urls: Some(vec![stalled, healthy]),
+            hedge_percentile: Some(95.0),
+            hedge_min_delay_ms: Some(50),
+            ..Config::default()
+        });
+        for _ in 0..hedge::MIN_SAMPLES {
+            state
+                .hedging
+                .as_ref()
+                .unwrap()
+                .record(Duration::from_millis(1));
+        }
+        let api1_url = serve(app(state)).await;
+
+        let started = Instant::now();
+        let response = reqwest::get(format!("{api1_url}/time?cache=bypass"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.source, "api1->api2[1]");
+
+        let metrics = reqwest::get(format!("{api1_url}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(metrics.contains("upstream_hedges_total{winner=\"hedge\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn ejects_an_instance_that_keeps_failing() {
+        let (bad_url, bad_calls) = flaky_api2(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await;
+        let (good_url, good_calls) = flaky_api2(0, StatusCode::INTERNAL_SERVER_ERROR).await;
+        let config = Config {
+            api2_urls: Some(vec![bad_url, good_url]),
+            ..Config::default()
+        };
+        let api1_url = serve(app(AppState::from_config(&config))).await;
+
+        for _ in 0..10 {
+            let response = reqwest::get(format!("{api1_url}/time?cache=bypass"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            let body: TimeResponse = response.json().await.unwrap();
+            assert_eq!(body.source, "api1->api2[1]");
+        }
+        // Three failures in a row eject the first instance; each was retried
+        // on the second.
+        assert_eq!(bad_calls.load(Ordering::SeqCst), 3);
+        assert_eq!(good_calls.load(Ordering::SeqCst), 10);
+
+        let health: serde_json::Value = reqwest::get(format!("{api1_url}/health"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let instances = &health["dependencies"]["api2_instances"];
+        assert_eq!(instances[0]["ejected"], true);
+        assert_eq!(instances[1]["ejected"], false);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let (api2_url, calls) = flaky_api2(2, StatusCode::SERVICE_UNAVAILABLE).await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.headers()[UPSTREAM_ATTEMPTS_HEADER], "3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (api2_url, calls) = flaky_api2(1, StatusCode::BAD_REQUEST).await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (api2_url, calls) = flaky_api2(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await;
+        let state = AppState {
+            retry: retry::RetryPolicy {
+                max_retries: 1,
+                ..retry::RetryPolicy::default()
+            },
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn stops_retrying_at_the_deadline() {
+        let (api2_url, calls) = flaky_api2(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await;
+        let config = Config {
+            api2_url: Some(api2_url),
+            max_retries: Some(10),
+            retry_base_delay_ms: Some(100),
+            retry_deadline_ms: Some(250),
+            ..Config::default()
+        };
+        let api1_url = serve(app(AppState::from_config(&config))).await;
+
+        // Retries start after ~100 ms; the next would wait until ~300 ms.
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_upstream() {
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                StatusCode::OK
+            }),
+        ))
+        .await;
+        let config = Config {
+            api2_url: Some(api2_url),
+            timeout_ms: Some(200),
+            max_retries: Some(0),
+            ..Config::default()
+        };
+        let api1_url = serve(app(AppState::from_config(&config))).await;
+
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "Upstream timeout after 200ms");
+    }
+
+    #[tokio::test]
+    async fn request_timeout_bounds_the_upstream_call() {
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                StatusCode::OK
+            }),
+        ))
+        .await;
+        let config = Config {
+            api2_url: Some(api2_url),
+            max_retries: Some(3),
+            route_timeouts: Some(vec!["/time=200".to_string()]),
+            ..Config::default()
+        };
+        let api1_url = serve(app(AppState::from_config(&config))).await;
+
+        // API2_TIMEOUT_MS is 5 s by default; the route allows 200 ms in all.
+        let started = Instant::now();
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert!(body.error.ends_with("after 200ms"), "{}", body.error);
+    }
+
+    #[tokio::test]
+    async fn reaches_api2_over_a_unix_socket() {
+        let directory = std::env::temp_dir().join(format!("api1-{}", Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let path = directory.join("api2.sock");
+        let listener = unix::bind(&path, 0o600).unwrap();
+        let api2 = Router::new()
+            .route("/time", get(mock_api2_time))
+            .route("/health", get(|| async { "ok" }));
+        tokio::spawn(unix::serve(listener, api2, std::future::pending()));
+
+        let api1_url = serve(app(AppState::from_config(&Config {
+            api2_url: Some("http://api2".to_string()),
+            api2_unix_socket: Some(path.to_string_lossy().into_owned()),
+            ..Config::default()
+        })))
+        .await;
+        let response = reqwest::get(format!("{api1_url}/time?timezone=Asia/Tokyo"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.timezone, "Asia/Tokyo");
+        assert_eq!(body.source, "api1->api2[0]");
+
+        let health: serde_json::Value = reqwest::get(format!("{api1_url}/health"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["dependencies"]["api2"], "healthy", "{health}");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn exposes_request_and_upstream_metrics() {
+        let api2_url = serve(Router::new().route("/time", get(mock_api2_time))).await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        reqwest::get(format!("{api1_url}/metrics")).await.unwrap();
+        let body = reqwest::get(format!("{api1_url}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+
+        assert!(body.contains("http_requests_total{route=\"/time\",status=\"2xx\"} 1\n"));
+        assert!(body.contains("upstream_request_duration_seconds_count 1\n"));
+        assert!(!body.contains("route=\"/metrics\""));
+    }
+
+    #[tokio::test]
+    async fn request_id_round_trips_through_api2() {
+        let seen = Arc::new(std::sync::Mutex::new(None));
+        let recorder = seen.clone();
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(move |headers: HeaderMap, query: Query<common::TimeQuery>| {
+                let id = headers
+                    .get(REQUEST_ID_HEADER)
+                    .map(|value| value.to_str().unwrap().to_string());
+                *recorder.lock().unwrap() = id;
+                mock_api2_time(query)
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("{api1_url}/time"))
+            .header(REQUEST_ID_HEADER, "edge-1234")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "edge-1234");
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.request_id, "edge-1234");
+        assert_eq!(body.source, "api1->api2[0]");
+        assert_eq!(body.utc_offset_seconds, Some(0));
+        assert_eq!(body.utc_offset_label.as_deref(), Some("+00:00"));
+        assert_eq!(seen.lock().unwrap().as_deref(), Some("edge-1234"));
+
+        // Without a header, api1 generates one and uses it everywhere. A new
+        // timezone keeps the response from being served by the cache.
+        let response = client
+            .get(format!("{api1_url}/time?timezone=Asia/Tokyo"))
+            .send()
+            .await
+            .unwrap();
+        let generated = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.request_id, generated);
+        assert_eq!(seen.lock().unwrap().as_deref(), Some(generated.as_str()));
+    }
+
+    #[tokio::test]
+    async fn readiness_probes_api2_once_per_cache_period() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let api2_url = serve(Router::new().route(
+            "/health/ready",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                "ok"
+            }),
+        ))
+        .await;
+        let state = AppState {
+            readiness: Arc::new(readiness::ReadinessCache::new(Duration::from_secs(60))),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+
+        let probes: Vec<_> = (0..5)
+            .map(|_| reqwest::get(format!("{api1_url}/health/ready")))
+            .collect();
+        for probe in join_all(probes).await {
+            let probe = probe.unwrap();
+            assert_eq!(probe.status(), reqwest::StatusCode::OK);
+            let body: serde_json::Value = probe.json().await.unwrap();
+            assert_eq!(body["checks"]["api2"]["status"], "pass");
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let live = reqwest::get(format!("{api1_url}/health/live"))
+            .await
+            .unwrap();
+        assert_eq!(live.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn not_ready_without_a_reachable_api2() {
+        let api1_url = serve(app(AppState::new("http://127.0.0.1:1"))).await;
+        let response = reqwest::get(format!("{api1_url}/health/ready"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["api2"]["status"], "fail");
+        assert_eq!(
+            body["checks"]["api2"]["instances"][0]["status"],
+            "unreachable"
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_local_time_only_when_enabled() {
+        let get = |api1_url: String| async move {
+            reqwest::get(format!("{api1_url}/time?timezone=Asia/Tokyo&format=unix"))
+                .await
+                .unwrap()
+        };
+        let api1_url = serve(app(AppState::new("http://127.0.0.1:1"))).await;
+        let response = get(api1_url).await;
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        let state = AppState {
+            fallback_local_time: true,
+            retry: retry::RetryPolicy {
+                max_retries: 0,
+                ..retry::RetryPolicy::default()
+            },
+            ..AppState::new("http://127.0.0.1:1")
+        };
+        let api1_url = serve(app(state)).await;
+        let response = get(api1_url.clone()).await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["source"], "api1-fallback");
+        assert_eq!(body["degraded"], true);
+        assert_eq!(body["timezone"], "Asia/Tokyo");
+        assert_eq!(body["utc_offset_seconds"], 9 * 3600);
+        assert_eq!(body["format"], "unix");
+
+        // Caller errors are still caller errors.
+        let response = reqwest::get(format!("{api1_url}/time?timezone=Mars/Olympus"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let metrics = reqwest::get(format!("{api1_url}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            metrics.contains("time_fallback_responses_total 1"),
+            "{metrics}"
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_repeated_requests_within_ttl() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(move |query: Query<common::TimeQuery>| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                mock_api2_time(query)
+            }),
+        ))
+        .await;
+        let state = AppState {
+            time_cache: Arc::new(cache::TimeCache::new(128, Duration::from_secs(60))),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+
+        let first: TimeResponse = reqwest::get(format!("{api1_url}/time?timezone=UTC"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let second = reqwest::get(format!("{api1_url}/time?timezone=UTC"))
+            .await
+            .unwrap();
+        assert_eq!(second.headers()["cache-control"], "max-age=60");
+        assert_eq!(second.headers()["age"], "0");
+        let second: TimeResponse = second.json().await.unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.source, "api1->api2[0]");
+        assert_eq!(second.source, "api1->cache");
+        assert_eq!(second.timestamp, first.timestamp);
+        assert_ne!(second.request_id, first.request_id);
+
+        // A bypass always reaches API2, and the invalid mode is rejected.
+        let bypassed = reqwest::get(format!("{api1_url}/time?timezone=UTC&cache=bypass"))
+            .await
+            .unwrap();
+        assert!(bypassed.headers().get("age").is_none());
+        let bypassed: TimeResponse = bypassed.json().await.unwrap();
+        assert_eq!(bypassed.source, "api1->api2[0]");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let invalid = reqwest::get(format!("{api1_url}/time?timezone=UTC&cache=never"))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let metrics = reqwest::get(format!("{api1_url}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        for result in ["hit", "miss", "bypass"] {
+            let line = format!("time_cache_lookups_total{{result=\"{result}\"}} 1");
+            assert!(metrics.contains(&line), "{metrics}");
+        }
+
+        let health: serde_json::Value = reqwest::get(format!("{api1_url}/health"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["cache_size"], 1);
+        assert_eq!(health["cache_capacity"], 128);
+    }
+
+    #[tokio::test]
+    async fn requires_api_key_except_for_health() {
+        let api2_url = serve(
+            Router::new()
+                .route("/time", get(mock_api2_time))
+                .route("/health", get(|| async { "ok" })),
+        )
+        .await;
+        let state = AppState {
+            api_keys: Arc::new(vec!["test-key-1".to_string(), "test-key-2".to_string()]),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+        let time = |key: Option<&'static str>| {
+            let request = client.get(format!("{api1_url}/time"));
+            match key {
+                Some(key) => request.header("x-api-key", key),
+                None => request,
+            }
+            .send()
+        };
+
+        let valid = time(Some("test-key-2")).await.unwrap();
+        assert_eq!(valid.status(), reqwest::StatusCode::OK);
+
+        let missing = time(None).await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: ErrorResponse = missing.json().await.unwrap();
+        assert_eq!(body.error, "Missing or invalid API key");
+
+        let wrong = time(Some("test-key-3")).await.unwrap();
+        assert_eq!(wrong.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let health = client
+            .get(format!("{api1_url}/health"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast_without_calling_api2() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let api2_url = serve(
+            Router::new()
+                .route(
+                    "/time",
+                    get(move || {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        async { StatusCode::INTERNAL_SERVER_ERROR }
+                    }),
+                )
+                .route("/health", get(|| async { "ok" })),
+        )
+        .await;
+        let state = AppState {
+            retry: retry::RetryPolicy {
+                max_retries: 0,
+                ..retry::RetryPolicy::default()
+            },
+            breaker: Arc::new(circuit_breaker::CircuitBreaker::new(2, 30, 60)),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+
+        for _ in 0..2 {
+            let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_GATEWAY);
+        }
+        let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "60");
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "circuit open");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let health: serde_json::Value = reqwest::get(format!("{api1_url}/health"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["circuit_breaker"], "open");
+    }
+
+    #[tokio::test]
+    async fn continues_incoming_trace_to_api2() {
+        let seen = Arc::new(std::sync::Mutex::new(None));
+        let recorder = seen.clone();
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(move |headers: HeaderMap, query: Query<common::TimeQuery>| {
+                let traceparent = headers
+                    .get(trace_context::TRACEPARENT_HEADER)
+                    .map(|value| value.to_str().unwrap().to_string());
+                *recorder.lock().unwrap() = traceparent;
+                mock_api2_time(query)
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        reqwest::Client::new()
+            .get(format!("{api1_url}/time"))
+            .header(trace_context::TRACEPARENT_HEADER, incoming)
+            .send()
+            .await
+            .unwrap();
+
+        let outbound = seen.lock().unwrap().clone().unwrap();
+        let forwarded = TraceContext::continue_from(&outbound).unwrap();
+        assert_eq!(forwarded.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(
+            outbound, incoming,
+            "api1 must send its own span as the parent"
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limits_rapid_requests_from_one_ip() {
+        let api2_url = serve(
+            Router::new()
+                .route("/time", get(mock_api2_time))
+                .route("/health", get(|| async { "ok" })),
+        )
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+
+        let mut limited = 0;
+        for _ in 0..50 {
+            let response = client.get(format!("{api1_url}/time")).send().await.unwrap();
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                limited += 1;
+                assert!(response.headers().contains_key("retry-after"));
+                assert!(response.headers().contains_key("retry-after-ms"));
+                let body: ErrorResponse = response.json().await.unwrap();
+                assert!(body.error.starts_with("Rate limit exceeded"));
+            }
+        }
+        // Burst of 20 at 10 req/s: about 30 of 50 back-to-back requests are
+        // rejected, less any tokens refilled while the loop runs.
+        assert!((20..=30).contains(&limited), "{limited} requests limited");
+
+        let metrics = client
+            .get(format!("{api1_url}/metrics"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            metrics.contains(&format!(
+                "rate_limited_requests_total{{route=\"default\",client=\"ip\"}} {limited}\n"
+            )),
+            "{metrics}"
+        );
+
+        let health = client
+            .get(format!("{api1_url}/health"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_each_api_key_separately() {
+        let api2_url = serve(Router::new().route("/time", get(mock_api2_time))).await;
+        let state = AppState {
+            api_keys: Arc::new(vec!["test-key-1".to_string(), "test-key-2".to_string()]),
+            rate_limiter: Arc::new(rate_limit::RateLimiter::new(0.01, 2.0)),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+        let time = |key: &'static str| {
+            client
+                .get(format!("{api1_url}/time"))
+                .header("x-api-key", key)
+                .send()
+        };
+        let header = |response: &reqwest::Response, name: &str| {
+            response.headers()[name].to_str().unwrap().to_string()
+        };
+
+        let first = time("test-key-1").await.unwrap();
+        assert_eq!(first.status(), reqwest::StatusCode::OK);
+        assert_eq!(header(&first, "x-ratelimit-limit"), "2");
+        assert_eq!(header(&first, "x-ratelimit-remaining"), "1");
+        assert_eq!(header(&first, "x-ratelimit-reset"), "100");
+        time("test-key-1").await.unwrap();
+
+        let limited = time("test-key-1").await.unwrap();
+        assert_eq!(limited.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(header(&limited, "x-ratelimit-remaining"), "0");
+        assert_eq!(header(&limited, "x-ratelimit-reset"), "200");
+
+        let other = time("test-key-2").await.unwrap();
+        assert_eq!(other.status(), reqwest::StatusCode::OK);
+        assert_eq!(header(&other, "x-ratelimit-remaining"), "1");
+    }
+
+    #[tokio::test]
+    async fn applies_tenant_defaults_quotas_and_endpoints() {
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(
+                |headers: HeaderMap, Query(params): Query<common::TimeQuery>| async move {
+                    let Json(mut time) = mock_api2_time(Query(params)).await;
+                    time.display = headers
+                        .get(TENANT_HEADER)
+                        .map(|tenant| tenant.to_str().unwrap().to_string());
+                    time.format = headers
+                        .get("x-api-key")
+                        .map(|key| key.to_str().unwrap().to_string());
+                    Json(time)
+                },
+            ),
+        ))
+        .await;
+        let config = Config {
+            api_keys: Some(vec!["operator-key".to_string()]),
+            tenants: Some(vec![
+                "team-a;keys=key-a;timezone=Asia/Bangkok;rate_limit=0.01:1;endpoints=/time"
+                    .to_string(),
+            ]),
+            ..Config::default()
+        };
+        let state = AppState {
+            api_keys: Arc::new(config.api_keys()),
+            ..AppState {
+                backends: Arc::new(balancer::Backends::new(
+                    vec![api2_url],
+                    balancer::Strategy::RoundRobin,
+                )),
+                ..AppState::from_config(&config)
+            }
+        };
+        let api1_url = serve(app(state)).await;
+        let client = reqwest::Client::new();
+        let get = |path: &str, key: &'static str| {
+            client
+                .get(format!("{api1_url}{path}"))
+                .header("x-api-key", key)
+                .send()
+        };
+
+        // Naming team-a with another key neither acts as team-a nor spends
+        // its one token.
+        let impersonated = client
+            .get(format!("{api1_url}/time"))
+            .header("x-api-key", "operator-key")
+            .header(TENANT_HEADER, "team-a")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(impersonated.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let time: TimeResponse = get("/v1/time", "key-a")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(time.timezone, "Asia/Bangkok");
+        assert_eq!(time.display.as_deref(), Some("team-a"));
+        assert_eq!(time.format.as_deref(), Some("key-a"));
+
+        let forbidden = get("/timezones", "key-a").await.unwrap();
+        assert_eq!(forbidden.status(), reqwest::StatusCode::FORBIDDEN);
+        let problem: ErrorResponse = forbidden.json().await.unwrap();
+        assert_eq!(problem.code, ErrorCode::Forbidden);
+
+        // The tenant's one token is spent; other keys keep their own buckets.
+        let limited = get("/time", "key-a").await.unwrap();
+        assert_eq!(limited.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        let operator = get("/time?timezone=UTC", "operator-key").await.unwrap();
+        assert_eq!(operator.status(), reqwest::StatusCode::OK);
+
+        let metrics = reqwest::get(format!("{api1_url}/metrics"))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            metrics.contains(
+                "http_requests_total{route=\"/v1/time\",tenant=\"team-a\",status=\"2xx\"} 1\n"
+            ),
+            "{metrics}"
+        );
+        assert!(metrics.contains(
+            "rate_limited_requests_total{route=\"tenant\",client=\"tenant\",tenant=\"team-a\"} 1\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn proxies_time_request_bodies_and_relays_422() {
+        let api2_url = serve(Router::new().route(
+            "/time",
+            post(|Json(request): Json<common::TimeRequest>| async move {
+                if request.locale.as_deref() == Some("xx") {
+                    return Err(ErrorResponse::new(
+                        ErrorCode::ValidationFailed,
+                        "Invalid request: locale: Unsupported locale: xx",
+                        "upstream",
+                    ));
+                }
+                Ok(Json(TimeResponse {
+                    timestamp: "2024-01-01T01:00:00+00:00".to_string(),
+                    timezone: request.timezone.unwrap_or_default(),
+                    request_id: "upstream".to_string(),
+                    source: "api2-service".to_string(),
+                    utc_offset_seconds: Some(0),
+                    utc_offset_label: Some("+00:00".to_string()),
+                    display: request.locale,
+                    format: None,
+                    degraded: None,
+                    clock_offset_ms: None,
+                }))
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+        let post = |body: serde_json::Value| {
+            client
+                .post(format!("{api1_url}/time"))
+                .header(REQUEST_ID_HEADER, "req-1")
+                .json(&body)
+                .send()
+        };
+
+        let response: TimeResponse = post(serde_json::json!({
+            "timezone": "UTC",
+            "locale": "en",
+            "offset_seconds": 3600
+        }))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+        assert_eq!(response.timezone, "UTC");
+        assert_eq!(response.display.as_deref(), Some("en"));
+        assert_eq!(response.request_id, "req-1");
+        assert_eq!(response.source, "api1->api2[0]");
+
+        let invalid = post(serde_json::json!({ "locale": "xx" })).await.unwrap();
+        assert_eq!(invalid.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let unknown = post(serde_json::json!({ "offset": 1 })).await.unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn proxies_batch_requests_to_api2() {
+        let api2_url = serve(Router::new().route(
+            "/time/batch",
+            post(|Json(batch): Json<common::BatchTimeRequest>| async move {
+                let time = TimeResponse {
+                    timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+                    timezone: batch.timezones[0].clone(),
+                    request_id: "upstream".to_string(),
+                    source: "api2-service".to_string(),
+                    utc_offset_seconds: Some(0),
+                    utc_offset_label: Some("+00:00".to_string()),
+                    display: None,
+                    format: None,
+                    degraded: None,
+                    clock_offset_ms: None,
+                };
+                let failure = common::BatchTimeError {
+                    timezone: batch.timezones[1].clone(),
+                    error: "Invalid timezone: Foo/Bar".to_string(),
+                };
+                Json(common::BatchTimeResponse {
+                    items: vec![
+                        common::BatchTimeItem::Time(time.clone()),
+                        common::BatchTimeItem::Error(failure.clone()),
+                    ],
+                    results: vec![time],
+                    errors: vec![failure],
+                    request_id: "upstream".to_string(),
+                })
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+
+        let response: common::BatchTimeResponse = client
+            .post(format!("{api1_url}/time/batch"))
+            .json(&serde_json::json!({ "timezones": ["UTC", "Foo/Bar"] }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.results[0].timezone, "UTC");
+        assert_eq!(response.results[0].source, "api1->api2[0]");
+        assert_eq!(response.errors[0].timezone, "Foo/Bar");
+
+        // A bare array works too, and `items` keeps the request positions.
+        let response: serde_json::Value = client
+            .post(format!("{api1_url}/time/batch"))
+            .json(&serde_json::json!(["UTC", "Foo/Bar"]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["items"][0]["timezone"], "UTC");
+        assert_eq!(response["items"][0]["source"], "api1->api2[0]");
+        assert_eq!(response["items"][1]["error"], "Invalid timezone: Foo/Bar");
+
+        let oversized = vec!["UTC"; common::DEFAULT_MAX_BATCH_SIZE + 1];
+        let response = client
+            .post(format!("{api1_url}/time/batch"))
+            .json(&serde_json::json!({ "timezones": oversized }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn forwards_format_to_api2() {
+        let api2_url = serve(Router::new().route(
+            "/time",
+            get(|Query(params): Query<common::TimeQuery>| async move {
+                Json(TimeResponse {
+                    timestamp: params.format.unwrap_or_default(),
+                    timezone: params.timezone.unwrap_or_default(),
+                    request_id: params.request_id.unwrap_or_default(),
+                    source: "api2-service".to_string(),
+                    utc_offset_seconds: None,
+                    utc_offset_label: None,
+                    display: None,
+                    format: None,
+                    degraded: None,
+                    clock_offset_ms: None,
+                })
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+
+        let response: TimeResponse = client
+            .get(format!("{api1_url}/time"))
+            .query(&[("format", "custom:%Y/%m/%d")])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.timestamp, "custom:%Y/%m/%d");
+
+        let response = client
+            .get(format!("{api1_url}/time?format=iso"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn proxies_timezone_list_with_prefix() {
+        let api2_url = serve(Router::new().route(
+            "/timezones",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let name = format!("{}Bangkok", params["prefix"]);
+                Json(common::TimezoneList {
+                    count: 1,
+                    timezones: vec![name.clone()],
+                    offsets: vec![common::TimezoneOffset {
+                        name,
+                        utc_offset_seconds: 25200,
+                        utc_offset_label: "+07:00".to_string(),
+                    }],
+                })
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let body: serde_json::Value = reqwest::get(format!("{api1_url}/timezones?prefix=Asia/"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "timezones": ["Asia/Bangkok"],
+                "count": 1,
+                "offsets": [{
+                    "name": "Asia/Bangkok",
+                    "utc_offset_seconds": 25200,
+                    "utc_offset_label": "+07:00"
+                }],
+                "source": "api1->api2[0]"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn health_reports_api2_status() {
+        let api2_url = serve(Router::new().route("/health", get(|| async { "ok" }))).await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let response = reqwest::get(format!("{api1_url}/health")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["dependencies"]["api2"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn serves_versioned_routes_and_deprecates_the_aliases() {
+        let api2_url = serve(Router::new().route("/time", get(mock_api2_time)).route(
+            "/v2/time",
+            get(|Query(params): Query<common::TimeQuery>| async move {
+                Json(common::TimeResponseV2 {
+                    timestamp: "2025-07-01T12:00:00-07:00".to_string(),
+                    timezone: params.timezone.unwrap_or_default(),
+                    request_id: String::new(),
+                    source: "api2-service".to_string(),
+                    format: "rfc3339".to_string(),
+                    utc_offset: common::UtcOffset {
+                        seconds: -25200,
+                        label: "-07:00".to_string(),
+                    },
+                    dst: common::DstInfo {
+                        active: true,
+                        offset_seconds: 3600,
+                        abbreviation: "PDT".to_string(),
+                        next_transition: Some("2025-11-02T09:00:00Z".to_string()),
+                    },
+                    display: None,
+                    clock_offset_ms: None,
+                })
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let response = reqwest::get(format!("{api1_url}/v2/time?timezone=PST"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+        let body: common::TimeResponseV2 = response.json().await.unwrap();
+        assert_eq!(body.timezone, "PST");
+        assert_eq!(body.source, "api1->api2[0]");
+        assert_eq!(body.dst.abbreviation, "PDT");
+
+        let response = reqwest::get(format!("{api1_url}/v1/time?timezone=UTC"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.utc_offset_label.as_deref(), Some("+00:00"));
+
+        let response = reqwest::get(format!("{api1_url}/time?timezone=UTC"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(
+            response.headers()["link"],
+            "</v1/time>; rel=\"successor-version\""
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_every_documented_route_and_the_docs() {
+        let api1_url = serve(app(AppState::new(closed_url().await))).await;
+        let client = reqwest::Client::new();
+
+        for (method, path) in openapi::document().operations() {
+            let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let response = client
+                .request(
+                    method.clone(),
+                    format!(
+                        "{api1_url}{}",
+                        path.replace("{id}", &format!("0-{}", Uuid::nil()))
+                    ),
+                )
+                .send()
+                .await
+                .unwrap();
+            assert!(
+                !matches!(response.status().as_u16(), 404 | 405),
+                "{method} {path}: {}",
+                response.status()
+            );
+        }
+
+        let document: serde_json::Value = reqwest::get(format!("{api1_url}/openapi.json"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(document["openapi"], "3.0.3");
+        let docs = reqwest::get(format!("{api1_url}/docs")).await.unwrap();
+        assert!(docs.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        assert!(docs.text().await.unwrap().contains("/openapi.json"));
+    }
+
+    /// A `/time` backend that reports `name` as its timezone.
+    async fn named_api2(name: &'static str) -> String {
+        serve(Router::new().route(
+            "/time",
+            get(move |Query(params): Query<common::TimeQuery>| async move {
+                Json(TimeResponse {
+                    timezone: name.to_string(),
+                    ..mock_api2_time(Query(params)).await.0
+                })
+            }),
+        ))
+        .await
+    }
+
+    /// A URL that refuses connections.
+    async fn closed_url() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        url
+    }
+
+    fn balanced_state(urls: Vec<String>) -> AppState {
+        AppState {
+            backends: Arc::new(balancer::Backends::new(
+                urls,
+                balancer::Strategy::RoundRobin,
+            )),
+            time_cache: Arc::new(cache::TimeCache::new(0, Duration::ZERO)),
+            ..AppState::new(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn round_robins_across_api2_instances() {
+        let urls = vec![
+            named_api2("first").await,
+            named_api2("second").await,
+            named_api2("third").await,
+        ];
+        let api1_url = serve(app(balanced_state(urls))).await;
+
+        let mut served = Vec::new();
+        for _ in 0..6 {
+            let body: TimeResponse = reqwest::get(format!("{api1_url}/time"))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            served.push((body.timezone, body.source));
+        }
+        let expected: Vec<_> = ["first", "second", "third"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.to_string(), format!("api1->api2[{index}]")))
+            .cycle()
+            .take(6)
+            .collect();
+        assert_eq!(served, expected);
+    }
+
+    #[tokio::test]
+    async fn fails_over_when_an_api2_instance_is_down() {
+        let urls = vec![closed_url().await, named_api2("second").await];
+        let state = AppState {
+            retry: retry::RetryPolicy {
+                max_retries: 0,
+                ..retry::RetryPolicy::default()
+            },
+            ..balanced_state(urls)
+        };
+        let api1_url = serve(app(state)).await;
+
+        for _ in 0..2 {
+            let response = reqwest::get(format!("{api1_url}/time")).await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            let body: TimeResponse = response.json().await.unwrap();
+            assert_eq!(body.source, "api1->api2[1]");
+        }
+
+        let health: serde_json::Value = reqwest::get(format!("{api1_url}/health"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            health["dependencies"]["api2_instances"][0]["status"],
+            "unreachable"
+        );
+    }
+
+    #[tokio::test]
+    async fn health_is_degraded_when_api2_is_down() {
+        // Bind and release a port so nothing is listening on it.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api2_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let response = reqwest::get(format!("{api1_url}/health")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["dependencies"]["api2"], "unreachable");
+        assert!(body["dependencies"]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn gzips_responses_when_accepted() {
+        let api2_url = serve(Router::new().route("/health", get(|| async { "ok" }))).await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("{api1_url}/health"))
+            .header(reqwest::header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_ENCODING],
+            "gzip"
+        );
+        let compressed = response.bytes().await.unwrap();
+        let mut gunzip = std::process::Command::new("gzip")
+            .arg("-dc")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        std::io::Write::write_all(&mut gunzip.stdin.take().unwrap(), &compressed).unwrap();
+        let output = gunzip.wait_with_output().unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(body["status"], "healthy");
+
+        let response = client
+            .get(format!("{api1_url}/health"))
+            .send()
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(reqwest::header::CONTENT_ENCODING));
+        assert!(response.json::<serde_json::Value>().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn proxies_time_diff_and_relays_validation_errors() {
+        let api2_url = serve(Router::new().route(
+            "/time/diff",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                match params.get("to") {
+                    Some(to) => Ok(Json(common::TimeDiffResponse {
+                        from_timezone: params["from"].clone(),
+                        to_timezone: to.clone(),
+                        offset_seconds: 3600,
+                        offset_label: "+01:00".to_string(),
+                        from_timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+                        to_timestamp: "2024-01-01T01:00:00+01:00".to_string(),
+                        request_id: "upstream".to_string(),
+                    })),
+                    None => Err(ErrorResponse::new(
+                        ErrorCode::InvalidRequest,
+                        "Missing required parameter: to",
+                        "upstream",
+                    )),
+                }
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let diff: common::TimeDiffResponse =
+            reqwest::get(format!("{api1_url}/time/diff?from=UTC&to=Europe/Paris"))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+        assert_eq!(diff.to_timezone, "Europe/Paris");
+        assert_eq!(diff.offset_label, "+01:00");
+
+        let response = reqwest::get(format!("{api1_url}/time/diff?from=UTC"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "Missing required parameter: to");
+    }
+
+    #[tokio::test]
+    async fn proxies_time_convert_and_relays_dst_errors() {
+        let api2_url = serve(Router::new().route(
+            "/time/convert",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                let side = |timezone: &str| common::ConvertedTime {
+                    timezone: timezone.to_string(),
+                    timestamp: params["timestamp"].clone(),
+                    utc_offset_seconds: 0,
+                    utc_offset_label: "+00:00".to_string(),
+                    is_dst: false,
+                };
+                match params.get("from") {
+                    Some(from) => Err(ErrorResponse::new(
+                        ErrorCode::ValidationFailed,
+                        format!("Nonexistent local time in {from}"),
+                        "upstream",
+                    )),
+                    None => Ok(Json(common::TimeConvertResponse {
+                        epoch_seconds: 0,
+                        from: side("UTC"),
+                        to: side(&params["to"]),
+                        request_id: "upstream".to_string(),
+                    })),
+                }
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+
+        let converted: common::TimeConvertResponse = reqwest::get(format!(
+            "{api1_url}/time/convert?timestamp=1970-01-01T00:00:00Z&to=Asia/Tokyo"
+        ))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+        assert_eq!(converted.to.timezone, "Asia/Tokyo");
+        assert_eq!(converted.to.timestamp, "1970-01-01T00:00:00Z");
+
+        let response = reqwest::get(format!(
+            "{api1_url}/time/convert?timestamp=2024-03-10T02:30:00&from=America/New_York&to=UTC"
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "Nonexistent local time in America/New_York");
+    }
+
+    #[tokio::test]
+    async fn proxies_event_stream_without_buffering() {
+        use futures_util::StreamExt;
+
+        let api2_url = serve(Router::new().route(
+            "/time/stream",
+            get(|| async {
+                // Two events, then hold the connection open like a live stream.
+                let events = futures_util::stream::iter([
+                    Ok::<_, std::convert::Infallible>("data: 1\n\n"),
+                    Ok("data: 2\n\n"),
+                ])
+                .chain(futures_util::stream::pending());
+                (
+                    [(header::CONTENT_TYPE, "text/event-stream")],
+                    axum::body::Body::from_stream(events),
+                )
+            }),
+        ))
+        .await;
+        let state = AppState {
+            api2_timeout: Duration::from_millis(50),
+            ..AppState::new(api2_url)
+        };
+        let api1_url = serve(app(state)).await;
+
+        let mut response = reqwest::get(format!("{api1_url}/time/stream"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let mut received = String::new();
+        while !received.contains("data: 2\n\n") {
+            let chunk = tokio::time::timeout(Duration::from_secs(2), response.chunk())
+                .await
+                .expect("events were buffered")
+                .unwrap()
+                .unwrap();
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        // Outliving API2_TIMEOUT_MS does not cut the stream off.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), response.chunk())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn tunnels_websocket_queries_to_api2() {
+        use common::websocket::{self, Message, MessageReader};
+
+        // Echoes each query back as a TimeResponse naming its timezone.
+        let api2_url = serve(Router::new().route(
+            "/time/ws",
+            get(|mut request: axum::extract::Request| async move {
+                let forwarded_id = request_id_from(request.headers());
+                websocket::upgrade(&mut request, move |text| {
+                    let forwarded_id = forwarded_id.clone();
+                    async move {
+                        let query: common::TimeQuery = serde_json::from_str(&text).unwrap();
+                        serde_json::to_string(&TimeResponse {
+                            timestamp: chrono::Utc::now().to_rfc3339(),
+                            timezone: query.timezone.unwrap_or_default(),
+                            request_id: forwarded_id,
+                            source: "api2-service".to_string(),
+                            utc_offset_seconds: None,
+                            utc_offset_label: None,
+                            display: None,
+                            format: None,
+                            degraded: None,
+                            clock_offset_ms: None,
+                        })
+                        .unwrap()
+                    }
+                })
+                .unwrap()
+            }),
+        ))
+        .await;
+        let api1_url = serve(app(AppState::new(api2_url))).await;
+        let api1_addr = api1_url.trim_start_matches("http://");
+
+        let mut stream = tokio::net::TcpStream::connect(api1_addr).await.unwrap();
+        let key = websocket::new_key();
+        let handshake = websocket::request_upgrade(
+            &mut stream,
+            api1_addr,
+            "/time/ws",
+            &key,
+            &[(REQUEST_ID_HEADER, "tunnel-1")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(handshake.status, 101);
+        assert_eq!(handshake.accept, Some(websocket::accept_key(&key)));
+
+        for timezone in ["Asia/Seoul", "Europe/Paris", "America/Chicago"] {
+            let query = Message::Text(format!(r#"{{"timezone": "{timezone}"}}"#));
+            websocket::write_message(&mut stream, &query, true)
+                .await
+                .unwrap();
+            let Some(Message::Text(reply)) = MessageReader::new(&mut stream).next().await.unwrap()
+            else {
+                panic!("expected a text reply");
+            };
+            let response: TimeResponse = serde_json::from_str(&reply).unwrap();
+            assert_eq!(response.timezone, timezone);
+            assert_eq!(response.request_id, "tunnel-1");
+        }
+
+        // A plain request is not tunnelled.
+        let response = reqwest::get(format!("{api1_url}/time/ws")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,283 @@
+//! Concurrency limit with a bounded queue, shedding load beyond both.
+//!
+//! With `MAX_IN_FLIGHT` set, at most that many requests are handled at once.
+//! Up to `MAX_QUEUED_REQUESTS` more (default: as many as `MAX_IN_FLIGHT`)
+//! wait in arrival order for one to finish; any others get `503` with
+//! `Retry-After` straight away, so a spike costs a bounded amount of memory
+//! instead of a task per request. A request counts as in flight until its
+//! response starts, so a long stream does not hold its place. The health
+//! probes and `/metrics` are never queued or shed.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use common::config::Config;
+use common::metrics::{self, Registry};
+use common::problem::ErrorCode;
+use common::ErrorResponse;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{Layer, Service};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::rate_limit::EXEMPT_PATHS;
+
+/// `Retry-After` sent with shed requests, in seconds.
+const RETRY_AFTER_SECS: u64 = 1;
+
+const REQUESTS_IN_FLIGHT: metrics::Gauge = metrics::Gauge {
+    name: "requests_in_flight",
+    help: "Requests being handled under MAX_IN_FLIGHT.",
+};
+
+const REQUEST_QUEUE_DEPTH: metrics::Gauge = metrics::Gauge {
+    name: "request_queue_depth",
+    help: "Requests waiting for MAX_IN_FLIGHT to allow them in.",
+};
+
+const SHED_REQUESTS_TOTAL: metrics::Counter = metrics::Counter {
+    name: "shed_requests_total",
+    help: "Requests rejected with 503 because the queue was full.",
+};
+
+pub struct LoadShedder {
+    max_in_flight: usize,
+    permits: Arc<Semaphore>,
+    max_queued: usize,
+    queued: AtomicUsize,
+    metrics: Arc<Registry>,
+}
+
+impl LoadShedder {
+    pub fn new(max_in_flight: usize, max_queued: usize, metrics: Arc<Registry>) -> Self {
+        LoadShedder {
+            max_in_flight,
+            permits: Arc::new(Semaphore::new(max_in_flight)),
+            max_queued,
+            queued: AtomicUsize::new(0),
+            metrics,
+        }
+    }
+
+    /// A shedder for `max_in_flight` and `max_queued_requests`, or `None`
+    /// when `max_in_flight` is unset.
+    pub fn from_config(config: &Config, metrics: Arc<Registry>) -> Option<Self> {
+        let max_in_flight = config.max_in_flight?;
+        Some(LoadShedder::new(
+            max_in_flight,
+            config.max_queued_requests.unwrap_or(max_in_flight),
+            metrics,
+        ))
+    }
+
+    /// Waits for a place in flight, or returns `None` at once when the queue
+    /// is full too.
+    async fn admit(self: Arc<Self>) -> Option<InFlight> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Some(self.in_flight(permit));
+        }
+        if self.queued.fetch_add(1, Ordering::AcqRel) >= self.max_queued {
+            self.queued.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+        let queued = Queued(self.clone());
+        self.update_gauges();
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        drop(queued);
+        Some(self.in_flight(permit))
+    }
+
+    fn in_flight(self: Arc<Self>, permit: OwnedSemaphorePermit) -> InFlight {
+        self.update_gauges();
+        InFlight {
+            permit: Some(permit),
+            shedder: self,
+        }
+    }
+
+    fn update_gauges(&self) {
+        let in_flight = self.max_in_flight - self.permits.available_permits();
+        self.metrics.set(&REQUESTS_IN_FLIGHT, &[], in_flight as f64);
+        self.metrics.set(
+            &REQUEST_QUEUE_DEPTH,
+            &[],
+            self.queued.load(Ordering::Acquire) as f64,
+        );
+    }
+}
+
+/// A request's place in the queue, given up when it is let in or its client
+/// goes away.
+struct Queued(Arc<LoadShedder>);
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.0.queued.fetch_sub(1, Ordering::AcqRel);
+        self.0.update_gauges();
+    }
+}
+
+/// A request's place in flight, given up when its response starts.
+struct InFlight {
+    permit: Option<OwnedSemaphorePermit>,
+    shedder: Arc<LoadShedder>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        self.shedder.update_gauges();
+    }
+}
+
+/// Applies a [`LoadShedder`]; passes every request through without one.
+#[derive(Clone)]
+pub struct LoadShedLayer {
+    shedder: Option<Arc<LoadShedder>>,
+}
+
+impl LoadShedLayer {
+    pub fn new(shedder: Option<Arc<LoadShedder>>) -> Self {
+        LoadShedLayer { shedder }
+    }
+}
+
+impl<S> Layer<S> for LoadShedLayer {
+    type Service = LoadShed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadShed {
+            inner,
+            shedder: self.shedder.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LoadShed<S> {
+    inner: S,
+    shedder: Option<Arc<LoadShedder>>,
+}
+
+impl<S> Service<Request<Body>> for LoadShed<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let shedder = match &self.shedder {
+            Some(shedder) if !EXEMPT_PATHS.contains(&request.uri().path()) => shedder.clone(),
+            _ => return Box::pin(self.inner.call(request)),
+        };
+        // The ready service goes with the request; its clone stays for the next.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let Some(in_flight) = shedder.clone().admit().await else {
+                shedder.metrics.increment(&SHED_REQUESTS_TOTAL, &[]);
+                return Ok(overloaded(&request));
+            };
+            let response = inner.call(request).await;
+            drop(in_flight);
+            response
+        })
+    }
+}
+
+fn overloaded(request: &Request<Body>) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    warn!(
+        request_id = %request_id,
+        path = %request.uri().path(),
+        "Server overloaded; shedding request"
+    );
+    let mut response = ErrorResponse::new(
+        ErrorCode::Unavailable,
+        "Server overloaded; retry shortly",
+        &request_id,
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Router};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn queues_up_to_the_limit_then_sheds() {
+        let metrics = Arc::new(Registry::default());
+        let shedder = Arc::new(LoadShedder::new(1, 1, metrics.clone()));
+        let (release, released) = tokio::sync::watch::channel(false);
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(move || {
+                    let mut released = released.clone();
+                    async move {
+                        released.wait_for(|released| *released).await.unwrap();
+                        "done"
+                    }
+                }),
+            )
+            .route("/health", get(|| async { "ok" }))
+            .layer(LoadShedLayer::new(Some(shedder.clone())));
+
+        let call = |uri: &'static str| {
+            let mut app = app.clone();
+            tokio::spawn(async move {
+                // Router is always ready, so poll_ready can be skipped.
+                app.call(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            })
+        };
+        let first = call("/slow");
+        let second = call("/slow");
+        while shedder.queued.load(Ordering::Acquire) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let render = metrics.render();
+        assert!(render.contains("requests_in_flight 1\n"), "{render}");
+        assert!(render.contains("request_queue_depth 1\n"), "{render}");
+
+        let shed = call("/slow").await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "1");
+        assert_eq!(call("/health").await.unwrap().status(), StatusCode::OK);
+
+        release.send(true).unwrap();
+        assert_eq!(first.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(second.await.unwrap().status(), StatusCode::OK);
+        let render = metrics.render();
+        assert!(render.contains("shed_requests_total 1\n"), "{render}");
+        assert!(render.contains("requests_in_flight 0\n"), "{render}");
+        assert!(render.contains("request_queue_depth 0\n"), "{render}");
+    }
+}
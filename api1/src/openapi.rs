@@ -0,0 +1,260 @@
+//! API1's OpenAPI document, served at `/openapi.json` with Swagger UI at
+//! `/docs`.
+
+use common::openapi::{self, array, object, time_options, typed, Document, Operation};
+use common::{
+    BatchTimeRequest, BatchTimeResponse, CreateTimerRequest, CronRequest, CronResponse,
+    TimeConvertResponse, TimeDiffResponse, TimeRequest, TimeResponse, TimeResponseV2,
+    TimerResponse,
+};
+use serde_json::{json, Value};
+
+use crate::timezones::ProxiedTimezoneList;
+
+/// Errors of every route that calls API2.
+fn upstream_errors(operation: Operation) -> Operation {
+    operation
+        .secured()
+        .error(413, "Request body too large.")
+        .error(429, "Rate limit exceeded.")
+        .error(502, "API2 answered with an error or could not be reached.")
+        .error(503, "Circuit breaker open, or shutting down.")
+        .error(504, "API2 or this request timed out.")
+}
+
+fn free_form(description: &str) -> Value {
+    json!({ "type": "object", "description": description, "additionalProperties": true })
+}
+
+pub fn document() -> Document {
+    let get_time = time_options(Operation::new("Current time in a timezone, from API2"))
+        .query(
+            "timezone",
+            "IANA name or alias such as `PST` (default: UTC).",
+        )
+        .query("cache", "`bypass` skips the response cache.")
+        .response::<TimeResponse>(200, "The current time.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.")
+        .error(400, "Invalid timezone, format or locale.");
+    let post_time = Operation::new("Current time with options in the body")
+        .body::<TimeRequest>("Timezone and rendering options.")
+        .response::<TimeResponse>(200, "The current time.")
+        .error(400, "Invalid timezone, format or locale.")
+        .error(422, "Malformed body or unknown field.");
+    let get_time_v2 = time_options(Operation::new("Current time with daylight saving details"))
+        .query(
+            "timezone",
+            "IANA name or alias such as `PST` (default: UTC).",
+        )
+        .response::<TimeResponseV2>(200, "The current time.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.")
+        .error(400, "Invalid timezone, format or locale.");
+    let post_time_v2 =
+        Operation::new("Current time with daylight saving details, options in the body")
+            .body::<TimeRequest>("Timezone and rendering options.")
+            .response::<TimeResponseV2>(200, "The current time.")
+            .error(400, "Invalid timezone, format or locale.")
+            .error(422, "Malformed body or unknown field.");
+    let batch = Operation::new("Current time in several timezones")
+        .body::<BatchTimeRequest>("Timezones to look up.")
+        .response::<BatchTimeResponse>(200, "One entry per timezone.")
+        .error(400, "Empty or oversized batch.")
+        .error(422, "Malformed body.");
+    let diff = Operation::new("Offset between two timezones")
+        .required_query("from", "IANA name.")
+        .required_query("to", "IANA name.")
+        .response::<TimeDiffResponse>(200, "The current offset.")
+        .error(400, "Missing or invalid timezone.");
+    let convert = Operation::new("An instant in two timezones")
+        .required_query("timestamp", "RFC 3339 time or Unix seconds.")
+        .required_query("from", "IANA name the timestamp is in.")
+        .required_query("to", "IANA name to convert to.")
+        .response::<TimeConvertResponse>(200, "The converted instant.")
+        .error(400, "Missing or invalid parameter.");
+    let stream = Operation::new("Server-sent events with the current time, relayed from API2")
+        .query("timezone", "IANA name (default: UTC).")
+        .query("interval_ms", "Milliseconds between events.")
+        .response_inline(
+            200,
+            "One `TimeResponse` JSON object per event.",
+            "text/event-stream",
+            typed("string", "Event stream."),
+        )
+        .error(400, "Invalid timezone or interval.");
+    let ws = Operation::new("WebSocket tunnel to API2's `/time/ws`")
+        .empty_response(101, "Switching to the WebSocket protocol.")
+        .error(400, "Not a WebSocket upgrade request.");
+    let timezones = Operation::new("Timezone names known to API2")
+        .query("prefix", "Only names starting with this, e.g. `Asia/`.")
+        .response::<ProxiedTimezoneList>(200, "Matching timezones.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.");
+
+    let cron_next = Operation::new("Upcoming fire times of a cron expression")
+        .body::<CronRequest>("Expression, timezone and count.")
+        .response::<CronResponse>(200, "The next fire times, soonest first.")
+        .error(
+            400,
+            "Malformed expression, invalid timezone or count out of range.",
+        )
+        .error(422, "Malformed body or unknown field.");
+
+    let create_timer = Operation::new("Start a named timer")
+        .body::<CreateTimerRequest>("The timer's name.")
+        .response::<TimerResponse>(201, "The started timer.")
+        .error(400, "Missing or overlong name.")
+        .error(422, "Malformed body or unknown field.");
+    let timer = Operation::new("Time elapsed on a timer")
+        .path("id", "ID returned when the timer was started.")
+        .response::<TimerResponse>(200, "The timer.")
+        .error(404, "Unknown or expired timer.");
+    let stop_timer = Operation::new("Stop a timer, freezing its elapsed time")
+        .path("id", "ID returned when the timer was started.")
+        .response::<TimerResponse>(200, "The stopped timer.")
+        .error(404, "Unknown or expired timer.");
+
+    let document = Document::new(
+        "API1 - Time Service Gateway",
+        "Gateway in front of API2. When `API_KEYS` is set, every route except the \
+         health checks, `/metrics` and these docs needs an `X-Api-Key`. The time \
+         routes are listed by their deprecated unversioned paths and are served \
+         unchanged under `/v1`; `/v2` differs only in `/time`.",
+    )
+    .route("get", "/time", upstream_errors(get_time))
+    .route("post", "/time", upstream_errors(post_time))
+    .route("get", "/v2/time", upstream_errors(get_time_v2))
+    .route("post", "/v2/time", upstream_errors(post_time_v2))
+    .route("post", "/time/batch", upstream_errors(batch))
+    .route("get", "/time/diff", upstream_errors(diff))
+    .route("get", "/time/convert", upstream_errors(convert))
+    .route("get", "/time/stream", upstream_errors(stream))
+    .route("get", "/time/ws", upstream_errors(ws))
+    .route("get", "/timezones", upstream_errors(timezones))
+    .route("post", "/cron/next", upstream_errors(cron_next))
+    .route("post", "/timer", upstream_errors(create_timer))
+    .route("get", "/timer/{id}", upstream_errors(timer))
+    .route("delete", "/timer/{id}", upstream_errors(stop_timer))
+    .route(
+        "get",
+        "/time/clock-synchronisation",
+        Operation::new("Timestamps for estimating clock offset from this service")
+            .response_inline(
+                200,
+                "Send and receive times with instructions.",
+                "application/json",
+                object(
+                    "Clock synchronisation sample.",
+                    &[
+                        ("t1", typed("string", "RFC 3339 receive time."), true),
+                        ("t2", typed("string", "RFC 3339 send time."), true),
+                        ("formula", typed("string", "Offset formula."), true),
+                        ("instructions", array(typed("string", "Step.")), true),
+                    ],
+                ),
+            )
+            .secured(),
+    )
+    .route(
+        "get",
+        "/time/api-response-time-sla",
+        Operation::new("Recent latency percentiles against targets")
+            .query("p50_target_ms", "Target for the median, in milliseconds.")
+            .query("p95_target_ms", "Target for p95, in milliseconds.")
+            .query("p99_target_ms", "Target for p99, in milliseconds.")
+            .response_inline(
+                200,
+                "Compliance of each percentile.",
+                "application/json",
+                free_form("SLA report."),
+            )
+            .error(400, "Invalid target.")
+            .secured(),
+    );
+    health_routes(document)
+}
+
+fn health_routes(document: Document) -> Document {
+    document
+        .route(
+            "get",
+            "/health",
+            Operation::new("Health of API1 and each API2 instance")
+                .response_inline(
+                    200,
+                    "Healthy.",
+                    "application/json",
+                    free_form("Status, dependencies and cache."),
+                )
+                .response_inline(
+                    503,
+                    "API2 is unreachable or the service is shutting down.",
+                    "application/json",
+                    free_form("Status and the failure."),
+                ),
+        )
+        .route(
+            "get",
+            common::health::LIVE_PATH,
+            Operation::new("Liveness probe").response_inline(
+                200,
+                "Alive.",
+                "application/json",
+                free_form("Status."),
+            ),
+        )
+        .route(
+            "get",
+            common::health::READY_PATH,
+            Operation::new("Readiness probe")
+                .response_inline(200, "Ready.", "application/json", free_form("Checks."))
+                .response_inline(
+                    503,
+                    "Not ready.",
+                    "application/json",
+                    free_form("Failing checks."),
+                ),
+        )
+        .route(
+            "get",
+            common::metrics::METRICS_PATH,
+            Operation::new("Prometheus metrics").response_inline(
+                200,
+                "Text exposition format.",
+                "text/plain",
+                typed("string", "Metrics."),
+            ),
+        )
+        .route(
+            "get",
+            openapi::OPENAPI_PATH,
+            Operation::new("This document").response_inline(
+                200,
+                "OpenAPI 3.0 document.",
+                "application/json",
+                free_form("OpenAPI document."),
+            ),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_the_error_schema_and_api_key() {
+        let document = document().into_json();
+        assert_eq!(document["openapi"], "3.0.3");
+        let get_time = &document["paths"]["/time"]["get"];
+        assert_eq!(
+            get_time["responses"]["502"]["content"]["application/problem+json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+        assert_eq!(get_time["security"][0]["ApiKey"], json!([]));
+        assert!(document["paths"]["/health"]["get"]["security"].is_null());
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["ProxiedTimezoneList"]["allOf"][0]["$ref"],
+            "#/components/schemas/TimezoneList"
+        );
+        assert!(schemas["ErrorResponse"].is_object());
+    }
+}
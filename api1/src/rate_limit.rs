@@ -0,0 +1,489 @@
+//! Token-bucket rate limiting, per API key for requests carrying a valid
+//! `X-Api-Key` and per client IP otherwise.
+//!
+//! Routes given their own limit (by default `/time/batch`, whose calls fan
+//! out to many timezones) have separate buckets; every other route shares
+//! the global one. A tenant given a `rate_limit` has a single bucket for all
+//! its requests instead; see [`common::tenants`]. The health probes and
+//! `/metrics` are never limited.
+//!
+//! Every limited response carries `X-RateLimit-Limit` (the burst),
+//! `X-RateLimit-Remaining` (whole tokens left) and `X-RateLimit-Reset`
+//! (seconds until the bucket is full again).
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use common::auth::{key_index, redact_key, API_KEY_HEADER};
+use common::config::Config;
+use common::health;
+use common::metrics::{self, Registry};
+use common::problem::ErrorCode;
+use common::rate_limit::RouteLimit;
+use common::tenants::{Tenants, TENANT_HEADER};
+use common::versioning::unversioned;
+use common::ErrorResponse;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::warn;
+use uuid::Uuid;
+
+const DEFAULT_RPS: f64 = 10.0;
+const DEFAULT_BURST: f64 = 20.0;
+/// Paths that are never rate limited or shed, so probes and scrapers keep
+/// working.
+pub(crate) const EXEMPT_PATHS: [&str; 4] =
+    ["/health", health::LIVE_PATH, health::READY_PATH, "/metrics"];
+/// Route limits applied unless configured otherwise: path, rate and burst.
+const DEFAULT_ROUTE_LIMITS: [(&str, f64, f64); 1] = [("/time/batch", 2.0, 5.0)];
+/// Idle buckets are pruned once this many clients are tracked.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+const RATE_LIMITED_REQUESTS_TOTAL: metrics::Counter = metrics::Counter {
+    name: "rate_limited_requests_total",
+    help: "Requests rejected with 429 by route limit and client kind: api_key, ip or tenant.",
+};
+
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Whose bucket a request draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Client {
+    /// Position of the key in the configured `api_keys`.
+    ApiKey(usize),
+    Ip(IpAddr),
+    /// Every request of a tenant with its own limiter.
+    Tenant,
+}
+
+impl From<IpAddr> for Client {
+    fn from(ip: IpAddr) -> Self {
+        Client::Ip(ip)
+    }
+}
+
+impl Client {
+    fn kind(&self) -> &'static str {
+        match self {
+            Client::ApiKey(_) => "api_key",
+            Client::Ip(_) => "ip",
+            Client::Tenant => "tenant",
+        }
+    }
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Client::ApiKey(index) => write!(f, "api_key[{index}]"),
+            Client::Ip(ip) => write!(f, "{ip}"),
+            Client::Tenant => f.write_str("tenant"),
+        }
+    }
+}
+
+/// What is left of a client's bucket after a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quota {
+    remaining: u64,
+    /// Until the bucket is full again.
+    reset: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    rate_per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<Client, Bucket>>,
+    /// Limiters used instead of this one for particular paths.
+    routes: HashMap<String, RateLimiter>,
+    /// Limiters used instead of any other for particular tenants' requests.
+    tenants: HashMap<String, RateLimiter>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(DEFAULT_RPS, DEFAULT_BURST).with_default_routes()
+    }
+}
+
+impl RateLimiter {
+    /// A single limit for every route.
+    pub fn new(rate_per_second: f64, burst: f64) -> Self {
+        RateLimiter {
+            rate_per_second,
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+            routes: HashMap::new(),
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Uses `rate_limit_rps` and `rate_limit_burst`, falling back to the
+    /// defaults when unset or not positive, and adds `rate_limit_routes` on
+    /// top of the default route limits and the `rate_limit` of each tenant.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a malformed route limit.
+    pub fn from_config(config: &Config) -> Self {
+        let positive = |value: Option<f64>, default| value.filter(|v| *v > 0.0).unwrap_or(default);
+        let mut limiter = RateLimiter::new(
+            positive(config.rate_limit_rps, DEFAULT_RPS),
+            positive(config.rate_limit_burst, DEFAULT_BURST),
+        )
+        .with_default_routes();
+        for route in config.rate_limit_routes.iter().flatten() {
+            let route = RouteLimit::parse(route).unwrap_or_else(|e| panic!("{e}"));
+            limiter = limiter.with_route(&route.path, route.rate_per_second, route.burst);
+        }
+        for tenant in Tenants::from_config(config).iter() {
+            if let Some((rate_per_second, burst)) = tenant.rate_limit {
+                limiter = limiter.with_tenant(&tenant.name, rate_per_second, burst);
+            }
+        }
+        limiter
+    }
+
+    /// Gives `path` its own limit, replacing any it had.
+    pub fn with_route(mut self, path: &str, rate_per_second: f64, burst: f64) -> Self {
+        self.routes
+            .insert(path.to_string(), RateLimiter::new(rate_per_second, burst));
+        self
+    }
+
+    /// Gives all requests of `tenant` one bucket, on every route, replacing
+    /// any limit it had.
+    pub fn with_tenant(mut self, tenant: &str, rate_per_second: f64, burst: f64) -> Self {
+        self.tenants
+            .insert(tenant.to_string(), RateLimiter::new(rate_per_second, burst));
+        self
+    }
+
+    fn with_default_routes(self) -> Self {
+        DEFAULT_ROUTE_LIMITS
+            .iter()
+            .fold(self, |limiter, (path, rate, burst)| {
+                limiter.with_route(path, *rate, *burst)
+            })
+    }
+
+    /// The limiter for `path` in any API version, with the route it is
+    /// labelled by in metrics.
+    fn for_path(&self, path: &str) -> (&str, &RateLimiter) {
+        match self.routes.get_key_value(unversioned(path)) {
+            Some((route, limiter)) => (route, limiter),
+            None => ("default", self),
+        }
+    }
+
+    /// Takes a token for `client` and returns what is left, or returns how
+    /// long until a token is available.
+    fn acquire(&self, client: impl Into<Client>, now: Instant) -> Result<Quota, Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let full_after = Duration::from_secs_f64(self.burst / self.rate_per_second);
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < full_after);
+        }
+
+        let bucket = buckets.entry(client.into()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate_per_second).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(Quota {
+                remaining: bucket.tokens.floor() as u64,
+                reset: self.refill_time(bucket.tokens),
+            })
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.rate_per_second,
+            ))
+        }
+    }
+
+    /// Time for a bucket holding `tokens` to fill up again.
+    fn refill_time(&self, tokens: f64) -> Duration {
+        Duration::from_secs_f64(((self.burst - tokens) / self.rate_per_second).max(0.0))
+    }
+
+    /// The quota of a client that was just refused and must wait `wait`.
+    fn exhausted(&self, wait: Duration) -> Quota {
+        Quota {
+            remaining: 0,
+            reset: self.refill_time(1.0 - wait.as_secs_f64() * self.rate_per_second),
+        }
+    }
+
+    fn insert_headers(&self, headers: &mut HeaderMap, quota: Quota) {
+        let reset = quota.reset.as_secs() + u64::from(quota.reset.subsec_nanos() > 0);
+        headers.insert(LIMIT_HEADER, HeaderValue::from(self.burst.floor() as u64));
+        headers.insert(REMAINING_HEADER, HeaderValue::from(quota.remaining));
+        headers.insert(RESET_HEADER, HeaderValue::from(reset));
+    }
+}
+
+/// Tower layer rejecting requests over their key's or IP's limit with `429`.
+///
+/// A key in `X-Api-Key` that is not one of `api_keys` is ignored here and the
+/// request is limited by IP; rejecting it is left to the auth layer. The
+/// client IP comes from `ConnectInfo<SocketAddr>`, so the router must be
+/// served with `into_make_service_with_connect_info`.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+    api_keys: Arc<Vec<String>>,
+    metrics: Arc<Registry>,
+}
+
+impl RateLimitLayer {
+    pub fn new(
+        limiter: Arc<RateLimiter>,
+        api_keys: Arc<Vec<String>>,
+        metrics: Arc<Registry>,
+    ) -> Self {
+        RateLimitLayer {
+            limiter,
+            api_keys,
+            metrics,
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            api_keys: self.api_keys.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+    api_keys: Arc<Vec<String>>,
+    metrics: Arc<Registry>,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if EXEMPT_PATHS.contains(&request.uri().path()) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let provided = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let key = provided.and_then(|provided| key_index(&self.api_keys, provided));
+        let client = match key {
+            Some(index) => Client::ApiKey(index),
+            None => Client::Ip(
+                request
+                    .extensions()
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |info| info.0.ip()),
+            ),
+        };
+
+        let limiters = self.limiter.clone();
+        let tenant = request
+            .headers()
+            .get(TENANT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|tenant| limiters.tenants.get_key_value(tenant));
+        let (route, limiter, client) = match tenant {
+            Some((_, limiter)) => ("tenant", limiter, Client::Tenant),
+            None => {
+                let (route, limiter) = limiters.for_path(request.uri().path());
+                (route, limiter, client)
+            }
+        };
+        match limiter.acquire(client, Instant::now()) {
+            Ok(quota) => {
+                let mut headers = HeaderMap::new();
+                limiter.insert_headers(&mut headers, quota);
+                let response = self.inner.call(request);
+                Box::pin(async move {
+                    let mut response = response.await?;
+                    response.headers_mut().extend(headers);
+                    Ok(response)
+                })
+            }
+            Err(wait) => {
+                let mut labels = vec![("route", route), ("client", client.kind())];
+                if let Some((tenant, _)) = tenant {
+                    labels.push(("tenant", tenant));
+                }
+                self.metrics
+                    .increment(&RATE_LIMITED_REQUESTS_TOTAL, &labels);
+                let api_key = key.and(provided).map(redact_key);
+                let mut response = too_many_requests(client, route, api_key, wait);
+                limiter.insert_headers(response.headers_mut(), limiter.exhausted(wait));
+                Box::pin(std::future::ready(Ok(response)))
+            }
+        }
+    }
+}
+
+fn too_many_requests(
+    client: Client,
+    route: &str,
+    api_key: Option<String>,
+    wait: Duration,
+) -> Response {
+    let request_id = Uuid::new_v4().to_string();
+    let wait_ms = wait.as_millis().max(1);
+
+    warn!(
+        request_id = %request_id,
+        client = %client,
+        route = %route,
+        api_key = api_key.as_deref(),
+        retry_after_ms = wait_ms as u64,
+        "Rate limit exceeded"
+    );
+
+    let mut response = ErrorResponse::new(
+        ErrorCode::RateLimited,
+        format!("Rate limit exceeded; retry in {wait_ms}ms"),
+        &request_id,
+    )
+    .into_response();
+    // Retry-After is defined in whole seconds; the exact wait goes alongside.
+    let headers = response.headers_mut();
+    headers.insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(wait.as_secs() + u64::from(wait.subsec_nanos() > 0)),
+    );
+    headers.insert("retry-after-ms", HeaderValue::from(wait_ms as u64));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_refills_at_configured_rate() {
+        let limiter = RateLimiter::new(10.0, 2.0);
+        let client = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+
+        assert!(limiter.acquire(client, start).is_ok());
+        assert!(limiter.acquire(client, start).is_ok());
+        let wait = limiter.acquire(client, start).unwrap_err();
+        assert_eq!(wait.as_millis(), 100);
+
+        assert!(limiter
+            .acquire(client, start + Duration::from_millis(100))
+            .is_ok());
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert!(limiter.acquire(other, start).is_ok());
+    }
+
+    #[test]
+    fn keys_have_their_own_buckets_and_report_quota() {
+        let limiter = RateLimiter::new(10.0, 3.0);
+        let client = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+
+        let quota = limiter.acquire(Client::ApiKey(0), start).unwrap();
+        assert_eq!(quota.remaining, 2);
+        assert_eq!(quota.reset, Duration::from_millis(100));
+        limiter.acquire(Client::ApiKey(0), start).unwrap();
+        limiter.acquire(Client::ApiKey(0), start).unwrap();
+        let wait = limiter.acquire(Client::ApiKey(0), start).unwrap_err();
+        assert_eq!(limiter.exhausted(wait).reset.as_millis(), 300);
+
+        assert_eq!(
+            limiter.acquire(Client::ApiKey(1), start).unwrap().remaining,
+            2
+        );
+        assert_eq!(limiter.acquire(client, start).unwrap().remaining, 2);
+    }
+
+    #[test]
+    fn configured_routes_replace_the_global_limit() {
+        let config = Config {
+            rate_limit_routes: Some(vec!["/time=1:1".to_string()]),
+            ..Config::default()
+        };
+        let limiter = RateLimiter::from_config(&config);
+        let client = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+
+        let (route, time) = limiter.for_path("/time");
+        assert_eq!(route, "/time");
+        assert!(time.acquire(client, start).is_ok());
+        assert!(time.acquire(client, start).is_err());
+
+        let (route, batch) = limiter.for_path("/time/batch");
+        assert_eq!(route, "/time/batch");
+        let (route, _) = limiter.for_path("/v2/time/batch");
+        assert_eq!(route, "/time/batch");
+        assert_eq!(batch.burst, 5.0);
+        let (route, other) = limiter.for_path("/timezones");
+        assert_eq!(route, "default");
+        assert_eq!(other.acquire(client, start).unwrap().remaining, 19);
+    }
+
+    #[test]
+    fn tenants_share_one_bucket() {
+        let config = Config {
+            tenants: Some(vec![
+                "team-a;rate_limit=1:2".to_string(),
+                "team-b".to_string(),
+            ]),
+            ..Config::default()
+        };
+        let limiter = RateLimiter::from_config(&config);
+        let start = Instant::now();
+
+        let team_a = &limiter.tenants["team-a"];
+        assert!(team_a.acquire(Client::Tenant, start).is_ok());
+        assert!(team_a.acquire(Client::Tenant, start).is_ok());
+        assert!(team_a.acquire(Client::Tenant, start).is_err());
+        assert!(!limiter.tenants.contains_key("team-b"));
+    }
+}
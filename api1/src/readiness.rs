@@ -0,0 +1,90 @@
+//! Liveness and readiness probes. Readiness requires at least one API2
+//! instance to be ready; probe results are reused for `readiness_cache_ms`
+//! so frequent or concurrent probes cost one round of calls to API2.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use common::config::Config;
+use common::health::{self, Check};
+use futures_util::future::join_all;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::{probe_api2, AppState};
+
+const DEFAULT_CACHE_MS: u64 = 2000;
+
+/// Per-instance probe results: URL, status and any error.
+type Probes = Vec<(String, &'static str, Option<String>)>;
+
+pub struct ReadinessCache {
+    ttl: Duration,
+    /// Held across a probe so concurrent callers wait for its result.
+    last: Mutex<Option<(Instant, Probes)>>,
+}
+
+impl Default for ReadinessCache {
+    fn default() -> Self {
+        ReadinessCache::new(Duration::from_millis(DEFAULT_CACHE_MS))
+    }
+}
+
+impl ReadinessCache {
+    pub fn new(ttl: Duration) -> Self {
+        ReadinessCache {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Uses `readiness_cache_ms`, falling back to the default.
+    pub fn from_config(config: &Config) -> Self {
+        ReadinessCache::new(Duration::from_millis(
+            config.readiness_cache_ms.unwrap_or(DEFAULT_CACHE_MS),
+        ))
+    }
+}
+
+pub async fn get_live() -> Json<serde_json::Value> {
+    health::live("api1")
+}
+
+pub async fn get_ready(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let probes = api2_probes(&state).await;
+    let ok = probes.iter().any(|(_, _, error)| error.is_none());
+    let instances: Vec<_> = probes
+        .iter()
+        .map(|(url, status, error)| json!({ "url": url, "status": status, "error": error }))
+        .collect();
+    health::ready(
+        "api1",
+        &state.shutdown,
+        [
+            Check::new("api2", ok, json!({ "instances": instances })),
+            state.flags.check(),
+        ],
+    )
+}
+
+async fn api2_probes(state: &AppState) -> Probes {
+    let cache = &state.readiness;
+    let mut last = cache.last.lock().await;
+    if let Some((probed, probes)) = last.as_ref() {
+        if probed.elapsed() < cache.ttl {
+            return probes.clone();
+        }
+    }
+    let urls = state.backends.urls();
+    let results = join_all(
+        urls.iter()
+            .map(|url| probe_api2(&state.api2, state.api2.ready(url))),
+    )
+    .await;
+    let probes: Probes = urls
+        .iter()
+        .zip(results)
+        .map(|(url, (status, error))| (url.clone(), status, error))
+        .collect();
+    *last = Some((Instant::now(), probes.clone()));
+    probes
+}
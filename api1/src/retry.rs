@@ -0,0 +1,121 @@
+//! Retry with exponential backoff for calls to API2.
+
+use common::api2_client::SendError;
+use common::backoff::{backoff_delay, jitter_factor};
+use common::config::Config;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubling for each one after it.
+    pub base_delay: Duration,
+    /// Time after the first attempt started past which no retry is started.
+    /// Each attempt is still bounded by its own timeout.
+    pub deadline: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_BASE_DELAY,
+            deadline: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Uses `max_retries`, `retry_base_delay_ms` and `retry_deadline_ms`,
+    /// falling back to the defaults when unset.
+    pub fn from_config(config: &Config) -> Self {
+        RetryPolicy {
+            max_retries: config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            base_delay: config
+                .retry_base_delay_ms
+                .map_or(DEFAULT_BASE_DELAY, Duration::from_millis),
+            deadline: config.retry_deadline_ms.map(Duration::from_millis),
+        }
+    }
+
+    /// Runs `send` until it succeeds, retrying network errors and 5xx
+    /// responses. 4xx responses are returned immediately. Returns the last
+    /// result with the number of attempts made.
+    pub async fn send<F, Fut>(
+        &self,
+        request_id: &str,
+        send: F,
+    ) -> (Result<reqwest::Response, SendError>, u32)
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<reqwest::Response, SendError>>,
+    {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let result = send().await;
+            let retryable = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(_) => true,
+            };
+            if !retryable || attempt >= self.max_retries {
+                return (result, attempt + 1);
+            }
+
+            let delay = backoff_delay(attempt + 1, self.base_delay, jitter_factor());
+            if let Some(deadline) = self.deadline {
+                if started.elapsed() + delay >= deadline {
+                    warn!(
+                        request_id = %request_id,
+                        attempts = attempt + 1,
+                        deadline_ms = deadline.as_millis() as u64,
+                        "Retry deadline reached; giving up on API2"
+                    );
+                    return (result, attempt + 1);
+                }
+            }
+            attempt += 1;
+            match &result {
+                Ok(response) => warn!(
+                    request_id = %request_id,
+                    attempt = attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    status = %response.status(),
+                    "API2 returned server error, retrying"
+                ),
+                Err(e) => warn!(
+                    request_id = %request_id,
+                    attempt = attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    error = %e,
+                    "Failed to reach API2, retrying"
+                ),
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_starts_from_configured_base() {
+        let config = Config {
+            retry_base_delay_ms: Some(300),
+            ..Config::default()
+        };
+        let policy = RetryPolicy::from_config(&config);
+        assert_eq!(policy.max_retries, DEFAULT_MAX_RETRIES);
+        let delays: Vec<u128> = (1..=3)
+            .map(|attempt| backoff_delay(attempt, policy.base_delay, 0.0).as_millis())
+            .collect();
+        assert_eq!(delays, [300, 600, 1000]);
+    }
+}
@@ -0,0 +1,211 @@
+//! Clock skew between API1 and API2, for debugging timing problems that span
+//! both.
+//!
+//! Each sample is a `/time` call to API2 timed on API1's clock, read as an
+//! NTP exchange in which API2's timestamp stands for both its receive and
+//! send times. Assuming the call took as long each way, the one-way latency
+//! is half the round trip and API2's clock is ahead of API1's by its
+//! timestamp minus the midpoint of the call. The estimate from the fastest
+//! sample is the most trustworthy, since an uneven split of its round trip
+//! can hide the least error.
+
+use axum::{
+    extract::{Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::problem::{ErrorCode, Query};
+use common::trace_context::TraceContext;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tracing::info;
+
+use crate::{
+    api2_source, call_context, error_response, forward_to_api2, request_id_from, ApiError, AppState,
+};
+
+const DEFAULT_SAMPLES: usize = 5;
+const MAX_SAMPLES: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct SkewQuery {
+    samples: Option<String>,
+}
+
+/// One timed call to API2, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkewSample {
+    round_trip_ms: f64,
+    one_way_ms: f64,
+    /// How far API2's clock is ahead of API1's; negative when it is behind.
+    offset_ms: f64,
+    api2_timestamp: String,
+    source: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Summary {
+    min: f64,
+    avg: f64,
+    max: f64,
+}
+
+impl Summary {
+    fn of(values: impl Iterator<Item = f64> + Clone) -> Summary {
+        let count = values.clone().count() as f64;
+        Summary {
+            min: values.clone().fold(f64::INFINITY, f64::min),
+            avg: values.clone().sum::<f64>() / count,
+            max: values.fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkewReport {
+    samples: usize,
+    round_trip_ms: Summary,
+    one_way_ms: Summary,
+    offset_ms: Summary,
+    /// Offset from the sample with the shortest round trip.
+    best_offset_ms: f64,
+    measurements: Vec<SkewSample>,
+    request_id: String,
+}
+
+impl SkewReport {
+    fn new(measurements: Vec<SkewSample>, request_id: String) -> SkewReport {
+        let best = measurements
+            .iter()
+            .min_by(|a, b| a.round_trip_ms.total_cmp(&b.round_trip_ms))
+            .expect("at least one sample");
+        SkewReport {
+            samples: measurements.len(),
+            round_trip_ms: Summary::of(measurements.iter().map(|s| s.round_trip_ms)),
+            one_way_ms: Summary::of(measurements.iter().map(|s| s.one_way_ms)),
+            offset_ms: Summary::of(measurements.iter().map(|s| s.offset_ms)),
+            best_offset_ms: best.offset_ms,
+            measurements,
+            request_id,
+        }
+    }
+}
+
+/// The sample for a call sent at `sent` on API1's clock that took
+/// `round_trip_ms`, answered with API2's time `api2`.
+fn sample(
+    sent: DateTime<Utc>,
+    round_trip_ms: f64,
+    api2: DateTime<Utc>,
+    source: String,
+) -> SkewSample {
+    let one_way_ms = round_trip_ms / 2.0;
+    let sent_ms = sent.timestamp_micros() as f64 / 1000.0;
+    let api2_ms = api2.timestamp_micros() as f64 / 1000.0;
+    SkewSample {
+        round_trip_ms,
+        one_way_ms,
+        offset_ms: api2_ms - (sent_ms + one_way_ms),
+        api2_timestamp: api2.to_rfc3339_opts(SecondsFormat::Micros, true),
+        source,
+    }
+}
+
+pub async fn get_debug_skew(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<SkewQuery>,
+) -> Result<Json<SkewReport>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let samples = match params.samples.as_deref() {
+        None => DEFAULT_SAMPLES,
+        Some(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|samples| (1..=MAX_SAMPLES).contains(samples))
+            .ok_or_else(|| {
+                error_response(
+                    ErrorCode::InvalidRequest,
+                    format!("Invalid samples: {value} (expected 1-{MAX_SAMPLES})"),
+                    &request_id,
+                )
+            })?,
+    };
+
+    info!(
+        request_id = %request_id,
+        samples,
+        "Received clock skew request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let mut measurements = Vec::with_capacity(samples);
+    // One at a time, so the samples do not queue behind each other.
+    for _ in 0..samples {
+        let sent = Utc::now();
+        let started = Instant::now();
+        let (time, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+            state.api2.time(api2_url, &context, "UTC", None, None)
+        })
+        .await?;
+        let round_trip_ms = started.elapsed().as_secs_f64() * 1000.0;
+        let api2 = DateTime::parse_from_rfc3339(&time.timestamp).map_err(|_| {
+            error_response(
+                ErrorCode::Internal,
+                format!("API2 returned an unreadable timestamp: {}", time.timestamp),
+                &request_id,
+            )
+        })?;
+        measurements.push(sample(
+            sent,
+            round_trip_ms,
+            api2.with_timezone(&Utc),
+            api2_source(answered.backend),
+        ));
+    }
+
+    Ok(Json(SkewReport::new(measurements, request_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn estimates_offset_from_the_midpoint_of_each_call() {
+        let sent = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let at = |ms| sent + chrono::Duration::milliseconds(ms);
+        let report = SkewReport::new(
+            vec![
+                // API2 read its clock 5 ms into a 10 ms call: no skew.
+                sample(sent, 10.0, at(5), String::new()),
+                // 250 ms ahead, on a slower call.
+                sample(sent, 30.0, at(265), String::new()),
+            ],
+            String::new(),
+        );
+
+        assert_eq!(report.samples, 2);
+        assert_eq!(
+            report.round_trip_ms,
+            Summary {
+                min: 10.0,
+                avg: 20.0,
+                max: 30.0
+            }
+        );
+        assert_eq!(report.one_way_ms.max, 15.0);
+        assert_eq!(
+            report.offset_ms,
+            Summary {
+                min: 0.0,
+                avg: 125.0,
+                max: 250.0
+            }
+        );
+        assert_eq!(report.best_offset_ms, 0.0);
+    }
+}
@@ -0,0 +1,176 @@
+//! Response-time SLA checks over recently observed request latencies.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{Json, Response},
+};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{AppState, ErrorResponse};
+
+/// Number of most recent request latencies kept for percentile estimates.
+const WINDOW_SIZE: usize = 1024;
+
+/// Sliding window of recent request latencies, in milliseconds.
+#[derive(Default)]
+pub struct LatencyRecorder {
+    samples: Mutex<VecDeque<f64>>,
+}
+
+impl LatencyRecorder {
+    pub fn record(&self, latency_ms: f64) {
+        let mut samples = self.samples.lock().expect("latency lock poisoned");
+        if samples.len() == WINDOW_SIZE {
+            samples.pop_front();
+        }
+        samples.push_back(latency_ms);
+    }
+
+    pub(crate) fn sorted_samples(&self) -> Vec<f64> {
+        let mut samples: Vec<f64> = self
+            .samples
+            .lock()
+            .expect("latency lock poisoned")
+            .iter()
+            .copied()
+            .collect();
+        samples.sort_by(f64::total_cmp);
+        samples
+    }
+}
+
+/// Middleware recording the handling time of every request.
+pub async fn record_latency(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let response = next.run(request).await;
+    state
+        .latency
+        .record(started.elapsed().as_secs_f64() * 1000.0);
+    response
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlaQuery {
+    p50_target_ms: Option<String>,
+    p95_target_ms: Option<String>,
+    p99_target_ms: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PercentileCompliance {
+    target_ms: f64,
+    actual_ms: Option<f64>,
+    compliant: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlaReport {
+    p50: PercentileCompliance,
+    p95: PercentileCompliance,
+    p99: PercentileCompliance,
+    overall_compliant: bool,
+    sample_count: usize,
+}
+
+pub async fn get_api_response_time_sla(
+    State(state): State<AppState>,
+    Query(params): Query<SlaQuery>,
+) -> Result<Json<SlaReport>, ErrorResponse> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Received response time SLA request"
+    );
+
+    let targets = [
+        ("p50_target_ms", params.p50_target_ms, 10.0),
+        ("p95_target_ms", params.p95_target_ms, 50.0),
+        ("p99_target_ms", params.p99_target_ms, 200.0),
+    ];
+    let mut parsed = [0.0; 3];
+    for (slot, (name, value, default)) in parsed.iter_mut().zip(targets) {
+        *slot = match value {
+            None => default,
+            Some(value) => match value.parse::<f64>() {
+                Ok(target) if target.is_finite() && target >= 0.0 => target,
+                _ => {
+                    return Err(ErrorResponse::new(
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid {name}: {value} (expected milliseconds >= 0)"),
+                        &request_id,
+                    ))
+                }
+            },
+        };
+    }
+
+    let samples = state.latency.sorted_samples();
+    let check = |percentile: f64, target_ms: f64| {
+        let actual_ms = nearest_rank(&samples, percentile);
+        PercentileCompliance {
+            target_ms,
+            actual_ms,
+            // With no traffic observed yet there is nothing in breach.
+            compliant: actual_ms.is_none_or(|actual| actual <= target_ms),
+        }
+    };
+    let p50 = check(50.0, parsed[0]);
+    let p95 = check(95.0, parsed[1]);
+    let p99 = check(99.0, parsed[2]);
+
+    Ok(Json(SlaReport {
+        overall_compliant: p50.compliant && p95.compliant && p99.compliant,
+        p50,
+        p95,
+        p99,
+        sample_count: samples.len(),
+    }))
+}
+
+/// Nearest-rank percentile of an ascending sample, rounded to 0.01 ms.
+pub(crate) fn nearest_rank(sorted: &[f64], percentile: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    let value = sorted[rank.clamp(1, sorted.len()) - 1];
+    Some((value * 100.0).round() / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(nearest_rank(&samples, 50.0), Some(50.0));
+        assert_eq!(nearest_rank(&samples, 95.0), Some(95.0));
+        assert_eq!(nearest_rank(&samples, 99.0), Some(99.0));
+        assert_eq!(nearest_rank(&[7.3449], 99.0), Some(7.34));
+        assert_eq!(nearest_rank(&[], 50.0), None);
+    }
+
+    #[test]
+    fn window_keeps_most_recent_samples() {
+        let recorder = LatencyRecorder::default();
+        for latency in 0..WINDOW_SIZE + 10 {
+            recorder.record(latency as f64);
+        }
+        let samples = recorder.sorted_samples();
+        assert_eq!(samples.len(), WINDOW_SIZE);
+        assert_eq!(samples[0], 10.0);
+    }
+}
@@ -0,0 +1,69 @@
+//! Pass-through proxy for API2's Server-Sent Events time stream.
+
+use axum::{
+    body::Body,
+    extract::{Extension, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use common::problem::Query;
+use common::trace_context::TraceContext;
+use futures_util::stream;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{call_context, request_id_from, send_to_api2, ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TimeStreamQuery {
+    timezone: Option<String>,
+    interval_ms: Option<u64>,
+}
+
+/// Relays API2's event stream chunk by chunk without buffering. No
+/// `API2_TIMEOUT_MS` deadline applies, since the stream is open-ended.
+pub async fn get_time_stream(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimeStreamQuery>,
+) -> Result<Response, ApiError> {
+    let request_id = request_id_from(&headers);
+
+    info!(
+        request_id = %request_id,
+        timezone = ?params.timezone,
+        "Received time stream request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (upstream, _) = send_to_api2(&state, &request_id, None, |api2_url| {
+        state.api2.time_stream(
+            api2_url,
+            &context,
+            params.timezone.as_deref(),
+            params.interval_ms,
+        )
+    })
+    .await?;
+
+    let chunks = stream::unfold(upstream, |mut upstream| async move {
+        match upstream.chunk().await {
+            Ok(Some(chunk)) => Some((Ok(chunk), upstream)),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), upstream)),
+        }
+    });
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/event-stream"),
+            ),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response())
+}
@@ -0,0 +1,56 @@
+//! Proxy for API2's `POST /time`, which takes its options as a JSON body.
+//!
+//! Options are validated by API2 and its `422` relayed; nothing is cached,
+//! since `offset_seconds` and `locale` make most requests unique.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::trace_context::TraceContext;
+use common::{TimeRequest, TimeResponse};
+use tracing::info;
+
+use crate::{
+    api2_source, call_context, error_for_status, forward_to_api2, request_id_from, ApiError,
+    AppState, UPSTREAM_ATTEMPTS_HEADER,
+};
+
+pub async fn post_time(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    body: Result<Json<TimeRequest>, JsonRejection>,
+) -> Result<(HeaderMap, Json<TimeResponse>), ApiError> {
+    let request_id = request_id_from(&headers);
+    // Malformed JSON is a 400; well-formed JSON of the wrong shape a 422.
+    let Json(request) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        timezone = ?request.timezone,
+        locale = ?request.locale,
+        offset_seconds = ?request.offset_seconds,
+        "Received time request body"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (time, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_post(api2_url, &context, &request)
+    })
+    .await?;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(UPSTREAM_ATTEMPTS_HEADER, answered.attempts.into());
+    Ok((
+        response_headers,
+        Json(TimeResponse {
+            request_id,
+            source: api2_source(answered.backend),
+            ..time
+        }),
+    ))
+}
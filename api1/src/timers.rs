@@ -0,0 +1,135 @@
+//! Proxy for API2's `/timer` routes.
+//!
+//! A timer exists only on the API2 instance that created it, so the ID API1
+//! hands out is prefixed with that instance's index, e.g. `1-<uuid>`, and
+//! reads and stops go to that instance alone, without failover.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use common::problem::ErrorCode;
+use common::trace_context::TraceContext;
+use common::{CreateTimerRequest, TimerResponse};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    call_context, error_for_status, error_response, forward_to_api2, forward_to_instance,
+    request_id_from, ApiError, AppState,
+};
+
+pub async fn post_timer(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    body: Result<Json<CreateTimerRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<TimerResponse>), ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(request) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        name = %request.name,
+        "Received timer request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (timer, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.timer_create(api2_url, &context, &request)
+    })
+    .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(proxied(timer, answered.backend, request_id)),
+    ))
+}
+
+pub async fn get_timer(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let (backend, timer_id) =
+        parse_id(&state, &id).ok_or_else(|| unknown_timer(&id, &request_id))?;
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (timer, _) = forward_to_instance(&state, &request_id, backend, |api2_url| {
+        state.api2.timer(api2_url, &context, &timer_id)
+    })
+    .await?;
+    Ok(Json(proxied(timer, backend, request_id)))
+}
+
+pub async fn delete_timer(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let request_id = request_id_from(&headers);
+    let (backend, timer_id) =
+        parse_id(&state, &id).ok_or_else(|| unknown_timer(&id, &request_id))?;
+
+    info!(
+        request_id = %request_id,
+        timer_id = %id,
+        "Received timer stop request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (timer, _) = forward_to_instance(&state, &request_id, backend, |api2_url| {
+        state.api2.timer_stop(api2_url, &context, &timer_id)
+    })
+    .await?;
+    Ok(Json(proxied(timer, backend, request_id)))
+}
+
+/// Splits an ID from [`proxied`] into the instance index and API2's ID, or
+/// `None` for an ID that could not have been handed out.
+fn parse_id(state: &AppState, id: &str) -> Option<(usize, Uuid)> {
+    let (backend, timer_id) = id.split_once('-')?;
+    let backend = backend.parse::<usize>().ok()?;
+    (backend < state.backends.urls().len()).then_some((backend, Uuid::parse_str(timer_id).ok()?))
+}
+
+fn unknown_timer(id: &str, request_id: &str) -> ApiError {
+    error_response(
+        ErrorCode::NotFound,
+        format!("Unknown timer: {id}"),
+        request_id,
+    )
+}
+
+fn proxied(timer: TimerResponse, backend: usize, request_id: String) -> TimerResponse {
+    TimerResponse {
+        id: format!("{backend}-{}", timer.id),
+        request_id,
+        ..timer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_ids_it_could_have_issued() {
+        let state = AppState::new("http://127.0.0.1:1".to_string());
+        let uuid = Uuid::new_v4();
+        assert_eq!(parse_id(&state, &format!("0-{uuid}")), Some((0, uuid)));
+        for id in [
+            format!("1-{uuid}"),
+            format!("x-{uuid}"),
+            uuid.to_string(),
+            "0-../time".to_string(),
+        ] {
+            assert_eq!(parse_id(&state, &id), None, "{id}");
+        }
+    }
+}
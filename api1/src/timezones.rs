@@ -0,0 +1,77 @@
+//! Proxy for API2's timezone discovery endpoint.
+
+use axum::{
+    extract::{Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::openapi::{object, reference, register, typed, Components, Schema};
+use common::problem::Query;
+use common::trace_context::TraceContext;
+use common::TimezoneList;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{api2_source, call_context, forward_to_api2, request_id_from, ApiError, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TimezonesQuery {
+    prefix: Option<String>,
+}
+
+/// API2's list with the `source` marker API1 adds to every proxied body.
+#[derive(Debug, Serialize)]
+pub struct ProxiedTimezoneList {
+    #[serde(flatten)]
+    list: TimezoneList,
+    source: String,
+}
+
+impl Schema for ProxiedTimezoneList {
+    const NAME: &'static str = "ProxiedTimezoneList";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                reference::<TimezoneList>(),
+                object(
+                    "Which API2 instance answered.",
+                    &[("source", typed("string", "e.g. `api1->api2[0]`."), true)],
+                ),
+            ],
+        })
+    }
+
+    fn references(components: &mut Components) {
+        register::<TimezoneList>(components);
+    }
+}
+
+pub async fn get_timezones(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimezonesQuery>,
+) -> Result<Json<ProxiedTimezoneList>, ApiError> {
+    let request_id = request_id_from(&headers);
+
+    info!(
+        request_id = %request_id,
+        prefix = params.prefix.as_deref().unwrap_or(""),
+        "Received timezones request"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (list, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state
+            .api2
+            .timezones(api2_url, &context, params.prefix.as_deref())
+    })
+    .await?;
+
+    Ok(Json(ProxiedTimezoneList {
+        list,
+        source: api2_source(answered.backend),
+    }))
+}
@@ -0,0 +1,95 @@
+//! Proxy for API2's `/v2/time`, which adds daylight saving details to the
+//! `/v1/time` answer.
+//!
+//! Always sent over HTTP, whatever `API2_TRANSPORT` is, and neither cached
+//! nor answered locally while API2 is down: those apply to `/v1/time` only.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::format::TimestampFormat;
+use common::problem::{ErrorCode, Query};
+use common::trace_context::TraceContext;
+use common::{TimeRequest, TimeResponseV2};
+use tracing::info;
+
+use crate::{
+    api2_source, call_context, error_for_status, error_response, forward_to_api2, request_id_from,
+    Answered, ApiError, AppState, TimeQuery, UPSTREAM_ATTEMPTS_HEADER,
+};
+
+pub async fn get_time(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    Query(params): Query<TimeQuery>,
+) -> Result<(HeaderMap, Json<TimeResponseV2>), ApiError> {
+    let request_id = request_id_from(&headers);
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Received v2 time request"
+    );
+
+    TimestampFormat::from_param(params.format.as_deref())
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (time, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_v2(
+            api2_url,
+            &context,
+            &timezone,
+            params.format.as_deref(),
+            params.locale.as_deref(),
+        )
+    })
+    .await?;
+    Ok(proxied(time, request_id, answered))
+}
+
+pub async fn post_time(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    headers: HeaderMap,
+    body: Result<Json<TimeRequest>, JsonRejection>,
+) -> Result<(HeaderMap, Json<TimeResponseV2>), ApiError> {
+    let request_id = request_id_from(&headers);
+    let Json(request) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        timezone = ?request.timezone,
+        "Received v2 time request body"
+    );
+
+    let context = call_context(&request_id, &trace, &headers);
+    let (time, answered) = forward_to_api2(&state, &request_id, |api2_url| {
+        state.api2.time_post_v2(api2_url, &context, &request)
+    })
+    .await?;
+    Ok(proxied(time, request_id, answered))
+}
+
+fn proxied(
+    time: TimeResponseV2,
+    request_id: String,
+    answered: Answered,
+) -> (HeaderMap, Json<TimeResponseV2>) {
+    let mut headers = HeaderMap::new();
+    headers.insert(UPSTREAM_ATTEMPTS_HEADER, answered.attempts.into());
+    (
+        headers,
+        Json(TimeResponseV2 {
+            request_id,
+            source: api2_source(answered.backend),
+            ..time
+        }),
+    )
+}
@@ -0,0 +1,210 @@
+//! Tunnels `/time/ws` WebSocket connections through to API2.
+//!
+//! API1 opens its own connection to an API2 instance and repeats the
+//! client's handshake there, keeping the client's `Sec-WebSocket-Key` so
+//! API2's answer is valid for the client too. Once API2 switches protocols,
+//! API1 completes the client's upgrade and copies bytes in both directions
+//! until either side closes; frames are never inspected.
+
+use axum::{
+    extract::{Extension, Request, State},
+    response::Response,
+};
+use common::api2_client::Api2Client;
+use common::problem::ErrorCode;
+use common::trace_context::{self, TraceContext};
+use common::{websocket, REQUEST_ID_HEADER};
+use hyper_util::rt::TokioIo;
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, UnixStream};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    circuit_open, error_response, now_ms, request_id_from, ApiError, AppState,
+    UPSTREAM_ERRORS_TOTAL,
+};
+
+/// A connection to API2, plain or TLS.
+trait Upstream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Upstream for T {}
+
+pub async fn get_time_ws(
+    State(state): State<AppState>,
+    Extension(trace): Extension<TraceContext>,
+    mut request: Request,
+) -> Result<Response, ApiError> {
+    let request_id = request_id_from(request.headers());
+
+    info!(request_id = %request_id, "Received time WebSocket request");
+
+    let Some(key) = websocket::upgrade_key(request.headers()) else {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            "Expected a WebSocket upgrade request",
+            &request_id,
+        ));
+    };
+
+    let mut upstream = open_tunnel(&state, &request_id, &trace, &key).await?;
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        let mut client = match on_upgrade.await {
+            Ok(upgraded) => TokioIo::new(upgraded),
+            Err(e) => return debug!(request_id = %request_id, error = %e, "Client upgrade failed"),
+        };
+        match tokio::io::copy_bidirectional(&mut client, &mut upstream).await {
+            Ok((to_api2, to_client)) => info!(
+                request_id = %request_id,
+                to_api2,
+                to_client,
+                "WebSocket tunnel closed"
+            ),
+            Err(e) => debug!(request_id = %request_id, error = %e, "WebSocket tunnel failed"),
+        }
+    });
+
+    Ok(websocket::switching_protocols(&key))
+}
+
+/// Connects to API2 and completes its handshake within `API2_TIMEOUT_MS`,
+/// failing over between instances on connection errors like other calls.
+async fn open_tunnel(
+    state: &AppState,
+    request_id: &str,
+    trace: &TraceContext,
+    key: &str,
+) -> Result<Box<dyn Upstream>, ApiError> {
+    let upstream_error = |kind: &str| {
+        state
+            .metrics
+            .increment(&UPSTREAM_ERRORS_TOTAL, &[("kind", kind)]);
+    };
+
+    let Some(permit) = state.breaker.try_acquire(now_ms()) else {
+        return Err(circuit_open(state, request_id));
+    };
+
+    let opened = tokio::time::timeout(state.api2_timeout, async {
+        let (mut upstream, api2_url) = connect_with_failover(state, request_id).await?;
+        let path = format!("{}/time/ws", api2_url.path().trim_end_matches('/'));
+        let traceparent = trace.traceparent();
+        let handshake = websocket::request_upgrade(
+            &mut upstream,
+            api2_url.authority(),
+            &path,
+            key,
+            &[
+                (REQUEST_ID_HEADER, request_id),
+                (trace_context::TRACEPARENT_HEADER, &traceparent),
+            ],
+        )
+        .await?;
+        Ok::<_, io::Error>((upstream, handshake))
+    })
+    .await;
+
+    match opened {
+        Ok(Ok((upstream, handshake)))
+            if handshake.status == 101
+                && handshake.accept.as_deref() == Some(&websocket::accept_key(key)) =>
+        {
+            permit.success();
+            Ok(upstream)
+        }
+        Ok(Ok((_, handshake))) => {
+            if handshake.status >= 500 {
+                permit.failure(now_ms());
+            } else {
+                permit.success();
+            }
+            upstream_error("status");
+            error!(
+                request_id = %request_id,
+                status = handshake.status,
+                "API2 refused the WebSocket upgrade"
+            );
+            Err(error_response(
+                ErrorCode::UpstreamBadResponse,
+                format!("API2 returned status: {}", handshake.status),
+                request_id,
+            ))
+        }
+        Ok(Err(e)) => {
+            permit.failure(now_ms());
+            upstream_error("connect");
+            error!(request_id = %request_id, error = %e, "Failed to connect to API2");
+            Err(error_response(
+                ErrorCode::UpstreamUnavailable,
+                "Failed to connect to API2",
+                request_id,
+            ))
+        }
+        Err(_) => {
+            permit.failure(now_ms());
+            upstream_error("timeout");
+            let timeout_ms = state.api2_timeout.as_millis();
+            error!(
+                request_id = %request_id,
+                timeout_ms = timeout_ms as u64,
+                "API2 WebSocket handshake timed out"
+            );
+            Err(error_response(
+                ErrorCode::UpstreamTimeout,
+                format!("Upstream timeout after {timeout_ms}ms"),
+                request_id,
+            ))
+        }
+    }
+}
+
+/// Opens a connection to the first reachable API2 instance in balancer order.
+async fn connect_with_failover(
+    state: &AppState,
+    request_id: &str,
+) -> io::Result<(Box<dyn Upstream>, reqwest::Url)> {
+    let mut order = state.backends.order(now_ms()).peekable();
+    loop {
+        let backend = order.next().expect("at least one API2 instance");
+        let api2_url = &state.backends.urls()[backend];
+        info!(
+            request_id = %request_id,
+            api2_url = %api2_url,
+            "Opening WebSocket tunnel to API2"
+        );
+        let url = reqwest::Url::parse(api2_url)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let connected = connect(&url, &state.api2).await;
+        state.backends.record(backend, connected.is_ok(), now_ms());
+        match connected {
+            Err(e) if order.peek().is_some() => warn!(
+                request_id = %request_id,
+                api2_url = %api2_url,
+                error = %e,
+                "API2 instance unreachable; failing over"
+            ),
+            result => return result.map(|upstream| (upstream, url)),
+        }
+    }
+}
+
+async fn connect(url: &reqwest::Url, api2: &Api2Client) -> io::Result<Box<dyn Upstream>> {
+    if let Some(path) = api2.unix_socket() {
+        return Ok(Box::new(UnixStream::connect(path).await?));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "API2 URL has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    let tcp = TcpStream::connect((host, port)).await?;
+    if url.scheme() != "https" {
+        return Ok(Box::new(tcp));
+    }
+    let connector = api2.tls_connector().map_err(io::Error::other)?;
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, tcp)
+        .await
+        .map_err(io::Error::other)?;
+    Ok(Box::new(tls))
+}
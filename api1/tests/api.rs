@@ -0,0 +1,669 @@
+//! Requests driven through API1's router without a listener, against an
+//! API2 mocked by a local server.
+
+use api1::{app, AppState};
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::{header, HeaderMap, Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use common::config::Config;
+use common::limits::{parse_request_timeout, REQUEST_TIMEOUT_HEADER};
+use common::problem::{ErrorCode, PROBLEM_JSON};
+use common::{
+    BatchTimeError, BatchTimeItem, BatchTimeRequest, BatchTimeResponse, CronRequest, CronResponse,
+    Elapsed, ErrorResponse, TimeQuery, TimeResponse, TimerResponse, TimezoneList,
+    REQUEST_ID_HEADER,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::Service;
+
+/// Serves `api2` on a local port and returns its URL.
+async fn mock_api2(api2: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, api2).await.unwrap() });
+    url
+}
+
+/// API1 calling `api2_url` once per request, with a short timeout.
+fn api1(api2_url: String) -> Router {
+    app(AppState::from_config(&Config {
+        api2_url: Some(api2_url),
+        max_retries: Some(0),
+        timeout_ms: Some(200),
+        ..Config::default()
+    }))
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(app: Router, uri: &str) -> (StatusCode, T) {
+    let request = Request::get(uri)
+        .header(REQUEST_ID_HEADER, "req-1")
+        .body(Body::empty())
+        .unwrap();
+    send_json(app, request).await
+}
+
+async fn send_json<T: serde::de::DeserializeOwned>(
+    mut app: Router,
+    request: Request<Body>,
+) -> (StatusCode, T) {
+    // Router is always ready, so poll_ready can be skipped.
+    let response = app.call(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+/// API2's `/time` for the zones this test knows about, echoing the
+/// request ID API1 forwards.
+async fn api2_time(headers: HeaderMap, Query(query): Query<TimeQuery>) -> Response {
+    let request_id = headers[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+    let timezone = query.timezone.unwrap_or_default();
+    let offset = match timezone.as_str() {
+        "UTC" => 0,
+        "Asia/Bangkok" => 25200,
+        _ => {
+            let error = ErrorResponse::new(
+                ErrorCode::InvalidTimezone,
+                format!("Invalid timezone: {timezone}"),
+                &request_id,
+            );
+            return error.into_response();
+        }
+    };
+    Json(TimeResponse {
+        timestamp: "2025-03-05T14:30:00+07:00".to_string(),
+        timezone,
+        request_id,
+        source: "api2-service".to_string(),
+        utc_offset_seconds: Some(offset),
+        utc_offset_label: None,
+        display: None,
+        format: Some("rfc3339".to_string()),
+        degraded: None,
+        clock_offset_ms: None,
+    })
+    .into_response()
+}
+
+#[tokio::test]
+async fn proxies_time_from_api2() {
+    let api1 = api1(mock_api2(Router::new().route("/time", get(api2_time))).await);
+
+    let (status, time): (_, TimeResponse) =
+        get_json(api1.clone(), "/v1/time?timezone=Asia/Bangkok").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.timezone, "Asia/Bangkok");
+    assert_eq!(time.utc_offset_seconds, Some(25200));
+    assert_eq!(time.source, "api1->api2[0]");
+    assert_eq!(time.request_id, "req-1");
+
+    let (status, time): (_, TimeResponse) = get_json(api1, "/time").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.timezone, "UTC");
+}
+
+#[tokio::test]
+async fn relays_invalid_timezones_and_formats_as_400() {
+    let api1 = api1(mock_api2(Router::new().route("/time", get(api2_time))).await);
+
+    let (status, error): (_, ErrorResponse) =
+        get_json(api1.clone(), "/v1/time?timezone=Mars/Olympus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid timezone: Mars/Olympus");
+    assert_eq!(error.code, ErrorCode::InvalidTimezone);
+    assert_eq!(error.request_id, "req-1");
+
+    // Rejected by API1 without calling API2.
+    let (status, error): (_, ErrorResponse) =
+        get_json(api1.clone(), "/v1/time?timezone=UTC&format=bogus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.code, ErrorCode::InvalidRequest);
+    assert!(error.error.contains("bogus"), "{}", error.error);
+
+    let request = Request::get("/v1/time?timezone=Mars/Olympus")
+        .body(Body::empty())
+        .unwrap();
+    let mut api1 = api1;
+    // Router is always ready, so poll_ready can be skipped.
+    let response = api1.call(request).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+}
+
+#[tokio::test]
+async fn maps_upstream_failures_to_gateway_errors() {
+    let failing = mock_api2(
+        Router::new().route("/time", get(|| async { StatusCode::INTERNAL_SERVER_ERROR })),
+    )
+    .await;
+    let (status, error): (_, ErrorResponse) = get_json(api1(failing), "/v1/time").await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(error.code, ErrorCode::UpstreamBadResponse);
+    assert_eq!(
+        error.error,
+        "API2 returned status: 500 Internal Server Error"
+    );
+
+    let slow = mock_api2(Router::new().route(
+        "/time",
+        get(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            StatusCode::OK
+        }),
+    ))
+    .await;
+    let (status, error): (_, ErrorResponse) = get_json(api1(slow), "/v1/time").await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(error.error, "Upstream timeout after 200ms");
+    assert_eq!(error.code, ErrorCode::UpstreamTimeout);
+
+    // Nothing listens on the port once the listener is dropped.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let (status, error): (_, ErrorResponse) = get_json(api1(closed), "/v1/time").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.error, "Failed to connect to API2");
+    assert_eq!(error.code, ErrorCode::UpstreamUnavailable);
+    assert_eq!(error.request_id, "req-1");
+}
+
+/// API2's `/timer` routes for one instance, naming every timer after
+/// `instance` so tests can tell which instance answered.
+fn api2_timers(instance: &'static str) -> Router {
+    let timer = move |id: String, running: bool| {
+        Json(TimerResponse {
+            id,
+            name: instance.to_string(),
+            started_at: "2025-03-05T07:30:00.000Z".to_string(),
+            stopped_at: None,
+            running,
+            elapsed: Elapsed {
+                milliseconds: 0,
+                seconds: 0.0,
+                minutes: 0.0,
+                hours: 0.0,
+                iso8601: "PT0S".to_string(),
+            },
+            request_id: String::new(),
+        })
+    };
+    Router::new()
+        .route(
+            "/timer",
+            post(move || async move {
+                let id = "6f1c2a0e-8d9b-4c3e-9f7a-1b2c3d4e5f60".to_string();
+                (StatusCode::CREATED, timer(id, true))
+            }),
+        )
+        .route(
+            "/timer/:id",
+            get(move |Path(id): Path<String>| async move { timer(id, true) })
+                .delete(move |Path(id): Path<String>| async move { timer(id, false) }),
+        )
+}
+
+#[tokio::test]
+async fn pins_timers_to_the_instance_that_created_them() {
+    let api1 = app(AppState::from_config(&Config {
+        api2_urls: Some(vec![
+            mock_api2(api2_timers("first")).await,
+            mock_api2(api2_timers("second")).await,
+        ]),
+        max_retries: Some(0),
+        ..Config::default()
+    }));
+    let create = || {
+        Request::post("/v1/timer")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name": "deploy"}"#))
+            .unwrap()
+    };
+
+    // Round-robin places consecutive timers on different instances.
+    let (status, first): (_, TimerResponse) = send_json(api1.clone(), create()).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, second): (_, TimerResponse) = send_json(api1.clone(), create()).await;
+    assert_eq!(first.id, "0-6f1c2a0e-8d9b-4c3e-9f7a-1b2c3d4e5f60");
+    assert_eq!(second.id, "1-6f1c2a0e-8d9b-4c3e-9f7a-1b2c3d4e5f60");
+
+    for _ in 0..2 {
+        let (status, timer): (_, TimerResponse) =
+            get_json(api1.clone(), &format!("/v1/timer/{}", second.id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(timer.name, "second");
+        assert_eq!(timer.id, second.id);
+        assert_eq!(timer.request_id, "req-1");
+    }
+
+    let request = Request::delete(format!("/timer/{}", first.id))
+        .body(Body::empty())
+        .unwrap();
+    let (status, stopped): (_, TimerResponse) = send_json(api1.clone(), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stopped.name, "first");
+    assert!(!stopped.running);
+
+    let (status, error): (_, ErrorResponse) =
+        get_json(api1, "/v1/timer/2-6f1c2a0e-8d9b-4c3e-9f7a-1b2c3d4e5f60").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        error.error,
+        "Unknown timer: 2-6f1c2a0e-8d9b-4c3e-9f7a-1b2c3d4e5f60"
+    );
+}
+
+/// API2's `/cron/next`, firing hourly for `@hourly` and rejecting anything
+/// else.
+async fn api2_cron(headers: HeaderMap, Json(request): Json<CronRequest>) -> Response {
+    let request_id = headers[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+    if request.expression != "@hourly" {
+        let error = ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            "Invalid cron expression: expected 5 fields, found 1",
+            &request_id,
+        );
+        return error.into_response();
+    }
+    Json(CronResponse {
+        expression: request.expression,
+        timezone: request.timezone.unwrap_or_else(|| "UTC".to_string()),
+        next: vec!["2025-03-05T08:00:00+00:00".to_string(); request.count.unwrap_or(5)],
+        request_id,
+    })
+    .into_response()
+}
+
+#[tokio::test]
+async fn relays_cron_previews_and_their_errors() {
+    let api1 = api1(mock_api2(Router::new().route("/cron/next", post(api2_cron))).await);
+    let preview = |body: &'static str| {
+        Request::post("/v1/cron/next")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, cron): (_, CronResponse) = send_json(
+        api1.clone(),
+        preview(r#"{"expression": "@hourly", "timezone": "Asia/Bangkok", "count": 2}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cron.timezone, "Asia/Bangkok");
+    assert_eq!(cron.next.len(), 2);
+    assert_eq!(cron.request_id, "req-1");
+
+    let (status, error): (_, ErrorResponse) =
+        send_json(api1.clone(), preview(r#"{"expression": "hourly"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid cron expression: expected 5 fields, found 1"
+    );
+
+    // Rejected by API1 without calling API2.
+    let (status, _): (_, ErrorResponse) =
+        send_json(api1, preview(r#"{"expression": "@hourly", "every": 2}"#)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+/// API2's `/cron/next`, answering with the `X-Request-Timeout` budget API1
+/// gave it, after a pause for `@slow`.
+async fn api2_budget(headers: HeaderMap, Json(request): Json<CronRequest>) -> Json<CronResponse> {
+    if request.expression == "@slow" {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    Json(CronResponse {
+        expression: request.expression,
+        timezone: "UTC".to_string(),
+        next: vec![headers[REQUEST_TIMEOUT_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string()],
+        request_id: String::new(),
+    })
+}
+
+#[tokio::test]
+async fn forwards_the_remaining_budget_to_api2() {
+    let api1 = api1(mock_api2(Router::new().route("/cron/next", post(api2_budget))).await);
+    let preview = |expression: &str, budget: Option<&'static str>| {
+        let mut request = Request::post("/v1/cron/next")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1");
+        if let Some(budget) = budget {
+            request = request.header(REQUEST_TIMEOUT_HEADER, budget);
+        }
+        let body = format!(r#"{{"expression": "{expression}"}}"#);
+        request.body(Body::from(body)).unwrap()
+    };
+    let forwarded = |cron: CronResponse| parse_request_timeout(&cron.next[0]).unwrap();
+
+    // Without a budget, API2 gets the per-call timeout.
+    let (_, cron): (_, CronResponse) = send_json(api1.clone(), preview("@hourly", None)).await;
+    assert_eq!(forwarded(cron), Duration::from_millis(200));
+
+    // A shorter budget is passed on, less the time API1 has spent.
+    let (status, cron): (_, CronResponse) =
+        send_json(api1.clone(), preview("@hourly", Some("150ms"))).await;
+    assert_eq!(status, StatusCode::OK);
+    let budget = forwarded(cron);
+    assert!(budget <= Duration::from_millis(150), "{budget:?}");
+    assert!(budget >= Duration::from_millis(100), "{budget:?}");
+
+    let (status, _): (_, ErrorResponse) =
+        send_json(api1.clone(), preview("@slow", Some("50ms"))).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+
+    let (status, error): (_, ErrorResponse) =
+        send_json(api1, preview("@hourly", Some("soon"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid X-Request-Timeout: soon (expected e.g. 500ms or 2s)"
+    );
+}
+
+#[tokio::test]
+async fn replays_responses_to_repeated_idempotency_keys() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&calls);
+    let api2 = Router::new().route(
+        "/cron/next",
+        post(move |headers: HeaderMap, request: Json<CronRequest>| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            api2_cron(headers, request)
+        }),
+    );
+    let mut api1 = api1(mock_api2(api2).await);
+    let preview = |key: &'static str, count: usize| {
+        let body = format!(r#"{{"expression": "@hourly", "count": {count}}}"#);
+        Request::post("/v1/cron/next")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .header("idempotency-key", key)
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    // Router is always ready, so poll_ready can be skipped.
+    let first = api1.call(preview("order-1", 2)).await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first = axum::body::to_bytes(first.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    let retry = api1.call(preview("order-1", 2)).await.unwrap();
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    let retry = axum::body::to_bytes(retry.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(retry, first);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let (status, error): (_, ErrorResponse) = send_json(api1.clone(), preview("order-1", 3)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        error.error,
+        "Idempotency-Key order-1 was already used for a different request"
+    );
+
+    let (status, _): (_, CronResponse) = send_json(api1, preview("order-2", 3)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn changes_payload_logging_and_rejects_bad_log_filters() {
+    let api1 = api1(mock_api2(Router::new()).await);
+    let put = |uri: &str, body: &'static str| {
+        Request::put(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, settings): (_, serde_json::Value) =
+        get_json(api1.clone(), "/admin/payload-logging").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(settings["enabled"], false);
+    let (status, settings): (_, serde_json::Value) = send_json(
+        api1.clone(),
+        put("/admin/payload-logging", r#"{"enabled": true}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(settings["enabled"], true);
+
+    let (status, error): (_, ErrorResponse) = send_json(
+        api1.clone(),
+        put("/admin/log-level", r#"{"filter": "info,api1=loud"}"#),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.code, ErrorCode::InvalidRequest);
+    // The test process has no subscriber installed by `logging::init`.
+    let (status, error): (_, ErrorResponse) = get_json(api1.clone(), "/admin/log-level").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.code, ErrorCode::Unavailable);
+    // Nor is AUDIT_LOG_PATH set.
+    let (status, error): (_, ErrorResponse) = get_json(api1, "/admin/audit").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.code, ErrorCode::Unavailable);
+}
+
+#[tokio::test]
+async fn answers_304_to_polls_of_an_unchanged_timezone_list() {
+    let api2 = Router::new().route(
+        "/timezones",
+        get(|| async {
+            Json(TimezoneList {
+                timezones: vec!["Asia/Bangkok".to_string(), "UTC".to_string()],
+                count: 2,
+                offsets: Vec::new(),
+            })
+        }),
+    );
+    let mut api1 = api1(mock_api2(api2).await);
+    let poll = |etag: Option<&str>| {
+        let request = Request::get("/v1/timezones");
+        match etag {
+            Some(etag) => request.header(header::IF_NONE_MATCH, etag),
+            None => request,
+        }
+        .body(Body::empty())
+        .unwrap()
+    };
+
+    // Router is always ready, so poll_ready can be skipped.
+    let first = api1.call(poll(None)).await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    let etag = first.headers()[header::ETAG].to_str().unwrap().to_string();
+
+    let again = api1.call(poll(Some(&etag))).await.unwrap();
+    assert_eq!(again.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(again.headers()[header::ETAG], etag.as_str());
+    let stale = api1.call(poll(Some("W/\"stale\""))).await.unwrap();
+    assert_eq!(stale.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn stamps_clock_synchronisation_samples_without_api2() {
+    let api1 = api1("http://127.0.0.1:9".to_string());
+    let (status, sample): (_, serde_json::Value) =
+        get_json(api1, "/v1/time/clock-synchronisation").await;
+    assert_eq!(status, StatusCode::OK);
+    let stamp =
+        |key: &str| chrono::DateTime::parse_from_rfc3339(sample[key].as_str().unwrap()).unwrap();
+    assert!(stamp("t1") <= stamp("t2"));
+    assert!(sample["formula"].as_str().unwrap().starts_with("offset = "));
+    assert_eq!(sample["instructions"].as_array().unwrap().len(), 6);
+}
+
+#[tokio::test]
+async fn opens_the_circuit_after_consecutive_failures() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let failing = mock_api2(Router::new().route(
+        "/time",
+        get(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { StatusCode::INTERNAL_SERVER_ERROR }
+        }),
+    ))
+    .await;
+    let api1 = app(AppState::from_config(&Config {
+        api2_url: Some(failing),
+        max_retries: Some(0),
+        timeout_ms: Some(200),
+        circuit_breaker_threshold: Some(2),
+        circuit_breaker_reset_secs: Some(45),
+        ..Config::default()
+    }));
+
+    for _ in 0..2 {
+        let (status, _): (_, ErrorResponse) = get_json(api1.clone(), "/v1/time").await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+    }
+    let request = Request::get("/v1/time").body(Body::empty()).unwrap();
+    let response = api1.clone().call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "45");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let (_, health): (_, serde_json::Value) = get_json(api1, "/health").await;
+    assert_eq!(health["circuit_breaker"], "open");
+}
+
+/// API2's `/time/batch`, knowing only UTC.
+async fn api2_batch(Json(batch): Json<BatchTimeRequest>) -> Json<BatchTimeResponse> {
+    let items: Vec<BatchTimeItem> = batch
+        .timezones
+        .into_iter()
+        .map(|timezone| match timezone.as_str() {
+            "UTC" => BatchTimeItem::Time(TimeResponse {
+                timestamp: "2025-03-05T07:30:00+00:00".to_string(),
+                timezone,
+                request_id: "req-1".to_string(),
+                source: "api2-service".to_string(),
+                utc_offset_seconds: Some(0),
+                utc_offset_label: None,
+                display: None,
+                format: None,
+                degraded: None,
+                clock_offset_ms: None,
+            }),
+            _ => BatchTimeItem::Error(BatchTimeError {
+                error: format!("Invalid timezone: {timezone}"),
+                timezone,
+            }),
+        })
+        .collect();
+    Json(BatchTimeResponse {
+        items,
+        results: Vec::new(),
+        errors: Vec::new(),
+        request_id: "req-1".to_string(),
+    })
+}
+
+#[tokio::test]
+async fn relays_batches_in_order_and_rejects_oversized_ones() {
+    let api1 = api1(mock_api2(Router::new().route("/time/batch", post(api2_batch))).await);
+    let batch = |body: String| {
+        Request::post("/v1/time/batch")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, response): (_, serde_json::Value) = send_json(
+        api1.clone(),
+        batch(r#"["Mars/Olympus", "UTC"]"#.to_string()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        response["items"][0]["error"],
+        "Invalid timezone: Mars/Olympus"
+    );
+    assert_eq!(response["items"][1]["timezone"], "UTC");
+    assert_eq!(response["items"][1]["source"], "api1->api2[0]");
+
+    let oversized = serde_json::to_string(&vec!["UTC"; 51]).unwrap();
+    let (status, error): (_, ErrorResponse) = send_json(api1, batch(oversized)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Batch of 51 timezones exceeds the limit of 50");
+}
+
+#[tokio::test]
+async fn serves_repeated_time_requests_from_the_cache() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let api2_url = mock_api2(Router::new().route(
+        "/time",
+        get(move |headers: HeaderMap, query: Query<TimeQuery>| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            api2_time(headers, query)
+        }),
+    ))
+    .await;
+    let api1 = app(AppState::from_config(&Config {
+        api2_url: Some(api2_url),
+        max_retries: Some(0),
+        cache_ttl_ms: Some(60_000),
+        ..Config::default()
+    }));
+    let time = |uri: &str| {
+        Request::get(uri)
+            .header(REQUEST_ID_HEADER, "req-2")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, first): (_, TimeResponse) =
+        send_json(api1.clone(), time("/v1/time?timezone=UTC")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first.source, "api1->api2[0]");
+    let response = api1
+        .clone()
+        .call(time("/v1/time?timezone=UTC"))
+        .await
+        .unwrap();
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=60");
+    assert!(response.headers().contains_key(header::AGE));
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let cached: TimeResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(cached.source, "api1->cache");
+    assert_eq!(cached.request_id, "req-2");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let (_, bypassed): (_, TimeResponse) =
+        send_json(api1.clone(), time("/v1/time?timezone=UTC&cache=bypass")).await;
+    assert_eq!(bypassed.source, "api1->api2[0]");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let (status, error): (_, ErrorResponse) =
+        send_json(api1, time("/v1/time?timezone=UTC&cache=sometimes")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid cache mode: sometimes (expected bypass)"
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,268 @@
+//! Operator endpoints changing API2's behaviour at runtime, protected by an
+//! API key.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::audit::{AuditPage, AuditQuery, AuditQueryError};
+use common::chaos::{Chaos, ChaosSettings};
+use common::flags::{FlagStates, FlagUpdate};
+use common::logging::{self, FilterError, LogFilter};
+use common::payload_log::{PayloadLogSettings, PayloadLogUpdate};
+use common::problem::{ErrorCode, Query};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::auth::require_api_key;
+use crate::{error_response, json_body, request_id_from, ApiError, AppState};
+
+pub async fn get_payload_logging(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<PayloadLogSettings>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    Ok(Json(state.payload_log.settings()))
+}
+
+pub async fn put_payload_logging(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<PayloadLogUpdate>, JsonRejection>,
+) -> Result<Json<PayloadLogSettings>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let update = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        enabled = update.enabled,
+        "Payload logging changed"
+    );
+    state.payload_log.set_enabled(update.enabled);
+    Ok(Json(state.payload_log.settings()))
+}
+
+pub async fn get_log_level(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<LogFilter>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let filter = logging::filter().ok_or(FilterError::NotInstalled);
+    filter
+        .map(|filter| Json(LogFilter { filter }))
+        .map_err(|e| filter_error(e, &request_id))
+}
+
+pub async fn put_log_level(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<LogFilter>, JsonRejection>,
+) -> Result<Json<LogFilter>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let update = json_body(body, &request_id)?;
+
+    let filter = logging::set_filter(&update.filter).map_err(|e| filter_error(e, &request_id))?;
+    info!(request_id = %request_id, filter = %filter, "Log filter changed");
+    Ok(Json(LogFilter { filter }))
+}
+
+pub async fn get_audit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditPage>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let audit = state.audit.as_ref().ok_or_else(|| {
+        error_response(
+            ErrorCode::Unavailable,
+            "Audit logging is disabled; set AUDIT_LOG_PATH",
+            &request_id,
+        )
+    })?;
+    audit.query(query).await.map(Json).map_err(|e| {
+        let code = match e {
+            AuditQueryError::Invalid(_) => ErrorCode::InvalidRequest,
+            AuditQueryError::Read(_) => ErrorCode::Internal,
+        };
+        error_response(code, e.to_string(), &request_id)
+    })
+}
+
+pub async fn get_flags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FlagStates>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    Ok(Json(state.flags.states()))
+}
+
+pub async fn put_flags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<FlagUpdate>, JsonRejection>,
+) -> Result<Json<FlagStates>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let update = json_body(body, &request_id)?;
+
+    let states = state
+        .flags
+        .update(&update)
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+    info!(request_id = %request_id, flags = ?update, "Feature flags changed");
+    Ok(Json(states))
+}
+
+pub async fn get_chaos(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ChaosSettings>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    Ok(Json(chaos(&state, &request_id)?.settings()))
+}
+
+pub async fn put_chaos(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<ChaosSettings>, JsonRejection>,
+) -> Result<Json<ChaosSettings>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let chaos = chaos(&state, &request_id)?;
+    let update = json_body(body, &request_id)?;
+
+    info!(request_id = %request_id, rules = ?update.rules, "Chaos rules changed");
+    chaos
+        .replace(update)
+        .map(Json)
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))
+}
+
+fn chaos<'a>(state: &'a AppState, request_id: &str) -> Result<&'a Chaos, ApiError> {
+    state.chaos.as_deref().ok_or_else(|| {
+        error_response(
+            ErrorCode::Unavailable,
+            "Chaos mode is disabled; set CHAOS_ENABLED",
+            request_id,
+        )
+    })
+}
+
+fn filter_error(error: FilterError, request_id: &str) -> ApiError {
+    let code = match error {
+        FilterError::Invalid(_) => ErrorCode::InvalidRequest,
+        FilterError::NotInstalled => ErrorCode::Unavailable,
+    };
+    error_response(code, error.to_string(), request_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::auth::API_KEY_HEADER;
+    use common::flags::FlagState;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn toggles_payload_logging_with_an_api_key() {
+        let state = AppState {
+            api_keys: Arc::new(vec!["key-1".to_string()]),
+            ..crate::tests::test_state()
+        };
+        let update = || Ok(Json(PayloadLogUpdate { enabled: true }));
+
+        let error = put_payload_logging(State(state.clone()), HeaderMap::new(), update())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+        assert!(!state.payload_log.is_enabled());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "key-1".parse().unwrap());
+        let Json(settings) = put_payload_logging(State(state.clone()), headers.clone(), update())
+            .await
+            .unwrap();
+        assert!(settings.enabled);
+        let Json(settings) = get_payload_logging(State(state), headers).await.unwrap();
+        assert!(settings.enabled);
+        assert!(settings.redacted.contains(&"callback_url".to_string()));
+    }
+
+    #[tokio::test]
+    async fn switches_feature_flags_with_an_api_key() {
+        let state = AppState {
+            api_keys: Arc::new(vec!["key-1".to_string()]),
+            ..crate::tests::test_state()
+        };
+        let update = |name: &str| Ok(Json(FlagUpdate::from([(name.to_string(), FlagState::Off)])));
+
+        let error = put_flags(State(state.clone()), HeaderMap::new(), update("alarms"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "key-1".parse().unwrap());
+        let Json(states) = put_flags(State(state.clone()), headers.clone(), update("alarms"))
+            .await
+            .unwrap();
+        assert_eq!(states["alarms"], FlagState::Off);
+        let error = put_flags(State(state.clone()), headers.clone(), update("teleport"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+        let Json(states) = get_flags(State(state), headers).await.unwrap();
+        assert_eq!(states["alarms"], FlagState::Off);
+        assert_eq!(states["time_ws"], FlagState::On);
+    }
+
+    #[tokio::test]
+    async fn replaces_chaos_rules_only_when_enabled() {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "key-1".parse().unwrap());
+        let state = AppState {
+            api_keys: Arc::new(vec!["key-1".to_string()]),
+            ..crate::tests::test_state()
+        };
+        let error = get_chaos(State(state.clone()), headers.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unavailable);
+
+        let state = AppState {
+            chaos: Some(Arc::new(Chaos::new(Vec::new(), state.metrics.clone()))),
+            ..state
+        };
+        let update = |status: u16| {
+            Ok(Json(
+                serde_json::from_value::<ChaosSettings>(serde_json::json!({
+                    "rules": [{"route": "/time", "fault": "error", "status": status, "probability": 0.5}]
+                }))
+                .unwrap(),
+            ))
+        };
+        let error = put_chaos(State(state.clone()), HeaderMap::new(), update(503))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unauthorized);
+        let error = put_chaos(State(state.clone()), headers.clone(), update(404))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+
+        let Json(settings) = put_chaos(State(state.clone()), headers.clone(), update(503))
+            .await
+            .unwrap();
+        assert_eq!(settings.rules.len(), 1);
+        let Json(settings) = get_chaos(State(state), headers).await.unwrap();
+        assert_eq!(settings.rules[0].route, "/time");
+    }
+}
@@ -0,0 +1,632 @@
+//! `/alarms`: one-shot webhooks fired at a given time.
+//!
+//! `POST /alarms` schedules a `POST` of an [`AlarmFired`] body to
+//! `callback_url` at `at`, or `after_secs` from now. Each pending alarm has
+//! its own task sleeping until it is due; cancelling one aborts its task.
+//! The callback is attempted once, and the alarm is forgotten whatever the
+//! outcome.
+//!
+//! With `ALARMS_PATH` set, pending alarms are written to that file as JSON
+//! on every change and scheduled again at startup; any that fell due while
+//! the service was down fire as soon as it is back. The routes need an API
+//! key, since an alarm makes this service call an arbitrary URL.
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::config::Config;
+use common::openapi::{array, object, reference, register, typed, Components, Schema};
+use common::problem::ErrorCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::auth::require_api_key;
+use crate::{error_response, json_body, request_id_from, ApiError, AppState};
+
+const DEFAULT_MAX_COUNT: usize = 1000;
+/// How far ahead an alarm may be set.
+const MAX_HORIZON_DAYS: i64 = 366;
+const MAX_LABEL_CHARS: usize = 64;
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body of a `POST /alarms` request: exactly one of `at` and `after_secs`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateAlarmRequest {
+    /// RFC 3339 time to fire at.
+    at: Option<String>,
+    /// Seconds from now to fire after.
+    after_secs: Option<u64>,
+    callback_url: String,
+    label: Option<String>,
+}
+
+/// A pending alarm, as listed and as persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alarm {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub label: Option<String>,
+    pub at: DateTime<Utc>,
+    pub callback_url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body `POST`ed to an alarm's callback URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmFired {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub label: Option<String>,
+    pub at: DateTime<Utc>,
+    pub fired_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlarmList {
+    count: usize,
+    alarms: Vec<Alarm>,
+}
+
+impl Schema for CreateAlarmRequest {
+    const NAME: &'static str = "CreateAlarmRequest";
+
+    fn schema() -> Value {
+        let mut schema = object(
+            "An alarm to set: exactly one of `at` and `after_secs`.",
+            &[
+                ("at", typed("string", "RFC 3339 time to fire at."), false),
+                (
+                    "after_secs",
+                    typed("integer", "Seconds from now to fire after."),
+                    false,
+                ),
+                (
+                    "callback_url",
+                    typed("string", "http(s) URL to `POST` to when due."),
+                    true,
+                ),
+                ("label", typed("string", "1 to 64 characters."), false),
+            ],
+        );
+        schema["additionalProperties"] = false.into();
+        schema
+    }
+}
+
+impl Schema for Alarm {
+    const NAME: &'static str = "Alarm";
+
+    fn schema() -> Value {
+        object(
+            "A pending alarm.",
+            &[
+                ("id", typed("string", "Alarm ID."), true),
+                ("label", typed("string", "Label given at creation."), false),
+                ("at", typed("string", "RFC 3339 time it fires at."), true),
+                ("callback_url", typed("string", "URL it will call."), true),
+                (
+                    "created_at",
+                    typed("string", "RFC 3339 time it was set."),
+                    true,
+                ),
+            ],
+        )
+    }
+}
+
+impl Schema for AlarmList {
+    const NAME: &'static str = "AlarmList";
+
+    fn schema() -> Value {
+        object(
+            "Pending alarms, soonest first.",
+            &[
+                ("count", typed("integer", "Number of alarms."), true),
+                ("alarms", array(reference::<Alarm>()), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<Alarm>(components);
+    }
+}
+
+struct Pending {
+    alarm: Alarm,
+    task: Option<AbortHandle>,
+}
+
+pub struct AlarmStore {
+    max_count: usize,
+    /// Where pending alarms are persisted, if anywhere.
+    path: Option<PathBuf>,
+    http: reqwest::Client,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl AlarmStore {
+    pub fn new(max_count: usize, path: Option<PathBuf>) -> Self {
+        let alarms = path.as_ref().map_or_else(Vec::new, |path| load(path));
+        AlarmStore {
+            max_count,
+            path,
+            http: reqwest::Client::builder()
+                .timeout(CALLBACK_TIMEOUT)
+                .build()
+                .expect("HTTP client"),
+            pending: Mutex::new(
+                alarms
+                    .into_iter()
+                    .map(|alarm| (alarm.id.clone(), Pending { alarm, task: None }))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Uses `alarm_max_count` and `alarms_path`, falling back to 1000
+    /// alarms kept in memory only. Alarms loaded from the file wait for
+    /// [`AlarmStore::resume`].
+    pub fn from_config(config: &Config) -> Self {
+        AlarmStore::new(
+            config.alarm_max_count.unwrap_or(DEFAULT_MAX_COUNT),
+            config.alarms_path.as_ref().map(PathBuf::from),
+        )
+    }
+
+    /// Schedules the alarms loaded at startup.
+    pub fn resume(self: &Arc<Self>) {
+        let mut pending = self.lock();
+        if !pending.is_empty() {
+            info!(count = pending.len(), "Resuming persisted alarms");
+        }
+        for entry in pending.values_mut() {
+            entry.task = Some(self.spawn(&entry.alarm));
+        }
+    }
+
+    /// Adds `alarm` and schedules it, unless `max_count` are already pending.
+    pub fn schedule(self: &Arc<Self>, alarm: Alarm) -> Result<Alarm, usize> {
+        let mut pending = self.lock();
+        if pending.len() >= self.max_count {
+            return Err(self.max_count);
+        }
+        let task = Some(self.spawn(&alarm));
+        pending.insert(
+            alarm.id.clone(),
+            Pending {
+                alarm: alarm.clone(),
+                task,
+            },
+        );
+        self.persist(&pending);
+        Ok(alarm)
+    }
+
+    /// Pending alarms, soonest first.
+    pub fn list(&self) -> Vec<Alarm> {
+        let mut alarms: Vec<Alarm> = self
+            .lock()
+            .values()
+            .map(|entry| entry.alarm.clone())
+            .collect();
+        alarms.sort_by_key(|alarm| alarm.at);
+        alarms
+    }
+
+    pub fn get(&self, id: &str) -> Option<Alarm> {
+        self.lock().get(id).map(|entry| entry.alarm.clone())
+    }
+
+    /// Removes a pending alarm so it never fires.
+    pub fn cancel(&self, id: &str) -> Option<Alarm> {
+        let mut pending = self.lock();
+        let entry = pending.remove(id)?;
+        if let Some(task) = entry.task {
+            task.abort();
+        }
+        self.persist(&pending);
+        Some(entry.alarm)
+    }
+
+    fn spawn(self: &Arc<Self>, alarm: &Alarm) -> AbortHandle {
+        let store = Arc::clone(self);
+        let id = alarm.id.clone();
+        let delay = (alarm.at - Utc::now()).to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            // Gone if cancelled while this task was waking up.
+            let Some(alarm) = store.take(&id) else {
+                return;
+            };
+            store.fire(alarm).await;
+        })
+        .abort_handle()
+    }
+
+    fn take(&self, id: &str) -> Option<Alarm> {
+        let mut pending = self.lock();
+        let entry = pending.remove(id)?;
+        self.persist(&pending);
+        Some(entry.alarm)
+    }
+
+    async fn fire(&self, alarm: Alarm) {
+        let body = AlarmFired {
+            id: alarm.id.clone(),
+            label: alarm.label,
+            at: alarm.at,
+            fired_at: Utc::now(),
+        };
+        match self.http.post(&alarm.callback_url).json(&body).send().await {
+            Ok(response) if response.status().is_success() => info!(
+                alarm_id = %alarm.id,
+                status = %response.status(),
+                "Alarm callback delivered"
+            ),
+            Ok(response) => warn!(
+                alarm_id = %alarm.id,
+                status = %response.status(),
+                "Alarm callback rejected"
+            ),
+            Err(e) => warn!(
+                alarm_id = %alarm.id,
+                error = %e,
+                "Alarm callback failed"
+            ),
+        }
+    }
+
+    /// Rewrites the file with `pending`, through a temporary file so a crash
+    /// mid-write leaves the previous contents.
+    fn persist(&self, pending: &HashMap<String, Pending>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let alarms: Vec<&Alarm> = pending.values().map(|entry| &entry.alarm).collect();
+        let temporary = path.with_extension("tmp");
+        let written = serde_json::to_vec_pretty(&alarms)
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(&temporary, json))
+            .and_then(|()| std::fs::rename(&temporary, path));
+        if let Err(e) = written {
+            warn!(path = %path.display(), error = %e, "Failed to persist alarms");
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Pending>> {
+        self.pending.lock().expect("alarms lock poisoned")
+    }
+}
+
+/// The alarms persisted at `path`; none when it does not exist yet or
+/// cannot be read.
+fn load(path: &std::path::Path) -> Vec<Alarm> {
+    match std::fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+            warn!(path = %path.display(), error = %e, "Ignoring unreadable alarms file");
+            Vec::new()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Failed to read alarms file");
+            Vec::new()
+        }
+    }
+}
+
+/// Validates `request` into an alarm created at `now`.
+fn new_alarm(request: CreateAlarmRequest, now: DateTime<Utc>) -> Result<Alarm, String> {
+    let at = match (request.at, request.after_secs) {
+        (Some(at), None) => DateTime::parse_from_rfc3339(&at)
+            .map_err(|e| format!("Invalid at: {e}"))?
+            .with_timezone(&Utc),
+        (None, Some(secs)) => i64::try_from(secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|after| now.checked_add_signed(after))
+            .ok_or("after_secs is too large")?,
+        _ => return Err("Give exactly one of at and after_secs".to_string()),
+    };
+    if at <= now {
+        return Err("The alarm time must be in the future".to_string());
+    }
+    if at > now + chrono::Duration::days(MAX_HORIZON_DAYS) {
+        return Err(format!(
+            "The alarm time must be within {MAX_HORIZON_DAYS} days"
+        ));
+    }
+
+    let callback = reqwest::Url::parse(&request.callback_url)
+        .map_err(|e| format!("Invalid callback_url: {e}"))?;
+    if !matches!(callback.scheme(), "http" | "https") {
+        return Err("callback_url must be an http or https URL".to_string());
+    }
+
+    let label = request.label.map(|label| label.trim().to_string());
+    if label
+        .as_ref()
+        .is_some_and(|label| label.is_empty() || label.chars().count() > MAX_LABEL_CHARS)
+    {
+        return Err(format!("label must be 1 to {MAX_LABEL_CHARS} characters"));
+    }
+
+    Ok(Alarm {
+        id: Uuid::new_v4().to_string(),
+        label,
+        at,
+        callback_url: callback.to_string(),
+        created_at: now,
+    })
+}
+
+fn unknown_alarm(id: &str, request_id: &str) -> ApiError {
+    error_response(
+        ErrorCode::NotFound,
+        format!("Unknown alarm: {id}"),
+        request_id,
+    )
+}
+
+pub async fn post_alarm(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<CreateAlarmRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<Alarm>), ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let request = json_body(body, &request_id)?;
+    let alarm = new_alarm(request, Utc::now())
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+
+    let alarm = state.alarms.schedule(alarm).map_err(|max_count| {
+        error_response(
+            ErrorCode::CapacityExceeded,
+            format!("Alarm limit of {max_count} reached"),
+            &request_id,
+        )
+    })?;
+    info!(
+        request_id = %request_id,
+        alarm_id = %alarm.id,
+        at = %alarm.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "Scheduled alarm"
+    );
+    Ok((StatusCode::CREATED, Json(alarm)))
+}
+
+pub async fn get_alarms(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AlarmList>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let alarms = state.alarms.list();
+    Ok(Json(AlarmList {
+        count: alarms.len(),
+        alarms,
+    }))
+}
+
+pub async fn get_alarm(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Alarm>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    state
+        .alarms
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| unknown_alarm(&id, &request_id))
+}
+
+pub async fn delete_alarm(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Alarm>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+    let alarm = state
+        .alarms
+        .cancel(&id)
+        .ok_or_else(|| unknown_alarm(&id, &request_id))?;
+    info!(request_id = %request_id, alarm_id = %id, "Cancelled alarm");
+    Ok(Json(alarm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::Router;
+    use tokio::sync::mpsc;
+
+    fn request(
+        at: Option<&str>,
+        after_secs: Option<u64>,
+        callback_url: &str,
+    ) -> CreateAlarmRequest {
+        CreateAlarmRequest {
+            at: at.map(str::to_string),
+            after_secs,
+            callback_url: callback_url.to_string(),
+            label: None,
+        }
+    }
+
+    /// A local callback receiver and the channel its deliveries arrive on.
+    async fn receiver() -> (String, mpsc::UnboundedReceiver<AlarmFired>) {
+        let (sender, received) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/hook",
+            post(move |Json(fired): Json<AlarmFired>| async move {
+                sender.send(fired).unwrap();
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn due_in(millis: i64, callback_url: &str) -> Alarm {
+        let now = Utc::now();
+        Alarm {
+            id: Uuid::new_v4().to_string(),
+            label: Some("test".to_string()),
+            at: now + chrono::Duration::milliseconds(millis),
+            callback_url: callback_url.to_string(),
+            created_at: now,
+        }
+    }
+
+    #[test]
+    fn validates_the_time_and_callback() {
+        let now = DateTime::parse_from_rfc3339("2025-03-05T07:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let alarm = new_alarm(request(None, Some(90), "http://hooks.local/a"), now).unwrap();
+        assert_eq!(alarm.at.to_rfc3339(), "2025-03-05T07:31:30+00:00");
+        let alarm = new_alarm(
+            request(Some("2025-03-05T15:00:00+07:00"), None, "https://x.test"),
+            now,
+        )
+        .unwrap();
+        assert_eq!(alarm.at.to_rfc3339(), "2025-03-05T08:00:00+00:00");
+
+        for (request, error) in [
+            (request(None, None, "http://x.test"), "Give exactly one"),
+            (request(Some("soon"), None, "http://x.test"), "Invalid at"),
+            (request(None, Some(0), "http://x.test"), "in the future"),
+            (request(None, Some(400 * 86_400), "http://x.test"), "within"),
+            (request(None, Some(60), "ftp://x.test"), "http or https"),
+            (request(None, Some(60), "not a url"), "Invalid callback_url"),
+        ] {
+            let message = new_alarm(request, now).unwrap_err();
+            assert!(message.contains(error), "{message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn fires_due_alarms_but_not_cancelled_ones() {
+        let (url, mut received) = receiver().await;
+        let store = Arc::new(AlarmStore::new(10, None));
+        let cancelled = store.schedule(due_in(50, &url)).unwrap();
+        let fired = store.schedule(due_in(100, &url)).unwrap();
+        assert_eq!(store.list(), vec![cancelled.clone(), fired.clone()]);
+        assert_eq!(store.cancel(&cancelled.id), Some(cancelled));
+
+        let delivered = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivered.id, fired.id);
+        assert_eq!(delivered.label.as_deref(), Some("test"));
+        assert!(delivered.fired_at >= fired.at);
+        assert!(store.list().is_empty());
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn persists_pending_alarms_across_restarts() {
+        let path = std::env::temp_dir().join(format!("alarms-{}.json", Uuid::new_v4()));
+        let (url, mut received) = receiver().await;
+
+        let store = Arc::new(AlarmStore::new(1, Some(path.clone())));
+        let alarm = store.schedule(due_in(200, &url)).unwrap();
+        assert_eq!(store.schedule(due_in(200, &url)), Err(1));
+        // Stand-in for the process exiting before the alarm was due.
+        store
+            .lock()
+            .get_mut(&alarm.id)
+            .unwrap()
+            .task
+            .take()
+            .unwrap()
+            .abort();
+
+        let restarted = Arc::new(AlarmStore::new(1, Some(path.clone())));
+        assert_eq!(restarted.list(), vec![alarm.clone()]);
+        restarted.resume();
+        let delivered = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivered.id, alarm.id);
+        assert!(AlarmStore::new(1, Some(path.clone())).list().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn requires_an_api_key() {
+        let state = AppState {
+            api_keys: Arc::new(vec!["key-1".to_string()]),
+            ..crate::tests::test_state()
+        };
+        let app = crate::app(state, &common::cors::CorsPolicy::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("{base}/v1/alarms"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .post(format!("{base}/v1/alarms"))
+            .header("x-api-key", "key-1")
+            .json(&serde_json::json!({
+                "after_secs": 3600,
+                "callback_url": "http://127.0.0.1:9/hook",
+                "label": "standup",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let alarm: Alarm = response.json().await.unwrap();
+
+        let response = client
+            .get(format!("{base}/v1/alarms"))
+            .header("x-api-key", "key-1")
+            .send()
+            .await
+            .unwrap();
+        let list: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(list["count"], 1);
+        assert_eq!(list["alarms"][0]["label"], "standup");
+
+        let delete = |id: String| {
+            client
+                .delete(format!("{base}/v1/alarms/{id}"))
+                .header("x-api-key", "key-1")
+                .send()
+        };
+        assert_eq!(
+            delete(alarm.id.clone()).await.unwrap().status(),
+            reqwest::StatusCode::OK
+        );
+        assert_eq!(
+            delete(alarm.id).await.unwrap().status(),
+            reqwest::StatusCode::NOT_FOUND
+        );
+    }
+}
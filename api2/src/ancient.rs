@@ -0,0 +1,146 @@
+//! Julian ↔ proleptic Gregorian calendar conversion for historical dates.
+
+use axum::response::Json;
+use chrono::NaiveDate;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+/// Julian Day Number of 0001-01-01 in the proleptic Gregorian calendar, minus one.
+pub(crate) const GREGORIAN_CE_JDN_OFFSET: i64 = 1_721_425;
+
+/// The first day of the Gregorian calendar, 1582-10-15 (Julian 1582-10-05).
+const GREGORIAN_REFORM_JDN: i64 = 2_299_161;
+
+#[derive(Debug, Deserialize)]
+pub struct AncientCalendarQuery {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AncientCalendarDate {
+    julian_date: String,
+    proleptic_gregorian: String,
+    calendar: &'static str,
+    days_difference: i64,
+}
+
+pub async fn get_ancient_calendar(
+    Query(params): Query<AncientCalendarQuery>,
+) -> Result<Json<AncientCalendarDate>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        year = params.year,
+        month = params.month,
+        day = params.day,
+        "Processing ancient calendar request"
+    );
+
+    julian_to_gregorian(params.year, params.month, params.day)
+        .map(Json)
+        .map_err(|message| error_response(ErrorCode::InvalidRequest, message, &request_id))
+}
+
+/// Converts a Julian calendar date to its proleptic Gregorian equivalent.
+///
+/// `calendar` names the calendar in civil use on that day: "Julian" before the
+/// 1582 reform and "Gregorian" from 1582-10-15 onwards.
+fn julian_to_gregorian(year: i32, month: u32, day: u32) -> Result<AncientCalendarDate, String> {
+    if !(1..=9999).contains(&year) {
+        return Err(format!("year must be between 1 and 9999, got {year}"));
+    }
+    if !(1..=12).contains(&month) {
+        return Err(format!("month must be between 1 and 12, got {month}"));
+    }
+    let month_length = julian_month_length(year, month);
+    if !(1..=month_length).contains(&day) {
+        return Err(format!(
+            "day must be between 1 and {month_length} for Julian {year:04}-{month:02}"
+        ));
+    }
+
+    let jdn = julian_calendar_to_jdn(year, month, day);
+    let gregorian = NaiveDate::from_num_days_from_ce_opt((jdn - GREGORIAN_CE_JDN_OFFSET) as i32)
+        .ok_or_else(|| "date is outside the supported range".to_string())?;
+
+    Ok(AncientCalendarDate {
+        julian_date: format!("{year:04}-{month:02}-{day:02}"),
+        proleptic_gregorian: gregorian.format("%Y-%m-%d").to_string(),
+        calendar: if jdn < GREGORIAN_REFORM_JDN {
+            "Julian"
+        } else {
+            "Gregorian"
+        },
+        days_difference: jdn - gregorian_calendar_to_jdn(year, month, day),
+    })
+}
+
+fn julian_month_length(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Julian Day Number of a Julian calendar date.
+fn julian_calendar_to_jdn(year: i32, month: u32, day: u32) -> i64 {
+    let (y, m) = shifted_year_month(year, month);
+    i64::from(day) + (153 * m + 2) / 5 + 365 * y + y.div_euclid(4) - 32_083
+}
+
+/// Julian Day Number of the same year/month/day label read as Gregorian. Used
+/// arithmetically, so it also accepts labels such as Julian-only leap days.
+fn gregorian_calendar_to_jdn(year: i32, month: u32, day: u32) -> i64 {
+    let (y, m) = shifted_year_month(year, month);
+    i64::from(day) + (153 * m + 2) / 5 + 365 * y + y.div_euclid(4) - y.div_euclid(100)
+        + y.div_euclid(400)
+        - 32_045
+}
+
+/// Shifts the year to start in March so the leap day falls at its end.
+fn shifted_year_month(year: i32, month: u32) -> (i64, i64) {
+    let a = i64::from(month <= 2);
+    (i64::from(year) + 4800 - a, i64::from(month) + 12 * a - 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battle_of_hastings() {
+        let date = julian_to_gregorian(1066, 10, 14).unwrap();
+        assert_eq!(date.julian_date, "1066-10-14");
+        assert_eq!(date.proleptic_gregorian, "1066-10-20");
+        assert_eq!(date.calendar, "Julian");
+        assert_eq!(date.days_difference, 6);
+    }
+
+    #[test]
+    fn gregorian_reform() {
+        let last_julian = julian_to_gregorian(1582, 10, 4).unwrap();
+        assert_eq!(last_julian.proleptic_gregorian, "1582-10-14");
+        assert_eq!(last_julian.calendar, "Julian");
+        assert_eq!(last_julian.days_difference, 10);
+
+        let first_gregorian = julian_to_gregorian(1582, 10, 5).unwrap();
+        assert_eq!(first_gregorian.proleptic_gregorian, "1582-10-15");
+        assert_eq!(first_gregorian.calendar, "Gregorian");
+    }
+
+    #[test]
+    fn julian_only_leap_day() {
+        let date = julian_to_gregorian(1100, 2, 29).unwrap();
+        assert_eq!(date.proleptic_gregorian, "1100-03-07");
+        assert!(julian_to_gregorian(1101, 2, 29).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+//! API key checks for operator-only endpoints.
+
+use axum::http::HeaderMap;
+use common::auth::{is_valid_key, API_KEY_HEADER};
+use common::problem::ErrorCode;
+use tracing::warn;
+
+use crate::{error_response, ApiError};
+
+/// Rejects the request with `401 Unauthorized` unless it carries one of
+/// `api_keys` in the `X-Api-Key` header. With no keys configured every
+/// request is rejected.
+pub fn require_api_key(
+    headers: &HeaderMap,
+    api_keys: &[String],
+    request_id: &str,
+) -> Result<(), ApiError> {
+    let provided = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    if provided.is_some_and(|provided| is_valid_key(api_keys, provided)) {
+        Ok(())
+    } else {
+        warn!(
+            request_id = %request_id,
+            key_present = provided.is_some(),
+            "Rejected request with missing or invalid API key"
+        );
+        Err(error_response(
+            ErrorCode::Unauthorized,
+            "Missing or invalid API key",
+            request_id,
+        ))
+    }
+}
@@ -0,0 +1,90 @@
+//! Current time in several timezones from one request.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use common::format::TimestampFormat;
+use common::problem::ErrorCode;
+use common::{BatchTimeError, BatchTimeItem, BatchTimeRequest, BatchTimeResponse};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{current_time_in, error_response, json_body, request_id_from, ApiError, AppState};
+
+pub async fn post_time_batch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<BatchTimeRequest>, JsonRejection>,
+) -> Result<Json<BatchTimeResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let batch = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        count = batch.timezones.len(),
+        "Processing batch time request"
+    );
+
+    if batch.timezones.len() > state.max_batch_size {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Batch of {} timezones exceeds the limit of {}",
+                batch.timezones.len(),
+                state.max_batch_size
+            ),
+            &request_id,
+        ));
+    }
+
+    let tasks: Vec<_> = batch
+        .timezones
+        .into_iter()
+        .map(|timezone| {
+            let (name, request_id) = (timezone.clone(), request_id.clone());
+            let task = tokio::task::spawn(async move {
+                current_time_in(&name, &TimestampFormat::Rfc3339, &request_id)
+            });
+            (timezone, task)
+        })
+        .collect();
+
+    let mut items = Vec::new();
+    for (timezone, task) in tasks {
+        items.push(match task.await {
+            Ok(Ok(local_time)) => {
+                state.record_timezone_use(&timezone);
+                BatchTimeItem::Time(local_time.into_response(timezone, request_id.clone()))
+            }
+            Ok(Err(failure)) => BatchTimeItem::Error(BatchTimeError {
+                timezone,
+                error: failure.error,
+            }),
+            Err(e) => {
+                error!(request_id = %request_id, error = %e, "Batch task failed");
+                BatchTimeItem::Error(BatchTimeError {
+                    timezone,
+                    error: "Internal error".to_string(),
+                })
+            }
+        });
+    }
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for item in &items {
+        match item {
+            BatchTimeItem::Time(time) => results.push(time.clone()),
+            BatchTimeItem::Error(failure) => errors.push(failure.clone()),
+        }
+    }
+
+    Ok(Json(BatchTimeResponse {
+        items,
+        results,
+        errors,
+        request_id,
+    }))
+}
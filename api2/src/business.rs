@@ -0,0 +1,507 @@
+//! Business hours and working-day arithmetic.
+//!
+//! A working day is a Monday to Friday that no selected holiday calendar
+//! lists. Calendars implement [`HolidayCalendar`]; `th` (Thai public
+//! holidays) is built in, and explicit `YYYY-MM-DD` dates can be mixed in.
+
+use axum::{http::HeaderMap, response::Json};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveTime, SecondsFormat, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, parse_timezone, request_id_from, ApiError};
+
+/// Largest `add_days` in either direction, about four years of working days.
+const MAX_BUSINESS_DAYS: i64 = 1000;
+/// Most explicit holiday dates one request may list.
+const MAX_EXPLICIT_HOLIDAYS: usize = 100;
+/// Years either side of today whose holidays are loaded, enough to cover
+/// `MAX_BUSINESS_DAYS` of arithmetic.
+const YEARS_LOADED: i32 = 5;
+
+/// A source of non-working days.
+pub trait HolidayCalendar: Send + Sync {
+    /// The holidays falling in `year`, with their names.
+    fn holidays(&self, year: i32) -> Vec<(NaiveDate, String)>;
+}
+
+/// Looks up a built-in calendar by the name used in `holidays=`.
+fn builtin_calendar(name: &str) -> Option<Box<dyn HolidayCalendar>> {
+    match name.to_ascii_lowercase().as_str() {
+        "th" => Some(Box::new(ThaiHolidays)),
+        _ => None,
+    }
+}
+
+const BUILTIN_CALENDARS: &[&str] = &["th"];
+
+/// Thai public holidays, with a substitution day on the next working day
+/// for each holiday falling on a weekend. The Buddhist holidays follow the
+/// lunar calendar and are listed for the years in [`THAI_LUNAR_HOLIDAYS`];
+/// other years get the fixed-date holidays only. Special holidays declared
+/// by the cabinet at short notice are not included.
+pub struct ThaiHolidays;
+
+const THAI_FIXED_HOLIDAYS: &[(u32, u32, &str)] = &[
+    (1, 1, "New Year's Day"),
+    (4, 6, "Chakri Memorial Day"),
+    (4, 13, "Songkran Festival"),
+    (4, 14, "Songkran Festival"),
+    (4, 15, "Songkran Festival"),
+    (5, 1, "National Labour Day"),
+    (5, 4, "Coronation Day"),
+    (6, 3, "Queen Suthida's Birthday"),
+    (7, 28, "King Vajiralongkorn's Birthday"),
+    (8, 12, "Queen Mother's Birthday"),
+    (10, 13, "King Bhumibol Memorial Day"),
+    (10, 23, "Chulalongkorn Day"),
+    (12, 5, "King Bhumibol's Birthday"),
+    (12, 10, "Constitution Day"),
+    (12, 31, "New Year's Eve"),
+];
+
+/// `(year, [(month, day)])` for Makha Bucha, Visakha Bucha, Asanha Bucha
+/// and Khao Phansa, in that order.
+const THAI_LUNAR_HOLIDAYS: &[(i32, [(u32, u32); 4])] = &[
+    (2024, [(2, 24), (5, 22), (7, 20), (7, 21)]),
+    (2025, [(2, 12), (5, 11), (7, 10), (7, 11)]),
+    (2026, [(3, 3), (5, 31), (7, 29), (7, 30)]),
+];
+
+const THAI_LUNAR_NAMES: [&str; 4] = [
+    "Makha Bucha Day",
+    "Visakha Bucha Day",
+    "Asanha Bucha Day",
+    "Khao Phansa Day",
+];
+
+impl HolidayCalendar for ThaiHolidays {
+    fn holidays(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        let fixed = THAI_FIXED_HOLIDAYS.iter().copied();
+        let lunar = THAI_LUNAR_HOLIDAYS
+            .iter()
+            .filter(|(lunar_year, _)| *lunar_year == year)
+            .flat_map(|(_, dates)| {
+                dates
+                    .iter()
+                    .zip(THAI_LUNAR_NAMES)
+                    .map(|(&(month, day), name)| (month, day, name))
+            });
+        let mut holidays: Vec<(NaiveDate, String)> = fixed
+            .chain(lunar)
+            .filter_map(|(month, day, name)| {
+                Some((NaiveDate::from_ymd_opt(year, month, day)?, name.to_string()))
+            })
+            .collect();
+        holidays.sort();
+
+        let mut substitutes = Vec::new();
+        for (date, name) in holidays.iter().filter(|(date, _)| is_weekend(*date)) {
+            let mut substitute = *date + Duration::days(1);
+            // New Year's Eve can push its substitute into the next year.
+            while is_weekend(substitute)
+                || holidays.iter().any(|(taken, _)| *taken == substitute)
+                || (substitute.month(), substitute.day()) == (1, 1)
+                || substitutes
+                    .iter()
+                    .any(|(taken, _): &(NaiveDate, String)| *taken == substitute)
+            {
+                substitute += Duration::days(1);
+            }
+            substitutes.push((substitute, format!("Substitution for {name}")));
+        }
+        holidays.extend(substitutes);
+        holidays.sort();
+        holidays
+    }
+}
+
+/// Holidays given as explicit dates in the query.
+struct ExplicitHolidays(Vec<NaiveDate>);
+
+impl HolidayCalendar for ExplicitHolidays {
+    fn holidays(&self, year: i32) -> Vec<(NaiveDate, String)> {
+        self.0
+            .iter()
+            .filter(|date| date.year() == year)
+            .map(|date| (*date, "Holiday".to_string()))
+            .collect()
+    }
+}
+
+/// Working days for a set of calendars, loaded for a range of years.
+struct WorkingDays {
+    holidays: HashMap<NaiveDate, String>,
+}
+
+impl WorkingDays {
+    fn load(calendars: &[Box<dyn HolidayCalendar>], years: std::ops::RangeInclusive<i32>) -> Self {
+        let mut holidays = HashMap::new();
+        for year in years {
+            for calendar in calendars {
+                for (date, name) in calendar.holidays(year) {
+                    holidays.entry(date).or_insert(name);
+                }
+            }
+        }
+        WorkingDays { holidays }
+    }
+
+    fn holiday(&self, date: NaiveDate) -> Option<&str> {
+        self.holidays.get(&date).map(String::as_str)
+    }
+
+    fn is_working(&self, date: NaiveDate) -> bool {
+        !is_weekend(date) && !self.holidays.contains_key(&date)
+    }
+
+    /// The date `days` working days after `from` (before it when negative),
+    /// not counting `from` itself; zero returns `from`.
+    fn add(&self, from: NaiveDate, days: i64) -> NaiveDate {
+        let step = Duration::days(days.signum());
+        let mut date = from;
+        let mut counted = 0;
+        while counted < days.unsigned_abs() {
+            date += step;
+            if self.is_working(date) {
+                counted += 1;
+            }
+        }
+        date
+    }
+
+    /// The first working day after `from`.
+    fn next_after(&self, from: NaiveDate) -> NaiveDate {
+        self.add(from, 1)
+    }
+}
+
+pub(crate) fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BusinessQuery {
+    timezone: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    holidays: Option<String>,
+    add_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ClosedReason {
+    BeforeHours,
+    AfterHours,
+    Weekend,
+    Holiday,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BusinessDays {
+    days: i64,
+    date: String,
+    opens_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BusinessHoursResponse {
+    timezone: String,
+    local_time: String,
+    start: String,
+    end: String,
+    open: bool,
+    closed_reason: Option<ClosedReason>,
+    holiday: Option<String>,
+    closes_at: Option<String>,
+    next_open_at: Option<String>,
+    seconds_until_open: i64,
+    business_days: Option<BusinessDays>,
+    request_id: String,
+}
+
+pub async fn get_business_hours(
+    headers: HeaderMap,
+    Query(params): Query<BusinessQuery>,
+) -> Result<Json<BusinessHoursResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Processing business hours request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let start = parse_clock(
+        params.start.as_deref().unwrap_or("09:00"),
+        "start",
+        &request_id,
+    )?;
+    let end = parse_clock(params.end.as_deref().unwrap_or("17:00"), "end", &request_id)?;
+    if start >= end {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            "start must be before end",
+            &request_id,
+        ));
+    }
+    if let Some(days) = params.add_days {
+        if !(-MAX_BUSINESS_DAYS..=MAX_BUSINESS_DAYS).contains(&days) {
+            return Err(error_response(
+                ErrorCode::InvalidRequest,
+                format!("add_days must be between -{MAX_BUSINESS_DAYS} and {MAX_BUSINESS_DAYS}"),
+                &request_id,
+            ));
+        }
+    }
+    let calendars = parse_holidays(params.holidays.as_deref().unwrap_or(""))
+        .map_err(|message| error_response(ErrorCode::InvalidRequest, message, &request_id))?;
+
+    let now = Utc::now().with_timezone(&tz);
+    let year = now.year();
+    let days = WorkingDays::load(&calendars, year - YEARS_LOADED..=year + YEARS_LOADED);
+    let hours = BusinessHours { start, end };
+    Ok(Json(hours.report(
+        &days,
+        now,
+        params.add_days,
+        timezone,
+        request_id,
+    )))
+}
+
+struct BusinessHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl BusinessHours {
+    fn report(
+        &self,
+        days: &WorkingDays,
+        now: DateTime<Tz>,
+        add_days: Option<i64>,
+        timezone: String,
+        request_id: String,
+    ) -> BusinessHoursResponse {
+        let tz = now.timezone();
+        let today = now.date_naive();
+        let time = now.time();
+        let holiday = days.holiday(today).map(str::to_string);
+
+        let closed_reason = if is_weekend(today) {
+            Some(ClosedReason::Weekend)
+        } else if holiday.is_some() {
+            Some(ClosedReason::Holiday)
+        } else if time < self.start {
+            Some(ClosedReason::BeforeHours)
+        } else if time >= self.end {
+            Some(ClosedReason::AfterHours)
+        } else {
+            None
+        };
+        let open = closed_reason.is_none();
+
+        let next_open = (!open).then(|| {
+            let date = if closed_reason == Some(ClosedReason::BeforeHours) {
+                today
+            } else {
+                days.next_after(today)
+            };
+            local(&tz, date, self.start)
+        });
+
+        BusinessHoursResponse {
+            timezone,
+            local_time: now.to_rfc3339_opts(SecondsFormat::Secs, false),
+            start: self.start.format("%H:%M").to_string(),
+            end: self.end.format("%H:%M").to_string(),
+            open,
+            closed_reason,
+            holiday,
+            closes_at: open.then(|| rfc3339(local(&tz, today, self.end))),
+            next_open_at: next_open.map(rfc3339),
+            seconds_until_open: next_open.map_or(0, |at| (at - now).num_seconds()),
+            business_days: add_days.map(|count| {
+                let date = days.add(today, count);
+                BusinessDays {
+                    days: count,
+                    date: date.to_string(),
+                    opens_at: rfc3339(local(&tz, date, self.start)),
+                }
+            }),
+            request_id,
+        }
+    }
+}
+
+/// `date` at `time` in `tz`, taking the earlier instant of an ambiguous
+/// local time and moving past a DST gap.
+pub(crate) fn local(tz: &Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Tz> {
+    let mut naive = date.and_time(time);
+    loop {
+        if let Some(at) = tz.from_local_datetime(&naive).earliest() {
+            return at;
+        }
+        naive += Duration::minutes(30);
+    }
+}
+
+fn rfc3339(at: DateTime<Tz>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+pub(crate) fn parse_clock(
+    value: &str,
+    name: &str,
+    request_id: &str,
+) -> Result<NaiveTime, ApiError> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Invalid {name}: {value} (expected HH:MM)"),
+            request_id,
+        )
+    })
+}
+
+/// Parses `holidays=`, a comma-separated list of calendar names and
+/// `YYYY-MM-DD` dates.
+fn parse_holidays(value: &str) -> Result<Vec<Box<dyn HolidayCalendar>>, String> {
+    let mut calendars = Vec::new();
+    let mut dates = Vec::new();
+    for item in value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        if let Some(calendar) = builtin_calendar(item) {
+            calendars.push(calendar);
+        } else if let Ok(date) = NaiveDate::parse_from_str(item, "%Y-%m-%d") {
+            dates.push(date);
+        } else {
+            return Err(format!(
+                "Unknown holiday calendar: {item} (expected YYYY-MM-DD or one of: {})",
+                BUILTIN_CALENDARS.join(", ")
+            ));
+        }
+    }
+    if dates.len() > MAX_EXPLICIT_HOLIDAYS {
+        return Err(format!(
+            "At most {MAX_EXPLICIT_HOLIDAYS} holiday dates may be listed"
+        ));
+    }
+    if !dates.is_empty() {
+        calendars.push(Box::new(ExplicitHolidays(dates)));
+    }
+    Ok(calendars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn thai_days() -> WorkingDays {
+        WorkingDays::load(&parse_holidays("th").unwrap(), 2024..=2026)
+    }
+
+    fn report_at(days: &WorkingDays, at: &str, add_days: Option<i64>) -> BusinessHoursResponse {
+        let hours = BusinessHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        };
+        let tz = chrono_tz::Asia::Bangkok;
+        let now = DateTime::parse_from_rfc3339(at).unwrap().with_timezone(&tz);
+        hours.report(days, now, add_days, tz.to_string(), "test".to_string())
+    }
+
+    #[test]
+    fn substitutes_weekend_holidays() {
+        let holidays = ThaiHolidays.holidays(2025);
+        let names: HashMap<_, _> = holidays.into_iter().collect();
+        // Visakha Bucha 2025 fell on a Sunday.
+        assert_eq!(names[&date(2025, 5, 11)], "Visakha Bucha Day");
+        assert_eq!(
+            names[&date(2025, 5, 12)],
+            "Substitution for Visakha Bucha Day"
+        );
+        // Songkran 2023 ran Thursday to Saturday; Monday 17th replaced it.
+        let names: HashMap<_, _> = ThaiHolidays.holidays(2023).into_iter().collect();
+        assert_eq!(
+            names[&date(2023, 4, 17)],
+            "Substitution for Songkran Festival"
+        );
+    }
+
+    #[test]
+    fn reports_open_and_closed_hours() {
+        let days = thai_days();
+        // Wednesday 2025-03-05, 14:30 in Bangkok.
+        let open = report_at(&days, "2025-03-05T07:30:00Z", None);
+        assert!(open.open);
+        assert_eq!(open.closes_at.as_deref(), Some("2025-03-05T17:00:00+07:00"));
+        assert_eq!(open.seconds_until_open, 0);
+
+        let early = report_at(&days, "2025-03-05T01:00:00Z", None);
+        assert_eq!(early.closed_reason, Some(ClosedReason::BeforeHours));
+        assert_eq!(
+            early.next_open_at.as_deref(),
+            Some("2025-03-05T09:00:00+07:00")
+        );
+        assert_eq!(early.seconds_until_open, 3600);
+
+        // Friday evening opens again on Monday.
+        let late = report_at(&days, "2025-03-07T11:00:00Z", None);
+        assert_eq!(late.closed_reason, Some(ClosedReason::AfterHours));
+        assert_eq!(
+            late.next_open_at.as_deref(),
+            Some("2025-03-10T09:00:00+07:00")
+        );
+    }
+
+    #[test]
+    fn skips_holidays() {
+        let days = thai_days();
+        // Songkran 2025 ran Sunday to Tuesday, with Wednesday as substitute.
+        let songkran = report_at(&days, "2025-04-14T03:00:00Z", Some(3));
+        assert_eq!(songkran.closed_reason, Some(ClosedReason::Holiday));
+        assert_eq!(songkran.holiday.as_deref(), Some("Songkran Festival"));
+        assert_eq!(
+            songkran.next_open_at.as_deref(),
+            Some("2025-04-17T09:00:00+07:00")
+        );
+        assert_eq!(songkran.business_days.unwrap().date, "2025-04-21");
+
+        let without = WorkingDays::load(&[], 2025..=2025);
+        assert_eq!(without.add(date(2025, 4, 14), 3), date(2025, 4, 17));
+    }
+
+    #[test]
+    fn counts_business_days_both_ways() {
+        let days = WorkingDays::load(&parse_holidays("2025-03-10").unwrap(), 2025..=2025);
+        let wednesday = date(2025, 3, 5);
+        assert_eq!(days.add(wednesday, 0), wednesday);
+        assert_eq!(days.add(wednesday, 3), date(2025, 3, 11));
+        assert_eq!(days.add(wednesday, -3), date(2025, 2, 28));
+    }
+
+    #[test]
+    fn rejects_unknown_calendars() {
+        let error = parse_holidays("th,xx").err().unwrap();
+        assert!(error.contains("Unknown holiday calendar: xx"), "{error}");
+        assert!(parse_holidays("TH, 2025-12-24,").is_ok());
+    }
+}
@@ -0,0 +1,43 @@
+//! Calendar arithmetic shared by the date-oriented endpoints.
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if NaiveDate::from_ymd_opt(year, 2, 29).is_some() => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+pub fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+pub fn previous_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    }
+}
+
+/// Returns the first instant of `date` in `tz`, or `None` when that falls
+/// outside the range chrono can represent.
+///
+/// Zones that spring forward at midnight have no 00:00 on the transition day,
+/// so this walks forward in 15-minute steps until a valid local time exists;
+/// no timezone skips more than four hours.
+pub fn start_of_local_day(tz: &Tz, date: NaiveDate) -> Option<DateTime<Tz>> {
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    (0..=16).find_map(|step| {
+        tz.from_local_datetime(&(midnight.checked_add_signed(Duration::minutes(15 * step))?))
+            .earliest()
+    })
+}
@@ -0,0 +1,133 @@
+//! Deterministic per-period checksums for partitioning cache keys by date.
+
+use axum::response::Json;
+use chrono::{DateTime, Datelike, Duration, SecondsFormat, Timelike, Utc};
+use chrono_tz::Tz;
+use common::compression::crc32;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::calendar::{next_month, start_of_local_day};
+use crate::{error_response, parse_timezone, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct ChecksumQuery {
+    timezone: Option<String>,
+    granularity: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DateChecksum {
+    checksum: String,
+    period: String,
+    valid_until: String,
+    granularity: String,
+}
+
+pub async fn get_checksum(
+    Query(params): Query<ChecksumQuery>,
+) -> Result<Json<DateChecksum>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let granularity = params.granularity.unwrap_or_else(|| "day".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        granularity = %granularity,
+        "Processing date checksum request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let (period, valid_until) = current_period(Utc::now().with_timezone(&tz), &granularity)
+        .ok_or_else(|| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!(
+                    "Unsupported granularity: {granularity} (expected hour, day, week or month)"
+                ),
+                &request_id,
+            )
+        })?;
+
+    Ok(Json(DateChecksum {
+        checksum: format!("{:08x}", crc32(period.as_bytes())),
+        period,
+        valid_until: valid_until.to_rfc3339_opts(SecondsFormat::Secs, true),
+        granularity,
+    }))
+}
+
+/// Returns the ISO 8601 label of the local period containing `now` and the
+/// instant the next period begins.
+fn current_period(now: DateTime<Tz>, granularity: &str) -> Option<(String, DateTime<Tz>)> {
+    let tz = now.timezone();
+    let date = now.date_naive();
+    match granularity {
+        "hour" => {
+            let into_hour = Duration::minutes(i64::from(now.minute()))
+                + Duration::seconds(i64::from(now.second()))
+                + Duration::nanoseconds(i64::from(now.nanosecond()));
+            Some((
+                now.format("%Y-%m-%dT%H").to_string(),
+                now - into_hour + Duration::hours(1),
+            ))
+        }
+        "day" => Some((
+            date.format("%Y-%m-%d").to_string(),
+            start_of_local_day(&tz, date.succ_opt()?)?,
+        )),
+        "week" => {
+            let week = date.iso_week();
+            let days_left = 7 - i64::from(date.weekday().num_days_from_monday());
+            Some((
+                format!("{}-W{:02}", week.year(), week.week()),
+                start_of_local_day(&tz, date + Duration::days(days_left))?,
+            ))
+        }
+        "month" => {
+            let (year, month) = next_month(date.year(), date.month());
+            Some((
+                date.format("%Y-%m").to_string(),
+                start_of_local_day(&tz, chrono::NaiveDate::from_ymd_opt(year, month, 1)?)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn periods_and_boundaries() {
+        let at = chrono_tz::UTC
+            .with_ymd_and_hms(2024, 1, 15, 13, 45, 10)
+            .unwrap();
+        let label = |granularity| {
+            let (period, until) = current_period(at, granularity).unwrap();
+            (period, until.to_rfc3339_opts(SecondsFormat::Secs, true))
+        };
+        assert_eq!(
+            label("hour"),
+            ("2024-01-15T13".into(), "2024-01-15T14:00:00Z".into())
+        );
+        assert_eq!(
+            label("day"),
+            ("2024-01-15".into(), "2024-01-16T00:00:00Z".into())
+        );
+        assert_eq!(
+            label("week"),
+            ("2024-W03".into(), "2024-01-22T00:00:00Z".into())
+        );
+        assert_eq!(
+            label("month"),
+            ("2024-01".into(), "2024-02-01T00:00:00Z".into())
+        );
+        assert!(current_period(at, "year").is_none());
+    }
+}
@@ -0,0 +1,421 @@
+//! Conversions between ways of writing an instant (`/convert/epoch`) and a
+//! calendar date (`/convert/calendar`).
+//!
+//! Each endpoint takes exactly one input form and answers with all of them,
+//! so any form converts to any other. Thai dates use the Buddhist era as
+//! Thailand has since 1941, with the year starting in January and running
+//! 543 ahead of the Gregorian one; earlier years that began in April are not
+//! modelled.
+
+use axum::{http::HeaderMap, response::Json};
+use chrono::{
+    DateTime, Datelike, LocalResult, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use common::resolve_timezone_alias;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::ancient::GREGORIAN_CE_JDN_OFFSET;
+use crate::epochs::UNIX_EPOCH_JULIAN_DATE;
+use crate::locale::Locale;
+use crate::{error_response, localize, parse_timezone, request_id_from, ApiError};
+
+/// Years accepted and returned by `/convert/calendar`, four digits as ISO
+/// 8601 writes them.
+const FIRST_YEAR: i32 = 1;
+const LAST_YEAR: i32 = 9999;
+/// Added to the Gregorian year to give the Buddhist-era year.
+const BUDDHIST_ERA_OFFSET: i32 = 543;
+const MILLIS_PER_DAY: f64 = 86_400_000.0;
+
+#[derive(Debug, Deserialize)]
+pub struct EpochQuery {
+    seconds: Option<String>,
+    millis: Option<String>,
+    julian_day: Option<String>,
+    /// RFC 3339, or `YYYY-MM-DDTHH:MM:SS[.fff]` local to `timezone`.
+    datetime: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpochConversion {
+    unix_seconds: i64,
+    unix_millis: i64,
+    julian_day: f64,
+    utc: String,
+    local: String,
+    timezone: String,
+    request_id: String,
+}
+
+pub async fn get_convert_epoch(
+    headers: HeaderMap,
+    Query(params): Query<EpochQuery>,
+) -> Result<Json<EpochConversion>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timezone = params.timezone.as_deref().unwrap_or("UTC").to_string();
+    let tz = parse_timezone(resolve_timezone_alias(&timezone), &request_id)?;
+    let inputs = [
+        ("seconds", params.seconds.as_deref()),
+        ("millis", params.millis.as_deref()),
+        ("julian_day", params.julian_day.as_deref()),
+        ("datetime", params.datetime.as_deref()),
+    ];
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let (name, value) = single_input(&inputs).map_err(bad_request)?;
+
+    info!(
+        request_id = %request_id,
+        input = name,
+        value = %value,
+        timezone = %timezone,
+        "Processing epoch conversion request"
+    );
+
+    let at = parse_instant(name, value, tz).map_err(bad_request)?;
+    let local = localize(at, tz)
+        .ok_or_else(|| bad_request(format!("{name} out of range in {timezone}: {value}")))?;
+    let unix_millis = at.timestamp_millis();
+    Ok(Json(EpochConversion {
+        unix_seconds: at.timestamp(),
+        unix_millis,
+        julian_day: unix_millis as f64 / MILLIS_PER_DAY + UNIX_EPOCH_JULIAN_DATE,
+        utc: at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        local: local.to_rfc3339_opts(SecondsFormat::Millis, true),
+        timezone,
+        request_id,
+    }))
+}
+
+/// The one input given, as `(name, value)`.
+fn single_input<'a>(
+    inputs: &[(&'static str, Option<&'a str>)],
+) -> Result<(&'static str, &'a str), String> {
+    let given: Vec<_> = inputs
+        .iter()
+        .filter_map(|(name, value)| value.map(|value| (*name, value)))
+        .collect();
+    match given[..] {
+        [input] => Ok(input),
+        _ => {
+            let names: Vec<_> = inputs.iter().map(|(name, _)| *name).collect();
+            Err(format!("Provide exactly one of: {}", names.join(", ")))
+        }
+    }
+}
+
+fn parse_instant(name: &str, value: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    let out_of_range = || format!("{name} out of range: {value}");
+    match name {
+        "seconds" => {
+            let seconds: i64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid seconds: {value} (expected integer Unix seconds)"))?;
+            DateTime::from_timestamp(seconds, 0).ok_or_else(out_of_range)
+        }
+        "millis" => {
+            let millis: i64 = value.trim().parse().map_err(|_| {
+                format!("Invalid millis: {value} (expected integer Unix milliseconds)")
+            })?;
+            DateTime::from_timestamp_millis(millis).ok_or_else(out_of_range)
+        }
+        "julian_day" => {
+            let julian_day = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|day| day.is_finite())
+                .ok_or_else(|| {
+                    format!("Invalid julian_day: {value} (expected a number of days)")
+                })?;
+            let millis = ((julian_day - UNIX_EPOCH_JULIAN_DATE) * MILLIS_PER_DAY).round();
+            if millis.abs() >= i64::MAX as f64 {
+                return Err(out_of_range());
+            }
+            DateTime::from_timestamp_millis(millis as i64).ok_or_else(out_of_range)
+        }
+        _ => parse_datetime(value, tz),
+    }
+}
+
+/// An RFC 3339 datetime, whose own offset wins, or a local one in `tz`.
+/// Ambiguous local times resolve to the earlier instant; times skipped by a
+/// DST gap are rejected.
+fn parse_datetime(value: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    let local = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").map_err(|_| {
+        format!("Invalid datetime: {value} (expected RFC 3339 or YYYY-MM-DDTHH:MM:SS)")
+    })?;
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Ok(datetime.with_timezone(&Utc))
+        }
+        LocalResult::None => Err(format!("{value} does not exist in {tz} (DST gap)")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarQuery {
+    /// Gregorian `YYYY-MM-DD`.
+    date: Option<String>,
+    /// `YYYY-Www-D`, e.g. `2025-W10-3`.
+    iso_week: Option<String>,
+    julian_day_number: Option<String>,
+    /// Buddhist-era `YYYY-MM-DD` or `D/M/YYYY`, e.g. `5/3/2568`.
+    thai: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IsoWeekDate {
+    year: i32,
+    week: u32,
+    /// 1 for Monday to 7 for Sunday.
+    weekday: u32,
+    formatted: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThaiDate {
+    year: i32,
+    date: String,
+    display: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarConversion {
+    date: String,
+    weekday: &'static str,
+    day_of_year: u32,
+    iso_week: IsoWeekDate,
+    julian_day_number: i64,
+    thai: ThaiDate,
+    request_id: String,
+}
+
+pub async fn get_convert_calendar(
+    headers: HeaderMap,
+    Query(params): Query<CalendarQuery>,
+) -> Result<Json<CalendarConversion>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let inputs = [
+        ("date", params.date.as_deref()),
+        ("iso_week", params.iso_week.as_deref()),
+        ("julian_day_number", params.julian_day_number.as_deref()),
+        ("thai", params.thai.as_deref()),
+    ];
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let (name, value) = single_input(&inputs).map_err(bad_request)?;
+
+    info!(
+        request_id = %request_id,
+        input = name,
+        value = %value,
+        "Processing calendar conversion request"
+    );
+
+    let date = parse_date(name, value.trim()).map_err(bad_request)?;
+    if !(FIRST_YEAR..=LAST_YEAR).contains(&date.year()) {
+        return Err(bad_request(format!(
+            "{name} out of range: {value} (Gregorian years {FIRST_YEAR} to {LAST_YEAR})"
+        )));
+    }
+    Ok(Json(calendar_conversion(date, request_id)))
+}
+
+fn parse_date(name: &str, value: &str) -> Result<NaiveDate, String> {
+    match name {
+        "date" => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {value} (expected YYYY-MM-DD)")),
+        "iso_week" => parse_iso_week(value).ok_or_else(|| {
+            format!("Invalid iso_week: {value} (expected YYYY-Www-D, e.g. 2025-W10-3)")
+        }),
+        "julian_day_number" => {
+            let jdn: i64 = value
+                .parse()
+                .map_err(|_| format!("Invalid julian_day_number: {value} (expected an integer)"))?;
+            jdn.checked_sub(GREGORIAN_CE_JDN_OFFSET)
+                .and_then(|days| i32::try_from(days).ok())
+                .and_then(NaiveDate::from_num_days_from_ce_opt)
+                .ok_or_else(|| format!("julian_day_number out of range: {value}"))
+        }
+        _ => parse_thai(value).ok_or_else(|| {
+            format!("Invalid thai: {value} (expected a Buddhist-era YYYY-MM-DD or D/M/YYYY)")
+        }),
+    }
+}
+
+/// `YYYY-Www-D`, or the basic `YYYYWwwD`.
+fn parse_iso_week(value: &str) -> Option<NaiveDate> {
+    let compact = value.replace('-', "");
+    let (year, rest) = compact.split_once('W')?;
+    if year.len() != 4 || rest.len() != 3 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let weekday = match rest[2..].parse::<u8>().ok()? {
+        day @ 1..=7 => Weekday::try_from(day - 1).ok()?,
+        _ => return None,
+    };
+    NaiveDate::from_isoywd_opt(year.parse().ok()?, rest[..2].parse().ok()?, weekday)
+}
+
+fn parse_thai(value: &str) -> Option<NaiveDate> {
+    let parts: Vec<&str> = match value.contains('/') {
+        true => value.split('/').rev().collect(),
+        false => value.split('-').collect(),
+    };
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    let year = year.parse::<i32>().ok()?.checked_sub(BUDDHIST_ERA_OFFSET)?;
+    NaiveDate::from_ymd_opt(year, month.parse().ok()?, day.parse().ok()?)
+}
+
+fn calendar_conversion(date: NaiveDate, request_id: String) -> CalendarConversion {
+    let week = date.iso_week();
+    let weekday = date.weekday().number_from_monday();
+    let thai_year = date.year() + BUDDHIST_ERA_OFFSET;
+    CalendarConversion {
+        date: date.format("%Y-%m-%d").to_string(),
+        weekday: Locale::En.day_name(date.weekday()),
+        day_of_year: date.ordinal(),
+        iso_week: IsoWeekDate {
+            year: week.year(),
+            week: week.week(),
+            weekday,
+            formatted: format!("{:04}-W{:02}-{weekday}", week.year(), week.week()),
+        },
+        julian_day_number: i64::from(date.num_days_from_ce()) + GREGORIAN_CE_JDN_OFFSET,
+        thai: ThaiDate {
+            year: thai_year,
+            date: format!("{thai_year:04}-{:02}-{:02}", date.month(), date.day()),
+            display: format!(
+                "{}ที่ {} {} พ.ศ. {thai_year}",
+                Locale::Th.day_name(date.weekday()),
+                date.day(),
+                Locale::Th.month_name(date.month()),
+            ),
+        },
+        request_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(name: &str, value: &str) -> Result<CalendarConversion, String> {
+        parse_date(name, value).map(|date| calendar_conversion(date, String::new()))
+    }
+
+    #[test]
+    fn converts_calendar_dates_every_way() {
+        let forms = [
+            ("date", "2025-03-05"),
+            ("iso_week", "2025-W10-3"),
+            ("iso_week", "2025W103"),
+            ("julian_day_number", "2460740"),
+            ("thai", "2568-03-05"),
+            ("thai", "5/3/2568"),
+        ];
+        for (name, value) in forms {
+            let converted = convert(name, value).unwrap();
+            assert_eq!(converted.date, "2025-03-05", "{name}={value}");
+            assert_eq!(converted.weekday, "Wednesday");
+            assert_eq!(converted.day_of_year, 64);
+            assert_eq!(converted.iso_week.formatted, "2025-W10-3");
+            assert_eq!(converted.julian_day_number, 2_460_740);
+            assert_eq!(converted.thai.date, "2568-03-05");
+            assert_eq!(converted.thai.display, "วันพุธที่ 5 มีนาคม พ.ศ. 2568");
+        }
+    }
+
+    #[test]
+    fn iso_weeks_cross_year_boundaries() {
+        // 2024-12-30 is the Monday of the first ISO week of 2025.
+        let converted = convert("date", "2024-12-30").unwrap();
+        assert_eq!(converted.iso_week.formatted, "2025-W01-1");
+        assert_eq!(
+            convert("iso_week", "2020-W53-7").unwrap().date,
+            "2021-01-03"
+        );
+        assert_eq!(
+            convert("julian_day_number", "2451545").unwrap().date,
+            "2000-01-01"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_calendar_dates() {
+        for (name, value) in [
+            ("date", "2025-02-29"),
+            ("iso_week", "2025-W53-1"),
+            ("iso_week", "2025-W10-8"),
+            ("iso_week", "2025-10-3"),
+            ("julian_day_number", "2460740.5"),
+            ("julian_day_number", "-9223372036854775808"),
+            ("thai", "2568-02-29"),
+            ("thai", "5/3"),
+            ("thai", "5/3/2568/1"),
+        ] {
+            assert!(convert(name, value).is_err(), "{name}={value}");
+        }
+    }
+
+    #[test]
+    fn converts_instants_every_way() {
+        let bangkok = chrono_tz::Asia::Bangkok;
+        let expected = Utc.with_ymd_and_hms(2025, 3, 5, 7, 30, 0).unwrap();
+        for (name, value) in [
+            ("seconds", "1741159800"),
+            ("millis", "1741159800000"),
+            ("julian_day", "2460739.8125"),
+            ("datetime", "2025-03-05T07:30:00Z"),
+            ("datetime", "2025-03-05T14:30:00"),
+        ] {
+            assert_eq!(
+                parse_instant(name, value, bangkok),
+                Ok(expected),
+                "{name}={value}"
+            );
+        }
+        let new_york = chrono_tz::America::New_York;
+        assert!(parse_instant("datetime", "2025-03-09T02:30:00", new_york).is_err());
+        assert!(parse_instant("seconds", "1.5", bangkok).is_err());
+        assert!(parse_instant("julian_day", "1e300", bangkok).is_err());
+        assert!(parse_instant("millis", &i64::MAX.to_string(), bangkok).is_err());
+    }
+
+    #[tokio::test]
+    async fn requires_exactly_one_input() {
+        let params = |seconds: Option<&str>, millis: Option<&str>| EpochQuery {
+            seconds: seconds.map(str::to_string),
+            millis: millis.map(str::to_string),
+            julian_day: None,
+            datetime: None,
+            timezone: Some("Asia/Bangkok".to_string()),
+        };
+        let Json(converted) = get_convert_epoch(HeaderMap::new(), Query(params(Some("0"), None)))
+            .await
+            .unwrap();
+        assert_eq!(converted.julian_day, UNIX_EPOCH_JULIAN_DATE);
+        assert_eq!(converted.local, "1970-01-01T07:00:00.000+07:00");
+
+        for (seconds, millis) in [(None, None), (Some("0"), Some("0"))] {
+            let error = get_convert_epoch(HeaderMap::new(), Query(params(seconds, millis)))
+                .await
+                .unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidRequest);
+            assert!(error.error.contains("exactly one of: seconds, millis"));
+        }
+    }
+}
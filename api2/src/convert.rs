@@ -0,0 +1,244 @@
+//! Conversion of a given instant between two timezones.
+//!
+//! The input is an RFC 3339 timestamp, Unix seconds, or a local
+//! `YYYY-MM-DDTHH:MM:SS` time in the `from` zone. Local times are the only
+//! input that can be ambiguous or missing around DST transitions; both are
+//! reported as `422` rather than guessed.
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, SecondsFormat, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, Tz};
+use common::problem::{ErrorCode, Query};
+use common::{ConvertedTime, TimeConvertResponse};
+use serde::Deserialize;
+use std::fmt;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    error_for_status, error_response, format_utc_offset, localize, parse_timezone, request_id_from,
+    ApiError,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TimeConvertQuery {
+    timestamp: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// Why a timestamp could not be placed on the timeline.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ConvertError {
+    /// Not RFC 3339, Unix seconds or a local datetime, or out of range.
+    Invalid(String),
+    /// The local time occurs twice, when clocks fall back.
+    Ambiguous {
+        local: String,
+        timezone: String,
+        earlier: String,
+        later: String,
+    },
+    /// The local time is skipped, when clocks spring forward.
+    Nonexistent { local: String, timezone: String },
+}
+
+impl ConvertError {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            ConvertError::Invalid(_) => StatusCode::BAD_REQUEST,
+            ConvertError::Ambiguous { .. } | ConvertError::Nonexistent { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Invalid(input) => write!(
+                f,
+                "Invalid timestamp: {input} (expected RFC 3339, Unix seconds or YYYY-MM-DDTHH:MM:SS)"
+            ),
+            ConvertError::Ambiguous {
+                local,
+                timezone,
+                earlier,
+                later,
+            } => write!(
+                f,
+                "Ambiguous local time: {local} occurs twice in {timezone} ({earlier} or {later}); \
+                 send an RFC 3339 timestamp with an offset"
+            ),
+            ConvertError::Nonexistent { local, timezone } => write!(
+                f,
+                "Nonexistent local time: {local} is skipped by a DST transition in {timezone}"
+            ),
+        }
+    }
+}
+
+pub async fn get_time_convert(
+    headers: HeaderMap,
+    Query(params): Query<TimeConvertQuery>,
+) -> Result<Json<TimeConvertResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    info!(
+        request_id = %request_id,
+        timestamp = ?params.timestamp,
+        from = ?params.from,
+        to = ?params.to,
+        "Processing time convert request"
+    );
+
+    convert_time(params.timestamp, params.from, params.to, request_id).map(Json)
+}
+
+/// Converts `timestamp` from `from` (default UTC) to `to`, for
+/// `/time/convert` and the gRPC `ConvertTime`.
+pub fn convert_time(
+    timestamp: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    request_id: String,
+) -> Result<TimeConvertResponse, ApiError> {
+    let missing = |name: &str| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Missing required parameter: {name}"),
+            &request_id,
+        )
+    };
+    let timestamp = timestamp.ok_or_else(|| missing("timestamp"))?;
+    let to = to.ok_or_else(|| missing("to"))?;
+    let from = from.unwrap_or_else(|| "UTC".to_string());
+    let from_tz = parse_timezone(&from, &request_id)?;
+    let to_tz = parse_timezone(&to, &request_id)?;
+
+    let instant = parse_instant(&timestamp, from_tz)
+        .map_err(|e| error_for_status(e.status(), e.to_string(), &request_id))?;
+    let local = |tz: Tz| {
+        localize(instant, tz).ok_or_else(|| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Timestamp out of range in {}: {timestamp}", tz.name()),
+                &request_id,
+            )
+        })
+    };
+    let (from, to) = (local(from_tz)?, local(to_tz)?);
+
+    Ok(TimeConvertResponse {
+        epoch_seconds: instant.timestamp(),
+        from: converted(from),
+        to: converted(to),
+        request_id,
+    })
+}
+
+/// Reads `input` as RFC 3339 (whose own offset wins), Unix seconds, or a
+/// local datetime in `from`.
+pub(crate) fn parse_instant(input: &str, from: Tz) -> Result<DateTime<Utc>, ConvertError> {
+    let invalid = || ConvertError::Invalid(input.to_string());
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+    if let Ok(seconds) = input.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0).ok_or_else(invalid);
+    }
+    let local = NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S").map_err(|_| invalid())?;
+    match from.from_local_datetime(&local) {
+        LocalResult::Single(datetime) => Ok(datetime.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earlier, later) => Err(ConvertError::Ambiguous {
+            local: input.to_string(),
+            timezone: from.name().to_string(),
+            earlier: earlier.to_rfc3339_opts(SecondsFormat::Secs, true),
+            later: later.to_rfc3339_opts(SecondsFormat::Secs, true),
+        }),
+        LocalResult::None => Err(ConvertError::Nonexistent {
+            local: input.to_string(),
+            timezone: from.name().to_string(),
+        }),
+    }
+}
+
+fn converted(local: DateTime<Tz>) -> ConvertedTime {
+    let utc_offset_seconds = local.offset().fix().local_minus_utc();
+    ConvertedTime {
+        timezone: local.timezone().name().to_string(),
+        timestamp: local.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        utc_offset_seconds,
+        utc_offset_label: format_utc_offset(utc_offset_seconds),
+        is_dst: !local.offset().dst_offset().is_zero(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America::New_York, Asia::Tokyo};
+
+    #[test]
+    fn accepts_rfc3339_epoch_and_local_input() {
+        let expected = Utc.with_ymd_and_hms(2024, 1, 15, 5, 0, 0).unwrap();
+        assert_eq!(
+            parse_instant("2024-01-15T14:00:00+09:00", New_York),
+            Ok(expected)
+        );
+        assert_eq!(parse_instant("1705294800", Tokyo), Ok(expected));
+        assert_eq!(parse_instant("2024-01-15T00:00:00", New_York), Ok(expected));
+        assert_eq!(
+            parse_instant("yesterday", New_York),
+            Err(ConvertError::Invalid("yesterday".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_local_times_around_dst_transitions() {
+        let ambiguous = parse_instant("2024-11-03T01:30:00", New_York).unwrap_err();
+        assert_eq!(ambiguous.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            ambiguous.to_string(),
+            "Ambiguous local time: 2024-11-03T01:30:00 occurs twice in America/New_York \
+             (2024-11-03T01:30:00-04:00 or 2024-11-03T01:30:00-05:00); \
+             send an RFC 3339 timestamp with an offset"
+        );
+
+        let skipped = parse_instant("2024-03-10T02:30:00", New_York).unwrap_err();
+        assert_eq!(
+            skipped,
+            ConvertError::Nonexistent {
+                local: "2024-03-10T02:30:00".to_string(),
+                timezone: "America/New_York".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn converts_with_offsets_and_dst_flags() {
+        let query = |to: &str| TimeConvertQuery {
+            timestamp: Some("2024-07-15T12:00:00Z".to_string()),
+            from: None,
+            to: Some(to.to_string()),
+        };
+        let Json(response) = get_time_convert(HeaderMap::new(), Query(query("America/New_York")))
+            .await
+            .unwrap();
+        assert_eq!(response.epoch_seconds, 1_721_044_800);
+        assert_eq!(response.from.timestamp, "2024-07-15T12:00:00Z");
+        assert!(!response.from.is_dst);
+        assert_eq!(response.to.timestamp, "2024-07-15T08:00:00-04:00");
+        assert_eq!(response.to.utc_offset_label, "-04:00");
+        assert!(response.to.is_dst);
+
+        let error = get_time_convert(HeaderMap::new(), Query(query("Foo/Bar")))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidTimezone);
+    }
+}
@@ -0,0 +1,374 @@
+//! Evaluation of cron expressions, for previewing schedules.
+//!
+//! Expressions have the five classic fields (minute, hour, day of month,
+//! month, day of week) with `*`, lists, ranges, `/` steps and three-letter
+//! month and day names, or one of the `@hourly`, `@daily`, `@midnight`,
+//! `@weekly`, `@monthly`, `@yearly` and `@annually` macros. As in Vixie
+//! cron, a time matches when either day field matches if both are
+//! restricted, and a day of week of 7 means Sunday.
+//!
+//! Times are evaluated on the local clock of the requested zone: a time
+//! skipped by a daylight saving change does not fire, and a repeated one
+//! fires once, at its first occurrence.
+
+use axum::{extract::rejection::JsonRejection, http::HeaderMap, response::Json};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use common::problem::ErrorCode;
+use common::{CronRequest, CronResponse};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, json_body, parse_timezone, request_id_from, ApiError};
+
+const DEFAULT_COUNT: usize = 5;
+const MAX_COUNT: usize = 100;
+/// How far ahead to look: long enough for `0 0 29 2 *` across a skipped
+/// leap year, and for any weekday-and-date combination to recur.
+const MAX_SEARCH_DAYS: i64 = 366 * 28;
+
+struct Field {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    /// Names for the values from `min` upwards.
+    names: &'static [&'static str],
+}
+
+const MINUTE: Field = Field {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: Field = Field {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY_OF_MONTH: Field = Field {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: Field = Field {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &[
+        "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+    ],
+};
+const DAY_OF_WEEK: Field = Field {
+    name: "day of week",
+    min: 0,
+    max: 7,
+    names: &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"],
+};
+
+/// A parsed expression, each field a bit set of the values it allows.
+#[derive(Debug, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    /// Bit 0 is Sunday.
+    days_of_week: u64,
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim().to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other if other.starts_with('@') => {
+                return Err(format!("unknown macro {}", expression.trim()));
+            }
+            _ => expression,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields, found {}", fields.len()));
+        };
+
+        let mut days_of_week = parse_field(weekday, &DAY_OF_WEEK)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            minutes: parse_field(minute, &MINUTE)?,
+            hours: parse_field(hour, &HOUR)?,
+            days_of_month: parse_field(day, &DAY_OF_MONTH)?,
+            months: parse_field(month, &MONTH)?,
+            days_of_week,
+            days_of_month_restricted: !day.starts_with('*'),
+            days_of_week_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let day = has(self.days_of_month, date.day());
+        let weekday = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.days_of_month_restricted, self.days_of_week_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// Up to `count` fire times strictly after `after`, soonest first.
+    pub fn next_after(&self, after: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let tz = after.timezone();
+        let mut times = Vec::with_capacity(count);
+        let first = after.date_naive();
+        let dates =
+            (0..=MAX_SEARCH_DAYS).map_while(|days| first.checked_add_signed(Duration::days(days)));
+        for date in dates.filter(|date| self.matches_date(*date)) {
+            for hour in values(self.hours) {
+                for minute in values(self.minutes) {
+                    let Some(local) = date.and_hms_opt(hour, minute, 0) else {
+                        continue;
+                    };
+                    match tz.from_local_datetime(&local).earliest() {
+                        Some(at) if at > after => times.push(at),
+                        _ => continue,
+                    }
+                    if times.len() == count {
+                        return times;
+                    }
+                }
+            }
+        }
+        times
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn values(bits: u64) -> impl Iterator<Item = u32> {
+    (0..64).filter(move |value| has(bits, *value))
+}
+
+/// Parses one field into a bit set, e.g. `1-5,10-30/5` or `MON-FRI`.
+fn parse_field(text: &str, field: &Field) -> Result<u64, String> {
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("{}: invalid step '{step}'", field.name))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (low, high) = if range == "*" {
+            (field.min, field.max)
+        } else if let Some((low, high)) = range.split_once('-') {
+            (value(low, field)?, value(high, field)?)
+        } else {
+            let low = value(range, field)?;
+            // `5/15` runs from 5 to the end of the range.
+            (low, if step.is_some() { field.max } else { low })
+        };
+        if low > high {
+            return Err(format!("{}: range {range} is reversed", field.name));
+        }
+        for value in (low..=high).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn value(text: &str, field: &Field) -> Result<u32, String> {
+    if let Some(index) = field
+        .names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(text))
+    {
+        return Ok(field.min + index as u32);
+    }
+    let value = text
+        .parse::<u32>()
+        .map_err(|_| format!("{}: invalid value '{text}'", field.name))?;
+    if !(field.min..=field.max).contains(&value) {
+        return Err(format!(
+            "{}: {value} is out of range {}-{}",
+            field.name, field.min, field.max
+        ));
+    }
+    Ok(value)
+}
+
+pub async fn post_cron_next(
+    headers: HeaderMap,
+    body: Result<Json<CronRequest>, JsonRejection>,
+) -> Result<Json<CronResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let request = json_body(body, &request_id)?;
+    let timezone = request.timezone.unwrap_or_else(|| "UTC".to_string());
+    let count = request.count.unwrap_or(DEFAULT_COUNT);
+
+    info!(
+        request_id = %request_id,
+        expression = %request.expression,
+        timezone = %timezone,
+        "Processing cron request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    if !(1..=MAX_COUNT).contains(&count) {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!("count must be between 1 and {MAX_COUNT}"),
+            &request_id,
+        ));
+    }
+    let schedule = CronSchedule::parse(&request.expression).map_err(|message| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Invalid cron expression: {message}"),
+            &request_id,
+        )
+    })?;
+
+    let next = schedule
+        .next_after(Utc::now().with_timezone(&tz), count)
+        .into_iter()
+        .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, false))
+        .collect();
+    Ok(Json(CronResponse {
+        expression: request.expression,
+        timezone,
+        next,
+        request_id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(expression: &str, tz: Tz, after: &str, count: usize) -> Vec<String> {
+        let after = DateTime::parse_from_rfc3339(after)
+            .unwrap()
+            .with_timezone(&tz);
+        CronSchedule::parse(expression)
+            .unwrap()
+            .next_after(after, count)
+            .into_iter()
+            .map(|at| at.to_rfc3339())
+            .collect()
+    }
+
+    #[test]
+    fn lists_fire_times_in_the_zone() {
+        // Friday 2025-03-07, 16:50 in Bangkok.
+        let times = next(
+            "*/15 9-17 * * MON-FRI",
+            chrono_tz::Asia::Bangkok,
+            "2025-03-07T09:50:00Z",
+            6,
+        );
+        assert_eq!(
+            times,
+            [
+                "2025-03-07T17:00:00+07:00",
+                "2025-03-07T17:15:00+07:00",
+                "2025-03-07T17:30:00+07:00",
+                "2025-03-07T17:45:00+07:00",
+                "2025-03-10T09:00:00+07:00",
+                "2025-03-10T09:15:00+07:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_either_day_field_when_both_are_restricted() {
+        let times = next("0 12 13 * FRI", Tz::UTC, "2025-06-01T00:00:00Z", 3);
+        assert_eq!(
+            times,
+            [
+                "2025-06-06T12:00:00+00:00",
+                "2025-06-13T12:00:00+00:00",
+                "2025-06-20T12:00:00+00:00",
+            ]
+        );
+        let times = next("0 0 * 2 7", Tz::UTC, "2025-01-01T00:00:00Z", 1);
+        assert_eq!(times, ["2025-02-02T00:00:00+00:00"]);
+    }
+
+    #[test]
+    fn follows_daylight_saving_changes() {
+        // 02:30 does not exist in New York on 2025-03-09.
+        let times = next(
+            "30 2 * * *",
+            chrono_tz::America::New_York,
+            "2025-03-08T00:00:00Z",
+            2,
+        );
+        assert_eq!(
+            times,
+            ["2025-03-08T02:30:00-05:00", "2025-03-10T02:30:00-04:00"]
+        );
+        // 01:30 happens twice on 2025-11-02 and fires once.
+        let times = next(
+            "30 1 * * *",
+            chrono_tz::America::New_York,
+            "2025-11-02T00:00:00Z",
+            2,
+        );
+        assert_eq!(
+            times,
+            ["2025-11-02T01:30:00-04:00", "2025-11-03T01:30:00-05:00"]
+        );
+    }
+
+    #[test]
+    fn expands_macros_and_handles_rare_schedules() {
+        assert_eq!(
+            CronSchedule::parse("@weekly"),
+            CronSchedule::parse("0 0 * * SUN")
+        );
+        let leap = next("0 0 29 2 *", Tz::UTC, "2025-01-01T00:00:00Z", 2);
+        assert_eq!(
+            leap,
+            ["2028-02-29T00:00:00+00:00", "2032-02-29T00:00:00+00:00"]
+        );
+        assert!(next("0 0 30 2 *", Tz::UTC, "2025-01-01T00:00:00Z", 1).is_empty());
+    }
+
+    #[test]
+    fn explains_malformed_expressions() {
+        for (expression, error) in [
+            ("* * * *", "expected 5 fields, found 4"),
+            ("60 * * * *", "minute: 60 is out of range 0-59"),
+            ("* 18-9 * * *", "hour: range 18-9 is reversed"),
+            ("*/0 * * * *", "minute: invalid step '0'"),
+            ("* * * FOO *", "month: invalid value 'FOO'"),
+            ("* * 0 * *", "day of month: 0 is out of range 1-31"),
+            ("@fortnightly", "unknown macro @fortnightly"),
+        ] {
+            assert_eq!(
+                CronSchedule::parse(expression).unwrap_err(),
+                error,
+                "{expression}"
+            );
+        }
+    }
+}
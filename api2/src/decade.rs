@@ -0,0 +1,154 @@
+use axum::response::Json;
+use chrono::{Datelike, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, parse_timezone, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct DecadeQuery {
+    timezone: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecadeCenturyMillennium {
+    decade: String,
+    decade_start: String,
+    decade_end: String,
+    century: String,
+    millennium: String,
+    years_into_decade: i32,
+}
+
+pub async fn get_decade_century_millennium(
+    Query(params): Query<DecadeQuery>,
+) -> Result<Json<DecadeCenturyMillennium>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Processing decade/century/millennium request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let date = match params.date {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Invalid date: {date} (expected YYYY-MM-DD)"),
+                &request_id,
+            )
+        })?,
+        None => Utc::now().with_timezone(&tz).date_naive(),
+    };
+
+    if date.year() < 1 {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            "Dates before year 1 are not supported",
+            &request_id,
+        ));
+    }
+
+    decade_century_millennium(&tz, date)
+        .map(Json)
+        .ok_or_else(|| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Date out of range: {date} (its decade ends past the last supported year)"),
+                &request_id,
+            )
+        })
+}
+
+/// Labels `date` with its decade, century and millennium.
+///
+/// Decades are counted colloquially (the 2020s are 2020-2029), while centuries
+/// and millennia follow the strict convention with no year zero, so the 21st
+/// century and 3rd millennium both began on 2001-01-01. `None` when the
+/// decade's boundaries fall outside chrono's range.
+fn decade_century_millennium(tz: &Tz, date: NaiveDate) -> Option<DecadeCenturyMillennium> {
+    let year = date.year();
+    let decade_start_year = year - year.rem_euclid(10);
+    let decade_end_year = decade_start_year + 9;
+
+    let format = |year: i32, month: u32, day: u32, h: u32, m: u32, s: u32| {
+        let naive = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(h, m, s)?;
+        Some(
+            tz.from_local_datetime(&naive)
+                .earliest()
+                .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_else(|| naive.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        )
+    };
+
+    Some(DecadeCenturyMillennium {
+        decade: format!("{decade_start_year}s"),
+        decade_start: format(decade_start_year, 1, 1, 0, 0, 0)?,
+        decade_end: format(decade_end_year, 12, 31, 23, 59, 59)?,
+        century: ordinal((year - 1) / 100 + 1),
+        millennium: ordinal((year - 1) / 1000 + 1),
+        years_into_decade: year - decade_start_year,
+    })
+}
+
+fn ordinal(n: i32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(year: i32, month: u32, day: u32) -> DecadeCenturyMillennium {
+        let date = NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        decade_century_millennium(&Tz::UTC, date).unwrap()
+    }
+
+    #[test]
+    fn year_2000_is_last_year_of_second_millennium() {
+        let labels = labels(2000, 1, 1);
+        assert_eq!(labels.decade, "2000s");
+        assert_eq!(labels.decade_start, "2000-01-01T00:00:00Z");
+        assert_eq!(labels.decade_end, "2009-12-31T23:59:59Z");
+        assert_eq!(labels.century, "20th");
+        assert_eq!(labels.millennium, "2nd");
+        assert_eq!(labels.years_into_decade, 0);
+    }
+
+    #[test]
+    fn third_millennium_starts_in_2001() {
+        let labels = labels(2001, 1, 1);
+        assert_eq!(labels.century, "21st");
+        assert_eq!(labels.millennium, "3rd");
+    }
+
+    #[test]
+    fn last_decade_runs_past_the_supported_range() {
+        let date = NaiveDate::from_ymd_opt(262_142, 6, 1).unwrap();
+        assert!(decade_century_millennium(&Tz::UTC, date).is_none());
+        assert_eq!(labels(262_139, 6, 1).decade_end, "+262139-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn ordinal_suffixes() {
+        assert_eq!(ordinal(1), "1st");
+        assert_eq!(ordinal(11), "11th");
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(22), "22nd");
+        assert_eq!(ordinal(113), "113th");
+    }
+}
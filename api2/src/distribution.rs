@@ -0,0 +1,90 @@
+use axum::response::Json;
+use chrono::{Duration, NaiveDate, Offset, SecondsFormat, Timelike, Utc};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::calendar::start_of_local_day;
+use crate::{error_response, parse_timezone, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct DistributionQuery {
+    timezone: Option<String>,
+    date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HourBucket {
+    hour: u32,
+    label: String,
+    utc_start: String,
+    utc_end: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dst_transition: bool,
+}
+
+pub async fn get_hour_of_day_distribution(
+    Query(params): Query<DistributionQuery>,
+) -> Result<Json<Vec<HourBucket>>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Processing hour-of-day distribution request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let date = match params.date {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Invalid date: {date} (expected YYYY-MM-DD)"),
+                &request_id,
+            )
+        })?,
+        None => Utc::now().with_timezone(&tz).date_naive(),
+    };
+
+    hour_buckets(&tz, date).map(Json).ok_or_else(|| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Date out of range: {date}"),
+            &request_id,
+        )
+    })
+}
+
+/// Splits the local day into one-hour UTC buckets, or `None` when the day
+/// reaches outside the range chrono can represent.
+///
+/// Days with a DST transition yield 23 or 25 buckets; the first bucket after
+/// the UTC offset changes is flagged with `dst_transition`.
+fn hour_buckets(tz: &Tz, date: NaiveDate) -> Option<Vec<HourBucket>> {
+    let day_start = start_of_local_day(tz, date)?.with_timezone(&Utc);
+    let day_end = start_of_local_day(tz, date.succ_opt()?)?.with_timezone(&Utc);
+
+    let mut buckets = Vec::with_capacity(25);
+    let mut previous_offset = None;
+    let mut start = day_start;
+    while start < day_end {
+        let end = (start + Duration::hours(1)).min(day_end);
+        let local = start.with_timezone(tz);
+        let offset = local.offset().fix().local_minus_utc();
+
+        buckets.push(HourBucket {
+            hour: local.hour(),
+            label: local.format("%-I:%M %p").to_string(),
+            utc_start: start.to_rfc3339_opts(SecondsFormat::Secs, true),
+            utc_end: end.to_rfc3339_opts(SecondsFormat::Secs, true),
+            dst_transition: previous_offset.is_some_and(|previous| previous != offset),
+        });
+
+        previous_offset = Some(offset);
+        start = end;
+    }
+    Some(buckets)
+}
@@ -0,0 +1,346 @@
+//! Parsing and humanizing durations, so frontends need not reimplement it.
+//!
+//! `/duration/parse` accepts ISO 8601 durations (`P1DT2H`), compact forms
+//! (`1h30m`, `500ms`) and words (`90 minutes`, `1 hour and 30 minutes`,
+//! `1 ชั่วโมง 30 นาที`). Years and months are rejected because their length
+//! depends on the date they start from.
+
+use axum::{http::HeaderMap, response::Json};
+use common::openapi::{object, reference, register, typed, Components, Schema};
+use common::problem::{ErrorCode, Query};
+use common::Elapsed;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::locale::Locale;
+use crate::timers::elapsed;
+use crate::{error_response, request_id_from, ApiError};
+
+/// Inputs longer than this are rejected before parsing.
+const MAX_INPUT_CHARS: usize = 128;
+/// Longest duration either endpoint accepts, 1000 years of 365 days.
+const MAX_SECONDS: f64 = 1000.0 * 365.0 * 86_400.0;
+const UNITS_IN_HUMANIZED: usize = 4;
+
+/// Unit names in compact and word forms, with their length in seconds.
+const UNITS: &[(&[&str], f64)] = &[
+    (
+        &[
+            "ms",
+            "msec",
+            "msecs",
+            "millisecond",
+            "milliseconds",
+            "มิลลิวินาที",
+        ],
+        0.001,
+    ),
+    (&["s", "sec", "secs", "second", "seconds", "วินาที"], 1.0),
+    (&["m", "min", "mins", "minute", "minutes", "นาที"], 60.0),
+    (&["h", "hr", "hrs", "hour", "hours", "ชั่วโมง"], 3_600.0),
+    (&["d", "day", "days", "วัน"], 86_400.0),
+    (&["w", "wk", "wks", "week", "weeks", "สัปดาห์"], 604_800.0),
+];
+
+/// Words allowed between components, e.g. `1 hour and 30 minutes`.
+const CONNECTIVES: &[&str] = &["and", "และ"];
+
+#[derive(Debug, Deserialize)]
+pub struct ParseQuery {
+    value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParsedDuration {
+    input: String,
+    duration: Elapsed,
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HumanizeQuery {
+    seconds: Option<u64>,
+    locale: Option<String>,
+    parts: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HumanizedDuration {
+    seconds: u64,
+    locale: String,
+    text: String,
+    request_id: String,
+}
+
+impl Schema for ParsedDuration {
+    const NAME: &'static str = "ParsedDuration";
+
+    fn schema() -> Value {
+        object(
+            "A parsed duration.",
+            &[
+                ("input", typed("string", "The value as given."), true),
+                ("duration", reference::<Elapsed>(), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<Elapsed>(components);
+    }
+}
+
+impl Schema for HumanizedDuration {
+    const NAME: &'static str = "HumanizedDuration";
+
+    fn schema() -> Value {
+        object(
+            "A duration in words.",
+            &[
+                ("seconds", typed("integer", "The duration given."), true),
+                ("locale", typed("string", "Locale tag as given."), true),
+                (
+                    "text",
+                    typed("string", "E.g. `1 hour 30 minutes` or `1 ชั่วโมง 30 นาที`."),
+                    true,
+                ),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+}
+
+pub async fn get_duration_parse(
+    headers: HeaderMap,
+    Query(params): Query<ParseQuery>,
+) -> Result<Json<ParsedDuration>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+
+    let input = params
+        .value
+        .ok_or_else(|| bad_request("Missing required parameter: value".to_string()))?;
+    info!(
+        request_id = %request_id,
+        value = %input,
+        "Processing duration parse request"
+    );
+
+    if input.chars().count() > MAX_INPUT_CHARS {
+        return Err(bad_request(format!(
+            "Duration must be at most {MAX_INPUT_CHARS} characters"
+        )));
+    }
+    let seconds =
+        parse(&input).map_err(|message| bad_request(format!("Invalid duration: {message}")))?;
+    Ok(Json(ParsedDuration {
+        duration: elapsed(Duration::from_secs_f64(seconds)),
+        input,
+        request_id,
+    }))
+}
+
+pub async fn get_duration_humanize(
+    headers: HeaderMap,
+    Query(params): Query<HumanizeQuery>,
+) -> Result<Json<HumanizedDuration>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+
+    let seconds = params
+        .seconds
+        .ok_or_else(|| bad_request("Missing required parameter: seconds".to_string()))?;
+    let tag = params.locale.unwrap_or_else(|| "en".to_string());
+    let parts = params.parts.unwrap_or(UNITS_IN_HUMANIZED);
+    info!(
+        request_id = %request_id,
+        seconds,
+        locale = %tag,
+        "Processing duration humanize request"
+    );
+
+    let locale = Locale::parse(&tag).map_err(bad_request)?;
+    if seconds as f64 > MAX_SECONDS {
+        return Err(bad_request(format!(
+            "seconds must be at most {}",
+            MAX_SECONDS as u64
+        )));
+    }
+    if !(1..=UNITS_IN_HUMANIZED).contains(&parts) {
+        return Err(bad_request(format!(
+            "parts must be between 1 and {UNITS_IN_HUMANIZED}"
+        )));
+    }
+    Ok(Json(HumanizedDuration {
+        seconds,
+        locale: tag,
+        text: locale.duration(seconds, parts),
+        request_id,
+    }))
+}
+
+/// Parses any accepted form into seconds.
+fn parse(input: &str) -> Result<f64, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("empty value".to_string());
+    }
+    let seconds = if input.starts_with(['P', 'p']) {
+        parse_iso8601(input)?
+    } else {
+        parse_components(input)?
+    };
+    if seconds > MAX_SECONDS {
+        return Err("longer than 1000 years".to_string());
+    }
+    Ok(seconds)
+}
+
+/// `P[nW][nD][T[nH][nM][nS]]`, any number possibly fractional.
+fn parse_iso8601(input: &str) -> Result<f64, String> {
+    let upper = input.to_ascii_uppercase();
+    let (date, time) = match upper[1..].split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (&upper[1..], None),
+    };
+    let date_units: &[(char, f64)] = &[('W', 604_800.0), ('D', 86_400.0)];
+    let time_units: &[(char, f64)] = &[('H', 3_600.0), ('M', 60.0), ('S', 1.0)];
+
+    let mut seconds = 0.0;
+    let mut components = 0;
+    for (part, units, is_date) in [
+        (date, date_units, true),
+        (time.unwrap_or(""), time_units, false),
+    ] {
+        let mut number = String::new();
+        let mut next_unit = 0;
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' || c == ',' {
+                number.push(if c == ',' { '.' } else { c });
+                continue;
+            }
+            if is_date && (c == 'Y' || c == 'M') {
+                return Err(format!(
+                    "{input} uses years or months, which have no fixed length"
+                ));
+            }
+            let position = units[next_unit..]
+                .iter()
+                .position(|(unit, _)| *unit == c)
+                .ok_or_else(|| format!("{input} is not an ISO 8601 duration"))?;
+            let value = number
+                .parse::<f64>()
+                .map_err(|_| format!("{input} is not an ISO 8601 duration"))?;
+            seconds += value * units[next_unit + position].1;
+            next_unit += position + 1;
+            components += 1;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return Err(format!("{input} is not an ISO 8601 duration"));
+        }
+    }
+    if components == 0 || time == Some("") {
+        return Err(format!("{input} is not an ISO 8601 duration"));
+    }
+    Ok(seconds)
+}
+
+/// Number-and-unit pairs such as `1h30m`, `90 minutes` or
+/// `1 hour, and 30 minutes`.
+fn parse_components(input: &str) -> Result<f64, String> {
+    let lower = input.to_lowercase();
+    let mut rest = lower.as_str();
+    let mut seconds = 0.0;
+    let mut components = 0;
+    let is_separator = |c: char| c.is_whitespace() || c == ',';
+    let is_number = |c: char| c.is_ascii_digit() || c == '.';
+    let is_word = |c: char| !is_separator(c) && !is_number(c);
+
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with(is_number) {
+            let end = rest.find(|c: char| !is_number(c)).unwrap_or(rest.len());
+            let number = &rest[..end];
+            let value = number
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number {number}"))?;
+            rest = rest[end..].trim_start();
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let unit = &rest[..end];
+            if unit.is_empty() {
+                return Err(format!("missing unit after {number}"));
+            }
+            let (_, size) = UNITS
+                .iter()
+                .find(|(names, _)| names.contains(&unit))
+                .ok_or_else(|| format!("unknown unit {unit}"))?;
+            seconds += value * size;
+            components += 1;
+            rest = &rest[end..];
+        } else {
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if components == 0 || !CONNECTIVES.contains(&word) {
+                return Err(format!("unexpected {word}"));
+            }
+            rest = &rest[end..];
+        }
+    }
+    Ok(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_accepted_form() {
+        for (input, seconds) in [
+            ("1h30m", 5400.0),
+            ("1h 30m", 5400.0),
+            ("90 minutes", 5400.0),
+            ("1 hour and 30 minutes", 5400.0),
+            ("1.5 Hours", 5400.0),
+            ("1 ชั่วโมง 30 นาที", 5400.0),
+            ("2 weeks, 1 day", 1_296_000.0),
+            ("500ms", 0.5),
+            ("P1DT2H", 93_600.0),
+            ("PT1H30M", 5400.0),
+            ("P2W", 1_209_600.0),
+            ("pt0,5s", 0.5),
+        ] {
+            assert_eq!(parse(input), Ok(seconds), "{input}");
+        }
+    }
+
+    #[test]
+    fn explains_what_it_cannot_parse() {
+        for (input, error) in [
+            ("", "empty value"),
+            ("90", "missing unit after 90"),
+            ("3 fortnights", "unknown unit fortnights"),
+            ("and 3 hours", "unexpected and"),
+            ("1..5h", "invalid number 1..5"),
+            (
+                "P1M",
+                "P1M uses years or months, which have no fixed length",
+            ),
+            ("PT", "PT is not an ISO 8601 duration"),
+            ("PT1S2H", "PT1S2H is not an ISO 8601 duration"),
+            ("P1DT", "P1DT is not an ISO 8601 duration"),
+            ("2000000 weeks", "longer than 1000 years"),
+        ] {
+            assert_eq!(parse(input).unwrap_err(), error, "{input}");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Classification of a moment on the geological and cosmological time scales.
+
+use axum::response::Json;
+use chrono::{TimeZone, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+/// A named division of geological time, bounded in millions of years ago.
+struct Division {
+    name: &'static str,
+    start_ma: f64,
+    end_ma: f64,
+}
+
+const fn division(name: &'static str, start_ma: f64, end_ma: f64) -> Division {
+    Division {
+        name,
+        start_ma,
+        end_ma,
+    }
+}
+
+/// Age of the Earth in millions of years; the start of the Hadean.
+const EARTH_AGE_MA: f64 = 4567.0;
+
+// Boundaries follow the ICS International Chronostratigraphic Chart (2023/09).
+const EONS: &[Division] = &[
+    division("Hadean", EARTH_AGE_MA, 4031.0),
+    division("Archean", 4031.0, 2500.0),
+    division("Proterozoic", 2500.0, 538.8),
+    division("Phanerozoic", 538.8, 0.0),
+];
+
+const ERAS: &[Division] = &[
+    division("Eoarchean", 4031.0, 3600.0),
+    division("Paleoarchean", 3600.0, 3200.0),
+    division("Mesoarchean", 3200.0, 2800.0),
+    division("Neoarchean", 2800.0, 2500.0),
+    division("Paleoproterozoic", 2500.0, 1600.0),
+    division("Mesoproterozoic", 1600.0, 1000.0),
+    division("Neoproterozoic", 1000.0, 538.8),
+    division("Paleozoic", 538.8, 251.902),
+    division("Mesozoic", 251.902, 66.0),
+    division("Cenozoic", 66.0, 0.0),
+];
+
+const PERIODS: &[Division] = &[
+    division("Cambrian", 538.8, 485.4),
+    division("Ordovician", 485.4, 443.8),
+    division("Silurian", 443.8, 419.2),
+    division("Devonian", 419.2, 358.9),
+    division("Carboniferous", 358.9, 298.9),
+    division("Permian", 298.9, 251.902),
+    division("Triassic", 251.902, 201.4),
+    division("Jurassic", 201.4, 143.1),
+    division("Cretaceous", 143.1, 66.0),
+    division("Paleogene", 66.0, 23.03),
+    division("Neogene", 23.03, 2.58),
+    division("Quaternary", 2.58, 0.0),
+];
+
+const EPOCHS: &[Division] = &[
+    division("Paleocene", 66.0, 56.0),
+    division("Eocene", 56.0, 33.9),
+    division("Oligocene", 33.9, 23.03),
+    division("Miocene", 23.03, 5.333),
+    division("Pliocene", 5.333, 2.58),
+    division("Pleistocene", 2.58, 0.0117),
+    division("Holocene", 0.0117, 0.0),
+];
+
+const AGES: &[Division] = &[
+    division("Gelasian", 2.58, 1.80),
+    division("Calabrian", 1.80, 0.774),
+    division("Chibanian", 0.774, 0.129),
+    division("Upper Pleistocene", 0.129, 0.0117),
+    division("Greenlandian", 0.0117, 0.0082),
+    division("Northgrippian", 0.0082, 0.0042),
+    division("Meghalayan", 0.0042, 0.0),
+];
+
+/// Age of the universe in years per the Planck 2018 results.
+const UNIVERSE_AGE_YEARS: f64 = 13.787e9;
+/// Seconds in a Julian year, the unit used for cosmological ages.
+const SECONDS_PER_JULIAN_YEAR: f64 = 365.25 * 86_400.0;
+
+#[derive(Debug, Deserialize)]
+pub struct EpochSegmentQuery {
+    scale: Option<String>,
+    years_ago: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum EpochSegment {
+    Geological(GeologicalSegment),
+    Cosmological(CosmologicalSegment),
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeologicalSegment {
+    eon: &'static str,
+    era: Option<&'static str>,
+    period: Option<&'static str>,
+    epoch: Option<&'static str>,
+    age: Option<&'static str>,
+    years_ago: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CosmologicalSegment {
+    cosmological_era: &'static str,
+    stellar_generation: &'static str,
+    universe_age_years: f64,
+    universe_age_seconds: f64,
+}
+
+pub async fn get_current_epoch_segment(
+    Query(params): Query<EpochSegmentQuery>,
+) -> Result<Json<EpochSegment>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let scale = params.scale.unwrap_or_else(|| "geological".to_string());
+
+    info!(
+        request_id = %request_id,
+        scale = %scale,
+        "Processing epoch segment request"
+    );
+
+    match scale.as_str() {
+        "geological" => {
+            let years_ago = params.years_ago.unwrap_or(0);
+            geological_segment(years_ago)
+                .map(|segment| Json(EpochSegment::Geological(segment)))
+                .ok_or_else(|| {
+                    error_response(
+                        ErrorCode::InvalidRequest,
+                        format!("years_ago must not exceed the age of the Earth ({EARTH_AGE_MA} million years)"),
+                        &request_id,
+                    )
+                })
+        }
+        "cosmological" => Ok(Json(EpochSegment::Cosmological(cosmological_segment()))),
+        other => Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!("Unsupported scale: {other} (expected geological or cosmological)"),
+            &request_id,
+        )),
+    }
+}
+
+fn geological_segment(years_ago: u64) -> Option<GeologicalSegment> {
+    let ma = years_ago as f64 / 1e6;
+    Some(GeologicalSegment {
+        eon: find_division(EONS, ma)?,
+        era: find_division(ERAS, ma),
+        period: find_division(PERIODS, ma),
+        epoch: find_division(EPOCHS, ma),
+        age: find_division(AGES, ma),
+        years_ago,
+    })
+}
+
+/// Finds the division spanning `ma`, treating each as `[end_ma, start_ma)`;
+/// the Hadean also includes its start, the formation of the Earth.
+fn find_division(divisions: &[Division], ma: f64) -> Option<&'static str> {
+    divisions
+        .iter()
+        .find(|division| {
+            ma >= division.end_ma
+                && (ma < division.start_ma || (ma == EARTH_AGE_MA && division.start_ma == ma))
+        })
+        .map(|division| division.name)
+}
+
+fn cosmological_segment() -> CosmologicalSegment {
+    // The Planck 2018 estimate was published in July 2018; count forward from there.
+    let reference = Utc
+        .with_ymd_and_hms(2018, 7, 17, 0, 0, 0)
+        .single()
+        .expect("valid reference date");
+    let elapsed_seconds = (Utc::now() - reference).num_seconds() as f64;
+    let universe_age_seconds = UNIVERSE_AGE_YEARS * SECONDS_PER_JULIAN_YEAR + elapsed_seconds;
+
+    CosmologicalSegment {
+        cosmological_era: "Stelliferous",
+        stellar_generation: "Population I",
+        universe_age_years: universe_age_seconds / SECONDS_PER_JULIAN_YEAR,
+        universe_age_seconds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_boundaries_in_the_younger_division() {
+        let extinction = geological_segment(66_000_000).unwrap();
+        assert_eq!(extinction.era, Some("Mesozoic"));
+        assert_eq!(extinction.period, Some("Cretaceous"));
+
+        let formation = geological_segment(4_567_000_000).unwrap();
+        assert_eq!(formation.eon, "Hadean");
+        assert_eq!(formation.era, None);
+        assert!(geological_segment(4_567_000_001).is_none());
+    }
+}
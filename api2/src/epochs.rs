@@ -0,0 +1,277 @@
+//! Conversions between Unix time and other epoch systems.
+
+use axum::response::Json;
+use chrono::{DateTime, LocalResult, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, localize, parse_timezone, ApiError};
+
+/// Julian Date of the Unix epoch (1970-01-01T00:00:00Z).
+pub(crate) const UNIX_EPOCH_JULIAN_DATE: f64 = 2_440_587.5;
+/// Unix time of the GPS epoch, 1980-01-06T00:00:00Z.
+const GPS_EPOCH_UNIX: i64 = 315_964_800;
+/// Leap seconds inserted into UTC since the GPS epoch (GPS time ignores them).
+const GPS_LEAP_SECONDS: i64 = 18;
+/// Unix time of J2000.0: 2000-01-01T12:00:00 TT, i.e. 11:58:55.816 UTC.
+const J2000_EPOCH_UNIX: f64 = 946_727_935.816;
+/// Leap seconds inserted into UTC since J2000.0 (TT ignores them).
+const J2000_LEAP_SECONDS: f64 = 5.0;
+/// Unix time of the Cocoa (Core Foundation) reference date, 2001-01-01T00:00:00Z.
+const COCOA_EPOCH_UNIX: f64 = 978_307_200.0;
+/// Windows FILETIME ticks (100 ns) between 1601-01-01 and the Unix epoch.
+const FILETIME_UNIX_EPOCH_TICKS: u64 = 116_444_736_000_000_000;
+/// `i64::MAX` seconds after the Unix epoch, beyond chrono's representable range.
+const SIGNED_64BIT_MAX_DATE: &str = "292277026596-12-04T15:30:07Z";
+/// Average seconds per Gregorian year.
+const SECONDS_PER_YEAR: f64 = 365.2425 * 86_400.0;
+
+#[derive(Debug, Serialize)]
+pub struct AlternativeEpochs {
+    unix_seconds: i64,
+    julian_date: f64,
+    gps_seconds: i64,
+    j2000_seconds: f64,
+    cocoa_seconds: f64,
+    windows_filetime: u64,
+}
+
+pub async fn get_time_since_epoch_alt() -> Json<AlternativeEpochs> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Processing alternative epoch request"
+    );
+
+    Json(alternative_epochs(Utc::now()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnixRollover {
+    year_2038_in_seconds: i64,
+    years_until_2038: f64,
+    #[serde(rename = "64bit_max_date")]
+    max_date_64bit: &'static str,
+    current_as_signed_32bit: i32,
+    current_as_unsigned_32bit: u32,
+    signed_32bit_overflow_at: String,
+    unsigned_32bit_overflow_at: String,
+}
+
+pub async fn get_unix_rollover() -> Json<UnixRollover> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Processing Unix rollover request"
+    );
+
+    Json(unix_rollover(Utc::now()))
+}
+
+fn unix_rollover(now: DateTime<Utc>) -> UnixRollover {
+    let unix_seconds = now.timestamp();
+    let year_2038_in_seconds = i64::from(i32::MAX) - unix_seconds;
+
+    UnixRollover {
+        year_2038_in_seconds,
+        years_until_2038: year_2038_in_seconds as f64 / SECONDS_PER_YEAR,
+        max_date_64bit: SIGNED_64BIT_MAX_DATE,
+        // Truncation is the point: these show what a 32-bit time_t would hold.
+        current_as_signed_32bit: unix_seconds as i32,
+        current_as_unsigned_32bit: unix_seconds as u32,
+        signed_32bit_overflow_at: last_representable(i64::from(i32::MAX)),
+        unsigned_32bit_overflow_at: last_representable(i64::from(u32::MAX)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnixToDateQuery {
+    epoch: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnixToDate {
+    epoch: i64,
+    timezone: String,
+    date: String,
+}
+
+pub async fn get_unix_to_date(
+    Query(params): Query<UnixToDateQuery>,
+) -> Result<Json<UnixToDate>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        epoch = ?params.epoch,
+        timezone = %timezone,
+        "Processing Unix-to-date request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let epoch = params
+        .epoch
+        .ok_or_else(|| bad_request("Missing required parameter: epoch".to_string()))?;
+    let epoch: i64 = epoch
+        .parse()
+        .map_err(|_| bad_request(format!("Invalid epoch: {epoch} (expected integer seconds)")))?;
+    let date = unix_to_date(epoch, tz)
+        .ok_or_else(|| bad_request(format!("epoch out of range: {epoch}")))?;
+
+    Ok(Json(UnixToDate {
+        epoch,
+        timezone,
+        date,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DateToUnixQuery {
+    date: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DateToUnix {
+    date: String,
+    timezone: String,
+    epoch: i64,
+}
+
+pub async fn get_date_to_unix(
+    Query(params): Query<DateToUnixQuery>,
+) -> Result<Json<DateToUnix>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        date = ?params.date,
+        timezone = %timezone,
+        "Processing date-to-Unix request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let date = params
+        .date
+        .ok_or_else(|| bad_request("Missing required parameter: date".to_string()))?;
+    let epoch = date_to_unix(&date, tz).map_err(bad_request)?;
+
+    Ok(Json(DateToUnix {
+        date,
+        timezone,
+        epoch,
+    }))
+}
+
+/// Formats `epoch` as RFC 3339 in `tz`, or `None` when the instant or its
+/// local time is outside chrono's range.
+fn unix_to_date(epoch: i64, tz: Tz) -> Option<String> {
+    let utc = DateTime::from_timestamp(epoch, 0)?;
+    Some(localize(utc, tz)?.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Parses a local `YYYY-MM-DDTHH:MM:SS` datetime in `tz` (or an RFC 3339
+/// datetime, whose own offset wins) into Unix seconds. Ambiguous local times
+/// resolve to the earlier instant; times skipped by a DST gap are rejected.
+fn date_to_unix(date: &str, tz: Tz) -> Result<i64, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Ok(datetime.timestamp());
+    }
+    let local = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S")
+        .map_err(|_| format!("Invalid date: {date} (expected YYYY-MM-DDTHH:MM:SS)"))?;
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Ok(datetime.timestamp())
+        }
+        LocalResult::None => Err(format!("{date} does not exist in {tz} (DST gap)")),
+    }
+}
+
+/// Formats the last second representable by a counter whose maximum is `max`.
+fn last_representable(max: i64) -> String {
+    Utc.timestamp_opt(max, 0)
+        .single()
+        .expect("32-bit limits are valid timestamps")
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn alternative_epochs(now: DateTime<Utc>) -> AlternativeEpochs {
+    let unix_seconds = now.timestamp();
+    let unix_fractional = unix_seconds as f64 + f64::from(now.timestamp_subsec_nanos()) / 1e9;
+    let unix_ticks = now
+        .timestamp_nanos_opt()
+        .map(|nanos| nanos / 100)
+        .expect("current time fits in i64 nanoseconds");
+
+    AlternativeEpochs {
+        unix_seconds,
+        julian_date: unix_fractional / 86_400.0 + UNIX_EPOCH_JULIAN_DATE,
+        gps_seconds: unix_seconds - GPS_EPOCH_UNIX + GPS_LEAP_SECONDS,
+        j2000_seconds: unix_fractional - J2000_EPOCH_UNIX + J2000_LEAP_SECONDS,
+        cocoa_seconds: unix_fractional - COCOA_EPOCH_UNIX,
+        windows_filetime: FILETIME_UNIX_EPOCH_TICKS + unix_ticks as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_from_each_epoch() {
+        let epochs = alternative_epochs(Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(epochs.unix_seconds, 978_307_200);
+        assert_eq!(epochs.cocoa_seconds, 0.0);
+        assert_eq!(epochs.julian_date, 2_451_910.5);
+        assert_eq!(epochs.gps_seconds, 662_342_418);
+        assert_eq!(epochs.windows_filetime, 126_227_808_000_000_000);
+        assert!((epochs.j2000_seconds - 31_579_269.184).abs() < 1e-6);
+    }
+
+    #[test]
+    fn year_2038_boundary() {
+        let rollover = unix_rollover(Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 0).unwrap());
+        assert_eq!(rollover.signed_32bit_overflow_at, "2038-01-19T03:14:07Z");
+        assert_eq!(rollover.unsigned_32bit_overflow_at, "2106-02-07T06:28:15Z");
+        assert_eq!(rollover.year_2038_in_seconds, 7);
+        assert_eq!(rollover.current_as_signed_32bit, i32::MAX - 7);
+    }
+
+    #[test]
+    fn signed_32bit_wraps_after_2038() {
+        let rollover = unix_rollover(Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 8).unwrap());
+        assert_eq!(rollover.current_as_signed_32bit, i32::MIN);
+        assert_eq!(rollover.year_2038_in_seconds, -1);
+    }
+
+    #[test]
+    fn unix_and_local_dates_round_trip() {
+        let new_york = chrono_tz::America::New_York;
+        assert_eq!(
+            unix_to_date(1_705_320_000, new_york).as_deref(),
+            Some("2024-01-15T07:00:00-05:00")
+        );
+        assert_eq!(
+            date_to_unix("2024-01-15T00:00:00", new_york),
+            Ok(1_705_294_800)
+        );
+        assert_eq!(
+            date_to_unix("2024-01-15T05:00:00Z", new_york),
+            Ok(1_705_294_800)
+        );
+        assert!(date_to_unix("2024-03-10T02:30:00", new_york).is_err());
+        assert!(unix_to_date(i64::MAX, new_york).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+//! Generational cohorts ("micro-eras") for a birth year.
+
+use axum::response::Json;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+const FIRST_YEAR: i32 = 1900;
+const LAST_YEAR: i32 = 2030;
+/// Reference year for the `age_in_2024` field.
+const AGE_REFERENCE_YEAR: i32 = 2024;
+
+struct Generation {
+    name: &'static str,
+    first_year: i32,
+    last_year: i32,
+    cultural_touchstones: &'static [&'static str],
+}
+
+/// Strauss–Howe generational sequence, using the commonly cited Pew Research
+/// birth-year boundaries (e.g. Millennials 1981–1996) rather than Strauss and
+/// Howe's original ones, which run each cohort a year or more later.
+const GENERATIONS: &[Generation] = &[
+    Generation {
+        name: "Lost Generation",
+        first_year: 1883,
+        last_year: 1900,
+        cultural_touchstones: &["World War I", "Roaring Twenties", "Prohibition"],
+    },
+    Generation {
+        name: "Greatest Generation",
+        first_year: 1901,
+        last_year: 1927,
+        cultural_touchstones: &["Great Depression", "World War II", "New Deal"],
+    },
+    Generation {
+        name: "Silent Generation",
+        first_year: 1928,
+        last_year: 1945,
+        cultural_touchstones: &["Korean War", "Rise of Rock and Roll", "Post-war prosperity"],
+    },
+    Generation {
+        name: "Baby Boomers",
+        first_year: 1946,
+        last_year: 1964,
+        cultural_touchstones: &["Civil Rights Movement", "Moon Landing", "Vietnam War"],
+    },
+    Generation {
+        name: "Generation X",
+        first_year: 1965,
+        last_year: 1980,
+        cultural_touchstones: &["Rise of Personal Computers", "MTV", "End of the Cold War"],
+    },
+    Generation {
+        name: "Millennials",
+        first_year: 1981,
+        last_year: 1996,
+        cultural_touchstones: &["Fall of Berlin Wall", "Rise of Internet", "9/11"],
+    },
+    Generation {
+        name: "Generation Z",
+        first_year: 1997,
+        last_year: 2012,
+        cultural_touchstones: &["Smartphones", "Social Media", "Global Financial Crisis"],
+    },
+    Generation {
+        name: "Generation Alpha",
+        first_year: 2013,
+        last_year: 2024,
+        cultural_touchstones: &[
+            "Tablets from Birth",
+            "COVID-19 Pandemic",
+            "Voice Assistants",
+        ],
+    },
+    Generation {
+        name: "Generation Beta",
+        first_year: 2025,
+        last_year: 2039,
+        cultural_touchstones: &["Generative AI", "Climate Adaptation"],
+    },
+];
+
+#[derive(Debug, Deserialize)]
+pub struct MicroEraQuery {
+    year: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MicroEra {
+    generation: &'static str,
+    birth_year_range: String,
+    age_in_2024: String,
+    cultural_touchstones: &'static [&'static str],
+}
+
+pub async fn get_micro_era(
+    Query(params): Query<MicroEraQuery>,
+) -> Result<Json<MicroEra>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        year = ?params.year,
+        "Processing micro-era request"
+    );
+
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let year = params
+        .year
+        .ok_or_else(|| bad_request("Missing required parameter: year".to_string()))?;
+    let year: i32 = year
+        .parse()
+        .map_err(|_| bad_request(format!("Invalid year: {year}")))?;
+    if !(FIRST_YEAR..=LAST_YEAR).contains(&year) {
+        return Err(bad_request(format!(
+            "year must be between {FIRST_YEAR} and {LAST_YEAR}, got {year}"
+        )));
+    }
+
+    let generation = generation_for(year).expect("generation table covers the accepted years");
+
+    Ok(Json(MicroEra {
+        generation: generation.name,
+        birth_year_range: format!("{}-{}", generation.first_year, generation.last_year),
+        age_in_2024: age_range(generation),
+        cultural_touchstones: generation.cultural_touchstones,
+    }))
+}
+
+fn generation_for(year: i32) -> Option<&'static Generation> {
+    GENERATIONS
+        .iter()
+        .find(|generation| (generation.first_year..=generation.last_year).contains(&year))
+}
+
+/// Ages reached during 2024 by the cohort's youngest and oldest members.
+fn age_range(generation: &Generation) -> String {
+    if generation.first_year > AGE_REFERENCE_YEAR {
+        return "not yet born".to_string();
+    }
+    let youngest = AGE_REFERENCE_YEAR - generation.last_year.min(AGE_REFERENCE_YEAR);
+    let oldest = AGE_REFERENCE_YEAR - generation.first_year;
+    format!("{youngest}-{oldest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_covers_accepted_years_without_gaps() {
+        for year in FIRST_YEAR..=LAST_YEAR {
+            assert!(generation_for(year).is_some(), "no generation for {year}");
+        }
+        let millennials = generation_for(1990).unwrap();
+        assert_eq!(millennials.name, "Millennials");
+        assert_eq!(age_range(millennials), "28-43");
+    }
+}
@@ -0,0 +1,169 @@
+//! The gRPC `time.v1.TimeService` (see `proto/time.proto`), served on
+//! `API2_GRPC_PORT` with the same logic as `GET /time` and
+//! `GET /time/convert`, and the same body size and timeout limits and
+//! tenant checks.
+
+use common::auth::API_KEY_HEADER;
+use common::grpc::{
+    ConvertTimeRequest, Metadata, Server, Status, CONVERT_TIME_PATH, GET_TIME_PATH,
+};
+use common::tenants::{Tenants, TENANT_HEADER};
+use common::TimeQuery;
+use reqwest::header::HeaderMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{convert, resolve_time, ApiError, AppState};
+
+fn status(error: ApiError) -> Status {
+    Status::from_http(error.status, error.error)
+}
+
+fn request_id(metadata: Metadata) -> String {
+    metadata
+        .request_id
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Refuses calls the tenant layer would refuse on the matching HTTP route.
+fn authorize(tenants: &Tenants, path: &str, metadata: &HeaderMap) -> Result<(), Status> {
+    let route = match path {
+        GET_TIME_PATH => "/time",
+        CONVERT_TIME_PATH => "/time/convert",
+        other => other,
+    };
+    let header = |name| metadata.get(name).and_then(|value| value.to_str().ok());
+    tenants
+        .admit(route, header(TENANT_HEADER), header(API_KEY_HEADER))
+        .map(|_| ())
+        .map_err(|(code, detail)| {
+            warn!(path = %path, "{detail}");
+            Status::from_http(code.status().as_u16(), detail)
+        })
+}
+
+pub fn server(state: AppState) -> Server {
+    let tenants = state.tenants.clone();
+    Server::default()
+        .limits(state.limits.max_body_bytes(), state.limits.timeout())
+        .authorize(move |path, metadata| authorize(&tenants, path, metadata))
+        .unary(GET_TIME_PATH, move |metadata, query: TimeQuery| {
+            let state = state.clone();
+            async move {
+                let request_id = request_id(metadata);
+                info!(request_id = %request_id, "Processing gRPC GetTime");
+                resolve_time(
+                    &state,
+                    query.timezone,
+                    query.format.as_deref(),
+                    query.locale.as_deref(),
+                    request_id,
+                )
+                .await
+                .map_err(status)
+            }
+        })
+        .unary(
+            CONVERT_TIME_PATH,
+            |metadata, request: ConvertTimeRequest| async move {
+                let request_id = request_id(metadata);
+                info!(request_id = %request_id, "Processing gRPC ConvertTime");
+                convert::convert_time(request.timestamp, request.from, request.to, request_id)
+                    .map_err(status)
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::grpc::{Client, Code};
+    use common::{TimeConvertResponse, TimeResponse};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn answers_get_time_and_convert_time() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(server(crate::tests::test_state()).serve(listener, std::future::pending()));
+        let client = Client::default();
+        let timeout = Duration::from_secs(2);
+
+        let query = TimeQuery {
+            timezone: Some("Asia/Kolkata".to_string()),
+            ..TimeQuery::default()
+        };
+        let time: TimeResponse = client
+            .unary(&base_url, GET_TIME_PATH, &query, "req-1", timeout)
+            .await
+            .unwrap();
+        assert_eq!(time.request_id, "req-1");
+        assert_eq!(time.utc_offset_seconds, Some(19800));
+        assert!(time.timestamp.ends_with("+05:30"));
+
+        let request = ConvertTimeRequest {
+            timestamp: Some("2024-03-10T02:30:00".to_string()),
+            from: Some("America/New_York".to_string()),
+            to: Some("UTC".to_string()),
+        };
+        let status = client
+            .unary::<_, TimeConvertResponse>(
+                &base_url,
+                CONVERT_TIME_PATH,
+                &request,
+                "req-2",
+                timeout,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code, Code::FailedPrecondition);
+        assert_eq!(status.http_status(), 422);
+        assert!(status.message.starts_with("Nonexistent local time"));
+
+        let request = ConvertTimeRequest {
+            timestamp: Some("0".to_string()),
+            to: Some("Asia/Tokyo".to_string()),
+            ..ConvertTimeRequest::default()
+        };
+        let converted: TimeConvertResponse = client
+            .unary(&base_url, CONVERT_TIME_PATH, &request, "req-3", timeout)
+            .await
+            .unwrap();
+        assert_eq!(converted.to.timestamp, "1970-01-01T09:00:00+09:00");
+    }
+
+    #[test]
+    fn refuses_calls_the_tenant_layer_would_refuse() {
+        let tenants = Tenants::new(vec![
+            common::tenants::Tenant::parse("team-a;keys=key-a;endpoints=/time/convert").unwrap(),
+            common::tenants::Tenant::parse("team-b").unwrap(),
+        ]);
+        let metadata = |pairs: &[(&'static str, &'static str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+        let code =
+            |path, pairs| authorize(&tenants, path, &metadata(pairs)).map_err(|status| status.code);
+
+        assert_eq!(code(GET_TIME_PATH, &[]), Ok(()));
+        assert_eq!(code(GET_TIME_PATH, &[(TENANT_HEADER, "team-b")]), Ok(()));
+        assert_eq!(
+            code(CONVERT_TIME_PATH, &[(API_KEY_HEADER, "key-a")]),
+            Ok(())
+        );
+        assert_eq!(
+            code(GET_TIME_PATH, &[(API_KEY_HEADER, "key-a")]),
+            Err(Code::PermissionDenied)
+        );
+        assert_eq!(
+            code(CONVERT_TIME_PATH, &[(TENANT_HEADER, "team-a")]),
+            Err(Code::Unauthenticated)
+        );
+        assert_eq!(
+            code(GET_TIME_PATH, &[(TENANT_HEADER, "team-z")]),
+            Err(Code::InvalidArgument)
+        );
+    }
+}
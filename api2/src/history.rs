@@ -0,0 +1,222 @@
+//! Recent `/time` responses kept in memory for debugging, served by
+//! `/time/history`.
+
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Utc};
+use common::config::Config;
+use common::problem::Query;
+use common::TimeResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::AppState;
+
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_MAX_AGE_SECS: u64 = 3600;
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub recorded_at: String,
+    #[serde(flatten)]
+    pub response: TimeResponse,
+}
+
+/// Ring buffer of the latest responses, oldest first. Entries past `max_age`
+/// are only dropped when the history is read.
+pub struct History {
+    capacity: usize,
+    max_age: chrono::Duration,
+    entries: RwLock<VecDeque<(DateTime<Utc>, TimeResponse)>>,
+}
+
+impl History {
+    pub fn new(capacity: usize, max_age_secs: u64) -> Self {
+        History {
+            capacity,
+            max_age: i64::try_from(max_age_secs)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .unwrap_or(chrono::Duration::MAX),
+            entries: RwLock::new(VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY))),
+        }
+    }
+
+    /// Uses `history_capacity` and `history_max_age_secs`, falling back to
+    /// 1000 entries and one hour.
+    pub fn from_config(config: &Config) -> Self {
+        History::new(
+            config.history_capacity.unwrap_or(DEFAULT_CAPACITY),
+            config.history_max_age_secs.unwrap_or(DEFAULT_MAX_AGE_SECS),
+        )
+    }
+
+    /// Appends `response`, dropping the oldest entry once at capacity.
+    pub async fn record(&self, response: &TimeResponse, now: DateTime<Utc>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.write().await;
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((now, response.clone()));
+    }
+
+    /// Up to `limit` of the newest entries, newest first, optionally only
+    /// those for `timezone`.
+    pub async fn recent(
+        &self,
+        limit: usize,
+        timezone: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<HistoryEntry> {
+        let cutoff = now
+            .checked_sub_signed(self.max_age)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let stale = |entries: &VecDeque<(DateTime<Utc>, TimeResponse)>| {
+            entries.front().is_some_and(|(at, _)| *at < cutoff)
+        };
+        // Readers only contend with writers when there is something to evict.
+        if stale(&*self.entries.read().await) {
+            let mut entries = self.entries.write().await;
+            while stale(&entries) {
+                entries.pop_front();
+            }
+        }
+
+        let entries = self.entries.read().await;
+        entries
+            .iter()
+            .rev()
+            .filter(|(at, response)| {
+                *at >= cutoff && timezone.is_none_or(|timezone| response.timezone == timezone)
+            })
+            .take(limit)
+            .map(|(at, response)| HistoryEntry {
+                recorded_at: at.to_rfc3339(),
+                response: response.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    limit: Option<usize>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryResponse {
+    count: usize,
+    entries: Vec<HistoryEntry>,
+}
+
+pub async fn get_time_history(
+    State(state): State<AppState>,
+    Query(params): Query<HistoryQuery>,
+) -> Json<HistoryResponse> {
+    let request_id = Uuid::new_v4().to_string();
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    info!(
+        request_id = %request_id,
+        limit,
+        timezone = params.timezone.as_deref().unwrap_or(""),
+        "Processing time history request"
+    );
+
+    let entries = state
+        .history
+        .recent(limit, params.timezone.as_deref(), Utc::now())
+        .await;
+    Json(HistoryResponse {
+        count: entries.len(),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(timezone: &str, request_id: &str) -> TimeResponse {
+        TimeResponse {
+            timestamp: "2025-07-01T00:00:00+00:00".to_string(),
+            timezone: timezone.to_string(),
+            request_id: request_id.to_string(),
+            source: "api2-service".to_string(),
+            utc_offset_seconds: Some(0),
+            utc_offset_label: Some("+00:00".to_string()),
+            display: None,
+            format: None,
+            degraded: None,
+            clock_offset_ms: None,
+        }
+    }
+
+    fn request_ids(entries: &[HistoryEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.response.request_id.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn keeps_only_the_newest_entries() {
+        let history = History::new(3, 3600);
+        let now = Utc::now();
+        for i in 0..5 {
+            history.record(&response("UTC", &i.to_string()), now).await;
+        }
+        assert_eq!(history.entries.read().await.len(), 3);
+        assert_eq!(
+            request_ids(&history.recent(10, None, now).await),
+            ["4", "3", "2"]
+        );
+        assert_eq!(request_ids(&history.recent(1, None, now).await), ["4"]);
+    }
+
+    #[tokio::test]
+    async fn evicts_expired_entries_when_read() {
+        let history = History::new(10, 60);
+        let start = Utc::now();
+        history.record(&response("UTC", "old"), start).await;
+        history
+            .record(
+                &response("UTC", "new"),
+                start + chrono::Duration::seconds(30),
+            )
+            .await;
+
+        let later = start + chrono::Duration::seconds(61);
+        assert_eq!(request_ids(&history.recent(10, None, later).await), ["new"]);
+        assert_eq!(history.entries.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn filters_by_timezone() {
+        let history = History::new(10, 3600);
+        let now = Utc::now();
+        for (timezone, request_id) in [("UTC", "1"), ("Asia/Tokyo", "2"), ("UTC", "3")] {
+            history.record(&response(timezone, request_id), now).await;
+        }
+        assert_eq!(
+            request_ids(&history.recent(10, Some("UTC"), now).await),
+            ["3", "1"]
+        );
+        assert_eq!(
+            request_ids(&history.recent(10, Some("Asia/Tokyo"), now).await),
+            ["2"]
+        );
+        assert!(history
+            .recent(10, Some("Europe/Paris"), now)
+            .await
+            .is_empty());
+    }
+}
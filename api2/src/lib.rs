@@ -0,0 +1,954 @@
+//! API2, the time provider.
+//!
+//! [`app`] builds the router, so tests can drive it without a listener;
+//! [`run`] serves it as the `api2` binary does.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+    routing::{get, post, MethodRouter},
+    Router,
+};
+use chrono::{Offset, TimeZone};
+use chrono_tz::Tz;
+use common::audit::{self, AuditLayer, AuditLog};
+use common::chaos::{self, Chaos};
+use common::compression::CompressionLayer;
+use common::config::Config;
+use common::cors::CorsPolicy;
+use common::etag;
+use common::flags::{self, FeatureFlags};
+use common::format::{format_utc_offset, TimestampFormat};
+use common::health;
+use common::limits::{self, RequestLimits};
+use common::logging;
+use common::metrics::{self, MetricsLayer, Registry};
+use common::payload_log::{self, PayloadLog};
+use common::problem::{ErrorCode, Query};
+use common::shutdown::{self, Shutdown};
+use common::tenants::{TenantLayer, Tenants};
+use common::tls;
+use common::trace_context::trace_context_middleware;
+use common::unix::{self, SocketMode};
+use common::versioning;
+use common::{resolve_timezone_alias, ErrorResponse, TimeQuery, TimeResponse, REQUEST_ID_HEADER};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+mod admin;
+mod alarms;
+mod ancient;
+mod auth;
+mod batch;
+mod business;
+mod calendar;
+mod checksum;
+mod conversions;
+mod convert;
+mod cron;
+mod decade;
+mod distribution;
+mod duration;
+mod epoch_segment;
+mod epochs;
+mod generations;
+mod grpc;
+mod history;
+mod locale;
+mod market_time;
+mod meeting;
+mod moment;
+mod named_moments;
+mod ntp;
+mod ntp_info;
+mod openapi;
+mod payroll;
+mod periods;
+mod places;
+mod poll;
+mod readiness;
+mod recurrence;
+mod retail_calendar;
+mod seasons;
+mod sidereal;
+mod stats;
+mod stream;
+mod sun;
+mod time_request;
+mod timers;
+mod timezones;
+mod transitions;
+mod tz_distance;
+mod tzdata;
+mod v2;
+mod ws;
+
+/// Error half of every handler result, sent as `application/problem+json`.
+type ApiError = ErrorResponse;
+
+fn error_response(code: ErrorCode, error: impl Into<String>, request_id: &str) -> ApiError {
+    ErrorResponse::new(code, error, request_id)
+}
+
+/// An error whose status was decided elsewhere, such as by an extractor.
+fn error_for_status(status: StatusCode, error: impl Into<String>, request_id: &str) -> ApiError {
+    ErrorResponse::with_status(status, ErrorCode::from_status(status), error, request_id)
+}
+
+/// Parses an IANA timezone name, mapping failures to a `400 Bad Request`.
+fn parse_timezone(name: &str, request_id: &str) -> Result<Tz, ApiError> {
+    name.parse::<Tz>().map_err(|_| {
+        error_response(
+            ErrorCode::InvalidTimezone,
+            format!("Invalid timezone: {name}"),
+            request_id,
+        )
+    })
+}
+
+/// `at` as local time in `tz`, or `None` when that local time lies past
+/// chrono's range, as it can within a day of `DateTime::<Utc>::MIN_UTC` or
+/// `MAX_UTC`.
+fn localize(at: chrono::DateTime<chrono::Utc>, tz: Tz) -> Option<chrono::DateTime<Tz>> {
+    let offset = tz.offset_from_utc_datetime(&at.naive_utc()).fix();
+    at.naive_utc().checked_add_offset(offset)?;
+    Some(at.with_timezone(&tz))
+}
+
+/// Unwraps a JSON request body, reporting malformed bodies as a JSON `400`.
+fn json_body<T>(body: Result<Json<T>, JsonRejection>, request_id: &str) -> Result<T, ApiError> {
+    body.map(|Json(value)| value).map_err(|rejection| {
+        error_response(ErrorCode::InvalidRequest, rejection.body_text(), request_id)
+    })
+}
+
+const DEFAULT_PORT: u16 = 4000;
+
+const TIMEZONE_REQUESTS_TOTAL: metrics::Counter = metrics::Counter {
+    name: "timezone_requests_total",
+    help: "Resolved time lookups by timezone, across /time, /time/batch and /time/stream.",
+};
+
+/// State shared by every handler, built once at startup.
+#[derive(Clone)]
+pub struct AppState {
+    api_keys: Arc<Vec<String>>,
+    timezone_stats: Arc<stats::TimezoneStats>,
+    metrics: Arc<Registry>,
+    max_batch_size: usize,
+    /// Sorted IANA names, built once at startup for `/timezones`.
+    timezone_names: Arc<Vec<&'static str>>,
+    audit: Option<AuditLog>,
+    history: Arc<history::History>,
+    /// Open `/time/ws` subscriptions across all connections.
+    ws_subscriptions: Arc<Semaphore>,
+    /// How long a `/time/stream` connection may stay open.
+    stream_max_lifetime: Duration,
+    /// `/time/poll` settings and waiting requests.
+    polls: Arc<poll::Polls>,
+    limits: Arc<RequestLimits>,
+    timers: Arc<timers::TimerStore>,
+    alarms: Arc<alarms::AlarmStore>,
+    /// Set when `NTP_SERVER` enables clock drift monitoring.
+    clock: Option<Arc<ntp::ClockMonitor>>,
+    /// Set when `TZDATA_MANIFEST_URL` enables the tzdata freshness check.
+    tzdata: Option<Arc<tzdata::FreshnessMonitor>>,
+    payload_log: Arc<PayloadLog>,
+    flags: Arc<FeatureFlags>,
+    /// Set when `CHAOS_ENABLED` lets `/admin/chaos` inject faults.
+    chaos: Option<Arc<Chaos>>,
+    tenants: Arc<Tenants>,
+    shutdown: Shutdown,
+}
+
+impl AppState {
+    /// Every setting in `config`; the shutdown signal is added by [`run`].
+    pub fn from_config(config: &Config) -> Self {
+        let metrics = Arc::new(Registry::default());
+        AppState {
+            api_keys: Arc::new(config.api_keys()),
+            timezone_stats: Arc::new(stats::TimezoneStats::default()),
+            tzdata: tzdata::FreshnessMonitor::from_config(config, metrics.clone()).map(Arc::new),
+            chaos: Chaos::from_config(config, metrics.clone()).map(Arc::new),
+            metrics,
+            max_batch_size: config.max_batch_size(),
+            timezone_names: Arc::new(timezones::timezone_names()),
+            audit: AuditLog::from_config(config),
+            history: Arc::new(history::History::from_config(config)),
+            ws_subscriptions: Arc::new(Semaphore::new(
+                config
+                    .ws_max_subscriptions
+                    .unwrap_or(ws::DEFAULT_MAX_SUBSCRIPTIONS),
+            )),
+            stream_max_lifetime: config
+                .stream_max_lifetime_secs
+                .map_or(stream::DEFAULT_MAX_LIFETIME, Duration::from_secs),
+            polls: Arc::new(poll::Polls::from_config(config)),
+            limits: Arc::new(RequestLimits::from_config(config)),
+            timers: Arc::new(timers::TimerStore::from_config(config)),
+            alarms: Arc::new(alarms::AlarmStore::from_config(config)),
+            clock: ntp::ClockMonitor::from_config(config).map(Arc::new),
+            payload_log: Arc::new(PayloadLog::from_config(config)),
+            flags: Arc::new(FeatureFlags::from_config(config)),
+            tenants: Arc::new(Tenants::from_config(config)),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    /// Counts a successful lookup of `timezone` in the `/stats` usage and
+    /// the `/metrics` counter. Only called for valid zones, which keeps the
+    /// label's cardinality bounded by the timezone database.
+    fn record_timezone_use(&self, timezone: &str) {
+        self.timezone_stats.record(timezone);
+        self.metrics
+            .increment(&TIMEZONE_REQUESTS_TOTAL, &[("timezone", timezone)]);
+    }
+
+    /// The clock offset measured by NTP monitoring, when enabled.
+    fn clock_offset_ms(&self) -> Option<i64> {
+        self.clock.as_ref().and_then(|clock| clock.offset_ms())
+    }
+}
+
+/// Serves API2 on the listeners `config` selects, plus gRPC, until a
+/// shutdown signal, then drains in-flight requests. Logging is set up by the
+/// caller.
+pub async fn run(config: Config) {
+    info!("API2 initializing");
+
+    let port = config.port.unwrap_or(DEFAULT_PORT);
+    let cors = CorsPolicy::from_config(&config);
+
+    if config.api_keys().is_empty() {
+        warn!("API_KEYS is not set; API-key protected endpoints will reject all requests");
+    }
+    if config.chaos_enabled == Some(true) {
+        warn!("CHAOS_ENABLED is set; requests may be delayed or failed on purpose");
+    }
+
+    let shutdown = Shutdown::on_signal().with_delay(config.shutdown_delay());
+    let state = AppState {
+        shutdown: shutdown.clone(),
+        ..AppState::from_config(&config)
+    };
+    state.alarms.resume();
+    if let Some(clock) = &state.clock {
+        clock.spawn();
+    }
+    if let Some(tzdata) = &state.tzdata {
+        tzdata.spawn();
+    }
+    let grpc = grpc::server(state.clone());
+    let app = app(state, &cors);
+
+    let socket_mode = SocketMode::from_config(&config);
+    let unix_app = app.clone();
+    let unix_shutdown = shutdown.clone();
+
+    let tcp_server = async {
+        if !socket_mode.tcp() {
+            return Ok(());
+        }
+        let tls = tls::acceptor_from_config(&config);
+        let scheme = if tls.is_some() { "HTTPS" } else { "HTTP" };
+        info!("API2 starting on port {} ({})", port, scheme);
+
+        let addr = SocketAddr::new(config.bind_address(), port);
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        info!("HTTP server listening on: {}", addr);
+
+        match tls {
+            Some(acceptor) => {
+                let server = tls::serve(listener, acceptor, app, shutdown.clone().requested());
+                shutdown
+                    .clone()
+                    .drain(server, config.shutdown_timeout())
+                    .await
+            }
+            None => {
+                let server = axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(shutdown.clone().requested());
+                shutdown
+                    .clone()
+                    .drain(server, config.shutdown_timeout())
+                    .await
+            }
+        }
+    };
+    let unix_server = async {
+        if !socket_mode.unix() {
+            return Ok(());
+        }
+        unix::serve_from_config(&config, unix_app, unix_shutdown).await
+    };
+    let grpc_server = async {
+        let Some(grpc_port) = config.grpc_port else {
+            return Ok(());
+        };
+        let addr = SocketAddr::new(config.bind_address(), grpc_port);
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        info!("gRPC server listening on: {}", addr);
+        let server = grpc.serve(listener, shutdown.clone().requested());
+        shutdown
+            .clone()
+            .drain(server, config.shutdown_timeout())
+            .await
+    };
+    // All listeners stop on the same signal and drain independently.
+    tokio::try_join!(tcp_server, unix_server, grpc_server).unwrap();
+    shutdown.log_summary();
+    info!("API2 shut down");
+}
+
+/// The router of every API2 route, with its middleware.
+pub fn app(state: AppState, cors: &CorsPolicy) -> Router {
+    let cors = cors.layer();
+    let metrics_layer = MetricsLayer::new(state.metrics.clone());
+    let audit_layer = AuditLayer::new(state.audit.clone());
+    let tenant_layer = TenantLayer::new(state.tenants.clone());
+
+    let v1 = api_routes(get(get_time).post(time_request::post_time));
+    let v2 = api_routes(get(v2::get_time).post(v2::post_time));
+
+    Router::new()
+        .route("/", get(root))
+        .route("/health", get(health_check))
+        .route(health::LIVE_PATH, get(readiness::get_live))
+        .route(health::READY_PATH, get(readiness::get_ready))
+        .route(metrics::METRICS_PATH, get(get_metrics))
+        .merge(common::openapi::routes(openapi::document().into_json()))
+        .merge(
+            v1.clone()
+                .route_layer(axum::middleware::from_fn(versioning::deprecated)),
+        )
+        .nest(versioning::V1_PREFIX, v1)
+        .nest(versioning::V2_PREFIX, v2)
+        .route(
+            payload_log::ADMIN_PATH,
+            get(admin::get_payload_logging).put(admin::put_payload_logging),
+        )
+        .route(
+            logging::ADMIN_PATH,
+            get(admin::get_log_level).put(admin::put_log_level),
+        )
+        .route(audit::ADMIN_PATH, get(admin::get_audit))
+        .route(
+            flags::ADMIN_PATH,
+            get(admin::get_flags).put(admin::put_flags),
+        )
+        .route(
+            chaos::ADMIN_PATH,
+            get(admin::get_chaos).put(admin::put_chaos),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.chaos.clone(),
+            chaos::inject,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.flags.clone(),
+            flags::enforce,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.payload_log.clone(),
+            payload_log::log,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.limits.clone(),
+            limits::enforce,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.shutdown.clone(),
+            shutdown::track_in_flight,
+        ))
+        .with_state(state)
+        .layer(axum::middleware::from_fn(logging::access_log))
+        .layer(axum::middleware::from_fn(trace_context_middleware))
+        .layer(metrics_layer)
+        .layer(
+            ServiceBuilder::new()
+                // Assign an X-Request-ID when the caller sent none, and echo it back.
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(tenant_layer)
+                .layer(audit_layer)
+                .layer(TraceLayer::new_for_http())
+                .layer(CompressionLayer::new())
+                .layer(cors),
+        )
+}
+
+/// The routes served under each API version, which differ only in `/time`.
+fn api_routes(time: MethodRouter<AppState>) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/time",
+            time.route_layer(axum::middleware::from_fn(etag::conditional)),
+        )
+        .route("/time/history", get(history::get_time_history))
+        .route("/time/batch", post(batch::post_time_batch))
+        .route("/time/diff", get(tz_distance::get_time_diff))
+        .route("/time/convert", get(convert::get_time_convert))
+        .route("/time/stream", get(stream::get_time_stream))
+        .route("/time/poll", get(poll::get_time_poll))
+        .route("/time/ws", get(ws::get_time_ws))
+        .route("/time/by-city", get(places::get_time_by_city))
+        .route("/time/by-location", get(places::get_time_by_location))
+        .route(
+            "/timezones",
+            get(timezones::get_timezones).route_layer(axum::middleware::from_fn(etag::conditional)),
+        )
+        .route("/timezones/version", get(tzdata::get_timezones_version))
+        .route("/timezone/*name", get(timezones::get_timezone))
+        .route("/time/business", get(business::get_business_hours))
+        .route("/time/payroll-period", get(payroll::get_payroll_period))
+        .route(
+            "/time/recurring-event-check",
+            get(recurrence::get_recurring_event_check),
+        )
+        .route(
+            "/time/hour-of-day-distribution",
+            get(distribution::get_hour_of_day_distribution),
+        )
+        .route(
+            "/time/time-since-epoch-alt",
+            get(epochs::get_time_since_epoch_alt),
+        )
+        .route(
+            "/time/moment-comparison",
+            get(moment::get_moment_comparison),
+        )
+        .route(
+            "/time/per-timezone-stats",
+            get(stats::get_per_timezone_stats),
+        )
+        .route("/time/ancient-calendar", get(ancient::get_ancient_calendar))
+        .route(
+            "/time/current-epoch-segment",
+            get(epoch_segment::get_current_epoch_segment),
+        )
+        .route(
+            "/time/network-time-protocol-info",
+            get(ntp_info::get_network_time_protocol_info),
+        )
+        .route(
+            "/time/astronomical-season",
+            get(seasons::get_astronomical_season),
+        )
+        .route(
+            "/time/decade-century-millennium",
+            get(decade::get_decade_century_millennium),
+        )
+        .route(
+            "/time/retail-calendar",
+            get(retail_calendar::get_retail_calendar),
+        )
+        .route("/time/unix-rollover", get(epochs::get_unix_rollover))
+        .route(
+            "/time/timezone-transition-list",
+            get(transitions::get_timezone_transition_list),
+        )
+        .route(
+            "/time/timezone-distance",
+            get(tz_distance::get_timezone_distance),
+        )
+        .route("/time/sidereal", get(sidereal::get_sidereal))
+        .route("/time/sun", get(sun::get_sun))
+        .route("/time/unix-to-date", get(epochs::get_unix_to_date))
+        .route("/time/date-to-unix", get(epochs::get_date_to_unix))
+        .route("/convert/epoch", get(conversions::get_convert_epoch))
+        .route("/convert/calendar", get(conversions::get_convert_calendar))
+        .route("/time/micro-era", get(generations::get_micro_era))
+        .route("/time/named-moment", get(named_moments::get_named_moment))
+        .route(
+            "/time/market-microstructure-time",
+            get(market_time::get_market_microstructure_time),
+        )
+        .route("/time/checksum", get(checksum::get_checksum))
+        .route("/time/period-overlap", get(periods::get_period_overlap))
+        .route(
+            "/time/slot-availability",
+            post(periods::post_slot_availability),
+        )
+        .route("/time/meeting", post(meeting::post_meeting))
+        .route("/time/merge-periods", post(periods::post_merge_periods))
+        .route(
+            "/time/complement-periods",
+            post(periods::post_complement_periods),
+        )
+        .route("/cron/next", post(cron::post_cron_next))
+        .route("/duration/parse", get(duration::get_duration_parse))
+        .route("/duration/humanize", get(duration::get_duration_humanize))
+        .route("/timer", post(timers::post_timer))
+        .route(
+            "/timer/:id",
+            get(timers::get_timer).delete(timers::delete_timer),
+        )
+        .route("/alarms", get(alarms::get_alarms).post(alarms::post_alarm))
+        .route(
+            "/alarms/:id",
+            get(alarms::get_alarm).delete(alarms::delete_alarm),
+        )
+}
+
+async fn root() -> &'static str {
+    "API2 - Time Service Provider"
+}
+
+async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.shutdown.is_requested() {
+        return state.shutdown.health_response("api2");
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "healthy",
+            "service": "api2",
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "feature_flags": state.flags.states()
+        })),
+    )
+}
+
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        state.metrics.render(),
+    )
+}
+
+/// Correlation ID sent by the caller in the `X-Request-ID` header.
+fn request_id_from(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// A resolved time, ready to be returned as a [`TimeResponse`].
+struct LocalTime {
+    timestamp: String,
+    utc_offset_seconds: i32,
+    /// [`TimestampFormat::name`] of `timestamp`.
+    format: &'static str,
+}
+
+impl LocalTime {
+    fn into_response(self, timezone: String, request_id: String) -> TimeResponse {
+        TimeResponse {
+            timestamp: self.timestamp,
+            timezone,
+            request_id,
+            source: "api2-service".to_string(),
+            utc_offset_seconds: Some(self.utc_offset_seconds),
+            utc_offset_label: Some(format_utc_offset(self.utc_offset_seconds)),
+            display: None,
+            format: Some(self.format.to_string()),
+            degraded: None,
+            clock_offset_ms: None,
+        }
+    }
+}
+
+/// Current time in `timezone`, accepting IANA names and aliases.
+fn current_time_in(
+    timezone: &str,
+    format: &TimestampFormat,
+    request_id: &str,
+) -> Result<LocalTime, ApiError> {
+    time_in_at(timezone, format, chrono::Utc::now(), request_id)
+}
+
+fn time_in_at(
+    timezone: &str,
+    format: &TimestampFormat,
+    at: chrono::DateTime<chrono::Utc>,
+    request_id: &str,
+) -> Result<LocalTime, ApiError> {
+    // Fast path: UTC needs no timezone database lookup.
+    if timezone == "UTC" {
+        return Ok(LocalTime {
+            timestamp: format.format(&at),
+            utc_offset_seconds: 0,
+            format: format.name(),
+        });
+    }
+    let tz = parse_timezone(resolve_timezone_alias(timezone), request_id)?;
+    let local = at.with_timezone(&tz);
+    Ok(LocalTime {
+        timestamp: format.format(&local),
+        utc_offset_seconds: local.offset().fix().local_minus_utc(),
+        format: format.name(),
+    })
+}
+
+async fn get_time(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<TimeQuery>,
+) -> Result<Json<TimeResponse>, ApiError> {
+    // Prefer the X-Request-ID header, then the legacy query parameter.
+    let request_id = request_id_from(&headers)
+        .or(params.request_id)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    resolve_time(
+        &state,
+        params.timezone,
+        params.format.as_deref(),
+        params.locale.as_deref(),
+        request_id,
+    )
+    .await
+    .map(Json)
+}
+
+/// Resolves one time query for `/time` and `/time/ws`, recording it in the
+/// per-timezone stats and the history.
+async fn resolve_time(
+    state: &AppState,
+    timezone: Option<String>,
+    format: Option<&str>,
+    locale: Option<&str>,
+    request_id: String,
+) -> Result<TimeResponse, ApiError> {
+    let timezone = timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Processing time request"
+    );
+
+    let format = TimestampFormat::from_param(format)
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+    let locale = locale
+        .map(locale::Locale::parse)
+        .transpose()
+        .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?;
+    let now = chrono::Utc::now();
+    let current_time = time_in_at(&timezone, &format, now, &request_id)?;
+    let display = match locale {
+        Some(locale) => {
+            let tz = parse_timezone(resolve_timezone_alias(&timezone), &request_id)?;
+            Some(locale.display(&now.with_timezone(&tz)))
+        }
+        None => None,
+    };
+
+    state.record_timezone_use(&timezone);
+
+    let mut response = current_time.into_response(timezone, request_id);
+    response.display = display;
+    response.clock_offset_ms = state.clock_offset_ms();
+    state.history.record(&response, chrono::Utc::now()).await;
+
+    info!(
+        request_id = %response.request_id,
+        timestamp = %response.timestamp,
+        timezone = %response.timezone,
+        "Time request processed successfully"
+    );
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn test_state() -> AppState {
+        AppState {
+            api_keys: Arc::new(Vec::new()),
+            timezone_stats: Arc::new(stats::TimezoneStats::default()),
+            metrics: Arc::new(Registry::default()),
+            max_batch_size: common::DEFAULT_MAX_BATCH_SIZE,
+            timezone_names: Arc::new(timezones::timezone_names()),
+            audit: None,
+            history: Arc::new(history::History::new(100, 3600)),
+            ws_subscriptions: Arc::new(Semaphore::new(ws::DEFAULT_MAX_SUBSCRIPTIONS)),
+            stream_max_lifetime: stream::DEFAULT_MAX_LIFETIME,
+            polls: Arc::new(poll::Polls::default()),
+            limits: Arc::new(RequestLimits::default()),
+            timers: Arc::new(timers::TimerStore::new(100, 3600)),
+            alarms: Arc::new(alarms::AlarmStore::new(100, None)),
+            clock: None,
+            tzdata: None,
+            payload_log: Arc::new(PayloadLog::default()),
+            flags: Arc::new(FeatureFlags::default()),
+            chaos: None,
+            tenants: Arc::new(Tenants::default()),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    async fn time_in(timezone: &str) -> Result<Json<TimeResponse>, ApiError> {
+        time_formatted(timezone, None).await
+    }
+
+    async fn time_formatted(
+        timezone: &str,
+        format: Option<&str>,
+    ) -> Result<Json<TimeResponse>, ApiError> {
+        get_time(
+            State(test_state()),
+            HeaderMap::new(),
+            Query(TimeQuery {
+                timezone: Some(timezone.to_string()),
+                request_id: None,
+                format: format.map(str::to_string),
+                locale: None,
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn supports_iana_timezones() {
+        for (timezone, offsets) in [
+            ("Asia/Bangkok", &["+07:00"][..]),
+            ("Pacific/Auckland", &["+12:00", "+13:00"][..]),
+            ("Asia/Kathmandu", &["+05:45"][..]),
+            ("PST", &["-08:00", "-07:00"][..]),
+            ("UTC", &["+00:00"][..]),
+        ] {
+            let Json(response) = time_in(timezone).await.unwrap();
+            assert_eq!(response.timezone, timezone);
+            assert!(
+                offsets
+                    .iter()
+                    .any(|offset| response.timestamp.ends_with(offset)),
+                "{timezone}: {}",
+                response.timestamp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_display_for_locale() {
+        let Json(response) = get_time(
+            State(test_state()),
+            HeaderMap::new(),
+            Query(TimeQuery {
+                timezone: Some("Asia/Bangkok".to_string()),
+                locale: Some("th".to_string()),
+                ..TimeQuery::default()
+            }),
+        )
+        .await
+        .unwrap();
+        let display = response.display.unwrap();
+        assert!(display.starts_with("วัน"), "{display}");
+        assert!(display.ends_with(" น."), "{display}");
+
+        let error = get_time(
+            State(test_state()),
+            HeaderMap::new(),
+            Query(TimeQuery {
+                locale: Some("xx".to_string()),
+                ..TimeQuery::default()
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+        assert!(error.error.starts_with("Unsupported locale: xx"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_timezone() {
+        let error = time_in("Foo/Bar").await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, ErrorCode::InvalidTimezone);
+        assert_eq!(error.error, "Invalid timezone: Foo/Bar");
+    }
+
+    #[tokio::test]
+    async fn counts_resolved_timezones_in_metrics() {
+        let state = test_state();
+        for timezone in ["Asia/Tokyo", "Asia/Tokyo", "Foo/Bar"] {
+            let _ = resolve_time(
+                &state,
+                Some(timezone.to_string()),
+                None,
+                None,
+                "id".to_string(),
+            )
+            .await;
+        }
+        let output = state.metrics.render();
+        assert!(output.contains("# TYPE timezone_requests_total counter\n"));
+        assert!(output.contains("timezone_requests_total{timezone=\"Asia/Tokyo\"} 2\n"));
+        assert!(!output.contains("Foo/Bar"));
+    }
+
+    #[tokio::test]
+    async fn request_id_header_wins_over_query() {
+        let mut headers = HeaderMap::new();
+        headers.insert(REQUEST_ID_HEADER, "from-header".parse().unwrap());
+        let Json(response) = get_time(
+            State(test_state()),
+            headers,
+            Query(TimeQuery {
+                timezone: None,
+                request_id: Some("from-query".to_string()),
+                format: None,
+                locale: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.request_id, "from-header");
+    }
+
+    #[tokio::test]
+    async fn health_fails_once_shutdown_is_requested() {
+        let state = test_state();
+        let (status, _) = health_check(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+
+        state.shutdown.request();
+        let (status, Json(body)) = health_check(State(state)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "shutting_down");
+        assert_eq!(body["in_flight"], 0);
+    }
+
+    #[tokio::test]
+    async fn assigns_and_echoes_request_ids() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = app(test_state(), &CorsPolicy::default());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{addr}/time"))
+            .send()
+            .await
+            .unwrap();
+        let assigned = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.request_id, assigned);
+
+        let response = client
+            .get(format!("http://{addr}/time"))
+            .header(REQUEST_ID_HEADER, "caller-id")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "caller-id");
+        let body: TimeResponse = response.json().await.unwrap();
+        assert_eq!(body.request_id, "caller-id");
+    }
+
+    async fn batch(timezones: &[&str]) -> Result<Json<common::BatchTimeResponse>, ApiError> {
+        let body = common::BatchTimeRequest {
+            timezones: timezones.iter().map(|tz| tz.to_string()).collect(),
+        };
+        batch::post_time_batch(State(test_state()), HeaderMap::new(), Ok(Json(body))).await
+    }
+
+    #[tokio::test]
+    async fn batch_reports_invalid_timezones_separately() {
+        let Json(response) = batch(&["UTC", "Asia/Tokyo", "Foo/Bar", "America/New_York"])
+            .await
+            .unwrap();
+
+        let resolved: Vec<_> = response
+            .results
+            .iter()
+            .map(|r| r.timezone.as_str())
+            .collect();
+        assert_eq!(resolved, ["UTC", "Asia/Tokyo", "America/New_York"]);
+        assert!(response.results[1].timestamp.ends_with("+09:00"));
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].timezone, "Foo/Bar");
+        assert_eq!(response.errors[0].error, "Invalid timezone: Foo/Bar");
+
+        let items: Vec<_> = response
+            .items
+            .iter()
+            .map(|item| match item {
+                common::BatchTimeItem::Time(time) => time.timezone.as_str(),
+                common::BatchTimeItem::Error(failure) => failure.error.as_str(),
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                "UTC",
+                "Asia/Tokyo",
+                "Invalid timezone: Foo/Bar",
+                "America/New_York"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_rejects_oversized_requests() {
+        let timezones = vec!["UTC"; common::DEFAULT_MAX_BATCH_SIZE + 1];
+        let error = batch(&timezones).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn formats_timestamp_on_request() {
+        let Json(unix) = time_formatted("Asia/Tokyo", Some("unix")).await.unwrap();
+        assert!(unix.timestamp.parse::<i64>().is_ok());
+        assert_eq!(unix.format.as_deref(), Some("unix"));
+
+        let Json(custom) = time_formatted("Asia/Tokyo", Some("custom:%z"))
+            .await
+            .unwrap();
+        assert_eq!(custom.timestamp, "+0900");
+        assert_eq!(custom.format.as_deref(), Some("custom"));
+
+        let Json(rfc2822) = time_formatted("Asia/Tokyo", Some("rfc2822")).await.unwrap();
+        assert!(
+            rfc2822.timestamp.ends_with(" +0900"),
+            "{}",
+            rfc2822.timestamp
+        );
+        let Json(default) = time_formatted("Asia/Tokyo", None).await.unwrap();
+        assert_eq!(default.format.as_deref(), Some("rfc3339"));
+
+        let body = time_formatted("UTC", Some("custom:%Q")).await.unwrap_err();
+        assert_eq!(body.code, ErrorCode::InvalidRequest);
+        assert_eq!(body.error, "Invalid format string: %Q");
+    }
+
+    #[test]
+    fn reports_utc_offset_across_dst_start() {
+        // US clocks sprang forward at 2024-03-10 07:00 UTC (02:00 EST).
+        let offset_at = |at: &str| {
+            let at = chrono::DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+            time_in_at("America/New_York", &TimestampFormat::Rfc3339, at, "test")
+                .unwrap()
+                .into_response("America/New_York".to_string(), "test".to_string())
+        };
+
+        let before = serde_json::to_value(offset_at("2024-03-10T06:59:59Z")).unwrap();
+        assert_eq!(before["utc_offset_seconds"], -18000);
+        assert_eq!(before["utc_offset_label"], "-05:00");
+
+        let after = serde_json::to_value(offset_at("2024-03-10T07:00:00Z")).unwrap();
+        assert_eq!(after["utc_offset_seconds"], -14400);
+        assert_eq!(after["utc_offset_label"], "-04:00");
+        assert_eq!(after["timestamp"], "2024-03-10T03:00:00-04:00");
+    }
+}
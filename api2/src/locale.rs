@@ -0,0 +1,227 @@
+//! Human-readable renderings of a time for the `locale` option, returned as
+//! `TimeResponse.display`, and of durations for `/duration/humanize`.
+//!
+//! Each locale is a [`LocaleData`] table: day and month names, the offset
+//! from the Gregorian year to the locale's era, and a layout. Adding one
+//! means adding a variant, its tag in [`Locale::parse`] and its table.
+
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Weekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    /// Thai, with Buddhist-era years (Gregorian + 543).
+    Th,
+}
+
+/// Tags accepted by [`Locale::parse`], for error messages.
+const SUPPORTED: &str = "en, th";
+
+/// The parts of a date and time a layout arranges, already localised.
+struct Parts<'a> {
+    day_name: &'a str,
+    day: u32,
+    month_name: &'a str,
+    year: i32,
+    time: String,
+}
+
+struct LocaleData {
+    /// Monday first, as `Weekday::num_days_from_monday` counts.
+    days: [&'static str; 7],
+    months: [&'static str; 12],
+    /// Added to the Gregorian year to give the year in the locale's era.
+    era_offset: i32,
+    layout: fn(&Parts) -> String,
+    /// `(singular, plural)` names of days, hours, minutes and seconds.
+    units: [(&'static str, &'static str); 4],
+}
+
+/// Seconds in each unit of `LocaleData::units`.
+const UNIT_SECONDS: [u64; 4] = [86_400, 3_600, 60, 1];
+
+const EN: LocaleData = LocaleData {
+    days: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    era_offset: 0,
+    layout: |p| {
+        format!(
+            "{} {} {} {}, {}",
+            p.day_name, p.day, p.month_name, p.year, p.time
+        )
+    },
+    units: [
+        ("day", "days"),
+        ("hour", "hours"),
+        ("minute", "minutes"),
+        ("second", "seconds"),
+    ],
+};
+
+const TH: LocaleData = LocaleData {
+    days: [
+        "วันจันทร์",
+        "วันอังคาร",
+        "วันพุธ",
+        "วันพฤหัสบดี",
+        "วันศุกร์",
+        "วันเสาร์",
+        "วันอาทิตย์",
+    ],
+    months: [
+        "มกราคม",
+        "กุมภาพันธ์",
+        "มีนาคม",
+        "เมษายน",
+        "พฤษภาคม",
+        "มิถุนายน",
+        "กรกฎาคม",
+        "สิงหาคม",
+        "กันยายน",
+        "ตุลาคม",
+        "พฤศจิกายน",
+        "ธันวาคม",
+    ],
+    era_offset: 543,
+    layout: |p| {
+        format!(
+            "{}ที่ {} {} {} เวลา {} น.",
+            p.day_name, p.day, p.month_name, p.year, p.time
+        )
+    },
+    // Thai nouns do not inflect for number.
+    units: [
+        ("วัน", "วัน"),
+        ("ชั่วโมง", "ชั่วโมง"),
+        ("นาที", "นาที"),
+        ("วินาที", "วินาที"),
+    ],
+};
+
+impl Locale {
+    /// Parses a language tag such as `en` or `en-US`; only the language
+    /// subtag is used.
+    pub fn parse(tag: &str) -> Result<Self, String> {
+        let language = tag.trim().split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Ok(Locale::En),
+            "th" => Ok(Locale::Th),
+            _ => Err(format!(
+                "Unsupported locale: {tag} (expected one of: {SUPPORTED})"
+            )),
+        }
+    }
+
+    /// `at` as a sentence-style date and time, e.g.
+    /// `Wednesday 5 March 2025, 14:30:00` or
+    /// `วันพุธที่ 5 มีนาคม 2568 เวลา 14:30:00 น.`.
+    pub fn display<Tz: TimeZone>(self, at: &DateTime<Tz>) -> String {
+        let data = self.data();
+        (data.layout)(&Parts {
+            day_name: data.days[at.weekday().num_days_from_monday() as usize],
+            day: at.day(),
+            month_name: data.months[at.month0() as usize],
+            year: at.year() + data.era_offset,
+            time: format!("{:02}:{:02}:{:02}", at.hour(), at.minute(), at.second()),
+        })
+    }
+
+    pub fn day_name(self, weekday: Weekday) -> &'static str {
+        self.data().days[weekday.num_days_from_monday() as usize]
+    }
+
+    /// The name of `month`, counted from 1.
+    pub fn month_name(self, month: u32) -> &'static str {
+        self.data().months[month as usize - 1]
+    }
+
+    /// `seconds` in days, hours, minutes and seconds, largest first and
+    /// skipping zeros, e.g. `1 hour 30 minutes` or `1 ชั่วโมง 30 นาที`.
+    /// At most `parts` units are given; smaller remainders are dropped.
+    pub fn duration(self, seconds: u64, parts: usize) -> String {
+        let units = self.data().units;
+        let mut rest = seconds;
+        let words: Vec<String> = UNIT_SECONDS
+            .iter()
+            .zip(units)
+            .filter_map(|(size, (singular, plural))| {
+                let count = rest / size;
+                rest %= size;
+                (count > 0)
+                    .then(|| format!("{count} {}", if count == 1 { singular } else { plural }))
+            })
+            .take(parts)
+            .collect();
+        if words.is_empty() {
+            let (_, plural) = units[units.len() - 1];
+            return format!("0 {plural}");
+        }
+        words.join(" ")
+    }
+
+    fn data(self) -> &'static LocaleData {
+        match self {
+            Locale::En => &EN,
+            Locale::Th => &TH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    #[test]
+    fn renders_english_and_thai_dates() {
+        let at = FixedOffset::east_opt(7 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, 5, 14, 30, 0)
+            .unwrap();
+        assert_eq!(
+            Locale::parse("en-GB").unwrap().display(&at),
+            "Wednesday 5 March 2025, 14:30:00"
+        );
+        assert_eq!(
+            Locale::parse("th_TH").unwrap().display(&at),
+            "วันพุธที่ 5 มีนาคม 2568 เวลา 14:30:00 น."
+        );
+        assert_eq!(
+            Locale::parse("fr").unwrap_err(),
+            "Unsupported locale: fr (expected one of: en, th)"
+        );
+    }
+
+    #[test]
+    fn renders_english_and_thai_durations() {
+        assert_eq!(Locale::En.duration(5400, 4), "1 hour 30 minutes");
+        assert_eq!(
+            Locale::En.duration(90_061, 4),
+            "1 day 1 hour 1 minute 1 second"
+        );
+        assert_eq!(Locale::En.duration(180_000, 1), "2 days");
+        assert_eq!(Locale::En.duration(0, 4), "0 seconds");
+        assert_eq!(Locale::Th.duration(5400, 4), "1 ชั่วโมง 30 นาที");
+    }
+}
@@ -0,0 +1,180 @@
+//! Exchange-local time and trading session status for major stock exchanges.
+
+use axum::response::Json;
+use chrono::{DateTime, Datelike, NaiveTime, SecondsFormat, Utc, Weekday};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TradingSession {
+    Pre,
+    Regular,
+    Post,
+    Closed,
+}
+
+/// Local `(start, end, session)` windows in `HH:MM`; start inclusive, end exclusive.
+type SessionWindow = (&'static str, &'static str, TradingSession);
+
+struct Exchange {
+    code: &'static str,
+    timezone: Tz,
+    sessions: &'static [SessionWindow],
+}
+
+/// Weekday session boundaries in exchange-local time. Exchange holidays and
+/// half days are not modelled; lunch breaks (TSE, HKEX) report `closed`.
+const EXCHANGES: &[Exchange] = &[
+    Exchange {
+        code: "NYSE",
+        timezone: chrono_tz::America::New_York,
+        sessions: &[
+            ("04:00", "09:30", TradingSession::Pre),
+            ("09:30", "16:00", TradingSession::Regular),
+            ("16:00", "20:00", TradingSession::Post),
+        ],
+    },
+    Exchange {
+        code: "NASDAQ",
+        timezone: chrono_tz::America::New_York,
+        sessions: &[
+            ("04:00", "09:30", TradingSession::Pre),
+            ("09:30", "16:00", TradingSession::Regular),
+            ("16:00", "20:00", TradingSession::Post),
+        ],
+    },
+    Exchange {
+        code: "LSE",
+        timezone: chrono_tz::Europe::London,
+        sessions: &[
+            ("07:50", "08:00", TradingSession::Pre),
+            ("08:00", "16:30", TradingSession::Regular),
+            ("16:30", "16:35", TradingSession::Post),
+        ],
+    },
+    Exchange {
+        code: "TSE",
+        timezone: chrono_tz::Asia::Tokyo,
+        sessions: &[
+            ("08:00", "09:00", TradingSession::Pre),
+            ("09:00", "11:30", TradingSession::Regular),
+            ("12:30", "15:30", TradingSession::Regular),
+        ],
+    },
+    Exchange {
+        code: "HKEX",
+        timezone: chrono_tz::Asia::Hong_Kong,
+        sessions: &[
+            ("09:00", "09:30", TradingSession::Pre),
+            ("09:30", "12:00", TradingSession::Regular),
+            ("13:00", "16:00", TradingSession::Regular),
+            ("16:00", "16:10", TradingSession::Post),
+        ],
+    },
+];
+
+#[derive(Debug, Deserialize)]
+pub struct MarketTimeQuery {
+    exchange: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarketTime {
+    exchange: &'static str,
+    exchange_time: String,
+    exchange_timezone: &'static str,
+    utc: String,
+    microseconds: u32,
+    trading_session: TradingSession,
+}
+
+pub async fn get_market_microstructure_time(
+    Query(params): Query<MarketTimeQuery>,
+) -> Result<Json<MarketTime>, ApiError> {
+    let now = Utc::now();
+    let request_id = Uuid::new_v4().to_string();
+    let exchange = params.exchange.unwrap_or_else(|| "NYSE".to_string());
+
+    info!(
+        request_id = %request_id,
+        exchange = %exchange,
+        "Processing market microstructure time request"
+    );
+
+    let Some(market) = EXCHANGES
+        .iter()
+        .find(|market| market.code.eq_ignore_ascii_case(&exchange))
+    else {
+        let known: Vec<&str> = EXCHANGES.iter().map(|market| market.code).collect();
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!(
+                "Unsupported exchange: {exchange} (expected one of {})",
+                known.join(", ")
+            ),
+            &request_id,
+        ));
+    };
+
+    Ok(Json(MarketTime {
+        exchange: market.code,
+        exchange_time: now
+            .with_timezone(&market.timezone)
+            .to_rfc3339_opts(SecondsFormat::Micros, true),
+        exchange_timezone: market.timezone.name(),
+        utc: now.to_rfc3339_opts(SecondsFormat::Micros, true),
+        microseconds: now.timestamp_subsec_micros(),
+        trading_session: trading_session(market, now),
+    }))
+}
+
+fn trading_session(market: &Exchange, at: DateTime<Utc>) -> TradingSession {
+    let local = at.with_timezone(&market.timezone);
+    if matches!(local.weekday(), Weekday::Sat | Weekday::Sun) {
+        return TradingSession::Closed;
+    }
+    let time = local.time();
+    let parse = |hhmm| NaiveTime::parse_from_str(hhmm, "%H:%M").expect("valid session time");
+    market
+        .sessions
+        .iter()
+        .find(|(start, end, _)| parse(start) <= time && time < parse(end))
+        .map_or(TradingSession::Closed, |&(_, _, session)| session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn session_at(code: &str, utc: (u32, u32)) -> TradingSession {
+        let market = EXCHANGES.iter().find(|market| market.code == code).unwrap();
+        // Wednesday 2024-07-10; New York is UTC-4, London UTC+1, Hong Kong UTC+8.
+        let at = Utc.with_ymd_and_hms(2024, 7, 10, utc.0, utc.1, 0).unwrap();
+        trading_session(market, at)
+    }
+
+    #[test]
+    fn classifies_sessions() {
+        assert_eq!(session_at("NYSE", (13, 29)), TradingSession::Pre);
+        assert_eq!(session_at("NYSE", (13, 30)), TradingSession::Regular);
+        assert_eq!(session_at("NASDAQ", (20, 0)), TradingSession::Post);
+        assert_eq!(session_at("NYSE", (0, 30)), TradingSession::Closed);
+        assert_eq!(session_at("LSE", (7, 0)), TradingSession::Regular);
+        assert_eq!(session_at("HKEX", (4, 30)), TradingSession::Closed);
+        assert_eq!(session_at("TSE", (6, 29)), TradingSession::Regular);
+    }
+
+    #[test]
+    fn weekends_are_closed() {
+        let market = &EXCHANGES[0];
+        let saturday = Utc.with_ymd_and_hms(2024, 7, 13, 15, 0, 0).unwrap();
+        assert_eq!(trading_session(market, saturday), TradingSession::Closed);
+    }
+}
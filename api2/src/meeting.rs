@@ -0,0 +1,427 @@
+//! `POST /time/meeting`: times in the coming days when every participant is
+//! within the same working hours in their own timezone.
+//!
+//! Each participant's hours are laid out on their own calendar, skipping
+//! their weekend unless `include_weekends` is set, and the UTC windows are
+//! intersected. Working hours follow each zone's DST, so the shared windows
+//! move when one participant's clocks change and another's do not.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use chrono::{DateTime, Duration, DurationRound, NaiveTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use common::problem::ErrorCode;
+use common::resolve_timezone_alias;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::business::{is_weekend, local, parse_clock};
+use crate::{error_response, json_body, parse_timezone, request_id_from, ApiError, AppState};
+
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 31;
+const DEFAULT_DURATION_MINUTES: u32 = 30;
+const DEFAULT_MAX_SLOTS: usize = 20;
+const MAX_SLOTS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct MeetingRequest {
+    /// Timezone of each participant; repeats are counted once.
+    participants: Vec<String>,
+    /// Working hours in each participant's local time, default `09:00`.
+    start: Option<String>,
+    /// Default `17:00`.
+    end: Option<String>,
+    /// How far ahead of `from` to look, default 7.
+    days: Option<u32>,
+    /// Shortest window worth returning, default 30.
+    duration_minutes: Option<u32>,
+    /// Default now.
+    from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    include_weekends: bool,
+    /// Default 20, at most 100.
+    max_slots: Option<usize>,
+}
+
+/// One participant's view of a slot.
+#[derive(Debug, Serialize)]
+pub struct ParticipantTime {
+    timezone: String,
+    start: String,
+    end: String,
+}
+
+/// A window in which every participant is working.
+#[derive(Debug, Serialize)]
+pub struct MeetingSlot {
+    start: String,
+    end: String,
+    duration_minutes: i64,
+    participants: Vec<ParticipantTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeetingResponse {
+    participants: Vec<String>,
+    start: String,
+    end: String,
+    duration_minutes: u32,
+    slots: Vec<MeetingSlot>,
+    /// Whether more slots were found than `max_slots`.
+    truncated: bool,
+    request_id: String,
+}
+
+/// A half-open UTC interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+pub async fn post_meeting(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<MeetingRequest>, JsonRejection>,
+) -> Result<Json<MeetingResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let meeting = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        participants = meeting.participants.len(),
+        days = meeting.days,
+        "Processing meeting request"
+    );
+
+    let invalid = |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    if meeting.participants.is_empty() {
+        return Err(invalid(
+            "participants must list at least one timezone".to_string(),
+        ));
+    }
+    if meeting.participants.len() > state.max_batch_size {
+        return Err(invalid(format!(
+            "{} participants exceed the limit of {}",
+            meeting.participants.len(),
+            state.max_batch_size
+        )));
+    }
+    let mut zones: Vec<Tz> = Vec::new();
+    for name in &meeting.participants {
+        let tz = parse_timezone(resolve_timezone_alias(name), &request_id)?;
+        if !zones.contains(&tz) {
+            zones.push(tz);
+        }
+    }
+    let start = parse_clock(
+        meeting.start.as_deref().unwrap_or("09:00"),
+        "start",
+        &request_id,
+    )?;
+    let end = parse_clock(
+        meeting.end.as_deref().unwrap_or("17:00"),
+        "end",
+        &request_id,
+    )?;
+    if start >= end {
+        return Err(invalid("start must be before end".to_string()));
+    }
+    let days = meeting.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(invalid(format!("days must be between 1 and {MAX_DAYS}")));
+    }
+    let duration_minutes = meeting.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+    let hours_minutes = (end - start).num_minutes();
+    if duration_minutes == 0 || i64::from(duration_minutes) > hours_minutes {
+        return Err(invalid(format!(
+            "duration_minutes must be between 1 and the {hours_minutes} minutes of working hours"
+        )));
+    }
+    let max_slots = meeting.max_slots.unwrap_or(DEFAULT_MAX_SLOTS);
+    if !(1..=MAX_SLOTS).contains(&max_slots) {
+        return Err(invalid(format!(
+            "max_slots must be between 1 and {MAX_SLOTS}"
+        )));
+    }
+
+    let from = meeting.from.unwrap_or_else(Utc::now);
+    // Slots start on a whole minute.
+    let minute = Duration::minutes(1);
+    let truncated = from.duration_trunc(minute).unwrap_or(from);
+    let from = if truncated < from {
+        truncated + minute
+    } else {
+        truncated
+    };
+    let search = Window {
+        start: from,
+        end: from + Duration::days(days.into()),
+    };
+
+    let mut windows = shared_windows(
+        &zones,
+        start,
+        end,
+        meeting.include_weekends,
+        search,
+        Duration::minutes(duration_minutes.into()),
+    );
+    let truncated = windows.len() > max_slots;
+    windows.truncate(max_slots);
+
+    Ok(Json(MeetingResponse {
+        participants: zones.iter().map(|tz| tz.name().to_string()).collect(),
+        start: start.format("%H:%M").to_string(),
+        end: end.format("%H:%M").to_string(),
+        duration_minutes,
+        slots: windows
+            .into_iter()
+            .map(|window| slot(window, &zones))
+            .collect(),
+        truncated,
+        request_id,
+    }))
+}
+
+/// Windows within `search`, at least `duration` long, in which every zone
+/// is between `start` and `end` on a working day, soonest first.
+fn shared_windows(
+    zones: &[Tz],
+    start: NaiveTime,
+    end: NaiveTime,
+    include_weekends: bool,
+    search: Window,
+    duration: Duration,
+) -> Vec<Window> {
+    let mut shared = vec![search];
+    for tz in zones {
+        shared = intersect(
+            &shared,
+            &working_windows(tz, start, end, include_weekends, search),
+        );
+    }
+    shared.retain(|window| window.end - window.start >= duration);
+    shared
+}
+
+/// `tz`'s working hours as UTC windows overlapping `search`, soonest first.
+fn working_windows(
+    tz: &Tz,
+    start: NaiveTime,
+    end: NaiveTime,
+    include_weekends: bool,
+    search: Window,
+) -> Vec<Window> {
+    // Local dates a day either side cover every offset from UTC.
+    let first = search.start.date_naive() - Duration::days(1);
+    let last = search.end.date_naive() + Duration::days(1);
+    first
+        .iter_days()
+        .take_while(|date| *date <= last)
+        .filter(|date| include_weekends || !is_weekend(*date))
+        .map(|date| Window {
+            start: local(tz, date, start).with_timezone(&Utc),
+            end: local(tz, date, end).with_timezone(&Utc),
+        })
+        .filter(|window| window.start < window.end)
+        .collect()
+}
+
+/// Overlaps of two sorted lists of disjoint windows.
+fn intersect(first: &[Window], second: &[Window]) -> Vec<Window> {
+    let mut overlaps = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < first.len() && j < second.len() {
+        let start = first[i].start.max(second[j].start);
+        let end = first[i].end.min(second[j].end);
+        if start < end {
+            overlaps.push(Window { start, end });
+        }
+        if first[i].end < second[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    overlaps
+}
+
+fn slot(window: Window, zones: &[Tz]) -> MeetingSlot {
+    let format = |at: DateTime<Tz>| at.to_rfc3339_opts(SecondsFormat::Secs, false);
+    MeetingSlot {
+        start: window.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+        end: window.end.to_rfc3339_opts(SecondsFormat::Secs, true),
+        duration_minutes: (window.end - window.start).num_minutes(),
+        participants: zones
+            .iter()
+            .map(|tz| ParticipantTime {
+                timezone: tz.name().to_string(),
+                start: format(window.start.with_timezone(tz)),
+                end: format(window.end.with_timezone(tz)),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::{America::New_York, Asia::Bangkok, Europe::London};
+
+    fn hours() -> (NaiveTime, NaiveTime) {
+        (
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        )
+    }
+
+    fn week_from(from: &str) -> Window {
+        let start = from.parse().unwrap();
+        Window {
+            start,
+            end: start + Duration::days(7),
+        }
+    }
+
+    fn utc(windows: &[Window]) -> Vec<(String, String)> {
+        windows
+            .iter()
+            .map(|window| (window.start.to_rfc3339(), window.end.to_rfc3339()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_overlap_on_each_working_day() {
+        let (start, end) = hours();
+        // Monday 3 March 2025.
+        let search = week_from("2025-03-03T00:00:00Z");
+        let windows = shared_windows(
+            &[Bangkok, London],
+            start,
+            end,
+            false,
+            search,
+            Duration::minutes(30),
+        );
+        assert_eq!(windows.len(), 5);
+        assert_eq!(
+            utc(&windows[..1]),
+            [(
+                "2025-03-03T09:00:00+00:00".to_string(),
+                "2025-03-03T10:00:00+00:00".to_string()
+            )]
+        );
+        let slot = slot(windows[0], &[Bangkok, London]);
+        assert_eq!(slot.duration_minutes, 60);
+        assert_eq!(slot.participants[0].start, "2025-03-03T16:00:00+07:00");
+        assert_eq!(slot.participants[1].end, "2025-03-03T10:00:00+00:00");
+
+        let with_weekends = shared_windows(
+            &[Bangkok, London],
+            start,
+            end,
+            true,
+            search,
+            Duration::minutes(30),
+        );
+        assert_eq!(with_weekends.len(), 7);
+        let too_long = shared_windows(
+            &[Bangkok, London],
+            start,
+            end,
+            false,
+            search,
+            Duration::minutes(90),
+        );
+        assert!(too_long.is_empty());
+    }
+
+    #[test]
+    fn follows_each_zones_daylight_saving_time() {
+        let (start, end) = hours();
+        // New York springs forward on 9 March 2025, London on 30 March.
+        let before = shared_windows(
+            &[New_York, London],
+            start,
+            end,
+            false,
+            week_from("2025-03-03T00:00:00Z"),
+            Duration::minutes(30),
+        );
+        let after = shared_windows(
+            &[New_York, London],
+            start,
+            end,
+            false,
+            week_from("2025-03-10T00:00:00Z"),
+            Duration::minutes(30),
+        );
+        assert_eq!(before[0].end - before[0].start, Duration::hours(3));
+        assert_eq!(after[0].end - after[0].start, Duration::hours(4));
+        assert_eq!(after[0].start.to_rfc3339(), "2025-03-10T13:00:00+00:00");
+    }
+
+    #[test]
+    fn starts_mid_window_from_the_requested_time() {
+        let (start, end) = hours();
+        let windows = shared_windows(
+            &[London],
+            start,
+            end,
+            false,
+            week_from("2025-03-03T12:00:00Z"),
+            Duration::minutes(30),
+        );
+        assert_eq!(windows[0].start.to_rfc3339(), "2025-03-03T12:00:00+00:00");
+        // Monday afternoon to Monday morning: six working days touched.
+        assert_eq!(windows.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_and_unknown_participants() {
+        let request = |participants: &[&str]| {
+            Ok(Json(MeetingRequest {
+                participants: participants.iter().map(|name| name.to_string()).collect(),
+                start: None,
+                end: None,
+                days: None,
+                duration_minutes: None,
+                from: None,
+                include_weekends: false,
+                max_slots: None,
+            }))
+        };
+        for (participants, code) in [
+            (&[][..], ErrorCode::InvalidRequest),
+            (
+                &["Asia/Bangkok", "Mars/Olympus"],
+                ErrorCode::InvalidTimezone,
+            ),
+        ] {
+            let error = post_meeting(
+                State(crate::tests::test_state()),
+                HeaderMap::new(),
+                request(participants),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(error.code, code, "{participants:?}");
+        }
+
+        let Json(found) = post_meeting(
+            State(crate::tests::test_state()),
+            HeaderMap::new(),
+            request(&["Asia/Bangkok", "Asia/Bangkok", "UTC"]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.participants, ["Asia/Bangkok", "UTC"]);
+        assert!(!found.slots.is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use axum::response::Json;
+use chrono::{DateTime, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct MomentQuery {
+    datetime: String,
+    tolerance_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    Past,
+    Present,
+    Future,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MomentComparison {
+    classification: Classification,
+    diff_seconds: i64,
+    within_tolerance: bool,
+}
+
+pub async fn get_moment_comparison(
+    Query(params): Query<MomentQuery>,
+) -> Result<Json<MomentComparison>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let tolerance_secs = params.tolerance_secs.unwrap_or(0);
+
+    info!(
+        request_id = %request_id,
+        datetime = %params.datetime,
+        tolerance_secs = tolerance_secs,
+        "Processing moment comparison request"
+    );
+
+    let moment = DateTime::parse_from_rfc3339(&params.datetime).map_err(|_| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Invalid datetime: {} (expected RFC 3339)", params.datetime),
+            &request_id,
+        )
+    })?;
+
+    Ok(Json(compare_moment(
+        moment.with_timezone(&Utc),
+        Utc::now(),
+        tolerance_secs,
+    )))
+}
+
+/// Classifies `moment` relative to `now`. A positive `diff_seconds` means the
+/// moment lies in the future; anything within `tolerance_secs` is "present".
+fn compare_moment(
+    moment: DateTime<Utc>,
+    now: DateTime<Utc>,
+    tolerance_secs: u64,
+) -> MomentComparison {
+    let diff_seconds = (moment - now).num_seconds();
+    let within_tolerance = diff_seconds.unsigned_abs() <= tolerance_secs;
+    let classification = if within_tolerance {
+        Classification::Present
+    } else if diff_seconds < 0 {
+        Classification::Past
+    } else {
+        Classification::Future
+    };
+
+    MomentComparison {
+        classification,
+        diff_seconds,
+        within_tolerance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn classifies_moments_around_the_tolerance() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let at = |seconds| now + chrono::Duration::seconds(seconds);
+
+        let late = compare_moment(at(-61), now, 60);
+        assert!(matches!(late.classification, Classification::Past));
+        assert_eq!(late.diff_seconds, -61);
+        assert!(!late.within_tolerance);
+
+        let edge = compare_moment(at(60), now, 60);
+        assert!(matches!(edge.classification, Classification::Present));
+        assert!(edge.within_tolerance);
+
+        let ahead = compare_moment(at(1), now, 0);
+        assert!(matches!(ahead.classification, Classification::Future));
+    }
+}
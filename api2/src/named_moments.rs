@@ -0,0 +1,104 @@
+//! Timestamps of well-known named moments in computing, science and history.
+
+use axum::response::Json;
+use chrono::{DateTime, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+/// `(slug, display name, RFC 3339 timestamp, description)`.
+type NamedMoment = (&'static str, &'static str, &'static str, &'static str);
+
+#[rustfmt::skip]
+const MOMENTS: &[NamedMoment] = &[
+    ("unix-epoch", "Unix Epoch", "1970-01-01T00:00:00Z", "The beginning of Unix time"),
+    ("y2k", "Y2K", "2000-01-01T00:00:00Z", "The start of the year 2000, feared for two-digit year rollover bugs"),
+    ("j2000", "J2000.0", "2000-01-01T11:58:55.816Z", "Standard astronomical epoch: 2000-01-01 12:00 Terrestrial Time"),
+    ("j1900", "J1900.0", "1899-12-31T12:00:00Z", "Earlier astronomical epoch used by Newcomb's tables"),
+    ("gps-epoch", "GPS Epoch", "1980-01-06T00:00:00Z", "The beginning of GPS time"),
+    ("ntp-epoch", "NTP Epoch", "1900-01-01T00:00:00Z", "Era 0 of the Network Time Protocol timestamp"),
+    ("windows-epoch", "Windows FILETIME Epoch", "1601-01-01T00:00:00Z", "Origin of Windows FILETIME and NT time"),
+    ("mac-epoch", "Classic Mac OS Epoch", "1904-01-01T00:00:00Z", "Origin of HFS and classic Mac OS timestamps"),
+    ("cocoa-epoch", "Cocoa Reference Date", "2001-01-01T00:00:00Z", "Origin of Apple Core Foundation and Cocoa absolute time"),
+    ("utc-leap-seconds", "UTC Leap Second Era", "1972-01-01T00:00:00Z", "UTC switched to whole-second steps kept in line by leap seconds"),
+    ("gregorian-reform", "Gregorian Calendar Reform", "1582-10-15T00:00:00Z", "First day of the Gregorian calendar"),
+    ("internet-epoch", "Internet Epoch", "1983-01-01T00:00:00Z", "ARPANET flag day switch to TCP/IP"),
+    ("unix-billennium", "Unix Billennium", "2001-09-09T01:46:40Z", "Unix time reached 1,000,000,000 seconds"),
+    ("unix-1234567890", "Unix Time 1234567890", "2009-02-13T23:31:30Z", "Unix time read 1234567890"),
+    ("y2038", "Year 2038 Problem", "2038-01-19T03:14:07Z", "Last second representable in a signed 32-bit time_t"),
+    ("unix-2-billion", "Unix Time 2 Billion", "2033-05-18T03:33:20Z", "Unix time reaches 2,000,000,000 seconds"),
+    ("bitcoin-genesis", "Bitcoin Genesis Block", "2009-01-03T18:15:05Z", "Timestamp of the first Bitcoin block"),
+    ("trinity-test", "Trinity Test", "1945-07-16T11:29:45Z", "First detonation of a nuclear weapon"),
+    ("sputnik-launch", "Sputnik 1 Launch", "1957-10-04T19:28:34Z", "Launch of the first artificial satellite"),
+    ("first-human-spaceflight", "Vostok 1 Launch", "1961-04-12T06:07:00Z", "Yuri Gagarin becomes the first human in space"),
+    ("apollo-11-launch", "Apollo 11 Launch", "1969-07-16T13:32:00Z", "Launch of the first crewed Moon landing mission"),
+    ("moon-landing", "First Moon Landing", "1969-07-20T20:17:40Z", "Apollo 11 lunar module Eagle lands on the Moon"),
+    ("first-moonwalk", "First Moonwalk", "1969-07-21T02:56:15Z", "Neil Armstrong steps onto the lunar surface"),
+    ("voyager-1-launch", "Voyager 1 Launch", "1977-09-05T12:56:00Z", "Launch of the probe that became the first to reach interstellar space"),
+    ("hubble-launch", "Hubble Space Telescope Launch", "1990-04-24T12:33:51Z", "Launch of the Hubble Space Telescope aboard STS-31"),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct NamedMomentQuery {
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamedMomentResponse {
+    name: &'static str,
+    timestamp: &'static str,
+    description: &'static str,
+    seconds_ago: i64,
+}
+
+pub async fn get_named_moment(
+    Query(params): Query<NamedMomentQuery>,
+) -> Result<Json<NamedMomentResponse>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let name = params.name.unwrap_or_default();
+
+    info!(
+        request_id = %request_id,
+        name = %name,
+        "Processing named moment request"
+    );
+
+    let slug = name.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+    let Some(&(_, display_name, timestamp, description)) =
+        MOMENTS.iter().find(|moment| moment.0 == slug)
+    else {
+        let known: Vec<&str> = MOMENTS.iter().map(|moment| moment.0).collect();
+        return Err(error_response(
+            ErrorCode::NotFound,
+            format!("Unknown moment: {name}. Known names: {}", known.join(", ")),
+            &request_id,
+        ));
+    };
+
+    let at = DateTime::parse_from_rfc3339(timestamp).expect("moment table holds valid timestamps");
+
+    Ok(Json(NamedMomentResponse {
+        name: display_name,
+        timestamp,
+        description,
+        // Negative for moments still in the future, such as y2038.
+        seconds_ago: (Utc::now() - at.with_timezone(&Utc)).num_seconds(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moment_table_is_valid() {
+        assert!(MOMENTS.len() >= 20);
+        for (index, (slug, _, timestamp, _)) in MOMENTS.iter().enumerate() {
+            assert!(DateTime::parse_from_rfc3339(timestamp).is_ok(), "{slug}");
+            assert!(MOMENTS[..index].iter().all(|moment| moment.0 != *slug));
+        }
+    }
+}
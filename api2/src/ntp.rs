@@ -0,0 +1,292 @@
+//! Clock drift monitoring against an NTP server.
+//!
+//! With `NTP_SERVER` set, a background task sends an SNTP (RFC 4330) query
+//! every `NTP_INTERVAL_SECS` and keeps the latest estimate of how far this
+//! host's clock is from the server's. `/time` responses carry it as
+//! `clock_offset_ms`, and readiness fails while it exceeds
+//! `NTP_MAX_DRIFT_MS`. Failed queries are logged and keep the previous
+//! estimate, so an unreachable server does not fail readiness by itself.
+
+use chrono::{DateTime, Utc};
+use common::config::Config;
+use common::health::Check;
+use serde_json::json;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{info, warn};
+
+const DEFAULT_PORT: u16 = 123;
+const DEFAULT_INTERVAL_SECS: u64 = 64;
+const DEFAULT_MAX_DRIFT_MS: u64 = 1000;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Seconds from the NTP era (1900-01-01) to the Unix epoch.
+const NTP_UNIX_OFFSET_SECS: i64 = 2_208_988_800;
+const PACKET_LEN: usize = 48;
+/// Leap indicator 0, version 4, mode 3 (client).
+const CLIENT_HEADER: u8 = 0b00_100_011;
+const MODE_SERVER: u8 = 4;
+
+/// One measurement of this host's clock against the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Server time minus local time: positive when the local clock is behind.
+    pub offset_ms: i64,
+    /// Network round trip, excluding the server's processing time.
+    pub delay_ms: i64,
+    pub measured_at: DateTime<Utc>,
+}
+
+pub struct ClockMonitor {
+    server: String,
+    interval: Duration,
+    max_drift_ms: u64,
+    last: RwLock<Option<Sample>>,
+    last_error: RwLock<Option<String>>,
+}
+
+impl ClockMonitor {
+    pub fn new(server: String, interval: Duration, max_drift_ms: u64) -> Self {
+        ClockMonitor {
+            server,
+            interval,
+            max_drift_ms,
+            last: RwLock::new(None),
+            last_error: RwLock::new(None),
+        }
+    }
+
+    /// A monitor for `ntp_server`, or `None` when monitoring is disabled.
+    /// Uses `ntp_interval_secs` and `ntp_max_drift_ms`, falling back to 64 s
+    /// and one second.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let server = config.ntp_server.clone()?;
+        Some(ClockMonitor::new(
+            server,
+            Duration::from_secs(config.ntp_interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS)),
+            config.ntp_max_drift_ms.unwrap_or(DEFAULT_MAX_DRIFT_MS),
+        ))
+    }
+
+    /// Queries the server now and then every interval, for as long as the
+    /// runtime lives.
+    pub fn spawn(self: &Arc<Self>) {
+        let monitor = Arc::clone(self);
+        info!(server = %monitor.server, "Monitoring clock drift over NTP");
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(monitor.interval);
+            loop {
+                interval.tick().await;
+                monitor.check().await;
+            }
+        });
+    }
+
+    async fn check(&self) {
+        match query(&self.server).await {
+            Ok(sample) => {
+                if sample.offset_ms.unsigned_abs() > self.max_drift_ms {
+                    warn!(
+                        server = %self.server,
+                        offset_ms = sample.offset_ms,
+                        max_drift_ms = self.max_drift_ms,
+                        "Clock drift exceeds the limit"
+                    );
+                }
+                *self.last.write().expect("ntp lock poisoned") = Some(sample);
+                *self.last_error.write().expect("ntp lock poisoned") = None;
+            }
+            Err(e) => {
+                warn!(server = %self.server, error = %e, "NTP query failed");
+                *self.last_error.write().expect("ntp lock poisoned") = Some(e);
+            }
+        }
+    }
+
+    /// The offset measured last, if any query has succeeded.
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.last
+            .read()
+            .expect("ntp lock poisoned")
+            .as_ref()
+            .map(|sample| sample.offset_ms)
+    }
+
+    /// Readiness check failing while the last measured offset is over the
+    /// limit.
+    pub fn check_drift(&self) -> Check {
+        let last = self.last.read().expect("ntp lock poisoned").clone();
+        let last_error = self.last_error.read().expect("ntp lock poisoned").clone();
+        Check::new(
+            "clock_drift",
+            last.as_ref()
+                .is_none_or(|sample| sample.offset_ms.unsigned_abs() <= self.max_drift_ms),
+            json!({
+                "server": self.server,
+                "offset_ms": last.as_ref().map(|sample| sample.offset_ms),
+                "delay_ms": last.as_ref().map(|sample| sample.delay_ms),
+                "measured_at": last.as_ref().map(|sample| sample.measured_at.to_rfc3339()),
+                "max_drift_ms": self.max_drift_ms,
+                "last_error": last_error,
+            }),
+        )
+    }
+
+    #[cfg(test)]
+    pub fn record(&self, sample: Sample) {
+        *self.last.write().unwrap() = Some(sample);
+    }
+}
+
+/// Sends one SNTP query to `server` (`host` or `host:port`).
+pub async fn query(server: &str) -> Result<Sample, String> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{server}:{DEFAULT_PORT}")
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| format!("bind: {e}"))?;
+    socket
+        .connect(&address)
+        .await
+        .map_err(|e| format!("{address}: {e}"))?;
+
+    let mut request = [0u8; PACKET_LEN];
+    request[0] = CLIENT_HEADER;
+    let sent_at = Utc::now();
+    let transmit = to_ntp(sent_at);
+    request[40..48].copy_from_slice(&transmit.to_be_bytes());
+    socket
+        .send(&request)
+        .await
+        .map_err(|e| format!("send: {e}"))?;
+
+    let mut reply = [0u8; PACKET_LEN];
+    let received = tokio::time::timeout(QUERY_TIMEOUT, socket.recv(&mut reply))
+        .await
+        .map_err(|_| format!("no reply within {}s", QUERY_TIMEOUT.as_secs()))?
+        .map_err(|e| format!("receive: {e}"))?;
+    let received_at = Utc::now();
+    if received < PACKET_LEN {
+        return Err(format!("short reply of {received} bytes"));
+    }
+    sample(&reply, transmit, sent_at, received_at)
+}
+
+/// Reads a server reply to a request whose transmit timestamp was
+/// `transmit`, sent at `t1` and answered at `t4` on the local clock.
+fn sample(
+    reply: &[u8; PACKET_LEN],
+    transmit: u64,
+    t1: DateTime<Utc>,
+    t4: DateTime<Utc>,
+) -> Result<Sample, String> {
+    let field = |at: usize| u64::from_be_bytes(reply[at..at + 8].try_into().expect("8 bytes"));
+    if reply[0] & 0b111 != MODE_SERVER {
+        return Err("reply is not from a server".to_string());
+    }
+    if reply[1] == 0 {
+        return Err("server sent a kiss-of-death reply".to_string());
+    }
+    if field(24) != transmit {
+        return Err("reply does not answer this request".to_string());
+    }
+    let t2 = from_ntp(field(32));
+    let t3 = from_ntp(field(40));
+
+    let offset = ((t2 - t1) + (t3 - t4)) / 2;
+    let delay = (t4 - t1) - (t3 - t2);
+    Ok(Sample {
+        offset_ms: offset.num_milliseconds(),
+        delay_ms: delay.num_milliseconds(),
+        measured_at: t4,
+    })
+}
+
+/// 64-bit NTP timestamp: seconds since 1900 and a 32-bit binary fraction.
+fn to_ntp(at: DateTime<Utc>) -> u64 {
+    let seconds = (at.timestamp() + NTP_UNIX_OFFSET_SECS) as u64;
+    let fraction = (u64::from(at.timestamp_subsec_nanos()) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+fn from_ntp(timestamp: u64) -> DateTime<Utc> {
+    let seconds = (timestamp >> 32) as i64 - NTP_UNIX_OFFSET_SECS;
+    let nanos = ((timestamp & 0xffff_ffff) * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(seconds, nanos as u32).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    /// An SNTP server whose clock runs `skew` ahead of this host's.
+    async fn skewed_server(skew: TimeDelta) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut request = [0u8; PACKET_LEN];
+            while let Ok((_, client)) = socket.recv_from(&mut request).await {
+                let now = to_ntp(Utc::now() + skew);
+                let mut reply = [0u8; PACKET_LEN];
+                reply[0] = 0b00_100_100;
+                reply[1] = 2;
+                reply[24..32].copy_from_slice(&request[40..48]);
+                reply[32..40].copy_from_slice(&now.to_be_bytes());
+                reply[40..48].copy_from_slice(&now.to_be_bytes());
+                socket.send_to(&reply, client).await.unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn round_trips_ntp_timestamps() {
+        let at = DateTime::parse_from_rfc3339("2025-03-05T07:30:00.250Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(to_ntp(at) >> 32, 3_950_148_600);
+        let back = from_ntp(to_ntp(at));
+        assert!((back - at).num_microseconds().unwrap().abs() <= 1);
+    }
+
+    #[test]
+    fn rejects_replies_to_other_requests() {
+        let now = Utc::now();
+        let mut reply = [0u8; PACKET_LEN];
+        reply[0] = 0b00_100_100;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&42u64.to_be_bytes());
+        assert!(sample(&reply, 41, now, now).is_err());
+        assert!(sample(&reply, 42, now, now).is_ok());
+        reply[1] = 0;
+        assert!(sample(&reply, 42, now, now)
+            .unwrap_err()
+            .contains("kiss-of-death"));
+    }
+
+    #[tokio::test]
+    async fn measures_the_offset_from_the_server() {
+        let server = skewed_server(TimeDelta::seconds(2)).await;
+        let sample = query(&server).await.unwrap();
+        assert!((1900..=2100).contains(&sample.offset_ms), "{sample:?}");
+        assert!(sample.delay_ms >= 0);
+
+        let monitor = ClockMonitor::new(server, Duration::from_secs(60), 1000);
+        assert!(monitor.check_drift().ok, "unknown drift should not fail");
+        monitor.check().await;
+        assert!(monitor.offset_ms().is_some_and(|offset| offset > 1000));
+        assert!(!monitor.check_drift().ok);
+
+        monitor.record(Sample {
+            offset_ms: -20,
+            delay_ms: 1,
+            measured_at: Utc::now(),
+        });
+        assert!(monitor.check_drift().ok);
+    }
+}
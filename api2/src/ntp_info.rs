@@ -0,0 +1,144 @@
+//! Reports the host clock's NTP synchronisation status.
+//!
+//! On Linux the status comes from `timedatectl` with `/proc/driver/rtc` as a
+//! fallback for the hardware clock. Every field is `null` when the data cannot
+//! be read, including on other platforms.
+
+use axum::response::Json;
+use serde::Serialize;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+#[derive(Debug, Default, Serialize)]
+pub struct NtpInfo {
+    ntp_synced: Option<bool>,
+    time_usec: Option<i64>,
+    rtc_offset_ms: Option<i64>,
+}
+
+pub async fn get_network_time_protocol_info() -> Json<NtpInfo> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Processing NTP info request"
+    );
+
+    // Both sources are blocking reads, so keep them off the async workers.
+    let ntp_info = tokio::task::spawn_blocking(read_ntp_info)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(request_id = %request_id, error = %e, "NTP info task failed");
+            NtpInfo::default()
+        });
+
+    Json(ntp_info)
+}
+
+#[cfg(target_os = "linux")]
+fn read_ntp_info() -> NtpInfo {
+    use chrono::Utc;
+    use std::process::Command;
+
+    let output = Command::new("timedatectl")
+        .args([
+            "show",
+            "--no-pager",
+            "--property=NTPSynchronized,TimeUSec,RTCTimeUSec",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+
+    let mut ntp_info = NtpInfo::default();
+    let mut rtc_usec = None;
+    for line in output.lines() {
+        match line.split_once('=') {
+            Some(("NTPSynchronized", value)) => ntp_info.ntp_synced = Some(value == "yes"),
+            Some(("TimeUSec", value)) => ntp_info.time_usec = parse_timedatectl_usec(value),
+            Some(("RTCTimeUSec", value)) => rtc_usec = parse_timedatectl_usec(value),
+            _ => {}
+        }
+    }
+
+    let rtc_usec = rtc_usec.or_else(|| {
+        std::fs::read_to_string("/proc/driver/rtc")
+            .ok()
+            .and_then(|contents| parse_proc_rtc(&contents))
+    });
+    let system_usec = ntp_info
+        .time_usec
+        .unwrap_or_else(|| Utc::now().timestamp_micros());
+    ntp_info.rtc_offset_ms = rtc_usec.map(|rtc| (rtc - system_usec) / 1000);
+
+    ntp_info
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_ntp_info() -> NtpInfo {
+    NtpInfo::default()
+}
+
+/// Parses a `timedatectl show` timestamp such as `Mon 2024-01-15 12:00:00 UTC`
+/// into microseconds since the Unix epoch. Timestamps without a zone, or with
+/// a zone other than UTC, are read in the host's local time.
+#[cfg(target_os = "linux")]
+fn parse_timedatectl_usec(value: &str) -> Option<i64> {
+    use chrono::{Local, NaiveDateTime, TimeZone};
+
+    let mut parts = value.split_whitespace().skip(1);
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let zone = parts.next();
+    let naive =
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M:%S").ok()?;
+
+    let timestamp = match zone {
+        Some("UTC") => naive.and_utc().timestamp_micros(),
+        _ => Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .timestamp_micros(),
+    };
+    Some(timestamp)
+}
+
+/// Parses the `rtc_time` and `rtc_date` lines of `/proc/driver/rtc`, which the
+/// kernel reports in UTC.
+#[cfg(target_os = "linux")]
+fn parse_proc_rtc(contents: &str) -> Option<i64> {
+    use chrono::NaiveDateTime;
+
+    let field = |name: &str| {
+        contents.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_string())
+        })
+    };
+    let naive = NaiveDateTime::parse_from_str(
+        &format!("{} {}", field("rtc_date")?, field("rtc_time")?),
+        "%Y-%m-%d %H:%M:%S",
+    )
+    .ok()?;
+    Some(naive.and_utc().timestamp_micros())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timedatectl_and_proc_rtc_timestamps() {
+        assert_eq!(
+            parse_timedatectl_usec("Mon 2024-01-15 12:00:00 UTC"),
+            Some(1_705_320_000_000_000)
+        );
+        assert_eq!(parse_timedatectl_usec("n/a"), None);
+
+        let rtc = "rtc_time\t: 12:00:01\nrtc_date\t: 2024-01-15\nalrm_time\t: 00:00:00\n";
+        assert_eq!(parse_proc_rtc(rtc), Some(1_705_320_001_000_000));
+        assert_eq!(parse_proc_rtc("rtc_time\t: 12:00:01\n"), None);
+    }
+}
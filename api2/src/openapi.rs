@@ -0,0 +1,334 @@
+//! API2's OpenAPI document, served at `/openapi.json` with Swagger UI at
+//! `/docs`. It covers the time lookups API1 proxies, the place and timezone
+//! lookups, cron previews, durations, timers and alarms; the calendar
+//! utilities are not yet described.
+
+use common::openapi::{self, time_options, typed, Document, Operation};
+use common::{
+    BatchTimeRequest, BatchTimeResponse, CreateTimerRequest, CronRequest, CronResponse,
+    TimeConvertResponse, TimeDiffResponse, TimeRequest, TimeResponse, TimeResponseV2,
+    TimerResponse, TimezoneList,
+};
+use serde_json::{json, Value};
+
+use crate::alarms::{Alarm, AlarmList, CreateAlarmRequest};
+use crate::duration::{HumanizedDuration, ParsedDuration};
+use crate::places::{CityTime, LocationTime};
+use crate::timezones::{TimezoneInfo, TimezoneOffsetAt};
+
+/// Errors of every route that reads a body or does real work.
+fn limit_errors(operation: Operation) -> Operation {
+    operation
+        .error(413, "Request body too large.")
+        .error(504, "Request timed out.")
+}
+
+fn free_form(description: &str) -> Value {
+    json!({ "type": "object", "description": description, "additionalProperties": true })
+}
+
+pub fn document() -> Document {
+    let get_time = time_options(Operation::new("Current time in a timezone"))
+        .query(
+            "timezone",
+            "IANA name or alias such as `PST` (default: UTC).",
+        )
+        .response::<TimeResponse>(200, "The current time.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.")
+        .error(400, "Invalid timezone, format or locale.");
+    let post_time = Operation::new("Current time with options in the body")
+        .body::<TimeRequest>("Timezone and rendering options.")
+        .response::<TimeResponse>(200, "The current time.")
+        .error(400, "Invalid timezone, format or locale.")
+        .error(422, "Malformed body or unknown field.");
+    let get_time_v2 = time_options(Operation::new("Current time with daylight saving details"))
+        .query(
+            "timezone",
+            "IANA name or alias such as `PST` (default: UTC).",
+        )
+        .response::<TimeResponseV2>(200, "The current time.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.")
+        .error(400, "Invalid timezone, format or locale.");
+    let post_time_v2 =
+        Operation::new("Current time with daylight saving details, options in the body")
+            .body::<TimeRequest>("Timezone and rendering options.")
+            .response::<TimeResponseV2>(200, "The current time.")
+            .error(400, "Invalid timezone, format or locale.")
+            .error(422, "Malformed body or unknown field.");
+    let batch = Operation::new("Current time in several timezones")
+        .body::<BatchTimeRequest>("Timezones to look up.")
+        .response::<BatchTimeResponse>(200, "One entry per timezone.")
+        .error(400, "Empty or oversized batch.")
+        .error(422, "Malformed body.");
+    let diff = Operation::new("Offset between two timezones")
+        .required_query("from", "IANA name.")
+        .required_query("to", "IANA name.")
+        .response::<TimeDiffResponse>(200, "The current offset.")
+        .error(400, "Missing or invalid timezone.");
+    let convert = Operation::new("An instant in two timezones")
+        .required_query("timestamp", "RFC 3339 time or Unix seconds.")
+        .required_query("from", "IANA name the timestamp is in.")
+        .required_query("to", "IANA name to convert to.")
+        .response::<TimeConvertResponse>(200, "The converted instant.")
+        .error(400, "Missing or invalid parameter.");
+    let stream = Operation::new("Server-sent events with the current time")
+        .query("timezone", "IANA name (default: UTC).")
+        .query("interval_ms", "Milliseconds between events.")
+        .response_inline(
+            200,
+            "One `TimeResponse` JSON object per event.",
+            "text/event-stream",
+            typed("string", "Event stream."),
+        )
+        .error(400, "Invalid timezone or interval.");
+    let ws = Operation::new("WebSocket with time subscriptions")
+        .empty_response(101, "Switching to the WebSocket protocol.")
+        .error(400, "Not a WebSocket upgrade request.");
+    let by_city = time_options(Operation::new("Current time in a city"))
+        .required_query("city", "City name, e.g. `Tokyo`.")
+        .response::<CityTime>(200, "The current time there.")
+        .error(400, "Missing city.")
+        .error(404, "Unknown city.");
+    let by_location = time_options(Operation::new("Current time at coordinates"))
+        .required_query("lat", "Latitude in degrees, -90 to 90.")
+        .required_query("lon", "Longitude in degrees, -180 to 180.")
+        .response::<LocationTime>(200, "The current time there.")
+        .error(400, "Missing or out-of-range coordinate.");
+    let timezones = Operation::new("Timezone names in the bundled database")
+        .query("prefix", "Only names starting with this, e.g. `Asia/`.")
+        .response::<TimezoneList>(200, "Matching timezones.")
+        .empty_response(304, "Unchanged since the `If-None-Match` ETag.");
+    let timezone = Operation::new("Details of one timezone")
+        .path("name", "IANA name, e.g. `Asia/Tokyo`.")
+        .response::<TimezoneInfo>(200, "Offset, abbreviation and transitions.")
+        .error(404, "Unknown timezone.");
+    let timezone_at = Operation::new("A timezone's offset at another instant")
+        .path("name", "IANA name, e.g. `Europe/London`.")
+        .required_query(
+            "timestamp",
+            "RFC 3339 time, Unix seconds, or `YYYY-MM-DDTHH:MM:SS` in the zone.",
+        )
+        .response::<TimezoneOffsetAt>(200, "Offset, abbreviation and DST then.")
+        .error(400, "Missing or unparsable timestamp.")
+        .error(404, "Unknown timezone.")
+        .error(422, "Local time ambiguous or skipped in the zone.");
+
+    let cron_next = Operation::new("Upcoming fire times of a cron expression")
+        .body::<CronRequest>("Expression, timezone and count.")
+        .response::<CronResponse>(200, "The next fire times, soonest first.")
+        .error(
+            400,
+            "Malformed expression, invalid timezone or count out of range, or malformed body.",
+        );
+
+    let parse_duration = Operation::new("Seconds in a duration such as `1h30m` or `P1DT2H`")
+        .required_query(
+            "value",
+            "ISO 8601 (`PT1H30M`), compact (`1h30m`) or words (`90 minutes`).",
+        )
+        .response::<ParsedDuration>(200, "The duration in several units.")
+        .error(400, "Missing, malformed or overlong value.");
+    let humanize_duration = Operation::new("A number of seconds in words")
+        .required_query("seconds", "Whole seconds, at most 1000 years.")
+        .query("locale", "`en` or `th` (default: en).")
+        .query("parts", "Most units to give, 1 to 4 (default: 4).")
+        .response::<HumanizedDuration>(200, "The duration in words.")
+        .error(400, "Missing or invalid parameter.");
+
+    let create_timer = Operation::new("Start a named timer")
+        .body::<CreateTimerRequest>("The timer's name.")
+        .response::<TimerResponse>(201, "The started timer.")
+        .error(400, "Missing or overlong name.")
+        .error(429, "Timer limit reached.")
+        .error(422, "Malformed body or unknown field.");
+    let timer = Operation::new("Time elapsed on a timer")
+        .path("id", "ID returned when the timer was started.")
+        .response::<TimerResponse>(200, "The timer.")
+        .error(404, "Unknown or expired timer.");
+    let stop_timer = Operation::new("Stop a timer, freezing its elapsed time")
+        .path("id", "ID returned when the timer was started.")
+        .response::<TimerResponse>(200, "The stopped timer.")
+        .error(404, "Unknown or expired timer.");
+
+    let create_alarm = Operation::new("Set an alarm that calls a URL when due")
+        .body::<CreateAlarmRequest>("When to fire and what to call.")
+        .response::<Alarm>(201, "The pending alarm.")
+        .error(
+            400,
+            "Invalid time, callback URL or label, or malformed body.",
+        )
+        .error(429, "Alarm limit reached.")
+        .secured();
+    let alarms = Operation::new("Pending alarms")
+        .response::<AlarmList>(200, "Pending alarms, soonest first.")
+        .secured();
+    let alarm = Operation::new("One pending alarm")
+        .path("id", "ID returned when the alarm was set.")
+        .response::<Alarm>(200, "The alarm.")
+        .error(404, "Unknown, fired or cancelled alarm.")
+        .secured();
+    let cancel_alarm = Operation::new("Cancel a pending alarm")
+        .path("id", "ID returned when the alarm was set.")
+        .response::<Alarm>(200, "The cancelled alarm.")
+        .error(404, "Unknown, fired or cancelled alarm.")
+        .secured();
+
+    Document::new(
+        "API2 - Time Service Provider",
+        "Computes times from the bundled timezone database. The time routes are \
+         listed by their deprecated unversioned paths and are served unchanged \
+         under `/v1`; `/v2` differs only in `/time`.",
+    )
+    .route("get", "/time", limit_errors(get_time))
+    .route("post", "/time", limit_errors(post_time))
+    .route("get", "/v2/time", limit_errors(get_time_v2))
+    .route("post", "/v2/time", limit_errors(post_time_v2))
+    .route("post", "/time/batch", limit_errors(batch))
+    .route("get", "/time/diff", limit_errors(diff))
+    .route("get", "/time/convert", limit_errors(convert))
+    .route("get", "/time/stream", stream)
+    .route("get", "/time/ws", ws)
+    .route("get", "/time/by-city", limit_errors(by_city))
+    .route("get", "/time/by-location", limit_errors(by_location))
+    .route("get", "/timezones", limit_errors(timezones))
+    .route("post", "/cron/next", limit_errors(cron_next))
+    .route("get", "/duration/parse", limit_errors(parse_duration))
+    .route("get", "/duration/humanize", limit_errors(humanize_duration))
+    .route("post", "/timer", limit_errors(create_timer))
+    .route("get", "/timer/{id}", limit_errors(timer))
+    .route("delete", "/timer/{id}", limit_errors(stop_timer))
+    .route("post", "/alarms", limit_errors(create_alarm))
+    .route("get", "/alarms", limit_errors(alarms))
+    .route("get", "/alarms/{id}", limit_errors(alarm))
+    .route("delete", "/alarms/{id}", limit_errors(cancel_alarm))
+    .route("get", "/timezone/{name}", limit_errors(timezone))
+    .route("get", "/timezone/{name}/at", limit_errors(timezone_at))
+    .route(
+        "get",
+        "/health",
+        Operation::new("Health of API2")
+            .response_inline(200, "Healthy.", "application/json", free_form("Status."))
+            .response_inline(
+                503,
+                "Shutting down.",
+                "application/json",
+                free_form("Status."),
+            ),
+    )
+    .route(
+        "get",
+        common::health::LIVE_PATH,
+        Operation::new("Liveness probe").response_inline(
+            200,
+            "Alive.",
+            "application/json",
+            free_form("Status."),
+        ),
+    )
+    .route(
+        "get",
+        common::health::READY_PATH,
+        Operation::new("Readiness probe")
+            .response_inline(200, "Ready.", "application/json", free_form("Checks."))
+            .response_inline(
+                503,
+                "Not ready.",
+                "application/json",
+                free_form("Failing checks."),
+            ),
+    )
+    .route(
+        "get",
+        common::metrics::METRICS_PATH,
+        Operation::new("Prometheus metrics").response_inline(
+            200,
+            "Text exposition format.",
+            "text/plain",
+            typed("string", "Metrics."),
+        ),
+    )
+    .route(
+        "get",
+        openapi::OPENAPI_PATH,
+        Operation::new("This document").response_inline(
+            200,
+            "OpenAPI 3.0 document.",
+            "application/json",
+            free_form("OpenAPI document."),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_nested_and_flattened_types() {
+        let document = document().into_json();
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["TimezoneInfo"]["properties"]["next_transitions"]["items"]["$ref"],
+            "#/components/schemas/Transition"
+        );
+        assert!(schemas["Transition"].is_object());
+        assert_eq!(
+            schemas["CityTime"]["allOf"][0]["$ref"],
+            "#/components/schemas/TimeResponse"
+        );
+        assert_eq!(
+            document["paths"]["/timezone/{name}"]["get"]["responses"]["404"]["content"]
+                ["application/problem+json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_every_documented_route_and_the_docs() {
+        let app = crate::app(
+            crate::tests::test_state(),
+            &common::cors::CorsPolicy::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+        let timer: common::TimerResponse = client
+            .post(format!("{base}/timer"))
+            .json(&json!({ "name": "docs" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        for (method, path) in document().operations() {
+            let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let url = format!(
+                "{base}{}",
+                path.replace("{name}", "Asia/Tokyo")
+                    .replace("{id}", &timer.id)
+            );
+            let response = client.request(method.clone(), url).send().await.unwrap();
+            assert!(
+                !matches!(response.status().as_u16(), 404 | 405),
+                "{method} {path}: {}",
+                response.status()
+            );
+        }
+
+        let served: Value = reqwest::get(format!("{base}/openapi.json"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(served["openapi"], "3.0.3");
+        let docs = reqwest::get(format!("{base}/docs")).await.unwrap();
+        assert!(docs.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+}
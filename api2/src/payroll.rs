@@ -0,0 +1,197 @@
+use axum::response::Json;
+use chrono::{Datelike, NaiveDate};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::calendar::{days_in_month, next_month, previous_month};
+use crate::{error_response, parse_timezone, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct PayrollQuery {
+    date: Option<String>,
+    pay_day_1: Option<u32>,
+    pay_day_2: Option<u32>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayrollPeriod {
+    period_number: u8,
+    period_start: String,
+    period_end: String,
+    next_pay_date: String,
+}
+
+pub async fn get_payroll_period(
+    Query(params): Query<PayrollQuery>,
+) -> Result<Json<PayrollPeriod>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let pay_day_1 = params.pay_day_1.unwrap_or(1);
+    let pay_day_2 = params.pay_day_2.unwrap_or(15);
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        "Processing payroll period request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+
+    if !(1..=31).contains(&pay_day_1) || !(1..=31).contains(&pay_day_2) || pay_day_1 >= pay_day_2 {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            "pay_day_1 and pay_day_2 must satisfy 1 <= pay_day_1 < pay_day_2 <= 31",
+            &request_id,
+        ));
+    }
+
+    let date = match params.date {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Invalid date: {date} (expected YYYY-MM-DD)"),
+                &request_id,
+            )
+        })?,
+        None => chrono::Utc::now().with_timezone(&tz).date_naive(),
+    };
+
+    payroll_period(date, pay_day_1, pay_day_2)
+        .map(Json)
+        .ok_or_else(|| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Date out of range for payroll periods: {date}"),
+                &request_id,
+            )
+        })
+}
+
+/// Computes the semi-monthly pay period containing `date`, or `None` when
+/// the period reaches past the last date chrono can represent.
+///
+/// Period 1 runs from `pay_day_1` through `pay_day_2`; period 2 runs from the
+/// day after `pay_day_2` up to the day before the next month's `pay_day_1`.
+/// Pay days past the end of a short month are clamped to its last day. Pay
+/// dates are calendar days, kept as they are when they fall on a weekend.
+fn payroll_period(date: NaiveDate, pay_day_1: u32, pay_day_2: u32) -> Option<PayrollPeriod> {
+    let first = pay_date(date.year(), date.month(), pay_day_1)?;
+    let second = pay_date(date.year(), date.month(), pay_day_2)?;
+
+    let (period_number, start, end) = if date < first {
+        let (year, month) = previous_month(date.year(), date.month());
+        let start = pay_date(year, month, pay_day_2)?.succ_opt()?;
+        (2, start, first.pred_opt()?)
+    } else if date <= second {
+        (1, first, second)
+    } else {
+        let (year, month) = next_month(date.year(), date.month());
+        let end = pay_date(year, month, pay_day_1)?.pred_opt()?;
+        (2, second.succ_opt()?, end)
+    };
+
+    // Period 1 is paid on its last day; period 2 on the following pay day.
+    let next_pay_date = if period_number == 1 {
+        end
+    } else {
+        end.succ_opt()?
+    };
+
+    Some(PayrollPeriod {
+        period_number,
+        period_start: start.to_string(),
+        period_end: end.to_string(),
+        next_pay_date: next_pay_date.to_string(),
+    })
+}
+
+fn pay_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day.min(days_in_month(year, month)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn period(date: &str, pay_day_1: u32, pay_day_2: u32) -> Option<(u8, String, String, String)> {
+        payroll_period(date.parse().unwrap(), pay_day_1, pay_day_2).map(|period| {
+            (
+                period.period_number,
+                period.period_start,
+                period.period_end,
+                period.next_pay_date,
+            )
+        })
+    }
+
+    fn expected(
+        number: u8,
+        start: &str,
+        end: &str,
+        paid: &str,
+    ) -> Option<(u8, String, String, String)> {
+        Some((number, start.into(), end.into(), paid.into()))
+    }
+
+    #[test]
+    fn splits_months_at_the_pay_days() {
+        assert_eq!(
+            period("2024-01-17", 1, 15),
+            expected(2, "2024-01-16", "2024-01-31", "2024-02-01")
+        );
+        assert_eq!(
+            period("2024-01-15", 1, 15),
+            expected(1, "2024-01-01", "2024-01-15", "2024-01-15")
+        );
+    }
+
+    #[test]
+    fn keeps_weekend_pay_dates() {
+        // 2024-06-15 is a Saturday.
+        assert_eq!(
+            period("2024-06-10", 1, 15),
+            expected(1, "2024-06-01", "2024-06-15", "2024-06-15")
+        );
+        // So is 2023-04-01, the pay day after March's second period.
+        assert_eq!(
+            period("2023-03-20", 1, 15),
+            expected(2, "2023-03-16", "2023-03-31", "2023-04-01")
+        );
+    }
+
+    #[test]
+    fn rolls_periods_across_month_and_year_ends() {
+        assert_eq!(
+            period("2024-12-20", 1, 15),
+            expected(2, "2024-12-16", "2024-12-31", "2025-01-01")
+        );
+        assert_eq!(
+            period("2025-01-05", 10, 25),
+            expected(2, "2024-12-26", "2025-01-09", "2025-01-10")
+        );
+        // Pay days past the end of February are clamped to its last day.
+        assert_eq!(
+            period("2024-02-20", 15, 31),
+            expected(1, "2024-02-15", "2024-02-29", "2024-02-29")
+        );
+        assert_eq!(
+            period("2023-03-05", 15, 31),
+            expected(2, "2023-03-01", "2023-03-14", "2023-03-15")
+        );
+        assert_eq!(
+            period("2023-02-20", 15, 31),
+            expected(1, "2023-02-15", "2023-02-28", "2023-02-28")
+        );
+    }
+
+    #[test]
+    fn gives_up_past_the_last_representable_date() {
+        assert_eq!(period("+262142-12-20", 1, 15), None);
+        assert_eq!(days_in_month(262142, 12), 31);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+}
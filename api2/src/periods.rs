@@ -0,0 +1,513 @@
+//! Interval arithmetic over time periods.
+//!
+//! Periods are half-open, `[start, end)`, so a period ending at 10:00 does not
+//! overlap one starting at 10:00.
+
+use axum::{extract::rejection::JsonRejection, response::Json};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize, Serializer};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, json_body, ApiError};
+
+/// Maximum number of periods accepted in a request body.
+const MAX_PERIODS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Period {
+    #[serde(serialize_with = "serialize_instant")]
+    start: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_instant")]
+    end: DateTime<Utc>,
+}
+
+impl Period {
+    /// Parses RFC 3339 bounds: 400 when unparseable, 422 unless `start < end`.
+    fn parse(
+        label: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+        request_id: &str,
+    ) -> Result<Self, ApiError> {
+        let parse = |name: String, value: Option<&str>| {
+            let value = value.ok_or_else(|| {
+                error_response(
+                    ErrorCode::InvalidRequest,
+                    format!("Missing required parameter: {name}"),
+                    request_id,
+                )
+            })?;
+            DateTime::parse_from_rfc3339(value)
+                .map(|datetime| datetime.with_timezone(&Utc))
+                .map_err(|_| {
+                    error_response(
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid {name}: {value} (expected RFC 3339)"),
+                        request_id,
+                    )
+                })
+        };
+        let period = Period {
+            start: parse(format!("start{label}"), start)?,
+            end: parse(format!("end{label}"), end)?,
+        };
+        period.validate(&format!("start{label}"), &format!("end{label}"), request_id)?;
+        Ok(period)
+    }
+
+    /// Rejects empty or inverted periods with `422 Unprocessable Entity`.
+    fn validate(&self, start_name: &str, end_name: &str, request_id: &str) -> Result<(), ApiError> {
+        if self.start >= self.end {
+            return Err(error_response(
+                ErrorCode::ValidationFailed,
+                format!("{start_name} must be before {end_name}"),
+                request_id,
+            ));
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> chrono::Duration {
+        self.end - self.start
+    }
+
+    fn intersection(&self, other: &Period) -> Option<Period> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Period { start, end })
+    }
+
+    fn contains(&self, other: &Period) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+fn format_instant(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn serialize_instant<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_instant(*at))
+}
+
+/// Validates every busy period in a request body.
+fn validate_periods(periods: &[Period], name: &str, request_id: &str) -> Result<(), ApiError> {
+    if periods.len() > MAX_PERIODS {
+        return Err(error_response(
+            ErrorCode::PayloadTooLarge,
+            format!(
+                "At most {MAX_PERIODS} {name} are accepted, got {}",
+                periods.len()
+            ),
+            request_id,
+        ));
+    }
+    for (index, period) in periods.iter().enumerate() {
+        period.validate(
+            &format!("{name}[{index}].start"),
+            &format!("{name}[{index}].end"),
+            request_id,
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeriodOverlapQuery {
+    start1: Option<String>,
+    end1: Option<String>,
+    start2: Option<String>,
+    end2: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Containment {
+    Full,
+    Partial,
+    None,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PeriodOverlap {
+    overlaps: bool,
+    overlap_start: Option<String>,
+    overlap_end: Option<String>,
+    overlap_seconds: i64,
+    containment: Containment,
+}
+
+pub async fn get_period_overlap(
+    Query(params): Query<PeriodOverlapQuery>,
+) -> Result<Json<PeriodOverlap>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Processing period overlap request"
+    );
+
+    let first = Period::parse(
+        "1",
+        params.start1.as_deref(),
+        params.end1.as_deref(),
+        &request_id,
+    )?;
+    let second = Period::parse(
+        "2",
+        params.start2.as_deref(),
+        params.end2.as_deref(),
+        &request_id,
+    )?;
+
+    Ok(Json(period_overlap(&first, &second)))
+}
+
+fn period_overlap(first: &Period, second: &Period) -> PeriodOverlap {
+    let overlap = first.intersection(second);
+    let containment = match overlap {
+        None => Containment::None,
+        Some(_) if first.contains(second) || second.contains(first) => Containment::Full,
+        Some(_) => Containment::Partial,
+    };
+
+    PeriodOverlap {
+        overlaps: overlap.is_some(),
+        overlap_start: overlap.map(|period| format_instant(period.start)),
+        overlap_end: overlap.map(|period| format_instant(period.end)),
+        overlap_seconds: overlap.map_or(0, |period| (period.end - period.start).num_seconds()),
+        containment,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlotAvailabilityRequest {
+    requested_start: DateTime<Utc>,
+    requested_end: DateTime<Utc>,
+    #[serde(default)]
+    busy_periods: Vec<Period>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlotAvailability {
+    available: bool,
+    conflicting_periods: Vec<Period>,
+    nearest_available_slot: Option<Period>,
+}
+
+pub async fn post_slot_availability(
+    body: Result<Json<SlotAvailabilityRequest>, JsonRejection>,
+) -> Result<Json<SlotAvailability>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let body = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        busy_periods = body.busy_periods.len(),
+        "Processing slot availability request"
+    );
+
+    let requested = Period {
+        start: body.requested_start,
+        end: body.requested_end,
+    };
+    requested.validate("requested_start", "requested_end", &request_id)?;
+    validate_periods(&body.busy_periods, "busy_periods", &request_id)?;
+
+    Ok(Json(slot_availability(requested, &body.busy_periods)))
+}
+
+fn slot_availability(requested: Period, busy_periods: &[Period]) -> SlotAvailability {
+    let conflicting_periods: Vec<Period> = busy_periods
+        .iter()
+        .filter(|busy| busy.intersection(&requested).is_some())
+        .copied()
+        .collect();
+
+    // Slide a slot of the requested length forward from the last conflict
+    // until it clears every busy period. There is none when the slot would
+    // run past the end of chrono's timeline.
+    let nearest_available_slot = conflicting_periods
+        .iter()
+        .map(|busy| busy.end)
+        .max()
+        .and_then(|mut start| {
+            let slot = |start: DateTime<Utc>| {
+                start
+                    .checked_add_signed(requested.duration())
+                    .map(|end| Period { start, end })
+            };
+            let mut sorted = busy_periods.to_vec();
+            sorted.sort_by_key(|busy| busy.start);
+            for busy in &sorted {
+                if busy.intersection(&slot(start)?).is_some() {
+                    start = start.max(busy.end);
+                }
+            }
+            slot(start)
+        });
+
+    SlotAvailability {
+        available: conflicting_periods.is_empty(),
+        conflicting_periods,
+        nearest_available_slot,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergePeriodsQuery {
+    merge_adjacent: Option<String>,
+}
+
+pub async fn post_merge_periods(
+    Query(params): Query<MergePeriodsQuery>,
+    body: Result<Json<Vec<Period>>, JsonRejection>,
+) -> Result<Json<Vec<Period>>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let periods = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        periods = periods.len(),
+        merge_adjacent = ?params.merge_adjacent,
+        "Processing merge periods request"
+    );
+
+    let merge_adjacent = match params.merge_adjacent.as_deref() {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(other) => {
+            return Err(error_response(
+                ErrorCode::InvalidRequest,
+                format!("Invalid merge_adjacent: {other} (expected true or false)"),
+                &request_id,
+            ))
+        }
+    };
+    validate_periods(&periods, "periods", &request_id)?;
+
+    Ok(Json(merge(periods, merge_adjacent)))
+}
+
+/// Sorts and coalesces overlapping periods (and touching ones when
+/// `merge_adjacent` is set) into a minimal disjoint set.
+fn merge(mut periods: Vec<Period>, merge_adjacent: bool) -> Vec<Period> {
+    periods.sort_by_key(|period| period.start);
+    let mut merged: Vec<Period> = Vec::with_capacity(periods.len());
+    for period in periods {
+        match merged.last_mut() {
+            Some(last)
+                if period.start < last.end || (merge_adjacent && period.start == last.end) =>
+            {
+                last.end = last.end.max(period.end);
+            }
+            _ => merged.push(period),
+        }
+    }
+    merged
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ComplementPeriodsRequest {
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    #[serde(default)]
+    busy_periods: Vec<Period>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FreeSlot {
+    #[serde(flatten)]
+    period: Period,
+    duration_seconds: i64,
+}
+
+pub async fn post_complement_periods(
+    body: Result<Json<ComplementPeriodsRequest>, JsonRejection>,
+) -> Result<Json<Vec<FreeSlot>>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let body = json_body(body, &request_id)?;
+
+    info!(
+        request_id = %request_id,
+        busy_periods = body.busy_periods.len(),
+        "Processing complement periods request"
+    );
+
+    let window = Period {
+        start: body.window_start,
+        end: body.window_end,
+    };
+    window.validate("window_start", "window_end", &request_id)?;
+    validate_periods(&body.busy_periods, "busy_periods", &request_id)?;
+
+    Ok(Json(complement(window, body.busy_periods)))
+}
+
+/// Returns the gaps within `window` not covered by any busy period.
+fn complement(window: Period, busy_periods: Vec<Period>) -> Vec<FreeSlot> {
+    let mut free = Vec::new();
+    let mut cursor = window.start;
+    for busy in merge(busy_periods, true) {
+        if busy.end <= cursor {
+            continue;
+        }
+        if busy.start >= window.end {
+            break;
+        }
+        if busy.start > cursor {
+            free.push(Period {
+                start: cursor,
+                end: busy.start,
+            });
+        }
+        cursor = busy.end;
+    }
+    if cursor < window.end {
+        free.push(Period {
+            start: cursor,
+            end: window.end,
+        });
+    }
+
+    free.into_iter()
+        .map(|period| FreeSlot {
+            period,
+            duration_seconds: period.duration().num_seconds(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn period(start: &str, end: &str) -> Period {
+        Period::parse("", Some(start), Some(end), "test").unwrap()
+    }
+
+    #[test]
+    fn classifies_overlaps() {
+        let morning = period("2024-01-15T09:00:00Z", "2024-01-15T11:00:00Z");
+
+        let partial = period_overlap(
+            &morning,
+            &period("2024-01-15T10:30:00Z", "2024-01-15T12:00:00Z"),
+        );
+        assert_eq!(partial.containment, Containment::Partial);
+        assert_eq!(partial.overlap_seconds, 1800);
+        assert_eq!(
+            partial.overlap_start.as_deref(),
+            Some("2024-01-15T10:30:00Z")
+        );
+
+        let full = period_overlap(
+            &morning,
+            &period("2024-01-15T09:30:00Z", "2024-01-15T10:00:00Z"),
+        );
+        assert_eq!(full.containment, Containment::Full);
+
+        let adjacent = period_overlap(
+            &morning,
+            &period("2024-01-15T11:00:00Z", "2024-01-15T12:00:00Z"),
+        );
+        assert!(!adjacent.overlaps);
+        assert_eq!(adjacent.containment, Containment::None);
+    }
+
+    #[test]
+    fn finds_nearest_slot_after_conflicts() {
+        let requested = period("2024-01-15T09:00:00Z", "2024-01-15T10:00:00Z");
+        let busy = [
+            period("2024-01-15T11:00:00Z", "2024-01-15T11:30:00Z"),
+            period("2024-01-15T08:30:00Z", "2024-01-15T09:30:00Z"),
+            period("2024-01-15T09:45:00Z", "2024-01-15T10:15:00Z"),
+            period("2024-01-15T10:30:00Z", "2024-01-15T11:00:00Z"),
+        ];
+        let result = slot_availability(requested, &busy);
+        assert!(!result.available);
+        assert_eq!(result.conflicting_periods.len(), 2);
+        // 10:15-10:30 is too short, so the slot lands after the 11:30 block.
+        assert_eq!(
+            result.nearest_available_slot,
+            Some(period("2024-01-15T11:30:00Z", "2024-01-15T12:30:00Z"))
+        );
+
+        let free = slot_availability(requested, &busy[..1]);
+        assert!(free.available);
+        assert!(free.nearest_available_slot.is_none());
+    }
+
+    #[test]
+    fn finds_no_slot_past_the_end_of_time() {
+        let end = DateTime::<Utc>::MAX_UTC;
+        let requested = Period {
+            start: end - chrono::TimeDelta::hours(2),
+            end: end - chrono::TimeDelta::hours(1),
+        };
+        let busy = [Period {
+            start: requested.start,
+            end,
+        }];
+        let result = slot_availability(requested, &busy);
+        assert!(!result.available);
+        assert_eq!(result.conflicting_periods.len(), 1);
+        assert!(result.nearest_available_slot.is_none());
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_periods() {
+        let periods = vec![
+            period("2024-01-15T13:00:00Z", "2024-01-15T14:00:00Z"),
+            period("2024-01-15T09:00:00Z", "2024-01-15T10:00:00Z"),
+            period("2024-01-15T09:30:00Z", "2024-01-15T11:00:00Z"),
+            period("2024-01-15T11:00:00Z", "2024-01-15T12:00:00Z"),
+            period("2024-01-15T09:45:00Z", "2024-01-15T10:00:00Z"),
+        ];
+        assert_eq!(
+            merge(periods.clone(), true),
+            vec![
+                period("2024-01-15T09:00:00Z", "2024-01-15T12:00:00Z"),
+                period("2024-01-15T13:00:00Z", "2024-01-15T14:00:00Z"),
+            ]
+        );
+        assert_eq!(merge(periods, false).len(), 3);
+        assert!(merge(Vec::new(), true).is_empty());
+    }
+
+    #[test]
+    fn complements_busy_periods_within_window() {
+        let window = period("2024-01-15T09:00:00Z", "2024-01-15T17:00:00Z");
+        let busy = vec![
+            period("2024-01-15T08:00:00Z", "2024-01-15T09:30:00Z"),
+            period("2024-01-15T12:00:00Z", "2024-01-15T13:00:00Z"),
+            period("2024-01-15T12:30:00Z", "2024-01-15T13:30:00Z"),
+            period("2024-01-15T18:00:00Z", "2024-01-15T19:00:00Z"),
+        ];
+        let free = complement(window, busy);
+        let bounds: Vec<Period> = free.iter().map(|slot| slot.period).collect();
+        assert_eq!(
+            bounds,
+            vec![
+                period("2024-01-15T09:30:00Z", "2024-01-15T12:00:00Z"),
+                period("2024-01-15T13:30:00Z", "2024-01-15T17:00:00Z"),
+            ]
+        );
+        assert_eq!(free[0].duration_seconds, 9000);
+        assert!(complement(window, vec![window]).is_empty());
+    }
+
+    #[test]
+    fn rejects_empty_periods() {
+        let error = Period::parse(
+            "1",
+            Some("2024-01-15T11:00:00Z"),
+            Some("2024-01-15T11:00:00Z"),
+            "test",
+        )
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+    }
+}
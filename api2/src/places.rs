@@ -0,0 +1,494 @@
+//! `/time/by-city` and `/time/by-location`: the current time for a place
+//! rather than an IANA name.
+//!
+//! Both are backed by the embedded [`CITIES`] table. A coordinate resolves to
+//! the zone of the nearest listed city; beyond [`MAX_NEAREST_KM`] of every
+//! city (open sea, sparse regions) it falls back to the nautical zone for its
+//! longitude, `Etc/GMT±N`, so the answer is approximate near borders.
+
+use axum::{extract::State, http::HeaderMap, response::Json};
+use chrono_tz::{Africa, America, Asia, Atlantic, Australia, Europe, Pacific, Tz};
+use common::openapi::{object, reference, register, typed, Components, Schema};
+use common::problem::{ErrorCode, Query};
+use common::TimeResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{error_response, request_id_from, resolve_time, ApiError, AppState};
+
+struct City {
+    name: &'static str,
+    timezone: Tz,
+    latitude: f64,
+    longitude: f64,
+}
+
+const fn city(name: &'static str, timezone: Tz, latitude: f64, longitude: f64) -> City {
+    City {
+        name,
+        timezone,
+        latitude,
+        longitude,
+    }
+}
+
+const CITIES: &[City] = &[
+    city("Bangkok", Asia::Bangkok, 13.7563, 100.5018),
+    city("Chiang Mai", Asia::Bangkok, 18.7883, 98.9853),
+    city("Phuket", Asia::Bangkok, 7.8804, 98.3923),
+    city("Vientiane", Asia::Vientiane, 17.9757, 102.6331),
+    city("Phnom Penh", Asia::Phnom_Penh, 11.5564, 104.9282),
+    city("Hanoi", Asia::Ho_Chi_Minh, 21.0278, 105.8342),
+    city("Ho Chi Minh City", Asia::Ho_Chi_Minh, 10.8231, 106.6297),
+    city("Yangon", Asia::Yangon, 16.8409, 96.1735),
+    city("Kuala Lumpur", Asia::Kuala_Lumpur, 3.1390, 101.6869),
+    city("Singapore", Asia::Singapore, 1.3521, 103.8198),
+    city("Jakarta", Asia::Jakarta, -6.2088, 106.8456),
+    city("Manila", Asia::Manila, 14.5995, 120.9842),
+    city("Hong Kong", Asia::Hong_Kong, 22.3193, 114.1694),
+    city("Taipei", Asia::Taipei, 25.0330, 121.5654),
+    city("Shanghai", Asia::Shanghai, 31.2304, 121.4737),
+    city("Beijing", Asia::Shanghai, 39.9042, 116.4074),
+    city("Seoul", Asia::Seoul, 37.5665, 126.9780),
+    city("Tokyo", Asia::Tokyo, 35.6762, 139.6503),
+    city("Osaka", Asia::Tokyo, 34.6937, 135.5023),
+    city("Dhaka", Asia::Dhaka, 23.8103, 90.4125),
+    city("Kathmandu", Asia::Kathmandu, 27.7172, 85.3240),
+    city("Delhi", Asia::Kolkata, 28.7041, 77.1025),
+    city("Mumbai", Asia::Kolkata, 19.0760, 72.8777),
+    city("Bangalore", Asia::Kolkata, 12.9716, 77.5946),
+    city("Karachi", Asia::Karachi, 24.8607, 67.0011),
+    city("Kabul", Asia::Kabul, 34.5553, 69.2075),
+    city("Tashkent", Asia::Tashkent, 41.2995, 69.2401),
+    city("Almaty", Asia::Almaty, 43.2220, 76.8512),
+    city("Novosibirsk", Asia::Novosibirsk, 55.0084, 82.9357),
+    city("Vladivostok", Asia::Vladivostok, 43.1198, 131.8869),
+    city("Tehran", Asia::Tehran, 35.6892, 51.3890),
+    city("Dubai", Asia::Dubai, 25.2048, 55.2708),
+    city("Riyadh", Asia::Riyadh, 24.7136, 46.6753),
+    city("Jerusalem", Asia::Jerusalem, 31.7683, 35.2137),
+    city("Istanbul", Europe::Istanbul, 41.0082, 28.9784),
+    city("Moscow", Europe::Moscow, 55.7558, 37.6173),
+    city("Kyiv", Europe::Kiev, 50.4501, 30.5234),
+    city("Athens", Europe::Athens, 37.9838, 23.7275),
+    city("Warsaw", Europe::Warsaw, 52.2297, 21.0122),
+    city("Stockholm", Europe::Stockholm, 59.3293, 18.0686),
+    city("Berlin", Europe::Berlin, 52.5200, 13.4050),
+    city("Rome", Europe::Rome, 41.9028, 12.4964),
+    city("Amsterdam", Europe::Amsterdam, 52.3676, 4.9041),
+    city("Paris", Europe::Paris, 48.8566, 2.3522),
+    city("Madrid", Europe::Madrid, 40.4168, -3.7038),
+    city("Lisbon", Europe::Lisbon, 38.7223, -9.1393),
+    city("London", Europe::London, 51.5074, -0.1278),
+    city("Dublin", Europe::Dublin, 53.3498, -6.2603),
+    city("Reykjavik", Atlantic::Reykjavik, 64.1466, -21.9426),
+    city("Cairo", Africa::Cairo, 30.0444, 31.2357),
+    city("Nairobi", Africa::Nairobi, -1.2921, 36.8219),
+    city("Johannesburg", Africa::Johannesburg, -26.2041, 28.0473),
+    city("Lagos", Africa::Lagos, 6.5244, 3.3792),
+    city("Casablanca", Africa::Casablanca, 33.5731, -7.5898),
+    city("New York", America::New_York, 40.7128, -74.0060),
+    city("Toronto", America::Toronto, 43.6532, -79.3832),
+    city("Chicago", America::Chicago, 41.8781, -87.6298),
+    city("Mexico City", America::Mexico_City, 19.4326, -99.1332),
+    city("Denver", America::Denver, 39.7392, -104.9903),
+    city("Phoenix", America::Phoenix, 33.4484, -112.0740),
+    city("Los Angeles", America::Los_Angeles, 34.0522, -118.2437),
+    city("San Francisco", America::Los_Angeles, 37.7749, -122.4194),
+    city("Seattle", America::Los_Angeles, 47.6062, -122.3321),
+    city("Vancouver", America::Vancouver, 49.2827, -123.1207),
+    city("Anchorage", America::Anchorage, 61.2181, -149.9003),
+    city("Honolulu", Pacific::Honolulu, 21.3069, -157.8583),
+    city("Bogota", America::Bogota, 4.7110, -74.0721),
+    city("Caracas", America::Caracas, 10.4806, -66.9036),
+    city("Lima", America::Lima, -12.0464, -77.0428),
+    city("Santiago", America::Santiago, -33.4489, -70.6693),
+    city(
+        "Buenos Aires",
+        America::Argentina::Buenos_Aires,
+        -34.6037,
+        -58.3816,
+    ),
+    city("Sao Paulo", America::Sao_Paulo, -23.5505, -46.6333),
+    city("Perth", Australia::Perth, -31.9505, 115.8605),
+    city("Adelaide", Australia::Adelaide, -34.9285, 138.6007),
+    city("Brisbane", Australia::Brisbane, -27.4698, 153.0251),
+    city("Melbourne", Australia::Melbourne, -37.8136, 144.9631),
+    city("Sydney", Australia::Sydney, -33.8688, 151.2093),
+    city("Auckland", Pacific::Auckland, -36.8485, 174.7633),
+];
+
+/// Coordinates further than this from every listed city use the nautical
+/// zone for their longitude instead.
+const MAX_NEAREST_KM: f64 = 800.0;
+
+/// Suggestions listed in a `404` for an unknown city.
+const MAX_SUGGESTIONS: usize = 3;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Lowercased, with `_` and `-` read as spaces, so `new_york` finds New York.
+fn normalize(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn find_city(name: &str) -> Option<&'static City> {
+    let name = normalize(name);
+    CITIES.iter().find(|city| normalize(city.name) == name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Listed cities whose names contain `name` or are a few edits away from it,
+/// closest first.
+fn suggestions(name: &str) -> Vec<&'static str> {
+    let name = normalize(name);
+    let mut scored: Vec<(usize, &'static str)> = CITIES
+        .iter()
+        .filter_map(|city| {
+            let candidate = normalize(city.name);
+            let distance = if !name.is_empty() && candidate.contains(&name) {
+                0
+            } else {
+                edit_distance(&name, &candidate)
+            };
+            (distance <= (name.chars().count() / 3).max(2)).then_some((distance, city.name))
+        })
+        .collect();
+    scored.sort();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Great-circle distance in kilometres.
+fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// The zone at a coordinate, with the city it was taken from if any.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Located {
+    pub(crate) timezone: String,
+    pub(crate) nearest_city: Option<&'static str>,
+    pub(crate) distance_km: Option<f64>,
+}
+
+pub(crate) fn locate(latitude: f64, longitude: f64) -> Located {
+    let nearest = CITIES
+        .iter()
+        .map(|city| {
+            let distance = haversine_km((latitude, longitude), (city.latitude, city.longitude));
+            (city, distance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    match nearest {
+        Some((city, distance)) if distance <= MAX_NEAREST_KM => Located {
+            timezone: city.timezone.name().to_string(),
+            nearest_city: Some(city.name),
+            distance_km: Some((distance * 10.0).round() / 10.0),
+        },
+        _ => Located {
+            timezone: nautical_zone(longitude),
+            nearest_city: None,
+            distance_km: None,
+        },
+    }
+}
+
+/// `Etc/GMT±N` for a longitude; the `Etc` sign is inverted, so UTC+7 is
+/// `Etc/GMT-7`.
+fn nautical_zone(longitude: f64) -> String {
+    let hours = (longitude / 15.0).round().clamp(-12.0, 12.0) as i32;
+    match hours {
+        0 => "Etc/GMT".to_string(),
+        hours if hours > 0 => format!("Etc/GMT-{hours}"),
+        hours => format!("Etc/GMT+{}", -hours),
+    }
+}
+
+/// Parses the required coordinate parameter `name`, in degrees within
+/// `±limit`.
+pub(crate) fn coordinate(
+    name: &str,
+    value: Option<String>,
+    limit: f64,
+    request_id: &str,
+) -> Result<f64, ApiError> {
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, request_id);
+    let value = value.ok_or_else(|| bad_request(format!("Missing required parameter: {name}")))?;
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|degrees| degrees.abs() <= limit)
+        .ok_or_else(|| {
+            bad_request(format!(
+                "Invalid {name}: {value} (expected degrees between -{limit} and {limit})"
+            ))
+        })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CityTimeQuery {
+    city: Option<String>,
+    format: Option<String>,
+    locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LocationTimeQuery {
+    lat: Option<String>,
+    lon: Option<String>,
+    format: Option<String>,
+    locale: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CityTime {
+    city: &'static str,
+    #[serde(flatten)]
+    time: TimeResponse,
+}
+
+impl Schema for CityTime {
+    const NAME: &'static str = "CityTime";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                reference::<TimeResponse>(),
+                object(
+                    "The matched city.",
+                    &[("city", typed("string", "Canonical city name."), true)],
+                ),
+            ],
+        })
+    }
+
+    fn references(components: &mut Components) {
+        register::<TimeResponse>(components);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocationTime {
+    latitude: f64,
+    longitude: f64,
+    /// `None` when the zone came from the longitude alone.
+    nearest_city: Option<&'static str>,
+    distance_km: Option<f64>,
+    #[serde(flatten)]
+    time: TimeResponse,
+}
+
+fn nullable(kind: &str, description: &str) -> Value {
+    json!({ "type": kind, "nullable": true, "description": description })
+}
+
+impl Schema for LocationTime {
+    const NAME: &'static str = "LocationTime";
+
+    fn schema() -> Value {
+        json!({
+            "allOf": [
+                reference::<TimeResponse>(),
+                object(
+                    "The coordinates and how the zone was chosen.",
+                    &[
+                        ("latitude", typed("number", "Degrees north."), true),
+                        ("longitude", typed("number", "Degrees east."), true),
+                        (
+                            "nearest_city",
+                            nullable("string", "`null` when the zone came from the longitude alone."),
+                            true,
+                        ),
+                        ("distance_km", nullable("number", "Distance to `nearest_city`."), true),
+                    ],
+                ),
+            ],
+        })
+    }
+
+    fn references(components: &mut Components) {
+        register::<TimeResponse>(components);
+    }
+}
+
+pub async fn get_time_by_city(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<CityTimeQuery>,
+) -> Result<Json<CityTime>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let name = params.city.ok_or_else(|| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            "Missing required parameter: city",
+            &request_id,
+        )
+    })?;
+    let Some(city) = find_city(&name) else {
+        let message = match suggestions(&name).as_slice() {
+            [] => format!("Unknown city: {name}"),
+            similar => format!(
+                "Unknown city: {name} (did you mean {}?)",
+                similar.join(", ")
+            ),
+        };
+        return Err(error_response(ErrorCode::NotFound, message, &request_id));
+    };
+
+    let time = resolve_time(
+        &state,
+        Some(city.timezone.name().to_string()),
+        params.format.as_deref(),
+        params.locale.as_deref(),
+        request_id,
+    )
+    .await?;
+    Ok(Json(CityTime {
+        city: city.name,
+        time,
+    }))
+}
+
+pub async fn get_time_by_location(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<LocationTimeQuery>,
+) -> Result<Json<LocationTime>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let latitude = coordinate("lat", params.lat, 90.0, &request_id)?;
+    let longitude = coordinate("lon", params.lon, 180.0, &request_id)?;
+
+    let located = locate(latitude, longitude);
+    let time = resolve_time(
+        &state,
+        Some(located.timezone),
+        params.format.as_deref(),
+        params.locale.as_deref(),
+        request_id,
+    )
+    .await?;
+    Ok(Json(LocationTime {
+        latitude,
+        longitude,
+        nearest_city: located.nearest_city,
+        distance_km: located.distance_km,
+        time,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_cities_loosely_and_suggests_near_misses() {
+        assert_eq!(find_city("new_york").unwrap().name, "New York");
+        assert_eq!(find_city(" BANGKOK ").unwrap().name, "Bangkok");
+        assert!(find_city("Bangkk").is_none());
+        assert_eq!(suggestions("Bangkk"), ["Bangkok"]);
+        assert_eq!(suggestions("san"), ["San Francisco", "Santiago"]);
+        assert!(suggestions("Atlantis").is_empty());
+    }
+
+    #[test]
+    fn locates_nearest_city_or_falls_back_to_nautical_zone() {
+        let pattaya = locate(12.9236, 100.8825);
+        assert_eq!(pattaya.timezone, "Asia/Bangkok");
+        assert_eq!(pattaya.nearest_city, Some("Bangkok"));
+
+        let pacific = locate(-30.0, -140.0);
+        assert_eq!(pacific.timezone, "Etc/GMT+9");
+        assert_eq!(pacific.nearest_city, None);
+        assert_eq!(nautical_zone(100.0), "Etc/GMT-7");
+        assert_eq!(nautical_zone(3.0), "Etc/GMT");
+    }
+
+    #[test]
+    fn every_city_has_a_unique_name() {
+        let mut names: Vec<String> = CITIES.iter().map(|city| normalize(city.name)).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), CITIES.len());
+    }
+
+    #[tokio::test]
+    async fn unknown_city_is_404_with_suggestions() {
+        let error = get_time_by_city(
+            State(crate::tests::test_state()),
+            HeaderMap::new(),
+            Query(CityTimeQuery {
+                city: Some("Tokio".to_string()),
+                format: None,
+                locale: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.error, "Unknown city: Tokio (did you mean Tokyo?)");
+
+        let Json(found) = get_time_by_city(
+            State(crate::tests::test_state()),
+            HeaderMap::new(),
+            Query(CityTimeQuery {
+                city: Some("tokyo".to_string()),
+                format: None,
+                locale: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.city, "Tokyo");
+        assert_eq!(found.time.timezone, "Asia/Tokyo");
+        assert!(found.time.timestamp.ends_with("+09:00"));
+    }
+
+    #[tokio::test]
+    async fn rejects_out_of_range_coordinates() {
+        let error = get_time_by_location(
+            State(crate::tests::test_state()),
+            HeaderMap::new(),
+            Query(LocationTimeQuery {
+                lat: Some("91".to_string()),
+                lon: Some("0".to_string()),
+                format: None,
+                locale: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+        assert_eq!(
+            error.error,
+            "Invalid lat: 91 (expected degrees between -90 and 90)"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Long polling for the minute to change, for clients that only need to
+//! update once a minute and cannot hold a stream open.
+//!
+//! `GET /time/poll` answers at once unless the caller has seen exactly the
+//! current minute, named by `since` or by the weak ETag of an earlier answer
+//! in `If-None-Match`; a minute still to come counts as unseen. Otherwise it
+//! waits until the minute rolls over and answers `200` with the new time, or
+//! answers `304` when it has waited `POLL_MAX_WAIT_SECS`, the request's
+//! deadline is near, or the service is shutting down. A client that disconnects drops the wait with its request.
+
+use axum::{
+    extract::State,
+    http::{
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use common::config::Config;
+use common::format::TimestampFormat;
+use common::limits;
+use common::metrics::Gauge;
+use common::problem::{ErrorCode, Query};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{current_time_in, error_response, request_id_from, ApiError, AppState};
+
+/// Wait used when `poll_max_wait_secs` is not configured; under the default
+/// request timeout, so a quiet minute ends in `304` rather than `504`.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(25);
+/// Left between answering `304` and the request's deadline.
+const DEADLINE_MARGIN: Duration = Duration::from_millis(250);
+
+const TIME_POLLS_WAITING: Gauge = Gauge {
+    name: "time_polls_waiting",
+    help: "/time/poll requests waiting for the minute to change.",
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TimePollQuery {
+    timezone: Option<String>,
+    /// RFC 3339 timestamp or Unix seconds in the last minute the caller saw.
+    since: Option<String>,
+}
+
+/// Long-poll settings, and the requests waiting now.
+pub struct Polls {
+    max_wait: Duration,
+    waiting: AtomicUsize,
+}
+
+impl Default for Polls {
+    fn default() -> Self {
+        Polls::new(DEFAULT_MAX_WAIT)
+    }
+}
+
+impl Polls {
+    pub fn new(max_wait: Duration) -> Self {
+        Polls {
+            max_wait,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Uses `poll_max_wait_secs`, falling back to the default.
+    pub fn from_config(config: &Config) -> Self {
+        Polls::new(
+            config
+                .poll_max_wait_secs
+                .map_or(DEFAULT_MAX_WAIT, Duration::from_secs),
+        )
+    }
+}
+
+/// A request waiting for the minute to change; counted until it answers or
+/// its client goes away.
+struct Waiting<'a> {
+    state: &'a AppState,
+    request_id: &'a str,
+    answered: bool,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(state: &'a AppState, request_id: &'a str) -> Self {
+        let waiting = state.polls.waiting.fetch_add(1, Ordering::AcqRel) + 1;
+        state.metrics.set(&TIME_POLLS_WAITING, &[], waiting as f64);
+        Waiting {
+            state,
+            request_id,
+            answered: false,
+        }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let waiting = self.state.polls.waiting.fetch_sub(1, Ordering::AcqRel) - 1;
+        self.state
+            .metrics
+            .set(&TIME_POLLS_WAITING, &[], waiting as f64);
+        if !self.answered {
+            info!(request_id = %self.request_id, "Time poll cancelled by the client");
+        }
+    }
+}
+
+/// Minutes since the Unix epoch. Every zone in use today is a whole number
+/// of minutes from UTC, so its minutes roll over at the same instants.
+fn minute_of(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(60)
+}
+
+/// Weak ETag naming the minute a response belongs to.
+fn minute_etag(minute: i64) -> String {
+    format!("W/\"minute-{minute}\"")
+}
+
+/// The minute named by `since`.
+fn parse_since(since: &str) -> Result<i64, String> {
+    let at = match since.trim().parse::<i64>() {
+        Ok(seconds) => DateTime::from_timestamp(seconds, 0),
+        Err(_) => DateTime::parse_from_rfc3339(since)
+            .ok()
+            .map(|at| at.with_timezone(&Utc)),
+    };
+    at.map(minute_of).ok_or_else(|| {
+        format!("Invalid since: {since} (expected an RFC 3339 timestamp or Unix seconds)")
+    })
+}
+
+/// The minute named by an `If-None-Match` header holding one of our tags.
+fn seen_in(if_none_match: &HeaderValue) -> Option<i64> {
+    if_none_match
+        .to_str()
+        .ok()?
+        .split(',')
+        .filter_map(|tag| {
+            tag.trim()
+                .strip_prefix("W/\"minute-")?
+                .strip_suffix('"')?
+                .parse()
+                .ok()
+        })
+        .max()
+}
+
+pub async fn get_time_poll(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<TimePollQuery>,
+) -> Result<Response, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let seen = match params.since.as_deref() {
+        Some(since) => Some(
+            parse_since(since)
+                .map_err(|e| error_response(ErrorCode::InvalidRequest, e, &request_id))?,
+        ),
+        None => headers.get(IF_NONE_MATCH).and_then(seen_in),
+    };
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        seen_minute = seen,
+        "Processing time poll request"
+    );
+
+    // Reject unknown timezones before waiting.
+    current_time_in(&timezone, &TimestampFormat::Rfc3339, &request_id)?;
+    state.record_timezone_use(&timezone);
+
+    if let Some(seen) = seen.filter(|seen| *seen == minute_of(Utc::now())) {
+        let rollover = seen
+            .checked_add(1)
+            .and_then(|next| next.checked_mul(60))
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .unwrap_or_else(Utc::now);
+        let until_rollover = (rollover - Utc::now()).to_std().unwrap_or_default();
+        let wait = limits::remaining().map_or(state.polls.max_wait, |remaining| {
+            state
+                .polls
+                .max_wait
+                .min(remaining.saturating_sub(DEADLINE_MARGIN))
+        });
+
+        let mut waiting = Waiting::new(&state, &request_id);
+        let rolled_over = tokio::select! {
+            () = tokio::time::sleep(until_rollover), if until_rollover < wait => true,
+            () = tokio::time::sleep(wait) => false,
+            () = state.shutdown.clone().requested() => false,
+        };
+        waiting.answered = true;
+        if !rolled_over {
+            return Ok(not_modified(seen));
+        }
+    }
+
+    let now = Utc::now();
+    let time = crate::time_in_at(&timezone, &TimestampFormat::Rfc3339, now, &request_id)?
+        .into_response(timezone, request_id);
+    let etag = HeaderValue::from_str(&minute_etag(minute_of(now))).expect("ETags are ASCII");
+    Ok((
+        [
+            (ETAG, etag),
+            (CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+        ],
+        Json(time),
+    )
+        .into_response())
+}
+
+fn not_modified(minute: i64) -> Response {
+    let etag = HeaderValue::from_str(&minute_etag(minute)).expect("ETags are ASCII");
+    (
+        StatusCode::NOT_MODIFIED,
+        [
+            (ETAG, etag),
+            (CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+        ],
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::Arc;
+
+    fn poll_state(max_wait: Duration) -> AppState {
+        AppState {
+            polls: Arc::new(Polls::new(max_wait)),
+            ..crate::tests::test_state()
+        }
+    }
+
+    /// Waits out the last two seconds of a minute, so a poll on the current
+    /// minute cannot see it roll over mid-test.
+    async fn early_in_the_minute() {
+        while Utc::now().timestamp().rem_euclid(60) >= 58 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    fn query(since: Option<String>) -> Query<TimePollQuery> {
+        Query(TimePollQuery {
+            timezone: Some("Asia/Bangkok".to_string()),
+            since,
+        })
+    }
+
+    #[test]
+    fn reads_the_minute_from_since_and_etags() {
+        let at = Utc.with_ymd_and_hms(2025, 3, 5, 7, 30, 59).unwrap();
+        assert_eq!(parse_since("2025-03-05T14:30:59+07:00"), Ok(minute_of(at)));
+        assert_eq!(parse_since(&at.timestamp().to_string()), Ok(minute_of(at)));
+        assert!(parse_since("half past").is_err());
+
+        let tag = minute_etag(minute_of(at));
+        let header = HeaderValue::from_str(&format!("\"other\", {tag}")).unwrap();
+        assert_eq!(seen_in(&header), Some(minute_of(at)));
+        assert_eq!(seen_in(&HeaderValue::from_static("W/\"abc\"")), None);
+    }
+
+    #[tokio::test]
+    async fn answers_at_once_for_a_new_minute_and_304_after_the_wait() {
+        let state = poll_state(Duration::from_millis(50));
+        let response = get_time_poll(
+            State(state.clone()),
+            HeaderMap::new(),
+            query(Some("2025-01-01T00:00:00Z".to_string())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].clone();
+
+        early_in_the_minute().await;
+        let mut headers = HeaderMap::new();
+        let current = minute_of(Utc::now());
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_str(&minute_etag(current)).unwrap(),
+        );
+        let response = get_time_poll(State(state.clone()), headers, query(None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], minute_etag(current).as_str());
+        assert!(etag.to_str().unwrap().starts_with("W/\"minute-"));
+
+        let error = get_time_poll(
+            State(state),
+            HeaderMap::new(),
+            query(Some("yesterday".to_string())),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+    }
+
+    #[tokio::test]
+    async fn stops_waiting_when_the_client_goes_away() {
+        let state = poll_state(Duration::from_secs(60));
+        early_in_the_minute().await;
+        let since = Utc::now().to_rfc3339();
+        let poll = tokio::spawn(get_time_poll(
+            State(state.clone()),
+            HeaderMap::new(),
+            query(Some(since)),
+        ));
+        while state.polls.waiting.load(Ordering::Acquire) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(state.metrics.render().contains("time_polls_waiting 1\n"));
+
+        poll.abort();
+        assert!(poll.await.unwrap_err().is_cancelled());
+        assert_eq!(state.polls.waiting.load(Ordering::Acquire), 0);
+        assert!(state.metrics.render().contains("time_polls_waiting 0\n"));
+    }
+
+    #[tokio::test]
+    async fn answers_future_minutes_at_once() {
+        // Long enough that waiting for either would time the test out.
+        let state = poll_state(Duration::from_secs(60));
+        let future = (Utc::now() + chrono::Duration::minutes(5)).to_rfc3339();
+        let poll = get_time_poll(State(state.clone()), HeaderMap::new(), query(Some(future)));
+        let response = tokio::time::timeout(Duration::from_secs(5), poll)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            IF_NONE_MATCH,
+            HeaderValue::from_str(&minute_etag(i64::MAX)).unwrap(),
+        );
+        let poll = get_time_poll(State(state), headers, query(None));
+        let response = tokio::time::timeout(Duration::from_secs(5), poll)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[ETAG], minute_etag(i64::MAX).as_str());
+    }
+}
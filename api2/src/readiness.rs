@@ -0,0 +1,124 @@
+//! Liveness and readiness probes. Readiness checks the bundled timezone
+//! database and that the wall clock has plausibly been set, and, with NTP
+//! monitoring enabled, that it has not drifted too far. With the tzdata
+//! freshness check enabled, it also reports whether a newer database exists.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use chrono::{DateTime, Offset, Utc};
+use chrono_tz::Tz;
+use common::health::{self, Check};
+use serde_json::json;
+
+use crate::{tzdata, AppState};
+
+/// 2024-01-01T00:00:00Z: a clock reading earlier than this was never set.
+const EARLIEST_SANE_CLOCK: i64 = 1_704_067_200;
+/// 2100-01-01T00:00:00Z: the end of the range the other endpoints accept.
+const LATEST_SANE_CLOCK: i64 = 4_102_444_800;
+
+/// A zone whose offset has been fixed for decades, to prove lookups work.
+const REFERENCE_ZONE: &str = "Asia/Bangkok";
+const REFERENCE_OFFSET_SECONDS: i32 = 7 * 3600;
+
+pub async fn get_live() -> Json<serde_json::Value> {
+    health::live("api2")
+}
+
+pub async fn get_ready(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let now = Utc::now();
+    let drift = state.clock.as_ref().map(|clock| clock.check_drift());
+    let freshness = state.tzdata.as_ref().map(|tzdata| tzdata.check_freshness());
+    health::ready(
+        "api2",
+        &state.shutdown,
+        [
+            timezone_database_check(state.timezone_names.len(), now),
+            clock_check(now),
+            state.flags.check(),
+        ]
+        .into_iter()
+        .chain(drift)
+        .chain(freshness),
+    )
+}
+
+fn timezone_database_check(zones: usize, now: DateTime<Utc>) -> Check {
+    let offset = REFERENCE_ZONE
+        .parse::<Tz>()
+        .ok()
+        .map(|tz| now.with_timezone(&tz).offset().fix().local_minus_utc());
+    Check::new(
+        "timezone_database",
+        zones > 0 && offset == Some(REFERENCE_OFFSET_SECONDS),
+        json!({
+            "zones": zones,
+            "version": tzdata::EMBEDDED_VERSION,
+            "reference_zone": REFERENCE_ZONE,
+            "reference_offset_seconds": offset,
+        }),
+    )
+}
+
+fn clock_check(now: DateTime<Utc>) -> Check {
+    Check::new(
+        "clock",
+        (EARLIEST_SANE_CLOCK..LATEST_SANE_CLOCK).contains(&now.timestamp()),
+        json!({ "now": now.to_rfc3339() }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ntp::{ClockMonitor, Sample};
+    use chrono::TimeZone;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn ready_while_checks_pass_and_not_shutting_down() {
+        let state = crate::tests::test_state();
+        let (status, Json(body)) = get_ready(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["checks"]["timezone_database"]["status"], "pass");
+        assert_eq!(body["checks"]["clock"]["status"], "pass");
+
+        state.shutdown.request();
+        let (status, _) = get_ready(State(state)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn not_ready_while_the_clock_has_drifted() {
+        let clock = Arc::new(ClockMonitor::new(
+            "127.0.0.1:9".to_string(),
+            Duration::from_secs(60),
+            500,
+        ));
+        let state = AppState {
+            clock: Some(clock.clone()),
+            ..crate::tests::test_state()
+        };
+        let (status, Json(body)) = get_ready(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["checks"]["clock_drift"]["status"], "pass");
+
+        clock.record(Sample {
+            offset_ms: -750,
+            delay_ms: 3,
+            measured_at: Utc::now(),
+        });
+        let (status, Json(body)) = get_ready(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["clock_drift"]["status"], "fail");
+        assert_eq!(state.clock_offset_ms(), Some(-750));
+    }
+
+    #[test]
+    fn rejects_unset_clocks_and_empty_databases() {
+        let epoch = Utc.timestamp_opt(0, 0).unwrap();
+        assert!(!clock_check(epoch).ok);
+        assert!(clock_check(Utc::now()).ok);
+        assert!(!timezone_database_check(0, Utc::now()).ok);
+    }
+}
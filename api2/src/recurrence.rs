@@ -0,0 +1,500 @@
+//! Evaluation of RFC 5545 recurrence rules against a single calendar date.
+//!
+//! Supports `FREQ` (DAILY, WEEKLY, MONTHLY, YEARLY), `INTERVAL`, `COUNT`,
+//! `UNTIL`, `WKST`, `BYMONTH`, `BYMONTHDAY` and `BYDAY` (including ordinal
+//! forms such as `2MO` or `-1FR`). Rules that use any other part are rejected
+//! rather than silently evaluated incorrectly.
+//!
+//! The `rrule` crate would cover the whole RFC, but it is not available to
+//! this workspace's offline builds, so the subset above is evaluated here.
+
+use axum::response::Json;
+use chrono::{DateTime, Datelike, NaiveDate, Weekday};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::calendar::days_in_month;
+use crate::{error_response, parse_timezone, ApiError};
+
+/// Upper bound on the span walked between `dtstart` and the queried date.
+const MAX_SPAN_DAYS: i64 = 366 * 200;
+
+#[derive(Debug, Deserialize)]
+pub struct RecurrenceQuery {
+    date: String,
+    dtstart: String,
+    rrule: String,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecurrenceCheck {
+    matches: bool,
+    occurrence_number: Option<u32>,
+}
+
+pub async fn get_recurring_event_check(
+    Query(params): Query<RecurrenceQuery>,
+) -> Result<Json<RecurrenceCheck>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        rrule = %params.rrule,
+        timezone = %timezone,
+        "Processing recurring event check"
+    );
+
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let date = NaiveDate::parse_from_str(&params.date, "%Y-%m-%d").map_err(|_| {
+        bad_request(format!(
+            "Invalid date: {} (expected YYYY-MM-DD)",
+            params.date
+        ))
+    })?;
+    let dtstart = DateTime::parse_from_rfc3339(&params.dtstart)
+        .map_err(|_| {
+            bad_request(format!(
+                "Invalid dtstart: {} (expected RFC 3339)",
+                params.dtstart
+            ))
+        })?
+        .with_timezone(&tz)
+        .date_naive();
+    let rule = RecurrenceRule::parse(&params.rrule, dtstart).map_err(bad_request)?;
+
+    if (date - dtstart).num_days() > MAX_SPAN_DAYS {
+        return Err(bad_request(format!(
+            "date must be within {MAX_SPAN_DAYS} days of dtstart"
+        )));
+    }
+
+    let occurrence_number = rule.occurrence_number(date);
+
+    Ok(Json(RecurrenceCheck {
+        matches: occurrence_number.is_some(),
+        occurrence_number,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ByDay {
+    ordinal: Option<i32>,
+    weekday: Weekday,
+}
+
+#[derive(Debug)]
+struct RecurrenceRule {
+    dtstart: NaiveDate,
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDate>,
+    week_start: Weekday,
+    by_month: Vec<u32>,
+    by_month_day: Vec<i32>,
+    by_day: Vec<ByDay>,
+}
+
+impl RecurrenceRule {
+    fn parse(rule: &str, dtstart: NaiveDate) -> Result<Self, String> {
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut frequency = None;
+        let mut parsed = RecurrenceRule {
+            dtstart,
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            week_start: Weekday::Mon,
+            by_month: Vec::new(),
+            by_month_day: Vec::new(),
+            by_day: Vec::new(),
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Malformed rrule part: {part}"))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("Unsupported FREQ: {other}")),
+                    })
+                }
+                "INTERVAL" => {
+                    parsed.interval = parse_number(key, value)?;
+                    if parsed.interval == 0 {
+                        return Err("INTERVAL must be at least 1".to_string());
+                    }
+                }
+                "COUNT" => parsed.count = Some(parse_number(key, value)?),
+                "UNTIL" => parsed.until = Some(parse_until(value)?),
+                "WKST" => parsed.week_start = parse_weekday(value)?,
+                "BYMONTH" => {
+                    parsed.by_month = parse_list(key, value)?;
+                    if parsed
+                        .by_month
+                        .iter()
+                        .any(|month| !(1..=12).contains(month))
+                    {
+                        return Err("BYMONTH values must be between 1 and 12".to_string());
+                    }
+                }
+                "BYMONTHDAY" => {
+                    parsed.by_month_day = parse_list(key, value)?;
+                    if parsed
+                        .by_month_day
+                        .iter()
+                        .any(|day| *day == 0 || !(-31..=31).contains(day))
+                    {
+                        return Err("BYMONTHDAY values must be between -31 and 31".to_string());
+                    }
+                }
+                "BYDAY" => {
+                    parsed.by_day = value
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Result<_, _>>()?
+                }
+                other => return Err(format!("Unsupported rrule part: {other}")),
+            }
+        }
+
+        parsed.frequency = frequency.ok_or_else(|| "rrule is missing FREQ".to_string())?;
+
+        // Mirror RFC 5545's implicit expansion: without explicit BY* parts the
+        // rule inherits the day (and month, for yearly rules) from DTSTART.
+        if parsed.by_month_day.is_empty() && parsed.by_day.is_empty() {
+            match parsed.frequency {
+                Frequency::Weekly => parsed.by_day.push(ByDay {
+                    ordinal: None,
+                    weekday: dtstart.weekday(),
+                }),
+                Frequency::Monthly => parsed.by_month_day.push(dtstart.day() as i32),
+                Frequency::Yearly => {
+                    parsed.by_month_day.push(dtstart.day() as i32);
+                    if parsed.by_month.is_empty() {
+                        parsed.by_month.push(dtstart.month());
+                    }
+                }
+                Frequency::Daily => {}
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the 1-based index of `date` among the rule's occurrences, or
+    /// `None` when the date is not an occurrence.
+    fn occurrence_number(&self, date: NaiveDate) -> Option<u32> {
+        if date < self.dtstart || !self.matches(date) {
+            return None;
+        }
+
+        let number = self
+            .dtstart
+            .iter_days()
+            .take_while(|day| *day <= date)
+            .filter(|day| self.matches(*day))
+            .count() as u32;
+
+        match self.count {
+            Some(count) if number > count => None,
+            _ => Some(number),
+        }
+    }
+
+    fn matches(&self, date: NaiveDate) -> bool {
+        if date < self.dtstart || self.until.is_some_and(|until| date > until) {
+            return false;
+        }
+        if !self.in_interval(date) {
+            return false;
+        }
+        if !self.by_month.is_empty() && !self.by_month.contains(&date.month()) {
+            return false;
+        }
+        if !self.by_month_day.is_empty() {
+            let length = days_in_month(date.year(), date.month()) as i32;
+            let day = date.day() as i32;
+            let matches_day = self
+                .by_month_day
+                .iter()
+                .any(|&wanted| wanted == day || wanted == day - length - 1);
+            if !matches_day {
+                return false;
+            }
+        }
+        if !self.by_day.is_empty()
+            && !self
+                .by_day
+                .iter()
+                .any(|by_day| self.matches_by_day(by_day, date))
+        {
+            return false;
+        }
+        true
+    }
+
+    fn in_interval(&self, date: NaiveDate) -> bool {
+        let interval = i64::from(self.interval);
+        let elapsed = match self.frequency {
+            Frequency::Daily => (date - self.dtstart).num_days(),
+            Frequency::Weekly => (week_start(date, self.week_start)
+                - week_start(self.dtstart, self.week_start))
+            .num_weeks(),
+            Frequency::Monthly => {
+                i64::from(date.year() - self.dtstart.year()) * 12 + i64::from(date.month())
+                    - i64::from(self.dtstart.month())
+            }
+            Frequency::Yearly => i64::from(date.year() - self.dtstart.year()),
+        };
+        elapsed % interval == 0
+    }
+
+    fn matches_by_day(&self, by_day: &ByDay, date: NaiveDate) -> bool {
+        if date.weekday() != by_day.weekday {
+            return false;
+        }
+        let Some(ordinal) = by_day.ordinal else {
+            return true;
+        };
+
+        // Ordinals count within the month for monthly rules (and yearly rules
+        // restricted by BYMONTH), otherwise within the year.
+        let within_month = self.frequency == Frequency::Monthly
+            || (self.frequency == Frequency::Yearly && !self.by_month.is_empty());
+        let (index, total_days) = if within_month {
+            (
+                date.day() as i32,
+                days_in_month(date.year(), date.month()) as i32,
+            )
+        } else {
+            let days_in_year = if NaiveDate::from_ymd_opt(date.year(), 2, 29).is_some() {
+                366
+            } else {
+                365
+            };
+            (date.ordinal() as i32, days_in_year)
+        };
+
+        if ordinal > 0 {
+            (index - 1) / 7 + 1 == ordinal
+        } else {
+            (total_days - index) / 7 + 1 == -ordinal
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {key} value: {value}"))
+}
+
+fn parse_list<T: std::str::FromStr>(key: &str, value: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| parse_number(key, item))
+        .collect()
+}
+
+fn parse_until(value: &str) -> Result<NaiveDate, String> {
+    let date_part = value.get(..8).unwrap_or(value);
+    NaiveDate::parse_from_str(date_part, "%Y%m%d")
+        .map_err(|_| format!("Invalid UNTIL value: {value}"))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("Invalid weekday: {value}")),
+    }
+}
+
+fn parse_by_day(value: &str) -> Result<ByDay, String> {
+    // The weekday is the last two bytes, which are only a char boundary for
+    // ASCII input.
+    if !value.is_ascii() {
+        return Err(format!("Invalid BYDAY value: {value}"));
+    }
+    let split = value.len().saturating_sub(2);
+    let (ordinal, weekday) = value.split_at(split);
+    let ordinal = match ordinal {
+        "" => None,
+        ordinal => {
+            let ordinal: i32 = ordinal
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| format!("Invalid BYDAY value: {value}"))?;
+            if ordinal == 0 || !(-53..=53).contains(&ordinal) {
+                return Err(format!("Invalid BYDAY ordinal: {value}"));
+            }
+            Some(ordinal)
+        }
+    };
+    Ok(ByDay {
+        ordinal,
+        weekday: parse_weekday(weekday)?,
+    })
+}
+
+fn week_start(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset =
+        (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    date - chrono::Duration::days(i64::from(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    /// Occurrence numbers of `rule`, starting on `dtstart`, for each date.
+    fn occurrences(rule: &str, dtstart: &str, dates: &[&str]) -> Vec<Option<u32>> {
+        let rule = RecurrenceRule::parse(rule, date(dtstart)).unwrap();
+        dates
+            .iter()
+            .map(|day| rule.occurrence_number(date(day)))
+            .collect()
+    }
+
+    #[test]
+    fn counts_occurrences_at_each_frequency() {
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY;BYMONTHDAY=15",
+                "2023-01-15",
+                &["2024-01-15", "2024-01-16", "2022-12-15"]
+            ),
+            [Some(13), None, None]
+        );
+        assert_eq!(
+            occurrences("FREQ=DAILY", "2024-01-01", &["2024-01-01", "2024-03-01"]),
+            [Some(1), Some(61)]
+        );
+        assert_eq!(
+            occurrences("FREQ=WEEKLY", "2024-01-01", &["2024-01-08", "2024-01-09"]),
+            [Some(2), None]
+        );
+        assert_eq!(
+            occurrences(
+                "RRULE:FREQ=YEARLY",
+                "2020-02-29",
+                &["2024-02-29", "2023-02-28"]
+            ),
+            [Some(2), None]
+        );
+    }
+
+    #[test]
+    fn applies_interval_count_and_until() {
+        assert_eq!(
+            occurrences(
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE",
+                "2024-01-01",
+                &["2024-01-03", "2024-01-08", "2024-01-15", "2024-01-17"]
+            ),
+            [Some(2), None, Some(3), Some(4)]
+        );
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;COUNT=3",
+                "2024-01-01",
+                &["2024-01-03", "2024-01-04"]
+            ),
+            [Some(3), None]
+        );
+        assert_eq!(
+            occurrences(
+                "FREQ=DAILY;UNTIL=20240105T000000Z",
+                "2024-01-01",
+                &["2024-01-05", "2024-01-06"]
+            ),
+            [Some(5), None]
+        );
+    }
+
+    #[test]
+    fn matches_ordinal_weekdays() {
+        // The second Monday and the last Friday of each month.
+        assert_eq!(
+            occurrences(
+                "FREQ=MONTHLY;BYDAY=2MO,-1FR",
+                "2024-01-01",
+                &["2024-01-08", "2024-01-26", "2024-01-19", "2024-02-12"]
+            ),
+            [Some(1), Some(2), None, Some(3)]
+        );
+        // The last Sunday of March, counted within the month by BYMONTH.
+        assert_eq!(
+            occurrences(
+                "FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+                "2024-01-01",
+                &["2024-03-31", "2025-03-30", "2025-03-23"]
+            ),
+            [Some(1), Some(2), None]
+        );
+        // The 20th Monday of the year.
+        assert_eq!(
+            occurrences("FREQ=YEARLY;BYDAY=+20MO", "2024-01-01", &["2024-05-13"]),
+            [Some(1)]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for (rule, error) in [
+            ("INTERVAL=2", "rrule is missing FREQ"),
+            ("FREQ=HOURLY", "Unsupported FREQ: HOURLY"),
+            ("FREQ=DAILY;INTERVAL=0", "INTERVAL must be at least 1"),
+            ("FREQ=DAILY;COUNT=-1", "Invalid COUNT value: -1"),
+            ("FREQ=DAILY;UNTIL=2024", "Invalid UNTIL value: 2024"),
+            ("FREQ=DAILY;BYSETPOS=1", "Unsupported rrule part: BYSETPOS"),
+            (
+                "FREQ=DAILY;BYMONTH=13",
+                "BYMONTH values must be between 1 and 12",
+            ),
+            ("FREQ=DAILY;BYDAY", "Malformed rrule part: BYDAY"),
+            ("FREQ=WEEKLY;BYDAY=XX", "Invalid weekday: XX"),
+            ("FREQ=MONTHLY;BYDAY=0MO", "Invalid BYDAY ordinal: 0MO"),
+            ("FREQ=MONTHLY;BYDAY=54MO", "Invalid BYDAY ordinal: 54MO"),
+            ("FREQ=WEEKLY;BYDAY=éa", "Invalid BYDAY value: éa"),
+            ("FREQ=WEEKLY;BYDAY=1é", "Invalid BYDAY value: 1é"),
+        ] {
+            assert_eq!(
+                RecurrenceRule::parse(rule, date("2024-01-01")).unwrap_err(),
+                error,
+                "{rule}"
+            );
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! 52/53-week retail calendars built from 4-4-5, 4-5-4 or 5-4-4 quarters.
+
+use axum::response::Json;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, parse_timezone, ApiError};
+
+/// Start of the NRF 2024 fiscal year, used when no `fy_start` is supplied.
+const DEFAULT_FY_START: &str = "2024-02-04";
+
+#[derive(Debug, Deserialize)]
+pub struct RetailCalendarQuery {
+    date: Option<String>,
+    pattern: Option<String>,
+    fy_start: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetailCalendarWeek {
+    retail_week: u32,
+    retail_period: u32,
+    retail_quarter: u32,
+    period_start: String,
+    period_end: String,
+    leap_week: bool,
+}
+
+pub async fn get_retail_calendar(
+    Query(params): Query<RetailCalendarQuery>,
+) -> Result<Json<RetailCalendarWeek>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let pattern = params.pattern.unwrap_or_else(|| "4-4-5".to_string());
+
+    info!(
+        request_id = %request_id,
+        pattern = %pattern,
+        timezone = %timezone,
+        "Processing retail calendar request"
+    );
+
+    let bad_request =
+        |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+    let parse_date = |name: &str, value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| bad_request(format!("Invalid {name}: {value} (expected YYYY-MM-DD)")))
+    };
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let weeks_per_period = match pattern.as_str() {
+        "4-4-5" => [4, 4, 5],
+        "4-5-4" => [4, 5, 4],
+        "5-4-4" => [5, 4, 4],
+        other => {
+            return Err(bad_request(format!(
+                "Unsupported pattern: {other} (expected 4-4-5, 4-5-4 or 5-4-4)"
+            )))
+        }
+    };
+    let fy_start = parse_date(
+        "fy_start",
+        params.fy_start.as_deref().unwrap_or(DEFAULT_FY_START),
+    )?;
+    let date = match params.date {
+        Some(date) => parse_date("date", &date)?,
+        None => Utc::now().with_timezone(&tz).date_naive(),
+    };
+
+    retail_week(date, fy_start, weeks_per_period)
+        .map(Json)
+        .ok_or_else(|| bad_request(format!("Date out of range for the retail calendar: {date}")))
+}
+
+/// Locates `date` in the retail calendar anchored at `fy_start`.
+///
+/// Every fiscal year starts on `fy_start`'s weekday, on the day nearest the
+/// anniversary of `fy_start`, so years are 52 weeks long with a 53rd "leap"
+/// week every five or six years. The leap week is added to the final period.
+/// `None` when the surrounding fiscal years fall outside chrono's range.
+fn retail_week(
+    date: NaiveDate,
+    fy_start: NaiveDate,
+    weeks_per_period: [u32; 3],
+) -> Option<RetailCalendarWeek> {
+    // A year can start up to three days after its anniversary, so with a
+    // late-December anchor the date may lie two years after the anniversary
+    // of the year it belongs to.
+    let (year, year_start) = (date.year() - 2..=date.year() + 1)
+        .rev()
+        .map(|year| Some((year, fiscal_year_start(fy_start, year)?)))
+        .find(|start| start.is_none_or(|(_, start)| start <= date))??;
+    let next_year_start = fiscal_year_start(fy_start, year + 1)?;
+    let weeks_in_year = ((next_year_start - year_start).num_days() / 7) as u32;
+
+    let week = ((date - year_start).num_days() / 7) as u32 + 1;
+
+    let mut period_first_week = 1;
+    let mut period = 1;
+    loop {
+        let mut length = weeks_per_period[(period as usize - 1) % 3];
+        if period == 12 {
+            length = weeks_in_year - period_first_week + 1;
+        }
+        if week < period_first_week + length {
+            let start = year_start + Duration::weeks(i64::from(period_first_week - 1));
+            let end = start + Duration::weeks(i64::from(length)) - Duration::days(1);
+            return Some(RetailCalendarWeek {
+                retail_week: week,
+                retail_period: period,
+                retail_quarter: (period - 1) / 3 + 1,
+                period_start: start.to_string(),
+                period_end: end.to_string(),
+                leap_week: weeks_in_year == 53,
+            });
+        }
+        period_first_week += length;
+        period += 1;
+    }
+}
+
+/// The day with `anchor`'s weekday nearest to `anchor`'s month and day in
+/// `year`, or `None` outside chrono's range.
+fn fiscal_year_start(anchor: NaiveDate, year: i32) -> Option<NaiveDate> {
+    // The anchor's day, clamped to the month's length (29 February).
+    let anniversary = (1..=anchor.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, anchor.month(), day))?;
+    let behind = (7 + anniversary.weekday().num_days_from_monday()
+        - anchor.weekday().num_days_from_monday())
+        % 7;
+    if behind <= 3 {
+        anniversary.checked_sub_signed(Duration::days(i64::from(behind)))
+    } else {
+        anniversary.checked_add_signed(Duration::days(i64::from(7 - behind)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn places_dates_in_years_anchored_at_the_year_end() {
+        // Years start on the Sunday nearest 31 December: 2025-12-28,
+        // 2027-01-03 and 2028-01-02.
+        let fy_start = date("2023-12-31");
+        let week = retail_week(date("2027-01-01"), fy_start, [4, 4, 5]).unwrap();
+        assert_eq!(week.retail_week, 53);
+        assert_eq!((week.retail_period, week.retail_quarter), (12, 4));
+        assert_eq!(week.period_start, "2026-11-22");
+        assert_eq!(week.period_end, "2027-01-02");
+        assert!(week.leap_week);
+
+        let week = retail_week(date("2027-01-05"), fy_start, [4, 4, 5]).unwrap();
+        assert_eq!((week.retail_week, week.retail_period), (1, 1));
+        assert_eq!(week.period_start, "2027-01-03");
+        assert!(!week.leap_week);
+    }
+
+    #[test]
+    fn adds_the_leap_week_to_the_last_period() {
+        // The year from 2026-02-01 runs to 2027-02-06: 53 weeks.
+        let fy_start = date("2024-02-04");
+        let week = retail_week(date("2027-02-06"), fy_start, [5, 4, 4]).unwrap();
+        assert_eq!((week.retail_week, week.retail_period), (53, 12));
+        assert_eq!(week.period_start, "2027-01-03");
+        assert_eq!(week.period_end, "2027-02-06");
+        assert!(week.leap_week);
+
+        let week = retail_week(date("2025-02-01"), fy_start, [4, 4, 5]).unwrap();
+        assert_eq!((week.retail_week, week.retail_period), (52, 12));
+        assert_eq!(week.period_end, "2025-02-01");
+        assert!(!week.leap_week);
+    }
+
+    #[test]
+    fn reports_years_outside_chronos_range() {
+        assert!(retail_week(NaiveDate::MAX, date("2024-02-04"), [4, 4, 5]).is_none());
+    }
+}
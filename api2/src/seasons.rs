@@ -0,0 +1,216 @@
+//! Astronomical seasons, bounded by the equinoxes and solstices.
+
+use axum::response::Json;
+use chrono::{DateTime, Datelike, NaiveDate, SecondsFormat, TimeZone, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, parse_timezone, ApiError};
+
+const FIRST_YEAR: i32 = 2000;
+const LAST_YEAR: i32 = 2050;
+
+/// Northern-hemisphere season beginning at each event, in table order.
+const NORTHERN_SEASONS: [&str; 4] = ["Spring", "Summer", "Autumn", "Winter"];
+const SOUTHERN_SEASONS: [&str; 4] = ["Autumn", "Winter", "Spring", "Summer"];
+
+/// UTC (month, day, hour, minute) of one equinox or solstice.
+type EventTime = (u32, u32, u32, u32);
+
+/// An equinox or solstice instant paired with its index within the year
+/// (0 = March equinox).
+type SeasonEvent = (DateTime<Utc>, usize);
+
+/// UTC times of the March equinox, June solstice,
+/// September equinox and December solstice for each year.
+///
+/// Computed with the method of Meeus, *Astronomical Algorithms* ch. 27, and
+/// truncated to the minute; values agree with the USNO tables to within a
+/// minute. The 1999 and 2051 rows bound queries at the ends of the range.
+#[rustfmt::skip]
+const EVENTS: &[(i32, [EventTime; 4])] = &[
+    (1999, [(3, 21, 1, 45), (6, 21, 19, 49), (9, 23, 11, 31), (12, 22, 7, 44)]),
+    (2000, [(3, 20, 7, 35), (6, 21, 1, 47), (9, 22, 17, 27), (12, 21, 13, 37)]),
+    (2001, [(3, 20, 13, 31), (6, 21, 7, 37), (9, 22, 23, 4), (12, 21, 19, 21)]),
+    (2002, [(3, 20, 19, 16), (6, 21, 13, 24), (9, 23, 4, 55), (12, 22, 1, 14)]),
+    (2003, [(3, 21, 1, 0), (6, 21, 19, 10), (9, 23, 10, 47), (12, 22, 7, 3)]),
+    (2004, [(3, 20, 6, 48), (6, 21, 0, 56), (9, 22, 16, 30), (12, 21, 12, 41)]),
+    (2005, [(3, 20, 12, 33), (6, 21, 6, 46), (9, 22, 22, 22), (12, 21, 18, 35)]),
+    (2006, [(3, 20, 18, 25), (6, 21, 12, 25), (9, 23, 4, 3), (12, 22, 0, 22)]),
+    (2007, [(3, 21, 0, 7), (6, 21, 18, 6), (9, 23, 9, 50), (12, 22, 6, 7)]),
+    (2008, [(3, 20, 5, 48), (6, 20, 23, 59), (9, 22, 15, 44), (12, 21, 12, 3)]),
+    (2009, [(3, 20, 11, 43), (6, 21, 5, 45), (9, 22, 21, 18), (12, 21, 17, 46)]),
+    (2010, [(3, 20, 17, 31), (6, 21, 11, 28), (9, 23, 3, 9), (12, 21, 23, 38)]),
+    (2011, [(3, 20, 23, 20), (6, 21, 17, 16), (9, 23, 9, 4), (12, 22, 5, 30)]),
+    (2012, [(3, 20, 5, 14), (6, 20, 23, 8), (9, 22, 14, 49), (12, 21, 11, 11)]),
+    (2013, [(3, 20, 11, 1), (6, 21, 5, 3), (9, 22, 20, 43), (12, 21, 17, 11)]),
+    (2014, [(3, 20, 16, 56), (6, 21, 10, 51), (9, 23, 2, 29), (12, 21, 23, 3)]),
+    (2015, [(3, 20, 22, 45), (6, 21, 16, 37), (9, 23, 8, 20), (12, 22, 4, 48)]),
+    (2016, [(3, 20, 4, 30), (6, 20, 22, 34), (9, 22, 14, 20), (12, 21, 10, 44)]),
+    (2017, [(3, 20, 10, 28), (6, 21, 4, 24), (9, 22, 20, 1), (12, 21, 16, 28)]),
+    (2018, [(3, 20, 16, 15), (6, 21, 10, 7), (9, 23, 1, 54), (12, 21, 22, 22)]),
+    (2019, [(3, 20, 21, 58), (6, 21, 15, 54), (9, 23, 7, 49), (12, 22, 4, 19)]),
+    (2020, [(3, 20, 3, 49), (6, 20, 21, 43), (9, 22, 13, 30), (12, 21, 10, 2)]),
+    (2021, [(3, 20, 9, 37), (6, 21, 3, 31), (9, 22, 19, 20), (12, 21, 15, 59)]),
+    (2022, [(3, 20, 15, 33), (6, 21, 9, 13), (9, 23, 1, 4), (12, 21, 21, 47)]),
+    (2023, [(3, 20, 21, 24), (6, 21, 14, 57), (9, 23, 6, 50), (12, 22, 3, 27)]),
+    (2024, [(3, 20, 3, 6), (6, 20, 20, 50), (9, 22, 12, 43), (12, 21, 9, 20)]),
+    (2025, [(3, 20, 9, 1), (6, 21, 2, 42), (9, 22, 18, 19), (12, 21, 15, 3)]),
+    (2026, [(3, 20, 14, 45), (6, 21, 8, 24), (9, 23, 0, 5), (12, 21, 20, 50)]),
+    (2027, [(3, 20, 20, 24), (6, 21, 14, 10), (9, 23, 6, 1), (12, 22, 2, 42)]),
+    (2028, [(3, 20, 2, 17), (6, 20, 20, 1), (9, 22, 11, 45), (12, 21, 8, 19)]),
+    (2029, [(3, 20, 8, 1), (6, 21, 1, 48), (9, 22, 17, 37), (12, 21, 14, 14)]),
+    (2030, [(3, 20, 13, 51), (6, 21, 7, 31), (9, 22, 23, 27), (12, 21, 20, 9)]),
+    (2031, [(3, 20, 19, 40), (6, 21, 13, 17), (9, 23, 5, 15), (12, 22, 1, 55)]),
+    (2032, [(3, 20, 1, 22), (6, 20, 19, 8), (9, 22, 11, 10), (12, 21, 7, 56)]),
+    (2033, [(3, 20, 7, 22), (6, 21, 1, 0), (9, 22, 16, 51), (12, 21, 13, 45)]),
+    (2034, [(3, 20, 13, 17), (6, 21, 6, 44), (9, 22, 22, 39), (12, 21, 19, 33)]),
+    (2035, [(3, 20, 19, 3), (6, 21, 12, 32), (9, 23, 4, 38), (12, 22, 1, 30)]),
+    (2036, [(3, 20, 1, 2), (6, 20, 18, 31), (9, 22, 10, 23), (12, 21, 7, 12)]),
+    (2037, [(3, 20, 6, 49), (6, 21, 0, 22), (9, 22, 16, 12), (12, 21, 13, 7)]),
+    (2038, [(3, 20, 12, 40), (6, 21, 6, 9), (9, 22, 22, 2), (12, 21, 19, 2)]),
+    (2039, [(3, 20, 18, 32), (6, 21, 11, 57), (9, 23, 3, 49), (12, 22, 0, 40)]),
+    (2040, [(3, 20, 0, 11), (6, 20, 17, 46), (9, 22, 9, 44), (12, 21, 6, 32)]),
+    (2041, [(3, 20, 6, 6), (6, 20, 23, 36), (9, 22, 15, 26), (12, 21, 12, 18)]),
+    (2042, [(3, 20, 11, 52), (6, 21, 5, 15), (9, 22, 21, 11), (12, 21, 18, 4)]),
+    (2043, [(3, 20, 17, 27), (6, 21, 10, 57), (9, 23, 3, 6), (12, 22, 0, 1)]),
+    (2044, [(3, 19, 23, 20), (6, 20, 16, 50), (9, 22, 8, 47), (12, 21, 5, 43)]),
+    (2045, [(3, 20, 5, 7), (6, 20, 22, 33), (9, 22, 14, 32), (12, 21, 11, 34)]),
+    (2046, [(3, 20, 10, 57), (6, 21, 4, 14), (9, 22, 20, 21), (12, 21, 17, 28)]),
+    (2047, [(3, 20, 16, 52), (6, 21, 10, 2), (9, 23, 2, 7), (12, 21, 23, 7)]),
+    (2048, [(3, 19, 22, 33), (6, 20, 15, 53), (9, 22, 8, 0), (12, 21, 5, 1)]),
+    (2049, [(3, 20, 4, 28), (6, 20, 21, 47), (9, 22, 13, 42), (12, 21, 10, 51)]),
+    (2050, [(3, 20, 10, 19), (6, 21, 3, 32), (9, 22, 19, 28), (12, 21, 16, 38)]),
+    (2051, [(3, 20, 15, 58), (6, 21, 9, 18), (9, 23, 1, 26), (12, 21, 22, 33)]),
+];
+
+#[derive(Debug, Deserialize)]
+pub struct SeasonQuery {
+    year: Option<i32>,
+    timezone: Option<String>,
+    hemisphere: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AstronomicalSeason {
+    current_season: &'static str,
+    season_start: String,
+    next_season: &'static str,
+    next_season_start: String,
+}
+
+pub async fn get_astronomical_season(
+    Query(params): Query<SeasonQuery>,
+) -> Result<Json<AstronomicalSeason>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let hemisphere = params.hemisphere.unwrap_or_else(|| "north".to_string());
+
+    info!(
+        request_id = %request_id,
+        year = ?params.year,
+        timezone = %timezone,
+        hemisphere = %hemisphere,
+        "Processing astronomical season request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let names = match hemisphere.as_str() {
+        "north" => &NORTHERN_SEASONS,
+        "south" => &SOUTHERN_SEASONS,
+        other => {
+            return Err(error_response(
+                ErrorCode::InvalidRequest,
+                format!("Unsupported hemisphere: {other} (expected north or south)"),
+                &request_id,
+            ))
+        }
+    };
+
+    // A `year` moves the current instant into that year, keeping the time of year.
+    let now = Utc::now();
+    let reference = match params.year {
+        None => now,
+        Some(year) if (FIRST_YEAR..=LAST_YEAR).contains(&year) => now
+            .with_year(year)
+            .or_else(|| (now - chrono::Duration::days(1)).with_year(year))
+            .expect("Feb 28 exists in every year"),
+        Some(year) => {
+            return Err(error_response(
+                ErrorCode::InvalidRequest,
+                format!("year must be between {FIRST_YEAR} and {LAST_YEAR}, got {year}"),
+                &request_id,
+            ))
+        }
+    };
+
+    let (current, next) = surrounding_events(reference).ok_or_else(|| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            format!("Season data is only available for {FIRST_YEAR}-{LAST_YEAR}"),
+            &request_id,
+        )
+    })?;
+
+    let format = |at: DateTime<Utc>| {
+        at.with_timezone(&tz)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    };
+
+    Ok(Json(AstronomicalSeason {
+        current_season: names[current.1],
+        season_start: format(current.0),
+        next_season: names[next.1],
+        next_season_start: format(next.0),
+    }))
+}
+
+/// Returns the most recent event at or before `at` and the event after it.
+fn surrounding_events(at: DateTime<Utc>) -> Option<(SeasonEvent, SeasonEvent)> {
+    let events: Vec<SeasonEvent> = EVENTS
+        .iter()
+        .flat_map(|(year, events)| {
+            events
+                .iter()
+                .enumerate()
+                .map(move |(index, &(month, day, hour, minute))| {
+                    let at = NaiveDate::from_ymd_opt(*year, month, day)
+                        .and_then(|date| date.and_hms_opt(hour, minute, 0))
+                        .expect("season table holds valid dates");
+                    (Utc.from_utc_datetime(&at), index)
+                })
+        })
+        .collect();
+
+    let next = events.iter().position(|(event, _)| *event > at)?;
+    Some((*events.get(next.checked_sub(1)?)?, events[next]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_events_either_side() {
+        let midsummer = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        let (current, next) = surrounding_events(midsummer).unwrap();
+        assert_eq!(
+            current,
+            (Utc.with_ymd_and_hms(2024, 6, 20, 20, 50, 0).unwrap(), 1)
+        );
+        assert_eq!(
+            next,
+            (Utc.with_ymd_and_hms(2024, 9, 22, 12, 43, 0).unwrap(), 2)
+        );
+        assert_eq!(
+            (NORTHERN_SEASONS[1], SOUTHERN_SEASONS[1]),
+            ("Summer", "Winter")
+        );
+
+        let solstice = Utc.with_ymd_and_hms(2024, 6, 20, 20, 50, 0).unwrap();
+        assert_eq!(surrounding_events(solstice).unwrap().0 .1, 1);
+        assert!(surrounding_events(Utc.with_ymd_and_hms(1990, 6, 1, 0, 0, 0).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+//! Greenwich and local mean sidereal time (IAU 2006 precession model).
+
+use axum::response::Json;
+use chrono::{DateTime, Utc};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, ApiError};
+
+const J2000_JULIAN_DATE: f64 = 2_451_545.0;
+const UNIX_EPOCH_JULIAN_DATE: f64 = 2_440_587.5;
+/// TT − UTC in seconds: 32.184 s plus the 37 leap seconds in effect since 2017.
+const TT_MINUS_UTC_SECONDS: f64 = 69.184;
+
+#[derive(Debug, Deserialize)]
+pub struct SiderealQuery {
+    longitude: Option<f64>,
+    datetime: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SiderealTime {
+    gst: String,
+    lst: String,
+    gst_degrees: f64,
+    lst_degrees: f64,
+}
+
+pub async fn get_sidereal(
+    Query(params): Query<SiderealQuery>,
+) -> Result<Json<SiderealTime>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let longitude = params.longitude.unwrap_or(0.0);
+
+    info!(
+        request_id = %request_id,
+        longitude = longitude,
+        "Processing sidereal time request"
+    );
+
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!("longitude must be between -180 and 180 degrees, got {longitude}"),
+            &request_id,
+        ));
+    }
+    let at = match params.datetime {
+        Some(datetime) => DateTime::parse_from_rfc3339(&datetime)
+            .map_err(|_| {
+                error_response(
+                    ErrorCode::InvalidRequest,
+                    format!("Invalid datetime: {datetime} (expected RFC 3339)"),
+                    &request_id,
+                )
+            })?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+
+    let gst_degrees = greenwich_mean_sidereal_degrees(at);
+    let lst_degrees = (gst_degrees + longitude).rem_euclid(360.0);
+
+    Ok(Json(SiderealTime {
+        gst: format_degrees_as_time(gst_degrees),
+        lst: format_degrees_as_time(lst_degrees),
+        gst_degrees,
+        lst_degrees,
+    }))
+}
+
+/// Greenwich Mean Sidereal Time in degrees, from the Earth Rotation Angle plus
+/// the IAU 2006 precession polynomial (Capitaine et al. 2003). UT1 is taken
+/// to equal UTC, which is accurate to within a second of time.
+fn greenwich_mean_sidereal_degrees(at: DateTime<Utc>) -> f64 {
+    let unix_seconds = at.timestamp() as f64 + f64::from(at.timestamp_subsec_nanos()) / 1e9;
+    let ut1_days = unix_seconds / 86_400.0 + UNIX_EPOCH_JULIAN_DATE - J2000_JULIAN_DATE;
+    let tt_centuries = (ut1_days + TT_MINUS_UTC_SECONDS / 86_400.0) / 36_525.0;
+
+    let earth_rotation_turns =
+        0.779_057_273_264 + 0.002_737_811_911_354_48 * ut1_days + ut1_days.fract();
+    let precession_arcsec =
+        0.014506 + 4612.156534 * tt_centuries + 1.3915817 * tt_centuries.powi(2)
+            - 0.00000044 * tt_centuries.powi(3)
+            - 0.000029956 * tt_centuries.powi(4)
+            - 0.0000000368 * tt_centuries.powi(5);
+
+    (earth_rotation_turns.fract() * 360.0 + precession_arcsec / 3600.0).rem_euclid(360.0)
+}
+
+/// Formats an angle as hours of right ascension, `HH:MM:SS.sss`.
+fn format_degrees_as_time(degrees: f64) -> String {
+    let total_millis = (degrees / 15.0 * 3_600_000.0).round() as u64 % 86_400_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        total_millis / 3_600_000,
+        total_millis / 60_000 % 60,
+        total_millis / 1000 % 60,
+        total_millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Difference between two angles in seconds of time, wrapping at 24h.
+    fn seconds_apart(a: f64, b: f64) -> f64 {
+        let difference = (a - b).rem_euclid(360.0);
+        difference.min(360.0 - difference) * 240.0
+    }
+
+    #[test]
+    fn gmst_at_j2000() {
+        // Astronomical Almanac: GMST at 2000-01-01 12:00 UT1 is 18h41m50.548s.
+        let at = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+        let expected = (18.0 + 41.0 / 60.0 + 50.548 / 3600.0) * 15.0;
+        assert!(seconds_apart(greenwich_mean_sidereal_degrees(at), expected) < 0.01);
+    }
+
+    #[test]
+    fn gmst_meeus_example_12b() {
+        // Meeus, Astronomical Algorithms, example 12.b: 1987-04-10 19:21:00 UT
+        // has a mean sidereal time of 8h34m57.0896s.
+        let at = Utc.with_ymd_and_hms(1987, 4, 10, 19, 21, 0).unwrap();
+        let expected = (8.0 + 34.0 / 60.0 + 57.0896 / 3600.0) * 15.0;
+        assert!(seconds_apart(greenwich_mean_sidereal_degrees(at), expected) < 0.05);
+    }
+
+    #[test]
+    fn formats_degrees_as_sidereal_time() {
+        assert_eq!(format_degrees_as_time(0.0), "00:00:00.000");
+        assert_eq!(format_degrees_as_time(215.939), "14:23:45.360");
+        assert_eq!(format_degrees_as_time(359.999_999_999), "00:00:00.000");
+    }
+}
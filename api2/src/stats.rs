@@ -0,0 +1,107 @@
+//! In-memory usage counters for the `/time` endpoint, keyed by timezone.
+
+use axum::{extract::State, http::HeaderMap, response::Json};
+use chrono::{TimeZone, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::auth::require_api_key;
+use crate::{ApiError, AppState};
+
+/// Request count and last-request time (Unix milliseconds) for one timezone.
+#[derive(Debug, Default)]
+struct TimezoneUsage {
+    request_count: AtomicU64,
+    last_requested_ms: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+pub struct TimezoneStats {
+    usage: RwLock<HashMap<String, TimezoneUsage>>,
+}
+
+impl TimezoneStats {
+    /// Records one request for `timezone`. Existing entries are updated under
+    /// the read lock; the write lock is only taken for a zone's first request.
+    pub fn record(&self, timezone: &str) {
+        let now_ms = Utc::now().timestamp_millis() as u64;
+        {
+            let usage = self.usage.read().expect("stats lock poisoned");
+            if let Some(entry) = usage.get(timezone) {
+                entry.touch(now_ms);
+                return;
+            }
+        }
+        let mut usage = self.usage.write().expect("stats lock poisoned");
+        usage.entry(timezone.to_string()).or_default().touch(now_ms);
+    }
+
+    fn snapshot(&self) -> TimezoneStatsResponse {
+        let usage = self.usage.read().expect("stats lock poisoned");
+        let mut timezones: Vec<TimezoneStat> = usage
+            .iter()
+            .map(|(timezone, entry)| {
+                let last_ms = entry.last_requested_ms.load(Ordering::Relaxed) as i64;
+                TimezoneStat {
+                    timezone: timezone.clone(),
+                    request_count: entry.request_count.load(Ordering::Relaxed),
+                    last_requested: Utc
+                        .timestamp_millis_opt(last_ms)
+                        .single()
+                        .map(|at| at.to_rfc3339())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        timezones.sort_by(|a, b| {
+            b.request_count
+                .cmp(&a.request_count)
+                .then_with(|| a.timezone.cmp(&b.timezone))
+        });
+
+        TimezoneStatsResponse {
+            total_requests: timezones.iter().map(|stat| stat.request_count).sum(),
+            timezones,
+        }
+    }
+}
+
+impl TimezoneUsage {
+    fn touch(&self, now_ms: u64) {
+        self.request_count.fetch_add(1, Ordering::Relaxed);
+        self.last_requested_ms.fetch_max(now_ms, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneStat {
+    timezone: String,
+    request_count: u64,
+    last_requested: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneStatsResponse {
+    total_requests: u64,
+    timezones: Vec<TimezoneStat>,
+}
+
+pub async fn get_per_timezone_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<TimezoneStatsResponse>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        "Processing per-timezone stats request"
+    );
+
+    require_api_key(&headers, &state.api_keys, &request_id)?;
+
+    Ok(Json(state.timezone_stats.snapshot()))
+}
@@ -0,0 +1,167 @@
+//! Server-Sent Events stream of the current time.
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use common::format::TimestampFormat;
+use common::problem::Query;
+use futures_util::stream::{self, Stream};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::time::{interval, Instant, Interval, MissedTickBehavior};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{current_time_in, request_id_from, ApiError, AppState};
+
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const MIN_INTERVAL_MS: u64 = 100;
+const MAX_INTERVAL_MS: u64 = 60_000;
+/// Stream lifetime used when `stream_max_lifetime_secs` is not configured.
+pub const DEFAULT_MAX_LIFETIME: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Deserialize)]
+pub struct TimeStreamQuery {
+    timezone: Option<String>,
+    interval_ms: Option<u64>,
+}
+
+/// Per-connection stream state; dropped when the client disconnects or the
+/// stream reaches its maximum lifetime, so no ticker outlives either.
+struct Ticker {
+    interval: Interval,
+    /// No tick at or after this instant is sent; the stream ends instead.
+    ends_at: Instant,
+    timezone: String,
+    request_id: String,
+}
+
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        info!(request_id = %self.request_id, "Time stream closed");
+    }
+}
+
+pub async fn get_time_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<TimeStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+    let period = params
+        .interval_ms
+        .unwrap_or(DEFAULT_INTERVAL_MS)
+        .clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS);
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        interval_ms = period,
+        "Processing time stream request"
+    );
+
+    // Reject unknown timezones before committing to a stream.
+    current_time_in(&timezone, &TimestampFormat::Rfc3339, &request_id)?;
+    state.record_timezone_use(&timezone);
+
+    let mut ticks = interval(Duration::from_millis(period));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let ticker = Ticker {
+        interval: ticks,
+        ends_at: Instant::now() + state.stream_max_lifetime,
+        timezone,
+        request_id,
+    };
+
+    let events = stream::unfold(ticker, |mut ticker| async move {
+        if ticker.interval.tick().await >= ticker.ends_at {
+            return None;
+        }
+        let time = current_time_in(
+            &ticker.timezone,
+            &TimestampFormat::Rfc3339,
+            &ticker.request_id,
+        )
+        .ok()?
+        .into_response(ticker.timezone.clone(), ticker.request_id.clone());
+        Some((Event::default().event("time").json_data(time), ticker))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use common::TimeResponse;
+
+    #[tokio::test]
+    async fn streams_time_events() {
+        let router = Router::new()
+            .route("/time/stream", get(get_time_stream))
+            .with_state(crate::tests::test_state());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let started = std::time::Instant::now();
+        let mut response = reqwest::get(format!(
+            "http://{addr}/time/stream?timezone=Asia/Tokyo&interval_ms=10"
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let mut received = String::new();
+        let mut events = Vec::new();
+        while events.len() < 3 {
+            let chunk = response.chunk().await.unwrap().expect("stream ended early");
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+            while let Some(end) = received.find("\n\n") {
+                let event: String = received.drain(..end + 2).collect();
+                if let Some(data) = event.lines().find_map(|line| line.strip_prefix("data: ")) {
+                    assert!(event.lines().any(|line| line == "event: time"), "{event}");
+                    events.push(serde_json::from_str::<TimeResponse>(data).unwrap());
+                }
+            }
+        }
+
+        // The first tick is immediate; MIN_INTERVAL_MS stretches the requested 10 ms.
+        assert!(started.elapsed() >= Duration::from_millis(2 * MIN_INTERVAL_MS));
+        assert!(events.iter().all(|event| event.timezone == "Asia/Tokyo"));
+        assert!(events
+            .iter()
+            .all(|event| event.request_id == events[0].request_id));
+        assert!(events[0].timestamp.ends_with("+09:00"));
+    }
+
+    #[tokio::test]
+    async fn ends_streams_after_their_maximum_lifetime() {
+        let mut state = crate::tests::test_state();
+        state.stream_max_lifetime = Duration::from_millis(250);
+        let router = Router::new()
+            .route("/time/stream", get(get_time_stream))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let mut response = reqwest::get(format!("http://{addr}/time/stream?interval_ms=100"))
+            .await
+            .unwrap();
+        let mut body = String::new();
+        let ended = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(chunk) = response.chunk().await.unwrap() {
+                body.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+        })
+        .await;
+        assert!(ended.is_ok(), "stream outlived its maximum lifetime");
+        // Ticks at 0, 100 and 200 ms fall within the lifetime.
+        assert_eq!(body.matches("event: time").count(), 3, "{body}");
+    }
+}
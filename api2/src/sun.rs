@@ -0,0 +1,403 @@
+//! `/time/sun`: sunrise, sunset, solar noon and day length at a coordinate.
+//!
+//! Uses the NOAA solar calculator's equations (after Meeus, *Astronomical
+//! Algorithms*), which agree with published tables to within a minute between
+//! latitudes ±72°; nearer the poles refraction makes the times less certain.
+//! Sunrise and sunset are when the Sun's upper limb touches the horizon, with
+//! the standard 34′ of refraction.
+//!
+//! The local timezone comes from [`places::locate`] unless `timezone` names
+//! one, and `date` is a calendar day in it.
+
+use axum::{http::HeaderMap, response::Json};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use common::resolve_timezone_alias;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::places::{self, coordinate};
+use crate::{error_response, parse_timezone, request_id_from, ApiError};
+
+const UNIX_EPOCH_JULIAN_DATE: f64 = 2_440_587.5;
+const J2000_JULIAN_DATE: f64 = 2_451_545.0;
+/// Zenith of the Sun's centre at sunrise and sunset: 90° plus 34′ of
+/// refraction and 16′ of solar semi-diameter.
+const SUNRISE_ZENITH_DEGREES: f64 = 90.833;
+const FIRST_YEAR: i32 = 1900;
+const LAST_YEAR: i32 = 2100;
+/// Refinements of each time, each recomputing the Sun's position for it.
+const ITERATIONS: usize = 3;
+
+#[derive(Debug, Deserialize)]
+pub struct SunQuery {
+    lat: Option<String>,
+    lon: Option<String>,
+    date: Option<String>,
+    timezone: Option<String>,
+}
+
+/// An instant in UTC and in the local timezone.
+#[derive(Debug, Serialize)]
+pub struct SunEvent {
+    utc: String,
+    local: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SunTimes {
+    latitude: f64,
+    longitude: f64,
+    date: String,
+    timezone: String,
+    /// `None` when the zone was given or came from the longitude alone.
+    nearest_city: Option<&'static str>,
+    /// `None` on days the Sun does not rise or does not set.
+    sunrise: Option<SunEvent>,
+    sunset: Option<SunEvent>,
+    solar_noon: SunEvent,
+    day_length_seconds: u32,
+    /// `HH:MM:SS`.
+    day_length: String,
+    /// `midnight_sun` or `polar_night` on days without a sunrise and sunset.
+    polar: Option<&'static str>,
+    request_id: String,
+}
+
+pub async fn get_sun(
+    headers: HeaderMap,
+    Query(params): Query<SunQuery>,
+) -> Result<Json<SunTimes>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let latitude = coordinate("lat", params.lat, 90.0, &request_id)?;
+    let longitude = coordinate("lon", params.lon, 180.0, &request_id)?;
+
+    let (tz, nearest_city) = match params.timezone.as_deref() {
+        Some(name) => (
+            parse_timezone(resolve_timezone_alias(name), &request_id)?,
+            None,
+        ),
+        None => {
+            let located = places::locate(latitude, longitude);
+            let tz = parse_timezone(&located.timezone, &request_id)?;
+            (tz, located.nearest_city)
+        }
+    };
+    let date = match params.date.as_deref() {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .filter(|date| (FIRST_YEAR..=LAST_YEAR).contains(&date.year()))
+            .ok_or_else(|| {
+                error_response(
+                    ErrorCode::InvalidRequest,
+                    format!(
+                        "Invalid date: {date} (expected YYYY-MM-DD between {FIRST_YEAR} and {LAST_YEAR})"
+                    ),
+                    &request_id,
+                )
+            })?,
+        None => Utc::now().with_timezone(&tz).date_naive(),
+    };
+
+    info!(
+        request_id = %request_id,
+        latitude,
+        longitude,
+        date = %date,
+        timezone = %tz.name(),
+        "Processing sun times request"
+    );
+
+    let day = sun_day(latitude, longitude, date, tz);
+    let event = |at: DateTime<Utc>| SunEvent {
+        utc: at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        local: at
+            .with_timezone(&tz)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    let (sunrise, sunset, day_length_seconds, polar) = match day.daylight {
+        Daylight::Normal { sunrise, sunset } => (
+            Some(event(sunrise)),
+            Some(event(sunset)),
+            (sunset - sunrise).num_seconds().clamp(0, 86_400) as u32,
+            None,
+        ),
+        Daylight::MidnightSun => (None, None, 86_400, Some("midnight_sun")),
+        Daylight::PolarNight => (None, None, 0, Some("polar_night")),
+    };
+    Ok(Json(SunTimes {
+        latitude,
+        longitude,
+        date: date.to_string(),
+        timezone: tz.name().to_string(),
+        nearest_city,
+        sunrise,
+        sunset,
+        solar_noon: event(day.solar_noon),
+        day_length_seconds,
+        day_length: format!(
+            "{:02}:{:02}:{:02}",
+            day_length_seconds / 3600,
+            day_length_seconds / 60 % 60,
+            day_length_seconds % 60
+        ),
+        polar,
+        request_id,
+    }))
+}
+
+#[derive(Debug, PartialEq)]
+enum Daylight {
+    Normal {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    MidnightSun,
+    PolarNight,
+}
+
+#[derive(Debug)]
+struct SunDay {
+    solar_noon: DateTime<Utc>,
+    daylight: Daylight,
+}
+
+/// The Sun's declination and the equation of time at an instant.
+struct SolarPosition {
+    declination_degrees: f64,
+    equation_of_time_minutes: f64,
+}
+
+fn solar_position(at: DateTime<Utc>) -> SolarPosition {
+    let julian_date = at.timestamp_millis() as f64 / 86_400_000.0 + UNIX_EPOCH_JULIAN_DATE;
+    let t = (julian_date - J2000_JULIAN_DATE) / 36525.0;
+
+    let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+    let m = mean_anomaly.to_radians();
+    let centre = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+        + (3.0 * m).sin() * 0.000289;
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude =
+        (mean_longitude + centre - 0.00569 - 0.00478 * omega.sin()).to_radians();
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+
+    let declination = (obliquity.sin() * apparent_longitude.sin()).asin();
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l0 = mean_longitude.to_radians();
+    let equation_of_time = y * (2.0 * l0).sin() - 2.0 * eccentricity * m.sin()
+        + 4.0 * eccentricity * y * m.sin() * (2.0 * l0).cos()
+        - 0.5 * y * y * (4.0 * l0).sin()
+        - 1.25 * eccentricity * eccentricity * (2.0 * m).sin();
+    SolarPosition {
+        declination_degrees: declination.to_degrees(),
+        equation_of_time_minutes: 4.0 * equation_of_time.to_degrees(),
+    }
+}
+
+/// Hour angle of sunrise in degrees, or why there is none.
+fn sunrise_hour_angle(latitude: f64, declination_degrees: f64) -> Result<f64, Daylight> {
+    let (latitude, declination) = (latitude.to_radians(), declination_degrees.to_radians());
+    let cos_hour_angle = SUNRISE_ZENITH_DEGREES.to_radians().cos()
+        / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    match cos_hour_angle {
+        cos if cos > 1.0 => Err(Daylight::PolarNight),
+        cos if cos < -1.0 => Err(Daylight::MidnightSun),
+        cos => Ok(cos.acos().to_degrees()),
+    }
+}
+
+/// When, on the UTC day starting at `midnight`, the Sun is `hour_angle`
+/// degrees east of the meridian (negative for west), refined for the Sun's
+/// motion meanwhile.
+fn event_time(
+    midnight: DateTime<Utc>,
+    latitude: f64,
+    longitude: f64,
+    hour_angle: impl Fn(f64, f64) -> Result<f64, Daylight>,
+) -> Result<DateTime<Utc>, Daylight> {
+    let mut at = midnight + Duration::minutes((720.0 - 4.0 * longitude) as i64);
+    for _ in 0..ITERATIONS {
+        let position = solar_position(at);
+        let angle = hour_angle(latitude, position.declination_degrees)?;
+        let minutes = 720.0 - 4.0 * (longitude + angle) - position.equation_of_time_minutes;
+        at = midnight + Duration::milliseconds((minutes * 60_000.0).round() as i64);
+    }
+    Ok(at)
+}
+
+/// Solar noon, sunrise and sunset on local day `date` in `tz`.
+fn sun_day(latitude: f64, longitude: f64, date: NaiveDate, tz: Tz) -> SunDay {
+    let noon_after = |day: NaiveDate| {
+        let midnight = Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight exists"));
+        let noon = event_time(midnight, latitude, longitude, |_, _| Ok(0.0))
+            .expect("the Sun always crosses the meridian");
+        (midnight, noon)
+    };
+    // Solar noon on UTC day `date` can fall on the next or previous local
+    // day where the zone's offset is far from the longitude's.
+    let (mut midnight, mut solar_noon) = noon_after(date);
+    let local_day = solar_noon.with_timezone(&tz).date_naive();
+    if local_day != date {
+        (midnight, solar_noon) = noon_after(date + (date - local_day));
+    }
+
+    let position = solar_position(solar_noon);
+    let daylight = match sunrise_hour_angle(latitude, position.declination_degrees) {
+        Err(polar) => polar,
+        Ok(_) => {
+            let sunrise = event_time(midnight, latitude, longitude, sunrise_hour_angle);
+            let sunset = event_time(midnight, latitude, longitude, |latitude, declination| {
+                sunrise_hour_angle(latitude, declination).map(|angle| -angle)
+            });
+            match (sunrise, sunset) {
+                (Ok(sunrise), Ok(sunset)) => Daylight::Normal { sunrise, sunset },
+                (Err(polar), _) | (_, Err(polar)) => polar,
+            }
+        }
+    };
+    SunDay {
+        solar_noon,
+        daylight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    /// Within two minutes of `expected`, `HH:MM` in `tz`.
+    fn assert_near(at: DateTime<Utc>, tz: Tz, expected: &str) {
+        let expected = NaiveTime::parse_from_str(expected, "%H:%M").unwrap();
+        let actual = at.with_timezone(&tz).time();
+        assert!(
+            (actual - expected).num_seconds().abs() <= 120,
+            "{actual} is not near {expected}"
+        );
+    }
+
+    #[test]
+    fn matches_published_times() {
+        // NOAA solar calculator, to the minute.
+        for (latitude, longitude, date, tz, sunrise, noon, sunset) in [
+            (
+                13.7563,
+                100.5018,
+                "2025-03-20",
+                chrono_tz::Asia::Bangkok,
+                "06:22",
+                "12:25",
+                "18:29",
+            ),
+            (
+                51.5074,
+                -0.1278,
+                "2025-06-21",
+                chrono_tz::Europe::London,
+                "04:43",
+                "13:02",
+                "21:21",
+            ),
+            (
+                -33.8688,
+                151.2093,
+                "2025-06-21",
+                chrono_tz::Australia::Sydney,
+                "07:00",
+                "11:57",
+                "16:54",
+            ),
+            (
+                40.7128,
+                -74.0060,
+                "2025-12-21",
+                chrono_tz::America::New_York,
+                "07:16",
+                "11:54",
+                "16:32",
+            ),
+        ] {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            let day = sun_day(latitude, longitude, date, tz);
+            let Daylight::Normal {
+                sunrise: rise,
+                sunset: set,
+            } = day.daylight
+            else {
+                panic!("{date}: {:?}", day.daylight);
+            };
+            assert_near(rise, tz, sunrise);
+            assert_near(day.solar_noon, tz, noon);
+            assert_near(set, tz, sunset);
+            assert_eq!(rise.with_timezone(&tz).date_naive(), date);
+        }
+    }
+
+    #[test]
+    fn reports_midnight_sun_and_polar_night() {
+        let tromso = |date: &str| {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            sun_day(69.6492, 18.9553, date, chrono_tz::Europe::Oslo).daylight
+        };
+        assert_eq!(tromso("2025-06-21"), Daylight::MidnightSun);
+        assert_eq!(tromso("2025-12-21"), Daylight::PolarNight);
+        assert!(matches!(tromso("2025-03-20"), Daylight::Normal { .. }));
+    }
+
+    #[test]
+    fn keeps_solar_noon_on_the_requested_local_day() {
+        // Kiribati's Line Islands are at UTC+14 but 157° west.
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let tz = chrono_tz::Pacific::Kiritimati;
+        let day = sun_day(1.87, -157.4, date, tz);
+        assert_eq!(day.solar_noon.with_timezone(&tz).date_naive(), date);
+    }
+
+    fn query(lat: &str, lon: &str, date: Option<&str>) -> Query<SunQuery> {
+        Query(SunQuery {
+            lat: Some(lat.to_string()),
+            lon: Some(lon.to_string()),
+            date: date.map(str::to_string),
+            timezone: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn reports_times_in_the_local_timezone() {
+        // Too far from any known city to find Europe/Oslo.
+        let mut tromso = query("69.6492", "18.9553", Some("2025-12-21"));
+        tromso.timezone = Some("Europe/Oslo".to_string());
+        let Json(sun) = get_sun(HeaderMap::new(), tromso).await.unwrap();
+        assert_eq!(sun.timezone, "Europe/Oslo");
+        assert!(sun.sunrise.is_none() && sun.sunset.is_none());
+        assert_eq!(sun.polar, Some("polar_night"));
+        assert_eq!(sun.day_length, "00:00:00");
+        assert!(sun.solar_noon.local.ends_with("+01:00"));
+
+        let Json(sun) = get_sun(
+            HeaderMap::new(),
+            query("13.7563", "100.5018", Some("2025-03-20")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(sun.timezone, "Asia/Bangkok");
+        assert!(sun.sunrise.unwrap().local.starts_with("2025-03-20T06:2"));
+        assert_eq!(sun.day_length_seconds / 3600, 12);
+
+        let error = get_sun(
+            HeaderMap::new(),
+            query("13.7563", "100.5018", Some("20/03/2025")),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidRequest);
+    }
+}
@@ -0,0 +1,231 @@
+//! `POST /time`: the options of `GET /time` plus `locale` and
+//! `offset_seconds`, sent as a JSON body.
+//!
+//! Every field is checked before answering, and all problems are reported
+//! together in one `422`.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use common::format::TimestampFormat;
+use common::problem::ErrorCode;
+use common::{TimeRequest, TimeResponse};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::locale::Locale;
+use crate::AppState;
+use crate::{
+    error_for_status, error_response, request_id_from, resolve_timezone_alias, time_in_at, ApiError,
+};
+
+/// Ten years either way, so the shifted time stays well inside chrono's range.
+const MAX_OFFSET_SECONDS: i64 = 10 * 365 * 24 * 3600;
+
+/// A request whose fields have all been validated.
+#[derive(Debug)]
+struct TimeOptions {
+    timezone: String,
+    tz: Tz,
+    format: TimestampFormat,
+    locale: Option<Locale>,
+    offset_seconds: i64,
+}
+
+impl TimeOptions {
+    /// Checks every field, returning one message per invalid field.
+    fn validate(request: TimeRequest) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        let timezone = request.timezone.unwrap_or_else(|| "UTC".to_string());
+        let tz = resolve_timezone_alias(&timezone)
+            .parse::<Tz>()
+            .map_err(|_| errors.push(format!("timezone: Invalid timezone: {timezone}")))
+            .ok();
+        let format = TimestampFormat::from_param(request.format.as_deref())
+            .map_err(|e| errors.push(format!("format: {e}")))
+            .ok();
+        let locale = match request.locale.as_deref().map(Locale::parse) {
+            Some(Err(e)) => {
+                errors.push(format!("locale: {e}"));
+                None
+            }
+            parsed => parsed.and_then(Result::ok),
+        };
+        let offset_seconds = request.offset_seconds.unwrap_or(0);
+        // A range check rather than `abs`, which overflows for `i64::MIN`.
+        if !(-MAX_OFFSET_SECONDS..=MAX_OFFSET_SECONDS).contains(&offset_seconds) {
+            errors.push(format!(
+                "offset_seconds: must be between -{MAX_OFFSET_SECONDS} and {MAX_OFFSET_SECONDS}"
+            ));
+        }
+
+        match (tz, format) {
+            (Some(tz), Some(format)) if errors.is_empty() => Ok(TimeOptions {
+                timezone,
+                tz,
+                format,
+                locale,
+                offset_seconds,
+            }),
+            _ => Err(errors),
+        }
+    }
+
+    fn resolve(&self, now: DateTime<Utc>, request_id: String) -> Result<TimeResponse, ApiError> {
+        let at = TimeDelta::try_seconds(self.offset_seconds)
+            .and_then(|offset| now.checked_add_signed(offset))
+            .ok_or_else(|| {
+                error_response(
+                    ErrorCode::ValidationFailed,
+                    "Invalid request: offset_seconds: out of range",
+                    &request_id,
+                )
+            })?;
+        let time = time_in_at(&self.timezone, &self.format, at, &request_id)?;
+        let mut response = time.into_response(self.timezone.clone(), request_id);
+        response.display = self
+            .locale
+            .map(|locale| locale.display(&at.with_timezone(&self.tz)));
+        Ok(response)
+    }
+}
+
+pub async fn post_time(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<TimeRequest>, JsonRejection>,
+) -> Result<Json<TimeResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    // Malformed JSON stays a 400; well-formed JSON of the wrong shape is a 422.
+    let Json(request) = body.map_err(|rejection| {
+        error_for_status(rejection.status(), rejection.body_text(), &request_id)
+    })?;
+
+    info!(
+        request_id = %request_id,
+        timezone = ?request.timezone,
+        locale = ?request.locale,
+        offset_seconds = ?request.offset_seconds,
+        "Processing time request body"
+    );
+
+    let options = TimeOptions::validate(request).map_err(|errors| {
+        error_response(
+            ErrorCode::ValidationFailed,
+            format!("Invalid request: {}", errors.join("; ")),
+            &request_id,
+        )
+    })?;
+    let mut response = options.resolve(Utc::now(), request_id)?;
+    response.clock_offset_ms = state.clock_offset_ms();
+
+    state.record_timezone_use(&options.timezone);
+    state.history.record(&response, Utc::now()).await;
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let errors = TimeOptions::validate(TimeRequest {
+            timezone: Some("Mars/Olympus".to_string()),
+            format: Some("iso".to_string()),
+            locale: Some("xx".to_string()),
+            offset_seconds: Some(i64::MAX),
+        })
+        .unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert_eq!(errors[0], "timezone: Invalid timezone: Mars/Olympus");
+        assert!(errors[1].starts_with("format: Invalid format: iso"));
+        assert!(errors[2].starts_with("locale: Unsupported locale: xx"));
+        assert!(errors[3].starts_with("offset_seconds: must be between"));
+    }
+
+    #[test]
+    fn rejects_offsets_beyond_ten_years_either_way() {
+        for offset_seconds in [i64::MIN, -MAX_OFFSET_SECONDS - 1, MAX_OFFSET_SECONDS + 1] {
+            let errors = TimeOptions::validate(TimeRequest {
+                offset_seconds: Some(offset_seconds),
+                ..TimeRequest::default()
+            })
+            .unwrap_err();
+            assert!(
+                errors[0].starts_with("offset_seconds: must be between"),
+                "{offset_seconds}: {errors:?}"
+            );
+        }
+        assert!(TimeOptions::validate(TimeRequest {
+            offset_seconds: Some(-MAX_OFFSET_SECONDS),
+            ..TimeRequest::default()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn shifts_by_offset_and_renders_locale() {
+        let options = TimeOptions::validate(TimeRequest {
+            timezone: Some("Asia/Bangkok".to_string()),
+            locale: Some("en".to_string()),
+            offset_seconds: Some(3600),
+            ..TimeRequest::default()
+        })
+        .unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 6, 30, 0).unwrap();
+        let response = options.resolve(now, "req".to_string()).unwrap();
+        assert_eq!(response.timestamp, "2025-03-05T14:30:00+07:00");
+        assert_eq!(
+            response.display.as_deref(),
+            Some("Wednesday 5 March 2025, 14:30:00")
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_fields_and_bad_values_with_422() {
+        let app = crate::app(
+            crate::tests::test_state(),
+            &common::cors::CorsPolicy::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/time", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+        let post = |body: serde_json::Value| client.post(&url).json(&body).send();
+
+        let ok = post(serde_json::json!({ "timezone": "UTC", "format": "unix" }))
+            .await
+            .unwrap();
+        assert_eq!(ok.status(), reqwest::StatusCode::OK);
+        let body: TimeResponse = ok.json().await.unwrap();
+        assert!(body.timestamp.parse::<i64>().is_ok());
+        assert_eq!(body.display, None);
+
+        let unknown = post(serde_json::json!({ "tz": "UTC" })).await.unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+        let invalid = post(serde_json::json!({ "timezone": "Foo/Bar" }))
+            .await
+            .unwrap();
+        assert_eq!(invalid.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let body: common::ErrorResponse = invalid.json().await.unwrap();
+        assert_eq!(
+            body.error,
+            "Invalid request: timezone: Invalid timezone: Foo/Bar"
+        );
+
+        let most_negative = post(serde_json::json!({ "offset_seconds": i64::MIN }))
+            .await
+            .unwrap();
+        assert_eq!(
+            most_negative.status(),
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! `/timer`: named stopwatches kept in memory.
+//!
+//! `POST /timer` starts one, `GET /timer/{id}` reads how long it has been
+//! running and `DELETE /timer/{id}` stops it, freezing the elapsed time.
+//! Elapsed time is measured on the monotonic clock, so it is unaffected by
+//! changes to the wall clock. Timers are forgotten `TIMER_TTL_SECS` after
+//! they were started, whether or not they were stopped, and at most
+//! `TIMER_MAX_COUNT` exist at once. They live in this instance only.
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::config::Config;
+use common::problem::ErrorCode;
+use common::{CreateTimerRequest, Elapsed, TimerResponse};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, json_body, request_id_from, ApiError, AppState};
+
+const DEFAULT_MAX_COUNT: usize = 1000;
+const DEFAULT_TTL_SECS: u64 = 3600;
+const MAX_NAME_CHARS: usize = 64;
+
+struct Timer {
+    name: String,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    /// How long the timer ran, once stopped.
+    stopped_after: Option<Duration>,
+}
+
+/// A timer as of the instant it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub id: String,
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub elapsed: Duration,
+    pub running: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TimerError {
+    /// `max_count` timers already exist.
+    LimitReached(usize),
+    /// No timer with this ID, or it has expired.
+    Unknown(String),
+}
+
+pub struct TimerStore {
+    max_count: usize,
+    ttl: Duration,
+    timers: Mutex<HashMap<String, Timer>>,
+}
+
+impl TimerStore {
+    pub fn new(max_count: usize, ttl_secs: u64) -> Self {
+        TimerStore {
+            max_count,
+            ttl: Duration::from_secs(ttl_secs),
+            timers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Uses `timer_max_count` and `timer_ttl_secs`, falling back to 1000
+    /// timers and one hour.
+    pub fn from_config(config: &Config) -> Self {
+        TimerStore::new(
+            config.timer_max_count.unwrap_or(DEFAULT_MAX_COUNT),
+            config.timer_ttl_secs.unwrap_or(DEFAULT_TTL_SECS),
+        )
+    }
+
+    /// Starts a timer called `name` at `now`, `started_at` on the wall clock.
+    pub fn create(
+        &self,
+        name: String,
+        now: Instant,
+        started_at: DateTime<Utc>,
+    ) -> Result<Snapshot, TimerError> {
+        let mut timers = self.lock();
+        // Expired timers only count against the limit until the next create.
+        timers.retain(|_, timer| !self.expired(timer, now));
+        if timers.len() >= self.max_count {
+            return Err(TimerError::LimitReached(self.max_count));
+        }
+        let id = Uuid::new_v4().to_string();
+        let timer = Timer {
+            name,
+            started_at,
+            started: now,
+            stopped_after: None,
+        };
+        let snapshot = snapshot(&id, &timer, now);
+        timers.insert(id, timer);
+        Ok(snapshot)
+    }
+
+    pub fn get(&self, id: &str, now: Instant) -> Result<Snapshot, TimerError> {
+        let timers = self.lock();
+        match timers.get(id) {
+            Some(timer) if !self.expired(timer, now) => Ok(snapshot(id, timer, now)),
+            _ => Err(TimerError::Unknown(id.to_string())),
+        }
+    }
+
+    /// Stops the timer at `now`. Stopping a stopped timer leaves it as it
+    /// was.
+    pub fn stop(&self, id: &str, now: Instant) -> Result<Snapshot, TimerError> {
+        let mut timers = self.lock();
+        match timers.get_mut(id) {
+            Some(timer) if !self.expired(timer, now) => {
+                timer
+                    .stopped_after
+                    .get_or_insert_with(|| now.saturating_duration_since(timer.started));
+                Ok(snapshot(id, timer, now))
+            }
+            _ => Err(TimerError::Unknown(id.to_string())),
+        }
+    }
+
+    fn expired(&self, timer: &Timer, now: Instant) -> bool {
+        now.saturating_duration_since(timer.started) >= self.ttl
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Timer>> {
+        // The map is never left half-updated, so a poisoned lock is still
+        // usable.
+        self.timers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn snapshot(id: &str, timer: &Timer, now: Instant) -> Snapshot {
+    Snapshot {
+        id: id.to_string(),
+        name: timer.name.clone(),
+        started_at: timer.started_at,
+        elapsed: timer
+            .stopped_after
+            .unwrap_or_else(|| now.saturating_duration_since(timer.started)),
+        running: timer.stopped_after.is_none(),
+    }
+}
+
+/// `duration` in milliseconds, fractional seconds, minutes and hours, and as
+/// an ISO 8601 duration.
+pub fn elapsed(duration: Duration) -> Elapsed {
+    let milliseconds = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    let seconds = duration.as_secs_f64();
+    let (hours, rest) = (milliseconds / 3_600_000, milliseconds % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (whole_seconds, millis) = (rest / 1000, rest % 1000);
+
+    let mut iso8601 = String::from("PT");
+    if hours > 0 {
+        iso8601.push_str(&format!("{hours}H"));
+    }
+    if minutes > 0 {
+        iso8601.push_str(&format!("{minutes}M"));
+    }
+    if millis > 0 {
+        iso8601.push_str(&format!("{whole_seconds}.{millis:03}S"));
+    } else if whole_seconds > 0 || iso8601 == "PT" {
+        iso8601.push_str(&format!("{whole_seconds}S"));
+    }
+
+    Elapsed {
+        milliseconds,
+        seconds,
+        minutes: seconds / 60.0,
+        hours: seconds / 3600.0,
+        iso8601,
+    }
+}
+
+fn response(snapshot: Snapshot, request_id: String) -> TimerResponse {
+    let stopped_at = (!snapshot.running).then(|| {
+        let elapsed = chrono::Duration::from_std(snapshot.elapsed).unwrap_or(chrono::Duration::MAX);
+        (snapshot.started_at + elapsed).to_rfc3339_opts(SecondsFormat::Millis, true)
+    });
+    TimerResponse {
+        id: snapshot.id,
+        name: snapshot.name,
+        started_at: snapshot
+            .started_at
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        stopped_at,
+        running: snapshot.running,
+        elapsed: elapsed(snapshot.elapsed),
+        request_id,
+    }
+}
+
+fn timer_error(error: TimerError, request_id: &str) -> ApiError {
+    match error {
+        TimerError::LimitReached(max_count) => error_response(
+            ErrorCode::CapacityExceeded,
+            format!("Timer limit of {max_count} reached"),
+            request_id,
+        ),
+        TimerError::Unknown(id) => error_response(
+            ErrorCode::NotFound,
+            format!("Unknown timer: {id}"),
+            request_id,
+        ),
+    }
+}
+
+pub async fn post_timer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<CreateTimerRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<TimerResponse>), ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let CreateTimerRequest { name } = json_body(body, &request_id)?;
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!("Timer name must be 1 to {MAX_NAME_CHARS} characters"),
+            &request_id,
+        ));
+    }
+
+    let snapshot = state
+        .timers
+        .create(name, Instant::now(), Utc::now())
+        .map_err(|error| timer_error(error, &request_id))?;
+    info!(
+        request_id = %request_id,
+        timer_id = %snapshot.id,
+        name = %snapshot.name,
+        "Started timer"
+    );
+    Ok((StatusCode::CREATED, Json(response(snapshot, request_id))))
+}
+
+pub async fn get_timer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let snapshot = state
+        .timers
+        .get(&id, Instant::now())
+        .map_err(|error| timer_error(error, &request_id))?;
+    Ok(Json(response(snapshot, request_id)))
+}
+
+pub async fn delete_timer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let snapshot = state
+        .timers
+        .stop(&id, Instant::now())
+        .map_err(|error| timer_error(error, &request_id))?;
+    info!(
+        request_id = %request_id,
+        timer_id = %id,
+        elapsed_ms = snapshot.elapsed.as_millis() as u64,
+        "Stopped timer"
+    );
+    Ok(Json(response(snapshot, request_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_until_stopped() {
+        let store = TimerStore::new(10, 3600);
+        let start = Instant::now();
+        let created = store
+            .create("build".to_string(), start, Utc::now())
+            .unwrap();
+        assert!(created.running);
+        assert_eq!(created.elapsed, Duration::ZERO);
+
+        let later = start + Duration::from_millis(1500);
+        let running = store.get(&created.id, later).unwrap();
+        assert_eq!(running.elapsed, Duration::from_millis(1500));
+
+        let stopped = store.stop(&created.id, later).unwrap();
+        assert!(!stopped.running);
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!(
+            store.get(&created.id, much_later).unwrap().elapsed,
+            Duration::from_millis(1500)
+        );
+        // Stopping again keeps the first stop.
+        assert_eq!(
+            store.stop(&created.id, much_later).unwrap().elapsed,
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            store.get("missing", later),
+            Err(TimerError::Unknown("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn enforces_the_limit_and_expires_timers() {
+        let store = TimerStore::new(2, 10);
+        let start = Instant::now();
+        let first = store.create("a".to_string(), start, Utc::now()).unwrap();
+        store.create("b".to_string(), start, Utc::now()).unwrap();
+        assert_eq!(
+            store.create("c".to_string(), start, Utc::now()),
+            Err(TimerError::LimitReached(2))
+        );
+
+        let expired = start + Duration::from_secs(10);
+        assert_eq!(
+            store.get(&first.id, expired),
+            Err(TimerError::Unknown(first.id.clone()))
+        );
+        assert_eq!(
+            store.stop(&first.id, expired),
+            Err(TimerError::Unknown(first.id.clone()))
+        );
+        assert!(store.create("c".to_string(), expired, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn reports_elapsed_in_several_units() {
+        let time = elapsed(Duration::from_millis(3_723_456));
+        assert_eq!(time.milliseconds, 3_723_456);
+        assert_eq!(time.seconds, 3723.456);
+        assert_eq!(time.iso8601, "PT1H2M3.456S");
+        assert!((time.hours - 1.03429).abs() < 1e-5);
+
+        assert_eq!(elapsed(Duration::ZERO).iso8601, "PT0S");
+        assert_eq!(elapsed(Duration::from_secs(120)).iso8601, "PT2M");
+        assert_eq!(elapsed(Duration::from_millis(50)).iso8601, "PT0.050S");
+    }
+
+    #[tokio::test]
+    async fn serves_timers_over_http() {
+        let app = crate::app(
+            crate::tests::test_state(),
+            &common::cors::CorsPolicy::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{base}/v1/timer"))
+            .json(&serde_json::json!({ "name": "deploy" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let created: TimerResponse = response.json().await.unwrap();
+        assert_eq!(created.name, "deploy");
+        assert!(created.running);
+
+        let response = client
+            .delete(format!("{base}/timer/{}", created.id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let stopped: TimerResponse = response.json().await.unwrap();
+        assert!(!stopped.running);
+        assert!(stopped.stopped_at.is_some());
+
+        let response = client
+            .get(format!("{base}/v1/timer/nope"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let response = client
+            .post(format!("{base}/v1/timer"))
+            .json(&serde_json::json!({ "name": " " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+}
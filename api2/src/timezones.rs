@@ -0,0 +1,461 @@
+//! Discovery endpoints: every supported IANA timezone name with its current
+//! UTC offset, details of one zone including its upcoming transitions, and
+//! the offset a zone had at any other instant.
+//!
+//! The bundled database keeps each zone's history back to its local mean
+//! time (`LMT`), whose offsets are not whole minutes. Before 1970 it is only
+//! as complete as the IANA data: zones that agree since 1970 share one
+//! history, so earlier offsets of the merged zones may be another city's.
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Offset, SecondsFormat, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use common::openapi::{array, object, reference, register, typed, Components, Schema};
+use common::problem::{ErrorCode, Query};
+use common::{TimezoneList, TimezoneOffset};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::convert::parse_instant;
+use crate::transitions::{transitions_between, Transition};
+use crate::{
+    error_for_status, error_response, format_utc_offset, request_id_from, ApiError, AppState,
+};
+
+/// Transitions listed by `GET /timezone/{name}`.
+const UPCOMING_TRANSITIONS: usize = 2;
+
+/// How far ahead to look for them; every zone that observes DST changes
+/// twice within this.
+const TRANSITION_HORIZON_DAYS: i64 = 2 * 366;
+
+/// Path suffix of `GET /timezone/{name}/at`, which shares the `/timezone/*name`
+/// route since zone names contain slashes.
+const AT_SUFFIX: &str = "/at";
+
+#[derive(Debug, Deserialize)]
+pub struct TimezonesQuery {
+    prefix: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimezoneAtQuery {
+    timestamp: Option<String>,
+}
+
+/// All names in the bundled timezone database, sorted alphabetically.
+pub fn timezone_names() -> Vec<&'static str> {
+    let mut names: Vec<_> = chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect();
+    names.sort_unstable();
+    names
+}
+
+/// Offset of the zone `name` from UTC at `at`.
+fn offset_at(name: &str, at: DateTime<Utc>) -> TimezoneOffset {
+    let tz: Tz = name.parse().expect("names come from TZ_VARIANTS");
+    let utc_offset_seconds = at.with_timezone(&tz).offset().fix().local_minus_utc();
+    TimezoneOffset {
+        name: name.to_string(),
+        utc_offset_seconds,
+        utc_offset_label: format_utc_offset(utc_offset_seconds),
+    }
+}
+
+pub async fn get_timezones(
+    State(state): State<AppState>,
+    Query(params): Query<TimezonesQuery>,
+) -> Json<TimezoneList> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        prefix = params.prefix.as_deref().unwrap_or(""),
+        "Processing timezones request"
+    );
+
+    let prefix = params.prefix.unwrap_or_default().to_lowercase();
+    let now = Utc::now();
+    let offsets: Vec<TimezoneOffset> = state
+        .timezone_names
+        .iter()
+        .filter(|name| name.to_lowercase().starts_with(&prefix))
+        .map(|name| offset_at(name, now))
+        .collect();
+
+    Json(TimezoneList {
+        count: offsets.len(),
+        timezones: offsets.iter().map(|offset| offset.name.clone()).collect(),
+        offsets,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneInfo {
+    name: String,
+    utc_offset_seconds: i32,
+    utc_offset_label: String,
+    abbreviation: String,
+    dst_active: bool,
+    /// Daylight saving included in `utc_offset_seconds`, `0` outside DST.
+    dst_offset_seconds: i64,
+    /// The next offset changes, empty for zones with a fixed offset.
+    next_transitions: Vec<Transition>,
+}
+
+impl Schema for TimezoneInfo {
+    const NAME: &'static str = "TimezoneInfo";
+
+    fn schema() -> Value {
+        object(
+            "A timezone's current offset and upcoming changes.",
+            &[
+                ("name", typed("string", "IANA name."), true),
+                (
+                    "utc_offset_seconds",
+                    typed("integer", "Offset from UTC."),
+                    true,
+                ),
+                ("utc_offset_label", typed("string", "`±HH:MM`."), true),
+                ("abbreviation", typed("string", "e.g. `JST`."), true),
+                (
+                    "dst_active",
+                    typed("boolean", "Whether DST is in effect."),
+                    true,
+                ),
+                (
+                    "dst_offset_seconds",
+                    typed("integer", "DST part of the offset, `0` outside DST."),
+                    true,
+                ),
+                ("next_transitions", array(reference::<Transition>()), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<Transition>(components);
+    }
+}
+
+/// Details of `tz` at `at`.
+fn timezone_info(tz: Tz, at: DateTime<Utc>) -> TimezoneInfo {
+    let offset = at.with_timezone(&tz).offset().to_owned();
+    let utc_offset_seconds = offset.fix().local_minus_utc();
+    let dst_offset_seconds = offset.dst_offset().num_seconds();
+    let mut next_transitions =
+        transitions_between(&tz, at, at + Duration::days(TRANSITION_HORIZON_DAYS));
+    next_transitions.truncate(UPCOMING_TRANSITIONS);
+    TimezoneInfo {
+        name: tz.name().to_string(),
+        utc_offset_seconds,
+        utc_offset_label: format_utc_offset(utc_offset_seconds),
+        abbreviation: offset.abbreviation().to_string(),
+        dst_active: dst_offset_seconds != 0,
+        dst_offset_seconds,
+        next_transitions,
+    }
+}
+
+/// The offset of `tz` at `at`, past or future.
+#[derive(Debug, Serialize)]
+pub struct TimezoneOffsetAt {
+    name: String,
+    /// `at` in UTC.
+    timestamp: String,
+    epoch_seconds: i64,
+    /// Wall-clock time in the zone, `YYYY-MM-DDTHH:MM:SS`, without an offset
+    /// since local mean time's has seconds that RFC 3339 cannot carry.
+    local_time: String,
+    utc_offset_seconds: i32,
+    /// `±HH:MM`, or `±HH:MM:SS` for offsets that are not whole minutes.
+    utc_offset_label: String,
+    abbreviation: String,
+    dst_active: bool,
+    dst_offset_seconds: i64,
+    request_id: String,
+}
+
+impl Schema for TimezoneOffsetAt {
+    const NAME: &'static str = "TimezoneOffsetAt";
+
+    fn schema() -> Value {
+        object(
+            "A timezone's offset at a given instant.",
+            &[
+                ("name", typed("string", "IANA name."), true),
+                (
+                    "timestamp",
+                    typed("string", "The instant, RFC 3339 in UTC."),
+                    true,
+                ),
+                (
+                    "epoch_seconds",
+                    typed("integer", "The instant in Unix seconds."),
+                    true,
+                ),
+                (
+                    "local_time",
+                    typed("string", "Wall-clock time there, `YYYY-MM-DDTHH:MM:SS`."),
+                    true,
+                ),
+                (
+                    "utc_offset_seconds",
+                    typed("integer", "Offset from UTC."),
+                    true,
+                ),
+                (
+                    "utc_offset_label",
+                    typed("string", "`±HH:MM`, with `:SS` for local mean time."),
+                    true,
+                ),
+                (
+                    "abbreviation",
+                    typed("string", "e.g. `BST`, or `LMT` before standard time."),
+                    true,
+                ),
+                (
+                    "dst_active",
+                    typed("boolean", "Whether DST was in effect."),
+                    true,
+                ),
+                (
+                    "dst_offset_seconds",
+                    typed("integer", "DST part of the offset, `0` outside DST."),
+                    true,
+                ),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+}
+
+fn offset_info(tz: Tz, at: DateTime<Utc>, request_id: String) -> TimezoneOffsetAt {
+    let local = at.with_timezone(&tz);
+    let offset = local.offset().to_owned();
+    let utc_offset_seconds = offset.fix().local_minus_utc();
+    let dst_offset_seconds = offset.dst_offset().num_seconds();
+    let mut utc_offset_label = format_utc_offset(utc_offset_seconds);
+    if utc_offset_seconds % 60 != 0 {
+        utc_offset_label.push_str(&format!(":{:02}", utc_offset_seconds.unsigned_abs() % 60));
+    }
+    TimezoneOffsetAt {
+        name: tz.name().to_string(),
+        timestamp: at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        epoch_seconds: at.timestamp(),
+        local_time: local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        utc_offset_seconds,
+        utc_offset_label,
+        abbreviation: offset.abbreviation().to_string(),
+        dst_active: dst_offset_seconds != 0,
+        dst_offset_seconds,
+        request_id,
+    }
+}
+
+/// `GET /timezone/{name}`, and `GET /timezone/{name}/at?timestamp=...` for
+/// the offset at another instant.
+pub async fn get_timezone(
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(params): Query<TimezoneAtQuery>,
+) -> Result<Response, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let (name, at) = match name.strip_suffix(AT_SUFFIX) {
+        Some(zone) => (zone, true),
+        None => (name.as_str(), false),
+    };
+
+    info!(
+        request_id = %request_id,
+        timezone = %name,
+        timestamp = ?params.timestamp,
+        "Processing timezone info request"
+    );
+
+    let tz: Tz = name.parse().map_err(|_| {
+        error_response(
+            ErrorCode::NotFound,
+            format!("Unknown timezone: {name}"),
+            &request_id,
+        )
+    })?;
+    if !at {
+        return Ok(Json(timezone_info(tz, Utc::now())).into_response());
+    }
+    let timestamp = params.timestamp.ok_or_else(|| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            "Missing required parameter: timestamp",
+            &request_id,
+        )
+    })?;
+    // Local datetimes are read in the zone itself.
+    let instant = parse_instant(&timestamp, tz)
+        .map_err(|e| error_for_status(e.status(), e.to_string(), &request_id))?;
+    Ok(Json(offset_info(tz, instant, request_id)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_variant_in_order() {
+        let names = timezone_names();
+        assert_eq!(names.len(), chrono_tz::TZ_VARIANTS.len());
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(names[0], "Africa/Abidjan");
+    }
+
+    #[tokio::test]
+    async fn filters_by_prefix_case_insensitively() {
+        let state = crate::tests::test_state();
+        let list = |prefix: Option<&str>| {
+            get_timezones(
+                State(state.clone()),
+                Query(TimezonesQuery {
+                    prefix: prefix.map(str::to_string),
+                }),
+            )
+        };
+
+        let Json(all) = list(None).await;
+        assert_eq!(all.count, chrono_tz::TZ_VARIANTS.len());
+
+        let Json(asia) = list(Some("asia/")).await;
+        assert_eq!(asia.count, asia.timezones.len());
+        assert!(asia.timezones.contains(&"Asia/Bangkok".to_string()));
+        assert!(asia.timezones.iter().all(|name| name.starts_with("Asia/")));
+        let names: Vec<_> = asia.offsets.iter().map(|offset| &offset.name).collect();
+        assert_eq!(names, asia.timezones.iter().collect::<Vec<_>>());
+        let bangkok = asia
+            .offsets
+            .iter()
+            .find(|offset| offset.name == "Asia/Bangkok")
+            .unwrap();
+        assert_eq!(bangkok.utc_offset_seconds, 7 * 3600);
+        assert_eq!(bangkok.utc_offset_label, "+07:00");
+    }
+
+    #[test]
+    fn offsets_follow_daylight_saving_time() {
+        let winter = "2024-01-15T12:00:00Z".parse().unwrap();
+        let summer = "2024-07-15T12:00:00Z".parse().unwrap();
+        assert_eq!(
+            offset_at("America/New_York", winter).utc_offset_label,
+            "-05:00"
+        );
+        assert_eq!(
+            offset_at("America/New_York", summer).utc_offset_label,
+            "-04:00"
+        );
+        assert_eq!(offset_at("Asia/Kolkata", summer).utc_offset_seconds, 19800);
+    }
+
+    #[test]
+    fn reports_dst_and_upcoming_transitions() {
+        let summer = "2024-07-15T12:00:00Z".parse().unwrap();
+        let info = timezone_info(chrono_tz::America::New_York, summer);
+        assert_eq!(info.abbreviation, "EDT");
+        assert!(info.dst_active);
+        assert_eq!(info.dst_offset_seconds, 3600);
+        let transitions: Vec<_> = info
+            .next_transitions
+            .iter()
+            .map(|transition| (transition.at.as_str(), transition.to_offset_seconds))
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                ("2024-11-03T06:00:00Z", -5 * 3600),
+                ("2025-03-09T07:00:00Z", -4 * 3600)
+            ]
+        );
+
+        let bangkok = timezone_info(chrono_tz::Asia::Bangkok, summer);
+        assert!(!bangkok.dst_active);
+        assert_eq!(bangkok.abbreviation, "+07");
+        assert!(bangkok.next_transitions.is_empty());
+    }
+
+    #[test]
+    fn reports_historical_offsets_back_to_local_mean_time() {
+        let at = |timestamp: &str| timestamp.parse().unwrap();
+        // British Standard Time: a year-round +01:00 that was not DST.
+        let london = offset_info(
+            chrono_tz::Europe::London,
+            at("1970-01-01T00:00:00Z"),
+            String::new(),
+        );
+        assert_eq!(london.abbreviation, "BST");
+        assert_eq!(london.utc_offset_seconds, 3600);
+        assert!(!london.dst_active);
+        assert_eq!(london.epoch_seconds, 0);
+
+        let new_york = offset_info(
+            chrono_tz::America::New_York,
+            at("1950-07-01T12:00:00Z"),
+            String::new(),
+        );
+        assert_eq!(new_york.abbreviation, "EDT");
+        assert_eq!(new_york.utc_offset_label, "-04:00");
+        assert_eq!(new_york.local_time, "1950-07-01T08:00:00");
+
+        let bangkok = offset_info(
+            chrono_tz::Asia::Bangkok,
+            at("1870-01-01T00:00:00Z"),
+            String::new(),
+        );
+        assert_eq!(bangkok.abbreviation, "LMT");
+        assert_eq!(bangkok.utc_offset_seconds, 6 * 3600 + 42 * 60 + 4);
+        assert_eq!(bangkok.utc_offset_label, "+06:42:04");
+        assert_eq!(bangkok.local_time, "1870-01-01T06:42:04");
+    }
+
+    #[tokio::test]
+    async fn serves_zone_names_with_slashes() {
+        let app = crate::app(
+            crate::tests::test_state(),
+            &common::cors::CorsPolicy::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/timezone", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let found = reqwest::get(format!("{base}/America/Argentina/Buenos_Aires"))
+            .await
+            .unwrap();
+        assert_eq!(found.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = found.json().await.unwrap();
+        assert_eq!(body["name"], "America/Argentina/Buenos_Aires");
+        assert_eq!(body["utc_offset_label"], "-03:00");
+
+        let past = reqwest::get(format!(
+            "{base}/America/Argentina/Buenos_Aires/at?timestamp=-1"
+        ))
+        .await
+        .unwrap();
+        assert_eq!(past.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = past.json().await.unwrap();
+        assert_eq!(body["timestamp"], "1969-12-31T23:59:59Z");
+        assert_eq!(body["utc_offset_label"], "-03:00");
+
+        let unparsable = reqwest::get(format!("{base}/Asia/Tokyo/at?timestamp=yesterday"))
+            .await
+            .unwrap();
+        assert_eq!(unparsable.status(), reqwest::StatusCode::BAD_REQUEST);
+        let missing = reqwest::get(format!("{base}/Asia/Tokyo/at")).await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let missing = reqwest::get(format!("{base}/Mars/Olympus")).await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+        let body: common::ErrorResponse = missing.json().await.unwrap();
+        assert_eq!(body.error, "Unknown timezone: Mars/Olympus");
+    }
+}
@@ -0,0 +1,192 @@
+//! Discovery of UTC offset transitions (DST changes and rule changes).
+//!
+//! `chrono_tz` resolves offsets for any instant but does not expose its
+//! transition table, so transitions are found by sampling hourly and then
+//! bisecting each change down to the exact second.
+
+use axum::response::Json;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Offset, SecondsFormat, TimeZone, Utc};
+use chrono_tz::{OffsetName, Tz};
+use common::openapi::{object, typed, Schema};
+use common::problem::{ErrorCode, Query};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, parse_timezone, ApiError};
+
+const MIN_YEAR: i32 = 1900;
+const MAX_YEAR: i32 = 2100;
+
+/// Every real-world transition is separated from the next by far more than
+/// this, so sampling at this interval cannot step over a pair of them.
+const SAMPLE_STEP_HOURS: i64 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition {
+    pub at: String,
+    pub from_offset_seconds: i32,
+    pub to_offset_seconds: i32,
+    pub abbreviation_before: String,
+    pub abbreviation_after: String,
+}
+
+impl Schema for Transition {
+    const NAME: &'static str = "Transition";
+
+    fn schema() -> Value {
+        object(
+            "A change of a timezone's offset from UTC.",
+            &[
+                (
+                    "at",
+                    typed("string", "RFC 3339 instant of the change."),
+                    true,
+                ),
+                (
+                    "from_offset_seconds",
+                    typed("integer", "Offset before."),
+                    true,
+                ),
+                ("to_offset_seconds", typed("integer", "Offset after."), true),
+                ("abbreviation_before", typed("string", "e.g. `PST`."), true),
+                ("abbreviation_after", typed("string", "e.g. `PDT`."), true),
+            ],
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransitionQuery {
+    timezone: Option<String>,
+    year: Option<i32>,
+}
+
+pub async fn get_timezone_transition_list(
+    Query(params): Query<TransitionQuery>,
+) -> Result<Json<Vec<Transition>>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+    let timezone = params.timezone.unwrap_or_else(|| "UTC".to_string());
+
+    info!(
+        request_id = %request_id,
+        timezone = %timezone,
+        year = ?params.year,
+        "Processing timezone transition list request"
+    );
+
+    let tz = parse_timezone(&timezone, &request_id)?;
+    let year = params
+        .year
+        .unwrap_or_else(|| Utc::now().with_timezone(&tz).year());
+    if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            format!("year must be between {MIN_YEAR} and {MAX_YEAR}, got {year}"),
+            &request_id,
+        ));
+    }
+
+    let start = year_start_utc(year);
+    let end = year_start_utc(year + 1);
+    Ok(Json(transitions_between(&tz, start, end)))
+}
+
+fn year_start_utc(year: i32) -> DateTime<Utc> {
+    let naive = NaiveDate::from_ymd_opt(year, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("valid year start");
+    Utc.from_utc_datetime(&naive)
+}
+
+/// Offset in seconds and abbreviation of `tz` at `at`.
+pub fn offset_at(tz: &Tz, at: DateTime<Utc>) -> (i32, String) {
+    let offset = tz.offset_from_utc_datetime(&at.naive_utc());
+    (
+        offset.fix().local_minus_utc(),
+        offset.abbreviation().to_string(),
+    )
+}
+
+/// All transitions of `tz` in `[start, end)`, in chronological order.
+pub fn transitions_between(tz: &Tz, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Transition> {
+    let step = Duration::hours(SAMPLE_STEP_HOURS);
+    let mut transitions = Vec::new();
+    let mut previous_at = start;
+    let mut previous = offset_at(tz, start);
+
+    while previous_at < end {
+        let next_at = (previous_at + step).min(end);
+        let next = offset_at(tz, next_at);
+        if next != previous {
+            let at = bisect_transition(tz, previous_at, next_at, &previous);
+            transitions.push(Transition {
+                at: at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                from_offset_seconds: previous.0,
+                to_offset_seconds: next.0,
+                abbreviation_before: previous.1.clone(),
+                abbreviation_after: next.1.clone(),
+            });
+        }
+        previous_at = next_at;
+        previous = next;
+    }
+
+    transitions
+}
+
+/// Finds the first second in `(low, high]` whose offset differs from `before`.
+fn bisect_transition(
+    tz: &Tz,
+    mut low: DateTime<Utc>,
+    mut high: DateTime<Utc>,
+    before: &(i32, String),
+) -> DateTime<Utc> {
+    while (high - low).num_seconds() > 1 {
+        let middle = low + (high - low) / 2;
+        if offset_at(tz, middle) == *before {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    high
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_each_transition_to_the_second() {
+        let transitions = transitions_between(
+            &chrono_tz::Europe::London,
+            year_start_utc(2024),
+            year_start_utc(2025),
+        );
+        let summary: Vec<_> = transitions
+            .iter()
+            .map(|t| {
+                (
+                    t.at.as_str(),
+                    t.abbreviation_before.as_str(),
+                    t.to_offset_seconds,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("2024-03-31T01:00:00Z", "GMT", 3600),
+                ("2024-10-27T01:00:00Z", "BST", 0),
+            ]
+        );
+        assert!(transitions_between(
+            &chrono_tz::Asia::Tokyo,
+            year_start_utc(2024),
+            year_start_utc(2025)
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use axum::{http::HeaderMap, response::Json};
+use chrono::{DateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use common::problem::{ErrorCode, Query};
+use common::TimeDiffResponse;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{error_response, format_utc_offset, parse_timezone, request_id_from, ApiError};
+
+#[derive(Debug, Deserialize)]
+pub struct TimezoneDistanceQuery {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimezoneDistance {
+    from_offset: String,
+    to_offset: String,
+    difference_hours: f64,
+    difference_minutes: i32,
+    to_is_ahead: bool,
+    description: String,
+}
+
+pub async fn get_timezone_distance(
+    Query(params): Query<TimezoneDistanceQuery>,
+) -> Result<Json<TimezoneDistance>, ApiError> {
+    let request_id = Uuid::new_v4().to_string();
+
+    info!(
+        request_id = %request_id,
+        from = ?params.from,
+        to = ?params.to,
+        "Processing timezone distance request"
+    );
+
+    let (Some(from), Some(to)) = (params.from, params.to) else {
+        return Err(error_response(
+            ErrorCode::InvalidRequest,
+            "Both from and to timezones are required",
+            &request_id,
+        ));
+    };
+    let from_tz = parse_timezone(&from, &request_id)?;
+    let to_tz = parse_timezone(&to, &request_id)?;
+
+    Ok(Json(timezone_distance(&from_tz, &to_tz)))
+}
+
+fn timezone_distance(from: &Tz, to: &Tz) -> TimezoneDistance {
+    let now = Utc::now().naive_utc();
+    let from_offset = from.offset_from_utc_datetime(&now).fix().local_minus_utc();
+    let to_offset = to.offset_from_utc_datetime(&now).fix().local_minus_utc();
+    let difference_minutes = (to_offset - from_offset) / 60;
+
+    let from_place = place_name(from);
+    let to_place = place_name(to);
+    let description = match difference_minutes {
+        0 => format!("{to_place} is at the same time as {from_place}"),
+        minutes => format!(
+            "{to_place} is {} {} {from_place}",
+            describe_minutes(minutes.unsigned_abs()),
+            if minutes > 0 { "ahead of" } else { "behind" },
+        ),
+    };
+
+    TimezoneDistance {
+        from_offset: format_utc_offset(from_offset),
+        to_offset: format_utc_offset(to_offset),
+        difference_hours: f64::from(difference_minutes) / 60.0,
+        difference_minutes,
+        to_is_ahead: difference_minutes > 0,
+        description,
+    }
+}
+
+pub async fn get_time_diff(
+    headers: HeaderMap,
+    Query(params): Query<TimezoneDistanceQuery>,
+) -> Result<Json<TimeDiffResponse>, ApiError> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    info!(
+        request_id = %request_id,
+        from = ?params.from,
+        to = ?params.to,
+        "Processing time diff request"
+    );
+
+    let timezone = |name: &str, value: Option<String>| {
+        let value = value.ok_or_else(|| {
+            error_response(
+                ErrorCode::InvalidRequest,
+                format!("Missing required parameter: {name}"),
+                &request_id,
+            )
+        })?;
+        let tz = value.parse::<Tz>().map_err(|_| {
+            error_response(
+                ErrorCode::InvalidTimezone,
+                format!("Invalid timezone for {name}: {value}"),
+                &request_id,
+            )
+        })?;
+        Ok::<_, ApiError>(tz)
+    };
+    let from = timezone("from", params.from)?;
+    let to = timezone("to", params.to)?;
+
+    Ok(Json(time_diff(from, to, Utc::now(), request_id)))
+}
+
+/// Offset of `to` relative to `from` at `at`, so DST on either side counts.
+fn time_diff(from: Tz, to: Tz, at: DateTime<Utc>, request_id: String) -> TimeDiffResponse {
+    let from_time = at.with_timezone(&from);
+    let to_time = at.with_timezone(&to);
+    let offset_seconds =
+        to_time.offset().fix().local_minus_utc() - from_time.offset().fix().local_minus_utc();
+
+    TimeDiffResponse {
+        from_timezone: from.name().to_string(),
+        to_timezone: to.name().to_string(),
+        offset_seconds,
+        offset_label: format_utc_offset(offset_seconds),
+        from_timestamp: from_time.to_rfc3339(),
+        to_timestamp: to_time.to_rfc3339(),
+        request_id,
+    }
+}
+
+/// Human-readable place for a zone: the last path segment of its IANA name,
+/// e.g. "New York" for `America/New_York`.
+fn place_name(tz: &Tz) -> String {
+    let name = tz.name();
+    name.rsplit('/').next().unwrap_or(name).replace('_', " ")
+}
+
+fn describe_minutes(minutes: u32) -> String {
+    let plural =
+        |count: u32, unit: &str| format!("{count} {unit}{}", if count == 1 { "" } else { "s" });
+    match (minutes / 60, minutes % 60) {
+        (hours, 0) => plural(hours, "hour"),
+        (0, minutes) => plural(minutes, "minute"),
+        (hours, minutes) => format!("{} {}", plural(hours, "hour"), plural(minutes, "minute")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff_at(from: Tz, to: Tz, at: &str) -> TimeDiffResponse {
+        let at = DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+        time_diff(from, to, at, "test".to_string())
+    }
+
+    #[test]
+    fn diff_tracks_dst_on_either_side() {
+        use chrono_tz::{America::New_York, Asia::Tokyo, Europe::London};
+
+        let winter = diff_at(New_York, Tokyo, "2024-01-15T12:00:00Z");
+        assert_eq!(winter.offset_seconds, 14 * 3600);
+        assert_eq!(winter.offset_label, "+14:00");
+        let summer = diff_at(New_York, Tokyo, "2024-07-15T12:00:00Z");
+        assert_eq!(summer.offset_seconds, 13 * 3600);
+
+        // The US and UK change clocks on different dates in March.
+        assert_eq!(
+            diff_at(New_York, London, "2024-03-05T12:00:00Z").offset_seconds,
+            5 * 3600
+        );
+        let gap = diff_at(New_York, London, "2024-03-15T12:00:00Z");
+        assert_eq!(gap.offset_seconds, 4 * 3600);
+        assert_eq!(gap.from_timestamp, "2024-03-15T08:00:00-04:00");
+        assert_eq!(gap.to_timestamp, "2024-03-15T12:00:00+00:00");
+
+        assert_eq!(
+            diff_at(London, New_York, "2024-03-15T12:00:00Z").offset_label,
+            "-04:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_names_the_bad_parameter() {
+        let diff = |from: Option<&str>, to: Option<&str>| {
+            get_time_diff(
+                HeaderMap::new(),
+                Query(TimezoneDistanceQuery {
+                    from: from.map(str::to_string),
+                    to: to.map(str::to_string),
+                }),
+            )
+        };
+
+        let body = diff(Some("UTC"), None).await.unwrap_err();
+        assert_eq!(body.code, ErrorCode::InvalidRequest);
+        assert_eq!(body.error, "Missing required parameter: to");
+
+        let body = diff(Some("Foo/Bar"), Some("UTC")).await.unwrap_err();
+        assert_eq!(body.code, ErrorCode::InvalidTimezone);
+        assert_eq!(body.status, 400);
+        assert_eq!(body.error, "Invalid timezone for from: Foo/Bar");
+    }
+}
@@ -0,0 +1,278 @@
+//! The bundled timezone database's version, and whether a newer one exists.
+//!
+//! chrono-tz compiles the IANA tzdb into the binary, so rule changes only
+//! arrive with a rebuild. With `TZDATA_MANIFEST_URL` set, a background task
+//! fetches that document every `TZDATA_CHECK_INTERVAL_SECS` (default one
+//! day) and compares the release it names with the bundled one. The manifest
+//! is either plain text starting with the version, like IANA's own
+//! `https://data.iana.org/time-zones/tzdb/version`, or a JSON object with a
+//! `version` field.
+//!
+//! An outdated database shows in `/timezones/version`, in the details of the
+//! `tzdata` readiness check and as the `tzdata_outdated` gauge. It does not
+//! fail readiness: every instance of a build has the same database, and
+//! taking them all out of rotation would not bring newer rules.
+
+use axum::{extract::State, http::HeaderMap, response::Json};
+use chrono::{DateTime, Utc};
+use common::config::Config;
+use common::health::Check;
+use common::metrics::{Gauge, Registry};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{request_id_from, AppState};
+
+/// Release of the tzdb compiled in, e.g. `2024a`.
+pub const EMBEDDED_VERSION: &str = chrono_tz::IANA_TZDB_VERSION;
+
+const DEFAULT_INTERVAL_SECS: u64 = 86_400;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const TZDATA_OUTDATED: Gauge = Gauge {
+    name: "tzdata_outdated",
+    help: "1 when the manifest names a newer tzdb release than the bundled one.",
+};
+
+/// The latest release the manifest named.
+#[derive(Debug, Clone, PartialEq)]
+struct Upstream {
+    latest: String,
+    checked_at: DateTime<Utc>,
+}
+
+pub struct FreshnessMonitor {
+    manifest_url: String,
+    interval: Duration,
+    http: reqwest::Client,
+    metrics: Arc<Registry>,
+    last: RwLock<Option<Upstream>>,
+    last_error: RwLock<Option<String>>,
+}
+
+impl FreshnessMonitor {
+    pub fn new(manifest_url: String, interval: Duration, metrics: Arc<Registry>) -> Self {
+        FreshnessMonitor {
+            manifest_url,
+            interval,
+            http: reqwest::Client::builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .expect("Failed to build tzdata manifest client"),
+            metrics,
+            last: RwLock::new(None),
+            last_error: RwLock::new(None),
+        }
+    }
+
+    /// A monitor for `tzdata_manifest_url` every `tzdata_check_interval_secs`,
+    /// or `None` when no manifest is configured.
+    pub fn from_config(config: &Config, metrics: Arc<Registry>) -> Option<Self> {
+        let manifest_url = config.tzdata_manifest_url.clone()?;
+        Some(FreshnessMonitor::new(
+            manifest_url,
+            Duration::from_secs(
+                config
+                    .tzdata_check_interval_secs
+                    .unwrap_or(DEFAULT_INTERVAL_SECS),
+            ),
+            metrics,
+        ))
+    }
+
+    /// Checks now and then every interval, for as long as the runtime lives.
+    pub fn spawn(self: &Arc<Self>) {
+        let monitor = Arc::clone(self);
+        info!(
+            manifest_url = %monitor.manifest_url,
+            embedded = EMBEDDED_VERSION,
+            "Checking tzdata freshness"
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(monitor.interval);
+            loop {
+                interval.tick().await;
+                monitor.check().await;
+            }
+        });
+    }
+
+    async fn check(&self) {
+        match fetch_latest(&self.http, &self.manifest_url).await {
+            Ok(latest) => self.record(latest),
+            Err(e) => {
+                warn!(manifest_url = %self.manifest_url, error = %e, "tzdata manifest check failed");
+                *self.last_error.write().expect("tzdata lock poisoned") = Some(e);
+            }
+        }
+    }
+
+    fn record(&self, latest: String) {
+        let outdated = is_newer(&latest, EMBEDDED_VERSION);
+        if outdated {
+            warn!(
+                embedded = EMBEDDED_VERSION,
+                latest = %latest,
+                "tzdata outdated; rebuild with a newer chrono-tz"
+            );
+        }
+        self.metrics
+            .set(&TZDATA_OUTDATED, &[], if outdated { 1.0 } else { 0.0 });
+        *self.last.write().expect("tzdata lock poisoned") = Some(Upstream {
+            latest,
+            checked_at: Utc::now(),
+        });
+        *self.last_error.write().expect("tzdata lock poisoned") = None;
+    }
+
+    /// Whether the last check found a newer release; `None` before one
+    /// succeeds.
+    fn outdated(&self) -> Option<bool> {
+        self.last
+            .read()
+            .expect("tzdata lock poisoned")
+            .as_ref()
+            .map(|upstream| is_newer(&upstream.latest, EMBEDDED_VERSION))
+    }
+
+    fn details(&self) -> Value {
+        let last = self.last.read().expect("tzdata lock poisoned").clone();
+        json!({
+            "embedded": EMBEDDED_VERSION,
+            "latest": last.as_ref().map(|upstream| upstream.latest.clone()),
+            "outdated": self.outdated(),
+            "checked_at": last.as_ref().map(|upstream| upstream.checked_at.to_rfc3339()),
+            "manifest_url": self.manifest_url,
+            "last_error": self.last_error.read().expect("tzdata lock poisoned").clone(),
+        })
+    }
+
+    /// Readiness check that always passes, reporting freshness in its details.
+    pub fn check_freshness(&self) -> Check {
+        Check::new("tzdata", true, self.details())
+    }
+}
+
+/// Fetches the manifest and reads the release it names.
+async fn fetch_latest(http: &reqwest::Client, url: &str) -> Result<String, String> {
+    let response = http
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?;
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_manifest(&body).ok_or_else(|| "manifest names no tzdb version".to_string())
+}
+
+/// The version in a plain-text or `{"version": ...}` manifest.
+fn parse_manifest(body: &str) -> Option<String> {
+    let version = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Object(fields)) => fields.get("version")?.as_str()?.trim().to_string(),
+        _ => body.split_whitespace().next()?.to_string(),
+    };
+    parse_version(&version)?;
+    Some(version)
+}
+
+/// `(year, letters)` of a release such as `2024b`.
+fn parse_version(version: &str) -> Option<(u32, &str)> {
+    let split = version.find(|c: char| !c.is_ascii_digit())?;
+    let (year, letters) = version.split_at(split);
+    let valid = year.len() == 4 && letters.chars().all(|c| c.is_ascii_lowercase());
+    valid.then_some((year.parse().ok()?, letters))
+}
+
+/// Whether release `candidate` is later than `than`. Letters run `a` to `z`
+/// within a year, so a longer suffix is later.
+fn is_newer(candidate: &str, than: &str) -> bool {
+    let key =
+        |version| parse_version(version).map(|(year, letters)| (year, letters.len(), letters));
+    match (key(candidate), key(than)) {
+        (Some(candidate), Some(than)) => candidate > than,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TzdataVersion {
+    /// IANA release compiled in.
+    version: &'static str,
+    zones: usize,
+    /// Present when `TZDATA_MANIFEST_URL` is set.
+    freshness: Option<Value>,
+    request_id: String,
+}
+
+pub async fn get_timezones_version(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Json<TzdataVersion> {
+    let request_id = request_id_from(&headers).unwrap_or_else(|| Uuid::new_v4().to_string());
+    info!(request_id = %request_id, "Processing tzdata version request");
+    Json(TzdataVersion {
+        version: EMBEDDED_VERSION,
+        zones: state.timezone_names.len(),
+        freshness: state.tzdata.as_ref().map(|monitor| monitor.details()),
+        request_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    #[test]
+    fn orders_releases_by_year_then_letter() {
+        assert!(is_newer("2024b", "2024a"));
+        assert!(is_newer("2025a", "2024z"));
+        assert!(!is_newer("2024a", "2024a"));
+        assert!(!is_newer("2023c", "2024a"));
+        assert!(!is_newer("latest", "2024a"));
+        assert!(parse_version(EMBEDDED_VERSION).is_some());
+    }
+
+    #[test]
+    fn reads_plain_text_and_json_manifests() {
+        assert_eq!(parse_manifest("2025b\n").as_deref(), Some("2025b"));
+        assert_eq!(
+            parse_manifest(r#"{"version": "2025b", "released": "2025-03-22"}"#).as_deref(),
+            Some("2025b")
+        );
+        assert_eq!(parse_manifest("<html>Not found</html>"), None);
+        assert_eq!(parse_manifest(r#"{"release": "2025b"}"#), None);
+    }
+
+    #[tokio::test]
+    async fn reports_a_newer_upstream_release() {
+        let upstream = axum::Router::new().route("/version", get(|| async { "2999a\n" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/version", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, upstream).await.unwrap() });
+
+        let metrics = Arc::new(Registry::default());
+        let monitor = FreshnessMonitor::new(url, Duration::from_secs(60), metrics.clone());
+        assert_eq!(monitor.outdated(), None);
+        monitor.check().await;
+
+        assert_eq!(monitor.outdated(), Some(true));
+        let check = monitor.check_freshness();
+        assert!(check.ok);
+        assert_eq!(check.details["latest"], "2999a");
+        assert_eq!(check.details["embedded"], EMBEDDED_VERSION);
+        assert!(metrics.render().contains("tzdata_outdated 1\n"));
+
+        let state = AppState {
+            tzdata: Some(Arc::new(monitor)),
+            ..crate::tests::test_state()
+        };
+        let Json(version) = get_timezones_version(State(state), HeaderMap::new()).await;
+        assert_eq!(version.version, EMBEDDED_VERSION);
+        assert_eq!(version.freshness.unwrap()["outdated"], true);
+    }
+}
@@ -0,0 +1,180 @@
+//! `/v2/time`: the `/v1/time` lookups answered as a [`TimeResponseV2`],
+//! with the offset grouped and daylight saving details added.
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::HeaderMap,
+    response::Json,
+};
+use chrono::{DateTime, Duration, Offset, TimeDelta, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use common::problem::Query;
+use common::{
+    resolve_timezone_alias, DstInfo, TimeQuery, TimeRequest, TimeResponse, TimeResponseV2,
+    UtcOffset,
+};
+
+use crate::transitions::transitions_between;
+use crate::{format_utc_offset, time_request, ApiError, AppState};
+
+/// How far ahead [`DstInfo::next_transition`] looks.
+const NEXT_TRANSITION_HORIZON_DAYS: i64 = 366;
+
+pub async fn get_time(
+    state: State<AppState>,
+    headers: HeaderMap,
+    query: Query<TimeQuery>,
+) -> Result<Json<TimeResponseV2>, ApiError> {
+    let Json(time) = crate::get_time(state, headers, query).await?;
+    Ok(Json(upgrade(time, Utc::now())))
+}
+
+pub async fn post_time(
+    state: State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<TimeRequest>, JsonRejection>,
+) -> Result<Json<TimeResponseV2>, ApiError> {
+    let offset_seconds = body
+        .as_ref()
+        .ok()
+        .and_then(|Json(request)| request.offset_seconds)
+        .unwrap_or(0);
+    let Json(time) = time_request::post_time(state, headers, body).await?;
+    // The offset was range-checked by the v1 handler.
+    let now = Utc::now();
+    let at = TimeDelta::try_seconds(offset_seconds)
+        .and_then(|offset| now.checked_add_signed(offset))
+        .unwrap_or(now);
+    Ok(Json(upgrade(time, at)))
+}
+
+/// Adds the daylight saving details of `time.timezone` at `at`, the instant
+/// `time` was computed for.
+fn upgrade(time: TimeResponse, at: DateTime<Utc>) -> TimeResponseV2 {
+    // v1 has already rejected unknown zones.
+    let tz: Tz = resolve_timezone_alias(&time.timezone)
+        .parse()
+        .unwrap_or(Tz::UTC);
+    let offset = at.with_timezone(&tz).offset().to_owned();
+    let seconds = offset.fix().local_minus_utc();
+    let dst_offset_seconds = offset.dst_offset().num_seconds();
+    let next_transition =
+        transitions_between(&tz, at, at + Duration::days(NEXT_TRANSITION_HORIZON_DAYS))
+            .into_iter()
+            .next()
+            .map(|transition| transition.at);
+    TimeResponseV2 {
+        timestamp: time.timestamp,
+        timezone: time.timezone,
+        request_id: time.request_id,
+        source: time.source,
+        format: time.format.unwrap_or_default(),
+        utc_offset: UtcOffset {
+            seconds,
+            label: format_utc_offset(seconds),
+        },
+        dst: DstInfo {
+            active: dst_offset_seconds != 0,
+            offset_seconds: dst_offset_seconds,
+            abbreviation: offset.abbreviation().to_string(),
+            next_transition,
+        },
+        display: time.display,
+        clock_offset_ms: time.clock_offset_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn v1_time(timezone: &str) -> TimeResponse {
+        TimeResponse {
+            timestamp: "2025-07-01T12:00:00-07:00".to_string(),
+            timezone: timezone.to_string(),
+            request_id: "req-1".to_string(),
+            source: "api2-service".to_string(),
+            utc_offset_seconds: Some(-25200),
+            utc_offset_label: Some("-07:00".to_string()),
+            display: None,
+            format: Some("rfc3339".to_string()),
+            degraded: None,
+            clock_offset_ms: None,
+        }
+    }
+
+    #[test]
+    fn reports_dst_and_the_next_change() {
+        let summer = Utc.with_ymd_and_hms(2025, 7, 1, 19, 0, 0).unwrap();
+        let time = upgrade(v1_time("PST"), summer);
+        assert_eq!(time.timezone, "PST");
+        assert_eq!(
+            time.utc_offset,
+            UtcOffset {
+                seconds: -25200,
+                label: "-07:00".to_string()
+            }
+        );
+        assert!(time.dst.active);
+        assert_eq!(time.dst.offset_seconds, 3600);
+        assert_eq!(time.dst.abbreviation, "PDT");
+        assert_eq!(
+            time.dst.next_transition.as_deref(),
+            Some("2025-11-02T09:00:00Z")
+        );
+
+        let time = upgrade(v1_time("Asia/Bangkok"), summer);
+        assert!(!time.dst.active);
+        assert_eq!(time.utc_offset.label, "+07:00");
+        assert_eq!(time.dst.next_transition, None);
+    }
+
+    #[tokio::test]
+    async fn serves_v2_alongside_v1() {
+        let app = crate::app(
+            crate::tests::test_state(),
+            &common::cors::CorsPolicy::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{base}/v2/time"))
+            .json(&serde_json::json!({ "timezone": "Asia/Tokyo" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: TimeResponseV2 = response.json().await.unwrap();
+        assert_eq!(body.utc_offset.label, "+09:00");
+        assert_eq!(body.dst.abbreviation, "JST");
+        assert_eq!(body.format, "rfc3339");
+
+        let response = client
+            .get(format!("{base}/v2/time?timezone=Mars/Olympus"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let response = client
+            .get(format!("{base}/v1/timezone/Asia/Tokyo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+        let response = client
+            .get(format!("{base}/timezone/Asia/Tokyo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["link"],
+            "</v1/timezone/Asia/Tokyo>; rel=\"successor-version\""
+        );
+    }
+}
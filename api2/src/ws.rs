@@ -0,0 +1,455 @@
+//! `/time` over a WebSocket, for clients sending many queries on one
+//! connection or subscribing to a clock.
+//!
+//! Each text frame holds a query such as
+//! `{"timezone": "Asia/Seoul", "format": "rfc3339", "request_id": "..."}` and
+//! is answered with a `TimeResponse` frame, or an `ErrorResponse` frame when
+//! the query is malformed or invalid.
+//!
+//! `{"subscribe": ["Asia/Tokyo", "UTC"], "interval_secs": 5}` is answered
+//! with a [`Subscribed`] frame, then a [`Tick`] frame at once and every
+//! `interval_secs` until `{"unsubscribe": <id>}` or the client disconnects.
+//! Subscriptions across all connections are capped by
+//! `WS_MAX_SUBSCRIPTIONS`.
+
+use axum::{
+    extract::{Request, State},
+    response::Response,
+};
+use common::format::TimestampFormat;
+use common::problem::ErrorCode;
+use common::{websocket, TimeQuery, TimeResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, OwnedSemaphorePermit};
+use tokio::task::AbortHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{current_time_in, error_response, request_id_from, resolve_time, ApiError, AppState};
+
+/// Subscription cap used when `ws_max_subscriptions` is not configured.
+pub const DEFAULT_MAX_SUBSCRIPTIONS: usize = 1000;
+
+const DEFAULT_INTERVAL_SECS: u64 = 1;
+const MIN_INTERVAL_SECS: u64 = 1;
+const MAX_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Subscribe {
+    subscribe: Vec<String>,
+    interval_secs: Option<u64>,
+    format: Option<String>,
+    request_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Unsubscribe {
+    unsubscribe: u64,
+}
+
+/// Acknowledges a subscription; ticks carry the same `subscription_id`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Subscribed {
+    pub subscription_id: u64,
+    pub timezones: Vec<String>,
+    pub interval_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Unsubscribed {
+    pub unsubscribed: u64,
+}
+
+/// The current time in each subscribed timezone, in subscription order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tick {
+    pub subscription_id: u64,
+    pub times: Vec<TimeResponse>,
+}
+
+/// One connection's subscriptions, shared by the message handler calls.
+#[derive(Clone)]
+struct Session {
+    state: AppState,
+    push: mpsc::Sender<String>,
+    next_id: Arc<AtomicU64>,
+    subscriptions: Arc<Mutex<HashMap<u64, AbortHandle>>>,
+}
+
+pub async fn get_time_ws(
+    State(state): State<AppState>,
+    mut request: Request,
+) -> Result<Response, ApiError> {
+    let request_id =
+        request_id_from(request.headers()).unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    info!(request_id = %request_id, "Processing time WebSocket request");
+
+    let (push, pushed) = mpsc::channel(16);
+    let session = Session {
+        state,
+        push,
+        next_id: Arc::new(AtomicU64::new(1)),
+        subscriptions: Arc::default(),
+    };
+    websocket::upgrade_with_pushes(&mut request, pushed, move |text| {
+        answer(session.clone(), text)
+    })
+    .ok_or_else(|| {
+        error_response(
+            ErrorCode::InvalidRequest,
+            "Expected a WebSocket upgrade request",
+            &request_id,
+        )
+    })
+}
+
+async fn answer(session: Session, text: String) -> String {
+    let reply = match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(message) if message.get("subscribe").is_some() => {
+            parse(message).and_then(|subscribe| session.subscribe(subscribe))
+        }
+        Ok(message) if message.get("unsubscribe").is_some() => {
+            parse(message).map(|unsubscribe| session.unsubscribe(unsubscribe))
+        }
+        Ok(message) => match parse::<TimeQuery>(message) {
+            Ok(query) => {
+                let request_id = query
+                    .request_id
+                    .unwrap_or_else(|| Uuid::new_v4().to_string());
+                resolve_time(
+                    &session.state,
+                    query.timezone,
+                    query.format.as_deref(),
+                    query.locale.as_deref(),
+                    request_id,
+                )
+                .await
+                .map(|response| to_json(&response))
+            }
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(invalid_query(e)),
+    };
+    match reply {
+        Ok(response) => response,
+        Err(error) => to_json(&error),
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(message: serde_json::Value) -> Result<T, ApiError> {
+    serde_json::from_value(message).map_err(invalid_query)
+}
+
+fn invalid_query(e: serde_json::Error) -> ApiError {
+    error_response(
+        ErrorCode::InvalidRequest,
+        format!("Invalid query: {e}"),
+        &Uuid::new_v4().to_string(),
+    )
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("responses serialize")
+}
+
+impl Session {
+    fn subscribe(&self, subscribe: Subscribe) -> Result<String, ApiError> {
+        let request_id = subscribe
+            .request_id
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let bad_request =
+            |message: String| error_response(ErrorCode::InvalidRequest, message, &request_id);
+        if subscribe.subscribe.is_empty() {
+            return Err(bad_request(
+                "subscribe needs at least one timezone".to_string(),
+            ));
+        }
+        if subscribe.subscribe.len() > self.state.max_batch_size {
+            return Err(bad_request(format!(
+                "Subscription to {} timezones exceeds the limit of {}",
+                subscribe.subscribe.len(),
+                self.state.max_batch_size
+            )));
+        }
+        let format =
+            TimestampFormat::from_param(subscribe.format.as_deref()).map_err(bad_request)?;
+        // Reject unknown timezones before committing to a subscription.
+        for timezone in &subscribe.subscribe {
+            current_time_in(timezone, &format, &request_id)?;
+        }
+        let Ok(permit) = self.state.ws_subscriptions.clone().try_acquire_owned() else {
+            return Err(error_response(
+                ErrorCode::Unavailable,
+                "Too many WebSocket subscriptions; try again later",
+                &request_id,
+            ));
+        };
+
+        let interval_secs = subscribe
+            .interval_secs
+            .unwrap_or(DEFAULT_INTERVAL_SECS)
+            .clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS);
+        let subscription_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        info!(
+            request_id = %request_id,
+            subscription_id,
+            timezones = ?subscribe.subscribe,
+            interval_secs,
+            "WebSocket subscription started"
+        );
+
+        let subscription = Subscription {
+            id: subscription_id,
+            timezones: subscribe.subscribe.clone(),
+            format,
+            period: Duration::from_secs(interval_secs),
+            request_id,
+            _permit: permit,
+        };
+        let ticking = subscription.run(self.state.clone(), self.push.clone());
+        let handle = tokio::spawn(ticking).abort_handle();
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .insert(subscription_id, handle);
+
+        Ok(to_json(&Subscribed {
+            subscription_id,
+            timezones: subscribe.subscribe,
+            interval_secs,
+        }))
+    }
+
+    fn unsubscribe(&self, unsubscribe: Unsubscribe) -> String {
+        let id = unsubscribe.unsubscribe;
+        if let Some(handle) = self.subscriptions.lock().unwrap().remove(&id) {
+            handle.abort();
+            info!(subscription_id = id, "WebSocket subscription stopped");
+        }
+        to_json(&Unsubscribed { unsubscribed: id })
+    }
+}
+
+/// A subscription's settings, owned by the task sending its ticks. The
+/// permit counts it against `WS_MAX_SUBSCRIPTIONS` until the task ends.
+struct Subscription {
+    id: u64,
+    timezones: Vec<String>,
+    format: TimestampFormat,
+    period: Duration,
+    request_id: String,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Subscription {
+    /// Pushes a tick every `period` until the session ends or the
+    /// subscription is aborted.
+    async fn run(self, state: AppState, push: mpsc::Sender<String>) {
+        let mut ticks = interval(self.period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                _ = push.closed() => break,
+            }
+            let times = self
+                .timezones
+                .iter()
+                .filter_map(|timezone| {
+                    let time = current_time_in(timezone, &self.format, &self.request_id).ok()?;
+                    state.record_timezone_use(timezone);
+                    Some(time.into_response(timezone.clone(), self.request_id.clone()))
+                })
+                .collect();
+            let tick = Tick {
+                subscription_id: self.id,
+                times,
+            };
+            if push.send(to_json(&tick)).await.is_err() {
+                break;
+            }
+        }
+        info!(
+            request_id = %self.request_id,
+            subscription_id = self.id,
+            "WebSocket subscription ended"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use common::websocket::{Message, MessageReader};
+    use common::{ErrorResponse, TimeResponse};
+    use tokio::net::TcpStream;
+
+    /// Sends `query` as a masked text frame and returns the text reply.
+    async fn ask(stream: &mut TcpStream, query: &str) -> String {
+        let message = Message::Text(query.to_string());
+        websocket::write_message(stream, &message, true)
+            .await
+            .unwrap();
+        match MessageReader::new(stream).next().await.unwrap() {
+            Some(Message::Text(text)) => text,
+            other => panic!("unexpected reply: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_sequential_queries_on_one_connection() {
+        let router = Router::new()
+            .route("/time/ws", get(get_time_ws))
+            .with_state(crate::tests::test_state());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let key = websocket::new_key();
+        let handshake =
+            websocket::request_upgrade(&mut stream, &addr.to_string(), "/time/ws", &key, &[])
+                .await
+                .unwrap();
+        assert_eq!(handshake.status, 101);
+        assert_eq!(handshake.accept, Some(websocket::accept_key(&key)));
+
+        for (timezone, offset) in [
+            ("Asia/Seoul", "+09:00"),
+            ("Asia/Kolkata", "+05:30"),
+            ("UTC", "+00:00"),
+        ] {
+            let query = format!(r#"{{"timezone": "{timezone}", "request_id": "ws-{timezone}"}}"#);
+            let response: TimeResponse =
+                serde_json::from_str(&ask(&mut stream, &query).await).unwrap();
+            assert_eq!(response.timezone, timezone);
+            assert_eq!(response.request_id, format!("ws-{timezone}"));
+            assert!(
+                response.timestamp.ends_with(offset),
+                "{}",
+                response.timestamp
+            );
+        }
+
+        // Bad queries get error frames and the connection stays usable.
+        let error: ErrorResponse =
+            serde_json::from_str(&ask(&mut stream, "not json").await).unwrap();
+        assert!(error.error.starts_with("Invalid query"), "{}", error.error);
+        let error: ErrorResponse =
+            serde_json::from_str(&ask(&mut stream, r#"{"timezone": "Foo/Bar"}"#).await).unwrap();
+        assert_eq!(error.error, "Invalid timezone: Foo/Bar");
+        let response: TimeResponse =
+            serde_json::from_str(&ask(&mut stream, r#"{"format": "unix"}"#).await).unwrap();
+        assert!(response.timestamp.parse::<i64>().is_ok());
+    }
+
+    /// Opens a WebSocket to a `/time/ws` served with `state`.
+    async fn connect(state: AppState) -> TcpStream {
+        let router = Router::new()
+            .route("/time/ws", get(get_time_ws))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let key = websocket::new_key();
+        websocket::request_upgrade(&mut stream, &addr.to_string(), "/time/ws", &key, &[])
+            .await
+            .unwrap();
+        stream
+    }
+
+    async fn next_text(stream: &mut TcpStream) -> String {
+        match MessageReader::new(stream).next().await.unwrap() {
+            Some(Message::Text(text)) => text,
+            other => panic!("unexpected frame: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn pushes_ticks_until_unsubscribed() {
+        let state = crate::tests::test_state();
+        let mut stream = connect(state.clone()).await;
+
+        let subscribe = r#"{"subscribe": ["Asia/Tokyo", "UTC"], "interval_secs": 1}"#;
+        let subscribed: Subscribed =
+            serde_json::from_str(&ask(&mut stream, subscribe).await).unwrap();
+        assert_eq!(subscribed.timezones, ["Asia/Tokyo", "UTC"]);
+        assert_eq!(subscribed.interval_secs, 1);
+        assert_eq!(
+            state.ws_subscriptions.available_permits(),
+            DEFAULT_MAX_SUBSCRIPTIONS - 1
+        );
+
+        // The first tick comes at once, the next after the interval.
+        for _ in 0..2 {
+            let tick: Tick = serde_json::from_str(&next_text(&mut stream).await).unwrap();
+            assert_eq!(tick.subscription_id, subscribed.subscription_id);
+            let zones: Vec<_> = tick.times.iter().map(|t| t.timezone.as_str()).collect();
+            assert_eq!(zones, ["Asia/Tokyo", "UTC"]);
+            assert!(tick.times[0].timestamp.ends_with("+09:00"));
+        }
+
+        let unsubscribe = format!(r#"{{"unsubscribe": {}}}"#, subscribed.subscription_id);
+        let unsubscribed: Unsubscribed =
+            serde_json::from_str(&ask(&mut stream, &unsubscribe).await).unwrap();
+        assert_eq!(unsubscribed.unsubscribed, subscribed.subscription_id);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            state.ws_subscriptions.available_permits(),
+            DEFAULT_MAX_SUBSCRIPTIONS
+        );
+    }
+
+    #[tokio::test]
+    async fn caps_subscriptions_and_releases_them_on_disconnect() {
+        let mut state = crate::tests::test_state();
+        state.ws_subscriptions = Arc::new(tokio::sync::Semaphore::new(1));
+        let mut stream = connect(state.clone()).await;
+
+        let error: ErrorResponse =
+            serde_json::from_str(&ask(&mut stream, r#"{"subscribe": ["Foo/Bar"]}"#).await).unwrap();
+        assert_eq!(error.error, "Invalid timezone: Foo/Bar");
+
+        let subscribe = r#"{"subscribe": ["UTC"], "interval_secs": 60}"#;
+        let _: Subscribed = serde_json::from_str(&ask(&mut stream, subscribe).await).unwrap();
+        let _: Tick = serde_json::from_str(&next_text(&mut stream).await).unwrap();
+        let error: ErrorResponse =
+            serde_json::from_str(&ask(&mut stream, subscribe).await).unwrap();
+        assert!(error.error.starts_with("Too many"), "{}", error.error);
+
+        drop(stream);
+        for _ in 0..50 {
+            if state.ws_subscriptions.available_permits() == 1 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("subscription outlived its connection");
+    }
+
+    #[tokio::test]
+    async fn rejects_plain_requests() {
+        let router = Router::new()
+            .route("/time/ws", get(get_time_ws))
+            .with_state(crate::tests::test_state());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let response = reqwest::get(format!("http://{addr}/time/ws"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+}
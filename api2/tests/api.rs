@@ -0,0 +1,582 @@
+//! Requests driven through API2's router without a listener.
+
+use api2::{app, AppState};
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use common::config::Config;
+use common::cors::CorsPolicy;
+use common::{CronResponse, ErrorResponse, TimeResponse, REQUEST_ID_HEADER};
+use serde::de::DeserializeOwned;
+use tower::Service;
+
+fn api2() -> Router {
+    app(
+        AppState::from_config(&Config::default()),
+        &CorsPolicy::default(),
+    )
+}
+
+async fn send<T: DeserializeOwned>(request: Request<Body>) -> (StatusCode, T) {
+    send_to(api2(), request).await
+}
+
+async fn send_to<T: DeserializeOwned>(mut app: Router, request: Request<Body>) -> (StatusCode, T) {
+    // Router is always ready, so poll_ready can be skipped.
+    let response = app.call(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn get<T: DeserializeOwned>(uri: &str) -> (StatusCode, T) {
+    let request = Request::get(uri)
+        .header(REQUEST_ID_HEADER, "req-1")
+        .body(Body::empty())
+        .unwrap();
+    send(request).await
+}
+
+#[tokio::test]
+async fn answers_time_in_iana_zones_and_aliases() {
+    let (status, time): (_, TimeResponse) = get("/v1/time?timezone=Asia/Bangkok").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.timezone, "Asia/Bangkok");
+    assert_eq!(time.utc_offset_label.as_deref(), Some("+07:00"));
+    assert!(time.timestamp.ends_with("+07:00"), "{}", time.timestamp);
+    assert_eq!(time.request_id, "req-1");
+    assert_eq!(time.source, "api2-service");
+
+    let (status, time): (_, TimeResponse) = get("/v1/time?format=unix").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.timezone, "UTC");
+    assert!(time.timestamp.parse::<i64>().is_ok(), "{}", time.timestamp);
+
+    let (status, time): (_, TimeResponse) = get("/v1/time?timezone=PST").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.timezone, "PST");
+    assert!(matches!(
+        time.utc_offset_label.as_deref(),
+        Some("-08:00" | "-07:00")
+    ));
+}
+
+#[tokio::test]
+async fn rejects_invalid_timezones_and_options() {
+    let (status, error): (_, ErrorResponse) = get("/v1/time?timezone=Mars/Olympus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid timezone: Mars/Olympus");
+    assert_eq!(error.request_id, "req-1");
+
+    let (status, _): (_, ErrorResponse) = get("/v1/time?format=bogus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _): (_, ErrorResponse) = get("/v1/time?locale=xx").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let request = Request::post("/v1/time")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"timezone": "UTC", "colour": "blue"}"#))
+        .unwrap();
+    let (status, error): (_, ErrorResponse) = send(request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(error.error.contains("colour"), "{}", error.error);
+}
+
+#[tokio::test]
+async fn previews_cron_schedules() {
+    let preview = |body: &'static str| {
+        Request::post("/v1/cron/next")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, cron): (_, CronResponse) = send(preview(
+        r#"{"expression": "0 9 * * MON-FRI", "timezone": "Asia/Bangkok"}"#,
+    ))
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cron.next.len(), 5);
+    assert!(cron.next.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(cron.next.iter().all(|at| at.ends_with("T09:00:00+07:00")));
+    assert_eq!(cron.request_id, "req-1");
+
+    let (status, error): (_, ErrorResponse) =
+        send(preview(r#"{"expression": "0 25 * * *"}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid cron expression: hour: 25 is out of range 0-23"
+    );
+    let (status, _): (_, ErrorResponse) =
+        send(preview(r#"{"expression": "@daily", "count": 0}"#)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn places_dates_in_the_retail_calendar() {
+    let (status, week): (_, serde_json::Value) =
+        get("/v1/time/retail-calendar?date=2027-01-01&fy_start=2023-12-31").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(week["retail_week"], 53);
+    assert_eq!(week["leap_week"], true);
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/retail-calendar?date=%2B262142-12-31").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Date out of range for the retail calendar: +262142-12-31"
+    );
+    let (status, _): (_, ErrorResponse) = get("/v1/time/retail-calendar?pattern=4-4-4").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn checks_dates_against_recurrence_rules() {
+    let (status, check): (_, serde_json::Value) = get(
+        "/v1/time/recurring-event-check?date=2024-01-15&dtstart=2023-01-15T00:00:00Z&rrule=FREQ%3DMONTHLY%3BBYMONTHDAY%3D15",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(check["matches"], true);
+    assert_eq!(check["occurrence_number"], 13);
+
+    let (status, error): (_, ErrorResponse) = get(
+        "/v1/time/recurring-event-check?date=2024-01-15&dtstart=2023-01-15T00:00:00Z&rrule=FREQ%3DWEEKLY%3BBYDAY%3D%C3%A9a",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid BYDAY value: éa");
+}
+
+#[tokio::test]
+async fn counts_business_days_within_bounds() {
+    let (status, hours): (_, serde_json::Value) =
+        get("/v1/time/business?timezone=Asia/Bangkok&holidays=th&add_days=-5").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(hours["business_days"]["days"], -5);
+
+    for days in ["1001", "-1001", "-9223372036854775808"] {
+        let (status, error): (_, ErrorResponse) =
+            get(&format!("/v1/time/business?add_days={days}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{days}");
+        assert_eq!(error.error, "add_days must be between -1000 and 1000");
+    }
+}
+
+#[tokio::test]
+async fn finds_payroll_periods() {
+    let (status, period): (_, serde_json::Value) = get(
+        "/v1/time/payroll-period?date=2024-01-17&pay_day_1=1&pay_day_2=15&timezone=America/New_York",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(period["period_number"], 2);
+    assert_eq!(period["period_end"], "2024-01-31");
+    assert_eq!(period["next_pay_date"], "2024-02-01");
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/payroll-period?date=%2B262142-12-20").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Date out of range for payroll periods: +262142-12-20"
+    );
+    let (status, _): (_, ErrorResponse) =
+        get("/v1/time/payroll-period?pay_day_1=15&pay_day_2=1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn splits_days_into_hour_buckets() {
+    let (status, buckets): (_, Vec<serde_json::Value>) =
+        get("/v1/time/hour-of-day-distribution?timezone=America/New_York&date=2024-03-10").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(buckets.len(), 23);
+    assert_eq!(buckets[0]["utc_start"], "2024-03-10T05:00:00Z");
+    assert_eq!(buckets[2]["hour"], 3);
+    assert_eq!(buckets[2]["dst_transition"], true);
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/hour-of-day-distribution?date=2024-13-01").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid date: 2024-13-01 (expected YYYY-MM-DD)"
+    );
+    let (status, _): (_, ErrorResponse) =
+        get("/v1/time/hour-of-day-distribution?date=%2B262142-12-31").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _): (_, ErrorResponse) =
+        get("/v1/time/hour-of-day-distribution?date=-262143-01-01&timezone=Asia/Tokyo").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn counts_time_since_alternative_epochs() {
+    let (status, epochs): (_, serde_json::Value) = get("/v1/time/time-since-epoch-alt").await;
+    assert_eq!(status, StatusCode::OK);
+    let unix = epochs["unix_seconds"].as_i64().unwrap();
+    assert_eq!(
+        epochs["gps_seconds"].as_i64(),
+        Some(unix - 315_964_800 + 18)
+    );
+    let filetime_seconds = epochs["windows_filetime"].as_u64().unwrap() / 10_000_000;
+    assert_eq!(filetime_seconds as i64, unix + 11_644_473_600);
+    let cocoa = epochs["cocoa_seconds"].as_f64().unwrap();
+    assert!((cocoa - (unix - 978_307_200) as f64).abs() < 1.0, "{cocoa}");
+}
+
+#[tokio::test]
+async fn compares_moments_with_now() {
+    let (status, comparison): (_, serde_json::Value) =
+        get("/v1/time/moment-comparison?datetime=2000-01-01T00:00:00Z&tolerance_secs=60").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(comparison["classification"], "past");
+    assert_eq!(comparison["within_tolerance"], false);
+    assert!(comparison["diff_seconds"].as_i64().unwrap() < -60);
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/moment-comparison?datetime=yesterday").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Invalid datetime: yesterday (expected RFC 3339)"
+    );
+}
+
+#[tokio::test]
+async fn counts_time_requests_per_timezone_for_operators() {
+    let app = app(
+        AppState::from_config(&Config {
+            api_keys: Some(vec!["key-1".to_string()]),
+            ..Config::default()
+        }),
+        &CorsPolicy::default(),
+    );
+    let stats = |key: &str| {
+        Request::get("/v1/time/per-timezone-stats")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .header("x-api-key", key)
+            .body(Body::empty())
+            .unwrap()
+    };
+    for timezone in ["Asia/Tokyo", "UTC", "Asia/Tokyo"] {
+        let request = Request::get(format!("/v1/time?timezone={timezone}"))
+            .body(Body::empty())
+            .unwrap();
+        let (status, _): (_, TimeResponse) = send_to(app.clone(), request).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, stats_body): (_, serde_json::Value) = send_to(app.clone(), stats("key-1")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stats_body["total_requests"], 3);
+    assert_eq!(stats_body["timezones"][0]["timezone"], "Asia/Tokyo");
+    assert_eq!(stats_body["timezones"][0]["request_count"], 2);
+
+    let (status, error): (_, ErrorResponse) = send_to(app, stats("wrong")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(error.error, "Missing or invalid API key");
+}
+
+#[tokio::test]
+async fn classifies_moments_in_geological_and_cosmological_time() {
+    let (status, segment): (_, serde_json::Value) =
+        get("/v1/time/current-epoch-segment?years_ago=150000000").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(segment["eon"], "Phanerozoic");
+    assert_eq!(segment["era"], "Mesozoic");
+    assert_eq!(segment["period"], "Jurassic");
+
+    let (status, segment): (_, serde_json::Value) =
+        get("/v1/time/current-epoch-segment?scale=cosmological").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(segment["cosmological_era"], "Stelliferous");
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/current-epoch-segment?years_ago=5000000000").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error.error.starts_with("years_ago must not exceed"),
+        "{}",
+        error.error
+    );
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/current-epoch-segment?scale=lunar").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Unsupported scale: lunar (expected geological or cosmological)"
+    );
+}
+
+#[tokio::test]
+async fn reports_ntp_status_even_when_it_cannot_be_read() {
+    let (status, info): (_, serde_json::Value) = get("/v1/time/network-time-protocol-info").await;
+    assert_eq!(status, StatusCode::OK);
+    for field in ["ntp_synced", "time_usec", "rtc_offset_ms"] {
+        assert!(info.get(field).is_some(), "{field} missing from {info}");
+    }
+}
+
+#[tokio::test]
+async fn finds_the_astronomical_season() {
+    let (status, season): (_, serde_json::Value) =
+        get("/v1/time/astronomical-season?year=2030&timezone=Asia/Tokyo&hemisphere=south").await;
+    assert_eq!(status, StatusCode::OK);
+    let order = ["Autumn", "Winter", "Spring", "Summer"];
+    let position = |key: &str| order.iter().position(|name| season[key] == *name).unwrap();
+    assert_eq!(
+        (position("current_season") + 1) % 4,
+        position("next_season")
+    );
+    let start = season["season_start"].as_str().unwrap();
+    assert!(
+        start.starts_with("20") && start.ends_with("+09:00"),
+        "{start}"
+    );
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/astronomical-season?hemisphere=east").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Unsupported hemisphere: east (expected north or south)"
+    );
+    let (status, error): (_, ErrorResponse) = get("/v1/time/astronomical-season?year=1999").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "year must be between 2000 and 2050, got 1999");
+}
+
+#[tokio::test]
+async fn lists_timezone_transitions_in_a_year() {
+    let (status, transitions): (_, Vec<serde_json::Value>) =
+        get("/v1/time/timezone-transition-list?timezone=America/New_York&year=2024").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(transitions.len(), 2);
+    assert_eq!(transitions[0]["at"], "2024-03-10T07:00:00Z");
+    assert_eq!(transitions[0]["abbreviation_after"], "EDT");
+    assert_eq!(transitions[1]["to_offset_seconds"], -18000);
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/timezone-transition-list?year=1899").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "year must be between 1900 and 2100, got 1899");
+    let (status, _): (_, ErrorResponse) =
+        get("/v1/time/timezone-transition-list?timezone=Mars/Olympus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn measures_the_distance_between_timezones() {
+    let (status, distance): (_, serde_json::Value) =
+        get("/v1/time/timezone-distance?from=Asia/Kolkata&to=Asia/Tokyo").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(distance["from_offset"], "+05:30");
+    assert_eq!(distance["difference_minutes"], 210);
+    assert_eq!(distance["difference_hours"], 3.5);
+    assert_eq!(distance["to_is_ahead"], true);
+    let description = distance["description"].as_str().unwrap();
+    assert!(description.starts_with("Tokyo is "), "{description}");
+    assert!(description.ends_with(" ahead of Kolkata"), "{description}");
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/timezone-distance?from=Asia/Kolkata").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Both from and to timezones are required");
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/timezone-distance?from=Asia/Kolkata&to=Mars/Olympus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid timezone: Mars/Olympus");
+}
+
+#[tokio::test]
+async fn answers_batches_in_request_order() {
+    let batch = |body: String| {
+        Request::post("/v1/time/batch")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(REQUEST_ID_HEADER, "req-1")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    let (status, response): (_, serde_json::Value) = send(batch(
+        r#"{"timezones": ["Asia/Tokyo", "Mars/Olympus", "UTC"]}"#.to_string(),
+    ))
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["items"][0]["utc_offset_label"], "+09:00");
+    assert_eq!(response["items"][1]["timezone"], "Mars/Olympus");
+    assert_eq!(
+        response["items"][1]["error"],
+        "Invalid timezone: Mars/Olympus"
+    );
+    assert_eq!(response["items"][2]["timezone"], "UTC");
+    assert_eq!(response["results"].as_array().unwrap().len(), 2);
+    assert_eq!(response["request_id"], "req-1");
+
+    let oversized = serde_json::to_string(&vec!["UTC"; 51]).unwrap();
+    let (status, error): (_, ErrorResponse) = send(batch(oversized)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Batch of 51 timezones exceeds the limit of 50");
+}
+
+#[tokio::test]
+async fn renders_timestamps_in_the_requested_format() {
+    let (status, time): (_, TimeResponse) =
+        get("/v1/time?timezone=Asia/Bangkok&format=rfc2822").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(time.format.as_deref(), Some("rfc2822"));
+    assert!(time.timestamp.ends_with(" +0700"), "{}", time.timestamp);
+
+    let (_, time): (_, TimeResponse) = get("/v1/time?format=unix_ms").await;
+    assert_eq!(time.format.as_deref(), Some("unix_ms"));
+    assert!(time.timestamp.parse::<i64>().unwrap() > 1_700_000_000_000);
+
+    let (_, time): (_, TimeResponse) =
+        get("/v1/time?timezone=Asia/Bangkok&format=custom:%25H:%25M%20%25Z").await;
+    assert_eq!(time.format.as_deref(), Some("custom"));
+    assert!(time.timestamp.ends_with(" +07"), "{}", time.timestamp);
+
+    let (status, error): (_, ErrorResponse) = get("/v1/time?format=custom:%25Q").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid format string: %Q");
+    let (status, error): (_, ErrorResponse) =
+        get(&format!("/v1/time?format=custom:{}", "%25Y".repeat(33))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Format string too long: 66 characters (at most 64)"
+    );
+}
+
+#[tokio::test]
+async fn converts_instants_between_timezones() {
+    let (status, converted): (_, serde_json::Value) =
+        get("/v1/time/convert?timestamp=2024-07-15T12:00:00Z&to=Asia/Tokyo").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(converted["to"]["timestamp"], "2024-07-15T21:00:00+09:00");
+
+    // Local times past chrono's range, at both ends of the timeline.
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/convert?timestamp=8210266876799&from=UTC&to=Pacific/Kiritimati").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Timestamp out of range in Pacific/Kiritimati: 8210266876799"
+    );
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/convert?timestamp=-8334601228800&from=UTC&to=America/New_York").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Timestamp out of range in America/New_York: -8334601228800"
+    );
+}
+
+#[tokio::test]
+async fn converts_epochs_and_calendar_dates() {
+    let (status, converted): (_, serde_json::Value) =
+        get("/v1/convert/epoch?seconds=0&timezone=Asia/Tokyo").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(converted["local"], "1970-01-01T09:00:00.000+09:00");
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/convert/epoch?seconds=8210266876799&timezone=Asia/Tokyo").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "seconds out of range in Asia/Tokyo: 8210266876799"
+    );
+    let (status, _): (_, ErrorResponse) =
+        get("/v1/convert/epoch?seconds=-8334601228800&timezone=America/New_York").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/convert/calendar?julian_day_number=-9223372036854775808").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "julian_day_number out of range: -9223372036854775808"
+    );
+}
+
+#[tokio::test]
+async fn formats_unix_epochs_as_local_dates() {
+    let (status, date): (_, serde_json::Value) =
+        get("/v1/time/unix-to-date?epoch=1705320000&timezone=America/New_York").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(date["date"], "2024-01-15T07:00:00-05:00");
+
+    // In range as UTC, but not as local time.
+    for query in [
+        "epoch=8210266876799&timezone=Pacific/Kiritimati",
+        "epoch=-8334601228800&timezone=America/New_York",
+    ] {
+        let (status, error): (_, ErrorResponse) =
+            get(&format!("/v1/time/unix-to-date?{query}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert!(error.error.starts_with("epoch out of range: "), "{query}");
+    }
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/unix-to-date?epoch=0&timezone=Mars/Olympus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error.error, "Invalid timezone: Mars/Olympus");
+}
+
+#[tokio::test]
+async fn labels_decades_centuries_and_millennia() {
+    let (status, labels): (_, serde_json::Value) =
+        get("/v1/time/decade-century-millennium?date=2001-01-01").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(labels["decade"], "2000s");
+    assert_eq!(labels["millennium"], "3rd");
+
+    let (status, error): (_, ErrorResponse) =
+        get("/v1/time/decade-century-millennium?date=%2B262142-06-01").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error.error,
+        "Date out of range: +262142-06-01 (its decade ends past the last supported year)"
+    );
+}
+
+#[tokio::test]
+async fn reports_malformed_query_strings_as_problems() {
+    let request = Request::get("/v1/time/ancient-calendar?year=abc")
+        .header(REQUEST_ID_HEADER, "req-1")
+        .body(Body::empty())
+        .unwrap();
+    let response = api2().call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/problem+json"
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["code"], "INVALID_REQUEST");
+    assert_eq!(error["request_id"], "req-1");
+    let detail = error["detail"].as_str().unwrap();
+    assert!(
+        detail.starts_with("Failed to deserialize query string"),
+        "{detail}"
+    );
+
+    let (status, error): (_, ErrorResponse) = get("/v1/time/moment-comparison").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error.error.contains("missing field `datetime`"),
+        "{}",
+        error.error
+    );
+}
@@ -0,0 +1,86 @@
+//! Why a call to the time service failed.
+
+use common::problem::ErrorCode;
+use common::ErrorResponse;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The service answered with a problem; its `code` is the one the
+    /// servers use.
+    Api(ErrorResponse),
+    /// No complete response: the connection failed, timed out or was cut
+    /// off.
+    Http(reqwest::Error),
+    /// A subscription's stream did not open within the timeout.
+    Timeout,
+    /// A response that is neither the expected body nor a problem, e.g. an
+    /// HTML error page from a proxy.
+    Decode { status: u16, detail: String },
+}
+
+impl Error {
+    /// The kind of failure, with transport failures read as the service
+    /// being unavailable, too slow or answering garbage.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Api(problem) => problem.code,
+            Error::Http(e) if e.is_timeout() => ErrorCode::UpstreamTimeout,
+            Error::Http(e) if e.is_decode() => ErrorCode::UpstreamBadResponse,
+            Error::Http(_) => ErrorCode::UpstreamUnavailable,
+            Error::Timeout => ErrorCode::UpstreamTimeout,
+            Error::Decode { .. } => ErrorCode::UpstreamBadResponse,
+        }
+    }
+
+    /// The HTTP status answered, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api(problem) => Some(problem.status),
+            Error::Http(e) => e.status().map(|status| status.as_u16()),
+            Error::Decode { status, .. } => Some(*status),
+            Error::Timeout => None,
+        }
+    }
+
+    /// Whether the same call might succeed if sent again: transport
+    /// failures and `5xx` answers.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Http(_) | Error::Timeout => true,
+            _ => self.status().is_some_and(|status| status >= 500),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api(problem) => write!(
+                f,
+                "{} ({:?}, request {})",
+                problem.error, problem.code, problem.request_id
+            ),
+            Error::Http(e) => e.fmt(f),
+            Error::Timeout => f.write_str("timed out waiting for the stream to open"),
+            Error::Decode { status, detail } => {
+                write!(f, "unreadable response (status {status}): {detail}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
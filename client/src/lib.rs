@@ -0,0 +1,239 @@
+//! Typed async client for the time service, for Rust services that call
+//! API1, or API2 directly, instead of hand-writing `reqwest` calls.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), time_service_client::Error> {
+//! use futures_util::StreamExt;
+//! use time_service_client::TimeClient;
+//!
+//! let client = TimeClient::new("http://localhost:3000").with_api_key("key-1");
+//! let time = client.get_time("Asia/Bangkok").await?;
+//! println!("{}", time.timestamp);
+//!
+//! let mut times = client
+//!     .subscribe("Asia/Tokyo", std::time::Duration::from_secs(1))
+//!     .await?;
+//! while let Some(time) = times.next().await {
+//!     println!("{}", time?.timestamp);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Calls go to the `/v1` routes, with the bodies the servers themselves
+//! serialize, so a schema change fails to compile here too. Failed calls are
+//! retried like API1 retries API2: transport errors and `5xx` answers, with
+//! jittered exponential backoff. Every attempt of a call carries the same
+//! `X-Request-ID`, so the service's logs tie them together, and
+//! `X-Request-Timeout`, so the service stops working on an attempt the
+//! client has given up on. Errors carry the servers' own [`ErrorCode`].
+
+mod error;
+mod retry;
+mod stream;
+
+pub use common::problem::ErrorCode;
+pub use common::{
+    BatchTimeError, BatchTimeItem, BatchTimeResponse, ConvertedTime, ErrorResponse,
+    TimeConvertResponse, TimeResponse,
+};
+pub use error::Error;
+pub use retry::RetryPolicy;
+pub use stream::TimeStream;
+
+use common::auth::API_KEY_HEADER;
+use common::limits::{format_request_timeout, REQUEST_TIMEOUT_HEADER};
+use common::versioning::V1_PREFIX;
+use common::{BatchTimeRequest, REQUEST_ID_HEADER};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Time allowed for each attempt of a call unless [`TimeClient::with_timeout`]
+/// says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest error body kept in [`Error::Decode`].
+const MAX_DETAIL_CHARS: usize = 200;
+
+/// A client for one time service; cheap to clone, sharing its connection
+/// pool.
+#[derive(Debug, Clone)]
+pub struct TimeClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    retry: RetryPolicy,
+    timeout: Duration,
+}
+
+impl TimeClient {
+    /// A client for the service at `base_url`, e.g. `http://api1:3000`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url: String = base_url.into();
+        TimeClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            retry: RetryPolicy::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sends calls through `http`, e.g. one with custom TLS or proxy settings.
+    pub fn with_http_client(self, http: reqwest::Client) -> Self {
+        TimeClient { http, ..self }
+    }
+
+    /// Sends `api_key` in `X-Api-Key` with every call.
+    pub fn with_api_key(self, api_key: impl Into<String>) -> Self {
+        TimeClient {
+            api_key: Some(api_key.into()),
+            ..self
+        }
+    }
+
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        TimeClient { retry, ..self }
+    }
+
+    /// Gives up on each attempt of a call after `timeout`. Subscriptions
+    /// only apply it to opening the stream.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        TimeClient { timeout, ..self }
+    }
+
+    /// `GET /v1/time`: the current time in `timezone`.
+    pub async fn get_time(&self, timezone: &str) -> Result<TimeResponse, Error> {
+        self.call(|http| http.get(self.url("/time")).query(&[("timezone", timezone)]))
+            .await
+    }
+
+    /// `GET /v1/time/convert`: `timestamp` (RFC 3339, or local time in
+    /// `from` without an offset) as seen in the `from` and `to` zones.
+    pub async fn convert(
+        &self,
+        timestamp: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<TimeConvertResponse, Error> {
+        self.call(|http| {
+            http.get(self.url("/time/convert")).query(&[
+                ("timestamp", timestamp),
+                ("from", from),
+                ("to", to),
+            ])
+        })
+        .await
+    }
+
+    /// `POST /v1/time/batch`: the current time in each of `timezones`.
+    /// Unknown zones are reported in the response rather than failing it.
+    pub async fn batch<I>(&self, timezones: I) -> Result<BatchTimeResponse, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let batch = BatchTimeRequest {
+            timezones: timezones.into_iter().map(Into::into).collect(),
+        };
+        self.call(|http| http.post(self.url("/time/batch")).json(&batch))
+            .await
+    }
+
+    /// `GET /v1/time/stream`: the time in `timezone` every `interval`,
+    /// until the service ends the stream. Opening the stream is retried;
+    /// a stream cut off later ends with the error.
+    pub async fn subscribe(&self, timezone: &str, interval: Duration) -> Result<TimeStream, Error> {
+        let request_id = Uuid::new_v4().to_string();
+        let interval_ms = interval.as_millis().to_string();
+        let (request_id, interval_ms) = (request_id.as_str(), interval_ms.as_str());
+        let response = self
+            .retry
+            .run(move || async move {
+                let request = self
+                    .http
+                    .get(self.url("/time/stream"))
+                    .query(&[("timezone", timezone), ("interval_ms", interval_ms)]);
+                // The body is open-ended, so only the wait for the headers
+                // is timed.
+                let response =
+                    tokio::time::timeout(self.timeout, self.headers(request, request_id).send())
+                        .await
+                        .map_err(|_| Error::Timeout)??;
+                if response.status().is_success() {
+                    Ok(response)
+                } else {
+                    Err(problem(response).await)
+                }
+            })
+            .await?;
+        Ok(stream::time_events(response))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{V1_PREFIX}{path}", self.base_url)
+    }
+
+    fn headers(&self, request: RequestBuilder, request_id: &str) -> RequestBuilder {
+        let request = request.header(REQUEST_ID_HEADER, request_id);
+        match &self.api_key {
+            Some(api_key) => request.header(API_KEY_HEADER, api_key),
+            None => request,
+        }
+    }
+
+    /// Sends the request `build` makes, retrying it as the policy allows,
+    /// and decodes the answer.
+    async fn call<T, F>(&self, build: F) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let request_id = Uuid::new_v4().to_string();
+        let (build, request_id) = (&build, request_id.as_str());
+        self.retry
+            .run(move || async move {
+                let response = self
+                    .headers(build(&self.http), request_id)
+                    .timeout(self.timeout)
+                    .header(REQUEST_TIMEOUT_HEADER, format_request_timeout(self.timeout))
+                    .send()
+                    .await?;
+                decode(response).await
+            })
+            .await
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(problem(response).await);
+    }
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| Error::Decode {
+        status: status.as_u16(),
+        detail: e.to_string(),
+    })
+}
+
+/// The error an unsuccessful response stands for.
+async fn problem(response: reqwest::Response) -> Error {
+    let status = response.status().as_u16();
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(e) => return Error::Http(e),
+    };
+    match serde_json::from_slice::<ErrorResponse>(&body) {
+        Ok(problem) => Error::Api(problem),
+        Err(_) => Error::Decode {
+            status,
+            detail: String::from_utf8_lossy(&body)
+                .chars()
+                .take(MAX_DETAIL_CHARS)
+                .collect(),
+        },
+    }
+}
@@ -0,0 +1,91 @@
+//! Reading `/time/stream`'s Server-Sent Events as times.
+
+use common::TimeResponse;
+use futures_util::stream::{self, BoxStream, StreamExt};
+
+use crate::Error;
+
+/// Times from a subscription, ending when the service closes the stream.
+pub type TimeStream = BoxStream<'static, Result<TimeResponse, Error>>;
+
+struct Events {
+    response: reqwest::Response,
+    received: String,
+    done: bool,
+}
+
+/// Decodes the `time` events of an open `/time/stream` response. A cut-off
+/// connection ends the stream with its error.
+pub(crate) fn time_events(response: reqwest::Response) -> TimeStream {
+    let events = Events {
+        response,
+        received: String::new(),
+        done: false,
+    };
+    stream::unfold(events, |mut events| async move {
+        loop {
+            if events.done {
+                return None;
+            }
+            if let Some(data) = take_time_event(&mut events.received) {
+                let time = serde_json::from_str(&data).map_err(|e| Error::Decode {
+                    status: events.response.status().as_u16(),
+                    detail: format!("unreadable time event: {e}"),
+                });
+                return Some((time, events));
+            }
+            match events.response.chunk().await {
+                Ok(Some(chunk)) => events.received.push_str(&String::from_utf8_lossy(&chunk)),
+                Ok(None) => return None,
+                Err(e) => {
+                    events.done = true;
+                    return Some((Err(Error::Http(e)), events));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Removes complete events from the front of `received` up to and including
+/// the first `time` event, returning its data. Comments such as keep-alives
+/// and other events are dropped.
+fn take_time_event(received: &mut String) -> Option<String> {
+    loop {
+        let end = received.find("\n\n")?;
+        let event: String = received.drain(..end + 2).collect();
+        let mut name = "message";
+        let mut data = Vec::new();
+        for line in event.lines() {
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => name = value,
+                "data" => data.push(value),
+                _ => {}
+            }
+        }
+        if name == "time" && !data.is_empty() {
+            return Some(data.join("\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_time_events_and_skips_the_rest() {
+        let mut received =
+            ":\n\nevent: time\ndata: {\"a\":1}\n\nevent: other\ndata: x\n\nevent: time\ndata:{\"b\"\ndata: :2}\n\nevent: ti"
+                .to_string();
+        assert_eq!(take_time_event(&mut received).as_deref(), Some("{\"a\":1}"));
+        assert_eq!(
+            take_time_event(&mut received).as_deref(),
+            Some("{\"b\"\n:2}")
+        );
+        assert_eq!(take_time_event(&mut received), None);
+        assert_eq!(received, "event: ti");
+    }
+}
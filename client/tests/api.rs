@@ -0,0 +1,144 @@
+//! The client against API2 served on a local port, and against mocks for
+//! the failures API2 does not produce on demand.
+
+use api2::{app, AppState};
+use axum::{http::HeaderMap, http::StatusCode, routing::get, Router};
+use common::config::Config;
+use common::cors::CorsPolicy;
+use common::REQUEST_ID_HEADER;
+use futures_util::StreamExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time_service_client::{BatchTimeItem, Error, ErrorCode, RetryPolicy, TimeClient};
+
+/// Serves `router` on a local port and returns its URL.
+async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    url
+}
+
+async fn api2() -> TimeClient {
+    let router = app(
+        AppState::from_config(&Config::default()),
+        &CorsPolicy::default(),
+    );
+    TimeClient::new(serve(router).await)
+}
+
+#[tokio::test]
+async fn calls_the_time_service() {
+    let client = api2().await;
+
+    let time = client.get_time("Asia/Bangkok").await.unwrap();
+    assert_eq!(time.timezone, "Asia/Bangkok");
+    assert_eq!(time.utc_offset_seconds, Some(7 * 3600));
+
+    let converted = client
+        .convert("2025-01-01T00:00:00Z", "UTC", "Asia/Tokyo")
+        .await
+        .unwrap();
+    assert_eq!(converted.epoch_seconds, 1_735_689_600);
+    assert!(converted.to.timestamp.starts_with("2025-01-01T09:00:00"));
+
+    let batch = client.batch(["UTC", "Mars/Olympus"]).await.unwrap();
+    assert!(matches!(&batch.items[0], BatchTimeItem::Time(time) if time.timezone == "UTC"));
+    assert_eq!(batch.errors[0].timezone, "Mars/Olympus");
+
+    let times = client
+        .subscribe("Asia/Tokyo", Duration::from_millis(10))
+        .await
+        .unwrap();
+    let times: Vec<_> = times.take(3).collect().await;
+    assert_eq!(times.len(), 3);
+    assert!(times
+        .iter()
+        .all(|time| time.as_ref().unwrap().timezone == "Asia/Tokyo"));
+}
+
+#[tokio::test]
+async fn reports_problems_with_the_servers_error_codes() {
+    let client = api2().await;
+
+    let error = client.get_time("Mars/Olympus").await.unwrap_err();
+    assert_eq!(error.code(), ErrorCode::InvalidTimezone);
+    assert_eq!(error.status(), Some(400));
+    let Error::Api(problem) = error else {
+        panic!("expected a problem, got {error:?}");
+    };
+    assert!(problem.error.contains("Mars/Olympus"));
+
+    let error = match client
+        .subscribe("Mars/Olympus", Duration::from_secs(1))
+        .await
+    {
+        Ok(_) => panic!("subscribed to an unknown timezone"),
+        Err(error) => error,
+    };
+    assert_eq!(error.code(), ErrorCode::InvalidTimezone);
+
+    let unreachable = TimeClient::new("http://127.0.0.1:1").with_retry_policy(RetryPolicy::none());
+    let error = unreachable.get_time("UTC").await.unwrap_err();
+    assert_eq!(error.code(), ErrorCode::UpstreamUnavailable);
+}
+
+#[tokio::test]
+async fn retries_server_errors_with_the_same_request_id() {
+    let request_ids = Arc::new(Mutex::new(Vec::new()));
+    let seen = request_ids.clone();
+    let router = Router::new()
+        .route(
+            "/v1/time",
+            get(move |headers: HeaderMap| {
+                let seen = seen.clone();
+                async move {
+                    let mut seen = seen.lock().unwrap();
+                    seen.push(headers[REQUEST_ID_HEADER].to_str().unwrap().to_string());
+                    if seen.len() < 3 {
+                        return (
+                            StatusCode::BAD_GATEWAY,
+                            "<html>Bad gateway</html>".to_string(),
+                        );
+                    }
+                    let time = serde_json::json!({
+                        "timestamp": "2025-01-01T00:00:00Z",
+                        "timezone": "UTC",
+                        "request_id": seen[0],
+                        "source": "mock",
+                    });
+                    (StatusCode::OK, time.to_string())
+                }
+            }),
+        )
+        .route(
+            "/v1/time/convert",
+            get(|| async { (StatusCode::BAD_GATEWAY, "<html>Bad gateway</html>") }),
+        );
+    let url = serve(router).await;
+    let retry = RetryPolicy {
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+    };
+
+    let time = TimeClient::new(url.as_str())
+        .with_retry_policy(retry)
+        .get_time("UTC")
+        .await
+        .unwrap();
+    assert_eq!(time.source, "mock");
+    let request_ids = request_ids.lock().unwrap().clone();
+    assert_eq!(request_ids.len(), 3);
+    assert!(request_ids.iter().all(|id| *id == request_ids[0]));
+
+    let error = TimeClient::new(url)
+        .with_retry_policy(RetryPolicy::none())
+        .convert("2025-01-01T00:00:00Z", "UTC", "UTC")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::Decode { status: 502, .. }),
+        "{error:?}"
+    );
+    assert_eq!(error.code(), ErrorCode::UpstreamBadResponse);
+}
@@ -0,0 +1,783 @@
+//! Typed client for the time provider's (API2's) HTTP API.
+//!
+//! Each method pairs an endpoint's path and parameters with the body it
+//! answers with, so a schema change in either service fails to compile
+//! instead of failing at runtime. The client only builds requests: API1
+//! sends them through its own retries, failover and circuit breaker.
+//!
+//! The v1 endpoints are called by their unversioned paths, which API2
+//! versions from before `/v1` also serve.
+//!
+//! With `API2_UNIX_SOCKET` set, [`Api2Client::send`] carries every call over
+//! that socket instead of TCP, for an API2 sidecar that does not listen on a
+//! port. The API2 URL then only supplies the `Host` header.
+
+use reqwest::{Method, RequestBuilder};
+use serde::Serialize;
+use std::fmt;
+use std::marker::PhantomData;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::auth::API_KEY_HEADER;
+use crate::config::Config;
+use crate::limits::{format_request_timeout, REQUEST_TIMEOUT_HEADER};
+use crate::otel;
+use crate::tenants::TENANT_HEADER;
+use crate::trace_context::{TraceContext, TRACEPARENT_HEADER, TRACESTATE_HEADER};
+use crate::versioning::V2_PREFIX;
+use crate::{
+    BatchTimeRequest, BatchTimeResponse, CreateTimerRequest, CronRequest, CronResponse,
+    TimeConvertResponse, TimeDiffResponse, TimeQuery, TimeRequest, TimeResponse, TimeResponseV2,
+    TimerResponse, TimezoneList, REQUEST_ID_HEADER,
+};
+
+/// Correlation headers sent with every call made on behalf of one request.
+#[derive(Debug, Clone, Copy)]
+pub struct CallContext<'a> {
+    pub request_id: &'a str,
+    /// The caller's span, sent as the parent in `traceparent`.
+    pub trace: &'a TraceContext,
+    /// The inbound `tracestate`, passed through unchanged.
+    pub tracestate: Option<&'a str>,
+    /// The tenant the request belongs to, sent as `X-Tenant-ID`.
+    pub tenant: Option<&'a str>,
+    /// The key the tenant's request was made with, sent as `X-Api-Key` so
+    /// API2 accepts the tenant too.
+    pub tenant_key: Option<&'a str>,
+}
+
+/// A request to API2 whose successful response body decodes as `T`.
+#[must_use]
+pub struct Api2Request<T> {
+    builder: RequestBuilder,
+    body: PhantomData<fn() -> T>,
+}
+
+impl<T> Api2Request<T> {
+    fn new(builder: RequestBuilder) -> Self {
+        Api2Request {
+            builder,
+            body: PhantomData,
+        }
+    }
+
+    /// Gives up on the call after `timeout`, and tells API2 so in
+    /// `X-Request-Timeout` for it to stop working on the call at the same
+    /// time.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Api2Request::new(
+            self.builder
+                .timeout(timeout)
+                .header(REQUEST_TIMEOUT_HEADER, format_request_timeout(timeout)),
+        )
+    }
+
+    pub fn into_builder(self) -> RequestBuilder {
+        self.builder
+    }
+}
+
+#[derive(Serialize)]
+struct TimeDiffParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct TimeConvertParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct TimeStreamParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct TimezonesParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prefix: Option<&'a str>,
+}
+
+/// Time allowed to establish a connection when `API2_CONNECT_TIMEOUT_MS` is unset.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Idle connections kept per API2 instance when `API2_POOL_MAX_IDLE_PER_HOST`
+/// is unset.
+pub const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 32;
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Why a call to API2 got no response.
+#[derive(Debug)]
+pub enum SendError {
+    Http(reqwest::Error),
+    /// The call failed on the Unix socket.
+    #[cfg(unix)]
+    Unix(hyper014::Error),
+    /// The call's timeout passed on the Unix socket.
+    Timeout,
+    /// Streamed bodies are only sent over TCP.
+    StreamedBody,
+}
+
+impl SendError {
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            SendError::Http(e) => e.is_timeout(),
+            SendError::Timeout => true,
+            _ => false,
+        }
+    }
+
+    /// Whether no connection could be made, so API2 never saw the call.
+    pub fn is_connect(&self) -> bool {
+        match self {
+            SendError::Http(e) => e.is_connect(),
+            #[cfg(unix)]
+            SendError::Unix(e) => e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Http(e) => e.fmt(f),
+            #[cfg(unix)]
+            SendError::Unix(e) => write!(f, "error sending request over Unix socket: {e}"),
+            SendError::Timeout => f.write_str("operation timed out"),
+            SendError::StreamedBody => {
+                f.write_str("streamed request bodies cannot be sent over a Unix socket")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// A pooled client for API2's Unix socket.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+struct UnixClient {
+    connector: crate::unix::UnixConnector,
+    http: hyper014::Client<crate::unix::UnixConnector>,
+}
+
+/// Builds calls to any API2 instance; `base_url` selects the instance.
+#[derive(Debug, Clone, Default)]
+pub struct Api2Client {
+    http: reqwest::Client,
+    /// TLS settings from the config, kept for connections made outside
+    /// `http`, such as WebSocket tunnels.
+    tls: Option<native_tls::TlsConnector>,
+    /// Set from `API2_UNIX_SOCKET`; carries every call in place of `http`.
+    #[cfg(unix)]
+    unix: Option<UnixClient>,
+}
+
+impl Api2Client {
+    /// Wraps a pooled HTTP client, which should be reused for all calls.
+    pub fn new(http: reqwest::Client) -> Self {
+        Api2Client {
+            http,
+            ..Api2Client::default()
+        }
+    }
+
+    /// Sends every call over the Unix socket at `path` instead of TCP.
+    #[cfg(unix)]
+    pub fn with_unix_socket(self, path: impl Into<PathBuf>) -> Self {
+        self.with_unix_client(hyper014::Client::builder(), path.into())
+    }
+
+    #[cfg(unix)]
+    fn with_unix_client(self, builder: hyper014::client::Builder, path: PathBuf) -> Self {
+        let connector = crate::unix::UnixConnector::new(path);
+        Api2Client {
+            unix: Some(UnixClient {
+                http: builder.build(connector.clone()),
+                connector,
+            }),
+            ..self
+        }
+    }
+
+    /// The socket calls go over, if not TCP.
+    #[cfg(unix)]
+    pub fn unix_socket(&self) -> Option<&Path> {
+        self.unix.as_ref().map(|unix| unix.connector.path())
+    }
+
+    /// Builds the pooled client from the `API2_CONNECT_TIMEOUT_MS`,
+    /// `API2_POOL_*` and `API2_TCP_KEEPALIVE_SECS` settings. The overall
+    /// per-call deadline is applied by the caller with
+    /// [`Api2Request::timeout`]. `https` URLs use the CA and client
+    /// certificate from [`crate::tls::connector_from_config`], if any.
+    pub fn from_config(config: &Config) -> Self {
+        let tls = crate::tls::connector_from_config(config);
+        let mut builder = reqwest::Client::builder();
+        if let Some(connector) = &tls {
+            builder = builder.use_preconfigured_tls(connector.clone());
+        }
+        let http = builder
+            .connect_timeout(
+                config
+                    .connect_timeout_ms
+                    .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_millis),
+            )
+            .pool_max_idle_per_host(
+                config
+                    .pool_max_idle_per_host
+                    .unwrap_or(DEFAULT_POOL_MAX_IDLE_PER_HOST),
+            )
+            .pool_idle_timeout(
+                config
+                    .pool_idle_timeout_secs
+                    .map_or(DEFAULT_POOL_IDLE_TIMEOUT, Duration::from_secs),
+            )
+            .tcp_keepalive(
+                config
+                    .tcp_keepalive_secs
+                    .map_or(DEFAULT_TCP_KEEPALIVE, Duration::from_secs),
+            )
+            .build()
+            .expect("HTTP client settings are valid");
+        let client = Api2Client {
+            http,
+            tls,
+            ..Api2Client::default()
+        };
+        #[cfg(unix)]
+        if let Some(path) = &config.api2_unix_socket {
+            let mut builder = hyper014::Client::builder();
+            builder
+                .pool_max_idle_per_host(
+                    config
+                        .pool_max_idle_per_host
+                        .unwrap_or(DEFAULT_POOL_MAX_IDLE_PER_HOST),
+                )
+                .pool_idle_timeout(
+                    config
+                        .pool_idle_timeout_secs
+                        .map_or(DEFAULT_POOL_IDLE_TIMEOUT, Duration::from_secs),
+                );
+            return client.with_unix_client(builder, PathBuf::from(path));
+        }
+        client
+    }
+
+    /// The connector for `https` API2 URLs reached without `reqwest`.
+    pub fn tls_connector(&self) -> Result<native_tls::TlsConnector, native_tls::Error> {
+        match &self.tls {
+            Some(connector) => Ok(connector.clone()),
+            None => native_tls::TlsConnector::new(),
+        }
+    }
+
+    /// Sends a call built by this client inside a tracing span, over the
+    /// Unix socket when one is set.
+    pub async fn send(&self, request: RequestBuilder) -> Result<reqwest::Response, SendError> {
+        let request = request.build().map_err(SendError::Http)?;
+        otel::send(request, |request| self.execute(request)).await
+    }
+
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response, SendError> {
+        #[cfg(unix)]
+        if let Some(unix) = &self.unix {
+            return execute_unix(unix, request).await;
+        }
+        self.http.execute(request).await.map_err(SendError::Http)
+    }
+
+    fn request(
+        &self,
+        base_url: &str,
+        method: Method,
+        path: &str,
+        context: &CallContext,
+    ) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{base_url}{path}"))
+            .header(REQUEST_ID_HEADER, context.request_id)
+            .header(TRACEPARENT_HEADER, context.trace.traceparent());
+        let request = match context.tracestate {
+            Some(tracestate) => request.header(TRACESTATE_HEADER, tracestate),
+            None => request,
+        };
+        let request = match context.tenant {
+            Some(tenant) => request.header(TENANT_HEADER, tenant),
+            None => request,
+        };
+        match context.tenant_key {
+            Some(key) => request.header(API_KEY_HEADER, key),
+            None => request,
+        }
+    }
+
+    /// `GET /time`: the current time in `timezone`. The request ID travels
+    /// only in the `X-Request-ID` header, not the legacy query parameter.
+    pub fn time(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        timezone: &str,
+        format: Option<&str>,
+        locale: Option<&str>,
+    ) -> Api2Request<TimeResponse> {
+        let query = TimeQuery {
+            timezone: Some(timezone.to_string()),
+            request_id: None,
+            format: format.map(str::to_string),
+            locale: locale.map(str::to_string),
+        };
+        Api2Request::new(
+            self.request(base_url, Method::GET, "/time", context)
+                .query(&query),
+        )
+    }
+
+    /// `POST /time`: the time described by `request`, with the options only
+    /// the JSON body supports.
+    pub fn time_post(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        request: &TimeRequest,
+    ) -> Api2Request<TimeResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::POST, "/time", context)
+                .json(request),
+        )
+    }
+
+    /// `GET /v2/time`: as [`Api2Client::time`], with daylight saving details.
+    pub fn time_v2(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        timezone: &str,
+        format: Option<&str>,
+        locale: Option<&str>,
+    ) -> Api2Request<TimeResponseV2> {
+        let query = TimeQuery {
+            timezone: Some(timezone.to_string()),
+            request_id: None,
+            format: format.map(str::to_string),
+            locale: locale.map(str::to_string),
+        };
+        let path = format!("{V2_PREFIX}/time");
+        Api2Request::new(
+            self.request(base_url, Method::GET, &path, context)
+                .query(&query),
+        )
+    }
+
+    /// `POST /v2/time`: as [`Api2Client::time_post`], with daylight saving
+    /// details.
+    pub fn time_post_v2(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        request: &TimeRequest,
+    ) -> Api2Request<TimeResponseV2> {
+        let path = format!("{V2_PREFIX}/time");
+        Api2Request::new(
+            self.request(base_url, Method::POST, &path, context)
+                .json(request),
+        )
+    }
+
+    /// `POST /time/batch`: the current time in several timezones.
+    pub fn time_batch(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        batch: &BatchTimeRequest,
+    ) -> Api2Request<BatchTimeResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::POST, "/time/batch", context)
+                .json(batch),
+        )
+    }
+
+    /// `GET /time/diff`: the current offset of `to` relative to `from`.
+    pub fn time_diff(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Api2Request<TimeDiffResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::GET, "/time/diff", context)
+                .query(&TimeDiffParams { from, to }),
+        )
+    }
+
+    /// `GET /time/convert`: `timestamp` as seen in the `from` and `to` zones.
+    pub fn time_convert(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        timestamp: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Api2Request<TimeConvertResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::GET, "/time/convert", context)
+                .query(&TimeConvertParams {
+                    timestamp,
+                    from,
+                    to,
+                }),
+        )
+    }
+
+    /// `GET /timezones`: supported zone names, optionally filtered by prefix.
+    pub fn timezones(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        prefix: Option<&str>,
+    ) -> Api2Request<TimezoneList> {
+        Api2Request::new(
+            self.request(base_url, Method::GET, "/timezones", context)
+                .query(&TimezonesParams { prefix }),
+        )
+    }
+
+    /// `GET /time/stream`: a Server-Sent Events stream, read as raw chunks
+    /// rather than decoded.
+    pub fn time_stream(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        timezone: Option<&str>,
+        interval_ms: Option<u64>,
+    ) -> RequestBuilder {
+        self.request(base_url, Method::GET, "/time/stream", context)
+            .query(&TimeStreamParams {
+                timezone,
+                interval_ms,
+            })
+    }
+
+    /// `POST /cron/next`: the next fire times of a cron expression.
+    pub fn cron_next(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        request: &CronRequest,
+    ) -> Api2Request<CronResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::POST, "/cron/next", context)
+                .json(request),
+        )
+    }
+
+    /// `POST /timer`: starts a timer on the instance at `base_url`.
+    pub fn timer_create(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        request: &CreateTimerRequest,
+    ) -> Api2Request<TimerResponse> {
+        Api2Request::new(
+            self.request(base_url, Method::POST, "/timer", context)
+                .json(request),
+        )
+    }
+
+    /// `GET /timer/{id}`: a timer's elapsed time. Timers live on the
+    /// instance that created them, so `base_url` must be that instance.
+    pub fn timer(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        id: &Uuid,
+    ) -> Api2Request<TimerResponse> {
+        let path = format!("/timer/{id}");
+        Api2Request::new(self.request(base_url, Method::GET, &path, context))
+    }
+
+    /// `DELETE /timer/{id}`: stops a timer, likewise on the instance that
+    /// created it.
+    pub fn timer_stop(
+        &self,
+        base_url: &str,
+        context: &CallContext,
+        id: &Uuid,
+    ) -> Api2Request<TimerResponse> {
+        let path = format!("/timer/{id}");
+        Api2Request::new(self.request(base_url, Method::DELETE, &path, context))
+    }
+
+    /// `GET /health`, without correlation headers since probes belong to no
+    /// request.
+    pub fn health(&self, base_url: &str) -> RequestBuilder {
+        self.http.get(format!("{base_url}/health"))
+    }
+
+    /// `GET /health/ready`, likewise without correlation headers.
+    pub fn ready(&self, base_url: &str) -> RequestBuilder {
+        self.http
+            .get(format!("{base_url}{}", crate::health::READY_PATH))
+    }
+}
+
+/// Sends `request` over the socket, bounded by its timeout as `reqwest`
+/// would. The timeout covers the response head; the body is read after.
+#[cfg(unix)]
+async fn execute_unix(
+    unix: &UnixClient,
+    request: reqwest::Request,
+) -> Result<reqwest::Response, SendError> {
+    let body = match request.body() {
+        Some(body) => {
+            hyper014::Body::from(body.as_bytes().ok_or(SendError::StreamedBody)?.to_vec())
+        }
+        None => hyper014::Body::empty(),
+    };
+    let mut outgoing = hyper014::Request::new(body);
+    *outgoing.method_mut() = request.method().clone();
+    *outgoing.uri_mut() = request
+        .url()
+        .as_str()
+        .parse()
+        .expect("reqwest URLs are valid URIs");
+    *outgoing.headers_mut() = request.headers().clone();
+
+    let response = unix.http.request(outgoing);
+    let response = match request.timeout() {
+        Some(timeout) => tokio::time::timeout(*timeout, response)
+            .await
+            .map_err(|_| SendError::Timeout)?,
+        None => response.await,
+    };
+    response
+        .map(reqwest::Response::from)
+        .map_err(SendError::Unix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{ConnectInfo, Query};
+    use axum::http::HeaderMap;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn sends_correlation_headers_and_typed_queries() {
+        let base_url = serve(Router::new().route(
+            "/time",
+            get(
+                |headers: HeaderMap, Query(query): Query<HashMap<String, String>>| async move {
+                    let header = |name: &str| headers[name].to_str().unwrap().to_string();
+                    Json(TimeResponse {
+                        timestamp: header(TRACESTATE_HEADER),
+                        timezone: query["timezone"].clone(),
+                        request_id: header(REQUEST_ID_HEADER),
+                        source: header(TRACEPARENT_HEADER),
+                        utc_offset_seconds: Some(query.len() as i32),
+                        utc_offset_label: query.get("format").cloned(),
+                        display: headers.get(TENANT_HEADER).map(|_| header(TENANT_HEADER)),
+                        format: headers
+                            .get(REQUEST_TIMEOUT_HEADER)
+                            .map(|_| header(REQUEST_TIMEOUT_HEADER)),
+                        degraded: None,
+                        clock_offset_ms: None,
+                    })
+                },
+            ),
+        ))
+        .await;
+
+        let trace = TraceContext::new_root();
+        let context = CallContext {
+            request_id: "req-1",
+            trace: &trace,
+            tracestate: Some("vendor=1"),
+            tenant: Some("team-a"),
+            tenant_key: None,
+        };
+        let request = Api2Client::default()
+            .time(&base_url, &context, "Asia/Tokyo", Some("unix"), None)
+            .timeout(Duration::from_millis(750));
+        let response: TimeResponse = request
+            .into_builder()
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(response.timezone, "Asia/Tokyo");
+        assert_eq!(response.request_id, "req-1");
+        assert_eq!(response.source, trace.traceparent());
+        assert_eq!(response.timestamp, "vendor=1");
+        assert_eq!(response.utc_offset_label.as_deref(), Some("unix"));
+        assert_eq!(response.display.as_deref(), Some("team-a"));
+        assert_eq!(response.format.as_deref(), Some("750ms"));
+        // Only timezone and format: the request ID is not in the URL.
+        assert_eq!(response.utc_offset_seconds, Some(2));
+    }
+
+    #[tokio::test]
+    async fn omits_unset_parameters() {
+        let base_url = serve(Router::new().route(
+            "/timezones",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                Json(TimezoneList {
+                    count: query.len(),
+                    timezones: query.into_keys().collect(),
+                    offsets: Vec::new(),
+                })
+            }),
+        ))
+        .await;
+
+        let trace = TraceContext::new_root();
+        let context = CallContext {
+            request_id: "req-2",
+            trace: &trace,
+            tracestate: None,
+            tenant: None,
+            tenant_key: None,
+        };
+        let client = Api2Client::default();
+        for (prefix, expected) in [(None, 0), (Some("Asia/"), 1)] {
+            let list: TimezoneList = client
+                .timezones(&base_url, &context, prefix)
+                .into_builder()
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(list.count, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_pooled_connections_unless_disabled() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let router =
+            Router::new().route(
+                "/health",
+                get(
+                    |ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>| async move {
+                        peer.to_string()
+                    },
+                ),
+            );
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
+            .unwrap()
+        });
+
+        let peers = |client: Api2Client| {
+            let base_url = base_url.clone();
+            async move {
+                let mut peers = Vec::new();
+                for _ in 0..2 {
+                    let response = client.health(&base_url).send().await.unwrap();
+                    peers.push(response.text().await.unwrap());
+                }
+                peers
+            }
+        };
+        let pooled = peers(Api2Client::from_config(&Config::default())).await;
+        assert_eq!(pooled[0], pooled[1]);
+        let unpooled = peers(Api2Client::from_config(&Config {
+            pool_max_idle_per_host: Some(0),
+            ..Config::default()
+        }))
+        .await;
+        assert_ne!(unpooled[0], unpooled[1]);
+    }
+
+    #[tokio::test]
+    async fn sends_calls_over_a_unix_socket() {
+        let directory = std::env::temp_dir().join(format!("api2-client-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let path = directory.join("api2.sock");
+        let client = Api2Client::from_config(&Config {
+            api2_unix_socket: Some(path.to_string_lossy().into_owned()),
+            ..Config::default()
+        });
+        assert_eq!(client.unix_socket(), Some(path.as_path()));
+
+        // Nothing listens yet: the failure is a connection error, which API1
+        // fails over on.
+        let error = client.send(client.health("http://api2")).await.unwrap_err();
+        assert!(error.is_connect(), "{error}");
+
+        let listener = crate::unix::bind(&path, 0o600).unwrap();
+        let router =
+            Router::new()
+                .route(
+                    "/health",
+                    get(|headers: HeaderMap| async move {
+                        headers["host"].to_str().unwrap().to_string()
+                    }),
+                )
+                .route(
+                    "/timezones",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        "late"
+                    }),
+                );
+        tokio::spawn(crate::unix::serve(listener, router, std::future::pending()));
+
+        let response = client.send(client.health("http://api2")).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "api2");
+
+        let trace = TraceContext::new_root();
+        let context = CallContext {
+            request_id: "req-3",
+            trace: &trace,
+            tracestate: None,
+            tenant: None,
+            tenant_key: None,
+        };
+        let request = client
+            .timezones("http://api2", &context, None)
+            .timeout(Duration::from_millis(50));
+        let error = client.send(request.into_builder()).await.unwrap_err();
+        assert!(error.is_timeout(), "{error}");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,600 @@
+//! Append-only audit log with one JSON line per completed request.
+//!
+//! Records are handed to a dedicated writer thread over a bounded channel, so
+//! request handling never waits on file I/O. The writer starts a new file
+//! each UTC day: `AUDIT_LOG_PATH=/var/log/audit.jsonl` writes to
+//! `/var/log/audit-2025-07-01.jsonl`, then `/var/log/audit-2025-07-02.jsonl`.
+//! With `audit_retention_days` set, it deletes older daily files when it
+//! starts and whenever it starts a new one.
+//!
+//! [`AuditLog::query`] reads one day's records back, newest first, for each
+//! service's `/admin/audit` endpoint.
+
+use axum::extract::{ConnectInfo, Query};
+use axum::http::{Request, Response};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::tenants::TENANT_HEADER;
+use crate::REQUEST_ID_HEADER;
+
+pub const ADMIN_PATH: &str = "/admin/audit";
+
+/// Records buffered between request handling and the writer thread.
+const CHANNEL_CAPACITY: usize = 1024;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+/// Longest `audit_retention_days` accepted, a century.
+pub const MAX_RETENTION_DAYS: u64 = 36_500;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub request_id: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub query_string: Option<String>,
+    pub client_ip: Option<String>,
+    /// The `timezone` query parameter, when there is one.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// The tenant the request belongs to, when there is one.
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub status_code: u16,
+    /// Time until the response headers were ready; streamed bodies may run longer.
+    pub duration_ms: f64,
+    pub timestamp: String,
+}
+
+/// Handle to the audit writer; cheap to clone.
+#[derive(Clone)]
+pub struct AuditLog {
+    sender: mpsc::Sender<AuditRecord>,
+    base_path: Arc<PathBuf>,
+}
+
+/// Query parameters of `GET /admin/audit`.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    /// UTC day to read, `YYYY-MM-DD`; default today.
+    pub date: Option<String>,
+    pub timezone: Option<String>,
+    pub tenant: Option<String>,
+    pub status: Option<u16>,
+    pub client_ip: Option<String>,
+    pub request_id: Option<String>,
+    /// Default 100, at most 1000.
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// One page of a day's matching records, newest first.
+#[derive(Debug, Serialize)]
+pub struct AuditPage {
+    pub date: String,
+    /// Matching records that day, across all pages.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    /// Offset of the next page, `None` on the last.
+    pub next_offset: Option<usize>,
+    pub records: Vec<AuditRecord>,
+}
+
+#[derive(Debug)]
+pub enum AuditQueryError {
+    Invalid(String),
+    Read(String),
+}
+
+impl fmt::Display for AuditQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditQueryError::Invalid(message) => f.write_str(message),
+            AuditQueryError::Read(error) => write!(f, "Failed to read the audit log: {error}"),
+        }
+    }
+}
+
+impl AuditLog {
+    /// Starts a writer for `audit_log_path`, keeping `audit_retention_days`
+    /// of files, or returns `None` when the path is unset.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let path = config.audit_log_path.as_deref()?;
+        info!(
+            path = %path,
+            retention_days = config.audit_retention_days,
+            "Audit logging enabled"
+        );
+        Some(AuditLog::spawn(
+            PathBuf::from(path),
+            config.audit_retention_days,
+        ))
+    }
+
+    /// Starts the writer thread for files derived from `base_path`, deleting
+    /// those more than `retention_days` old when set.
+    pub fn spawn(base_path: PathBuf, retention_days: Option<u64>) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let writer_path = base_path.clone();
+        std::thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || write_records(&writer_path, retention_days, receiver))
+            .expect("Failed to start audit log writer");
+        AuditLog {
+            sender,
+            base_path: Arc::new(base_path),
+        }
+    }
+
+    /// Reads the page of records `query` asks for, off the async runtime.
+    pub async fn query(&self, query: AuditQuery) -> Result<AuditPage, AuditQueryError> {
+        let date = match query.date.as_deref() {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+                AuditQueryError::Invalid(format!("Invalid date: {date} (expected YYYY-MM-DD)"))
+            })?,
+            None => Utc::now().date_naive(),
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(AuditQueryError::Invalid(format!(
+                "limit must be between 1 and {MAX_PAGE_SIZE}"
+            )));
+        }
+        let path = dated_path(&self.base_path, date);
+        let records = tokio::task::spawn_blocking(move || read_records(&path))
+            .await
+            .map_err(|e| AuditQueryError::Read(e.to_string()))?
+            .map_err(|e| AuditQueryError::Read(e.to_string()))?;
+        Ok(page(records, &query, date, limit))
+    }
+
+    /// Queues `record`, dropping it with a warning when the writer is behind.
+    pub fn record(&self, record: AuditRecord) {
+        match self.sender.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(record)) => warn!(
+                request_id = ?record.request_id,
+                "Audit log channel full; dropping record"
+            ),
+            Err(TrySendError::Closed(_)) => warn!("Audit log writer stopped; dropping record"),
+        }
+    }
+}
+
+/// Writes records until every [`AuditLog`] handle has been dropped.
+fn write_records(
+    base_path: &Path,
+    retention_days: Option<u64>,
+    mut receiver: mpsc::Receiver<AuditRecord>,
+) {
+    if let Some(days) = retention_days {
+        prune(base_path, days, Utc::now().date_naive());
+    }
+    let mut current: Option<(NaiveDate, File)> = None;
+    while let Some(record) = receiver.blocking_recv() {
+        let today = Utc::now().date_naive();
+        if current.as_ref().map(|(date, _)| *date) != Some(today) {
+            if let (Some(days), Some(_)) = (retention_days, &current) {
+                prune(base_path, days, today);
+            }
+            let path = dated_path(base_path, today);
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => current = Some((today, file)),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Failed to open audit log");
+                    current = None;
+                    continue;
+                }
+            }
+        }
+        let Some((_, file)) = current.as_mut() else {
+            continue;
+        };
+        let mut line = serde_json::to_vec(&record).expect("audit records serialize");
+        line.push(b'\n');
+        if let Err(e) = file.write_all(&line) {
+            warn!(error = %e, "Failed to write audit record");
+        }
+    }
+}
+
+/// Inserts the date before the extension: `audit.jsonl` -> `audit-2025-07-01.jsonl`.
+fn dated_path(base_path: &Path, date: NaiveDate) -> PathBuf {
+    let (stem, extension) = name_parts(base_path);
+    let name = match extension {
+        Some(extension) => format!("{stem}-{date}.{extension}"),
+        None => format!("{stem}-{date}"),
+    };
+    base_path.with_file_name(name)
+}
+
+fn name_parts(base_path: &Path) -> (String, Option<String>) {
+    let stem = base_path
+        .file_stem()
+        .map_or_else(|| "audit".into(), |stem| stem.to_string_lossy());
+    let extension = base_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned());
+    (stem.into_owned(), extension)
+}
+
+/// The date of a daily file derived from `base_path`, if `name` is one.
+fn file_date(base_path: &Path, name: &str) -> Option<NaiveDate> {
+    let (stem, extension) = name_parts(base_path);
+    let rest = name.strip_prefix(&stem)?.strip_prefix('-')?;
+    let date = match extension {
+        Some(extension) => rest.strip_suffix(&extension)?.strip_suffix('.')?,
+        None => rest,
+    };
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Deletes daily files more than `retention_days` before `today`, or none
+/// when that reaches past the earliest date there is.
+fn prune(base_path: &Path, retention_days: u64, today: NaiveDate) {
+    let Some(oldest_kept) = i64::try_from(retention_days)
+        .ok()
+        .and_then(Duration::try_days)
+        .and_then(|retention| today.checked_sub_signed(retention))
+    else {
+        return;
+    };
+    let directory = match base_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(directory = %directory.display(), error = %e, "Failed to list audit logs");
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let expired =
+            file_date(base_path, &name.to_string_lossy()).is_some_and(|date| date < oldest_kept);
+        if !expired {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => info!(path = %entry.path().display(), "Deleted expired audit log"),
+            Err(e) => warn!(
+                path = %entry.path().display(),
+                error = %e,
+                "Failed to delete expired audit log"
+            ),
+        }
+    }
+}
+
+/// Every record in a daily file, skipping unreadable lines such as one
+/// being written; a missing file has none.
+fn read_records(path: &Path) -> std::io::Result<Vec<AuditRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(record) = serde_json::from_str(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// The matching `records`, newest first, cut to one page.
+fn page(records: Vec<AuditRecord>, query: &AuditQuery, date: NaiveDate, limit: usize) -> AuditPage {
+    let matches = |wanted: &Option<String>, value: &Option<String>| {
+        wanted.is_none() || wanted.as_deref() == value.as_deref()
+    };
+    let mut records: Vec<AuditRecord> = records
+        .into_iter()
+        .rev()
+        .filter(|record| {
+            matches(&query.timezone, &record.timezone)
+                && matches(&query.tenant, &record.tenant)
+                && matches(&query.client_ip, &record.client_ip)
+                && matches(&query.request_id, &record.request_id)
+                && query
+                    .status
+                    .is_none_or(|status| status == record.status_code)
+        })
+        .collect();
+    let total = records.len();
+    let offset = query.offset.unwrap_or(0).min(total);
+    records.drain(..offset);
+    records.truncate(limit);
+    let end = offset + records.len();
+    AuditPage {
+        date: date.to_string(),
+        total,
+        offset,
+        limit,
+        next_offset: (end < total).then_some(end),
+        records,
+    }
+}
+
+/// Tower layer recording every request to an [`AuditLog`]; a no-op without one.
+///
+/// The client IP comes from `ConnectInfo<SocketAddr>` when the router is
+/// served with `into_make_service_with_connect_info`.
+#[derive(Clone)]
+pub struct AuditLayer {
+    log: Option<AuditLog>,
+}
+
+impl AuditLayer {
+    pub fn new(log: Option<AuditLog>) -> Self {
+        AuditLayer { log }
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Audit {
+            inner,
+            log: self.log.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Audit<S> {
+    inner: S,
+    log: Option<AuditLog>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Audit<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let Some(log) = self.log.clone() else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        let request_id = header_value(request.headers(), REQUEST_ID_HEADER);
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        let query_string = request.uri().query().map(str::to_string);
+        let client_ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip().to_string());
+        let timezone = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+            .ok()
+            .and_then(|Query(mut params)| params.remove("timezone"));
+        let tenant = header_value(request.headers(), TENANT_HEADER);
+        let started = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await?;
+            log.record(AuditRecord {
+                // Fall back to the ID a request-ID layer assigned on the way out.
+                request_id: request_id
+                    .or_else(|| header_value(response.headers(), REQUEST_ID_HEADER)),
+                method,
+                path,
+                query_string,
+                client_ip,
+                timezone,
+                tenant,
+                status_code: response.status().as_u16(),
+                duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+                timestamp: Utc::now().to_rfc3339(),
+            });
+            Ok(response)
+        })
+    }
+}
+
+fn header_value(headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use axum::{routing::get, Router};
+    use std::time::Duration;
+
+    #[test]
+    fn inserts_date_before_extension() {
+        let date = NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        assert_eq!(
+            dated_path(Path::new("/var/log/audit.jsonl"), date),
+            Path::new("/var/log/audit-2025-07-01.jsonl")
+        );
+        assert_eq!(
+            dated_path(Path::new("audit"), date),
+            Path::new("audit-2025-07-01")
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_one_record_per_request() {
+        let directory = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let log = AuditLog::spawn(directory.join("audit.jsonl"), None);
+        let mut router = Router::new()
+            .route("/time", get(|| async { StatusCode::ACCEPTED }))
+            .layer(AuditLayer::new(Some(log.clone())));
+
+        let mut request = Request::get("/time?timezone=Asia/Bangkok")
+            .header(REQUEST_ID_HEADER, "audit-test-1")
+            .header(TENANT_HEADER, "team-a")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 7], 51000))));
+        // Router is always ready, so poll_ready can be skipped.
+        router.call(request).await.unwrap();
+
+        let path = dated_path(&directory.join("audit.jsonl"), Utc::now().date_naive());
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = std::fs::read_to_string(&path).unwrap_or_default();
+            if contents.ends_with('\n') {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let record: serde_json::Value = serde_json::from_str(contents.trim_end()).unwrap();
+        assert_eq!(record["request_id"], "audit-test-1");
+        assert_eq!(record["method"], "GET");
+        assert_eq!(record["path"], "/time");
+        assert_eq!(record["query_string"], "timezone=Asia/Bangkok");
+        assert_eq!(record["client_ip"], "192.0.2.7");
+        assert_eq!(record["timezone"], "Asia/Bangkok");
+        assert_eq!(record["tenant"], "team-a");
+        assert_eq!(record["status_code"], 202);
+        assert!(record["duration_ms"].as_f64().unwrap() >= 0.0);
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(record["timestamp"].as_str().unwrap()).is_ok()
+        );
+
+        let page = log
+            .query(AuditQuery {
+                timezone: Some("Asia/Bangkok".to_string()),
+                tenant: Some("team-a".to_string()),
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.records[0].request_id.as_deref(), Some("audit-test-1"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    fn record(request_id: &str, status_code: u16) -> AuditRecord {
+        AuditRecord {
+            request_id: Some(request_id.to_string()),
+            method: "GET".to_string(),
+            path: "/time".to_string(),
+            query_string: None,
+            client_ip: None,
+            timezone: None,
+            tenant: None,
+            status_code,
+            duration_ms: 1.0,
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn pages_newest_first_through_matching_records() {
+        let date = NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        let records = || {
+            (1..=5)
+                .map(|i| record(&i.to_string(), if i % 2 == 0 { 500 } else { 200 }))
+                .collect::<Vec<_>>()
+        };
+        let ids = |page: &AuditPage| {
+            page.records
+                .iter()
+                .map(|record| record.request_id.clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let first = page(records(), &AuditQuery::default(), date, 2);
+        assert_eq!((first.total, first.next_offset), (5, Some(2)));
+        assert_eq!(ids(&first), ["5", "4"]);
+        let query = AuditQuery {
+            offset: Some(4),
+            ..AuditQuery::default()
+        };
+        let last = page(records(), &query, date, 2);
+        assert_eq!(
+            (ids(&last), last.next_offset),
+            (vec!["1".to_string()], None)
+        );
+
+        let query = AuditQuery {
+            status: Some(200),
+            ..AuditQuery::default()
+        };
+        assert_eq!(ids(&page(records(), &query, date, 10)), ["5", "3", "1"]);
+    }
+
+    #[test]
+    fn prunes_daily_files_past_retention() {
+        let directory = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let base = directory.join("audit.jsonl");
+        for name in [
+            "audit-2025-06-01.jsonl",
+            "audit-2025-06-24.jsonl",
+            "audit-2025-07-01.jsonl",
+            "other-2025-06-01.jsonl",
+            "audit-notes.jsonl",
+        ] {
+            std::fs::write(directory.join(name), "").unwrap();
+        }
+
+        // Retention reaching past the earliest date keeps everything.
+        prune(
+            &base,
+            u64::MAX,
+            NaiveDate::from_ymd_opt(2025, 7, 1).unwrap(),
+        );
+        prune(&base, 1 << 40, NaiveDate::from_ymd_opt(2025, 7, 1).unwrap());
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 5);
+
+        prune(&base, 7, NaiveDate::from_ymd_opt(2025, 7, 1).unwrap());
+        let mut left: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "audit-2025-06-24.jsonl",
+                "audit-2025-07-01.jsonl",
+                "audit-notes.jsonl",
+                "other-2025-06-01.jsonl"
+            ]
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
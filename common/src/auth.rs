@@ -0,0 +1,61 @@
+//! API key helpers shared by both services.
+
+/// Header carrying the client's API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Whether `provided` is one of `api_keys`. Every key is compared in full so
+/// the time taken does not reveal how much of a key matched.
+pub fn is_valid_key(api_keys: &[String], provided: &str) -> bool {
+    key_index(api_keys, provided).is_some()
+}
+
+/// Position of `provided` in `api_keys`, compared in constant time like
+/// [`is_valid_key`]. Identifies a key without holding on to its value.
+pub fn key_index(api_keys: &[String], provided: &str) -> Option<usize> {
+    api_keys
+        .iter()
+        .enumerate()
+        .fold(None, |found, (index, key)| {
+            if constant_time_eq(key.as_bytes(), provided.as_bytes()) {
+                Some(index)
+            } else {
+                found
+            }
+        })
+}
+
+/// The first four characters of `key` followed by `****`, for logging.
+pub fn redact_key(key: &str) -> String {
+    format!("{}****", key.chars().take(4).collect::<String>())
+}
+
+/// Compares two byte strings without short-circuiting on the first mismatch.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_any_configured_key() {
+        let keys = vec!["first-key".to_string(), "second-key".to_string()];
+        assert!(is_valid_key(&keys, "second-key"));
+        assert!(!is_valid_key(&keys, "second-ke"));
+        assert!(!is_valid_key(&keys, "second-kez"));
+        assert!(!is_valid_key(&[], ""));
+        assert_eq!(key_index(&keys, "second-key"), Some(1));
+        assert_eq!(key_index(&keys, "third-key"), None);
+    }
+
+    #[test]
+    fn redacts_all_but_four_characters() {
+        assert_eq!(redact_key("sk-live-123456"), "sk-l****");
+        assert_eq!(redact_key("ab"), "ab****");
+        assert_eq!(redact_key("ไทยไทย"), "ไทยไ****");
+    }
+}
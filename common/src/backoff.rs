@@ -0,0 +1,51 @@
+//! Exponential backoff with jitter, shared by API1's retries of API2 and the
+//! client library's retries of API1.
+
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Cap on each delay, unless the base delay is already longer.
+pub const MAX_DELAY: Duration = Duration::from_secs(1);
+/// Maximum relative deviation applied to each delay, to spread out retries.
+pub const JITTER: f64 = 0.1;
+
+/// Delay before retry number `attempt` (1-based): `base` doubling up to
+/// [`MAX_DELAY`], scaled by `jitter` in `[-JITTER, JITTER]`.
+pub fn backoff_delay(attempt: u32, base: Duration, jitter: f64) -> Duration {
+    let exponential = base.saturating_mul(1 << (attempt - 1).min(16));
+    exponential.min(MAX_DELAY.max(base)).mul_f64(1.0 + jitter)
+}
+
+/// Uniform random value in `[-JITTER, JITTER]`, drawn from the 62 random
+/// bits in the low half of a v4 UUID. The two bits above them are the
+/// RFC 4122 variant, always `10`, and would skew every draw positive.
+pub fn jitter_factor() -> f64 {
+    let random = Uuid::new_v4().as_u128() as u64 & ((1 << 62) - 1);
+    (random as f64 / (1u64 << 62) as f64 * 2.0 - 1.0) * JITTER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let base = Duration::from_millis(50);
+        let delays: Vec<u128> = (1..=7)
+            .map(|attempt| backoff_delay(attempt, base, 0.0).as_millis())
+            .collect();
+        assert_eq!(delays, [50, 100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(backoff_delay(1, base, JITTER).as_millis(), 55);
+        assert_eq!(backoff_delay(40, base, -JITTER).as_millis(), 900);
+        // A base above the cap is used as is rather than shortened.
+        assert_eq!(backoff_delay(3, Duration::from_secs(2), 0.0).as_secs(), 2);
+    }
+
+    #[test]
+    fn jitter_stays_in_range_on_both_sides() {
+        let draws: Vec<f64> = (0..1000).map(|_| jitter_factor()).collect();
+        assert!(draws.iter().all(|jitter| jitter.abs() <= JITTER));
+        assert!(draws.iter().any(|&jitter| jitter < 0.0));
+        assert!(draws.iter().any(|&jitter| jitter > 0.0));
+    }
+}
@@ -0,0 +1,55 @@
+//! How API1 picks among the API2 instances in `API2_URLS`.
+
+use crate::config::Config;
+
+/// The load-balancing strategy, from `API2_LB_STRATEGY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    RoundRobin,
+    Random,
+    /// The instance whose last failure is oldest, or that never failed,
+    /// rotating among equals.
+    LeastRecentlyFailed,
+}
+
+impl Strategy {
+    /// The configured strategy; invalid values were rejected at startup.
+    pub fn from_config(config: &Config) -> Self {
+        config
+            .api2_lb_strategy
+            .as_deref()
+            .and_then(|value| Strategy::parse(value).ok())
+            .unwrap_or(Strategy::RoundRobin)
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "round-robin" => Ok(Strategy::RoundRobin),
+            "random" => Ok(Strategy::Random),
+            "least-recently-failed" => Ok(Strategy::LeastRecentlyFailed),
+            _ => Err(format!(
+                "Invalid load-balancing strategy: {value} \
+                 (expected round-robin, random or least-recently-failed)"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strategy_names() {
+        assert_eq!(
+            Strategy::parse("round-robin").unwrap(),
+            Strategy::RoundRobin
+        );
+        assert_eq!(Strategy::parse(" Random ").unwrap(), Strategy::Random);
+        assert_eq!(
+            Strategy::parse("least-recently-failed").unwrap(),
+            Strategy::LeastRecentlyFailed
+        );
+        assert!(Strategy::parse("least-connections").is_err());
+    }
+}
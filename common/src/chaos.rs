@@ -0,0 +1,469 @@
+//! Fault injection for resilience testing, so API1's retries, hedging and
+//! circuit breaker can be exercised against a misbehaving API2.
+//!
+//! Nothing is injected unless `chaos_enabled` is set. Each rule names a
+//! route template, unversioned as in `route_timeouts`, or `*` for every
+//! route but the health and metrics probes, plus a fault and the
+//! probability of injecting it into a request:
+//!
+//! - `latency`: the request waits `latency_ms` before it is handled, so the
+//!   request timeout still applies;
+//! - `error`: the request is answered with a `5xx` problem instead;
+//! - `malformed_json`: the request is answered `200` with a truncated JSON
+//!   body;
+//! - `connection_reset`: the response headers are sent and the connection
+//!   is then dropped mid-body.
+//!
+//! Rules are rolled in order: every latency that fires adds up, and the
+//! first other fault that fires answers the request. `chaos_rules` sets the
+//! rules at startup, written `ROUTE=FAULT:PROBABILITY[:VALUE]`, and
+//! `/admin/chaos` reports and replaces them. Admin routes are never faulted,
+//! so chaos can always be switched off.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::health;
+use crate::metrics::{self, Counter, Registry};
+use crate::problem::ErrorCode;
+use crate::versioning::unversioned;
+use crate::ErrorResponse;
+use crate::REQUEST_ID_HEADER;
+
+pub const ADMIN_PATH: &str = "/admin/chaos";
+
+/// Header naming the fault injected into a response.
+pub const FAULT_HEADER: &str = "x-chaos-fault";
+
+/// Longest latency a rule may inject.
+pub const MAX_LATENCY_MS: u64 = 60_000;
+
+/// Routes `*` leaves alone, so probes keep reporting on the service itself.
+const PROBES: [&str; 4] = [
+    "/health",
+    health::LIVE_PATH,
+    health::READY_PATH,
+    metrics::METRICS_PATH,
+];
+
+const CHAOS_FAULTS_INJECTED_TOTAL: Counter = Counter {
+    name: "chaos_faults_injected_total",
+    help: "Faults injected by chaos mode, by route and fault.",
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "fault", rename_all = "snake_case")]
+pub enum Fault {
+    Latency {
+        latency_ms: u64,
+    },
+    Error {
+        #[serde(default = "default_error_status")]
+        status: u16,
+    },
+    MalformedJson,
+    ConnectionReset,
+}
+
+fn default_error_status() -> u16 {
+    StatusCode::INTERNAL_SERVER_ERROR.as_u16()
+}
+
+impl Fault {
+    fn name(&self) -> &'static str {
+        match self {
+            Fault::Latency { .. } => "latency",
+            Fault::Error { .. } => "error",
+            Fault::MalformedJson => "malformed_json",
+            Fault::ConnectionReset => "connection_reset",
+        }
+    }
+}
+
+/// One fault injected into a route's requests at `probability`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosRule {
+    /// A route template such as `/time` or `/timezone/*name`, or `*`.
+    pub route: String,
+    #[serde(flatten)]
+    pub fault: Fault,
+    pub probability: f64,
+}
+
+impl ChaosRule {
+    /// Parses a `chaos_rules` entry, e.g. `/time=latency:0.2:500` or
+    /// `*=error:0.1:503`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid chaos rule: {value} (expected ROUTE=latency:P:MS, ROUTE=error:P[:STATUS], \
+                 ROUTE=malformed_json:P or ROUTE=connection_reset:P)"
+            )
+        };
+        let (route, fault) = value.trim().split_once('=').ok_or_else(invalid)?;
+        let parts: Vec<&str> = fault.split(':').map(str::trim).collect();
+        let probability = parts
+            .get(1)
+            .and_then(|p| p.parse::<f64>().ok())
+            .ok_or_else(invalid)?;
+        let number = |part: &str| part.parse::<u64>().map_err(|_| invalid());
+        let fault = match parts[..] {
+            ["latency", _, latency_ms] => Fault::Latency {
+                latency_ms: number(latency_ms)?,
+            },
+            ["error", _] => Fault::Error {
+                status: default_error_status(),
+            },
+            ["error", _, status] => Fault::Error {
+                status: u16::try_from(number(status)?).map_err(|_| invalid())?,
+            },
+            ["malformed_json", _] => Fault::MalformedJson,
+            ["connection_reset", _] => Fault::ConnectionReset,
+            _ => return Err(invalid()),
+        };
+        let rule = ChaosRule {
+            route: route.trim().to_string(),
+            fault,
+            probability,
+        };
+        rule.validate()?;
+        Ok(rule)
+    }
+
+    /// Rejects rules for admin routes and out-of-range values.
+    pub fn validate(&self) -> Result<(), String> {
+        let route = &self.route;
+        if route != "*" && !route.starts_with('/') {
+            return Err(format!(
+                "Invalid chaos route: {route} (expected a path or *)"
+            ));
+        }
+        if route.starts_with("/admin") {
+            return Err(format!("Chaos rules cannot cover admin routes: {route}"));
+        }
+        if !(0.0..=1.0).contains(&self.probability) {
+            return Err(format!(
+                "Invalid chaos probability for {route}: {} (expected 0-1)",
+                self.probability
+            ));
+        }
+        match self.fault {
+            Fault::Latency { latency_ms } if !(1..=MAX_LATENCY_MS).contains(&latency_ms) => {
+                Err(format!(
+                    "Invalid chaos latency for {route}: {latency_ms} ms (expected 1-{MAX_LATENCY_MS})"
+                ))
+            }
+            Fault::Error { status } if !(500..=599).contains(&status) => Err(format!(
+                "Invalid chaos error status for {route}: {status} (expected 500-599)"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn covers(&self, route: &str) -> bool {
+        if self.route == "*" {
+            !PROBES.contains(&route)
+        } else {
+            self.route == route
+        }
+    }
+}
+
+/// Body of `PUT /admin/chaos`, and what `/admin/chaos` reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosSettings {
+    pub rules: Vec<ChaosRule>,
+}
+
+/// The rules in force, when chaos mode is enabled.
+pub struct Chaos {
+    rules: RwLock<Vec<ChaosRule>>,
+    metrics: Arc<Registry>,
+}
+
+impl Chaos {
+    pub fn new(rules: Vec<ChaosRule>, metrics: Arc<Registry>) -> Self {
+        Chaos {
+            rules: RwLock::new(rules),
+            metrics,
+        }
+    }
+
+    /// Uses `chaos_rules` when `chaos_enabled` is set, skipping rules that do
+    /// not parse; config validation rejects those first.
+    pub fn from_config(config: &Config, metrics: Arc<Registry>) -> Option<Self> {
+        if config.chaos_enabled != Some(true) {
+            return None;
+        }
+        let rules = config
+            .chaos_rules
+            .iter()
+            .flatten()
+            .filter_map(|rule| ChaosRule::parse(rule).ok())
+            .collect();
+        Some(Chaos::new(rules, metrics))
+    }
+
+    pub fn settings(&self) -> ChaosSettings {
+        ChaosSettings {
+            rules: self.rules.read().expect("chaos lock poisoned").clone(),
+        }
+    }
+
+    /// Replaces every rule, or none when one is invalid, returning the new
+    /// settings.
+    pub fn replace(&self, settings: ChaosSettings) -> Result<ChaosSettings, String> {
+        settings.rules.iter().try_for_each(ChaosRule::validate)?;
+        *self.rules.write().expect("chaos lock poisoned") = settings.rules;
+        Ok(self.settings())
+    }
+
+    /// The latency to add to a request for `route`, and the fault to answer
+    /// it with, if any, rolling each rule covering it with `roll`.
+    fn draw(&self, route: &str, mut roll: impl FnMut() -> f64) -> (Duration, Option<Fault>) {
+        let rules = self.rules.read().expect("chaos lock poisoned");
+        let mut latency = Duration::ZERO;
+        for rule in rules.iter().filter(|rule| rule.covers(route)) {
+            if roll() >= rule.probability {
+                continue;
+            }
+            self.metrics.increment(
+                &CHAOS_FAULTS_INJECTED_TOTAL,
+                &[("route", route), ("fault", rule.fault.name())],
+            );
+            match rule.fault {
+                Fault::Latency { latency_ms } => latency += Duration::from_millis(latency_ms),
+                fault => return (latency, Some(fault)),
+            }
+        }
+        (latency, None)
+    }
+}
+
+/// Uniform random value in `[0, 1)`, drawn from the 62 random bits in the
+/// low half of a v4 UUID.
+fn roll() -> f64 {
+    let random = Uuid::new_v4().as_u128() as u64 & ((1 << 62) - 1);
+    random as f64 / (1u64 << 62) as f64
+}
+
+/// Middleware injecting the faults drawn for each request, when chaos mode
+/// is enabled.
+pub async fn inject(
+    State(chaos): State<Option<Arc<Chaos>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(chaos) = chaos else {
+        return next.run(request).await;
+    };
+    let path = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_string(),
+        |route| route.as_str().to_string(),
+    );
+    let route = unversioned(&path);
+    if route.starts_with("/admin") {
+        return next.run(request).await;
+    }
+
+    let (latency, fault) = chaos.draw(route, roll);
+    if latency.is_zero() && fault.is_none() {
+        return next.run(request).await;
+    }
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    info!(
+        request_id = %request_id,
+        route = %route,
+        latency_ms = latency.as_millis() as u64,
+        fault = fault.as_ref().map(Fault::name),
+        "Injecting chaos faults"
+    );
+
+    tokio::time::sleep(latency).await;
+    let mut response = match fault {
+        None => return next.run(request).await,
+        Some(Fault::Latency { .. }) => unreachable!("latency is added up, not answered"),
+        Some(Fault::Error { status }) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            ErrorResponse::with_status(
+                status,
+                ErrorCode::from_status(status),
+                "Fault injected by chaos mode",
+                &request_id,
+            )
+            .into_response()
+        }
+        Some(Fault::MalformedJson) => (
+            [(header::CONTENT_TYPE, "application/json")],
+            r#"{"timestamp": "20"#,
+        )
+            .into_response(),
+        Some(Fault::ConnectionReset) => {
+            let reset = futures_util::stream::once(async {
+                Err::<Bytes, _>(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "connection reset by chaos mode",
+                ))
+            });
+            Body::from_stream(reset).into_response()
+        }
+    };
+    if let Some(fault) = fault {
+        response
+            .headers_mut()
+            .insert(FAULT_HEADER, HeaderValue::from_static(fault.name()));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::Service;
+
+    fn rule(route: &str, fault: Fault, probability: f64) -> ChaosRule {
+        ChaosRule {
+            route: route.to_string(),
+            fault,
+            probability,
+        }
+    }
+
+    #[test]
+    fn parses_and_validates_rules() {
+        assert_eq!(
+            ChaosRule::parse(" /time = latency:0.25:500"),
+            Ok(rule("/time", Fault::Latency { latency_ms: 500 }, 0.25))
+        );
+        assert_eq!(
+            ChaosRule::parse("*=error:0.1"),
+            Ok(rule("*", Fault::Error { status: 500 }, 0.1))
+        );
+        assert_eq!(
+            ChaosRule::parse("/timezones=connection_reset:1"),
+            Ok(rule("/timezones", Fault::ConnectionReset, 1.0))
+        );
+        for invalid in [
+            "/time",
+            "/time=latency:0.5",
+            "/time=error:0.5:404",
+            "/time=malformed_json:1.5",
+            "/time=teleport:0.5",
+            "/admin/chaos=error:1",
+            "time=error:1",
+        ] {
+            assert!(ChaosRule::parse(invalid).is_err(), "{invalid}");
+        }
+
+        let settings: ChaosSettings = serde_json::from_str(
+            r#"{"rules": [{"route": "/time", "fault": "error", "status": 503, "probability": 0.5}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.rules,
+            [rule("/time", Fault::Error { status: 503 }, 0.5)]
+        );
+    }
+
+    #[test]
+    fn adds_up_latency_and_stops_at_the_first_other_fault() {
+        let metrics = Arc::new(Registry::default());
+        let chaos = Chaos::new(
+            vec![
+                rule("*", Fault::Latency { latency_ms: 100 }, 1.0),
+                rule("/time", Fault::Latency { latency_ms: 50 }, 0.5),
+                rule("/time", Fault::MalformedJson, 0.5),
+                rule("/time", Fault::ConnectionReset, 1.0),
+            ],
+            metrics.clone(),
+        );
+
+        let (latency, fault) = chaos.draw("/time", || 0.25);
+        assert_eq!(latency, Duration::from_millis(150));
+        assert_eq!(fault, Some(Fault::MalformedJson));
+        let (latency, fault) = chaos.draw("/time", || 0.75);
+        assert_eq!(latency, Duration::from_millis(100));
+        assert_eq!(fault, Some(Fault::ConnectionReset));
+        assert_eq!(chaos.draw("/health", || 0.0), (Duration::ZERO, None));
+        assert!(metrics
+            .render()
+            .contains("chaos_faults_injected_total{route=\"/time\",fault=\"latency\"} 3"));
+
+        let replaced = chaos.replace(ChaosSettings {
+            rules: vec![rule("/time", Fault::Error { status: 200 }, 1.0)],
+        });
+        assert!(replaced.is_err());
+        assert_eq!(chaos.settings().rules.len(), 4);
+        assert!((0..100).map(|_| roll()).all(|r| (0.0..1.0).contains(&r)));
+    }
+
+    #[tokio::test]
+    async fn injects_faults_into_responses() {
+        let chaos = Arc::new(Chaos::new(Vec::new(), Arc::new(Registry::default())));
+        let mut app = Router::new()
+            .route("/time", get(|| async { "time" }))
+            .route("/admin/chaos", get(|| async { "admin" }))
+            .nest(
+                "/v2",
+                Router::new().route("/time", get(|| async { "time" })),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                Some(chaos.clone()),
+                inject,
+            ));
+        let mut call = |uri: &str| {
+            let request = axum::http::Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.call(request);
+            async move { response.await.unwrap() }
+        };
+        let set = |fault| {
+            chaos
+                .replace(ChaosSettings {
+                    rules: vec![rule("*", fault, 1.0)],
+                })
+                .unwrap()
+        };
+
+        assert_eq!(call("/time").await.status(), StatusCode::OK);
+
+        set(Fault::Error { status: 503 });
+        let response = call("/v2/time").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[FAULT_HEADER], "error");
+        assert_eq!(call("/admin/chaos").await.status(), StatusCode::OK);
+
+        set(Fault::MalformedJson);
+        let body = axum::body::to_bytes(call("/time").await.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&body).is_err());
+
+        set(Fault::ConnectionReset);
+        let response = call("/time").await;
+        assert_eq!(response.headers()[FAULT_HEADER], "connection_reset");
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .is_err());
+
+        set(Fault::Latency { latency_ms: 30 });
+        let started = std::time::Instant::now();
+        assert_eq!(call("/time").await.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_millis(30));
+    }
+}
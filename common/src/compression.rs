@@ -0,0 +1,356 @@
+//! Gzip response compression.
+//!
+//! The encoder emits a single DEFLATE block with the fixed Huffman codes
+//! (RFC 1951 §3.2.6) over greedy LZ77 matches, which is enough for the
+//! repetitive JSON these services return.
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::warn;
+
+/// Bodies shorter than this are sent as-is; the gzip framing alone is 18 bytes.
+const MIN_COMPRESS_BYTES: usize = 32;
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Candidates examined per position; bounds the cost of long hash chains.
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+const NO_POSITION: usize = usize::MAX;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// CRC-32 (IEEE 802.3, reflected polynomial `0xEDB88320`), as used by gzip.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+/// Packs bits least-significant first, as DEFLATE requires.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, bits: u32) {
+        self.pending |= u64::from(value) << self.pending_bits;
+        self.pending_bits += bits;
+        while self.pending_bits >= 8 {
+            self.out.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    /// Huffman codes are defined most-significant bit first.
+    fn write_code(&mut self, code: u32, bits: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    fn write_literal_length(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_match(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|&base| usize::from(base) <= length)
+            .expect("match length is at least 3");
+        self.write_literal_length(257 + index as u32);
+        self.write_bits(
+            (length - usize::from(LENGTH_BASE[index])) as u32,
+            u32::from(LENGTH_EXTRA_BITS[index]),
+        );
+
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| usize::from(base) <= distance)
+            .expect("match distance is at least 1");
+        self.write_code(index as u32, 5);
+        self.write_bits(
+            (distance - usize::from(DISTANCE_BASE[index])) as u32,
+            u32::from(DISTANCE_EXTRA_BITS[index]),
+        );
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending_bits > 0 {
+            self.out.push(self.pending as u8);
+        }
+        self.out
+    }
+}
+
+/// Raw DEFLATE stream of `data`.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let hash = |i: usize| {
+        let key = u32::from(data[i]) << 16 | u32::from(data[i + 1]) << 8 | u32::from(data[i + 2]);
+        (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+    let mut head = vec![NO_POSITION; 1 << HASH_BITS];
+    let mut previous = vec![NO_POSITION; data.len()];
+    let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            previous[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut writer = BitWriter::default();
+    // Final block (BFINAL = 1) compressed with fixed codes (BTYPE = 01).
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_length, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let longest = MAX_MATCH.min(data.len() - i);
+            let mut candidate = head[hash(i)];
+            for _ in 0..MAX_CHAIN {
+                if candidate == NO_POSITION || i - candidate > WINDOW_SIZE {
+                    break;
+                }
+                let length = (0..longest)
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+                if length > best_length {
+                    (best_length, best_distance) = (length, i - candidate);
+                    if length == longest {
+                        break;
+                    }
+                }
+                candidate = previous[candidate];
+            }
+        }
+
+        if best_length >= MIN_MATCH {
+            writer.write_match(best_length, best_distance);
+            for position in i..i + best_length {
+                insert(position, &mut head, &mut previous);
+            }
+            i += best_length;
+        } else {
+            writer.write_literal_length(u32::from(data[i]));
+            insert(i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+
+    writer.write_literal_length(256);
+    writer.finish()
+}
+
+/// Gzip member (RFC 1952) wrapping the DEFLATE stream of `data`.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    // Magic, CM = deflate, no flags, no mtime, no extra flags, OS unknown.
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend(deflate(data));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Whether an `Accept-Encoding` header admits gzip with a non-zero quality.
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let refused = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+        })
+}
+
+/// JSON and text bodies, except event streams that must not be buffered.
+fn is_compressible<B>(response: &Response<B>) -> bool {
+    if response.headers().contains_key(header::CONTENT_ENCODING)
+        || matches!(
+            response.status(),
+            StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED
+        )
+    {
+        return false;
+    }
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            (content_type.starts_with("application/json") || content_type.starts_with("text/"))
+                && !content_type.starts_with("text/event-stream")
+        })
+}
+
+/// Tower layer gzip-compressing responses for clients that accept it.
+#[derive(Clone, Default)]
+pub struct CompressionLayer;
+
+impl CompressionLayer {
+    pub fn new() -> Self {
+        CompressionLayer
+    }
+}
+
+impl<S> Layer<S> for CompressionLayer {
+    type Service = Compression<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Compression { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct Compression<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for Compression<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let accepts_gzip = accepts_gzip(request.headers());
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            if !is_compressible(&response) {
+                return Ok(response);
+            }
+
+            let (mut parts, body) = response.into_parts();
+            parts
+                .headers
+                .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+            if !accepts_gzip {
+                return Ok(Response::from_parts(parts, body));
+            }
+            let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!(error = %e, "Failed to buffer response body for compression");
+                    parts.status = StatusCode::INTERNAL_SERVER_ERROR;
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                    return Ok(Response::from_parts(parts, Body::empty()));
+                }
+            };
+            if bytes.len() < MIN_COMPRESS_BYTES {
+                return Ok(Response::from_parts(parts, Body::from(bytes)));
+            }
+
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            Ok(Response::from_parts(parts, Body::from(gzip(&bytes))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decompresses with the system `gzip`, an independent decoder.
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("gzip")
+            .arg("-dc")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("gzip is installed");
+        child.stdin.take().unwrap().write_all(data).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "gzip rejected the stream");
+        output.stdout
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn gzip_round_trips() {
+        let json = (0..200)
+            .map(|i| format!("{{\"timezone\":\"Etc/GMT+{}\",\"offset\":{i}}}", i % 12))
+            .collect::<Vec<_>>()
+            .join(",");
+        let long_run = vec![b'a'; 70_000];
+        for data in [&b""[..], b"a", b"abcabcabc", json.as_bytes(), &long_run] {
+            let compressed = gzip(data);
+            assert_eq!(gunzip(&compressed), data);
+        }
+        assert!(gzip(json.as_bytes()).len() < json.len() / 4);
+    }
+
+    #[test]
+    fn honours_accept_encoding_quality() {
+        let accepts = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::ACCEPT_ENCODING,
+                HeaderValue::from_str(value).unwrap(),
+            );
+            accepts_gzip(&headers)
+        };
+        assert!(accepts("gzip"));
+        assert!(accepts("br;q=1.0, GZIP;q=0.5"));
+        assert!(accepts("*"));
+        assert!(!accepts("br"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+    }
+}
@@ -0,0 +1,1024 @@
+//! Service configuration from `config.toml`, overridden by environment variables.
+
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::shutdown::DEFAULT_SHUTDOWN_TIMEOUT;
+
+/// Path read when `CONFIG_PATH` is unset; a missing file there is not an error.
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+/// Settings shared by both services. Every field is optional: environment
+/// variables override the file, and each consumer applies its own default
+/// when neither sets a value.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// `PORT`
+    pub port: Option<u16>,
+    /// `BIND_ADDRESS`
+    pub bind_address: Option<IpAddr>,
+    /// `API2_URL`
+    pub api2_url: Option<String>,
+    /// `API2_URLS`; takes precedence over `api2_url`.
+    pub api2_urls: Option<Vec<String>>,
+    /// `API2_LB_STRATEGY`
+    pub api2_lb_strategy: Option<String>,
+    /// `API2_EJECT_AFTER_FAILURES`
+    pub api2_eject_after_failures: Option<u32>,
+    /// `API2_EJECT_SECS`
+    pub api2_eject_secs: Option<u64>,
+    /// `API2_TRANSPORT`: `http` (default) or `grpc`.
+    pub api2_transport: Option<String>,
+    /// `API2_GRPC_URL`, e.g. `http://time-provider:50051`; used when
+    /// `api2_transport` is `grpc`.
+    pub api2_grpc_url: Option<String>,
+    /// `API2_GRPC_PORT`: API2 serves gRPC on this port when set.
+    pub grpc_port: Option<u16>,
+    /// `API2_TIMEOUT_MS`
+    pub timeout_ms: Option<u64>,
+    /// `API2_CONNECT_TIMEOUT_MS`
+    pub connect_timeout_ms: Option<u64>,
+    /// `API2_POOL_MAX_IDLE_PER_HOST`; `0` disables connection reuse.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// `API2_POOL_IDLE_TIMEOUT_SECS`
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// `API2_TCP_KEEPALIVE_SECS`
+    pub tcp_keepalive_secs: Option<u64>,
+    /// `API2_UNIX_SOCKET`: reach API2 over this Unix socket instead of TCP.
+    pub api2_unix_socket: Option<String>,
+    /// `API2_MAX_RETRIES`
+    pub max_retries: Option<u32>,
+    /// `API2_RETRY_BASE_DELAY_MS`
+    pub retry_base_delay_ms: Option<u64>,
+    /// `API2_RETRY_DEADLINE_MS`
+    pub retry_deadline_ms: Option<u64>,
+    /// `API2_HEDGE_PERCENTILE`: hedge calls to API2 still unanswered after
+    /// this percentile of recent latencies, e.g. `95`. Unset disables hedging.
+    pub hedge_percentile: Option<f64>,
+    /// `API2_HEDGE_MIN_DELAY_MS`
+    pub hedge_min_delay_ms: Option<u64>,
+    /// `ALLOWED_ORIGINS`
+    pub allowed_origins: Option<Vec<String>>,
+    /// `ALLOWED_METHODS`
+    pub allowed_methods: Option<Vec<String>>,
+    /// `ALLOWED_HEADERS`
+    pub allowed_headers: Option<Vec<String>>,
+    /// `CORS_MAX_AGE_SECS`
+    pub cors_max_age_secs: Option<u64>,
+    /// `CORS_ALLOW_CREDENTIALS`
+    pub cors_allow_credentials: Option<bool>,
+    /// `CORS_ALLOW_ANY`; allows any origin, for local development only.
+    pub cors_allow_any: Option<bool>,
+    /// `SHUTDOWN_TIMEOUT_SECS`
+    pub shutdown_timeout_secs: Option<u64>,
+    /// `SHUTDOWN_DELAY_SECS`
+    pub shutdown_delay_secs: Option<u64>,
+    /// `MAX_BATCH_SIZE`
+    pub max_batch_size: Option<usize>,
+    /// `API1_RATE_LIMIT_RPS`
+    pub rate_limit_rps: Option<f64>,
+    /// `API1_RATE_LIMIT_BURST`
+    pub rate_limit_burst: Option<f64>,
+    /// `API1_RATE_LIMIT_ROUTES`, each `PATH=RPS:BURST`.
+    pub rate_limit_routes: Option<Vec<String>>,
+    /// `MAX_IN_FLIGHT`: requests handled at once; unset means no limit.
+    pub max_in_flight: Option<usize>,
+    /// `MAX_QUEUED_REQUESTS`: requests waiting for one of `max_in_flight`.
+    pub max_queued_requests: Option<usize>,
+    /// `REQUEST_TIMEOUT_MS`
+    pub request_timeout_ms: Option<u64>,
+    /// `REQUEST_TIMEOUT_ROUTES`, each `PATH=MILLISECONDS`.
+    pub route_timeouts: Option<Vec<String>>,
+    /// `MAX_BODY_BYTES`
+    pub max_body_bytes: Option<usize>,
+    /// `FALLBACK_LOCAL_TIME`
+    pub fallback_local_time: Option<bool>,
+    /// `CACHE_TTL_MS`
+    pub cache_ttl_ms: Option<u64>,
+    /// `CACHE_CAPACITY`
+    pub cache_capacity: Option<usize>,
+    /// `AUDIT_LOG_PATH`
+    pub audit_log_path: Option<String>,
+    /// `AUDIT_RETENTION_DAYS`: daily audit files older than this are deleted.
+    pub audit_retention_days: Option<u64>,
+    /// `TLS_CERT_PATH`
+    pub tls_cert_path: Option<String>,
+    /// `TLS_KEY_PATH`
+    pub tls_key_path: Option<String>,
+    /// `TLS_CLIENT_CA_PATH`: when set, clients must present a certificate
+    /// signed by this CA.
+    pub tls_client_ca_path: Option<String>,
+    /// `API2_CA_CERT_PATH`, trusted in addition to the system roots.
+    pub api2_ca_cert_path: Option<String>,
+    /// `API2_CLIENT_CERT_PATH`
+    pub api2_client_cert_path: Option<String>,
+    /// `API2_CLIENT_KEY_PATH`
+    pub api2_client_key_path: Option<String>,
+    /// `CIRCUIT_BREAKER_THRESHOLD`
+    pub circuit_breaker_threshold: Option<u32>,
+    /// `CIRCUIT_BREAKER_WINDOW_SECS`
+    pub circuit_breaker_window_secs: Option<u64>,
+    /// `CIRCUIT_BREAKER_RESET_SECS`
+    pub circuit_breaker_reset_secs: Option<u64>,
+    /// `HISTORY_CAPACITY`
+    pub history_capacity: Option<usize>,
+    /// `HISTORY_MAX_AGE_SECS`
+    pub history_max_age_secs: Option<u64>,
+    /// `LOG_FORMAT`
+    pub log_format: Option<String>,
+    /// `LOG_LEVEL`
+    pub log_level: Option<String>,
+    /// `LOG_FILE`
+    pub log_file: Option<String>,
+    /// `LOG_ROTATION`
+    pub log_rotation: Option<String>,
+    /// `LOG_FILE_MAX_FILES`
+    pub log_file_max_files: Option<usize>,
+    /// `UNIX_SOCKET_PATH`
+    pub unix_socket_path: Option<String>,
+    /// `SOCKET_MODE`
+    pub socket_mode: Option<String>,
+    /// `UNIX_SOCKET_PERMS`, in octal.
+    pub unix_socket_perms: Option<String>,
+    /// `API_KEYS`
+    pub api_keys: Option<Vec<String>>,
+    /// `WS_MAX_SUBSCRIPTIONS`
+    pub ws_max_subscriptions: Option<usize>,
+    /// `STREAM_MAX_LIFETIME_SECS`
+    pub stream_max_lifetime_secs: Option<u64>,
+    /// `POLL_MAX_WAIT_SECS`: longest `/time/poll` waits for the minute to
+    /// change.
+    pub poll_max_wait_secs: Option<u64>,
+    /// `TIMER_MAX_COUNT`
+    pub timer_max_count: Option<usize>,
+    /// `TIMER_TTL_SECS`
+    pub timer_ttl_secs: Option<u64>,
+    /// `ALARMS_PATH`
+    pub alarms_path: Option<String>,
+    /// `ALARM_MAX_COUNT`
+    pub alarm_max_count: Option<usize>,
+    /// `NTP_SERVER`, e.g. `pool.ntp.org` or `10.0.0.1:123`; enables clock
+    /// drift monitoring in API2.
+    pub ntp_server: Option<String>,
+    /// `NTP_INTERVAL_SECS`
+    pub ntp_interval_secs: Option<u64>,
+    /// `NTP_MAX_DRIFT_MS`
+    pub ntp_max_drift_ms: Option<u64>,
+    /// `TZDATA_MANIFEST_URL`: a document naming the latest tzdb release;
+    /// enables API2's tzdata freshness check.
+    pub tzdata_manifest_url: Option<String>,
+    /// `TZDATA_CHECK_INTERVAL_SECS`
+    pub tzdata_check_interval_secs: Option<u64>,
+    /// `READINESS_CACHE_MS`
+    pub readiness_cache_ms: Option<u64>,
+    /// `IDEMPOTENCY_TTL_SECS`
+    pub idempotency_ttl_secs: Option<u64>,
+    /// `IDEMPOTENCY_CAPACITY`; 0 disables `Idempotency-Key` replay.
+    pub idempotency_capacity: Option<usize>,
+    /// `PAYLOAD_LOG`: log payloads from startup; see [`crate::payload_log`].
+    pub payload_log: Option<bool>,
+    /// `PAYLOAD_LOG_MAX_BYTES`
+    pub payload_log_max_bytes: Option<usize>,
+    /// `PAYLOAD_LOG_REDACT`: header, query parameter and JSON field names,
+    /// redacted in addition to the built-in ones.
+    pub payload_log_redact: Option<Vec<String>>,
+    /// `FEATURE_FLAGS`: flag states written `NAME=on|off|unavailable`, e.g.
+    /// `time_ws=off`; see [`crate::flags`].
+    pub feature_flags: Option<Vec<String>>,
+    /// `CHAOS_ENABLED`: lets API2 inject faults; see [`crate::chaos`].
+    pub chaos_enabled: Option<bool>,
+    /// `CHAOS_RULES`: faults written `ROUTE=FAULT:PROBABILITY[:VALUE]`, e.g.
+    /// `/time=latency:0.2:500`.
+    pub chaos_rules: Option<Vec<String>>,
+    /// `TENANTS`: tenants written
+    /// `NAME;keys=K1|K2;timezone=TZ;locale=L;rate_limit=RPS:BURST;endpoints=/A|/B`;
+    /// see [`crate::tenants`].
+    pub tenants: Option<Vec<String>>,
+    /// `DASHBOARD_TIMEZONES`: zones shown on API1's `/dashboard`.
+    pub dashboard_timezones: Option<Vec<String>>,
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT`, e.g. `http://otel-collector:4318`.
+    pub otel_exporter_endpoint: Option<String>,
+    /// `OTEL_TRACES_SAMPLER_ARG`, the share of new traces exported.
+    pub otel_sampling_ratio: Option<f64>,
+}
+
+impl Config {
+    /// Loads the file at `CONFIG_PATH` (default `./config.toml`), then
+    /// applies environment overrides and validates the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the file is malformed, when `CONFIG_PATH` names a file
+    /// that cannot be read, or listing every invalid setting, so a broken
+    /// deployment fails at startup.
+    pub fn load() -> Self {
+        let explicit = std::env::var("CONFIG_PATH").ok();
+        let path = explicit.as_deref().unwrap_or(DEFAULT_CONFIG_PATH);
+        let file = match std::fs::read_to_string(path) {
+            Ok(text) => Config::from_toml(&text)
+                .unwrap_or_else(|e| panic!("Invalid configuration file {path}: {e}")),
+            Err(e) if explicit.is_some() => panic!("Cannot read configuration file {path}: {e}"),
+            Err(_) => Config::default(),
+        };
+        let (config, mut errors) = file.apply_env();
+        errors.extend(config.problems());
+        if !errors.is_empty() {
+            panic!("Invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+        config
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        serde_json::from_value(crate::toml::parse(text)?).map_err(|e| e.to_string())
+    }
+
+    /// Replaces fields with the values set in the environment; blank
+    /// variables count as unset. Fails with one message per variable that
+    /// does not parse.
+    pub fn with_env_overrides(self) -> Result<Self, Vec<String>> {
+        match self.apply_env() {
+            (config, errors) if errors.is_empty() => Ok(config),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    /// Applies the valid environment overrides, returning the unparsable
+    /// ones alongside.
+    fn apply_env(self) -> (Self, Vec<String>) {
+        let mut env = Env::default();
+        let config = Config {
+            port: env.get("PORT").or(self.port),
+            bind_address: env.get("BIND_ADDRESS").or(self.bind_address),
+            api2_url: env.get("API2_URL").or(self.api2_url),
+            api2_urls: env.list("API2_URLS").or(self.api2_urls),
+            api2_lb_strategy: env.get("API2_LB_STRATEGY").or(self.api2_lb_strategy),
+            api2_eject_after_failures: env
+                .get("API2_EJECT_AFTER_FAILURES")
+                .or(self.api2_eject_after_failures),
+            api2_eject_secs: env.get("API2_EJECT_SECS").or(self.api2_eject_secs),
+            api2_transport: env.get("API2_TRANSPORT").or(self.api2_transport),
+            api2_grpc_url: env.get("API2_GRPC_URL").or(self.api2_grpc_url),
+            grpc_port: env.get("API2_GRPC_PORT").or(self.grpc_port),
+            timeout_ms: env.get("API2_TIMEOUT_MS").or(self.timeout_ms),
+            connect_timeout_ms: env
+                .get("API2_CONNECT_TIMEOUT_MS")
+                .or(self.connect_timeout_ms),
+            pool_max_idle_per_host: env
+                .get("API2_POOL_MAX_IDLE_PER_HOST")
+                .or(self.pool_max_idle_per_host),
+            pool_idle_timeout_secs: env
+                .get("API2_POOL_IDLE_TIMEOUT_SECS")
+                .or(self.pool_idle_timeout_secs),
+            tcp_keepalive_secs: env
+                .get("API2_TCP_KEEPALIVE_SECS")
+                .or(self.tcp_keepalive_secs),
+            api2_unix_socket: env.get("API2_UNIX_SOCKET").or(self.api2_unix_socket),
+            max_retries: env.get("API2_MAX_RETRIES").or(self.max_retries),
+            retry_base_delay_ms: env
+                .get("API2_RETRY_BASE_DELAY_MS")
+                .or(self.retry_base_delay_ms),
+            retry_deadline_ms: env.get("API2_RETRY_DEADLINE_MS").or(self.retry_deadline_ms),
+            hedge_percentile: env.get("API2_HEDGE_PERCENTILE").or(self.hedge_percentile),
+            hedge_min_delay_ms: env
+                .get("API2_HEDGE_MIN_DELAY_MS")
+                .or(self.hedge_min_delay_ms),
+            allowed_origins: env.list("ALLOWED_ORIGINS").or(self.allowed_origins),
+            allowed_methods: env.list("ALLOWED_METHODS").or(self.allowed_methods),
+            allowed_headers: env.list("ALLOWED_HEADERS").or(self.allowed_headers),
+            cors_max_age_secs: env.get("CORS_MAX_AGE_SECS").or(self.cors_max_age_secs),
+            cors_allow_credentials: env
+                .get("CORS_ALLOW_CREDENTIALS")
+                .or(self.cors_allow_credentials),
+            cors_allow_any: env.get("CORS_ALLOW_ANY").or(self.cors_allow_any),
+            shutdown_timeout_secs: env
+                .get("SHUTDOWN_TIMEOUT_SECS")
+                .or(self.shutdown_timeout_secs),
+            shutdown_delay_secs: env.get("SHUTDOWN_DELAY_SECS").or(self.shutdown_delay_secs),
+            max_batch_size: env.get("MAX_BATCH_SIZE").or(self.max_batch_size),
+            rate_limit_rps: env.get("API1_RATE_LIMIT_RPS").or(self.rate_limit_rps),
+            rate_limit_burst: env.get("API1_RATE_LIMIT_BURST").or(self.rate_limit_burst),
+            rate_limit_routes: env
+                .list("API1_RATE_LIMIT_ROUTES")
+                .or(self.rate_limit_routes),
+            max_in_flight: env.get("MAX_IN_FLIGHT").or(self.max_in_flight),
+            max_queued_requests: env.get("MAX_QUEUED_REQUESTS").or(self.max_queued_requests),
+            request_timeout_ms: env.get("REQUEST_TIMEOUT_MS").or(self.request_timeout_ms),
+            route_timeouts: env.list("REQUEST_TIMEOUT_ROUTES").or(self.route_timeouts),
+            max_body_bytes: env.get("MAX_BODY_BYTES").or(self.max_body_bytes),
+            fallback_local_time: env.get("FALLBACK_LOCAL_TIME").or(self.fallback_local_time),
+            cache_ttl_ms: env.get("CACHE_TTL_MS").or(self.cache_ttl_ms),
+            cache_capacity: env.get("CACHE_CAPACITY").or(self.cache_capacity),
+            audit_log_path: env.get("AUDIT_LOG_PATH").or(self.audit_log_path),
+            audit_retention_days: env
+                .get("AUDIT_RETENTION_DAYS")
+                .or(self.audit_retention_days),
+            tls_cert_path: env.get("TLS_CERT_PATH").or(self.tls_cert_path),
+            tls_key_path: env.get("TLS_KEY_PATH").or(self.tls_key_path),
+            tls_client_ca_path: env.get("TLS_CLIENT_CA_PATH").or(self.tls_client_ca_path),
+            api2_ca_cert_path: env.get("API2_CA_CERT_PATH").or(self.api2_ca_cert_path),
+            api2_client_cert_path: env
+                .get("API2_CLIENT_CERT_PATH")
+                .or(self.api2_client_cert_path),
+            api2_client_key_path: env
+                .get("API2_CLIENT_KEY_PATH")
+                .or(self.api2_client_key_path),
+            circuit_breaker_threshold: env
+                .get("CIRCUIT_BREAKER_THRESHOLD")
+                .or(self.circuit_breaker_threshold),
+            circuit_breaker_window_secs: env
+                .get("CIRCUIT_BREAKER_WINDOW_SECS")
+                .or(self.circuit_breaker_window_secs),
+            circuit_breaker_reset_secs: env
+                .get("CIRCUIT_BREAKER_RESET_SECS")
+                .or(self.circuit_breaker_reset_secs),
+            history_capacity: env.get("HISTORY_CAPACITY").or(self.history_capacity),
+            history_max_age_secs: env
+                .get("HISTORY_MAX_AGE_SECS")
+                .or(self.history_max_age_secs),
+            log_format: env.get("LOG_FORMAT").or(self.log_format),
+            log_level: env.get("LOG_LEVEL").or(self.log_level),
+            log_file: env.get("LOG_FILE").or(self.log_file),
+            log_rotation: env.get("LOG_ROTATION").or(self.log_rotation),
+            log_file_max_files: env.get("LOG_FILE_MAX_FILES").or(self.log_file_max_files),
+            unix_socket_path: env.get("UNIX_SOCKET_PATH").or(self.unix_socket_path),
+            socket_mode: env.get("SOCKET_MODE").or(self.socket_mode),
+            unix_socket_perms: env.get("UNIX_SOCKET_PERMS").or(self.unix_socket_perms),
+            api_keys: env.list("API_KEYS").or(self.api_keys),
+            ws_max_subscriptions: env
+                .get("WS_MAX_SUBSCRIPTIONS")
+                .or(self.ws_max_subscriptions),
+            stream_max_lifetime_secs: env
+                .get("STREAM_MAX_LIFETIME_SECS")
+                .or(self.stream_max_lifetime_secs),
+            poll_max_wait_secs: env.get("POLL_MAX_WAIT_SECS").or(self.poll_max_wait_secs),
+            timer_max_count: env.get("TIMER_MAX_COUNT").or(self.timer_max_count),
+            timer_ttl_secs: env.get("TIMER_TTL_SECS").or(self.timer_ttl_secs),
+            alarms_path: env.get("ALARMS_PATH").or(self.alarms_path),
+            alarm_max_count: env.get("ALARM_MAX_COUNT").or(self.alarm_max_count),
+            ntp_server: env.get("NTP_SERVER").or(self.ntp_server),
+            ntp_interval_secs: env.get("NTP_INTERVAL_SECS").or(self.ntp_interval_secs),
+            ntp_max_drift_ms: env.get("NTP_MAX_DRIFT_MS").or(self.ntp_max_drift_ms),
+            tzdata_manifest_url: env.get("TZDATA_MANIFEST_URL").or(self.tzdata_manifest_url),
+            tzdata_check_interval_secs: env
+                .get("TZDATA_CHECK_INTERVAL_SECS")
+                .or(self.tzdata_check_interval_secs),
+            readiness_cache_ms: env.get("READINESS_CACHE_MS").or(self.readiness_cache_ms),
+            idempotency_ttl_secs: env
+                .get("IDEMPOTENCY_TTL_SECS")
+                .or(self.idempotency_ttl_secs),
+            idempotency_capacity: env
+                .get("IDEMPOTENCY_CAPACITY")
+                .or(self.idempotency_capacity),
+            payload_log: env.get("PAYLOAD_LOG").or(self.payload_log),
+            payload_log_max_bytes: env
+                .get("PAYLOAD_LOG_MAX_BYTES")
+                .or(self.payload_log_max_bytes),
+            payload_log_redact: env.list("PAYLOAD_LOG_REDACT").or(self.payload_log_redact),
+            feature_flags: env.list("FEATURE_FLAGS").or(self.feature_flags),
+            chaos_enabled: env.get("CHAOS_ENABLED").or(self.chaos_enabled),
+            chaos_rules: env.list("CHAOS_RULES").or(self.chaos_rules),
+            tenants: env.list("TENANTS").or(self.tenants),
+            dashboard_timezones: env.list("DASHBOARD_TIMEZONES").or(self.dashboard_timezones),
+            otel_exporter_endpoint: env
+                .get("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .or(self.otel_exporter_endpoint),
+            otel_sampling_ratio: env
+                .get("OTEL_TRACES_SAMPLER_ARG")
+                .or(self.otel_sampling_ratio),
+        };
+        (config, env.errors)
+    }
+
+    /// Checks values that parse but cannot work, such as a zero batch size or
+    /// a Unix socket mode without a path, returning the config when all pass.
+    pub fn validated(self) -> Result<Self, Vec<String>> {
+        match self.problems() {
+            errors if errors.is_empty() => Ok(self),
+            errors => Err(errors),
+        }
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, key: &str, env: &str, problem: String| {
+            if !ok {
+                errors.push(format!("{key} ({env}): {problem}"));
+            }
+        };
+        let positive = |value: Option<f64>| value.is_none_or(|value| value > 0.0);
+
+        check(
+            positive(self.max_batch_size.map(|size| size as f64)),
+            "max_batch_size",
+            "MAX_BATCH_SIZE",
+            "must be greater than 0".to_string(),
+        );
+        check(
+            positive(self.retry_base_delay_ms.map(|delay| delay as f64)),
+            "retry_base_delay_ms",
+            "API2_RETRY_BASE_DELAY_MS",
+            "must be greater than 0".to_string(),
+        );
+        for (value, key, env) in [
+            (
+                self.connect_timeout_ms,
+                "connect_timeout_ms",
+                "API2_CONNECT_TIMEOUT_MS",
+            ),
+            (
+                self.pool_idle_timeout_secs,
+                "pool_idle_timeout_secs",
+                "API2_POOL_IDLE_TIMEOUT_SECS",
+            ),
+            (
+                self.tcp_keepalive_secs,
+                "tcp_keepalive_secs",
+                "API2_TCP_KEEPALIVE_SECS",
+            ),
+            (
+                self.hedge_min_delay_ms,
+                "hedge_min_delay_ms",
+                "API2_HEDGE_MIN_DELAY_MS",
+            ),
+            (
+                self.request_timeout_ms,
+                "request_timeout_ms",
+                "REQUEST_TIMEOUT_MS",
+            ),
+            (self.api2_eject_secs, "api2_eject_secs", "API2_EJECT_SECS"),
+            (
+                self.timer_max_count.map(|count| count as u64),
+                "timer_max_count",
+                "TIMER_MAX_COUNT",
+            ),
+            (self.timer_ttl_secs, "timer_ttl_secs", "TIMER_TTL_SECS"),
+            (
+                self.poll_max_wait_secs,
+                "poll_max_wait_secs",
+                "POLL_MAX_WAIT_SECS",
+            ),
+            (
+                self.alarm_max_count.map(|count| count as u64),
+                "alarm_max_count",
+                "ALARM_MAX_COUNT",
+            ),
+            (
+                self.ntp_interval_secs,
+                "ntp_interval_secs",
+                "NTP_INTERVAL_SECS",
+            ),
+            (
+                self.ntp_max_drift_ms,
+                "ntp_max_drift_ms",
+                "NTP_MAX_DRIFT_MS",
+            ),
+            (
+                self.tzdata_check_interval_secs,
+                "tzdata_check_interval_secs",
+                "TZDATA_CHECK_INTERVAL_SECS",
+            ),
+            (
+                self.idempotency_ttl_secs,
+                "idempotency_ttl_secs",
+                "IDEMPOTENCY_TTL_SECS",
+            ),
+            (
+                self.audit_retention_days,
+                "audit_retention_days",
+                "AUDIT_RETENTION_DAYS",
+            ),
+            (
+                self.payload_log_max_bytes.map(|bytes| bytes as u64),
+                "payload_log_max_bytes",
+                "PAYLOAD_LOG_MAX_BYTES",
+            ),
+            (
+                self.max_in_flight.map(|count| count as u64),
+                "max_in_flight",
+                "MAX_IN_FLIGHT",
+            ),
+            (
+                self.api2_eject_after_failures.map(u64::from),
+                "api2_eject_after_failures",
+                "API2_EJECT_AFTER_FAILURES",
+            ),
+            (
+                self.circuit_breaker_threshold.map(u64::from),
+                "circuit_breaker_threshold",
+                "CIRCUIT_BREAKER_THRESHOLD",
+            ),
+            (
+                self.circuit_breaker_window_secs,
+                "circuit_breaker_window_secs",
+                "CIRCUIT_BREAKER_WINDOW_SECS",
+            ),
+            (
+                self.circuit_breaker_reset_secs,
+                "circuit_breaker_reset_secs",
+                "CIRCUIT_BREAKER_RESET_SECS",
+            ),
+        ] {
+            check(
+                positive(value.map(|value| value as f64)),
+                key,
+                env,
+                "must be greater than 0".to_string(),
+            );
+        }
+        check(
+            self.audit_retention_days
+                .is_none_or(|days| days <= crate::audit::MAX_RETENTION_DAYS),
+            "audit_retention_days",
+            "AUDIT_RETENTION_DAYS",
+            format!("must be at most {}", crate::audit::MAX_RETENTION_DAYS),
+        );
+        check(
+            positive(self.stream_max_lifetime_secs.map(|secs| secs as f64)),
+            "stream_max_lifetime_secs",
+            "STREAM_MAX_LIFETIME_SECS",
+            "must be greater than 0".to_string(),
+        );
+        check(
+            positive(self.rate_limit_rps),
+            "rate_limit_rps",
+            "API1_RATE_LIMIT_RPS",
+            "must be greater than 0".to_string(),
+        );
+        check(
+            positive(self.rate_limit_burst),
+            "rate_limit_burst",
+            "API1_RATE_LIMIT_BURST",
+            "must be greater than 0".to_string(),
+        );
+        for route in self.rate_limit_routes.iter().flatten() {
+            if let Err(e) = crate::rate_limit::RouteLimit::parse(route) {
+                check(false, "rate_limit_routes", "API1_RATE_LIMIT_ROUTES", e);
+            }
+        }
+        check(
+            positive(self.max_body_bytes.map(|bytes| bytes as f64)),
+            "max_body_bytes",
+            "MAX_BODY_BYTES",
+            "must be greater than 0".to_string(),
+        );
+        for route in self.route_timeouts.iter().flatten() {
+            if let Err(e) = crate::limits::RouteTimeout::parse(route) {
+                check(false, "route_timeouts", "REQUEST_TIMEOUT_ROUTES", e);
+            }
+        }
+        for setting in self.feature_flags.iter().flatten() {
+            if let Err(e) = crate::flags::parse_setting(setting) {
+                check(false, "feature_flags", "FEATURE_FLAGS", e);
+            }
+        }
+        for rule in self.chaos_rules.iter().flatten() {
+            if let Err(e) = crate::chaos::ChaosRule::parse(rule) {
+                check(false, "chaos_rules", "CHAOS_RULES", e);
+            }
+        }
+        check(
+            self.chaos_rules.is_none() || self.chaos_enabled == Some(true),
+            "chaos_rules",
+            "CHAOS_RULES",
+            "requires chaos_enabled".to_string(),
+        );
+        for problem in crate::tenants::problems(self.tenants.as_deref().unwrap_or_default()) {
+            check(false, "tenants", "TENANTS", problem);
+        }
+        for zone in self.dashboard_timezones.iter().flatten() {
+            if crate::resolve_timezone_alias(zone)
+                .parse::<chrono_tz::Tz>()
+                .is_err()
+            {
+                check(
+                    false,
+                    "dashboard_timezones",
+                    "DASHBOARD_TIMEZONES",
+                    format!("unknown timezone {zone}"),
+                );
+            }
+        }
+        for url in self.api2_urls.iter().flatten().chain(&self.api2_url) {
+            if let Err(e) = reqwest::Url::parse(url) {
+                check(false, "api2_url", "API2_URL(S)", format!("{url}: {e}"));
+            }
+        }
+        check(
+            self.hedge_percentile
+                .is_none_or(|percentile| percentile > 0.0 && percentile < 100.0),
+            "hedge_percentile",
+            "API2_HEDGE_PERCENTILE",
+            "must be between 0 and 100".to_string(),
+        );
+        check(
+            self.api2_unix_socket.is_none()
+                || self.api2_urls.as_ref().is_none_or(|urls| urls.len() <= 1),
+            "api2_unix_socket",
+            "API2_UNIX_SOCKET",
+            "reaches a single API2 instance; list at most one API2 URL".to_string(),
+        );
+        if let Some(Err(e)) = self
+            .api2_lb_strategy
+            .as_deref()
+            .map(crate::balancing::Strategy::parse)
+        {
+            check(false, "api2_lb_strategy", "API2_LB_STRATEGY", e);
+        }
+        match self
+            .api2_transport
+            .as_deref()
+            .map(crate::grpc::Transport::parse)
+        {
+            Some(Err(e)) => check(false, "api2_transport", "API2_TRANSPORT", e),
+            Some(Ok(transport)) => check(
+                transport == crate::grpc::Transport::Http || self.api2_grpc_url.is_some(),
+                "api2_grpc_url",
+                "API2_GRPC_URL",
+                "required when api2_transport is grpc".to_string(),
+            ),
+            None => {}
+        }
+        if let Some(Err(e)) = self.api2_grpc_url.as_deref().map(reqwest::Url::parse) {
+            check(false, "api2_grpc_url", "API2_GRPC_URL", e.to_string());
+        }
+        if let Some(Err(e)) = self.tzdata_manifest_url.as_deref().map(reqwest::Url::parse) {
+            check(
+                false,
+                "tzdata_manifest_url",
+                "TZDATA_MANIFEST_URL",
+                e.to_string(),
+            );
+        }
+        check(
+            self.otel_sampling_ratio
+                .is_none_or(|ratio| (0.0..=1.0).contains(&ratio)),
+            "otel_sampling_ratio",
+            "OTEL_TRACES_SAMPLER_ARG",
+            "must be between 0 and 1".to_string(),
+        );
+        if let Some(Err(e)) = self
+            .otel_exporter_endpoint
+            .as_deref()
+            .map(reqwest::Url::parse)
+        {
+            check(
+                false,
+                "otel_exporter_endpoint",
+                "OTEL_EXPORTER_OTLP_ENDPOINT",
+                e.to_string(),
+            );
+        }
+        if let Err(e) = crate::cors::CorsPolicy::parse(
+            self.allowed_origins.as_deref().unwrap_or_default(),
+            self.allowed_methods.as_deref().unwrap_or_default(),
+            self.allowed_headers.as_deref().unwrap_or_default(),
+            self.cors_allow_any.unwrap_or(false),
+        ) {
+            check(false, "allowed_*", "ALLOWED_*", e);
+        }
+        if let Some(Err(e)) = self
+            .log_format
+            .as_deref()
+            .map(crate::logging::LogFormat::parse)
+        {
+            check(false, "log_format", "LOG_FORMAT", e);
+        }
+        if let Some(Err(e)) = self
+            .log_rotation
+            .as_deref()
+            .map(crate::rolling_file::Rotation::parse)
+        {
+            check(false, "log_rotation", "LOG_ROTATION", e);
+        }
+        check(
+            positive(self.log_file_max_files.map(|files| files as f64)),
+            "log_file_max_files",
+            "LOG_FILE_MAX_FILES",
+            "must be greater than 0".to_string(),
+        );
+        if let Some(level) = &self.log_level {
+            if let Err(e) = tracing_subscriber::EnvFilter::try_new(level) {
+                check(false, "log_level", "LOG_LEVEL", format!("{level}: {e}"));
+            }
+        }
+        check(
+            self.tls_cert_path.is_some() == self.tls_key_path.is_some(),
+            "tls_cert_path/tls_key_path",
+            "TLS_CERT_PATH/TLS_KEY_PATH",
+            "must be set together".to_string(),
+        );
+        check(
+            self.tls_client_ca_path.is_none() || self.tls_cert_path.is_some(),
+            "tls_client_ca_path",
+            "TLS_CLIENT_CA_PATH",
+            "requires tls_cert_path and tls_key_path".to_string(),
+        );
+        check(
+            self.api2_client_cert_path.is_some() == self.api2_client_key_path.is_some(),
+            "api2_client_cert_path/api2_client_key_path",
+            "API2_CLIENT_CERT_PATH/API2_CLIENT_KEY_PATH",
+            "must be set together".to_string(),
+        );
+        #[cfg(unix)]
+        {
+            use crate::unix::{parse_perms, SocketMode};
+            match self.socket_mode.as_deref().map(SocketMode::parse) {
+                Some(Err(e)) => check(false, "socket_mode", "SOCKET_MODE", e),
+                Some(Ok(mode)) => check(
+                    !mode.unix() || self.unix_socket_path.is_some(),
+                    "unix_socket_path",
+                    "UNIX_SOCKET_PATH",
+                    format!("required when socket_mode is {mode:?}"),
+                ),
+                None => {}
+            }
+            if let Some(Err(e)) = self.unix_socket_perms.as_deref().map(parse_perms) {
+                check(false, "unix_socket_perms", "UNIX_SOCKET_PERMS", e);
+            }
+        }
+
+        errors
+    }
+
+    /// Time allowed for in-flight requests after a shutdown signal.
+    pub fn shutdown_timeout(&self) -> Duration {
+        self.shutdown_timeout_secs
+            .map_or(DEFAULT_SHUTDOWN_TIMEOUT, Duration::from_secs)
+    }
+
+    /// Time between a shutdown signal and closing the listeners, during
+    /// which health checks fail; none unless configured.
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay_secs.unwrap_or(0))
+    }
+
+    /// Address to listen on, all IPv4 interfaces unless configured.
+    pub fn bind_address(&self) -> IpAddr {
+        self.bind_address
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+
+    /// Accepted `X-Api-Key` values, including tenants' keys; empty when
+    /// none are configured.
+    pub fn api_keys(&self) -> Vec<String> {
+        let tenant_keys = crate::tenants::Tenants::from_config(self).api_keys();
+        let mut api_keys = self.api_keys.clone().unwrap_or_default();
+        api_keys.extend(tenant_keys);
+        api_keys
+    }
+
+    /// Maximum timezones per `/time/batch` call.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size.unwrap_or(crate::DEFAULT_MAX_BATCH_SIZE)
+    }
+}
+
+/// Reads environment variables, collecting one message per variable that
+/// is set but does not parse.
+#[derive(Default)]
+struct Env {
+    errors: Vec<String>,
+}
+
+impl Env {
+    /// A variable's parsed value; a blank one counts as unset.
+    fn get<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        let value = std::env::var(name).ok()?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.errors.push(format!("{name}: invalid value {value:?}"));
+                None
+            }
+        }
+    }
+
+    /// A comma-separated variable; a blank one counts as unset.
+    fn list(&mut self, name: &str) -> Option<Vec<String>> {
+        let value = std::env::var(name).ok()?;
+        let entries: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect();
+        (!entries.is_empty()).then_some(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Held by tests that set environment variables, which `load` reads.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn loads_file_from_config_path_with_env_overrides() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+                port = 8080
+                api2_url = "http://time-provider:4000"
+                timeout_ms = 1500
+                max_retries = 1
+                allowed_origins = ["https://app.example.com", "https://staging.example.com"]
+                shutdown_timeout_secs = 5
+            "#,
+        )
+        .unwrap();
+
+        let _env = ENV.lock().unwrap();
+        std::env::set_var("CONFIG_PATH", &path);
+        let config = Config::load();
+        assert_eq!(
+            config,
+            Config {
+                port: Some(8080),
+                api2_url: Some("http://time-provider:4000".to_string()),
+                timeout_ms: Some(1500),
+                max_retries: Some(1),
+                allowed_origins: Some(vec![
+                    "https://app.example.com".to_string(),
+                    "https://staging.example.com".to_string(),
+                ]),
+                shutdown_timeout_secs: Some(5),
+                ..Config::default()
+            }
+        );
+        assert_eq!(config.shutdown_timeout(), Duration::from_secs(5));
+
+        std::env::set_var("API2_TIMEOUT_MS", "250");
+        std::env::set_var("ALLOWED_ORIGINS", "https://override.example.com");
+        let config = Config::load();
+        assert_eq!(config.timeout_ms, Some(250));
+        assert_eq!(
+            config.allowed_origins.unwrap(),
+            ["https://override.example.com"]
+        );
+        assert_eq!(config.max_retries, Some(1));
+
+        std::env::remove_var("API2_TIMEOUT_MS");
+        std::env::remove_var("ALLOWED_ORIGINS");
+        std::env::remove_var("CONFIG_PATH");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn lists_every_invalid_setting() {
+        let _env = ENV.lock().unwrap();
+        std::env::set_var("CIRCUIT_BREAKER_THRESHOLD", "many");
+        std::env::set_var("BIND_ADDRESS", "localhost");
+        let errors = Config::default().with_env_overrides().unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "BIND_ADDRESS: invalid value \"localhost\"",
+                "CIRCUIT_BREAKER_THRESHOLD: invalid value \"many\"",
+            ]
+        );
+        std::env::set_var("BIND_ADDRESS", "127.0.0.1");
+        std::env::set_var("CIRCUIT_BREAKER_THRESHOLD", " ");
+        let config = Config::default().with_env_overrides().unwrap();
+        assert_eq!(config.bind_address(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(config.circuit_breaker_threshold, None);
+        std::env::remove_var("BIND_ADDRESS");
+        std::env::remove_var("CIRCUIT_BREAKER_THRESHOLD");
+
+        let config = Config {
+            max_batch_size: Some(0),
+            log_format: Some("xml".to_string()),
+            tls_cert_path: Some("cert.pem".to_string()),
+            api2_url: Some("time-provider".to_string()),
+            ..Config::default()
+        };
+        let errors = config.validated().unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert_eq!(
+            errors[0],
+            "max_batch_size (MAX_BATCH_SIZE): must be greater than 0"
+        );
+        assert!(errors[1].starts_with("api2_url (API2_URL(S)): time-provider: "));
+        assert!(errors[2].starts_with("log_format (LOG_FORMAT): "));
+        assert_eq!(
+            errors[3],
+            "tls_cert_path/tls_key_path (TLS_CERT_PATH/TLS_KEY_PATH): must be set together"
+        );
+        assert!(Config::default().validated().is_ok());
+
+        let config = Config {
+            circuit_breaker_threshold: Some(0),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["circuit_breaker_threshold (CIRCUIT_BREAKER_THRESHOLD): must be greater than 0"]
+        );
+
+        let config = Config {
+            audit_retention_days: Some(36_501),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["audit_retention_days (AUDIT_RETENTION_DAYS): must be at most 36500"]
+        );
+
+        let config = Config {
+            tls_client_ca_path: Some("ca.pem".to_string()),
+            api2_client_key_path: Some("client.key".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            [
+                "tls_client_ca_path (TLS_CLIENT_CA_PATH): requires tls_cert_path and tls_key_path",
+                "api2_client_cert_path/api2_client_key_path (API2_CLIENT_CERT_PATH/API2_CLIENT_KEY_PATH): must be set together",
+            ]
+        );
+
+        let config = Config {
+            api2_unix_socket: Some("/run/api2.sock".to_string()),
+            api2_urls: Some(vec![
+                "http://a:4000".to_string(),
+                "http://b:4000".to_string(),
+            ]),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["api2_unix_socket (API2_UNIX_SOCKET): reaches a single API2 instance; list at most one API2 URL"]
+        );
+
+        let config = Config {
+            api2_lb_strategy: Some("least-connections".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["api2_lb_strategy (API2_LB_STRATEGY): Invalid load-balancing strategy: least-connections \
+              (expected round-robin, random or least-recently-failed)"]
+        );
+
+        let config = Config {
+            dashboard_timezones: Some(vec!["Asia/Bangkok".to_string(), "Mars/Olympus".to_string()]),
+            feature_flags: Some(vec!["time_ws=off".to_string()]),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["dashboard_timezones (DASHBOARD_TIMEZONES): unknown timezone Mars/Olympus"]
+        );
+
+        let config = Config {
+            chaos_rules: Some(vec!["/time=error:0.5:503".to_string()]),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["chaos_rules (CHAOS_RULES): requires chaos_enabled"]
+        );
+        let config = Config {
+            chaos_enabled: Some(true),
+            chaos_rules: Some(vec!["/time=error:0.5:503".to_string()]),
+            ..Config::default()
+        };
+        assert!(config.validated().is_ok());
+
+        let config = Config {
+            tenants: Some(vec![
+                "team-a;keys=key-a;timezone=Asia/Bangkok".to_string(),
+                "team-b;timezone=Mars/Olympus".to_string(),
+            ]),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.validated().unwrap_err(),
+            ["tenants (TENANTS): Unknown timezone for tenant team-b: Mars/Olympus"]
+        );
+        let config = Config {
+            api_keys: Some(vec!["operator-key".to_string()]),
+            tenants: Some(vec!["team-a;keys=key-a|key-a2".to_string()]),
+            ..Config::default()
+        };
+        assert_eq!(config.api_keys(), ["operator-key", "key-a", "key-a2"]);
+    }
+
+    #[test]
+    fn rejects_unknown_and_mistyped_keys() {
+        assert!(Config::from_toml("prot = 3000")
+            .unwrap_err()
+            .contains("unknown field"));
+        assert!(Config::from_toml("port = \"3000\"").is_err());
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+}
@@ -0,0 +1,96 @@
+//! Connection handling shared by the accept loops that `axum::serve` cannot
+//! run: TLS and Unix domain sockets.
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, watch};
+use tower::Service;
+use tracing::debug;
+
+/// Tracks spawned connections so a server can stop accepting, ask them to
+/// finish their in-flight requests, and wait until they have.
+pub(crate) struct Connections {
+    closing: watch::Sender<bool>,
+    // Every connection task holds a sender; `recv` yields `None` once all are gone.
+    open: mpsc::Sender<()>,
+    all_closed: mpsc::Receiver<()>,
+}
+
+impl Connections {
+    pub(crate) fn new() -> Self {
+        let (closing, _) = watch::channel(false);
+        let (open, all_closed) = mpsc::channel(1);
+        Connections {
+            closing,
+            open,
+            all_closed,
+        }
+    }
+
+    /// Spawns `connection`, which receives a flag that turns `true` once
+    /// the server starts draining.
+    pub(crate) fn spawn<F, Fut>(&self, connection: F)
+    where
+        F: FnOnce(watch::Receiver<bool>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let open = self.open.clone();
+        let connection = connection(self.closing.subscribe());
+        tokio::spawn(async move {
+            let _open = open;
+            connection.await;
+        });
+    }
+
+    /// Signals every connection to close once idle, then waits for them.
+    pub(crate) async fn drain(mut self) {
+        let _ = self.closing.send(true);
+        drop(self.open);
+        self.all_closed.recv().await;
+    }
+}
+
+/// Serves HTTP/1.1 (with upgrades) on one connection until the client
+/// disconnects or `closing` turns `true` and in-flight requests are done.
+/// `remote` is exposed to handlers as `ConnectInfo<SocketAddr>` when known.
+pub(crate) async fn serve_http1<I>(
+    io: I,
+    router: Router,
+    remote: Option<SocketAddr>,
+    mut closing: watch::Receiver<bool>,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |request: Request<Incoming>| {
+        let mut request = request.map(Body::new);
+        if let Some(remote) = remote {
+            request.extensions_mut().insert(ConnectInfo(remote));
+        }
+        router.clone().call(request)
+    });
+    let connection = http1::Builder::new()
+        .serve_connection(TokioIo::new(io), service)
+        .with_upgrades();
+    tokio::pin!(connection);
+    let closing = async move {
+        let _ = closing.wait_for(|closing| *closing).await;
+    };
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        () = closing => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+    if let Err(e) = result {
+        debug!(client = ?remote, error = %e, "Connection error");
+    }
+}
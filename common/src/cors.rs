@@ -0,0 +1,444 @@
+//! CORS policy configured from `allowed_origins`, `allowed_methods`,
+//! `allowed_headers`, `cors_max_age_secs` and `cors_allow_credentials`.
+//!
+//! Only the listed origins may make cross-origin requests; with none listed,
+//! none may. An origin such as `https://*.example.com` allows every subdomain
+//! of `example.com` over `https`, but not `example.com` itself. Allowing any
+//! origin, method or header needs `cors_allow_any`, meant for local
+//! development: it makes every unset list, or one containing `*`, allow
+//! anything.
+
+use axum::http::{request::Parts, HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+use crate::auth::API_KEY_HEADER;
+use crate::config::Config;
+use crate::limits::REQUEST_TIMEOUT_HEADER;
+use crate::trace_context::{TRACEPARENT_HEADER, TRACESTATE_HEADER};
+use crate::REQUEST_ID_HEADER;
+
+/// Methods allowed when `allowed_methods` is unset.
+const DEFAULT_METHODS: [Method; 4] = [Method::GET, Method::HEAD, Method::POST, Method::DELETE];
+
+/// Request headers allowed when `allowed_headers` is unset: the ones either
+/// service reads.
+const DEFAULT_HEADERS: [&str; 7] = [
+    "content-type",
+    REQUEST_ID_HEADER,
+    API_KEY_HEADER,
+    REQUEST_TIMEOUT_HEADER,
+    TRACEPARENT_HEADER,
+    TRACESTATE_HEADER,
+    "idempotency-key",
+];
+
+/// An entry of `allowed_origins`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OriginPattern {
+    Exact(HeaderValue),
+    /// `scheme://*.domain[:port]`, kept as the text around the `*`.
+    Subdomains {
+        prefix: String,
+        suffix: String,
+    },
+}
+
+impl OriginPattern {
+    fn parse(entry: &str) -> Option<Self> {
+        let Some((prefix, suffix)) = entry.split_once('*') else {
+            return HeaderValue::from_str(entry).ok().map(OriginPattern::Exact);
+        };
+        let scheme = prefix.strip_suffix("://")?;
+        let valid = !scheme.is_empty()
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric())
+            && suffix.len() > 1
+            && suffix.starts_with('.')
+            && !suffix.contains(['*', '/'])
+            && HeaderValue::from_str(suffix).is_ok();
+        valid.then(|| OriginPattern::Subdomains {
+            prefix: prefix.to_ascii_lowercase(),
+            suffix: suffix.to_ascii_lowercase(),
+        })
+    }
+
+    pub fn matches(&self, origin: &HeaderValue) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed == origin,
+            OriginPattern::Subdomains { prefix, suffix } => {
+                let Ok(origin) = origin.to_str() else {
+                    return false;
+                };
+                let origin = origin.to_ascii_lowercase();
+                origin
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|rest| rest.strip_suffix(suffix.as_str()))
+                    .is_some_and(|subdomain| {
+                        !subdomain.is_empty() && subdomain.split('.').all(is_dns_label)
+                    })
+            }
+        }
+    }
+}
+
+/// A parsed list, or what an unset one allows.
+fn or_default<T>(
+    list: Option<Option<Vec<T>>>,
+    default: Option<Vec<T>>,
+    allow_any: bool,
+) -> Option<Vec<T>> {
+    match list {
+        Some(list) => list,
+        None if allow_any => None,
+        None => default,
+    }
+}
+
+fn is_dns_label(label: &str) -> bool {
+    !label.is_empty()
+        && !label.starts_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Allowed origins, methods and headers; `None` allows any value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    pub origins: Option<Vec<OriginPattern>>,
+    pub methods: Option<Vec<Method>>,
+    pub headers: Option<Vec<HeaderName>>,
+    /// How long browsers may cache a preflight response.
+    pub max_age: Option<Duration>,
+    /// Sends `Access-Control-Allow-Credentials: true`, so browsers include
+    /// cookies and `Authorization` headers.
+    pub allow_credentials: bool,
+}
+
+/// No cross-origin access, with the default methods and headers for when
+/// origins are added.
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        CorsPolicy {
+            origins: Some(Vec::new()),
+            methods: Some(DEFAULT_METHODS.to_vec()),
+            headers: Some(
+                DEFAULT_HEADERS
+                    .iter()
+                    .map(|name| HeaderName::from_static(name))
+                    .collect(),
+            ),
+            max_age: None,
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsPolicy {
+    /// Builds the policy from the loaded configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the offending setting and entry when a list contains a
+    /// malformed value, so a typo cannot silently widen or narrow access.
+    pub fn from_config(config: &Config) -> Self {
+        let policy = CorsPolicy::parse(
+            config.allowed_origins.as_deref().unwrap_or_default(),
+            config.allowed_methods.as_deref().unwrap_or_default(),
+            config.allowed_headers.as_deref().unwrap_or_default(),
+            config.cors_allow_any.unwrap_or(false),
+        )
+        .unwrap_or_else(|e| panic!("Invalid CORS configuration: {e}"));
+        CorsPolicy {
+            max_age: config.cors_max_age_secs.map(Duration::from_secs),
+            allow_credentials: config.cors_allow_credentials.unwrap_or(false),
+            ..policy
+        }
+    }
+
+    /// Parses the allowed lists. An empty list keeps the default: no origins,
+    /// and the methods and headers the services use. With `allow_any`, an
+    /// empty list or one containing `*` allows anything; without it, `*` is
+    /// an error.
+    pub fn parse(
+        origins: &[String],
+        methods: &[String],
+        headers: &[String],
+        allow_any: bool,
+    ) -> Result<Self, String> {
+        let default = CorsPolicy::default();
+        Ok(CorsPolicy {
+            origins: or_default(
+                parse_list("allowed_origins", origins, allow_any, OriginPattern::parse)?,
+                default.origins,
+                allow_any,
+            ),
+            methods: or_default(
+                parse_list("allowed_methods", methods, allow_any, |method| {
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes()).ok()
+                })?,
+                default.methods,
+                allow_any,
+            ),
+            headers: or_default(
+                parse_list("allowed_headers", headers, allow_any, |header| {
+                    HeaderName::from_bytes(header.as_bytes()).ok()
+                })?,
+                default.headers,
+                allow_any,
+            ),
+            ..default
+        })
+    }
+
+    /// `Access-Control-Allow-*: *` cannot be combined with credentials, so
+    /// "any" is sent by echoing the request instead.
+    pub fn layer(&self) -> CorsLayer {
+        let origins = self.origins.clone();
+        let layer = CorsLayer::new()
+            .allow_origin(match origins {
+                Some(origins) => AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| {
+                    origins.iter().any(|pattern| pattern.matches(origin))
+                }),
+                None => AllowOrigin::mirror_request(),
+            })
+            .allow_methods(match &self.methods {
+                Some(methods) => AllowMethods::list(methods.iter().cloned()),
+                None => AllowMethods::mirror_request(),
+            })
+            .allow_headers(match &self.headers {
+                Some(headers) => AllowHeaders::list(headers.iter().cloned()),
+                None => AllowHeaders::mirror_request(),
+            })
+            .allow_credentials(self.allow_credentials);
+        match self.max_age {
+            Some(max_age) => layer.max_age(max_age),
+            None => layer,
+        }
+    }
+}
+
+/// `Ok(None)` for `*` and `Ok(Some(vec![]))` for an empty list; the caller
+/// decides what an empty list means.
+fn parse_list<T>(
+    name: &str,
+    values: &[String],
+    allow_any: bool,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<Option<Vec<T>>>, String> {
+    let entries: Vec<&str> = values
+        .iter()
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    if entries.contains(&"*") {
+        return if allow_any {
+            Ok(Some(None))
+        } else {
+            Err(format!(
+                "{name} contains *, which needs CORS_ALLOW_ANY=true"
+            ))
+        };
+    }
+    entries
+        .into_iter()
+        .map(|entry| {
+            parse(entry).ok_or_else(|| format!("{name} contains malformed entry {entry:?}"))
+        })
+        .collect::<Result<_, _>>()
+        .map(|list| Some(Some(list)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::{routing::get, Router};
+    use tower::Service;
+
+    fn list(values: &str) -> Vec<String> {
+        values.split(',').map(str::to_string).collect()
+    }
+
+    fn exact(origin: &'static str) -> OriginPattern {
+        OriginPattern::Exact(HeaderValue::from_static(origin))
+    }
+
+    #[test]
+    fn parses_comma_separated_lists() {
+        let policy = CorsPolicy::parse(
+            &list("https://app.example.com, https://*.staging.example.com"),
+            &list("get,POST"),
+            &list("content-type, X-Request-ID"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.origins.unwrap(),
+            [
+                exact("https://app.example.com"),
+                OriginPattern::Subdomains {
+                    prefix: "https://".to_string(),
+                    suffix: ".staging.example.com".to_string(),
+                },
+            ]
+        );
+        assert_eq!(policy.methods.unwrap(), [Method::GET, Method::POST]);
+        assert_eq!(
+            policy.headers.unwrap(),
+            [
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-request-id")
+            ]
+        );
+    }
+
+    #[test]
+    fn unset_or_blank_allows_no_origins() {
+        let policy = CorsPolicy::parse(&[], &[], &[], false).unwrap();
+        assert_eq!(policy, CorsPolicy::default());
+        assert_eq!(policy.origins, Some(Vec::new()));
+        assert!(policy.headers.unwrap().contains(&header::CONTENT_TYPE));
+        assert_eq!(
+            CorsPolicy::parse(&list(" "), &list(""), &list(""), false).unwrap(),
+            CorsPolicy::default()
+        );
+    }
+
+    #[test]
+    fn wildcard_needs_allow_any() {
+        let error = CorsPolicy::parse(&list("*"), &[], &[], false).unwrap_err();
+        assert_eq!(
+            error,
+            "allowed_origins contains *, which needs CORS_ALLOW_ANY=true"
+        );
+        assert!(CorsPolicy::parse(&[], &list("*"), &[], false).is_err());
+
+        let policy = CorsPolicy::parse(&list("*"), &list("*"), &list("*"), true).unwrap();
+        assert_eq!(policy.origins, None);
+        assert_eq!(policy.methods, None);
+        assert_eq!(policy.headers, None);
+        assert_eq!(CorsPolicy::parse(&[], &[], &[], true).unwrap(), policy);
+
+        // Lists still narrow what the flag allows.
+        let policy = CorsPolicy::parse(&[], &list("GET"), &[], true).unwrap();
+        assert_eq!(policy.origins, None);
+        assert_eq!(policy.methods.unwrap(), [Method::GET]);
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        let error = CorsPolicy::parse(&list("https://ok.example.com,bad\norigin"), &[], &[], false)
+            .unwrap_err();
+        assert!(error.starts_with("allowed_origins"), "{error}");
+        for origin in [
+            "*.example.com",
+            "https://*",
+            "https://*.",
+            "https://a*.example.com",
+            "https://*.*.example.com",
+        ] {
+            assert!(
+                CorsPolicy::parse(&list(origin), &[], &[], false).is_err(),
+                "{origin}"
+            );
+        }
+        assert!(CorsPolicy::parse(&[], &list("GET,NOT A METHOD"), &[], false).is_err());
+        assert!(CorsPolicy::parse(&[], &[], &list("x-ok,bad header"), false).is_err());
+    }
+
+    #[test]
+    fn matches_subdomains_of_the_pattern_only() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+        let matches = |origin: &'static str| pattern.matches(&HeaderValue::from_static(origin));
+        assert!(matches("https://app.example.com"));
+        assert!(matches("https://a.b.Example.com"));
+        assert!(!matches("https://example.com"));
+        assert!(!matches("http://app.example.com"));
+        assert!(!matches("https://app.example.com:8443"));
+        assert!(!matches("https://evil.com/.example.com"));
+        assert!(!matches("https://app.example.com.evil.com"));
+    }
+
+    async fn preflight(policy: &CorsPolicy, origin: &'static str) -> axum::response::Response {
+        let mut router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(policy.layer());
+        let request = Request::options("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap();
+        // Router is always ready, so poll_ready can be skipped.
+        router.call(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn omits_cors_headers_for_disallowed_origins() {
+        let policy = CorsPolicy::parse(
+            &list("https://app.example.com,https://*.preview.example.com"),
+            &[],
+            &[],
+            false,
+        )
+        .unwrap();
+        let mut router = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(policy.layer());
+        let mut send = |origin| {
+            let request = Request::get("/")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap();
+            // Router is always ready, so poll_ready can be skipped.
+            router.call(request)
+        };
+
+        let allowed = send("https://app.example.com").await.unwrap();
+        assert_eq!(
+            allowed.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        let preview = send("https://pr-42.preview.example.com").await.unwrap();
+        assert_eq!(
+            preview.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://pr-42.preview.example.com"
+        );
+
+        let denied = send("https://evil.example.com").await.unwrap();
+        assert!(!denied
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let closed = preflight(&CorsPolicy::default(), "https://app.example.com").await;
+        assert!(!closed
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn sends_max_age_and_credentials() {
+        let policy = CorsPolicy {
+            max_age: Some(Duration::from_secs(600)),
+            allow_credentials: true,
+            ..CorsPolicy::parse(&list("https://app.example.com"), &[], &[], false).unwrap()
+        };
+        let response = preflight(&policy, "https://app.example.com").await;
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        // Any origin is echoed back, since `*` is not allowed with credentials.
+        let any = CorsPolicy {
+            allow_credentials: true,
+            ..CorsPolicy::parse(&[], &[], &[], true).unwrap()
+        };
+        let response = preflight(&any, "http://localhost:5173").await;
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:5173"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! ETags and `If-None-Match` for responses that polling clients fetch again
+//! and again, such as `/timezones`.
+//!
+//! [`conditional`] tags each `200` response to a `GET` with a weak ETag of
+//! its body, and answers `304 Not Modified` without a body when the request's
+//! `If-None-Match` already names it. The tag is weak because compression
+//! changes the bytes sent but not the content.
+//!
+//! A JSON body's top-level `request_id` is left out of the tag, since it
+//! differs on every request while the content it belongs to may not: `/time`
+//! with `format=unix` keeps its tag for a whole second.
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+/// Middleware adding an ETag to successful `GET` responses and answering
+/// `304` to requests that already have it.
+pub async fn conditional(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let if_none_match = request.headers().get(IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    // Streams are never complete enough to tag.
+    if response.status() != StatusCode::OK || response.body().size_hint().exact().is_none() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let is_json = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    let etag = etag(&body, is_json);
+    parts.headers.insert(
+        ETAG,
+        HeaderValue::from_str(&etag).expect("ETags are hex digits"),
+    );
+
+    if if_none_match.is_some_and(|tags| matches(&tags, &etag)) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(CONTENT_TYPE);
+        parts.headers.remove(CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// `W/"<hash>"` of `body`, without its `request_id` when it is JSON.
+fn etag(body: &[u8], is_json: bool) -> String {
+    let hash = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(mut fields)) if is_json && fields.contains_key("request_id") => {
+            fields.remove("request_id");
+            fnv1a(Value::Object(fields).to_string().as_bytes())
+        }
+        _ => fnv1a(body),
+    };
+    format!("W/\"{hash:016x}\"")
+}
+
+/// FNV-1a, which unlike `DefaultHasher` gives the same tag on every
+/// instance and release.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether an `If-None-Match` list names `etag`, comparing weakly as
+/// RFC 9110 requires for it.
+fn matches(if_none_match: &HeaderValue, etag: &str) -> bool {
+    let Ok(tags) = if_none_match.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    tags.split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use tower::Service;
+
+    async fn get_with(app: &mut Router, uri: &str, if_none_match: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(tags) = if_none_match {
+            request = request.header(IF_NONE_MATCH, tags);
+        }
+        // Router is always ready, so poll_ready can be skipped.
+        app.call(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn answers_304_to_a_matching_if_none_match() {
+        let mut app = Router::new()
+            .route("/zones", get(|| async { Json(["Asia/Bangkok", "UTC"]) }))
+            .route_layer(axum::middleware::from_fn(conditional));
+
+        let response = get_with(&mut app, "/zones", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+        assert!(etag.starts_with("W/\""), "{etag}");
+
+        let strong = etag.trim_start_matches("W/");
+        for tags in [etag.as_str(), strong, "\"other\", *"] {
+            let response = get_with(&mut app, "/zones", Some(tags)).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{tags}");
+            assert_eq!(response.headers()[ETAG], etag.as_str());
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.is_empty());
+        }
+
+        let response = get_with(&mut app, "/zones", Some("W/\"0\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn ignores_request_ids_in_json_bodies() {
+        let first = etag(br#"{"timestamp":"1","request_id":"a"}"#, true);
+        assert_eq!(first, etag(br#"{"timestamp":"1","request_id":"b"}"#, true));
+        assert_ne!(first, etag(br#"{"timestamp":"2","request_id":"a"}"#, true));
+        assert_ne!(
+            etag(br#"{"request_id":"a"}"#, false),
+            etag(br#"{"request_id":"b"}"#, false)
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Feature flags turning risky endpoints on and off at runtime.
+//!
+//! Each flag covers a fixed set of routes, under any API version prefix.
+//! A flag is `on` (the default), `off`, which answers its routes with `404`
+//! as if they did not exist so they can ship dark, or `unavailable`, which
+//! answers `503` for a feature that exists but is paused. `feature_flags`
+//! sets the states at startup, written `NAME=STATE`, and each service's
+//! `/admin/flags` endpoint reports and changes them. The states are also
+//! listed in `/health` and as an always-passing `feature_flags` readiness
+//! check.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+use crate::config::Config;
+use crate::health::Check;
+use crate::problem::ErrorCode;
+use crate::versioning::{V1_PREFIX, V2_PREFIX};
+use crate::ErrorResponse;
+use crate::REQUEST_ID_HEADER;
+
+pub const ADMIN_PATH: &str = "/admin/flags";
+
+/// Every flag, with the routes it covers; a route covers the paths below it.
+pub const FLAGS: [(&str, &[&str]); 3] = [
+    ("time_stream", &["/time/stream"]),
+    ("time_ws", &["/time/ws"]),
+    ("alarms", &["/alarms"]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagState {
+    On,
+    /// Routes answer `404`.
+    Off,
+    /// Routes answer `503`.
+    Unavailable,
+}
+
+impl FlagState {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "on" => Some(FlagState::On),
+            "off" => Some(FlagState::Off),
+            "unavailable" => Some(FlagState::Unavailable),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a `feature_flags` entry, e.g. `time_ws=off`.
+pub fn parse_setting(value: &str) -> Result<(&'static str, FlagState), String> {
+    let invalid = || format!("Invalid feature flag: {value} (expected NAME=on|off|unavailable)");
+    let (name, state) = value.split_once('=').ok_or_else(invalid)?;
+    let state = FlagState::parse(state).ok_or_else(invalid)?;
+    Ok((known(name.trim())?, state))
+}
+
+/// The static name of flag `name`, or an error listing the known flags.
+fn known(name: &str) -> Result<&'static str, String> {
+    FLAGS
+        .iter()
+        .map(|(flag, _)| *flag)
+        .find(|flag| *flag == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = FLAGS.iter().map(|(flag, _)| *flag).collect();
+            format!(
+                "Unknown feature flag: {name} (expected one of: {})",
+                names.join(", ")
+            )
+        })
+}
+
+/// Body of `PUT /admin/flags`: the flags to change and their new states.
+pub type FlagUpdate = BTreeMap<String, FlagState>;
+
+/// Every flag's state, as `/admin/flags` reports them.
+pub type FlagStates = BTreeMap<&'static str, FlagState>;
+
+/// The current state of every flag.
+pub struct FeatureFlags {
+    states: RwLock<FlagStates>,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        FeatureFlags::new(&[])
+    }
+}
+
+impl FeatureFlags {
+    /// Every flag on, except as `settings` say.
+    pub fn new(settings: &[(&'static str, FlagState)]) -> Self {
+        let mut states: FlagStates = FLAGS
+            .iter()
+            .map(|(name, _)| (*name, FlagState::On))
+            .collect();
+        states.extend(settings.iter().copied());
+        FeatureFlags {
+            states: RwLock::new(states),
+        }
+    }
+
+    /// Uses `feature_flags`, skipping entries that do not parse; config
+    /// validation rejects those first.
+    pub fn from_config(config: &Config) -> Self {
+        let settings: Vec<_> = config
+            .feature_flags
+            .iter()
+            .flatten()
+            .filter_map(|setting| parse_setting(setting).ok())
+            .collect();
+        FeatureFlags::new(&settings)
+    }
+
+    pub fn states(&self) -> FlagStates {
+        self.states.read().expect("flags lock poisoned").clone()
+    }
+
+    pub fn state(&self, name: &str) -> FlagState {
+        self.states
+            .read()
+            .expect("flags lock poisoned")
+            .get(name)
+            .copied()
+            .unwrap_or(FlagState::On)
+    }
+
+    /// Applies every change in `update`, or none when it names an unknown
+    /// flag, returning the new states.
+    pub fn update(&self, update: &FlagUpdate) -> Result<FlagStates, String> {
+        let changes = update
+            .iter()
+            .map(|(name, state)| Ok((known(name)?, *state)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut states = self.states.write().expect("flags lock poisoned");
+        states.extend(changes);
+        Ok(states.clone())
+    }
+
+    /// The flag covering `path`, with its state, if any does.
+    fn covering(&self, path: &str) -> Option<(&'static str, FlagState)> {
+        let path = [V1_PREFIX, V2_PREFIX]
+            .iter()
+            .find_map(|prefix| {
+                path.strip_prefix(prefix)
+                    .filter(|rest| rest.starts_with('/'))
+            })
+            .unwrap_or(path);
+        let (name, _) = FLAGS.iter().find(|(_, routes)| {
+            routes.iter().any(|route| {
+                path.strip_prefix(route)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+        })?;
+        Some((name, self.state(name)))
+    }
+
+    /// Readiness check that always passes, listing the flag states.
+    pub fn check(&self) -> Check {
+        Check::new("feature_flags", true, json!(self.states()))
+    }
+}
+
+/// Middleware answering `404` or `503` for routes whose flag is not on.
+pub async fn enforce(
+    State(flags): State<Arc<FeatureFlags>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (code, detail) = match flags.covering(request.uri().path()) {
+        None | Some((_, FlagState::On)) => return next.run(request).await,
+        Some((_, FlagState::Off)) => (ErrorCode::NotFound, "Not found".to_string()),
+        Some((name, FlagState::Unavailable)) => (
+            ErrorCode::Unavailable,
+            format!("{name} is temporarily unavailable"),
+        ),
+    };
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_string);
+    info!(
+        request_id = %request_id,
+        path = %request.uri().path(),
+        "Route disabled by feature flag"
+    );
+    ErrorResponse::new(code, detail, &request_id).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use tower::Service;
+
+    #[test]
+    fn parses_settings_and_rejects_unknown_flags() {
+        assert_eq!(
+            parse_setting(" time_ws = off"),
+            Ok(("time_ws", FlagState::Off))
+        );
+        assert_eq!(
+            parse_setting("alarms=unavailable"),
+            Ok(("alarms", FlagState::Unavailable))
+        );
+        assert!(parse_setting("alarms").is_err());
+        assert!(parse_setting("alarms=maybe").is_err());
+        assert!(parse_setting("teleport=on")
+            .unwrap_err()
+            .contains("time_stream"));
+
+        let flags = FeatureFlags::default();
+        let update = FlagUpdate::from([
+            ("alarms".to_string(), FlagState::Off),
+            ("teleport".to_string(), FlagState::Off),
+        ]);
+        assert!(flags.update(&update).is_err());
+        assert_eq!(flags.state("alarms"), FlagState::On);
+    }
+
+    #[tokio::test]
+    async fn hides_or_pauses_routes_whose_flag_is_not_on() {
+        let flags = Arc::new(FeatureFlags::new(&[("time_ws", FlagState::Off)]));
+        let mut app = Router::new()
+            .route("/time", get(|| async { "time" }))
+            .route("/v1/time/ws", get(|| async { "ws" }))
+            .route("/alarms/:id", get(|| async { "alarm" }))
+            .route("/alarmsx", get(|| async { "other" }))
+            .layer(axum::middleware::from_fn_with_state(flags.clone(), enforce));
+        let mut status = |uri: &str| {
+            let request = axum::http::Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.call(request);
+            async move { response.await.unwrap().status() }
+        };
+
+        assert_eq!(status("/time").await, StatusCode::OK);
+        assert_eq!(status("/v1/time/ws").await, StatusCode::NOT_FOUND);
+        assert_eq!(status("/alarms/1").await, StatusCode::OK);
+
+        flags
+            .update(&FlagUpdate::from([
+                ("time_ws".to_string(), FlagState::On),
+                ("alarms".to_string(), FlagState::Unavailable),
+            ]))
+            .unwrap();
+        assert_eq!(status("/v1/time/ws").await, StatusCode::OK);
+        assert_eq!(status("/alarms/1").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("/alarmsx").await, StatusCode::OK);
+        assert_eq!(flags.check().details["alarms"], "unavailable");
+    }
+}
@@ -0,0 +1,150 @@
+//! Timestamp formats selectable with the `format` query parameter.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, TimeZone};
+use std::fmt::Display;
+
+/// How `TimeResponse.timestamp` is rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 with the local offset, e.g. `2024-01-01T07:00:00+07:00`.
+    Rfc3339,
+    /// RFC 2822, as in email headers, e.g. `Mon, 1 Jan 2024 07:00:00 +0700`.
+    Rfc2822,
+    /// Whole seconds since the Unix epoch.
+    Unix,
+    /// Milliseconds since the Unix epoch.
+    UnixMs,
+    /// A `chrono` strftime pattern, validated when parsed.
+    Custom(String),
+}
+
+/// Longest accepted `custom:` pattern, which also bounds the output size.
+const MAX_PATTERN_LEN: usize = 64;
+
+impl TimestampFormat {
+    /// Parses `rfc3339`, `rfc2822`, `unix`, `unix_ms` or `custom:<STRFTIME>`.
+    ///
+    /// Custom patterns must be at most 64 characters of printable text and
+    /// valid `chrono` specifiers, so a request cannot ask for an unbounded
+    /// or control-character-laden timestamp.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "rfc2822" => Ok(TimestampFormat::Rfc2822),
+            "unix" => Ok(TimestampFormat::Unix),
+            "unix_ms" => Ok(TimestampFormat::UnixMs),
+            _ => match value.strip_prefix("custom:") {
+                Some(pattern) if pattern.chars().count() > MAX_PATTERN_LEN => Err(format!(
+                    "Format string too long: {} characters (at most {MAX_PATTERN_LEN})",
+                    pattern.chars().count()
+                )),
+                Some(pattern) if pattern.chars().any(char::is_control) => {
+                    Err("Format string must not contain control characters".to_string())
+                }
+                Some(pattern)
+                    if !pattern.is_empty()
+                        && !StrftimeItems::new(pattern).any(|item| item == Item::Error) =>
+                {
+                    Ok(TimestampFormat::Custom(pattern.to_string()))
+                }
+                Some(pattern) => Err(format!("Invalid format string: {pattern}")),
+                None => Err(format!(
+                    "Invalid format: {value} (expected rfc3339, rfc2822, unix, unix_ms or custom:<strftime>)"
+                )),
+            },
+        }
+    }
+
+    /// The format's name as reported in `TimeResponse.format`: the name it
+    /// is selected by, or `custom` for any pattern.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::Rfc2822 => "rfc2822",
+            TimestampFormat::Unix => "unix",
+            TimestampFormat::UnixMs => "unix_ms",
+            TimestampFormat::Custom(_) => "custom",
+        }
+    }
+
+    /// Parses an optional `format` parameter, defaulting to RFC 3339.
+    pub fn from_param(value: Option<&str>) -> Result<Self, String> {
+        value.map_or(Ok(TimestampFormat::Rfc3339), TimestampFormat::parse)
+    }
+
+    pub fn format<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        match self {
+            TimestampFormat::Rfc3339 => at.to_rfc3339(),
+            TimestampFormat::Rfc2822 => at.to_rfc2822(),
+            TimestampFormat::Unix => at.timestamp().to_string(),
+            TimestampFormat::UnixMs => at.timestamp_millis().to_string(),
+            TimestampFormat::Custom(pattern) => at.format(pattern).to_string(),
+        }
+    }
+}
+
+/// Formats a UTC offset in seconds as `±HH:MM`, e.g. `+05:30`.
+pub fn format_utc_offset(offset_seconds: i32) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let minutes = offset_seconds.unsigned_abs() / 60;
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn bangkok_new_year() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(7 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 1, 1, 7, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn formats_builtin_formats() {
+        let at = bangkok_new_year();
+        let format = |value| TimestampFormat::parse(value).unwrap().format(&at);
+        assert_eq!(format("rfc3339"), "2024-01-01T07:00:00+07:00");
+        assert_eq!(format("rfc2822"), "Mon, 1 Jan 2024 07:00:00 +0700");
+        assert_eq!(format("unix"), "1704067200");
+        assert_eq!(format("unix_ms"), "1704067200000");
+        assert_eq!(
+            TimestampFormat::from_param(None)
+                .unwrap()
+                .format(&Utc.timestamp_opt(0, 0).unwrap()),
+            "1970-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn formats_custom_strftime_pattern() {
+        let format = TimestampFormat::parse("custom:%Y/%m/%d %H:%M:%S %z").unwrap();
+        assert_eq!(
+            format.format(&bangkok_new_year()),
+            "2024/01/01 07:00:00 +0700"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_formats() {
+        assert!(TimestampFormat::parse("iso").is_err());
+        assert!(TimestampFormat::parse("custom:").is_err());
+        assert!(TimestampFormat::parse("custom:%Q").is_err());
+        assert!(TimestampFormat::parse("custom:%Y\n").is_err());
+        let long = format!("custom:{}", "%Y".repeat(33));
+        assert_eq!(
+            TimestampFormat::parse(&long).unwrap_err(),
+            "Format string too long: 66 characters (at most 64)"
+        );
+        assert_eq!(
+            TimestampFormat::parse("custom:%Y").unwrap().name(),
+            "custom"
+        );
+    }
+}
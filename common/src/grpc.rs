@@ -0,0 +1,1028 @@
+//! Unary gRPC over cleartext HTTP/2 for API1 → API2 calls.
+//!
+//! This is the small part of gRPC the services need: protobuf encoding of
+//! the messages in `proto/time.proto`, length-prefixed message framing and
+//! `grpc-status` handling, on hyper's HTTP/2 support. Servers report errors
+//! as trailers-only responses, so the status is always in the headers or in
+//! the trailers after a single message. Compressed messages and streaming
+//! RPCs are not supported.
+
+use hyper014::body::{Bytes, HttpBody};
+use hyper014::client::HttpConnector;
+use hyper014::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use hyper014::server::conn::AddrIncoming;
+use hyper014::service::{make_service_fn, service_fn};
+use hyper014::{Body, Request, Response};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::limits::{DEFAULT_MAX_BODY_BYTES, DEFAULT_REQUEST_TIMEOUT};
+use crate::{ConvertedTime, TimeConvertResponse, TimeQuery, TimeResponse, REQUEST_ID_HEADER};
+
+pub const GET_TIME_PATH: &str = "/time.v1.TimeService/GetTime";
+pub const CONVERT_TIME_PATH: &str = "/time.v1.TimeService/ConvertTime";
+
+const GRPC_CONTENT_TYPE: &str = "application/grpc";
+const STATUS_HEADER: &str = "grpc-status";
+const MESSAGE_HEADER: &str = "grpc-message";
+const TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// How API1 reaches API2, from `API2_TRANSPORT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Http,
+    Grpc,
+}
+
+impl Transport {
+    /// The configured transport; invalid values were rejected at startup.
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        config
+            .api2_transport
+            .as_deref()
+            .and_then(|value| Transport::parse(value).ok())
+            .unwrap_or(Transport::Http)
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "http" => Ok(Transport::Http),
+            "grpc" => Ok(Transport::Grpc),
+            _ => Err(format!(
+                "Invalid API2 transport: {value} (expected http or grpc)"
+            )),
+        }
+    }
+}
+
+/// The gRPC status codes these services use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    Ok = 0,
+    Unknown = 2,
+    InvalidArgument = 3,
+    DeadlineExceeded = 4,
+    NotFound = 5,
+    PermissionDenied = 7,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    Unauthenticated = 16,
+}
+
+impl Code {
+    fn from_value(value: &str) -> Code {
+        match value.trim().parse::<u8>() {
+            Ok(0) => Code::Ok,
+            Ok(3) => Code::InvalidArgument,
+            Ok(4) => Code::DeadlineExceeded,
+            Ok(5) => Code::NotFound,
+            Ok(7) => Code::PermissionDenied,
+            Ok(8) => Code::ResourceExhausted,
+            Ok(9) => Code::FailedPrecondition,
+            Ok(12) => Code::Unimplemented,
+            Ok(13) => Code::Internal,
+            Ok(14) => Code::Unavailable,
+            Ok(16) => Code::Unauthenticated,
+            _ => Code::Unknown,
+        }
+    }
+}
+
+/// A failed call: its code and a human-readable message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub code: Code,
+    pub message: String,
+}
+
+impl Status {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Status {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The code for an HTTP error status, so a handler shared with the HTTP
+    /// API can answer both. [`Status::http_status`] maps it back.
+    pub fn from_http(status: u16, message: impl Into<String>) -> Self {
+        let code = match status {
+            400 => Code::InvalidArgument,
+            401 => Code::Unauthenticated,
+            403 => Code::PermissionDenied,
+            404 => Code::NotFound,
+            413 => Code::ResourceExhausted,
+            422 => Code::FailedPrecondition,
+            503 => Code::Unavailable,
+            504 => Code::DeadlineExceeded,
+            _ => Code::Internal,
+        };
+        Status::new(code, message)
+    }
+
+    /// The HTTP status API1 answers with when a call fails with this status.
+    pub fn http_status(&self) -> u16 {
+        match self.code {
+            Code::InvalidArgument => 400,
+            Code::Unauthenticated => 401,
+            Code::PermissionDenied => 403,
+            Code::NotFound => 404,
+            Code::ResourceExhausted => 413,
+            Code::FailedPrecondition => 422,
+            Code::Unavailable => 503,
+            Code::DeadlineExceeded => 504,
+            _ => 502,
+        }
+    }
+}
+
+/// A protobuf message, encoded by hand following `proto/time.proto`.
+pub trait Message: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+    fn decode(bytes: &[u8]) -> Result<Self, String>;
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Proto3 omits default values, so empty strings and zeros are not written.
+fn put_string(buf: &mut Vec<u8>, field: u32, value: &str) {
+    if !value.is_empty() {
+        put_bytes(buf, field, value.as_bytes());
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    put_varint(buf, u64::from(field << 3 | 2));
+    put_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+/// `int32` and `int64` share this encoding: negatives are sign-extended.
+fn put_int(buf: &mut Vec<u8>, field: u32, value: i64) {
+    if value != 0 {
+        put_varint(buf, u64::from(field << 3));
+        put_varint(buf, value as u64);
+    }
+}
+
+fn put_message(buf: &mut Vec<u8>, field: u32, message: &impl Message) {
+    let mut nested = Vec::new();
+    message.encode(&mut nested);
+    put_bytes(buf, field, &nested);
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Value<'a> {
+    fn string(self) -> Result<String, String> {
+        match self {
+            Value::Bytes(bytes) => String::from_utf8(bytes.to_vec())
+                .map_err(|_| "string field is not UTF-8".to_string()),
+            Value::Varint(_) => Err("expected a string field".to_string()),
+        }
+    }
+
+    fn optional_string(self) -> Result<Option<String>, String> {
+        self.string()
+            .map(|value| Some(value).filter(|value| !value.is_empty()))
+    }
+
+    fn int(self) -> Result<i64, String> {
+        match self {
+            Value::Varint(value) => Ok(value as i64),
+            Value::Bytes(_) => Err("expected an integer field".to_string()),
+        }
+    }
+
+    fn int32(self) -> Result<i32, String> {
+        Ok(self.int()? as i32)
+    }
+
+    fn message<M: Message>(self) -> Result<M, String> {
+        match self {
+            Value::Bytes(bytes) => M::decode(bytes),
+            Value::Varint(_) => Err("expected a message field".to_string()),
+        }
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| "truncated varint".to_string())?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err("varint is too long".to_string())
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if bytes.len() < len {
+        return Err("truncated field".to_string());
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(value)
+}
+
+/// Calls `field` with each field in `bytes`, skipping fixed-width ones,
+/// which none of these messages use.
+fn read_fields<'a>(
+    mut bytes: &'a [u8],
+    mut field: impl FnMut(u32, Value<'a>) -> Result<(), String>,
+) -> Result<(), String> {
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        let number = (key >> 3) as u32;
+        match key & 7 {
+            0 => field(number, Value::Varint(read_varint(&mut bytes)?))?,
+            1 => drop(take(&mut bytes, 8)?),
+            2 => {
+                let len = read_varint(&mut bytes)? as usize;
+                field(number, Value::Bytes(take(&mut bytes, len)?))?;
+            }
+            5 => drop(take(&mut bytes, 4)?),
+            wire_type => return Err(format!("unsupported wire type {wire_type}")),
+        }
+    }
+    Ok(())
+}
+
+/// `GetTimeRequest`. The request ID travels in the `x-request-id` metadata.
+impl Message for TimeQuery {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_string(buf, 1, self.timezone.as_deref().unwrap_or_default());
+        put_string(buf, 2, self.format.as_deref().unwrap_or_default());
+        put_string(buf, 3, self.locale.as_deref().unwrap_or_default());
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut query = TimeQuery::default();
+        read_fields(bytes, |field, value| {
+            match field {
+                1 => query.timezone = value.optional_string()?,
+                2 => query.format = value.optional_string()?,
+                3 => query.locale = value.optional_string()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(query)
+    }
+}
+
+/// `TimeReply`.
+impl Message for TimeResponse {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_string(buf, 1, &self.timestamp);
+        put_string(buf, 2, &self.timezone);
+        put_string(buf, 3, &self.request_id);
+        put_string(buf, 4, &self.source);
+        put_int(buf, 5, self.utc_offset_seconds.unwrap_or_default().into());
+        put_string(buf, 6, self.utc_offset_label.as_deref().unwrap_or_default());
+        put_string(buf, 7, self.format.as_deref().unwrap_or_default());
+        put_string(buf, 8, self.display.as_deref().unwrap_or_default());
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut response = TimeResponse {
+            timestamp: String::new(),
+            timezone: String::new(),
+            request_id: String::new(),
+            source: String::new(),
+            utc_offset_seconds: Some(0),
+            utc_offset_label: None,
+            display: None,
+            format: None,
+            degraded: None,
+            clock_offset_ms: None,
+        };
+        read_fields(bytes, |field, value| {
+            match field {
+                1 => response.timestamp = value.string()?,
+                2 => response.timezone = value.string()?,
+                3 => response.request_id = value.string()?,
+                4 => response.source = value.string()?,
+                5 => response.utc_offset_seconds = Some(value.int32()?),
+                6 => response.utc_offset_label = value.optional_string()?,
+                7 => response.format = value.optional_string()?,
+                8 => response.display = value.optional_string()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(response)
+    }
+}
+
+/// `ConvertTimeRequest`; empty fields are unset, as in `GET /time/convert`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConvertTimeRequest {
+    pub timestamp: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Message for ConvertTimeRequest {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_string(buf, 1, self.timestamp.as_deref().unwrap_or_default());
+        put_string(buf, 2, self.from.as_deref().unwrap_or_default());
+        put_string(buf, 3, self.to.as_deref().unwrap_or_default());
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut request = ConvertTimeRequest::default();
+        read_fields(bytes, |field, value| {
+            match field {
+                1 => request.timestamp = value.optional_string()?,
+                2 => request.from = value.optional_string()?,
+                3 => request.to = value.optional_string()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(request)
+    }
+}
+
+impl Message for ConvertedTime {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_string(buf, 1, &self.timezone);
+        put_string(buf, 2, &self.timestamp);
+        put_int(buf, 3, self.utc_offset_seconds.into());
+        put_string(buf, 4, &self.utc_offset_label);
+        put_int(buf, 5, self.is_dst.into());
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut converted = ConvertedTime {
+            timezone: String::new(),
+            timestamp: String::new(),
+            utc_offset_seconds: 0,
+            utc_offset_label: String::new(),
+            is_dst: false,
+        };
+        read_fields(bytes, |field, value| {
+            match field {
+                1 => converted.timezone = value.string()?,
+                2 => converted.timestamp = value.string()?,
+                3 => converted.utc_offset_seconds = value.int32()?,
+                4 => converted.utc_offset_label = value.string()?,
+                5 => converted.is_dst = value.int()? != 0,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(converted)
+    }
+}
+
+/// `ConvertTimeReply`.
+impl Message for TimeConvertResponse {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_int(buf, 1, self.epoch_seconds);
+        put_message(buf, 2, &self.from);
+        put_message(buf, 3, &self.to);
+        put_string(buf, 4, &self.request_id);
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let (mut epoch_seconds, mut from, mut to, mut request_id) = (0, None, None, String::new());
+        read_fields(bytes, |field, value| {
+            match field {
+                1 => epoch_seconds = value.int()?,
+                2 => from = Some(value.message()?),
+                3 => to = Some(value.message()?),
+                4 => request_id = value.string()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        let missing = |name: &str| format!("missing field {name}");
+        Ok(TimeConvertResponse {
+            epoch_seconds,
+            from: from.ok_or_else(|| missing("from"))?,
+            to: to.ok_or_else(|| missing("to"))?,
+            request_id,
+        })
+    }
+}
+
+/// One message with gRPC's prefix: an uncompressed flag and the length.
+fn frame(message: &impl Message) -> Bytes {
+    let mut payload = Vec::new();
+    message.encode(&mut payload);
+    let mut framed = Vec::with_capacity(payload.len() + 5);
+    framed.push(0);
+    framed.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    framed.extend_from_slice(&payload);
+    framed.into()
+}
+
+/// The single message in a unary request or response body.
+fn unframe<M: Message>(body: &[u8]) -> Result<M, Status> {
+    let invalid = |message: String| Status::new(Code::Internal, message);
+    let [compressed, a, b, c, d, payload @ ..] = body else {
+        return Err(invalid("truncated gRPC message".to_string()));
+    };
+    if *compressed != 0 {
+        return Err(Status::new(
+            Code::Unimplemented,
+            "compressed messages are not supported",
+        ));
+    }
+    let len = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+    if payload.len() != len {
+        return Err(invalid(format!(
+            "gRPC message length {len} does not match body of {} bytes",
+            payload.len()
+        )));
+    }
+    M::decode(payload).map_err(invalid)
+}
+
+/// `grpc-message` is percent-encoded so any text fits in a header.
+fn encode_message(message: &str) -> String {
+    message
+        .bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => char::from(byte).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn decode_message(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A request body holding one message, refused as soon as its prefix
+/// declares more than `max_message_bytes` or more than that arrives.
+async fn read_message(mut body: Body, max_message_bytes: usize) -> Result<Bytes, Status> {
+    let too_large = || {
+        Status::new(
+            Code::ResourceExhausted,
+            format!("gRPC message exceeds the limit of {max_message_bytes} bytes"),
+        )
+    };
+    let mut message = Vec::new();
+    while let Some(chunk) = body.data().await {
+        message.extend_from_slice(&chunk.map_err(|e| Status::new(Code::Internal, e.to_string()))?);
+        if let [_, a, b, c, d, ..] = message[..] {
+            if u32::from_be_bytes([a, b, c, d]) as usize > max_message_bytes {
+                return Err(too_large());
+            }
+        }
+        if message.len() > max_message_bytes.saturating_add(5) {
+            return Err(too_large());
+        }
+    }
+    Ok(message.into())
+}
+
+/// A `grpc-timeout` value: up to eight digits and a unit, e.g. `250m`.
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let digits = &value[..value.len() - unit.len_utf8()];
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    match unit {
+        'H' => Some(Duration::from_secs(amount * 3600)),
+        'M' => Some(Duration::from_secs(amount * 60)),
+        'S' => Some(Duration::from_secs(amount)),
+        'm' => Some(Duration::from_millis(amount)),
+        'u' => Some(Duration::from_micros(amount)),
+        'n' => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Reads the status from response headers or trailers, if present.
+fn status_from(headers: &HeaderMap) -> Option<Result<(), Status>> {
+    let code = Code::from_value(headers.get(STATUS_HEADER)?.to_str().unwrap_or_default());
+    Some(match code {
+        Code::Ok => Ok(()),
+        code => Err(Status::new(
+            code,
+            headers
+                .get(MESSAGE_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(decode_message)
+                .unwrap_or_default(),
+        )),
+    })
+}
+
+/// Metadata a server handler receives with each call.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub request_id: Option<String>,
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = Result<Bytes, Status>> + Send>>;
+type Handler = Arc<dyn Fn(Metadata, Bytes) -> BoxFuture + Send + Sync>;
+type Authorizer = Arc<dyn Fn(&str, &HeaderMap) -> Result<(), Status> + Send + Sync>;
+
+/// Unary RPCs by path, served over HTTP/2 without TLS.
+///
+/// Like the HTTP routes, each call is held to a message size and a timeout
+/// ([`DEFAULT_MAX_BODY_BYTES`] and [`DEFAULT_REQUEST_TIMEOUT`] unless set
+/// with [`Server::limits`]), and to the check set with [`Server::authorize`].
+#[derive(Clone)]
+pub struct Server {
+    methods: HashMap<&'static str, Handler>,
+    max_message_bytes: usize,
+    timeout: Duration,
+    authorize: Option<Authorizer>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            methods: HashMap::new(),
+            max_message_bytes: DEFAULT_MAX_BODY_BYTES,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            authorize: None,
+        }
+    }
+}
+
+impl Server {
+    /// Refuses messages over `max_message_bytes` with
+    /// [`Code::ResourceExhausted`], from the length in their prefix and
+    /// before reading them, and fails calls running longer than `timeout`, or
+    /// the caller's shorter `grpc-timeout`, with [`Code::DeadlineExceeded`].
+    pub fn limits(mut self, max_message_bytes: usize, timeout: Duration) -> Self {
+        self.max_message_bytes = max_message_bytes;
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs `check` on each call's path and metadata before its message is
+    /// read, answering with the status it fails with instead.
+    pub fn authorize<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&str, &HeaderMap) -> Result<(), Status> + Send + Sync + 'static,
+    {
+        self.authorize = Some(Arc::new(check));
+        self
+    }
+
+    /// Routes `path`, e.g. [`GET_TIME_PATH`], to `handler`.
+    pub fn unary<Req, Resp, F, Fut>(mut self, path: &'static str, handler: F) -> Self
+    where
+        Req: Message + Send + 'static,
+        Resp: Message + 'static,
+        F: Fn(Metadata, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, Status>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.methods.insert(
+            path,
+            Arc::new(move |metadata, body| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    let request = unframe::<Req>(&body)
+                        .map_err(|e| Status::new(Code::InvalidArgument, e.message))?;
+                    handler(metadata, request).await.map(|reply| frame(&reply))
+                })
+            }),
+        );
+        self
+    }
+
+    async fn call(&self, request: Request<Body>) -> Response<UnaryBody> {
+        let result = self.answer(request).await;
+
+        let mut response = Response::new(UnaryBody::default());
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC_CONTENT_TYPE));
+        match result {
+            Ok(message) => {
+                let mut trailers = HeaderMap::new();
+                trailers.insert(STATUS_HEADER, HeaderValue::from(Code::Ok as u16));
+                *response.body_mut() = UnaryBody {
+                    message: Some(message),
+                    trailers: Some(trailers),
+                };
+            }
+            // Trailers-only: the status goes in the headers, with no body.
+            Err(status) => {
+                headers.insert(STATUS_HEADER, HeaderValue::from(status.code as u16));
+                if let Ok(message) = HeaderValue::from_str(&encode_message(&status.message)) {
+                    headers.insert(MESSAGE_HEADER, message);
+                }
+            }
+        }
+        response
+    }
+
+    async fn answer(&self, request: Request<Body>) -> Result<Bytes, Status> {
+        let path = request.uri().path().to_string();
+        let Some(handler) = self.methods.get(path.as_str()).cloned() else {
+            return Err(Status::new(
+                Code::Unimplemented,
+                format!("unknown method {path}"),
+            ));
+        };
+        if let Some(authorize) = &self.authorize {
+            authorize(&path, request.headers())?;
+        }
+        let timeout = match request.headers().get(TIMEOUT_HEADER) {
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(parse_timeout)
+                .ok_or_else(|| Status::new(Code::InvalidArgument, "Invalid grpc-timeout"))?
+                .min(self.timeout),
+            None => self.timeout,
+        };
+        let metadata = Metadata {
+            request_id: request
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+
+        let max_message_bytes = self.max_message_bytes;
+        let call = async move {
+            let body = read_message(request.into_body(), max_message_bytes).await?;
+            handler(metadata, body).await
+        };
+        tokio::time::timeout(timeout, call)
+            .await
+            .unwrap_or_else(|_| {
+                Err(Status::new(
+                    Code::DeadlineExceeded,
+                    format!("deadline of {}ms exceeded", timeout.as_millis()),
+                ))
+            })
+    }
+
+    /// Serves until `shutdown` resolves, then lets in-flight calls finish.
+    pub async fn serve(
+        self,
+        listener: tokio::net::TcpListener,
+        shutdown: impl Future<Output = ()>,
+    ) -> io::Result<()> {
+        let incoming = AddrIncoming::from_listener(listener).map_err(io::Error::other)?;
+        let server = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.call(request).await) }
+                }))
+            }
+        });
+        hyper014::Server::builder(incoming)
+            .http2_only(true)
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown)
+            .await
+            .map_err(io::Error::other)
+    }
+}
+
+/// A unary response body: one framed message, then the status trailers.
+#[derive(Default)]
+struct UnaryBody {
+    message: Option<Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl HttpBody for UnaryBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Infallible>>> {
+        Poll::Ready(self.message.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Infallible>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.message.is_none() && self.trailers.is_none()
+    }
+}
+
+/// Makes unary calls over a pooled HTTP/2 connection per server.
+#[derive(Clone)]
+pub struct Client {
+    http: hyper014::Client<HttpConnector, Body>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client {
+            http: hyper014::Client::builder().http2_only(true).build_http(),
+        }
+    }
+}
+
+impl Client {
+    /// Calls `path` on the server at `base_url`, e.g. `http://api2:50051`.
+    /// Exceeding `timeout` fails with [`Code::DeadlineExceeded`], and
+    /// transport failures with [`Code::Unavailable`].
+    pub async fn unary<Req: Message, Resp: Message>(
+        &self,
+        base_url: &str,
+        path: &str,
+        request: &Req,
+        request_id: &str,
+        timeout: Duration,
+    ) -> Result<Resp, Status> {
+        let unavailable = |e: &dyn std::fmt::Display| Status::new(Code::Unavailable, e.to_string());
+        let request = Request::post(format!("{}{path}", base_url.trim_end_matches('/')))
+            .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+            .header("te", "trailers")
+            .header(TIMEOUT_HEADER, format!("{}m", timeout.as_millis()))
+            .header(REQUEST_ID_HEADER, request_id)
+            .body(Body::from(frame(request)))
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+
+        let call = async {
+            let response = self
+                .http
+                .request(request)
+                .await
+                .map_err(|e| unavailable(&e))?;
+            if !response.status().is_success() {
+                return Err(Status::new(
+                    Code::Unavailable,
+                    format!("HTTP status {}", response.status()),
+                ));
+            }
+            if let Some(status) = status_from(response.headers()) {
+                status?;
+            }
+            let mut body = response.into_body();
+            let mut message = Vec::new();
+            while let Some(chunk) = body.data().await {
+                message.extend_from_slice(&chunk.map_err(|e| unavailable(&e))?);
+            }
+            let trailers = body.trailers().await.map_err(|e| unavailable(&e))?;
+            match trailers.as_ref().and_then(status_from) {
+                Some(status) => status?,
+                None => return Err(Status::new(Code::Internal, "missing grpc-status")),
+            }
+            unframe(&message)
+        };
+        tokio::time::timeout(timeout, call)
+            .await
+            .unwrap_or_else(|_| {
+                Err(Status::new(
+                    Code::DeadlineExceeded,
+                    format!("deadline of {}ms exceeded", timeout.as_millis()),
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<M: Message>(message: &M) -> M {
+        unframe(&frame(message)).unwrap()
+    }
+
+    #[test]
+    fn encodes_messages_to_the_protobuf_wire_format() {
+        let query = TimeQuery {
+            timezone: Some("Asia/Tokyo".to_string()),
+            ..TimeQuery::default()
+        };
+        let mut encoded = Vec::new();
+        query.encode(&mut encoded);
+        assert_eq!(encoded, b"\x0a\x0aAsia/Tokyo");
+
+        let mut encoded = Vec::new();
+        put_int(&mut encoded, 5, -1);
+        assert_eq!(encoded.len(), 11, "negative ints are ten-byte varints");
+
+        let converted = ConvertedTime {
+            timezone: "America/New_York".to_string(),
+            timestamp: "2024-07-01T08:00:00-04:00".to_string(),
+            utc_offset_seconds: -4 * 3600,
+            utc_offset_label: "-04:00".to_string(),
+            is_dst: true,
+        };
+        let response = TimeConvertResponse {
+            epoch_seconds: 1_719_835_200,
+            from: converted.clone(),
+            to: converted,
+            request_id: "req".to_string(),
+        };
+        let decoded = round_trip(&response);
+        assert_eq!(decoded.epoch_seconds, response.epoch_seconds);
+        assert_eq!(decoded.to, response.to);
+
+        assert!(unframe::<TimeQuery>(&[0, 0, 0, 0, 9, 1]).is_err());
+        assert_eq!(
+            decode_message(&encode_message("Invalid timezone: 100%")),
+            "Invalid timezone: 100%"
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_unary_calls_with_status() {
+        let server =
+            Server::default().unary(GET_TIME_PATH, |metadata, query: TimeQuery| async move {
+                match query.timezone.as_deref() {
+                    Some("UTC") => Ok(TimeResponse {
+                        timestamp: "1970-01-01T00:00:00Z".to_string(),
+                        timezone: "UTC".to_string(),
+                        request_id: metadata.request_id.unwrap_or_default(),
+                        source: "test".to_string(),
+                        utc_offset_seconds: Some(0),
+                        utc_offset_label: Some("+00:00".to_string()),
+                        display: None,
+                        format: None,
+                        degraded: None,
+                        clock_offset_ms: None,
+                    }),
+                    other => Err(Status::new(
+                        Code::InvalidArgument,
+                        format!("Invalid timezone: {}", other.unwrap_or_default()),
+                    )),
+                }
+            });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(server.serve(listener, std::future::pending()));
+
+        let client = Client::default();
+        let call = |timezone: &str| {
+            let query = TimeQuery {
+                timezone: Some(timezone.to_string()),
+                ..TimeQuery::default()
+            };
+            let (client, base_url) = (client.clone(), base_url.clone());
+            async move {
+                client
+                    .unary::<_, TimeResponse>(
+                        &base_url,
+                        GET_TIME_PATH,
+                        &query,
+                        "req-1",
+                        Duration::from_secs(2),
+                    )
+                    .await
+            }
+        };
+
+        let response = call("UTC").await.unwrap();
+        assert_eq!(response.request_id, "req-1");
+        assert_eq!(response.utc_offset_label.as_deref(), Some("+00:00"));
+
+        let status = call("Mars/Olympus").await.unwrap_err();
+        assert_eq!(status.code, Code::InvalidArgument);
+        assert_eq!(status.message, "Invalid timezone: Mars/Olympus");
+        assert_eq!(status.http_status(), 400);
+
+        let unimplemented = client
+            .unary::<_, TimeResponse>(
+                &base_url,
+                "/time.v1.TimeService/Nope",
+                &TimeQuery::default(),
+                "req-2",
+                Duration::from_secs(2),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(unimplemented.code, Code::Unimplemented);
+    }
+
+    #[test]
+    fn parses_grpc_timeouts() {
+        assert_eq!(parse_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_timeout("1H"), Some(Duration::from_secs(3600)));
+        for invalid in ["", "m", "123456789m", "5x", "-5m", "5é"] {
+            assert_eq!(parse_timeout(invalid), None, "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn enforces_message_limits_timeouts_and_authorization() {
+        let server = Server::default()
+            .limits(64, Duration::from_millis(100))
+            .authorize(|_, metadata| match metadata.get("x-api-key") {
+                Some(key) if key == "key-1" => Ok(()),
+                _ => Err(Status::new(
+                    Code::Unauthenticated,
+                    "Missing or invalid API key",
+                )),
+            })
+            .unary(GET_TIME_PATH, |_, query: TimeQuery| async move {
+                if query.timezone.as_deref() == Some("slow") {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Ok(query)
+            });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}{GET_TIME_PATH}", listener.local_addr().unwrap());
+        tokio::spawn(server.serve(listener, std::future::pending()));
+        let http = hyper014::Client::builder()
+            .http2_only(true)
+            .build_http::<Body>();
+        let call = |key: &'static str, body: Body| {
+            let request = Request::post(&url)
+                .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+                .header("x-api-key", key)
+                .body(body)
+                .unwrap();
+            let http = http.clone();
+            async move {
+                let response = http.request(request).await.unwrap();
+                status_from(response.headers()).unwrap()
+            }
+        };
+        let query = |timezone: &str| {
+            Body::from(frame(&TimeQuery {
+                timezone: Some(timezone.to_string()),
+                ..TimeQuery::default()
+            }))
+        };
+
+        assert_eq!(
+            call("nope", query("UTC")).await.unwrap_err().code,
+            Code::Unauthenticated
+        );
+        assert_eq!(
+            call("key-1", query("slow")).await.unwrap_err().code,
+            Code::DeadlineExceeded
+        );
+
+        // The prefix alone is enough to refuse the message; the rest never
+        // has to arrive.
+        let (mut sender, body) = Body::channel();
+        sender
+            .send_data(Bytes::from_static(&[0, 0, 0x10, 0, 0]))
+            .await
+            .unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(2), call("key-1", body))
+            .await
+            .expect("refused before the message was read")
+            .unwrap_err();
+        assert_eq!(status.code, Code::ResourceExhausted);
+        assert_eq!(status.http_status(), 413);
+        drop(sender);
+    }
+}
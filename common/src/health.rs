@@ -0,0 +1,118 @@
+//! Liveness and readiness probe responses shared by both services.
+//!
+//! `/health/live` only says the process is serving requests. `/health/ready`
+//! lists named checks, each `{"status": "pass" | "fail", ...details}`, and
+//! answers `503` when any fails or shutdown has been requested.
+
+use axum::http::StatusCode;
+use axum::response::Json;
+use serde_json::{json, Map, Value};
+
+use crate::shutdown::Shutdown;
+
+pub const LIVE_PATH: &str = "/health/live";
+pub const READY_PATH: &str = "/health/ready";
+
+/// One readiness check: whether it passed, plus details for operators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    /// Extra fields merged into the check's JSON object.
+    pub details: Map<String, Value>,
+}
+
+impl Check {
+    pub fn new(name: &'static str, ok: bool, details: Value) -> Self {
+        let details = match details {
+            Value::Object(details) => details,
+            Value::Null => Map::new(),
+            other => Map::from_iter([("detail".to_string(), other)]),
+        };
+        Check { name, ok, details }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = Map::from_iter([(
+            "status".to_string(),
+            json!(if self.ok { "pass" } else { "fail" }),
+        )]);
+        object.extend(self.details.clone());
+        Value::Object(object)
+    }
+}
+
+/// `200` while the process is up, whatever the state of its dependencies.
+pub fn live(service: &str) -> Json<Value> {
+    Json(json!({
+        "status": "alive",
+        "service": service,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+/// `200` when every check passes and no shutdown is under way, else `503`.
+pub fn ready(
+    service: &str,
+    shutdown: &Shutdown,
+    checks: impl IntoIterator<Item = Check>,
+) -> (StatusCode, Json<Value>) {
+    let shutting_down = Check::new(
+        "shutdown",
+        !shutdown.is_requested(),
+        json!({ "in_flight": shutdown.in_flight() }),
+    );
+    let checks: Vec<_> = std::iter::once(shutting_down).chain(checks).collect();
+    let ok = checks.iter().all(|check| check.ok);
+    let checks: Map<String, Value> = checks
+        .iter()
+        .map(|check| (check.name.to_string(), check.to_json()))
+        .collect();
+    (
+        if ok {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        Json(json!({
+            "status": if ok { "ready" } else { "not_ready" },
+            "service": service,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "checks": checks
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_when_any_check_or_shutdown_fails() {
+        let shutdown = Shutdown::default();
+        let (status, Json(body)) = ready(
+            "test",
+            &shutdown,
+            [Check::new("clock", true, json!({ "now": "2025-01-01" }))],
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["checks"]["clock"]["status"], "pass");
+        assert_eq!(body["checks"]["clock"]["now"], "2025-01-01");
+        assert_eq!(body["checks"]["shutdown"]["status"], "pass");
+
+        let (status, Json(body)) = ready(
+            "test",
+            &shutdown,
+            [Check::new("api2", false, json!("unreachable"))],
+        );
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["api2"]["detail"], "unreachable");
+
+        shutdown.request();
+        let (status, Json(body)) = ready("test", &shutdown, []);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["shutdown"]["status"], "fail");
+    }
+}
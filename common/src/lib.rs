@@ -0,0 +1,333 @@
+//! Wire types shared by the time services.
+
+use serde::{Deserialize, Serialize};
+
+pub mod api2_client;
+pub mod audit;
+pub mod auth;
+pub mod backoff;
+pub mod balancing;
+pub mod chaos;
+pub mod compression;
+pub mod config;
+mod connections;
+pub mod cors;
+pub mod etag;
+pub mod flags;
+pub mod format;
+pub mod grpc;
+pub mod health;
+pub mod limits;
+pub mod logging;
+pub mod metrics;
+pub mod openapi;
+pub mod otel;
+pub mod payload_log;
+pub mod problem;
+pub mod rate_limit;
+pub mod rolling_file;
+pub mod shutdown;
+pub mod tenants;
+pub mod tls;
+mod tls_stream;
+mod toml;
+pub mod trace_context;
+#[cfg(unix)]
+pub mod unix;
+pub mod versioning;
+pub mod websocket;
+
+/// Header carrying the correlation ID shared by API1 and API2.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Body of a successful `/time` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeResponse {
+    pub timestamp: String,
+    pub timezone: String,
+    pub request_id: String,
+    pub source: String,
+    /// Offset from UTC at `timestamp`, in seconds east of Greenwich.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset_seconds: Option<i32>,
+    /// The same offset as `±HH:MM`, e.g. `+05:30`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset_label: Option<String>,
+    /// Human-readable rendering in the requested locale, if one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    /// Name of the format `timestamp` is in, e.g. `rfc3339` or `custom`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Set when the time was computed without the time provider, e.g. by
+    /// API1 while API2 is unreachable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<bool>,
+    /// How far the provider's clock was from its NTP server at the last
+    /// check, in milliseconds; positive when the clock is behind. Only set
+    /// when NTP monitoring is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>,
+}
+
+/// Body of a successful `/v2/time` response: the `/v1/time` fields with the
+/// offset grouped and daylight saving details added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeResponseV2 {
+    pub timestamp: String,
+    pub timezone: String,
+    pub request_id: String,
+    pub source: String,
+    /// Name of the format `timestamp` is in, e.g. `rfc3339` or `custom`.
+    pub format: String,
+    pub utc_offset: UtcOffset,
+    pub dst: DstInfo,
+    /// Human-readable rendering in the requested locale, if one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    /// As [`TimeResponse::clock_offset_ms`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>,
+}
+
+/// Offset from UTC at the time of a `/v2/time` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtcOffset {
+    /// Seconds east of Greenwich, including any daylight saving.
+    pub seconds: i32,
+    /// The same offset as `±HH:MM`, e.g. `+05:30`.
+    pub label: String,
+}
+
+/// Daylight saving in a zone at the time of a `/v2/time` response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DstInfo {
+    pub active: bool,
+    /// Daylight saving included in [`UtcOffset::seconds`], `0` outside DST.
+    pub offset_seconds: i64,
+    /// Abbreviation in use, e.g. `PDT`, or the offset for zones without one.
+    pub abbreviation: String,
+    /// RFC 3339 instant of the next offset change within a year, if any.
+    pub next_transition: Option<String>,
+}
+
+/// Body of a `/time/diff` response: the current offset of `to` relative to `from`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeDiffResponse {
+    pub from_timezone: String,
+    pub to_timezone: String,
+    pub offset_seconds: i32,
+    pub offset_label: String,
+    pub from_timestamp: String,
+    pub to_timestamp: String,
+    pub request_id: String,
+}
+
+/// One side of a `/time/convert` response: the instant as seen in a zone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConvertedTime {
+    pub timezone: String,
+    pub timestamp: String,
+    pub utc_offset_seconds: i32,
+    pub utc_offset_label: String,
+    /// Whether daylight saving time is in effect in the zone at that instant.
+    pub is_dst: bool,
+}
+
+/// Body of a `/time/convert` response: one instant in both zones.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeConvertResponse {
+    pub epoch_seconds: i64,
+    pub from: ConvertedTime,
+    pub to: ConvertedTime,
+    pub request_id: String,
+}
+
+/// Body of a `/timezones` response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimezoneList {
+    pub timezones: Vec<String>,
+    pub count: usize,
+    /// Current offset of each listed zone, in the same order as `timezones`.
+    #[serde(default)]
+    pub offsets: Vec<TimezoneOffset>,
+}
+
+/// A timezone's offset from UTC at the time of the request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimezoneOffset {
+    pub name: String,
+    pub utc_offset_seconds: i32,
+    /// The offset as `±HH:MM`, e.g. `+05:30`.
+    pub utc_offset_label: String,
+}
+
+/// Body of every error response, an RFC 7807 problem; see [`problem`].
+/// `type` and `title` are derived from `code` when serialized, and `detail`
+/// repeats `error`.
+#[derive(Debug, Deserialize)]
+pub struct ErrorResponse {
+    pub status: u16,
+    pub code: problem::ErrorCode,
+    pub error: String,
+    pub request_id: String,
+    pub timestamp: String,
+}
+
+/// Query parameters of the time provider's `/time` endpoint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TimeQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Timestamp format; see [`format::TimestampFormat::parse`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Language tag for [`TimeResponse::display`], e.g. `en` or `th`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+/// Maps the abbreviations `/time` accepted before it supported every IANA
+/// zone onto the zones they used to select.
+pub fn resolve_timezone_alias(name: &str) -> &str {
+    match name {
+        "EST" => "US/Eastern",
+        "PST" => "US/Pacific",
+        "CET" => "Europe/Berlin",
+        other => other,
+    }
+}
+
+/// Body of a `POST /time` request. Every field is optional; unknown fields
+/// are rejected so a misspelt option is not silently ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Timestamp format; see [`format::TimestampFormat::parse`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Language tag for `TimeResponse.display`, e.g. `en`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Seconds to add to the current time, to ask what the time will be
+    /// (or was, when negative).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_seconds: Option<i64>,
+}
+
+/// Batch size used when `max_batch_size` is not configured.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 50;
+
+/// Body of a `POST /time/batch` request. Also accepted as a bare JSON array
+/// of timezone names.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "BatchTimeBody")]
+pub struct BatchTimeRequest {
+    pub timezones: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchTimeBody {
+    Names(Vec<String>),
+    Object { timezones: Vec<String> },
+}
+
+impl From<BatchTimeBody> for BatchTimeRequest {
+    fn from(body: BatchTimeBody) -> Self {
+        match body {
+            BatchTimeBody::Names(timezones) | BatchTimeBody::Object { timezones } => {
+                BatchTimeRequest { timezones }
+            }
+        }
+    }
+}
+
+/// A timezone from a batch that could not be resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTimeError {
+    pub timezone: String,
+    pub error: String,
+}
+
+/// One entry per requested timezone: its time, or why it failed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchTimeItem {
+    Time(TimeResponse),
+    Error(BatchTimeError),
+}
+
+/// Body of a `POST /time/batch` response. `items` matches the request
+/// position for position; `results` and `errors` split the same entries for
+/// older callers, each keeping the request order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchTimeResponse {
+    #[serde(default)]
+    pub items: Vec<BatchTimeItem>,
+    pub results: Vec<TimeResponse>,
+    pub errors: Vec<BatchTimeError>,
+    pub request_id: String,
+}
+
+/// Body of a `POST /cron/next` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CronRequest {
+    /// Five-field cron expression, e.g. `*/15 9-17 * * MON-FRI`, or a macro
+    /// such as `@daily`.
+    pub expression: String,
+    /// IANA name the expression is evaluated in; UTC when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// How many fire times to return; 5 when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+}
+
+/// The next fire times of a cron expression, soonest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CronResponse {
+    pub expression: String,
+    pub timezone: String,
+    /// RFC 3339 local times; fewer than requested when the expression fires
+    /// rarely or never (e.g. `0 0 30 2 *`).
+    pub next: Vec<String>,
+    pub request_id: String,
+}
+
+/// Body of a `POST /timer` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateTimerRequest {
+    pub name: String,
+}
+
+/// A duration in several units, each the whole duration rather than a
+/// component of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Elapsed {
+    pub milliseconds: u64,
+    pub seconds: f64,
+    pub minutes: f64,
+    pub hours: f64,
+    /// ISO 8601 duration, e.g. `PT1H2M3.456S`.
+    pub iso8601: String,
+}
+
+/// A server-side timer, as returned by the `/timer` routes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimerResponse {
+    pub id: String,
+    pub name: String,
+    pub started_at: String,
+    /// Set once the timer has been stopped; `elapsed` no longer grows.
+    pub stopped_at: Option<String>,
+    pub running: bool,
+    pub elapsed: Elapsed,
+    pub request_id: String,
+}
@@ -0,0 +1,410 @@
+//! Per-request time and body-size limits shared by both services.
+//!
+//! [`enforce`] answers `504` when a route takes longer than its timeout to
+//! produce a response, and `413` when the request body is larger than
+//! `max_body_bytes`, both as an [`ErrorResponse`]. While a handler runs, the
+//! time it has left is available from [`remaining`], so calls it makes to
+//! other services can give up when the caller would have stopped waiting.
+//! Only the response head is timed: streamed bodies such as
+//! `/time/stream` continue past the deadline.
+//!
+//! A caller can shorten, but not extend, the timeout with an
+//! [`REQUEST_TIMEOUT_HEADER`] budget such as `500ms`. API1 sends API2 the
+//! time it will wait for each call this way, so API2 stops working on a
+//! request as soon as nobody is waiting for the answer.
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::problem::ErrorCode;
+use crate::versioning::unversioned;
+use crate::{ErrorResponse, REQUEST_ID_HEADER};
+
+/// Timeout used when `request_timeout_ms` is not configured.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Body size limit used when `max_body_bytes` is not configured.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Time the caller will wait for the response, e.g. `500ms` or `2s`.
+pub const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout";
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Time left before the current request times out, or `None` outside a
+/// request handled by [`enforce`].
+pub fn remaining() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+/// Parses an [`REQUEST_TIMEOUT_HEADER`] value: whole milliseconds (`500ms`,
+/// or a bare `500`) or seconds (`2s`).
+pub fn parse_request_timeout(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let parsed = if let Some(ms) = value.strip_suffix("ms") {
+        ms.trim().parse().map(Duration::from_millis)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.trim().parse().map(Duration::from_secs)
+    } else {
+        value.parse().map(Duration::from_millis)
+    };
+    parsed.map_err(|_| format!("Invalid X-Request-Timeout: {value} (expected e.g. 500ms or 2s)"))
+}
+
+/// Renders a budget for [`REQUEST_TIMEOUT_HEADER`], rounded down to the
+/// millisecond.
+pub fn format_request_timeout(timeout: Duration) -> String {
+    format!("{}ms", timeout.as_millis())
+}
+
+/// A timeout applied to one route in place of the global one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTimeout {
+    pub path: String,
+    pub timeout: Duration,
+}
+
+impl RouteTimeout {
+    /// Parses e.g. `/time/batch=10000`: ten seconds for batch requests.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid route timeout: {value} (expected PATH=MILLISECONDS)");
+        let (path, timeout_ms) = value.trim().split_once('=').ok_or_else(invalid)?;
+        match (path.trim(), timeout_ms.trim().parse::<u64>()) {
+            (path, Ok(timeout_ms)) if path.starts_with('/') && timeout_ms > 0 => Ok(RouteTimeout {
+                path: path.to_string(),
+                timeout: Duration::from_millis(timeout_ms),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    timeout: Duration,
+    /// Overrides of `timeout`, by route template such as `/timezone/*name`.
+    route_timeouts: HashMap<String, Duration>,
+    max_body_bytes: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            route_timeouts: HashMap::new(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+}
+
+impl RequestLimits {
+    /// Uses `request_timeout_ms`, `route_timeouts` and `max_body_bytes`,
+    /// falling back to the defaults when unset.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a malformed route timeout; [`Config::validated`] reports
+    /// those first.
+    pub fn from_config(config: &Config) -> Self {
+        let route_timeouts = config
+            .route_timeouts
+            .iter()
+            .flatten()
+            .map(|route| {
+                let route = RouteTimeout::parse(route).unwrap_or_else(|e| panic!("{e}"));
+                (route.path, route.timeout)
+            })
+            .collect();
+        RequestLimits {
+            timeout: config
+                .request_timeout_ms
+                .map_or(DEFAULT_REQUEST_TIMEOUT, Duration::from_millis),
+            route_timeouts,
+            max_body_bytes: config.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES),
+        }
+    }
+
+    /// The timeout for routes without an override.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    fn timeout_for(&self, route: &str) -> Duration {
+        self.route_timeouts
+            .get(unversioned(route))
+            .copied()
+            .unwrap_or(self.timeout)
+    }
+}
+
+fn error(code: ErrorCode, message: String, request_id: Option<String>) -> Response {
+    let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    ErrorResponse::new(code, message, &request_id).into_response()
+}
+
+/// Middleware applying [`RequestLimits`]. Bodies are read in full, up to the
+/// limit, before the handler runs.
+pub async fn enforce(
+    State(limits): State<Arc<RequestLimits>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_string(),
+        |route| route.as_str().to_string(),
+    );
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let budget = match request.headers().get(REQUEST_TIMEOUT_HEADER) {
+        Some(value) => {
+            match value
+                .to_str()
+                .map_err(|_| "Invalid X-Request-Timeout".to_string())
+                .and_then(parse_request_timeout)
+            {
+                Ok(budget) => Some(budget),
+                Err(message) => return error(ErrorCode::InvalidRequest, message, request_id),
+            }
+        }
+        None => None,
+    };
+    if budget == Some(Duration::ZERO) {
+        warn!(request_id = request_id.as_deref(), route = %route, "Request deadline already passed");
+        return error(
+            ErrorCode::Timeout,
+            "Request deadline exceeded before it was handled".to_string(),
+            request_id,
+        );
+    }
+    let timeout = budget.map_or(limits.timeout_for(&route), |budget| {
+        budget.min(limits.timeout_for(&route))
+    });
+    let deadline = Instant::now() + timeout;
+
+    let too_large = || {
+        warn!(request_id = request_id.as_deref(), route = %route, "Request body too large");
+        error(
+            ErrorCode::PayloadTooLarge,
+            format!(
+                "Request body exceeds the limit of {} bytes",
+                limits.max_body_bytes
+            ),
+            request_id.clone(),
+        )
+    };
+    let declared_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    if declared_length.is_some_and(|length| length > limits.max_body_bytes as u64) {
+        return too_large();
+    }
+
+    let run = async {
+        let (parts, body) = request.into_parts();
+        // Chunked bodies have no declared length, so the limit is applied
+        // while reading.
+        let body = match axum::body::to_bytes(body, limits.max_body_bytes).await {
+            Ok(body) => body,
+            Err(_) => return too_large(),
+        };
+        next.run(Request::from_parts(parts, Body::from(body))).await
+    };
+    match tokio::time::timeout_at(deadline, DEADLINE.scope(deadline, run)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(
+                request_id = request_id.as_deref(),
+                route = %route,
+                timeout_ms = timeout.as_millis() as u64,
+                "Request timed out"
+            );
+            error(
+                ErrorCode::Timeout,
+                format!("Request timed out after {}ms", timeout.as_millis()),
+                request_id,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn parses_path_and_milliseconds() {
+        assert_eq!(
+            RouteTimeout::parse(" /time/batch = 10000 "),
+            Ok(RouteTimeout {
+                path: "/time/batch".to_string(),
+                timeout: Duration::from_secs(10),
+            })
+        );
+        assert!(RouteTimeout::parse("/time=0").is_err());
+        assert!(RouteTimeout::parse("time=100").is_err());
+        assert!(RouteTimeout::parse("/time=1.5").is_err());
+    }
+
+    #[test]
+    fn parses_request_timeouts() {
+        assert_eq!(
+            parse_request_timeout("500ms"),
+            Ok(Duration::from_millis(500))
+        );
+        assert_eq!(parse_request_timeout(" 2s "), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_request_timeout("250"), Ok(Duration::from_millis(250)));
+        assert_eq!(
+            parse_request_timeout("1.5s").unwrap_err(),
+            "Invalid X-Request-Timeout: 1.5s (expected e.g. 500ms or 2s)"
+        );
+        assert!(parse_request_timeout("-5ms").is_err());
+        assert_eq!(
+            format_request_timeout(Duration::from_micros(499_900)),
+            "499ms"
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_504_and_413_as_error_responses() {
+        let limits = RequestLimits {
+            timeout: Duration::from_secs(5),
+            route_timeouts: HashMap::from([("/slow/*rest".to_string(), Duration::from_millis(50))]),
+            max_body_bytes: 8,
+        };
+        let app = Router::new()
+            .route(
+                "/slow/*rest",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }),
+            )
+            .route(
+                "/v1/slow/*rest",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }),
+            )
+            .route(
+                "/remaining",
+                get(|| async { remaining().unwrap().as_millis().to_string() }),
+            )
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(limits),
+                enforce,
+            ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://{addr}/slow/down"))
+            .header(REQUEST_ID_HEADER, "req-1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "Request timed out after 50ms");
+        assert_eq!(body.request_id, "req-1");
+        // Route timeouts apply to every version of the route.
+        let response = client
+            .get(format!("http://{addr}/v1/slow/down"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+
+        let remaining: u64 = client
+            .get(format!("http://{addr}/remaining"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((4000..=5000).contains(&remaining), "{remaining}");
+
+        // A caller's budget shortens the timeout but cannot extend it.
+        let remaining_with = |budget: &'static str| {
+            client
+                .get(format!("http://{addr}/remaining"))
+                .header(REQUEST_TIMEOUT_HEADER, budget)
+                .send()
+        };
+        let remaining: u64 = remaining_with("300ms")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((200..=300).contains(&remaining), "{remaining}");
+        let remaining: u64 = remaining_with("60s")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(remaining <= 5000, "{remaining}");
+        let response = remaining_with("0ms").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(
+            body.error,
+            "Request deadline exceeded before it was handled"
+        );
+        let response = remaining_with("soon").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let echo = |body: reqwest::Body| client.post(format!("http://{addr}/echo")).body(body);
+        let response = echo("12345678".into()).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "12345678");
+        let response = echo("123456789".into()).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error, "Request body exceeds the limit of 8 bytes");
+        // Without a Content-Length the limit applies while reading.
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"POST /echo HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+                  5\r\n12345\r\n4\r\n6789\r\n0\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = [0; 12];
+        stream.read_exact(&mut response).await.unwrap();
+        assert_eq!(&response, b"HTTP/1.1 413");
+    }
+}
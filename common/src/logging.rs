@@ -0,0 +1,453 @@
+//! Log output for both services: human-readable text (compact or pretty),
+//! or one JSON object per line for log aggregators, on stdout and optionally
+//! also in a [`RollingFile`].
+//!
+//! [`access_log`] writes one `access` event per request with its
+//! `request_id`, `method`, `route`, `status` and `duration_ms`.
+//!
+//! The filter set by `log_level` can be replaced while the service runs with
+//! [`set_filter`], which backs each service's `/admin/log-level`.
+//!
+//! JSON lines follow the layout of tracing-subscriber's own `json` format:
+//! `timestamp`, `level`, `target`, the event's `fields`, the innermost `span`
+//! and every enclosing span in `spans`, each with its fields as keys.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::{info, span, Event, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::{FmtContext, FormattedFields, MakeWriter};
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::Config;
+use crate::rolling_file::{RollingFile, Rotation};
+use crate::REQUEST_ID_HEADER;
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+pub const ADMIN_PATH: &str = "/admin/log-level";
+
+/// Handle to the filter [`init`] installed, for [`set_filter`].
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Body of `PUT /admin/log-level`, and what `/admin/log-level` reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogFilter {
+    /// `EnvFilter` directives, e.g. `info,api1=debug`.
+    pub filter: String,
+}
+
+/// Why [`set_filter`] left the filter as it was.
+#[derive(Debug, PartialEq)]
+pub enum FilterError {
+    /// The directives do not parse.
+    Invalid(String),
+    /// [`init`] has not installed a subscriber in this process.
+    NotInstalled,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::Invalid(message) => write!(f, "{message}"),
+            FilterError::NotInstalled => write!(f, "Logging was not set up by this process"),
+        }
+    }
+}
+
+/// The filter in effect, or `None` before [`init`].
+pub fn filter() -> Option<String> {
+    FILTER.get()?.with_current(ToString::to_string).ok()
+}
+
+/// Replaces the filter with `directives`, returning it as parsed. Events
+/// already filtered out are not recovered; the new filter applies from now.
+pub fn set_filter(directives: &str) -> Result<String, FilterError> {
+    let filter = parse_filter(directives)?;
+    let handle = FILTER.get().ok_or(FilterError::NotInstalled)?;
+    let parsed = filter.to_string();
+    handle
+        .reload(filter)
+        .map_err(|_| FilterError::NotInstalled)?;
+    Ok(parsed)
+}
+
+fn parse_filter(directives: &str) -> Result<EnvFilter, FilterError> {
+    if directives.trim().is_empty() {
+        return Err(FilterError::Invalid(
+            "Log filter must not be empty".to_string(),
+        ));
+    }
+    EnvFilter::try_new(directives.trim())
+        .map_err(|e| FilterError::Invalid(format!("Invalid log filter {directives}: {e}")))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    /// Multi-line text with each field on its own line, for local use.
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "Invalid log format: {value} (expected text, pretty or json)"
+            )),
+        }
+    }
+}
+
+/// Installs the global subscriber for `log_format` and `log_level`, writing
+/// to stdout and, when `log_file` is set, also to a file rotated per
+/// `log_rotation` (default `daily`).
+///
+/// `log_level` is a level such as `debug`, or any `EnvFilter` directive list
+/// such as `info,api1=debug`; [`set_filter`] can replace it later.
+///
+/// # Panics
+///
+/// Panics on an unknown format, rotation or unparsable level, if the log
+/// file cannot be opened, or if a global subscriber is already set.
+pub fn init(config: &Config) {
+    let format = config
+        .log_format
+        .as_deref()
+        .map_or(Ok(LogFormat::Text), LogFormat::parse)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let level = config.log_level.as_deref().unwrap_or(DEFAULT_LOG_LEVEL);
+    let filter = parse_filter(level).unwrap_or_else(|e| panic!("Invalid log level: {e}"));
+
+    let installed = match &config.log_file {
+        Some(path) => {
+            let rotation = config
+                .log_rotation
+                .as_deref()
+                .map_or(Ok(Rotation::Daily), Rotation::parse)
+                .unwrap_or_else(|e| panic!("{e}"));
+            let file = RollingFile::open(Path::new(path), rotation, config.log_file_max_files)
+                .unwrap_or_else(|e| panic!("Cannot open log file {path}: {e}"));
+            // No colour codes: they would end up in the file.
+            install(format, filter, false, std::io::stdout.and(file))
+        }
+        None => install(format, filter, true, std::io::stdout),
+    };
+    installed.expect("Failed to set tracing subscriber");
+}
+
+fn install<W>(
+    format: LogFormat,
+    filter: EnvFilter,
+    ansi: bool,
+    writer: W,
+) -> Result<(), tracing::subscriber::SetGlobalDefaultError>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER.set(handle);
+    let text = tracing_subscriber::fmt::layer().with_ansi(ansi);
+    let output = match format {
+        LogFormat::Text => text.with_writer(writer).boxed(),
+        LogFormat::Pretty => text.pretty().with_writer(writer).boxed(),
+        LogFormat::Json => json_layer(writer).boxed(),
+    };
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::registry().with(filter).with(output),
+    )
+}
+
+/// Middleware logging each completed request as an `access` event.
+pub async fn access_log(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    // Route template rather than raw path, as in the metrics and spans.
+    let route = request.extensions().get::<MatchedPath>().map_or_else(
+        || request.uri().path().to_string(),
+        |route| route.as_str().to_string(),
+    );
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    info!(
+        target: "access",
+        request_id = request_id.as_deref(),
+        method = %method,
+        route = %route,
+        status = response.status().as_u16(),
+        duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "Request completed"
+    );
+    response
+}
+
+fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .fmt_fields(JsonFields)
+        .event_format(JsonFormat)
+}
+
+#[cfg(test)]
+fn json_subscriber<W>(filter: EnvFilter, writer: W) -> impl Subscriber + Send + Sync
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json_layer(writer))
+}
+
+/// Stores span fields as a JSON object so [`JsonFormat`] can nest them.
+pub struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = JsonVisitor::default();
+        fields.record(&mut visitor);
+        write!(writer, "{}", Value::Object(visitor.0))
+    }
+
+    /// Merges fields recorded after the span was created into its object.
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &span::Record<'_>,
+    ) -> fmt::Result {
+        let mut visitor = JsonVisitor(parse_object(&current.fields));
+        fields.record(&mut visitor);
+        current.fields = Value::Object(visitor.0).to_string();
+        Ok(())
+    }
+}
+
+/// Writes each event as a single JSON line.
+pub struct JsonFormat;
+
+impl<S> FormatEvent<S, JsonFields> for JsonFormat
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, JsonFields>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut fields = JsonVisitor::default();
+        event.record(&mut fields);
+
+        let spans: Vec<Value> = ctx
+            .event_scope()
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .map(|span| {
+                let extensions = span.extensions();
+                let mut object = extensions
+                    .get::<FormattedFields<JsonFields>>()
+                    .map(|formatted| parse_object(&formatted.fields))
+                    .unwrap_or_default();
+                object.insert("name".to_string(), span.name().into());
+                Value::Object(object)
+            })
+            .collect();
+
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Utc::now().to_rfc3339().into());
+        line.insert("level".to_string(), metadata.level().as_str().into());
+        line.insert("target".to_string(), metadata.target().into());
+        line.insert("fields".to_string(), Value::Object(fields.0));
+        if let Some(current) = spans.last() {
+            line.insert("span".to_string(), current.clone());
+        }
+        if !spans.is_empty() {
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+fn parse_object(text: &str) -> Map<String, Value> {
+    match serde_json::from_str(text) {
+        Ok(Value::Object(object)) => object,
+        _ => Map::new(),
+    }
+}
+
+/// Collects fields as JSON values, keeping numbers and booleans typed.
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use tracing::{info, info_span};
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_events_and_span_fields_as_json() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = json_subscriber(EnvFilter::new("debug"), move || writer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!(
+                "trace",
+                request_id = "req-1",
+                status = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            span.record("status", 200);
+            info!(
+                timezone = "Asia/Bangkok",
+                attempt = 2,
+                "Processing time request"
+            );
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1, "{output}");
+        let line: Value = serde_json::from_str(lines[0]).unwrap();
+
+        assert_eq!(line["level"], "INFO");
+        assert!(line["timestamp"].is_string());
+        assert_eq!(line["fields"]["message"], "Processing time request");
+        assert_eq!(line["fields"]["timezone"], "Asia/Bangkok");
+        assert_eq!(line["fields"]["attempt"], 2);
+        assert_eq!(line["span"]["name"], "trace");
+        assert_eq!(line["span"]["request_id"], "req-1");
+        assert_eq!(line["span"]["status"], 200);
+        assert_eq!(line["spans"][0]["request_id"], "req-1");
+    }
+
+    #[tokio::test]
+    async fn logs_each_request_with_its_route_and_status() {
+        use axum::{routing::get, Router};
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = json_subscriber(EnvFilter::new("info"), move || writer.clone());
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route("/time/:zone", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(access_log));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/time/UTC", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        reqwest::Client::new()
+            .get(url)
+            .header(REQUEST_ID_HEADER, "req-7")
+            .send()
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["target"], "access");
+        assert_eq!(line["fields"]["request_id"], "req-7");
+        assert_eq!(line["fields"]["method"], "GET");
+        assert_eq!(line["fields"]["route"], "/time/:zone");
+        assert_eq!(line["fields"]["status"], 200);
+        assert!(line["fields"]["duration_ms"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn rejects_invalid_filters_before_reloading() {
+        assert!(matches!(
+            set_filter(" "),
+            Err(FilterError::Invalid(message)) if message == "Log filter must not be empty"
+        ));
+        assert!(matches!(
+            set_filter("info,api1=loud"),
+            Err(FilterError::Invalid(message)) if message.starts_with("Invalid log filter info,api1=loud")
+        ));
+        // No test installs the global subscriber.
+        assert_eq!(set_filter("debug"), Err(FilterError::NotInstalled));
+        assert_eq!(filter(), None);
+    }
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!(LogFormat::parse("text").unwrap(), LogFormat::Text);
+        assert_eq!(LogFormat::parse(" JSON ").unwrap(), LogFormat::Json);
+        assert_eq!(LogFormat::parse("pretty").unwrap(), LogFormat::Pretty);
+        assert!(LogFormat::parse("logfmt").is_err());
+    }
+}
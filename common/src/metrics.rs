@@ -0,0 +1,360 @@
+//! Minimal Prometheus metrics registry with text exposition output.
+//!
+//! Counters, gauges and histograms are keyed by name and label set and
+//! rendered in the Prometheus text format (version 0.0.4) by
+//! [`Registry::render`].
+
+use axum::extract::MatchedPath;
+use axum::http::{Request, Response};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+
+use crate::tenants::TENANT_HEADER;
+
+/// Route serving the metrics themselves; excluded from request metrics.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// `Content-Type` of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds, in seconds, of the latency histogram buckets.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A named counter with its `# HELP` text.
+pub struct Counter {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+/// A named gauge with its `# HELP` text.
+pub struct Gauge {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+/// A named histogram with its `# HELP` text.
+pub struct Histogram {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+pub const HTTP_REQUESTS_TOTAL: Counter = Counter {
+    name: "http_requests_total",
+    help: "Total HTTP requests by route, status class and tenant, if any.",
+};
+
+pub const HTTP_REQUEST_DURATION_SECONDS: Histogram = Histogram {
+    name: "http_request_duration_seconds",
+    help: "HTTP request latency in seconds by route and tenant, if any.",
+};
+
+enum Series {
+    Counter(f64),
+    Gauge(f64),
+    Histogram {
+        buckets: [u64; BUCKETS.len()],
+        sum: f64,
+        count: u64,
+    },
+}
+
+struct Family {
+    help: &'static str,
+    kind: &'static str,
+    /// Series keyed by their rendered label set, e.g. `route="/time"`.
+    series: BTreeMap<String, Series>,
+}
+
+#[derive(Default)]
+pub struct Registry {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+}
+
+impl Registry {
+    pub fn increment(&self, counter: &Counter, labels: &[(&str, &str)]) {
+        self.with_series(counter.name, counter.help, "counter", labels, |series| {
+            if let Series::Counter(value) = series {
+                *value += 1.0;
+            }
+        });
+    }
+
+    pub fn set(&self, gauge: &Gauge, labels: &[(&str, &str)], value: f64) {
+        self.with_series(gauge.name, gauge.help, "gauge", labels, |series| {
+            if let Series::Gauge(current) = series {
+                *current = value;
+            }
+        });
+    }
+
+    pub fn observe(&self, histogram: &Histogram, labels: &[(&str, &str)], seconds: f64) {
+        self.with_series(
+            histogram.name,
+            histogram.help,
+            "histogram",
+            labels,
+            |series| {
+                if let Series::Histogram {
+                    buckets,
+                    sum,
+                    count,
+                } = series
+                {
+                    for (bucket, bound) in buckets.iter_mut().zip(BUCKETS) {
+                        if seconds <= bound {
+                            *bucket += 1;
+                        }
+                    }
+                    *sum += seconds;
+                    *count += 1;
+                }
+            },
+        );
+    }
+
+    fn with_series(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        kind: &'static str,
+        labels: &[(&str, &str)],
+        update: impl FnOnce(&mut Series),
+    ) {
+        let mut families = self.families.lock().expect("metrics lock poisoned");
+        let family = families.entry(name).or_insert_with(|| Family {
+            help,
+            kind,
+            series: BTreeMap::new(),
+        });
+        let series = family
+            .series
+            .entry(render_labels(labels))
+            .or_insert_with(|| match kind {
+                "counter" => Series::Counter(0.0),
+                "gauge" => Series::Gauge(0.0),
+                _ => Series::Histogram {
+                    buckets: [0; BUCKETS.len()],
+                    sum: 0.0,
+                    count: 0,
+                },
+            });
+        update(series);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    ///
+    /// Each sample is one `name{labels} value` line, so a scraper (or a test)
+    /// can split on the last space:
+    ///
+    /// ```
+    /// use common::metrics::{Registry, HTTP_REQUESTS_TOTAL};
+    ///
+    /// let registry = Registry::default();
+    /// registry.increment(&HTTP_REQUESTS_TOTAL, &[("route", "/time"), ("status", "2xx")]);
+    ///
+    /// let output = registry.render();
+    /// let line = output
+    ///     .lines()
+    ///     .find(|line| line.starts_with("http_requests_total{"))
+    ///     .unwrap();
+    /// let (series, value) = line.rsplit_once(' ').unwrap();
+    /// assert_eq!(series, r#"http_requests_total{route="/time",status="2xx"}"#);
+    /// assert_eq!(value.parse::<f64>().unwrap(), 1.0);
+    /// ```
+    pub fn render(&self) -> String {
+        let families = self.families.lock().expect("metrics lock poisoned");
+        let mut output = String::new();
+        for (name, family) in families.iter() {
+            let _ = writeln!(output, "# HELP {name} {}", family.help);
+            let _ = writeln!(output, "# TYPE {name} {}", family.kind);
+            for (labels, series) in &family.series {
+                match series {
+                    Series::Counter(value) | Series::Gauge(value) => {
+                        let _ = writeln!(output, "{name}{} {value}", braces(labels));
+                    }
+                    Series::Histogram {
+                        buckets,
+                        sum,
+                        count,
+                    } => {
+                        let separator = if labels.is_empty() { "" } else { "," };
+                        for (bound, bucket) in BUCKETS.iter().zip(buckets) {
+                            let _ = writeln!(
+                                output,
+                                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {bucket}"
+                            );
+                        }
+                        let _ = writeln!(
+                            output,
+                            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {count}"
+                        );
+                        let _ = writeln!(output, "{name}_sum{} {sum}", braces(labels));
+                        let _ = writeln!(output, "{name}_count{} {count}", braces(labels));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| {
+            let escaped = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{key}=\"{escaped}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn braces(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{labels}}}")
+    }
+}
+
+/// Status class label (`2xx`, `4xx`, ...) for a status code.
+pub fn status_class(status: u16) -> String {
+    format!("{}xx", status / 100)
+}
+
+/// Tower layer recording `http_requests_total` and
+/// `http_request_duration_seconds` for every request except [`METRICS_PATH`].
+#[derive(Clone)]
+pub struct MetricsLayer {
+    registry: Arc<Registry>,
+}
+
+impl MetricsLayer {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        MetricsLayer { registry }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    registry: Arc<Registry>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for MetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // Label by route template, not raw path, to keep cardinality bounded.
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or("unmatched", MatchedPath::as_str)
+            .to_string();
+        // Set by the tenant layer only for requests resolved to a tenant.
+        let tenant = request
+            .headers()
+            .get(TENANT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let future = self.inner.call(request);
+        if route == METRICS_PATH {
+            return Box::pin(future);
+        }
+
+        let registry = self.registry.clone();
+        let started = Instant::now();
+        Box::pin(async move {
+            let response = future.await?;
+            let status = status_class(response.status().as_u16());
+            let mut labels = vec![("route", route.as_str())];
+            if let Some(tenant) = &tenant {
+                labels.push(("tenant", tenant));
+            }
+            registry.observe(
+                &HTTP_REQUEST_DURATION_SECONDS,
+                &labels,
+                started.elapsed().as_secs_f64(),
+            );
+            labels.push(("status", &status));
+            registry.increment(&HTTP_REQUESTS_TOTAL, &labels);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_histogram() {
+        let registry = Registry::default();
+        for seconds in [0.003, 0.02, 3.0] {
+            registry.observe(&HTTP_REQUEST_DURATION_SECONDS, &[("route", "/")], seconds);
+        }
+        let output = registry.render();
+        assert!(output.contains("# TYPE http_request_duration_seconds histogram\n"));
+        assert!(
+            output.contains("http_request_duration_seconds_bucket{route=\"/\",le=\"0.005\"} 1\n")
+        );
+        assert!(
+            output.contains("http_request_duration_seconds_bucket{route=\"/\",le=\"0.025\"} 2\n")
+        );
+        assert!(
+            output.contains("http_request_duration_seconds_bucket{route=\"/\",le=\"+Inf\"} 3\n")
+        );
+        assert!(output.contains("http_request_duration_seconds_count{route=\"/\"} 3\n"));
+    }
+
+    #[test]
+    fn gauges_keep_the_last_value_set() {
+        const GAUGE: Gauge = Gauge {
+            name: "test_gauge",
+            help: "A test gauge.",
+        };
+        let registry = Registry::default();
+        registry.set(&GAUGE, &[], 3.0);
+        registry.set(&GAUGE, &[], 1.0);
+        let output = registry.render();
+        assert!(output.contains("# TYPE test_gauge gauge\n"));
+        assert!(output.contains("test_gauge 1\n"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(render_labels(&[("a", "x\"y\\z")]), r#"a="x\"y\\z""#);
+    }
+}
@@ -0,0 +1,882 @@
+//! OpenAPI 3.0 documents for both services, served at `/openapi.json`, with
+//! Swagger UI at `/docs`.
+//!
+//! Schemas are written next to the types they describe by implementing
+//! [`Schema`]; each service lists its routes with a [`Document`], and a test
+//! in each service checks that every documented route is served.
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+use crate::auth::API_KEY_HEADER;
+use crate::problem::{ErrorCode, PROBLEM_JSON};
+use crate::{
+    BatchTimeError, BatchTimeRequest, BatchTimeResponse, ConvertedTime, CreateTimerRequest,
+    CronRequest, CronResponse, DstInfo, Elapsed, ErrorResponse, TimeConvertResponse,
+    TimeDiffResponse, TimeRequest, TimeResponse, TimeResponseV2, TimerResponse, TimezoneList,
+    TimezoneOffset, UtcOffset, REQUEST_ID_HEADER,
+};
+
+pub const OPENAPI_PATH: &str = "/openapi.json";
+pub const DOCS_PATH: &str = "/docs";
+const API_KEY_SCHEME: &str = "ApiKey";
+
+/// Schemas by name, as in `components.schemas`.
+pub type Components = BTreeMap<&'static str, Value>;
+
+/// A type with a named schema in `components.schemas`.
+pub trait Schema {
+    const NAME: &'static str;
+
+    fn schema() -> Value;
+
+    /// Registers the schemas [`Schema::schema`] refers to.
+    fn references(_components: &mut Components) {}
+}
+
+/// Adds `T` and everything it refers to, once.
+pub fn register<T: Schema>(components: &mut Components) {
+    if !components.contains_key(T::NAME) {
+        components.insert(T::NAME, T::schema());
+        T::references(components);
+    }
+}
+
+/// `{"$ref": ...}` pointing at `T`'s schema.
+pub fn reference<T: Schema>() -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", T::NAME) })
+}
+
+/// An object schema; `properties` are `(name, schema, required)`.
+pub fn object(description: &str, properties: &[(&str, Value, bool)]) -> Value {
+    let required: Vec<&str> = properties
+        .iter()
+        .filter(|(_, _, required)| *required)
+        .map(|(name, _, _)| *name)
+        .collect();
+    let properties: Map<String, Value> = properties
+        .iter()
+        .map(|(name, schema, _)| (name.to_string(), schema.clone()))
+        .collect();
+    json!({
+        "type": "object",
+        "description": description,
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// A schema of a primitive `kind` such as `string` or `integer`.
+pub fn typed(kind: &str, description: &str) -> Value {
+    json!({ "type": kind, "description": description })
+}
+
+pub fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// One method on one path.
+pub struct Operation {
+    value: Map<String, Value>,
+    schemas: Vec<fn(&mut Components)>,
+}
+
+impl Operation {
+    pub fn new(summary: &str) -> Self {
+        let mut value = Map::new();
+        value.insert("summary".into(), summary.into());
+        value.insert(
+            "parameters".into(),
+            json!([{
+                "name": REQUEST_ID_HEADER,
+                "in": "header",
+                "description": "Correlation ID, echoed back; generated when absent.",
+                "schema": { "type": "string" },
+            }]),
+        );
+        value.insert("responses".into(), json!({}));
+        Operation {
+            value,
+            schemas: vec![register::<ErrorResponse>],
+        }
+    }
+
+    fn parameter(mut self, location: &str, name: &str, description: &str, required: bool) -> Self {
+        self.value["parameters"]
+            .as_array_mut()
+            .expect("parameters is an array")
+            .push(json!({
+                "name": name,
+                "in": location,
+                "description": description,
+                "required": required,
+                "schema": { "type": "string" },
+            }));
+        self
+    }
+
+    pub fn query(self, name: &str, description: &str) -> Self {
+        self.parameter("query", name, description, false)
+    }
+
+    pub fn required_query(self, name: &str, description: &str) -> Self {
+        self.parameter("query", name, description, true)
+    }
+
+    /// A `{name}` segment of the path.
+    pub fn path(self, name: &str, description: &str) -> Self {
+        self.parameter("path", name, description, true)
+    }
+
+    /// A JSON request body.
+    pub fn body<T: Schema>(mut self, description: &str) -> Self {
+        self.value.insert(
+            "requestBody".into(),
+            json!({
+                "description": description,
+                "required": true,
+                "content": { "application/json": { "schema": reference::<T>() } },
+            }),
+        );
+        self.schemas.push(register::<T>);
+        self
+    }
+
+    fn respond(mut self, status: u16, description: &str, content: Option<Value>) -> Self {
+        let mut response = json!({ "description": description });
+        if let Some(content) = content {
+            response["content"] = content;
+        }
+        self.value["responses"][status.to_string()] = response;
+        self
+    }
+
+    /// A JSON response of type `T`.
+    pub fn response<T: Schema>(mut self, status: u16, description: &str) -> Self {
+        self.schemas.push(register::<T>);
+        let content = json!({ "application/json": { "schema": reference::<T>() } });
+        self.respond(status, description, Some(content))
+    }
+
+    /// A response with an inline `schema` for a body of `media_type`, e.g.
+    /// `text/event-stream`, or a JSON body without a named type.
+    pub fn response_inline(
+        self,
+        status: u16,
+        description: &str,
+        media_type: &str,
+        schema: Value,
+    ) -> Self {
+        let content = json!({ media_type: { "schema": schema } });
+        self.respond(status, description, Some(content))
+    }
+
+    /// A response without a body, such as `101 Switching Protocols`.
+    pub fn empty_response(self, status: u16, description: &str) -> Self {
+        self.respond(status, description, None)
+    }
+
+    /// Requires an `X-Api-Key` when the service has keys configured.
+    pub fn secured(mut self) -> Self {
+        self.value
+            .insert("security".into(), json!([{ API_KEY_SCHEME: [] }]));
+        self.error(401, "Missing or invalid `X-Api-Key`.")
+    }
+
+    /// An [`ErrorResponse`] with `status`.
+    pub fn error(self, status: u16, description: &str) -> Self {
+        let content = json!({ (PROBLEM_JSON): { "schema": reference::<ErrorResponse>() } });
+        self.respond(status, description, Some(content))
+    }
+}
+
+/// An OpenAPI 3.0 document under construction.
+pub struct Document {
+    value: Value,
+    components: Components,
+}
+
+impl Document {
+    pub fn new(title: &str, description: &str) -> Self {
+        Document {
+            value: json!({
+                "openapi": "3.0.3",
+                "info": {
+                    "title": title,
+                    "description": description,
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "paths": {},
+            }),
+            components: Components::new(),
+        }
+    }
+
+    /// Documents `method` (lower case, e.g. `get`) on `path`, written with
+    /// `{name}` segments as OpenAPI expects.
+    pub fn route(mut self, method: &str, path: &str, operation: Operation) -> Self {
+        for register in &operation.schemas {
+            register(&mut self.components);
+        }
+        self.value["paths"][path][method] = Value::Object(operation.value);
+        self
+    }
+
+    /// Every documented `(method, path)`.
+    pub fn operations(&self) -> Vec<(String, String)> {
+        let paths = self.value["paths"].as_object().expect("paths is an object");
+        paths
+            .iter()
+            .flat_map(|(path, methods)| {
+                let methods = methods.as_object().expect("path item is an object");
+                methods
+                    .keys()
+                    .map(move |method| (method.clone(), path.clone()))
+            })
+            .collect()
+    }
+
+    pub fn into_json(mut self) -> Value {
+        self.value["components"] = json!({
+            "schemas": self.components,
+            "securitySchemes": {
+                API_KEY_SCHEME: { "type": "apiKey", "in": "header", "name": API_KEY_HEADER },
+            },
+        });
+        self.value
+    }
+}
+
+/// Serves `document` at [`OPENAPI_PATH`] and Swagger UI at [`DOCS_PATH`].
+pub fn routes<S: Clone + Send + Sync + 'static>(document: Value) -> Router<S> {
+    Router::new()
+        .route(
+            OPENAPI_PATH,
+            get(move || {
+                let document = document.clone();
+                async move { Json(document) }
+            }),
+        )
+        .route(DOCS_PATH, get(docs))
+}
+
+/// Swagger UI's assets are loaded from a CDN rather than bundled.
+async fn docs() -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "no-cache")],
+        Html(format!(
+            r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>API documentation</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({{ url: "{OPENAPI_PATH}", dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##
+        )),
+    )
+}
+
+/// `±HH:MM` offset label, shared by several schemas.
+fn offset_label() -> Value {
+    typed("string", "Offset from UTC as `±HH:MM`, e.g. `+05:30`.")
+}
+
+fn clock_offset_ms() -> Value {
+    typed(
+        "integer",
+        "Provider clock's offset from its NTP server at the last check, when monitored.",
+    )
+}
+
+fn error_code() -> Value {
+    let codes: Vec<Value> = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::InvalidTimezone,
+        ErrorCode::ValidationFailed,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::Conflict,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::UnsupportedMediaType,
+        ErrorCode::RateLimited,
+        ErrorCode::CapacityExceeded,
+        ErrorCode::Timeout,
+        ErrorCode::UpstreamUnavailable,
+        ErrorCode::UpstreamBadResponse,
+        ErrorCode::UpstreamTimeout,
+        ErrorCode::Unavailable,
+        ErrorCode::Internal,
+    ]
+    .into_iter()
+    .map(|code| json!(code))
+    .collect();
+    json!({
+        "type": "string",
+        "enum": codes,
+        "description": "Stable, machine-readable kind of failure.",
+    })
+}
+
+impl Schema for ErrorResponse {
+    const NAME: &'static str = "ErrorResponse";
+
+    fn schema() -> Value {
+        object(
+            "Body of every error response, an RFC 7807 problem sent as `application/problem+json`.",
+            &[
+                (
+                    "type",
+                    typed("string", "URI naming the kind of problem, e.g. `urn:time-api:problem:invalid-timezone`."),
+                    true,
+                ),
+                ("title", typed("string", "Summary of the kind of problem."), true),
+                ("status", typed("integer", "HTTP status code."), true),
+                ("code", error_code(), true),
+                ("detail", typed("string", "What went wrong."), true),
+                ("error", typed("string", "Same as `detail`, for older clients."), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+                (
+                    "timestamp",
+                    typed("string", "RFC 3339 time of the error."),
+                    true,
+                ),
+            ],
+        )
+    }
+}
+
+impl Schema for TimeResponse {
+    const NAME: &'static str = "TimeResponse";
+
+    fn schema() -> Value {
+        object(
+            "The current time in a timezone.",
+            &[
+                (
+                    "timestamp",
+                    typed("string", "In the requested format."),
+                    true,
+                ),
+                ("timezone", typed("string", "IANA name as requested."), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+                (
+                    "source",
+                    typed("string", "Which service answered, e.g. `api1->api2[0]`."),
+                    true,
+                ),
+                (
+                    "utc_offset_seconds",
+                    typed("integer", "Offset from UTC in seconds east of Greenwich."),
+                    false,
+                ),
+                ("utc_offset_label", offset_label(), false),
+                (
+                    "display",
+                    typed(
+                        "string",
+                        "Human-readable rendering in the requested locale.",
+                    ),
+                    false,
+                ),
+                (
+                    "format",
+                    typed("string", "Name of the format `timestamp` is in."),
+                    false,
+                ),
+                (
+                    "degraded",
+                    typed("boolean", "Set when computed without the time provider."),
+                    false,
+                ),
+                ("clock_offset_ms", clock_offset_ms(), false),
+            ],
+        )
+    }
+}
+
+impl Schema for TimeResponseV2 {
+    const NAME: &'static str = "TimeResponseV2";
+
+    fn schema() -> Value {
+        object(
+            "The current time in a timezone, with daylight saving details.",
+            &[
+                (
+                    "timestamp",
+                    typed("string", "In the requested format."),
+                    true,
+                ),
+                ("timezone", typed("string", "IANA name as requested."), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+                ("source", typed("string", "Which service answered."), true),
+                (
+                    "format",
+                    typed("string", "Name of the format `timestamp` is in."),
+                    true,
+                ),
+                ("utc_offset", reference::<UtcOffset>(), true),
+                ("dst", reference::<DstInfo>(), true),
+                (
+                    "display",
+                    typed(
+                        "string",
+                        "Human-readable rendering in the requested locale.",
+                    ),
+                    false,
+                ),
+                ("clock_offset_ms", clock_offset_ms(), false),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<UtcOffset>(components);
+        register::<DstInfo>(components);
+    }
+}
+
+impl Schema for UtcOffset {
+    const NAME: &'static str = "UtcOffset";
+
+    fn schema() -> Value {
+        object(
+            "Offset from UTC, including any daylight saving.",
+            &[
+                (
+                    "seconds",
+                    typed("integer", "Seconds east of Greenwich."),
+                    true,
+                ),
+                ("label", offset_label(), true),
+            ],
+        )
+    }
+}
+
+impl Schema for DstInfo {
+    const NAME: &'static str = "DstInfo";
+
+    fn schema() -> Value {
+        let mut next_transition = typed(
+            "string",
+            "RFC 3339 instant of the next offset change within a year.",
+        );
+        next_transition["nullable"] = true.into();
+        object(
+            "Daylight saving in the zone.",
+            &[
+                (
+                    "active",
+                    typed("boolean", "Whether DST is in effect."),
+                    true,
+                ),
+                (
+                    "offset_seconds",
+                    typed("integer", "DST part of the offset, `0` outside DST."),
+                    true,
+                ),
+                ("abbreviation", typed("string", "e.g. `PDT`."), true),
+                ("next_transition", next_transition, true),
+            ],
+        )
+    }
+}
+
+impl Schema for TimeRequest {
+    const NAME: &'static str = "TimeRequest";
+
+    fn schema() -> Value {
+        let mut schema = object(
+            "Options of a `POST /time` request; unknown fields are rejected.",
+            &[
+                (
+                    "timezone",
+                    typed("string", "IANA name (default: UTC)."),
+                    false,
+                ),
+                ("format", typed("string", "Timestamp format."), false),
+                (
+                    "locale",
+                    typed("string", "Language tag for `display`."),
+                    false,
+                ),
+                (
+                    "offset_seconds",
+                    typed("integer", "Seconds to add to the current time."),
+                    false,
+                ),
+            ],
+        );
+        schema["additionalProperties"] = false.into();
+        schema
+    }
+}
+
+impl Schema for BatchTimeRequest {
+    const NAME: &'static str = "BatchTimeRequest";
+
+    fn schema() -> Value {
+        json!({
+            "description": "Timezones to look up, as an object or a bare array of names.",
+            "oneOf": [
+                object(
+                    "Timezones to look up.",
+                    &[("timezones", array(typed("string", "IANA name.")), true)],
+                ),
+                array(typed("string", "IANA name.")),
+            ],
+        })
+    }
+}
+
+impl Schema for BatchTimeError {
+    const NAME: &'static str = "BatchTimeError";
+
+    fn schema() -> Value {
+        object(
+            "A timezone from a batch that could not be resolved.",
+            &[
+                ("timezone", typed("string", "As requested."), true),
+                ("error", typed("string", "Why it failed."), true),
+            ],
+        )
+    }
+}
+
+impl Schema for BatchTimeResponse {
+    const NAME: &'static str = "BatchTimeResponse";
+
+    fn schema() -> Value {
+        let item = json!({
+            "oneOf": [reference::<TimeResponse>(), reference::<BatchTimeError>()],
+        });
+        object(
+            "One entry per requested timezone, in request order.",
+            &[
+                ("items", array(item), true),
+                ("results", array(reference::<TimeResponse>()), true),
+                ("errors", array(reference::<BatchTimeError>()), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<TimeResponse>(components);
+        register::<BatchTimeError>(components);
+    }
+}
+
+impl Schema for TimeDiffResponse {
+    const NAME: &'static str = "TimeDiffResponse";
+
+    fn schema() -> Value {
+        object(
+            "The current offset of `to` relative to `from`.",
+            &[
+                ("from_timezone", typed("string", "IANA name."), true),
+                ("to_timezone", typed("string", "IANA name."), true),
+                (
+                    "offset_seconds",
+                    typed("integer", "`to` minus `from`."),
+                    true,
+                ),
+                ("offset_label", offset_label(), true),
+                ("from_timestamp", typed("string", "RFC 3339."), true),
+                ("to_timestamp", typed("string", "RFC 3339."), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+}
+
+impl Schema for ConvertedTime {
+    const NAME: &'static str = "ConvertedTime";
+
+    fn schema() -> Value {
+        object(
+            "An instant as seen in one timezone.",
+            &[
+                ("timezone", typed("string", "IANA name."), true),
+                ("timestamp", typed("string", "RFC 3339."), true),
+                (
+                    "utc_offset_seconds",
+                    typed("integer", "Offset from UTC."),
+                    true,
+                ),
+                ("utc_offset_label", offset_label(), true),
+                (
+                    "is_dst",
+                    typed("boolean", "Whether DST is in effect."),
+                    true,
+                ),
+            ],
+        )
+    }
+}
+
+impl Schema for TimeConvertResponse {
+    const NAME: &'static str = "TimeConvertResponse";
+
+    fn schema() -> Value {
+        object(
+            "One instant in both timezones.",
+            &[
+                (
+                    "epoch_seconds",
+                    typed("integer", "Unix time of the instant."),
+                    true,
+                ),
+                ("from", reference::<ConvertedTime>(), true),
+                ("to", reference::<ConvertedTime>(), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<ConvertedTime>(components);
+    }
+}
+
+impl Schema for TimezoneOffset {
+    const NAME: &'static str = "TimezoneOffset";
+
+    fn schema() -> Value {
+        object(
+            "A timezone's current offset from UTC.",
+            &[
+                ("name", typed("string", "IANA name."), true),
+                (
+                    "utc_offset_seconds",
+                    typed("integer", "Offset from UTC."),
+                    true,
+                ),
+                ("utc_offset_label", offset_label(), true),
+            ],
+        )
+    }
+}
+
+impl Schema for TimezoneList {
+    const NAME: &'static str = "TimezoneList";
+
+    fn schema() -> Value {
+        object(
+            "Timezone names, sorted alphabetically.",
+            &[
+                ("timezones", array(typed("string", "IANA name.")), true),
+                ("count", typed("integer", "Number of names."), true),
+                ("offsets", array(reference::<TimezoneOffset>()), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<TimezoneOffset>(components);
+    }
+}
+
+impl Schema for CronRequest {
+    const NAME: &'static str = "CronRequest";
+
+    fn schema() -> Value {
+        let mut schema = object(
+            "A cron expression to evaluate; unknown fields are rejected.",
+            &[
+                (
+                    "expression",
+                    typed(
+                        "string",
+                        "Minute, hour, day of month, month and day of week, e.g. \
+                         `*/15 9-17 * * MON-FRI`, or `@hourly`, `@daily`, `@weekly`, \
+                         `@monthly` or `@yearly`.",
+                    ),
+                    true,
+                ),
+                (
+                    "timezone",
+                    typed("string", "IANA name (default: UTC)."),
+                    false,
+                ),
+                (
+                    "count",
+                    typed("integer", "Fire times to list, 1 to 100 (default: 5)."),
+                    false,
+                ),
+            ],
+        );
+        schema["additionalProperties"] = false.into();
+        schema
+    }
+}
+
+impl Schema for CronResponse {
+    const NAME: &'static str = "CronResponse";
+
+    fn schema() -> Value {
+        object(
+            "Upcoming fire times of a cron expression.",
+            &[
+                (
+                    "expression",
+                    typed("string", "The expression as given."),
+                    true,
+                ),
+                (
+                    "timezone",
+                    typed("string", "Zone it was evaluated in."),
+                    true,
+                ),
+                (
+                    "next",
+                    array(typed("string", "RFC 3339 local fire time.")),
+                    true,
+                ),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+}
+
+impl Schema for CreateTimerRequest {
+    const NAME: &'static str = "CreateTimerRequest";
+
+    fn schema() -> Value {
+        let mut schema = object(
+            "A timer to start; unknown fields are rejected.",
+            &[("name", typed("string", "1 to 64 characters."), true)],
+        );
+        schema["additionalProperties"] = false.into();
+        schema
+    }
+}
+
+impl Schema for Elapsed {
+    const NAME: &'static str = "Elapsed";
+
+    fn schema() -> Value {
+        object(
+            "A duration, whole in each unit.",
+            &[
+                (
+                    "milliseconds",
+                    typed("integer", "Whole milliseconds."),
+                    true,
+                ),
+                ("seconds", typed("number", "Fractional seconds."), true),
+                ("minutes", typed("number", "Fractional minutes."), true),
+                ("hours", typed("number", "Fractional hours."), true),
+                (
+                    "iso8601",
+                    typed("string", "ISO 8601 duration, e.g. `PT1H2M3.456S`."),
+                    true,
+                ),
+            ],
+        )
+    }
+}
+
+impl Schema for TimerResponse {
+    const NAME: &'static str = "TimerResponse";
+
+    fn schema() -> Value {
+        let mut stopped_at = typed("string", "RFC 3339 instant it was stopped.");
+        stopped_at["nullable"] = true.into();
+        object(
+            "A server-side timer.",
+            &[
+                ("id", typed("string", "Timer ID."), true),
+                ("name", typed("string", "Name given at creation."), true),
+                (
+                    "started_at",
+                    typed("string", "RFC 3339 instant it was started."),
+                    true,
+                ),
+                ("stopped_at", stopped_at, true),
+                ("running", typed("boolean", "`false` once stopped."), true),
+                ("elapsed", reference::<Elapsed>(), true),
+                ("request_id", typed("string", "Correlation ID."), true),
+            ],
+        )
+    }
+
+    fn references(components: &mut Components) {
+        register::<Elapsed>(components);
+    }
+}
+
+/// Query parameters shared by the `/time` lookups of both services.
+pub fn time_options(operation: Operation) -> Operation {
+    operation
+        .query(
+            "format",
+            "`rfc3339` (default), `rfc2822`, `unix`, `unix_ms` or `custom:<strftime>`.",
+        )
+        .query("locale", "Language tag for `display`: `en` or `th`.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_referenced_schemas_once() {
+        let document = Document::new("Test", "A test service.")
+            .route(
+                "post",
+                "/time/batch",
+                Operation::new("Batch")
+                    .body::<BatchTimeRequest>("Timezones")
+                    .response::<BatchTimeResponse>(200, "Times")
+                    .error(400, "Bad request"),
+            )
+            .route(
+                "get",
+                "/timezone/{name}",
+                Operation::new("Zone")
+                    .path("name", "IANA name")
+                    .response::<TimeResponse>(200, "Time"),
+            );
+        assert_eq!(
+            document.operations(),
+            [
+                ("post".to_string(), "/time/batch".to_string()),
+                ("get".to_string(), "/timezone/{name}".to_string()),
+            ]
+        );
+        let document = document.into_json();
+        let schemas = document["components"]["schemas"].as_object().unwrap();
+        assert_eq!(
+            schemas.keys().collect::<Vec<_>>(),
+            [
+                "BatchTimeError",
+                "BatchTimeRequest",
+                "BatchTimeResponse",
+                "ErrorResponse",
+                "TimeResponse"
+            ]
+        );
+        let operation = &document["paths"]["/timezone/{name}"]["get"];
+        assert_eq!(operation["parameters"][1]["in"], "path");
+        assert_eq!(
+            operation["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/TimeResponse"
+        );
+        assert_eq!(schemas["TimeResponse"]["required"][0], "timestamp");
+    }
+}
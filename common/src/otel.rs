@@ -0,0 +1,403 @@
+//! Span export to an OpenTelemetry collector over OTLP/HTTP with JSON
+//! encoding, so a request can be followed across API1 and API2 in Jaeger or
+//! Tempo.
+//!
+//! Each service records a `SERVER` span per request (in
+//! [`trace_context_middleware`](crate::trace_context::trace_context_middleware))
+//! and a `CLIENT` span per outbound call made with [`send`]. IDs are the ones
+//! already propagated in `traceparent`, so spans from both services join up.
+//! Only sampled traces are exported; new traces are sampled at
+//! `otel_sampling_ratio`, continued ones follow the caller's decision.
+
+use reqwest::header::HeaderValue;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::trace_context::{TraceContext, TRACEPARENT_HEADER};
+
+/// Spans waiting beyond this many are dropped rather than buffered.
+const QUEUE_CAPACITY: usize = 2048;
+/// Most spans sent in one export request.
+const MAX_BATCH: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(2);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static TELEMETRY: OnceLock<Telemetry> = OnceLock::new();
+
+struct Telemetry {
+    sampling_ratio: f64,
+    exporter: Option<Exporter>,
+}
+
+/// OTLP span kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Server = 2,
+    Client = 3,
+}
+
+/// An attribute value: OTLP distinguishes strings from integers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::String(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        AttributeValue::Int(value)
+    }
+}
+
+/// A finished span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanData {
+    pub context: TraceContext,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+    pub error: bool,
+}
+
+impl SpanData {
+    fn to_json(&self) -> Value {
+        let nanos = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+                .to_string()
+        };
+        let mut span = json!({
+            "traceId": self.context.trace_id_hex(),
+            "spanId": self.context.span_id_hex(),
+            "name": self.name,
+            "kind": self.kind as u8,
+            "startTimeUnixNano": nanos(self.start),
+            "endTimeUnixNano": nanos(self.end),
+            "attributes": attributes_json(&self.attributes),
+            "status": { "code": if self.error { 2 } else { 0 } },
+        });
+        if let Some(parent) = self.context.parent_span_id {
+            span["parentSpanId"] = json!(format!("{parent:016x}"));
+        }
+        span
+    }
+}
+
+fn attributes_json(attributes: &[(&str, AttributeValue)]) -> Vec<Value> {
+    attributes
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                AttributeValue::String(value) => json!({ "stringValue": value }),
+                // OTLP JSON encodes 64-bit integers as strings.
+                AttributeValue::Int(value) => json!({ "intValue": value.to_string() }),
+            };
+            json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+/// Batches spans and posts them to `<endpoint>/v1/traces` in the background.
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    spans: mpsc::Sender<SpanData>,
+}
+
+impl Exporter {
+    /// Spawns the export task; must be called inside a Tokio runtime.
+    pub fn start(endpoint: &str, service: &str, interval: Duration) -> Self {
+        let (spans, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        tokio::spawn(export_loop(url, service.to_string(), interval, receiver));
+        Exporter { spans }
+    }
+
+    /// Queues `span`, dropping it when the queue is full.
+    pub fn export(&self, span: SpanData) {
+        if self.spans.try_send(span).is_err() {
+            warn!("Span export queue full; dropping span");
+        }
+    }
+}
+
+async fn export_loop(
+    url: String,
+    service: String,
+    interval: Duration,
+    mut receiver: mpsc::Receiver<SpanData>,
+) {
+    let http = reqwest::Client::new();
+    let mut batch = Vec::new();
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    if batch.len() < MAX_BATCH {
+                        continue;
+                    }
+                }
+                None => {
+                    post_batch(&http, &url, &service, &mut batch).await;
+                    return;
+                }
+            },
+            _ = ticker.tick() => {}
+        }
+        post_batch(&http, &url, &service, &mut batch).await;
+    }
+}
+
+/// Posts and clears `batch`, logging failures; the spans are not retried.
+async fn post_batch(http: &reqwest::Client, url: &str, service: &str, batch: &mut Vec<SpanData>) {
+    if batch.is_empty() {
+        return;
+    }
+    let body = export_request(service, batch);
+    batch.clear();
+    let sent = http
+        .post(url)
+        .timeout(EXPORT_TIMEOUT)
+        .json(&body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = sent {
+        warn!(url = %url, error = %e, "Failed to export spans");
+    }
+}
+
+/// An `ExportTraceServiceRequest` body for `spans` from `service`.
+fn export_request(service: &str, spans: &[SpanData]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": attributes_json(&[("service.name", service.into())]),
+            },
+            "scopeSpans": [{
+                "scope": { "name": "time-api" },
+                "spans": spans.iter().map(SpanData::to_json).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+/// Sets the sampling ratio and, when `otel_exporter_endpoint` is set,
+/// starts exporting spans tagged with `service`. Without this, every trace is
+/// sampled and nothing is exported. Must be called inside a Tokio runtime.
+///
+/// # Panics
+///
+/// Panics when called twice.
+pub fn init(service: &str, config: &Config) {
+    let exporter = config.otel_exporter_endpoint.as_deref().map(|endpoint| {
+        info!(endpoint = %endpoint, service = %service, "Exporting spans over OTLP");
+        Exporter::start(endpoint, service, EXPORT_INTERVAL)
+    });
+    let telemetry = Telemetry {
+        sampling_ratio: config.otel_sampling_ratio.unwrap_or(1.0),
+        exporter,
+    };
+    if TELEMETRY.set(telemetry).is_err() {
+        panic!("OpenTelemetry export already initialised");
+    }
+}
+
+/// Whether a new trace with `trace_id` is sampled. The decision depends only
+/// on the ID, like OpenTelemetry's `TraceIdRatioBased` sampler.
+pub fn should_sample(trace_id: u128) -> bool {
+    let ratio = TELEMETRY.get().map_or(1.0, |t| t.sampling_ratio);
+    sampled_at(ratio, trace_id)
+}
+
+fn sampled_at(ratio: f64, trace_id: u128) -> bool {
+    if ratio >= 1.0 {
+        return true;
+    }
+    // The low 64 bits of a trace ID are random; compare them to the ratio.
+    ((trace_id as u64) as f64) < ratio * u64::MAX as f64
+}
+
+/// Queues `span` for export if its trace is sampled and an exporter runs.
+pub fn export(span: SpanData) {
+    if let Some(exporter) = TELEMETRY.get().and_then(|t| t.exporter.as_ref()) {
+        if span.context.sampled() {
+            exporter.export(span);
+        }
+    }
+}
+
+/// Sends `request` with `execute` inside a `CLIENT` span: the span becomes
+/// the parent named in the outgoing `traceparent`, and is exported when the
+/// call completes. Requests without a valid `traceparent` are sent as they
+/// are.
+pub async fn send<F, Fut, E>(
+    mut request: reqwest::Request,
+    execute: F,
+) -> Result<reqwest::Response, E>
+where
+    F: FnOnce(reqwest::Request) -> Fut,
+    Fut: Future<Output = Result<reqwest::Response, E>>,
+    E: std::fmt::Display,
+{
+    // The caller's span is the parent named in the header it set.
+    let Some(context) = request
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::continue_from)
+    else {
+        return execute(request).await;
+    };
+
+    if let Ok(traceparent) = HeaderValue::from_str(&context.traceparent()) {
+        request
+            .headers_mut()
+            .insert(TRACEPARENT_HEADER, traceparent);
+    }
+    let name = format!("{} {}", request.method(), request.url().path());
+    let mut attributes: Vec<(&'static str, AttributeValue)> = vec![
+        ("http.request.method", request.method().as_str().into()),
+        ("url.full", request.url().to_string().into()),
+    ];
+    let start = SystemTime::now();
+    let result = execute(request).await;
+    let error = match &result {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            attributes.push(("http.response.status_code", i64::from(status).into()));
+            status >= 500
+        }
+        Err(e) => {
+            attributes.push(("error.type", e.to_string().into()));
+            true
+        }
+    };
+    export(SpanData {
+        context,
+        name,
+        kind: SpanKind::Client,
+        start,
+        end: SystemTime::now(),
+        attributes,
+        error,
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    fn span(parent_span_id: Option<u64>) -> SpanData {
+        SpanData {
+            context: TraceContext {
+                trace_id: 0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736,
+                span_id: 0x00f0_67aa_0ba9_02b7,
+                parent_span_id,
+                flags: 1,
+            },
+            name: "GET /time".to_string(),
+            kind: SpanKind::Server,
+            start: UNIX_EPOCH + Duration::from_millis(1500),
+            end: UNIX_EPOCH + Duration::from_millis(1501),
+            attributes: vec![
+                ("http.route", "/time".into()),
+                ("http.response.status_code", 200.into()),
+            ],
+            error: false,
+        }
+    }
+
+    #[test]
+    fn encodes_spans_as_otlp_json() {
+        let body = export_request("api1", &[span(Some(0xab))]);
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0],
+            json!({ "key": "service.name", "value": { "stringValue": "api1" } })
+        );
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["spanId"], "00f067aa0ba902b7");
+        assert_eq!(span["parentSpanId"], "00000000000000ab");
+        assert_eq!(span["kind"], 2);
+        assert_eq!(span["startTimeUnixNano"], "1500000000");
+        assert_eq!(
+            span["attributes"][1],
+            json!({ "key": "http.response.status_code", "value": { "intValue": "200" } })
+        );
+
+        let root = SpanData::to_json(&self::span(None));
+        assert!(root.get("parentSpanId").is_none());
+    }
+
+    #[test]
+    fn samples_by_trace_id() {
+        assert!(sampled_at(1.0, 0));
+        assert!(!sampled_at(0.0, 0));
+        assert!(sampled_at(0.5, 1));
+        assert!(!sampled_at(0.5, u64::MAX as u128));
+    }
+
+    #[tokio::test]
+    async fn posts_batches_to_the_collector() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let collector = Router::new().route(
+            "/v1/traces",
+            post(move |Json(body): Json<Value>| async move {
+                sink.lock().unwrap().push(body);
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await.unwrap() });
+
+        let exporter = Exporter::start(&endpoint, "api2", Duration::from_millis(20));
+        exporter.export(span(None));
+        exporter.export(span(Some(1)));
+        drop(exporter);
+
+        for _ in 0..100 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let received = received.lock().unwrap();
+        let spans: usize = received
+            .iter()
+            .map(|body| {
+                body["resourceSpans"][0]["scopeSpans"][0]["spans"]
+                    .as_array()
+                    .unwrap()
+                    .len()
+            })
+            .sum();
+        assert_eq!(spans, 2);
+    }
+}
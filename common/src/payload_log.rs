@@ -0,0 +1,359 @@
+//! Debug logging of request and response payloads, for chasing production
+//! incidents.
+//!
+//! When enabled, [`log`] writes a `payload` event at debug level for each
+//! request (query, headers and body) and its response (headers and body).
+//! Bodies are cut to `payload_log_max_bytes`. Headers, query parameters and
+//! JSON fields named in `payload_log_redact`, or in the built-in list of
+//! credentials and callback URLs, are replaced by `[REDACTED]`. Streamed
+//! responses, such as `/time/stream`, are logged without their body.
+//!
+//! Logging starts off unless `payload_log` is set, and each service's
+//! `/admin/payload-logging` endpoint turns it on and off at runtime. The
+//! events are only written when the log level includes
+//! `payload=debug`.
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{debug, Level};
+
+use crate::config::Config;
+use crate::REQUEST_ID_HEADER;
+
+pub const ADMIN_PATH: &str = "/admin/payload-logging";
+
+const DEFAULT_MAX_BYTES: usize = 4096;
+const REDACTED: &str = "[REDACTED]";
+
+/// Names always redacted, in addition to `payload_log_redact`.
+const DEFAULT_REDACT: [&str; 6] = [
+    "authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "api_key",
+    "callback_url",
+];
+
+/// Whether payloads are logged, and how.
+pub struct PayloadLog {
+    enabled: AtomicBool,
+    max_bytes: usize,
+    /// Lowercase header, query parameter and JSON field names.
+    redact: Vec<String>,
+}
+
+impl Default for PayloadLog {
+    fn default() -> Self {
+        PayloadLog::new(false, DEFAULT_MAX_BYTES, &[])
+    }
+}
+
+/// Body of `PUT /admin/payload-logging`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PayloadLogUpdate {
+    pub enabled: bool,
+}
+
+/// The current settings, as `/admin/payload-logging` reports them.
+#[derive(Debug, Serialize)]
+pub struct PayloadLogSettings {
+    pub enabled: bool,
+    pub max_bytes: usize,
+    pub redacted: Vec<String>,
+    /// Whether the log level lets the events through; when `false`, set
+    /// `LOG_LEVEL` to include `payload=debug`.
+    pub debug_enabled: bool,
+}
+
+impl PayloadLog {
+    /// Logs with `max_bytes` per body, redacting `redact` on top of the
+    /// built-in names.
+    pub fn new(enabled: bool, max_bytes: usize, redact: &[String]) -> Self {
+        let mut names: Vec<String> = DEFAULT_REDACT.iter().map(|name| name.to_string()).collect();
+        for name in redact {
+            let name = name.trim().to_ascii_lowercase();
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        PayloadLog {
+            enabled: AtomicBool::new(enabled),
+            max_bytes,
+            redact: names,
+        }
+    }
+
+    /// Uses `payload_log`, `payload_log_max_bytes` and `payload_log_redact`,
+    /// starting disabled with 4096 bytes per body.
+    pub fn from_config(config: &Config) -> Self {
+        PayloadLog::new(
+            config.payload_log.unwrap_or(false),
+            config.payload_log_max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+            config.payload_log_redact.as_deref().unwrap_or_default(),
+        )
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn settings(&self) -> PayloadLogSettings {
+        PayloadLogSettings {
+            enabled: self.is_enabled(),
+            max_bytes: self.max_bytes,
+            redacted: self.redact.clone(),
+            debug_enabled: tracing::enabled!(target: "payload", Level::DEBUG),
+        }
+    }
+
+    fn redacts(&self, name: &str) -> bool {
+        self.redact
+            .iter()
+            .any(|redacted| redacted.eq_ignore_ascii_case(name))
+    }
+
+    /// Headers as a JSON object, with redacted values replaced.
+    fn headers(&self, headers: &HeaderMap) -> String {
+        let headers: serde_json::Map<String, Value> = headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redacts(name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value.into())
+            })
+            .collect();
+        Value::Object(headers).to_string()
+    }
+
+    /// A query string with redacted parameter values replaced.
+    fn query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.redacts(name) => format!("{name}={REDACTED}"),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// A body as text, redacted when it is JSON and cut to `max_bytes`.
+    fn body(&self, body: &[u8]) -> String {
+        let text = match serde_json::from_slice::<Value>(body) {
+            Ok(mut json) => {
+                self.redact_json(&mut json);
+                json.to_string()
+            }
+            Err(_) => match std::str::from_utf8(body) {
+                Ok(text) => text.to_string(),
+                Err(_) => return format!("<{} bytes of binary>", body.len()),
+            },
+        };
+        truncate(text, self.max_bytes)
+    }
+
+    fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (name, value) in fields.iter_mut() {
+                    if self.redacts(name) {
+                        *value = REDACTED.into();
+                    } else {
+                        self.redact_json(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Cuts `text` to at most `max_bytes`, on a character boundary, noting the
+/// full length.
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let length = text.len();
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    format!("{text}... ({length} bytes)")
+}
+
+/// Middleware logging each request and response payload while enabled.
+///
+/// Runs inside the request limits, which have already bounded the body.
+pub async fn log(State(log): State<Arc<PayloadLog>>, request: Request, next: Next) -> Response {
+    if !log.is_enabled() || !tracing::enabled!(target: "payload", Level::DEBUG) {
+        return next.run(request).await;
+    }
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return next.run(Request::from_parts(parts, Body::empty())).await;
+    };
+    debug!(
+        target: "payload",
+        request_id = request_id.as_deref(),
+        method = %parts.method,
+        path = %parts.uri.path(),
+        query = parts.uri.query().map(|query| log.query(query)),
+        headers = %log.headers(&parts.headers),
+        body = %log.body(&body),
+        "Request payload"
+    );
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    // Buffering a stream would hold it back until it ends.
+    if response.body().size_hint().exact().is_none() {
+        debug!(
+            target: "payload",
+            request_id = request_id.as_deref(),
+            status = response.status().as_u16(),
+            headers = %log.headers(response.headers()),
+            "Response payload streamed; body not logged"
+        );
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_else(|_| Bytes::new());
+    debug!(
+        target: "payload",
+        request_id = request_id.as_deref(),
+        status = parts.status.as_u16(),
+        headers = %log.headers(&parts.headers),
+        body = %log.body(&body),
+        "Response payload"
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use axum::{routing::post, Router};
+    use std::io;
+    use std::sync::Mutex;
+    use tower::Service;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn logs_payloads_and_passes_them_through() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let log = Arc::new(PayloadLog::new(true, 1024, &[]));
+        let mut app = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route_layer(axum::middleware::from_fn_with_state(log, super::log));
+        let body = r#"{"callback_url":"https://hooks.example.com/t0k3n","label":"standup"}"#;
+        let request = Request::post("/echo?api_key=key-1")
+            .header("x-api-key", "key-1")
+            .body(Body::from(body))
+            .unwrap();
+        // Router is always ready, so poll_ready can be skipped.
+        let response = app.call(request).await.unwrap();
+        let echoed = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(echoed, body);
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("Request payload"), "{output}");
+        assert!(output.contains("Response payload"), "{output}");
+        assert!(output.contains("standup"), "{output}");
+        assert!(!output.contains("t0k3n"), "{output}");
+        assert!(!output.contains("key-1"), "{output}");
+    }
+
+    #[test]
+    fn redacts_configured_and_built_in_names() {
+        let log = PayloadLog::new(true, 1024, &["Secret".to_string()]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_static("key-1"));
+        headers.insert("x-request-id", HeaderValue::from_static("req-1"));
+        let headers: Value = serde_json::from_str(&log.headers(&headers)).unwrap();
+        assert_eq!(headers["x-api-key"], REDACTED);
+        assert_eq!(headers["x-request-id"], "req-1");
+
+        assert_eq!(
+            log.query("timezone=UTC&secret=hunter2"),
+            "timezone=UTC&secret=[REDACTED]"
+        );
+
+        let body: Value = serde_json::from_str(&log.body(
+            br#"{"after_secs":5,"callback_url":"https://hooks.example.com/t0k3n","nested":[{"SECRET":1}]}"#,
+        ))
+        .unwrap();
+        assert_eq!(body["after_secs"], 5);
+        assert_eq!(body["callback_url"], REDACTED);
+        assert_eq!(body["nested"][0]["SECRET"], REDACTED);
+    }
+
+    #[test]
+    fn truncates_long_and_binary_bodies() {
+        let log = PayloadLog::new(true, 4, &[]);
+        assert_eq!(log.body(b"abc"), "abc");
+        assert_eq!(log.body(b"abcdef"), "abcd... (6 bytes)");
+        // Cut on a character boundary: each Thai character is three bytes.
+        assert_eq!(log.body("นาที".as_bytes()), "น... (12 bytes)");
+        assert_eq!(log.body(&[0xff, 0xfe]), "<2 bytes of binary>");
+    }
+
+    #[test]
+    fn toggles_at_runtime() {
+        let log = PayloadLog::default();
+        assert!(!log.settings().enabled);
+        log.set_enabled(true);
+        assert!(log.is_enabled());
+        assert_eq!(log.settings().max_bytes, DEFAULT_MAX_BYTES);
+    }
+}
@@ -0,0 +1,297 @@
+//! RFC 7807 problem details for error responses.
+//!
+//! Every error either service returns is an [`ErrorResponse`] sent as
+//! `application/problem+json`. Its `code` is one of [`ErrorCode`]'s stable,
+//! machine-readable values, so clients can branch on the kind of failure
+//! rather than on the wording of `detail`. The `error`, `request_id` and
+//! `timestamp` members of the original error body are kept as extensions.
+//! Handlers take query strings through [`Query`], so those that do not
+//! deserialize are reported the same way.
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize, Serializer};
+
+use crate::{ErrorResponse, REQUEST_ID_HEADER};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Prefix of each problem's `type` URI, followed by the code in kebab case.
+const TYPE_PREFIX: &str = "urn:time-api:problem:";
+
+/// The kinds of failure clients can tell apart, serialized as `code`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// A malformed or out-of-range parameter or body.
+    InvalidRequest,
+    /// A timezone name that is not in the IANA database.
+    InvalidTimezone,
+    /// A well-formed body that fails validation.
+    ValidationFailed,
+    Unauthorized,
+    /// The caller is known but may not use this endpoint, e.g. one outside
+    /// its tenant's allowed endpoints.
+    Forbidden,
+    NotFound,
+    /// The request conflicts with one already made.
+    Conflict,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    /// The caller exceeded its request rate.
+    RateLimited,
+    /// A limit on stored resources, such as timers, is reached.
+    CapacityExceeded,
+    /// The request ran out of time in the service itself.
+    Timeout,
+    /// API2 could not be reached, or the circuit breaker is open.
+    UpstreamUnavailable,
+    /// API2 answered with an error or an unreadable body.
+    UpstreamBadResponse,
+    UpstreamTimeout,
+    /// The service cannot answer right now, e.g. while shutting down.
+    Unavailable,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Status sent with this code unless the caller picks another.
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidRequest | ErrorCode::InvalidTimezone => StatusCode::BAD_REQUEST,
+            ErrorCode::ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::RateLimited | ErrorCode::CapacityExceeded => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::Timeout | ErrorCode::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ErrorCode::UpstreamUnavailable | ErrorCode::Unavailable => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ErrorCode::UpstreamBadResponse => StatusCode::BAD_GATEWAY,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The generic code for a status decided elsewhere, such as by a body
+    /// extractor's rejection.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::ValidationFailed,
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::GATEWAY_TIMEOUT => ErrorCode::Timeout,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Unavailable,
+            StatusCode::BAD_GATEWAY => ErrorCode::UpstreamBadResponse,
+            status if status.is_client_error() => ErrorCode::InvalidRequest,
+            _ => ErrorCode::Internal,
+        }
+    }
+
+    /// Short summary that is the same for every occurrence, the `title`.
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "Invalid request",
+            ErrorCode::InvalidTimezone => "Invalid timezone",
+            ErrorCode::ValidationFailed => "Validation failed",
+            ErrorCode::Unauthorized => "Unauthorized",
+            ErrorCode::Forbidden => "Forbidden",
+            ErrorCode::NotFound => "Not found",
+            ErrorCode::Conflict => "Conflict",
+            ErrorCode::PayloadTooLarge => "Payload too large",
+            ErrorCode::UnsupportedMediaType => "Unsupported media type",
+            ErrorCode::RateLimited => "Rate limited",
+            ErrorCode::CapacityExceeded => "Capacity exceeded",
+            ErrorCode::Timeout => "Request timed out",
+            ErrorCode::UpstreamUnavailable => "Upstream unavailable",
+            ErrorCode::UpstreamBadResponse => "Upstream bad response",
+            ErrorCode::UpstreamTimeout => "Upstream timed out",
+            ErrorCode::Unavailable => "Service unavailable",
+            ErrorCode::Internal => "Internal error",
+        }
+    }
+
+    /// The `type` URI, e.g. `urn:time-api:problem:invalid-timezone`.
+    pub fn problem_type(self) -> String {
+        let code = serde_json::to_value(self).expect("codes serialize");
+        let code = code.as_str().expect("codes are strings");
+        format!(
+            "{TYPE_PREFIX}{}",
+            code.to_ascii_lowercase().replace('_', "-")
+        )
+    }
+}
+
+impl ErrorResponse {
+    /// A problem with `code`'s usual status.
+    pub fn new(code: ErrorCode, detail: impl Into<String>, request_id: &str) -> Self {
+        ErrorResponse::with_status(code.status(), code, detail, request_id)
+    }
+
+    /// A problem sent with `status`, for codes that apply to several.
+    pub fn with_status(
+        status: StatusCode,
+        code: ErrorCode,
+        detail: impl Into<String>,
+        request_id: &str,
+    ) -> Self {
+        ErrorResponse {
+            status: status.as_u16(),
+            code,
+            error: detail.into(),
+            request_id: request_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// [`ErrorResponse`] as sent, with the members derived from `code`.
+#[derive(Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: &'static str,
+    status: u16,
+    code: ErrorCode,
+    detail: &'a str,
+    error: &'a str,
+    request_id: &'a str,
+    timestamp: &'a str,
+}
+
+impl Serialize for ErrorResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Problem {
+            problem_type: self.code.problem_type(),
+            title: self.code.title(),
+            status: self.status,
+            code: self.code,
+            detail: &self.error,
+            error: &self.error,
+            request_id: &self.request_id,
+            timestamp: &self.timestamp,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let mut response = (status, axum::Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
+/// [`axum::extract::Query`], but a query string that does not deserialize
+/// is answered as an `INVALID_REQUEST` problem rather than axum's plain text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+impl<T> std::ops::Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for Query<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Query::from_request_parts(parts, state).await {
+            Ok(axum::extract::Query(value)) => Ok(Query(value)),
+            Err(rejection) => {
+                let request_id = parts
+                    .headers
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map_or_else(|| uuid::Uuid::new_v4().to_string(), str::to_string);
+                Err(ErrorResponse::new(
+                    ErrorCode::InvalidRequest,
+                    rejection.body_text(),
+                    &request_id,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn responds_with_problem_details() {
+        let response = ErrorResponse::new(
+            ErrorCode::InvalidTimezone,
+            "Invalid timezone: Mars/Olympus",
+            "req-1",
+        )
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], "urn:time-api:problem:invalid-timezone");
+        assert_eq!(body["title"], "Invalid timezone");
+        assert_eq!(body["status"], 400);
+        assert_eq!(body["code"], "INVALID_TIMEZONE");
+        assert_eq!(body["detail"], "Invalid timezone: Mars/Olympus");
+        assert_eq!(body["error"], body["detail"]);
+        assert_eq!(body["request_id"], "req-1");
+    }
+
+    #[test]
+    fn maps_statuses_to_generic_codes() {
+        for (status, code) in [
+            (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest),
+            (StatusCode::METHOD_NOT_ALLOWED, ErrorCode::InvalidRequest),
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::ValidationFailed,
+            ),
+            (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ErrorCode::UnsupportedMediaType,
+            ),
+            (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::Unavailable),
+            (StatusCode::NOT_IMPLEMENTED, ErrorCode::Internal),
+        ] {
+            assert_eq!(ErrorCode::from_status(status), code, "{status}");
+        }
+    }
+}
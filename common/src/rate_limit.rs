@@ -0,0 +1,54 @@
+//! Per-route rate limit settings, written `PATH=RPS:BURST`.
+
+/// A token bucket applied to one route in place of the global limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteLimit {
+    pub path: String,
+    pub rate_per_second: f64,
+    pub burst: f64,
+}
+
+impl RouteLimit {
+    /// Parses e.g. `/time/batch=2:5`: two requests a second, bursts of five.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid route rate limit: {value} (expected PATH=RPS:BURST)");
+        let (path, limit) = value.trim().split_once('=').ok_or_else(invalid)?;
+        let (rate, burst) = limit.split_once(':').ok_or_else(invalid)?;
+        let positive = |number: &str| {
+            number
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| *number > 0.0)
+        };
+        match (path.trim(), positive(rate), positive(burst)) {
+            (path, Some(rate_per_second), Some(burst)) if path.starts_with('/') => Ok(RouteLimit {
+                path: path.to_string(),
+                rate_per_second,
+                burst,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_path_rate_and_burst() {
+        assert_eq!(
+            RouteLimit::parse(" /time/batch = 2:5 "),
+            Ok(RouteLimit {
+                path: "/time/batch".to_string(),
+                rate_per_second: 2.0,
+                burst: 5.0,
+            })
+        );
+        assert!(RouteLimit::parse("/time=0.5:1").is_ok());
+        assert!(RouteLimit::parse("/time=0:5").is_err());
+        assert!(RouteLimit::parse("time=1:5").is_err());
+        assert!(RouteLimit::parse("/time=1").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Log file that starts afresh every hour or day, optionally deleting the
+//! oldest files.
+//!
+//! Files are named after the configured path plus the period, like
+//! `tracing-appender`: `api1.log.2025-01-15` when daily,
+//! `api1.log.2025-01-15-09` when hourly, and the path itself with `never`.
+
+use chrono::{DateTime, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::fmt::MakeWriter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl Rotation {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "hourly" => Ok(Rotation::Hourly),
+            "daily" => Ok(Rotation::Daily),
+            "never" => Ok(Rotation::Never),
+            _ => Err(format!(
+                "Invalid log rotation: {value} (expected hourly, daily or never)"
+            )),
+        }
+    }
+
+    /// Suffix naming the file written at `now`, empty when never rotating.
+    fn suffix(self, now: DateTime<Utc>) -> String {
+        match self {
+            Rotation::Hourly => now.format(".%Y-%m-%d-%H").to_string(),
+            Rotation::Daily => now.format(".%Y-%m-%d").to_string(),
+            Rotation::Never => String::new(),
+        }
+    }
+}
+
+struct Current {
+    suffix: String,
+    file: File,
+}
+
+/// A [`MakeWriter`] appending to the file for the current period.
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    /// Files kept, counting the current one; all when `None`.
+    max_files: Option<usize>,
+    current: Mutex<Current>,
+}
+
+impl RollingFile {
+    /// Opens the file for the current period, creating its directory.
+    pub fn open(path: &Path, rotation: Rotation, max_files: Option<usize>) -> io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let suffix = rotation.suffix(Utc::now());
+        let file = open_append(path, &suffix)?;
+        let rolling = RollingFile {
+            path: path.to_path_buf(),
+            rotation,
+            max_files,
+            current: Mutex::new(Current { suffix, file }),
+        };
+        rolling.prune();
+        Ok(rolling)
+    }
+
+    fn write_at(&self, bytes: &[u8], now: DateTime<Utc>) -> io::Result<usize> {
+        let mut current = self.current.lock().expect("log file lock poisoned");
+        let suffix = self.rotation.suffix(now);
+        if suffix != current.suffix {
+            *current = Current {
+                file: open_append(&self.path, &suffix)?,
+                suffix,
+            };
+            self.prune();
+        }
+        current.file.write(bytes)
+    }
+
+    /// Deletes the oldest rotated files beyond `max_files`. Failures are
+    /// ignored: logging must not stop because a file could not be removed.
+    fn prune(&self) {
+        let (Some(max_files), Some(dir), Some(name)) = (
+            self.max_files,
+            self.path.parent(),
+            self.path.file_name().and_then(|name| name.to_str()),
+        ) else {
+            return;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = format!("{name}.");
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut rotated: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|file| file.starts_with(&prefix))
+            })
+            .map(|entry| entry.path())
+            .collect();
+        // Period suffixes sort chronologically.
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(max_files.max(1));
+        for old in &rotated[..excess] {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+fn open_append(path: &Path, suffix: &str) -> io::Result<File> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    OpenOptions::new().create(true).append(true).open(name)
+}
+
+/// Writes one formatted event to the current file.
+pub struct RollingWriter<'a>(&'a RollingFile);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.write_at(bytes, Utc::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0
+            .current
+            .lock()
+            .expect("log file lock poisoned")
+            .file
+            .flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn rotates_by_period_and_keeps_the_newest_files() {
+        let dir = std::env::temp_dir().join(format!("rolling-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("api.log");
+        let log = RollingFile::open(&path, Rotation::Hourly, Some(2)).unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2030, 1, 15, hour, 30, 0).unwrap();
+
+        log.write_at(b"first\n", at(9)).unwrap();
+        log.write_at(b"second\n", at(9)).unwrap();
+        log.write_at(b"third\n", at(10)).unwrap();
+        log.write_at(b"fourth\n", at(11)).unwrap();
+
+        let mut files: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["api.log.2030-01-15-10", "api.log.2030-01-15-11"]);
+        assert_eq!(
+            fs::read_to_string(dir.join("api.log.2030-01-15-11")).unwrap(),
+            "fourth\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_rotation_names() {
+        assert_eq!(Rotation::parse(" Daily ").unwrap(), Rotation::Daily);
+        assert_eq!(
+            Rotation::Daily.suffix(Utc.with_ymd_and_hms(2025, 1, 5, 23, 0, 0).unwrap()),
+            ".2025-01-05"
+        );
+        assert_eq!(Rotation::Never.suffix(Utc::now()), "");
+        assert!(Rotation::parse("weekly").is_err());
+    }
+}
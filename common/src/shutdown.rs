@@ -0,0 +1,282 @@
+//! Graceful shutdown on Ctrl-C or SIGTERM with a bounded drain period.
+
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{Json, Response};
+use std::future::IntoFuture;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Drain period used when `shutdown_timeout_secs` is not configured.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Resolves on Ctrl-C, or on SIGTERM where Unix signals are available.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+/// Broadcasts that shutdown was requested to the server, its drain deadline
+/// and the health checks, and counts the requests still being handled.
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+    requested: watch::Receiver<bool>,
+    /// How long listeners keep accepting after the signal, while health
+    /// checks already report the service as shutting down.
+    delay: Duration,
+    in_flight: Arc<AtomicUsize>,
+    /// When shutdown was requested and how many requests were in flight.
+    started: Arc<OnceLock<(Instant, usize)>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, requested) = watch::channel(false);
+        Shutdown {
+            sender: Arc::new(sender),
+            requested,
+            delay: Duration::ZERO,
+            in_flight: Arc::default(),
+            started: Arc::default(),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Starts listening for [`shutdown_signal`] in the background.
+    pub fn on_signal() -> Self {
+        let shutdown = Shutdown::default();
+        let signalled = shutdown.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            signalled.request();
+        });
+        shutdown
+    }
+
+    /// Keeps listeners accepting for `delay` after shutdown is requested, so
+    /// load balancers see the failing health check before connections are
+    /// refused.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Requests shutdown, as the signal handler does.
+    pub fn request(&self) {
+        let in_flight = self.in_flight();
+        if self.started.set((Instant::now(), in_flight)).is_ok() {
+            info!(
+                in_flight,
+                delay_secs = self.delay.as_secs_f64(),
+                "Shutdown requested; failing health checks before draining"
+            );
+            let _ = self.sender.send(true);
+        }
+    }
+
+    /// Whether shutdown has been requested, for health checks.
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Requests whose handlers are still running.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Resolves once shutdown has been requested and the delay has passed;
+    /// pass it to `axum::serve(..).with_graceful_shutdown`.
+    pub async fn requested(mut self) {
+        let _ = self.requested.wait_for(|requested| *requested).await;
+        tokio::time::sleep(self.delay).await;
+        info!("No longer accepting connections");
+    }
+
+    /// Drives `server` until it has drained, dropping whatever is still in
+    /// flight once `timeout` has passed since the listeners stopped.
+    pub async fn drain<F>(self, server: F, timeout: Duration) -> io::Result<()>
+    where
+        F: IntoFuture<Output = io::Result<()>>,
+    {
+        let deadline = async {
+            self.requested().await;
+            tokio::time::sleep(timeout).await;
+        };
+        tokio::select! {
+            result = server.into_future() => result,
+            () = deadline => {
+                warn!(
+                    timeout_secs = timeout.as_secs_f64(),
+                    "Shutdown timeout elapsed; dropping in-flight requests"
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// The `503` body health checks answer with once shutdown is requested.
+    pub fn health_response(&self, service: &str) -> (StatusCode, Json<serde_json::Value>) {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "status": "shutting_down",
+                "service": service,
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "in_flight": self.in_flight()
+            })),
+        )
+    }
+
+    /// Logs how the shutdown went, once every listener has drained.
+    pub fn log_summary(&self) {
+        let Some((started, in_flight_at_signal)) = self.started.get() else {
+            return;
+        };
+        info!(
+            in_flight_at_signal,
+            dropped = self.in_flight(),
+            duration_ms = started.elapsed().as_millis() as u64,
+            "Shutdown complete"
+        );
+    }
+}
+
+/// Middleware counting requests in [`Shutdown::in_flight`] while their
+/// handlers run; streamed bodies are not counted once headers are sent.
+pub async fn track_in_flight(
+    State(shutdown): State<Shutdown>,
+    request: Request,
+    next: Next,
+) -> Response {
+    struct Guard(Arc<AtomicUsize>);
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    shutdown.in_flight.fetch_add(1, Ordering::AcqRel);
+    let _guard = Guard(shutdown.in_flight.clone());
+    next.run(request).await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+
+    #[tokio::test]
+    async fn counts_in_flight_requests_and_delays_the_drain() {
+        let shutdown = Shutdown::default().with_delay(Duration::from_millis(200));
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    "done"
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                shutdown.clone(),
+                track_in_flight,
+            ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server =
+            axum::serve(listener, router).with_graceful_shutdown(shutdown.clone().requested());
+        tokio::spawn(shutdown.clone().drain(server, Duration::from_secs(5)));
+
+        let slow = tokio::spawn(reqwest::get(format!("http://{addr}/slow")));
+        // Wait for the request to arrive rather than for a fixed time.
+        for _ in 0..100 {
+            if shutdown.in_flight() > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(shutdown.in_flight(), 1);
+
+        shutdown.request();
+        assert!(shutdown.is_requested());
+        // Still accepting during the delay.
+        let during_delay = reqwest::get(format!("http://{addr}/slow")).await.unwrap();
+        assert_eq!(during_delay.text().await.unwrap(), "done");
+        assert_eq!(slow.await.unwrap().unwrap().text().await.unwrap(), "done");
+        assert_eq!(shutdown.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn sigterm_drains_in_flight_requests_and_exits() {
+        let router = Router::new().route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "done"
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let shutdown = Shutdown::on_signal();
+        let server =
+            axum::serve(listener, router).with_graceful_shutdown(shutdown.clone().requested());
+        let server = tokio::spawn(shutdown.drain(server, Duration::from_secs(5)));
+        // Let the signal task install its handlers before signalling.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let in_flight = tokio::spawn(async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            tokio::io::AsyncWriteExt::write_all(
+                &mut stream,
+                b"GET /slow HTTP/1.1\r\nHost: test\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+            let mut response = String::new();
+            tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut response)
+                .await
+                .unwrap();
+            response
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let result = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not shut down");
+        assert!(result.unwrap().is_ok());
+        let response = in_flight.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("done"));
+    }
+}
@@ -0,0 +1,646 @@
+//! Tenants: the teams sharing one deployment, each with its own defaults,
+//! quota and allowed endpoints.
+//!
+//! `tenants` defines them, one entry per tenant written
+//! `NAME;keys=K1|K2;timezone=TZ;locale=LOCALE;rate_limit=RPS:BURST;endpoints=/A|/B`,
+//! where every option is optional. A request belongs to the tenant owning
+//! its `X-Api-Key`, or else to a tenant without keys named in `X-Tenant-ID`.
+//! Naming a tenant that has keys without presenting one of them, or another
+//! tenant than the key's, is rejected with `401`, so no other caller can
+//! spend a tenant's quota or act under its name; an unknown tenant is
+//! rejected with `400`. Tenant keys are accepted wherever `api_keys` are.
+//!
+//! [`TenantLayer`] resolves the tenant before anything else sees the
+//! request, and then:
+//!
+//! - answers `403` for a path its `endpoints` do not cover, each covering
+//!   the paths below it in any API version;
+//! - fills in a `timezone` or `locale` query parameter the request left out
+//!   from the tenant's defaults;
+//! - sets `X-Tenant-ID` to the tenant, for audit records, metrics labels
+//!   and calls to API2, and runs the request in a `tenant` span.
+//!
+//! Without tenants configured, `X-Tenant-ID` is dropped and nothing else
+//! changes. The health probes and `/metrics` never belong to a tenant.
+
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{uri::PathAndQuery, HeaderValue, Request, Uri},
+    response::{IntoResponse, Response},
+};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+use tracing::{info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::auth::{key_index, API_KEY_HEADER};
+use crate::config::Config;
+use crate::health;
+use crate::metrics::METRICS_PATH;
+use crate::problem::ErrorCode;
+use crate::rate_limit::RouteLimit;
+use crate::versioning::unversioned;
+use crate::{ErrorResponse, REQUEST_ID_HEADER};
+
+/// Header naming the tenant a request belongs to.
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Paths that never belong to a tenant, so probes and scrapers keep working.
+const PROBES: [&str; 4] = [
+    "/health",
+    health::LIVE_PATH,
+    health::READY_PATH,
+    METRICS_PATH,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenant {
+    pub name: String,
+    /// API keys identifying the tenant's requests.
+    pub api_keys: Vec<String>,
+    /// Used when a request gives no `timezone`.
+    pub timezone: Option<String>,
+    /// Used when a request gives no `locale`.
+    pub locale: Option<String>,
+    /// Rate and burst of the bucket all the tenant's requests share on API1.
+    pub rate_limit: Option<(f64, f64)>,
+    /// Routes the tenant may call; every route when unset.
+    pub endpoints: Option<Vec<String>>,
+}
+
+impl Tenant {
+    /// Parses a `tenants` entry, e.g.
+    /// `team-a;keys=ka1|ka2;timezone=Asia/Bangkok;rate_limit=5:10`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut options = value.split(';');
+        let name = options.next().unwrap_or_default().trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid tenant name in {value} (expected letters, digits, - or _)"
+            ));
+        }
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split('|')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let mut tenant = Tenant {
+            name: name.to_string(),
+            api_keys: Vec::new(),
+            timezone: None,
+            locale: None,
+            rate_limit: None,
+            endpoints: None,
+        };
+        for option in options.map(str::trim).filter(|option| !option.is_empty()) {
+            let invalid = || format!("Invalid option for tenant {name}: {option}");
+            let (key, setting) = option.split_once('=').ok_or_else(invalid)?;
+            let setting = setting.trim();
+            match key.trim() {
+                "keys" => tenant.api_keys = list(setting),
+                "timezone" => {
+                    crate::resolve_timezone_alias(setting)
+                        .parse::<chrono_tz::Tz>()
+                        .map_err(|_| format!("Unknown timezone for tenant {name}: {setting}"))?;
+                    tenant.timezone = Some(setting.to_string());
+                }
+                "locale" if !setting.is_empty() => tenant.locale = Some(setting.to_string()),
+                "rate_limit" => {
+                    let limit = RouteLimit::parse(&format!("/={setting}")).map_err(|_| {
+                        format!(
+                            "Invalid rate_limit for tenant {name}: {setting} (expected RPS:BURST)"
+                        )
+                    })?;
+                    tenant.rate_limit = Some((limit.rate_per_second, limit.burst));
+                }
+                "endpoints" => {
+                    let endpoints = list(setting);
+                    if let Some(endpoint) = endpoints.iter().find(|e| !e.starts_with('/')) {
+                        return Err(format!(
+                            "Invalid endpoint for tenant {name}: {endpoint} (expected a path)"
+                        ));
+                    }
+                    tenant.endpoints = Some(endpoints);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(tenant)
+    }
+
+    /// Whether the tenant may call `path`, in any API version.
+    pub fn allows(&self, path: &str) -> bool {
+        let Some(endpoints) = &self.endpoints else {
+            return true;
+        };
+        let path = unversioned(path);
+        endpoints.iter().any(|endpoint| {
+            path.strip_prefix(endpoint.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// Why a request could not be given a tenant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// `X-Tenant-ID` names no tenant.
+    Unknown(String),
+    /// `X-Tenant-ID` names another tenant than the one owning the API key.
+    KeyMismatch { named: String, owner: String },
+    /// `X-Tenant-ID` names a tenant with API keys, but none of them was
+    /// presented.
+    KeyRequired(String),
+}
+
+/// Every configured tenant.
+#[derive(Debug, Default)]
+pub struct Tenants {
+    tenants: Vec<Tenant>,
+    /// Every tenant's API keys, and the position of each key's owner in
+    /// `tenants`.
+    keys: Vec<String>,
+    owners: Vec<usize>,
+}
+
+impl Tenants {
+    pub fn new(tenants: Vec<Tenant>) -> Self {
+        let (keys, owners) = tenants
+            .iter()
+            .enumerate()
+            .flat_map(|(index, tenant)| tenant.api_keys.iter().map(move |key| (key.clone(), index)))
+            .unzip();
+        Tenants {
+            tenants,
+            keys,
+            owners,
+        }
+    }
+
+    /// Uses `tenants`, skipping entries that do not parse; config validation
+    /// rejects those first.
+    pub fn from_config(config: &Config) -> Self {
+        Tenants::new(
+            config
+                .tenants
+                .iter()
+                .flatten()
+                .filter_map(|tenant| Tenant::parse(tenant).ok())
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    /// Every tenant's API keys.
+    pub fn api_keys(&self) -> Vec<String> {
+        self.keys.clone()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Tenant> {
+        self.tenants.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tenant> {
+        self.tenants.iter().find(|tenant| tenant.name == name)
+    }
+
+    /// The tenant owning `api_key`, compared in constant time.
+    fn owner(&self, api_key: &str) -> Option<&Tenant> {
+        key_index(&self.keys, api_key).map(|index| &self.tenants[self.owners[index]])
+    }
+
+    /// The tenant a request naming `named` in `X-Tenant-ID` and carrying
+    /// `api_key` belongs to, if any. A tenant with keys is only ever
+    /// resolved from one of them.
+    pub fn resolve(
+        &self,
+        named: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<Option<&Tenant>, Rejection> {
+        let owner = api_key.and_then(|key| self.owner(key));
+        match (named, owner) {
+            (Some(named), Some(owner)) if named != owner.name => Err(Rejection::KeyMismatch {
+                named: named.to_string(),
+                owner: owner.name.clone(),
+            }),
+            (_, Some(owner)) => Ok(Some(owner)),
+            (Some(named), None) => match self.get(named) {
+                None => Err(Rejection::Unknown(named.to_string())),
+                Some(tenant) if !tenant.api_keys.is_empty() => {
+                    Err(Rejection::KeyRequired(named.to_string()))
+                }
+                tenant => Ok(tenant),
+            },
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// The tenant a call to `path` belongs to, or the error code and detail
+    /// to refuse it with: the checks [`TenantLayer`] applies, for servers
+    /// outside the router such as gRPC.
+    pub fn admit(
+        &self,
+        path: &str,
+        named: Option<&str>,
+        api_key: Option<&str>,
+    ) -> Result<Option<&Tenant>, (ErrorCode, String)> {
+        if self.is_empty() || PROBES.contains(&path) {
+            return Ok(None);
+        }
+        let tenant = match self.resolve(named.map(str::trim), api_key) {
+            Ok(Some(tenant)) => tenant,
+            Ok(None) => return Ok(None),
+            Err(Rejection::Unknown(name)) => {
+                return Err((ErrorCode::InvalidRequest, format!("Unknown tenant: {name}")))
+            }
+            Err(Rejection::KeyMismatch { named, .. }) => {
+                return Err((
+                    ErrorCode::Unauthorized,
+                    format!("API key does not belong to tenant {named}"),
+                ))
+            }
+            Err(Rejection::KeyRequired(named)) => {
+                return Err((
+                    ErrorCode::Unauthorized,
+                    format!("Tenant {named} requires one of its API keys"),
+                ))
+            }
+        };
+        if !tenant.allows(path) {
+            return Err((
+                ErrorCode::Forbidden,
+                format!("Tenant {} may not call {path}", tenant.name),
+            ));
+        }
+        Ok(Some(tenant))
+    }
+}
+
+/// Validation problems across `tenants` entries: parse errors, names used
+/// twice and keys shared between tenants.
+pub fn problems(entries: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+    let mut owners = HashMap::new();
+    for entry in entries {
+        match Tenant::parse(entry) {
+            Ok(tenant) => {
+                if !names.insert(tenant.name.clone()) {
+                    problems.push(format!("Tenant {} is defined twice", tenant.name));
+                }
+                for key in &tenant.api_keys {
+                    if let Some(owner) = owners.insert(key.clone(), tenant.name.clone()) {
+                        if owner != tenant.name {
+                            problems.push(format!(
+                                "Tenants {owner} and {} share an API key",
+                                tenant.name
+                            ));
+                        }
+                    }
+                }
+            }
+            Err(e) => problems.push(e),
+        }
+    }
+    problems
+}
+
+/// Tower layer resolving each request's tenant; see the module docs.
+#[derive(Clone)]
+pub struct TenantLayer {
+    tenants: Arc<Tenants>,
+}
+
+impl TenantLayer {
+    pub fn new(tenants: Arc<Tenants>) -> Self {
+        TenantLayer { tenants }
+    }
+}
+
+impl<S> Layer<S> for TenantLayer {
+    type Service = TenantService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TenantService {
+            inner,
+            tenants: self.tenants.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TenantService<S> {
+    inner: S,
+    tenants: Arc<Tenants>,
+}
+
+impl<S, ResBody> Service<Request<Body>> for TenantService<S>
+where
+    S: Service<Request<Body>, Response = axum::http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: axum::body::HttpBody<Data = axum::body::Bytes> + Send + 'static,
+    ResBody::Error: Into<axum::BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let named = request.headers_mut().remove(TENANT_HEADER);
+        let named = named.as_ref().and_then(|value| value.to_str().ok());
+        let api_key = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let tenant = match self.tenants.admit(request.uri().path(), named, api_key) {
+            Ok(Some(tenant)) => tenant,
+            Ok(None) => return Box::pin(boxed(self.inner.call(request))),
+            Err((code, detail)) => {
+                return Box::pin(std::future::ready(Ok(reject(&request, code, detail))));
+            }
+        };
+        apply_defaults(&mut request, tenant);
+        request.headers_mut().insert(
+            TENANT_HEADER,
+            HeaderValue::from_str(&tenant.name).expect("tenant names are ASCII"),
+        );
+        let span = info_span!("tenant", tenant = %tenant.name);
+        let response = span.in_scope(|| self.inner.call(request));
+        Box::pin(boxed(response).instrument(span))
+    }
+}
+
+/// The inner service's response with its body boxed, so the layer can sit
+/// outside layers that change the body type.
+async fn boxed<F, ResBody, E>(response: F) -> Result<Response, E>
+where
+    F: Future<Output = Result<axum::http::Response<ResBody>, E>>,
+    ResBody: axum::body::HttpBody<Data = axum::body::Bytes> + Send + 'static,
+    ResBody::Error: Into<axum::BoxError>,
+{
+    Ok(response.await?.map(Body::new))
+}
+
+/// Adds the tenant's default `timezone` and `locale` to the query when the
+/// request gives none.
+fn apply_defaults(request: &mut Request<Body>, tenant: &Tenant) {
+    let given = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|Query(params)| params)
+        .unwrap_or_default();
+    let defaults: Vec<String> = [("timezone", &tenant.timezone), ("locale", &tenant.locale)]
+        .into_iter()
+        .filter(|(name, _)| !given.contains_key(*name))
+        .filter_map(|(name, value)| {
+            let value = value.as_deref()?;
+            Some(format!("{name}={}", encode(value)))
+        })
+        .collect();
+    if defaults.is_empty() {
+        return;
+    }
+
+    let uri = request.uri();
+    let query = match uri.query() {
+        Some(query) if !query.is_empty() => format!("{query}&{}", defaults.join("&")),
+        _ => defaults.join("&"),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = PathAndQuery::try_from(format!("{}?{query}", uri.path())).ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+}
+
+/// Percent-encodes the characters of a timezone or locale that are not
+/// safe in a query value.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn reject(request: &Request<Body>, code: ErrorCode, detail: String) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    warn!(
+        request_id = %request_id,
+        path = %request.uri().path(),
+        "{detail}"
+    );
+    ErrorResponse::new(code, detail, &request_id).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Router};
+
+    fn tenants() -> Tenants {
+        Tenants::new(vec![
+            Tenant::parse(
+                "team-a;keys=key-a|key-a2;timezone=Asia/Bangkok;locale=th-TH;rate_limit=5:10;endpoints=/time|/timezones",
+            )
+            .unwrap(),
+            Tenant::parse("team-b").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn parses_and_validates_tenants() {
+        let tenant = tenants().get("team-a").unwrap().clone();
+        assert_eq!(tenant.api_keys, ["key-a", "key-a2"]);
+        assert_eq!(tenant.timezone.as_deref(), Some("Asia/Bangkok"));
+        assert_eq!(tenant.locale.as_deref(), Some("th-TH"));
+        assert_eq!(tenant.rate_limit, Some((5.0, 10.0)));
+        assert!(tenant.allows("/v2/time/convert"));
+        assert!(tenant.allows("/timezones"));
+        assert!(!tenant.allows("/timezonesx"));
+        assert!(!tenant.allows("/admin/flags"));
+
+        for invalid in [
+            "",
+            "team a",
+            "team-a;timezone=Mars/Olympus",
+            "team-a;rate_limit=0:1",
+            "team-a;endpoints=time",
+            "team-a;color=blue",
+        ] {
+            assert!(Tenant::parse(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(
+            problems(&[
+                "team-a;keys=k1".to_string(),
+                "team-b;keys=k1".to_string(),
+                "team-b".to_string(),
+            ]),
+            [
+                "Tenants team-a and team-b share an API key",
+                "Tenant team-b is defined twice",
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_by_key_then_header() {
+        let tenants = tenants();
+        let name = |result: Result<Option<&Tenant>, Rejection>| {
+            result.map(|tenant| tenant.map(|tenant| tenant.name.clone()))
+        };
+        assert_eq!(
+            name(tenants.resolve(None, Some("key-a2"))),
+            Ok(Some("team-a".to_string()))
+        );
+        assert_eq!(
+            name(tenants.resolve(Some("team-b"), Some("operator-key"))),
+            Ok(Some("team-b".to_string()))
+        );
+        assert_eq!(name(tenants.resolve(None, Some("operator-key"))), Ok(None));
+        assert_eq!(
+            name(tenants.resolve(Some("team-c"), None)),
+            Err(Rejection::Unknown("team-c".to_string()))
+        );
+        assert!(matches!(
+            tenants.resolve(Some("team-b"), Some("key-a")),
+            Err(Rejection::KeyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn resolves_tenants_with_keys_only_from_their_keys() {
+        let tenants = tenants();
+        assert_eq!(
+            tenants
+                .resolve(Some("team-a"), Some("key-a"))
+                .map(|tenant| tenant.map(|tenant| tenant.name.as_str())),
+            Ok(Some("team-a"))
+        );
+        // Neither a global key nor no key at all may act as team-a.
+        for api_key in [Some("operator-key"), None] {
+            assert_eq!(
+                tenants.resolve(Some("team-a"), api_key),
+                Err(Rejection::KeyRequired("team-a".to_string())),
+                "{api_key:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_the_tenants_defaults_and_endpoints() {
+        let echo = |request: Request<Body>| async move {
+            let tenant = request
+                .headers()
+                .get(TENANT_HEADER)
+                .map(|value| value.to_str().unwrap().to_string());
+            format!("{:?} {}", tenant, request.uri())
+        };
+        let app = Router::new()
+            .route("/time", get(echo))
+            .route("/alarms", get(echo))
+            .route("/health", get(echo))
+            .layer(TenantLayer::new(Arc::new(tenants())));
+        let call = |uri: &str, headers: &[(&str, &str)]| {
+            let mut request = Request::get(uri);
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            let mut app = app.clone();
+            let request = request.body(Body::empty()).unwrap();
+            async move {
+                // Router is always ready, so poll_ready can be skipped.
+                let response = app.call(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        assert_eq!(
+            call("/time?format=unix", &[(API_KEY_HEADER, "key-a")]).await,
+            (
+                StatusCode::OK,
+                "Some(\"team-a\") /time?format=unix&timezone=Asia/Bangkok&locale=th-TH".to_string()
+            )
+        );
+        assert_eq!(
+            call(
+                "/time?timezone=UTC",
+                &[(TENANT_HEADER, "team-a"), (API_KEY_HEADER, "key-a2")]
+            )
+            .await,
+            (
+                StatusCode::OK,
+                "Some(\"team-a\") /time?timezone=UTC&locale=th-TH".to_string()
+            )
+        );
+        assert_eq!(
+            call("/time", &[]).await,
+            (StatusCode::OK, "None /time".to_string())
+        );
+        assert_eq!(
+            call("/health", &[(TENANT_HEADER, "team-c")]).await,
+            (StatusCode::OK, "None /health".to_string())
+        );
+        assert_eq!(
+            call("/alarms", &[(API_KEY_HEADER, "key-a")]).await.0,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            call("/time", &[(TENANT_HEADER, "team-b")]).await,
+            (StatusCode::OK, "Some(\"team-b\") /time".to_string())
+        );
+        assert_eq!(
+            call(
+                "/time",
+                &[(TENANT_HEADER, "team-a"), (API_KEY_HEADER, "operator-key")]
+            )
+            .await
+            .0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call("/time", &[(TENANT_HEADER, "team-c")]).await.0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call(
+                "/time",
+                &[(TENANT_HEADER, "team-b"), (API_KEY_HEADER, "key-a")]
+            )
+            .await
+            .0,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! An OpenSSL server stream over a Tokio `TcpStream`.
+//!
+//! OpenSSL does blocking-style I/O through `Read`/`Write`, so it runs over
+//! in-memory buffers: ciphertext OpenSSL writes is queued in `outgoing` and
+//! flushed to the socket, and a `WANT_READ` is answered by reading more of
+//! the socket into `incoming` before retrying.
+
+use openssl::ssl::{ErrorCode, Ssl, SslStream};
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+
+const READ_CHUNK: usize = 16 * 1024;
+
+#[derive(Default)]
+struct Buffers {
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    eof: bool,
+}
+
+impl Read for Buffers {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.incoming.is_empty() {
+            return match self.eof {
+                true => Ok(0),
+                false => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+        let n = buf.len().min(self.incoming.len());
+        buf[..n].copy_from_slice(&self.incoming[..n]);
+        self.incoming.drain(..n);
+        Ok(n)
+    }
+}
+
+impl Write for Buffers {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) struct TlsStream {
+    tcp: TcpStream,
+    ssl: SslStream<Buffers>,
+}
+
+fn ssl_error(e: openssl::ssl::Error) -> io::Error {
+    e.into_io_error()
+        .unwrap_or_else(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+impl TlsStream {
+    /// Runs the server side of the handshake on `tcp`.
+    pub(crate) async fn accept(ssl: Ssl, tcp: TcpStream) -> io::Result<Self> {
+        let ssl = SslStream::new(ssl, Buffers::default()).map_err(io::Error::other)?;
+        let mut stream = TlsStream { tcp, ssl };
+        loop {
+            let result = stream.ssl.accept();
+            let outgoing = std::mem::take(&mut stream.ssl.get_mut().outgoing);
+            stream.tcp.write_all(&outgoing).await?;
+            match result {
+                Ok(()) => return Ok(stream),
+                Err(e) if e.code() == ErrorCode::WANT_READ => {
+                    let mut chunk = vec![0; READ_CHUNK];
+                    let n = stream.tcp.read(&mut chunk).await?;
+                    if n == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    stream.ssl.get_mut().incoming.extend_from_slice(&chunk[..n]);
+                }
+                Err(e) => return Err(ssl_error(e)),
+            }
+        }
+    }
+
+    pub(crate) fn ssl(&self) -> &openssl::ssl::SslRef {
+        self.ssl.ssl()
+    }
+
+    /// Writes queued ciphertext to the socket.
+    fn poll_flush_outgoing(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let buffers = self.ssl.get_mut();
+        while !buffers.outgoing.is_empty() {
+            let n = ready!(Pin::new(&mut self.tcp).poll_write(cx, &buffers.outgoing))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            buffers.outgoing.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Reads more ciphertext from the socket for OpenSSL.
+    fn poll_fill_incoming(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut chunk = [0; READ_CHUNK];
+        let mut buf = ReadBuf::new(&mut chunk);
+        ready!(Pin::new(&mut self.tcp).poll_read(cx, &mut buf))?;
+        let buffers = self.ssl.get_mut();
+        match buf.filled() {
+            [] => buffers.eof = true,
+            data => buffers.incoming.extend_from_slice(data),
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for TlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let result = this.ssl.ssl_read(buf.initialize_unfilled());
+            // Reads can produce output too, such as session tickets.
+            if let Poll::Ready(Err(e)) = this.poll_flush_outgoing(cx) {
+                return Poll::Ready(Err(e));
+            }
+            match result {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Err(e) if e.code() == ErrorCode::ZERO_RETURN => return Poll::Ready(Ok(())),
+                Err(e) if e.code() == ErrorCode::WANT_READ => {
+                    if this.ssl.get_ref().eof {
+                        return Poll::Ready(Ok(()));
+                    }
+                    ready!(this.poll_fill_incoming(cx))?;
+                }
+                Err(e) => return Poll::Ready(Err(ssl_error(e))),
+            }
+        }
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // Accept new data only once earlier ciphertext is on the wire, so
+        // `outgoing` stays bounded.
+        ready!(this.poll_flush_outgoing(cx))?;
+        loop {
+            match this.ssl.ssl_write(buf) {
+                Ok(n) => {
+                    if let Poll::Ready(Err(e)) = this.poll_flush_outgoing(cx) {
+                        return Poll::Ready(Err(e));
+                    }
+                    return Poll::Ready(Ok(n));
+                }
+                Err(e) if e.code() == ErrorCode::WANT_READ => {
+                    ready!(this.poll_fill_incoming(cx))?;
+                }
+                Err(e) => return Poll::Ready(Err(ssl_error(e))),
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_flush_outgoing(cx))?;
+        Pin::new(&mut this.tcp).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.ssl.get_shutdown().is_empty() {
+            // Queues close_notify; the peer's reply is not awaited.
+            let _ = this.ssl.shutdown();
+        }
+        ready!(this.poll_flush_outgoing(cx))?;
+        Pin::new(&mut this.tcp).poll_shutdown(cx)
+    }
+}
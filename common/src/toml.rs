@@ -0,0 +1,295 @@
+//! Parser for the subset of TOML used by `config.toml`.
+//!
+//! Supports comments, `[table]` headers, bare and quoted keys, basic and
+//! literal strings, integers, floats, booleans and (multi-line) arrays.
+//! Inline tables, dotted keys and dates are rejected with an error.
+
+use serde_json::{Map, Number, Value};
+
+/// Parses a TOML document into a JSON object for use with `serde_json::from_value`.
+pub(crate) fn parse(text: &str) -> Result<Value, String> {
+    Parser {
+        chars: text.chars().collect(),
+        position: 0,
+        line: 1,
+    }
+    .document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl std::fmt::Display) -> Result<T, String> {
+        Err(format!("line {}: {message}", self.line))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => self.error(format!("expected '{expected}', found '{c}'")),
+            None => self.error(format!("expected '{expected}', found end of file")),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    /// Skips whitespace, newlines and comments between items.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.bump();
+                }
+                Some('#') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Requires the rest of the line to be blank or a comment.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+        match self.bump() {
+            None | Some('\n') => Ok(()),
+            Some(c) => self.error(format!("unexpected '{c}' after value")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Value, String> {
+        let mut root = Map::new();
+        let mut table: Option<String> = None;
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(Value::Object(root)),
+                Some('[') => {
+                    self.bump();
+                    self.skip_spaces();
+                    let name = self.key()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    self.end_of_line()?;
+                    if root.contains_key(&name) {
+                        return self.error(format!("duplicate table [{name}]"));
+                    }
+                    root.insert(name.clone(), Value::Object(Map::new()));
+                    table = Some(name);
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.skip_spaces();
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    self.end_of_line()?;
+                    let target = match &table {
+                        Some(name) => root[name].as_object_mut().expect("tables are objects"),
+                        None => &mut root,
+                    };
+                    if target.insert(key.clone(), value).is_some() {
+                        return self.error(format!("duplicate key {key}"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.position;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.bump();
+                }
+                if self.position == start {
+                    return match self.peek() {
+                        Some(c) => self.error(format!("expected a key, found '{c}'")),
+                        None => self.error("expected a key"),
+                    };
+                }
+                if self.peek() == Some('.') {
+                    return self.error("dotted keys are not supported");
+                }
+                Ok(self.chars[start..self.position].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.error("inline tables are not supported"),
+            Some(_) => self.scalar(),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(match self.bump() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some(c) => return self.error(format!("unsupported escape \\{c}")),
+                    None => return self.error("unterminated string"),
+                }),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            match self.bump() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                Some(c) => return self.error(format!("expected ',' or ']', found '{c}'")),
+                None => return self.error("unterminated array"),
+            }
+        }
+    }
+
+    /// Booleans and numbers, which run until whitespace, a comma or a bracket.
+    fn scalar(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if !c.is_whitespace() && !matches!(c, ',' | ']' | '#'))
+        {
+            self.bump();
+        }
+        let token: String = self.chars[start..self.position].iter().collect();
+        let digits = token.replace('_', "");
+        if token == "true" || token == "false" {
+            Ok(Value::Bool(token == "true"))
+        } else if let Ok(integer) = digits.parse::<i64>() {
+            Ok(Value::Number(integer.into()))
+        } else if let Some(float) = digits.parse::<f64>().ok().and_then(Number::from_f64) {
+            Ok(Value::Number(float))
+        } else {
+            self.error(format!("invalid value {token}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_supported_syntax() {
+        let document = r#"
+            # Service settings
+            port = 8_080
+            api2_url = "http://api2:4000" # trailing comment
+            'literal key' = 'C:\no\escapes'
+            ratio = 2.5
+            enabled = true
+            origins = [
+                "https://a.example.com",  # first
+                "https://b.example.com",
+            ]
+            empty = []
+
+            [limits]
+            burst = -20
+        "#;
+        assert_eq!(
+            parse(document).unwrap(),
+            json!({
+                "port": 8080,
+                "api2_url": "http://api2:4000",
+                "literal key": "C:\\no\\escapes",
+                "ratio": 2.5,
+                "enabled": true,
+                "origins": ["https://a.example.com", "https://b.example.com"],
+                "empty": [],
+                "limits": { "burst": -20 }
+            })
+        );
+        assert_eq!(parse("").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = |document| parse(document).unwrap_err();
+        assert_eq!(error("port = 1\nport = 2"), "line 2: duplicate key port");
+        assert_eq!(error("\nname = \"open"), "line 2: unterminated string");
+        assert_eq!(error("port 1"), "line 1: expected '=', found '1'");
+        assert_eq!(error("port = 1 2"), "line 1: unexpected '2' after value");
+        assert_eq!(
+            error("when = 1979-05-27"),
+            "line 1: invalid value 1979-05-27"
+        );
+        assert_eq!(error("a.b = 1"), "line 1: dotted keys are not supported");
+        assert_eq!(
+            error("t = { a = 1 }"),
+            "line 1: inline tables are not supported"
+        );
+    }
+}
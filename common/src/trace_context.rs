@@ -0,0 +1,211 @@
+//! W3C Trace Context (`traceparent`/`tracestate`) propagation.
+//!
+//! [`trace_context_middleware`] continues the caller's trace, or starts a new
+//! one, and runs the request inside a `trace` span carrying the trace and span IDs.
+//! Handlers read the request's [`TraceContext`] from the extensions to inject
+//! it into outbound calls.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::time::SystemTime;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+use crate::otel::{self, SpanData, SpanKind};
+use crate::REQUEST_ID_HEADER;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+const SAMPLED_FLAG: u8 = 0x01;
+
+/// The trace and span a request is handled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    /// Span ID of the caller, when the trace was continued from a header.
+    pub parent_span_id: Option<u64>,
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Starts a new trace with random IDs, sampled as [`otel::should_sample`]
+    /// decides.
+    pub fn new_root() -> Self {
+        let trace_id = Uuid::new_v4().as_u128();
+        TraceContext {
+            trace_id,
+            span_id: random_span_id(),
+            parent_span_id: None,
+            flags: if otel::should_sample(trace_id) {
+                SAMPLED_FLAG
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Parses a `traceparent` header and returns a child span of it.
+    ///
+    /// Returns `None` for malformed headers, all-zero IDs or the reserved
+    /// version `ff`, in which case callers should start a new trace.
+    pub fn continue_from(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next().filter(|part| part.len() == 2)?;
+        let trace_id = parts.next().filter(|part| part.len() == 32)?;
+        let parent_id = parts.next().filter(|part| part.len() == 16)?;
+        let flags = parts.next().filter(|part| part.len() == 2)?;
+        // Version 00 has exactly four fields; later versions may append more.
+        if version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        u8::from_str_radix(version, 16).ok()?;
+
+        let trace_id = parse_hex_id(trace_id, u128::from_str_radix)?;
+        let parent_span_id = parse_hex_id(parent_id, u64::from_str_radix)?;
+        Some(TraceContext {
+            trace_id,
+            span_id: random_span_id(),
+            parent_span_id: Some(parent_span_id),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Whether the trace is recorded, per the `sampled` flag.
+    pub fn sampled(&self) -> bool {
+        self.flags & SAMPLED_FLAG != 0
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    /// `traceparent` value naming this span as the parent of an outbound call.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id_hex(),
+            self.span_id_hex(),
+            self.flags
+        )
+    }
+}
+
+/// Parses lowercase hex, rejecting uppercase digits and all-zero IDs as the
+/// specification requires.
+fn parse_hex_id<T: PartialEq + Default>(
+    hex: &str,
+    parse: fn(&str, u32) -> Result<T, std::num::ParseIntError>,
+) -> Option<T> {
+    if hex.bytes().any(|byte| byte.is_ascii_uppercase()) {
+        return None;
+    }
+    parse(hex, 16).ok().filter(|id| *id != T::default())
+}
+
+fn random_span_id() -> u64 {
+    // The low half of a v4 UUID is random apart from two variant bits.
+    (Uuid::new_v4().as_u128() as u64).max(1)
+}
+
+/// Middleware attaching a [`TraceContext`] to each request and running it in
+/// a `trace` span with `trace_id`, `span_id`, `parent_span_id` and
+/// `request_id` fields. The request is also exported as a `SERVER` span when
+/// [`otel`] export is enabled.
+pub async fn trace_context_middleware(mut request: Request, next: Next) -> Response {
+    let (context, span) = {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let context = header(TRACEPARENT_HEADER)
+            .and_then(TraceContext::continue_from)
+            .unwrap_or_else(TraceContext::new_root);
+        let span = info_span!(
+            "trace",
+            trace_id = %context.trace_id_hex(),
+            span_id = %context.span_id_hex(),
+            parent_span_id = context.parent_span_id.map(|id| format!("{id:016x}")),
+            request_id = header(REQUEST_ID_HEADER),
+        );
+        (context, span)
+    };
+
+    request.extensions_mut().insert(context);
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    // Name spans by route template, not raw path, as the metrics do.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| path.clone(), |route| route.as_str().to_string());
+    let start = SystemTime::now();
+    let response = next.run(request).instrument(span).await;
+
+    let status = response.status().as_u16();
+    otel::export(SpanData {
+        context,
+        name: format!("{method} {route}"),
+        kind: SpanKind::Server,
+        start,
+        end: SystemTime::now(),
+        attributes: vec![
+            ("http.request.method", method.into()),
+            ("url.path", path.into()),
+            ("http.route", route.into()),
+            ("http.response.status_code", i64::from(status).into()),
+        ],
+        error: status >= 500,
+    });
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continues_valid_traceparent() {
+        let context =
+            TraceContext::continue_from("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+                .unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_span_id, Some(0x00f0_67aa_0ba9_02b7));
+        assert_ne!(context.span_id, 0x00f0_67aa_0ba9_02b7);
+
+        let outbound = context.traceparent();
+        assert!(outbound.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(outbound.ends_with("-01"));
+        assert_eq!(
+            TraceContext::continue_from(&outbound)
+                .unwrap()
+                .parent_span_id,
+            Some(context.span_id)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        for header in [
+            "",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            assert!(TraceContext::continue_from(header).is_none(), "{header}");
+        }
+    }
+}
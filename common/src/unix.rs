@@ -0,0 +1,319 @@
+//! Optional Unix domain socket listener, for clients on the same host or in
+//! the same pod that should not go through TCP loopback, and the connector
+//! API1 uses to reach an API2 that only listens on one.
+//!
+//! Requests arriving over the socket carry no `ConnectInfo<SocketAddr>`:
+//! the audit log records no client IP for them, and API1's rate limiter
+//! counts all of them against a single bucket.
+
+use axum::Router;
+use hyper014::client::connect::{Connected, Connection};
+use std::future::Future;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::connections::{serve_http1, Connections};
+use crate::shutdown::Shutdown;
+
+/// Socket file mode used when `unix_socket_perms` is not configured.
+pub const DEFAULT_SOCKET_PERMS: u32 = 0o600;
+
+/// Which listeners a service opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketMode {
+    Tcp,
+    Unix,
+    Both,
+}
+
+impl SocketMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tcp" => Ok(SocketMode::Tcp),
+            "unix" => Ok(SocketMode::Unix),
+            "both" => Ok(SocketMode::Both),
+            _ => Err(format!(
+                "Invalid socket mode: {value} (expected unix, tcp or both)"
+            )),
+        }
+    }
+
+    /// `socket_mode`, defaulting to `both` when `unix_socket_path` is set
+    /// and `tcp` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics on an unknown mode, or on `unix` or `both` without a path.
+    pub fn from_config(config: &Config) -> Self {
+        let mode = match &config.socket_mode {
+            Some(mode) => SocketMode::parse(mode).unwrap_or_else(|e| panic!("{e}")),
+            None if config.unix_socket_path.is_some() => SocketMode::Both,
+            None => SocketMode::Tcp,
+        };
+        if mode.unix() && config.unix_socket_path.is_none() {
+            panic!("SOCKET_MODE={mode:?} requires UNIX_SOCKET_PATH");
+        }
+        mode
+    }
+
+    pub fn tcp(self) -> bool {
+        matches!(self, SocketMode::Tcp | SocketMode::Both)
+    }
+
+    pub fn unix(self) -> bool {
+        matches!(self, SocketMode::Unix | SocketMode::Both)
+    }
+}
+
+/// Parses an octal file mode such as `600`, `0600` or `0o660`.
+pub fn parse_perms(value: &str) -> Result<u32, String> {
+    let digits = value.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("Invalid socket permissions: {value} (expected octal, e.g. 0660)"))
+}
+
+/// Binds `path`, replacing a socket left behind by an earlier run, and sets
+/// its permissions to `mode`. Any other kind of file at `path` is an error
+/// rather than being deleted.
+pub fn bind(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            info!(path = %path.display(), "Removing stale Unix socket");
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Binds `unix_socket_path` and serves `router` on it until shutdown, then
+/// drains like the TCP listener.
+///
+/// # Panics
+///
+/// Panics when `unix_socket_path` is unset, `unix_socket_perms` is invalid,
+/// or the socket cannot be bound, so a broken deployment fails at startup.
+pub async fn serve_from_config(
+    config: &Config,
+    router: Router,
+    shutdown: Shutdown,
+) -> io::Result<()> {
+    let path = PathBuf::from(
+        config
+            .unix_socket_path
+            .as_deref()
+            .expect("UNIX_SOCKET_PATH must be set to serve on a Unix socket"),
+    );
+    let mode = config
+        .unix_socket_perms
+        .as_deref()
+        .map_or(Ok(DEFAULT_SOCKET_PERMS), parse_perms)
+        .unwrap_or_else(|e| panic!("{e}"));
+    let listener = bind(&path, mode)
+        .unwrap_or_else(|e| panic!("Cannot bind Unix socket {}: {e}", path.display()));
+    info!(path = %path.display(), mode = format!("{mode:o}"), "Listening on Unix socket");
+
+    let server = serve(listener, router, shutdown.clone().requested());
+    shutdown.drain(server, config.shutdown_timeout()).await
+}
+
+/// Serves `router` until `shutdown` resolves, removes the socket file so new
+/// clients fail fast, then waits for open connections to finish.
+pub async fn serve(
+    listener: UnixListener,
+    router: Router,
+    shutdown: impl Future<Output = ()>,
+) -> io::Result<()> {
+    let path = listener.local_addr()?.as_pathname().map(Path::to_path_buf);
+    let connections = Connections::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    debug!(error = %e, "Failed to accept Unix socket connection");
+                    continue;
+                }
+            },
+            () = &mut shutdown => break,
+        };
+        let router = router.clone();
+        connections.spawn(|closing| serve_http1(stream, router, None, closing));
+    }
+
+    drop(listener);
+    if let Some(path) = path {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!(path = %path.display(), error = %e, "Failed to remove Unix socket");
+        }
+    }
+    connections.drain().await;
+    Ok(())
+}
+
+/// Connects an HTTP client to the socket at one path, whatever host the
+/// request URL names.
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    path: Arc<PathBuf>,
+}
+
+impl UnixConnector {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        UnixConnector {
+            path: Arc::new(path.into()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl tower::Service<hyper014::Uri> for UnixConnector {
+    type Response = UnixConnection;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<UnixConnection>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: hyper014::Uri) -> Self::Future {
+        let path = Arc::clone(&self.path);
+        Box::pin(async move {
+            UnixStream::connect(path.as_path())
+                .await
+                .map(UnixConnection)
+        })
+    }
+}
+
+/// A client connection made by [`UnixConnector`].
+#[derive(Debug)]
+pub struct UnixConnection(UnixStream);
+
+impl Connection for UnixConnection {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl AsyncRead for UnixConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UnixConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn serves_http_over_a_unix_socket() {
+        let directory = std::env::temp_dir().join(format!("unix-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&directory).unwrap();
+        let path = directory.join("api.sock");
+        // A stale socket from an earlier run is replaced.
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let listener = bind(&path, 0o660).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        let router = Router::new().route("/time", get(|| async { "now" }));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, router, async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /time HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("now"), "{response}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_replace_other_files() {
+        let path = std::env::temp_dir().join(format!("unix-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "keep me").unwrap();
+        assert_eq!(
+            bind(&path, DEFAULT_SOCKET_PERMS).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parses_modes_and_permissions() {
+        assert_eq!(SocketMode::parse("Both").unwrap(), SocketMode::Both);
+        assert!(SocketMode::parse("udp").is_err());
+        assert_eq!(parse_perms("600").unwrap(), 0o600);
+        assert_eq!(parse_perms("0o660").unwrap(), 0o660);
+        assert!(parse_perms("999").is_err());
+
+        let config = Config {
+            unix_socket_path: Some("/run/api.sock".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(SocketMode::from_config(&config), SocketMode::Both);
+        assert_eq!(SocketMode::from_config(&Config::default()), SocketMode::Tcp);
+    }
+}
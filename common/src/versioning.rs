@@ -0,0 +1,91 @@
+//! Versioned route namespaces shared by both services.
+//!
+//! The API routes are served under [`V1_PREFIX`] and [`V2_PREFIX`]. The
+//! unversioned paths remain as aliases of v1, answered with a `Deprecation`
+//! header and a `Link` to the `/v1` path that replaces them. Settings keyed
+//! by route, such as per-route timeouts and rate limits, are written without
+//! a version and apply to every version of the route.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub const V1_PREFIX: &str = "/v1";
+pub const V2_PREFIX: &str = "/v2";
+
+pub const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+
+/// `path` without its version prefix, e.g. `/time` for `/v2/time`.
+pub fn unversioned(path: &str) -> &str {
+    [V1_PREFIX, V2_PREFIX]
+        .iter()
+        .find_map(|prefix| {
+            path.strip_prefix(prefix)
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .map_or(path, |rest| if rest.is_empty() { "/" } else { rest })
+}
+
+/// Middleware marking responses of the unversioned aliases as deprecated.
+pub async fn deprecated(request: Request, next: Next) -> Response {
+    let successor = format!(
+        "<{V1_PREFIX}{}>; rel=\"successor-version\"",
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.append(axum::http::header::LINK, link);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+
+    #[test]
+    fn strips_only_whole_version_segments() {
+        assert_eq!(unversioned("/v1/time"), "/time");
+        assert_eq!(
+            unversioned("/v2/timezone/Asia/Tokyo"),
+            "/timezone/Asia/Tokyo"
+        );
+        assert_eq!(unversioned("/v2"), "/");
+        assert_eq!(unversioned("/time"), "/time");
+        assert_eq!(unversioned("/v10/time"), "/v10/time");
+    }
+
+    #[tokio::test]
+    async fn links_deprecated_paths_to_v1() {
+        let routes = Router::new().route("/time", get(|| async { "now" }));
+        let app = Router::new()
+            .merge(
+                routes
+                    .clone()
+                    .route_layer(axum::middleware::from_fn(deprecated)),
+            )
+            .nest(V1_PREFIX, routes);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let response = reqwest::get(format!("http://{addr}/time")).await.unwrap();
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(
+            response.headers()["link"],
+            "</v1/time>; rel=\"successor-version\""
+        );
+        let response = reqwest::get(format!("http://{addr}/v1/time"))
+            .await
+            .unwrap();
+        assert!(response.headers().get("deprecation").is_none());
+        assert_eq!(response.text().await.unwrap(), "now");
+    }
+}
@@ -0,0 +1,546 @@
+//! Minimal WebSocket support (RFC 6455): the opening handshake and a frame
+//! codec, enough for JSON text messages. No extensions or subprotocols are
+//! negotiated.
+//!
+//! Servers answer an upgrade request with [`upgrade`], which returns the
+//! `101 Switching Protocols` response and runs the session once hyper hands
+//! over the connection. [`request_upgrade`] performs the client side of the
+//! handshake on an already-open stream.
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::problem::ErrorCode;
+use crate::ErrorResponse;
+
+/// Appended to the client's key before hashing (RFC 6455 §1.3).
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Messages are small JSON documents; anything larger is a protocol error.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+/// Longest accepted handshake response head.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// How often an idle session is pinged; one unanswered ping closes it.
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+/// The client's `Sec-WebSocket-Key` when `headers` ask for a version 13
+/// WebSocket upgrade.
+pub fn upgrade_key(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let connection_upgrade = header(header::CONNECTION).is_some_and(|connection| {
+        connection
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+    });
+    let websocket = header(header::UPGRADE)
+        .is_some_and(|upgrade| upgrade.trim().eq_ignore_ascii_case("websocket"));
+    let version_13 = header(header::SEC_WEBSOCKET_VERSION).is_some_and(|v| v.trim() == "13");
+    if !(connection_upgrade && websocket && version_13) {
+        return None;
+    }
+    header(header::SEC_WEBSOCKET_KEY)
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+}
+
+/// `Sec-WebSocket-Accept` for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    BASE64.encode(sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+/// A fresh random `Sec-WebSocket-Key`.
+pub fn new_key() -> String {
+    BASE64.encode(Uuid::new_v4().as_bytes())
+}
+
+/// The `101` response completing the handshake for `key`.
+pub fn switching_protocols(key: &str) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(
+        header::SEC_WEBSOCKET_ACCEPT,
+        HeaderValue::from_str(&accept_key(key)).expect("base64 is a valid header value"),
+    );
+    response
+}
+
+/// Accepts a WebSocket upgrade and answers each text message with the text
+/// `on_text` returns. Binary messages get an [`ErrorResponse`] frame; the
+/// session stays open either way.
+///
+/// Returns `None`, leaving the request untouched, when it is not a WebSocket
+/// upgrade request.
+pub fn upgrade<B, F, Fut>(request: &mut Request<B>, on_text: F) -> Option<Response<Body>>
+where
+    F: FnMut(String) -> Fut + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    upgrade_with_pushes(request, mpsc::channel(1).1, on_text)
+}
+
+/// Like [`upgrade`], also sending each text received on `pushed` to the
+/// client unprompted. Senders for `pushed` fail once the session ends.
+pub fn upgrade_with_pushes<B, F, Fut>(
+    request: &mut Request<B>,
+    pushed: mpsc::Receiver<String>,
+    on_text: F,
+) -> Option<Response<Body>>
+where
+    F: FnMut(String) -> Fut + Send + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    let key = upgrade_key(request.headers())?;
+    let on_upgrade = hyper::upgrade::on(request);
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => serve(TokioIo::new(upgraded), pushed, on_text).await,
+            Err(e) => debug!(error = %e, "WebSocket upgrade failed"),
+        }
+    });
+    Some(switching_protocols(&key))
+}
+
+/// Runs a server session on `stream` until the client closes it, stops
+/// answering pings, or breaks the protocol.
+pub async fn serve<S, F, Fut>(stream: S, mut pushed: mpsc::Receiver<String>, mut on_text: F)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = String>,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    // Reading runs in its own task because a partly read frame cannot be
+    // resumed after losing a `select!` race against the ping timer.
+    let (incoming, mut messages) = mpsc::channel(16);
+    let reading = tokio::spawn(async move {
+        let mut reader = MessageReader::new(reader);
+        loop {
+            let message = reader.next().await.transpose();
+            let done = !matches!(message, Some(Ok(_)));
+            if let Some(message) = message {
+                if incoming.send(message).await.is_err() {
+                    break;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+    });
+
+    let start = tokio::time::Instant::now() + PING_INTERVAL;
+    let mut ping = tokio::time::interval_at(start, PING_INTERVAL);
+    let mut awaiting_pong = false;
+    loop {
+        let reply = tokio::select! {
+            message = messages.recv() => match message {
+                Some(Ok(Message::Text(text))) => Message::Text(on_text(text).await),
+                Some(Ok(Message::Binary(_))) => Message::Text(error_frame("Expected a text frame")),
+                Some(Ok(Message::Ping(payload))) => Message::Pong(payload),
+                Some(Ok(Message::Pong(_))) => {
+                    awaiting_pong = false;
+                    continue;
+                }
+                Some(Ok(Message::Close)) => Message::Close,
+                Some(Err(e)) => {
+                    debug!(error = %e, "Closing WebSocket after protocol error");
+                    Message::Close
+                }
+                None => break,
+            },
+            Some(text) = pushed.recv() => Message::Text(text),
+            _ = ping.tick() => {
+                if awaiting_pong {
+                    info!("WebSocket peer did not answer ping; closing");
+                    Message::Close
+                } else {
+                    awaiting_pong = true;
+                    Message::Ping(Vec::new())
+                }
+            }
+        };
+        let closing = reply == Message::Close;
+        if write_message(&mut writer, &reply, false).await.is_err() || closing {
+            break;
+        }
+    }
+    let _ = writer.shutdown().await;
+    reading.abort();
+}
+
+fn error_frame(error: &str) -> String {
+    serde_json::to_string(&ErrorResponse::new(
+        ErrorCode::InvalidRequest,
+        error,
+        &Uuid::new_v4().to_string(),
+    ))
+    .expect("error responses serialize")
+}
+
+/// Status and `Sec-WebSocket-Accept` of a handshake response.
+#[derive(Debug)]
+pub struct Handshake {
+    pub status: u16,
+    pub accept: Option<String>,
+}
+
+/// Sends the opening handshake for `path` with `key` and any `extra_headers`
+/// over `stream`, and reads the response head.
+///
+/// The head is read a byte at a time so that no frame data following it is
+/// consumed; the stream is ready for frames once this returns `101`.
+pub async fn request_upgrade<S>(
+    stream: &mut S,
+    host: &str,
+    path: &str,
+    key: &str,
+    extra_headers: &[(&str, &str)],
+) -> io::Result<Handshake>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut head = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\n\
+         Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n"
+    );
+    for (name, value) in extra_headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HEAD_BYTES {
+            return Err(invalid("handshake response head too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let mut lines = response.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("malformed handshake status line"))?;
+    let accept = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-accept"))
+        .map(|(_, value)| value.trim().to_string());
+    Ok(Handshake { status, accept })
+}
+
+/// Reads whole messages, reassembling fragmented ones.
+pub struct MessageReader<R> {
+    inner: R,
+    /// Opcode and payload of a fragmented message still being received.
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: AsyncRead + Unpin> MessageReader<R> {
+    pub fn new(inner: R) -> Self {
+        MessageReader {
+            inner,
+            partial: None,
+        }
+    }
+
+    /// The next message, or `None` once the peer closes the stream.
+    pub async fn next(&mut self) -> io::Result<Option<Message>> {
+        loop {
+            let Some((fin, opcode, payload)) = self.read_frame().await? else {
+                return Ok(None);
+            };
+            let (opcode, payload) = match (opcode, self.partial.take()) {
+                (OPCODE_PING, partial) => {
+                    self.partial = partial;
+                    return Ok(Some(Message::Ping(payload)));
+                }
+                (OPCODE_PONG, partial) => {
+                    self.partial = partial;
+                    return Ok(Some(Message::Pong(payload)));
+                }
+                (OPCODE_CLOSE, _) => return Ok(Some(Message::Close)),
+                (OPCODE_CONTINUATION, Some((opcode, mut data))) => {
+                    if data.len() + payload.len() > MAX_MESSAGE_BYTES {
+                        return Err(invalid("message too large"));
+                    }
+                    data.extend_from_slice(&payload);
+                    (opcode, data)
+                }
+                (OPCODE_TEXT | OPCODE_BINARY, None) => (opcode, payload),
+                (OPCODE_CONTINUATION, None) => return Err(invalid("unexpected continuation")),
+                (OPCODE_TEXT | OPCODE_BINARY, Some(_)) => {
+                    return Err(invalid("new message before the previous one ended"))
+                }
+                _ => return Err(invalid("unknown opcode")),
+            };
+            if !fin {
+                self.partial = Some((opcode, payload));
+                continue;
+            }
+            return match opcode {
+                OPCODE_TEXT => String::from_utf8(payload)
+                    .map(|text| Some(Message::Text(text)))
+                    .map_err(|_| invalid("text message is not UTF-8")),
+                _ => Ok(Some(Message::Binary(payload))),
+            };
+        }
+    }
+
+    /// One frame as `(fin, opcode, unmasked payload)`, or `None` on a clean
+    /// end of stream before its first byte.
+    async fn read_frame(&mut self) -> io::Result<Option<(bool, u8, Vec<u8>)>> {
+        let first = match self.inner.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if first & 0x70 != 0 {
+            return Err(invalid("reserved bits set"));
+        }
+        let fin = first & 0x80 != 0;
+        let opcode = first & 0x0F;
+        let second = self.inner.read_u8().await?;
+        let masked = second & 0x80 != 0;
+        let length = match second & 0x7F {
+            126 => u64::from(self.inner.read_u16().await?),
+            127 => self.inner.read_u64().await?,
+            length => u64::from(length),
+        };
+        if opcode >= OPCODE_CLOSE && (!fin || length > 125) {
+            return Err(invalid("malformed control frame"));
+        }
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|length| *length <= MAX_MESSAGE_BYTES)
+            .ok_or_else(|| invalid("message too large"))?;
+
+        let mut mask = [0; 4];
+        if masked {
+            self.inner.read_exact(&mut mask).await?;
+        }
+        let mut payload = vec![0; length];
+        self.inner.read_exact(&mut payload).await?;
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+        Ok(Some((fin, opcode, payload)))
+    }
+}
+
+/// Writes `message` as a single frame. Clients must set `masked`; servers
+/// must not.
+pub async fn write_message<W>(writer: &mut W, message: &Message, masked: bool) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let (opcode, payload): (u8, &[u8]) = match message {
+        Message::Text(text) => (OPCODE_TEXT, text.as_bytes()),
+        Message::Binary(data) => (OPCODE_BINARY, data),
+        Message::Ping(data) => (OPCODE_PING, data),
+        Message::Pong(data) => (OPCODE_PONG, data),
+        // Status 1000, normal closure.
+        Message::Close => (OPCODE_CLOSE, &[0x03, 0xE8]),
+    };
+
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    let mask_bit = if masked { 0x80 } else { 0 };
+    match payload.len() {
+        length @ 0..=125 => frame.push(mask_bit | length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    let start = frame.len();
+    if masked {
+        let mask: [u8; 4] = Uuid::new_v4().as_bytes()[..4]
+            .try_into()
+            .expect("a UUID has 16 bytes");
+        frame.extend_from_slice(&mask);
+        frame.extend_from_slice(payload);
+        apply_mask(&mut frame[start + 4..], mask);
+    } else {
+        frame.extend_from_slice(payload);
+    }
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (byte, mask) in payload.iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= mask;
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// SHA-1 (FIPS 180-4), needed only to derive `Sec-WebSocket-Accept`.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().expect("four bytes"));
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = next;
+        }
+        for (total, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *total = total.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_accept_key() {
+        // The example from RFC 6455 §1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        let hex: String = sha1(b"abc").iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[tokio::test]
+    async fn round_trips_masked_and_fragmented_messages() {
+        let (mut client, server) = tokio::io::duplex(1 << 20);
+        let mut reader = MessageReader::new(server);
+
+        let long = "x".repeat(40_000);
+        for message in [
+            Message::Text("{\"timezone\":\"Asia/Seoul\"}".to_string()),
+            Message::Text(long.clone()),
+            Message::Ping(b"hi".to_vec()),
+            Message::Binary(vec![1, 2, 3]),
+        ] {
+            write_message(&mut client, &message, true).await.unwrap();
+            assert_eq!(reader.next().await.unwrap(), Some(message));
+        }
+
+        // "Hel" + ping + "lo", unmasked.
+        client
+            .write_all(&[0x01, 3, b'H', b'e', b'l', 0x89, 0, 0x80, 2, b'l', b'o'])
+            .await
+            .unwrap();
+        assert_eq!(
+            reader.next().await.unwrap(),
+            Some(Message::Ping(Vec::new()))
+        );
+        assert_eq!(
+            reader.next().await.unwrap(),
+            Some(Message::Text("Hello".to_string()))
+        );
+
+        drop(client);
+        assert_eq!(reader.next().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_messages() {
+        let (mut client, server) = tokio::io::duplex(1 << 20);
+        let mut reader = MessageReader::new(server);
+        let huge = Message::Binary(vec![0; MAX_MESSAGE_BYTES + 1]);
+        write_message(&mut client, &huge, true).await.unwrap();
+        assert_eq!(
+            reader.next().await.unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn recognises_upgrade_requests() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, "keep-alive, Upgrade".parse().unwrap());
+        headers.insert(header::UPGRADE, "websocket".parse().unwrap());
+        headers.insert(header::SEC_WEBSOCKET_VERSION, "13".parse().unwrap());
+        assert_eq!(upgrade_key(&headers), None);
+        headers.insert(header::SEC_WEBSOCKET_KEY, "abc==".parse().unwrap());
+        assert_eq!(upgrade_key(&headers).as_deref(), Some("abc=="));
+        headers.insert(header::SEC_WEBSOCKET_VERSION, "8".parse().unwrap());
+        assert_eq!(upgrade_key(&headers), None);
+    }
+}